@@ -0,0 +1,84 @@
+//! Tokenizer throughput. Run with `cargo bench --bench html_tokenizer`.
+//!
+//! Set `HTML_BENCH_FILE` to benchmark a real page, e.g. the single-page HTML
+//! standard from <https://html.spec.whatwg.org/>; otherwise a generated
+//! document of similar shape is used.
+
+#![expect(clippy::print_stdout, reason = "benchmark results go to stdout")]
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use mochi_browser::html_tokenizer::{HtmlTokenizer, TokenizerOptions};
+
+const RUNS: u32 = 10;
+
+fn main() {
+    let (name, html) = match std::env::var("HTML_BENCH_FILE") {
+        Ok(path) => match std::fs::read_to_string(&path) {
+            Ok(html) => (path, html),
+            Err(error) => {
+                println!("Cannot read {path}: {error}");
+                return;
+            }
+        },
+        Err(_) => ("generated page".to_owned(), generated_page()),
+    };
+    println!("{name}: {:.1} MB", megabytes(&html));
+
+    bench("default", &html, &TokenizerOptions::new());
+    bench(
+        "error recovery",
+        &html,
+        &TokenizerOptions::new().error_recovery(true),
+    );
+    bench(
+        "spec conformance",
+        &html,
+        &TokenizerOptions::new().spec_conformance(true),
+    );
+}
+
+fn bench(label: &str, html: &str, options: &TokenizerOptions) {
+    let mut best = Duration::MAX;
+    let mut tokens = 0;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        tokens = HtmlTokenizer::new(black_box(html))
+            .with_options(options.clone())
+            .iter()
+            .map_while(Result::ok)
+            .count();
+        best = best.min(start.elapsed());
+    }
+    println!(
+        "{label:>18}: {tokens} tokens, best of {RUNS} {best:.2?} ({:.0} MB/s)",
+        megabytes(html) / best.as_secs_f64()
+    );
+}
+
+fn megabytes(html: &str) -> f64 {
+    html.len() as f64 / 1_000_000.0
+}
+
+/// About 5 MB of prose-heavy markup with the mix of the HTML standard:
+/// long text runs, links, references, comments and the odd script.
+fn generated_page() -> String {
+    let mut html = String::from("<!DOCTYPE html><html><head><title>Bench</title></head><body>");
+    for section in 0..8000 {
+        html.push_str(&format!(
+            "<section id=\"s{section}\"><h2>Section {section}</h2>\
+             <!-- section {section} -->\
+             <p class=\"note\">The <a href=\"#dom-node-{section}\" title='node'>node</a> \
+             tree is walked in tree order &mdash; each step consults the \
+             <code>Document</code>&apos;s mode, so authors &amp; tools see the same result. \
+             A user agent must not modify the list while iterating over it, and must \
+             queue a task to fire an event named <code>change</code> instead.</p>\
+             <pre><code>if (a &lt; b) {{ run(a); }}</code></pre>\
+             <script>window.marks.push({section} < 10 ? 'early' : 'late');</script>\
+             <ul><li>First</li><li>Second &ndash; more</li><li>Third</li></ul></section>\n"
+        ));
+    }
+    html.push_str("</body></html>");
+    html
+}
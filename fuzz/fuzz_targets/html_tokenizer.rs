@@ -0,0 +1,50 @@
+//! Run with `cargo +nightly fuzz run html_tokenizer` from the repository root.
+//!
+//! Feeds arbitrary bytes through every tokenizer entry point. Any panic, or
+//! a hang caught by libFuzzer's timeout, is a bug.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use mochi_browser::html_tokenizer::{
+    HtmlTokenizer, StreamingTokenizer, TokenRewriter, TokenizerOptions, serialize_tokens,
+};
+
+fuzz_target!(|data: &[u8]| {
+    // The first byte picks the options and where to split the input.
+    let Some((&control, bytes)) = data.split_first() else {
+        return;
+    };
+    let options = TokenizerOptions::new()
+        .spec_conformance(control & 1 != 0)
+        .error_recovery(control & 2 != 0)
+        .decode_entities(control & 4 != 0)
+        .preserve_whitespace(control & 8 != 0);
+    let input = String::from_utf8_lossy(bytes);
+
+    let tokens: Vec<_> = HtmlTokenizer::new(&input)
+        .with_options(options.clone())
+        .iter()
+        .map_while(Result::ok)
+        .collect();
+    serialize_tokens(&tokens);
+
+    let split = usize::from(control >> 4).min(bytes.len());
+    let (head, tail) = bytes.split_at(split);
+    let mut streaming = StreamingTokenizer::new().with_options(options.clone());
+    streaming.feed(head);
+    streaming.feed(tail);
+    streaming.finish();
+
+    TokenRewriter::new().rewrite(&input);
+
+    if let Ok(mut document) = HtmlTokenizer::new(&input)
+        .with_options(options)
+        .incremental()
+    {
+        let middle = input.len() / 2;
+        if input.is_char_boundary(middle) {
+            document.retokenize(middle..middle, "<!--").ok();
+        }
+    }
+});
@@ -0,0 +1,78 @@
+#!/usr/bin/env python3
+"""Generates src/html_tokenizer/entities/named_references.rs from the WHATWG
+named character reference list.
+
+    scripts/generate_named_references.py [<entities.json>]
+
+Without an argument the list is downloaded from ENTITIES_URL. The HTML
+standard says the list is static and will not be expanded or changed, so
+there is no revision to pin; the number of references is checked instead,
+and the SHA-256 of the source is printed to compare with the last run.
+"""
+
+import hashlib
+import json
+import sys
+import urllib.request
+from pathlib import Path
+
+ENTITIES_URL = "https://html.spec.whatwg.org/entities.json"
+
+# Every named character reference, with and without the semicolon.
+EXPECTED_COUNT = 2231
+
+OUTPUT = (
+    Path(__file__).resolve().parent.parent
+    / "src/html_tokenizer/entities/named_references.rs"
+)
+
+HEADER = f"""\
+// Generated from the WHATWG named character reference list
+// ({ENTITIES_URL}) by scripts/generate_named_references.py.
+// Do not edit by hand.
+
+/// Every named character reference, without the leading `&` and sorted by
+/// name. Legacy references that may omit the semicolon appear both with
+/// and without it.
+pub(super) const NAMED_REFERENCES: &[(&str, &str)] = &[
+"""
+
+
+def rust_string(text):
+    """A Rust string literal of `text`, with all but printable ASCII escaped."""
+    escaped = ""
+    for c in text:
+        if c in '"\\':
+            escaped += "\\" + c
+        elif " " <= c <= "~":
+            escaped += c
+        else:
+            escaped += "\\u{%x}" % ord(c)
+    return f'"{escaped}"'
+
+
+def main():
+    if len(sys.argv) > 2:
+        sys.exit(f"usage: {sys.argv[0]} [<entities.json>]")
+    if len(sys.argv) == 2:
+        source = Path(sys.argv[1]).read_bytes()
+    else:
+        with urllib.request.urlopen(ENTITIES_URL) as response:
+            source = response.read()
+
+    entities = json.loads(source)
+    if len(entities) != EXPECTED_COUNT:
+        sys.exit(f"expected {EXPECTED_COUNT} references, got {len(entities)}")
+
+    lines = [HEADER]
+    for name in sorted(entities):
+        characters = "".join(map(chr, entities[name]["codepoints"]))
+        lines.append(f"    ({rust_string(name.removeprefix('&'))}, {rust_string(characters)}),\n")
+    lines.append("];\n")
+    OUTPUT.write_text("".join(lines), encoding="utf-8")
+
+    print(f"entities.json SHA-256: {hashlib.sha256(source).hexdigest()}")
+
+
+if __name__ == "__main__":
+    main()
@@ -1,10 +1,27 @@
 #[cfg(not(target_arch = "wasm32"))]
-use std::sync::mpsc;
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+use std::path::PathBuf;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::{Arc, mpsc};
 
-use crate::http::HttpResponse;
+use crate::{
+    http::HttpResponse,
+    i18n::{Language, Strings},
+};
 
 #[cfg(not(target_arch = "wasm32"))]
-use crate::http::fetch_url;
+use crate::{
+    content_blocker::{ContentBlocker, host_of},
+    dom::{Document, LinkKind, NodeId, PageMetadata, TextMatch},
+    extension::{ExtensionRegistry, OutgoingRequest},
+    http::{fetch_bytes_with_headers, fetch_url_with_headers},
+    images::{FetchBytes, ImageCache},
+    layout::{BoxTree, LayoutTree},
+    paint::{DisplayList, Highlight},
+    resources::{Fetch, ResourceTracker},
+    style::{Cascade, ColorScheme, Device, Styles},
+};
 
 /// We derive Deserialize/Serialize so we can persist app state on shutdown.
 #[derive(serde::Deserialize, serde::Serialize)]
@@ -20,13 +37,160 @@ pub struct TemplateApp {
     response: Option<HttpResponse>,
     loading: bool,
 
+    /// URL of the page currently shown, if any navigation has happened.
+    #[serde(skip)]
+    current_url: Option<String>,
+
+    /// Session history behind the current page, most recent last.
+    #[serde(skip)]
+    back_stack: Vec<String>,
+
+    /// Session history ahead of the current page, most recent last.
+    #[serde(skip)]
+    forward_stack: Vec<String>,
+
+    /// Every URL loaded in this session, which links to match `:visited`.
+    #[serde(skip)]
+    visited: HashSet<String>,
+
+    /// Hosts where content blocking has been switched off.
+    blocking_disabled_sites: Vec<String>,
+
+    /// User hosts files and EasyList-style lists blocked on top of the
+    /// built-in filter list.
+    filter_list_files: Vec<PathBuf>,
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    filter_list_input: String,
+
+    /// Whether the layout tree of the page is shown next to it, to debug
+    /// layout.
+    show_layout_tree: bool,
+
+    /// Whether the response is shown as it came, with its headers, instead
+    /// of the rendered page.
+    show_raw_response: bool,
+
+    /// UI language chosen in the settings; `None` follows the system locale.
+    language_override: Option<Language>,
+
+    #[serde(skip)]
+    system_language: Language,
+
     #[serde(skip)]
     #[cfg(not(target_arch = "wasm32"))]
     receiver: Option<mpsc::Receiver<Result<HttpResponse, String>>>,
+
+    /// The page being shown, with the style sheets loaded so far.
+    #[serde(skip)]
+    #[cfg(not(target_arch = "wasm32"))]
+    document: Option<Document>,
+
+    /// The style sheets of `document` that are still loading.
+    #[serde(skip)]
+    #[cfg(not(target_arch = "wasm32"))]
+    resources: Option<ResourceTracker>,
+
+    /// The styles of `document`, and the cascade they were computed with,
+    /// for the device it has; `None` when they must be computed again.
+    #[serde(skip)]
+    #[cfg(not(target_arch = "wasm32"))]
+    styles: Option<(Cascade, Styles)>,
+
+    /// The boxes of `document`, kept from one layout to the next so that
+    /// only the parts of the page that changed are laid out again.
+    #[serde(skip)]
+    #[cfg(not(target_arch = "wasm32"))]
+    boxes: Option<BoxTree>,
+
+    /// Whether any style rule of `document` has `:hover` in it, so that
+    /// its styles change as the pointer moves over the page.
+    #[serde(skip)]
+    #[cfg(not(target_arch = "wasm32"))]
+    hover_rules: bool,
+
+    /// The layout of `document` with `styles`, and the viewport it was
+    /// laid out for; `None` when the page must be laid out again.
+    #[serde(skip)]
+    #[cfg(not(target_arch = "wasm32"))]
+    layout: Option<(egui::Vec2, LayoutTree)>,
+
+    /// What painting `layout` takes.
+    #[serde(skip)]
+    #[cfg(not(target_arch = "wasm32"))]
+    display_list: Option<DisplayList>,
+
+    /// How far the scroll containers of the page are scrolled, to scroll
+    /// them back to when it is laid out again.
+    #[serde(skip)]
+    #[cfg(not(target_arch = "wasm32"))]
+    box_scroll_offsets: BTreeMap<NodeId, egui::Vec2>,
+
+    /// The images of the pages shown in this session, by URL.
+    #[serde(skip)]
+    #[cfg(not(target_arch = "wasm32"))]
+    images: ImageCache,
+
+    /// Find in page, and its matches on the page shown.
+    #[serde(skip)]
+    #[cfg(not(target_arch = "wasm32"))]
+    find: FindInPage,
+
+    /// The size of the area the page was last shown in, which it is laid
+    /// out for; zero before it is first shown.
+    #[serde(skip)]
+    #[cfg(not(target_arch = "wasm32"))]
+    page_viewport: egui::Vec2,
+
+    /// Compiled-in extensions, shared with the fetch threads.
+    #[serde(skip)]
+    #[cfg(not(target_arch = "wasm32"))]
+    extensions: Arc<ExtensionRegistry>,
+
+    /// Also registered in `extensions`; kept here for the toolbar badge.
+    #[serde(skip)]
+    #[cfg(not(target_arch = "wasm32"))]
+    content_blocker: Arc<ContentBlocker>,
+}
+
+/// The state of the find bar.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Default)]
+struct FindInPage {
+    /// Whether the find bar is shown.
+    open: bool,
+    query: String,
+
+    /// The matches of `query` in the text of the document; `None` when
+    /// the query or the document changed since they were looked for.
+    matches: Option<Vec<TextMatch>>,
+
+    /// The matches that are painted, in document order, where the current
+    /// layout has them.
+    highlights: Vec<Highlight>,
+
+    /// The index of the current match in `highlights`.
+    current: usize,
+
+    /// Whether the query field is to take the keyboard focus.
+    focus: bool,
+
+    /// Whether the current match is to be scrolled into view.
+    scroll_to_current: bool,
 }
 
 impl Default for TemplateApp {
     fn default() -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        let content_blocker = Arc::new(ContentBlocker::default());
+        #[cfg(not(target_arch = "wasm32"))]
+        let extensions = {
+            let mut extensions = ExtensionRegistry::new();
+            extensions.register(Box::new(Arc::clone(&content_blocker)));
+            Arc::new(extensions)
+        };
+
         Self {
             // Example stuff:
             label: "Hello World!".to_owned(),
@@ -34,8 +198,46 @@ impl Default for TemplateApp {
             url_input: String::new(),
             response: None,
             loading: false,
+            current_url: None,
+            back_stack: Vec::new(),
+            forward_stack: Vec::new(),
+            visited: HashSet::new(),
+            blocking_disabled_sites: Vec::new(),
+            filter_list_files: Vec::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            filter_list_input: String::new(),
+            show_layout_tree: false,
+            show_raw_response: false,
+            language_override: None,
+            system_language: Language::system(),
             #[cfg(not(target_arch = "wasm32"))]
             receiver: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            document: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            resources: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            styles: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            boxes: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            hover_rules: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            layout: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            display_list: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            box_scroll_offsets: BTreeMap::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            images: ImageCache::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            find: FindInPage::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            page_viewport: egui::Vec2::ZERO,
+            #[cfg(not(target_arch = "wasm32"))]
+            extensions,
+            #[cfg(not(target_arch = "wasm32"))]
+            content_blocker,
         }
     }
 }
@@ -48,10 +250,619 @@ impl TemplateApp {
 
         // Load previous app state (if any).
         // Note that you must enable the `persistence` feature for this to work.
-        if let Some(storage) = cc.storage {
+        let app: Self = if let Some(storage) = cc.storage {
             eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default()
         } else {
             Default::default()
+        };
+
+        #[cfg(not(target_arch = "wasm32"))]
+        crate::i18n::install_cjk_fallback_font(&cc.egui_ctx);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        for site in &app.blocking_disabled_sites {
+            app.content_blocker.set_enabled_for(site, false);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        for path in &app.filter_list_files {
+            if let Err(err) = app.content_blocker.add_list_file(path) {
+                log::warn!("Could not load filter list {}: {err}", path.display());
+            }
+        }
+
+        app
+    }
+
+    /// Chrome strings in the effective UI language.
+    fn strings(&self) -> &'static Strings {
+        self.language_override
+            .unwrap_or(self.system_language)
+            .strings()
+    }
+
+    /// Navigates to `url`, pushing the current page onto the back history.
+    fn navigate(&mut self, url: String) {
+        if let Some(current) = self.current_url.take() {
+            self.back_stack.push(current);
+        }
+        self.forward_stack.clear();
+        self.load(url);
+    }
+
+    /// Returns to the previous page in the session history, if there is one.
+    fn go_back(&mut self) {
+        let Some(url) = self.back_stack.pop() else {
+            return;
+        };
+        if let Some(current) = self.current_url.take() {
+            self.forward_stack.push(current);
+        }
+        self.load(url);
+    }
+
+    /// Moves to the next page in the session history, if there is one.
+    fn go_forward(&mut self) {
+        let Some(url) = self.forward_stack.pop() else {
+            return;
+        };
+        if let Some(current) = self.current_url.take() {
+            self.back_stack.push(current);
+        }
+        self.load(url);
+    }
+
+    /// Maps mouse buttons 4/5 to back/forward, as mainstream browsers do.
+    fn handle_mouse_navigation(&mut self, ctx: &egui::Context) {
+        let (back_pressed, forward_pressed) = ctx.input(|i| {
+            (
+                i.pointer.button_pressed(egui::PointerButton::Extra1),
+                i.pointer.button_pressed(egui::PointerButton::Extra2),
+            )
+        });
+        if back_pressed {
+            self.go_back();
+        }
+        if forward_pressed {
+            self.go_forward();
+        }
+    }
+
+    /// Toolbar badge showing how many requests were blocked on the current page,
+    /// with a switch to turn blocking off for the current site.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn content_blocking_badge(&mut self, ui: &mut egui::Ui) {
+        ui.add_space(16.0);
+        ui.label(format!("🛡 {}", self.content_blocker.blocked_count()))
+            .on_hover_text(self.strings().blocked_requests_hover);
+
+        let Some(site) = self.current_url.as_deref().and_then(host_of) else {
+            return;
+        };
+        let mut enabled = self.content_blocker.is_enabled_for(&site);
+        if ui
+            .checkbox(&mut enabled, self.strings().block_on_this_site)
+            .changed()
+        {
+            self.content_blocker.set_enabled_for(&site, enabled);
+            self.blocking_disabled_sites = self.content_blocker.disabled_sites();
+        }
+    }
+
+    /// Menu listing the user filter lists, with a field to add one by path.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn filter_lists_menu(&mut self, ui: &mut egui::Ui) {
+        let strings = self.strings();
+        ui.menu_button(strings.menu_filter_lists, |ui| {
+            for path in &self.filter_list_files {
+                ui.label(path.display().to_string());
+            }
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut self.filter_list_input);
+                if ui.button(strings.add_filter_list).clicked() {
+                    let path = PathBuf::from(self.filter_list_input.trim());
+                    match self.content_blocker.add_list_file(&path) {
+                        Ok(()) => {
+                            self.filter_list_files.push(path);
+                            self.filter_list_input.clear();
+                        }
+                        Err(err) => {
+                            log::warn!("Could not load filter list {}: {err}", path.display());
+                        }
+                    }
+                }
+            });
+        });
+    }
+
+    /// Picks up a finished fetch, if any, titles the window after the page
+    /// and starts loading its style sheets.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn poll_response(&mut self, ctx: &egui::Context) {
+        if let Some(receiver) = &self.receiver
+            && let Ok(result) = receiver.try_recv()
+        {
+            self.loading = false;
+            self.receiver = None;
+            match result {
+                Ok(response) => {
+                    let mut document = Document::parse(&response.body);
+                    if let Some(url) = &self.current_url {
+                        document.set_url(url.clone());
+                    }
+                    document.add_visited_urls(self.visited.iter().cloned());
+                    self.extensions.run_dom_ready_hooks(&mut document);
+                    let metadata = PageMetadata::from_document(&document);
+                    if let Some(title) = metadata.title.filter(|title| !title.is_empty()) {
+                        ctx.send_viewport_cmd(egui::ViewportCommand::Title(title));
+                    }
+                    self.resources = Some(ResourceTracker::start(&document, &self.fetcher()));
+                    self.document = Some(document);
+                    self.find.matches = None;
+                    self.styles = None;
+                    self.boxes = None;
+                    self.response = Some(response);
+                    self.load_images();
+                }
+                Err(e) => {
+                    self.response = Some(HttpResponse {
+                        status: 0,
+                        headers: vec![],
+                        body: format!("{}: {e}", self.strings().error_prefix),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Adds the style sheets of the current page that have arrived, and
+    /// keeps checking for the others until they are all in.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn poll_resources(&mut self, ctx: &egui::Context) {
+        let (Some(resources), Some(document)) = (&mut self.resources, &mut self.document) else {
+            return;
+        };
+        if resources.poll(document) {
+            self.styles = None;
+            ctx.request_repaint();
+        }
+        if resources.is_complete() {
+            self.resources = None;
+        } else {
+            ctx.request_repaint_after(std::time::Duration::from_millis(100));
+        }
+    }
+
+    /// Starts loading the images of the page that are not in the cache
+    /// yet, and sizes those that are.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load_images(&mut self) {
+        let fetch = self.image_fetcher();
+        let Some(document) = &self.document else {
+            return;
+        };
+        for link in document.links() {
+            if let (LinkKind::Image, Some(url)) = (link.kind, link.url) {
+                self.images.load(&url, &fetch);
+            }
+        }
+        self.apply_image_sizes();
+    }
+
+    /// Uploads the images that have arrived, and keeps checking for the
+    /// others until they are all in.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn poll_images(&mut self, ctx: &egui::Context) {
+        if !self.images.poll(ctx).is_empty() {
+            self.apply_image_sizes();
+            ctx.request_repaint();
+        }
+        if self.images.is_loading() {
+            ctx.request_repaint_after(std::time::Duration::from_millis(100));
+        }
+    }
+
+    /// Gives the images of the page the size of their image where it has
+    /// loaded, and lays the page out again if that changes any.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn apply_image_sizes(&mut self) {
+        let Some(document) = &mut self.document else {
+            return;
+        };
+        let mut changed = false;
+        for link in document.links() {
+            if let (LinkKind::Image, Some(url)) = (link.kind, &link.url)
+                && let Some(size) = self.images.size(url)
+            {
+                changed |= document.set_natural_size(link.element, size);
+            }
+        }
+        if changed {
+            if let (Some(boxes), Some((_, styles))) = (&mut self.boxes, &self.styles) {
+                boxes.update(document, styles);
+            }
+            self.layout = None;
+        }
+    }
+
+    /// Brings the page up to date with what has arrived, and with the
+    /// window: its document, subresources, styles and layout.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn update_page(&mut self, ctx: &egui::Context) {
+        self.poll_response(ctx);
+        self.poll_resources(ctx);
+        self.poll_images(ctx);
+        self.update_styles(ctx);
+        self.update_layout(ctx);
+    }
+
+    /// Computes the styles of the page again if its style sheets changed,
+    /// or if the window did in a way media queries can tell.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn update_styles(&mut self, ctx: &egui::Context) {
+        let Some(document) = &mut self.document else {
+            return;
+        };
+        let device = device_of(ctx);
+        if self
+            .styles
+            .as_ref()
+            .is_some_and(|(cascade, _)| *cascade.device() == device)
+        {
+            return;
+        }
+        self.extensions.run_before_render_hooks(document);
+        let cascade = Cascade::for_document(document, device);
+        let styles = cascade.compute(document);
+        self.hover_rules = cascade.uses_hover();
+        if let Some(boxes) = &mut self.boxes {
+            boxes.update(document, &styles);
+        }
+        self.styles = Some((cascade, styles));
+        self.layout = None;
+    }
+
+    /// Lays the page out again if its styles changed, or the area it is
+    /// shown in did, for that area, or the window the styles were computed
+    /// for until the page is shown. Boxes the change did not affect keep
+    /// their layout.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn update_layout(&mut self, ctx: &egui::Context) {
+        let (Some(document), Some((cascade, styles))) = (&self.document, &self.styles) else {
+            self.layout = None;
+            self.display_list = None;
+            return;
+        };
+        let viewport = if self.page_viewport == egui::Vec2::ZERO {
+            let device = cascade.device();
+            egui::vec2(device.width, device.height)
+        } else {
+            self.page_viewport
+        };
+        if self
+            .layout
+            .as_ref()
+            .is_some_and(|(laid_out_for, _)| *laid_out_for == viewport)
+        {
+            return;
+        }
+        let boxes = self
+            .boxes
+            .get_or_insert_with(|| BoxTree::build(document, styles));
+        let mut layout = ctx.fonts_mut(|fonts| boxes.layout(viewport, fonts));
+        for (&node, &offset) in &self.box_scroll_offsets {
+            layout.scroll_to(node, offset);
+        }
+        self.display_list = Some(DisplayList::build(&layout, document));
+        self.layout = Some((viewport, layout));
+        if self.find.open {
+            self.highlight_matches(ctx);
+        }
+    }
+
+    /// The find bar, if it is open, which Ctrl+F (⌘F on macOS) opens and
+    /// Escape closes. Enter goes to the next match, and Shift+Enter to the
+    /// previous one.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn find_bar(&mut self, ui: &mut egui::Ui) {
+        if ui.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::F)) {
+            self.find.open = true;
+            self.find.focus = true;
+            self.search(ui.ctx());
+        }
+        if !self.find.open {
+            return;
+        }
+        let strings = self.strings();
+        let mut step = None;
+        let mut close = ui.input(|i| i.key_pressed(egui::Key::Escape));
+        ui.horizontal(|ui| {
+            ui.label(strings.find);
+            let query = ui.text_edit_singleline(&mut self.find.query);
+            if std::mem::take(&mut self.find.focus) {
+                query.request_focus();
+            }
+            if query.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                step = Some(ui.input(|i| i.modifiers.shift));
+                query.request_focus();
+            }
+            if query.changed() {
+                self.find.current = 0;
+                self.find.scroll_to_current = true;
+                self.search(ui.ctx());
+            }
+            let found = !self.find.highlights.is_empty();
+            if ui
+                .add_enabled(found, egui::Button::new("▲"))
+                .on_hover_text(strings.find_previous)
+                .clicked()
+            {
+                step = Some(true);
+            }
+            if ui
+                .add_enabled(found, egui::Button::new("▼"))
+                .on_hover_text(strings.find_next)
+                .clicked()
+            {
+                step = Some(false);
+            }
+            if found {
+                ui.label(format!(
+                    "{}/{}",
+                    self.find.current + 1,
+                    self.find.highlights.len()
+                ));
+            } else if !self.find.query.trim().is_empty() {
+                ui.label(strings.find_no_matches);
+            }
+            close |= ui.button("✖").on_hover_text(strings.find_close).clicked();
+        });
+        let count = self.find.highlights.len();
+        if let (Some(backwards), true) = (step, count > 0) {
+            self.find.current = if backwards {
+                (self.find.current + count - 1) % count
+            } else {
+                (self.find.current + 1) % count
+            };
+            self.find.scroll_to_current = true;
+        }
+        if close {
+            self.find.open = false;
+            self.find.matches = None;
+            self.find.highlights.clear();
+        }
+    }
+
+    /// Looks for the query of the find bar in the page again, and
+    /// highlights what it finds, keeping the current match where it can.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn search(&mut self, ctx: &egui::Context) {
+        self.find.matches = None;
+        self.highlight_matches(ctx);
+    }
+
+    /// Highlights the matches of the find bar where the current layout
+    /// has them, looking for them first if the query or the page changed
+    /// since the last search.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn highlight_matches(&mut self, ctx: &egui::Context) {
+        self.find.highlights.clear();
+        let Some(document) = &self.document else {
+            return;
+        };
+        let matches = self
+            .find
+            .matches
+            .get_or_insert_with(|| document.find_text(&self.find.query));
+        if let (false, Some(display_list)) = (matches.is_empty(), &self.display_list) {
+            self.find.highlights =
+                ctx.fonts_mut(|fonts| display_list.highlights(document, matches, fonts));
+        }
+        self.find.current = self
+            .find
+            .current
+            .min(self.find.highlights.len().saturating_sub(1));
+    }
+
+    /// The rendered page, on its canvas, in an area that scrolls over it,
+    /// of which only the part in view is painted. The mouse wheel scrolls
+    /// the scroll containers under the pointer first, and the page with
+    /// what they do not take. Returns whether there is a page to show.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn page_view(&mut self, ui: &mut egui::Ui) -> bool {
+        let (Some((_, layout)), Some(display_list), Some(document)) =
+            (&mut self.layout, &mut self.display_list, &self.document)
+        else {
+            return false;
+        };
+        let viewport = ui.available_size().round();
+        if viewport != self.page_viewport {
+            // Lay the page out for the new size on the next frame.
+            self.page_viewport = viewport;
+            ui.ctx().request_repaint();
+        }
+        let scroll_to = std::mem::take(&mut self.find.scroll_to_current)
+            .then(|| self.find.highlights.get(self.find.current))
+            .flatten()
+            .filter(|highlight| !highlight.fixed)
+            .map(Highlight::bounds);
+        let highlights = |painter: &egui::Painter, origin, fixed| {
+            for (index, highlight) in self.find.highlights.iter().enumerate() {
+                if highlight.fixed == fixed {
+                    highlight.paint(painter, origin, index == self.find.current);
+                }
+            }
+        };
+        // The top left corner of the viewport of the area, where it starts.
+        let corner = ui.available_rect_before_wrap().min;
+        let mut scrolled = false;
+        let output = egui::ScrollArea::both()
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                let (rect, response) =
+                    ui.allocate_exact_size(layout.content_size(), egui::Sense::click());
+                let origin = rect.min.to_vec2();
+                // Scroll the boxes before the page does, which is when the
+                // area ends, as a nested `ScrollArea` would.
+                let delta = ui.input(|i| i.smooth_scroll_delta);
+                if let Some(pos) = response.hover_pos()
+                    && delta != egui::Vec2::ZERO
+                {
+                    let left = -layout.scroll_by(pos - corner.to_vec2(), corner - rect.min, -delta);
+                    if left != delta {
+                        ui.input_mut(|i| i.smooth_scroll_delta = left);
+                        *display_list = DisplayList::build(layout, document);
+                        scrolled = true;
+                    }
+                }
+                ui.painter().rect_filled(rect, 0.0, display_list.canvas());
+                display_list.paint(ui.painter(), origin, &self.images);
+                highlights(ui.painter(), origin, false);
+                if let Some(current) = scroll_to {
+                    ui.scroll_to_rect(current.translate(origin), Some(egui::Align::Center));
+                }
+                (response.hover_pos(), response.clicked())
+            });
+        // The fixed layers stay put in the viewport, over the page that
+        // scrolls under them.
+        let viewport = output.inner_rect;
+        let painter = ui.painter_at(viewport);
+        display_list.paint_fixed(&painter, viewport.min.to_vec2(), &self.images);
+        highlights(&painter, viewport.min.to_vec2(), true);
+        let (hover, clicked) = output.inner;
+        let target = hover
+            .and_then(|pos| layout.hit_test(pos - viewport.min.to_vec2(), output.state.offset));
+        if scrolled {
+            self.box_scroll_offsets.clone_from(layout.scroll_offsets());
+            if self.find.open {
+                self.highlight_matches(ui.ctx());
+            }
+        }
+        self.set_hovered(target, ui.ctx());
+        let url = target.and_then(|target| {
+            let document = self.document.as_ref()?;
+            document.link_url(document.enclosing_link(target)?)
+        });
+        if let Some(url) = url {
+            ui.ctx().set_cursor_icon(egui::CursorIcon::PointingHand);
+            if clicked {
+                self.navigate(url);
+            }
+        }
+        true
+    }
+
+    /// Makes the element `target` is, or is in, the one under the pointer,
+    /// and computes the styles that changes again if the page has `:hover`
+    /// rules. The page is only laid out again if a box changed.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn set_hovered(&mut self, target: Option<NodeId>, ctx: &egui::Context) {
+        let Some(document) = &mut self.document else {
+            return;
+        };
+        let element = target.and_then(|target| {
+            std::iter::once(target)
+                .chain(document.ancestors(target))
+                .find(|&id| document.element(id).is_some())
+        });
+        let changed = document.set_hovered(element);
+        if changed.is_empty() || !self.hover_rules {
+            return;
+        }
+        let Some((cascade, styles)) = &mut self.styles else {
+            return;
+        };
+        cascade.restyle(document, styles, &changed);
+        if self
+            .boxes
+            .as_mut()
+            .is_none_or(|boxes| boxes.update(document, styles))
+        {
+            self.layout = None;
+            ctx.request_repaint();
+        }
+    }
+
+    /// A side panel with the layout tree of the page, if it is switched on.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn layout_tree_panel(&self, ctx: &egui::Context) {
+        let (true, Some(document), Some((_, layout))) =
+            (self.show_layout_tree, &self.document, &self.layout)
+        else {
+            return;
+        };
+        egui::SidePanel::right("layout_tree").show(ctx, |ui| {
+            ui.heading(self.strings().layout_tree);
+            egui::ScrollArea::both()
+                .auto_shrink([false; 2])
+                .show(ui, |ui| {
+                    ui.monospace(layout.dump(document));
+                });
+        });
+    }
+
+    /// Fetches subresources the way pages are fetched, with the
+    /// extension hooks, on behalf of the current page.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn fetcher(&self) -> Fetch {
+        let extensions = Arc::clone(&self.extensions);
+        let strings = self.strings();
+        let page_url = self.current_url.clone();
+        Arc::new(move |url: &str| {
+            fetch_with_extensions(&extensions, strings, url.to_owned(), page_url.clone())
+        })
+    }
+
+    /// Fetches images the way subresources are fetched, with the request
+    /// hooks of the extensions, but keeps their bodies as they came.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn image_fetcher(&self) -> FetchBytes {
+        let extensions = Arc::clone(&self.extensions);
+        let strings = self.strings();
+        let page_url = self.current_url.clone();
+        Arc::new(move |url: &str| {
+            let request = prepare_request(&extensions, strings, url.to_owned(), page_url.clone())?;
+            fetch_bytes_with_headers(&request.url, &request.headers).map_err(|e| e.to_string())
+        })
+    }
+
+    /// Makes `url` the current page and starts fetching it.
+    fn load(&mut self, url: String) {
+        self.url_input.clone_from(&url);
+        self.current_url = Some(url.clone());
+        self.visited.insert(url.clone());
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.response = None;
+            self.document = None;
+            self.resources = None;
+            self.styles = None;
+            self.boxes = None;
+            self.layout = None;
+            self.display_list = None;
+            self.box_scroll_offsets.clear();
+            self.loading = true;
+            self.content_blocker.reset_blocked_count();
+            let (sender, receiver) = mpsc::channel();
+            self.receiver = Some(receiver);
+            let extensions = Arc::clone(&self.extensions);
+            let strings = self.strings();
+
+            std::thread::spawn(move || {
+                sender
+                    .send(fetch_with_extensions(&extensions, strings, url, None))
+                    .ok();
+            });
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            drop(url);
+            self.response = Some(HttpResponse {
+                status: 0,
+                headers: vec![],
+                body: self.strings().error_wasm_fetch.to_owned(),
+            });
         }
     }
 }
@@ -65,21 +876,10 @@ impl eframe::App for TemplateApp {
     /// Called each time the UI needs repainting, which may be many times per second.
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         #[cfg(not(target_arch = "wasm32"))]
-        if let Some(receiver) = &self.receiver
-            && let Ok(result) = receiver.try_recv() {
-                self.loading = false;
-                self.receiver = None;
-                match result {
-                    Ok(response) => self.response = Some(response),
-                    Err(e) => {
-                        self.response = Some(HttpResponse {
-                            status: 0,
-                            headers: vec![],
-                            body: format!("Error: {e}"),
-                        });
-                    }
-                }
-            }
+        self.update_page(ctx);
+
+        self.handle_mouse_navigation(ctx);
+        let strings = self.strings();
 
         // Put your widgets into a `SidePanel`, `TopBottomPanel`, `CentralPanel`, `Window` or `Area`.
         // For inspiration and more examples, go to https://emilk.github.io/egui
@@ -91,58 +891,73 @@ impl eframe::App for TemplateApp {
                 // NOTE: no File->Quit on web pages!
                 let is_web = cfg!(target_arch = "wasm32");
                 if !is_web {
-                    ui.menu_button("File", |ui| {
-                        if ui.button("Quit").clicked() {
+                    ui.menu_button(strings.menu_file, |ui| {
+                        if ui.button(strings.menu_quit).clicked() {
                             ctx.send_viewport_cmd(egui::ViewportCommand::Close);
                         }
                     });
                     ui.add_space(16.0);
                 }
 
+                ui.menu_button(strings.menu_language, |ui| {
+                    ui.radio_value(&mut self.language_override, None, strings.language_system);
+                    for language in Language::ALL {
+                        ui.radio_value(
+                            &mut self.language_override,
+                            Some(language),
+                            language.native_name(),
+                        );
+                    }
+                });
+                ui.add_space(16.0);
+
                 egui::widgets::global_theme_preference_buttons(ui);
+
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                    ui.add_space(16.0);
+                    ui.checkbox(&mut self.show_layout_tree, strings.layout_tree);
+                    ui.checkbox(&mut self.show_raw_response, strings.raw_response);
+                    self.content_blocking_badge(ui);
+                    self.filter_lists_menu(ui);
+                }
             });
         });
 
+        #[cfg(not(target_arch = "wasm32"))]
+        self.layout_tree_panel(ctx);
+
         egui::CentralPanel::default().show(ctx, |ui| {
-            ui.heading("URL Fetcher");
+            ui.heading(strings.heading);
 
             ui.horizontal(|ui| {
-                ui.label("URL: ");
+                if ui
+                    .add_enabled(!self.back_stack.is_empty(), egui::Button::new("◀"))
+                    .on_hover_text(strings.back)
+                    .clicked()
+                {
+                    self.go_back();
+                }
+                if ui
+                    .add_enabled(!self.forward_stack.is_empty(), egui::Button::new("▶"))
+                    .on_hover_text(strings.forward)
+                    .clicked()
+                {
+                    self.go_forward();
+                }
+                ui.label(strings.url_label);
                 ui.text_edit_singleline(&mut self.url_input);
-                if ui.button("Fetch").clicked() && !self.loading {
+                if ui.button(strings.fetch).clicked() && !self.loading {
                     if self.url_input.trim().is_empty() {
                         self.response = Some(HttpResponse {
                             status: 0,
                             headers: vec![],
-                            body: "Error: URL cannot be empty".to_owned(),
+                            body: format!("{}: {}", strings.error_prefix, strings.error_empty_url),
                         });
                         return;
                     }
 
-                    #[cfg(not(target_arch = "wasm32"))]
-                    {
-                        self.response = None;
-                        self.loading = true;
-                        let url = self.url_input.clone();
-                        let (sender, receiver) = mpsc::channel();
-                        self.receiver = Some(receiver);
-
-                        std::thread::spawn(move || {
-                            let result = fetch_url(&url).map_err(|e| e.to_string());
-                            sender.send(result).ok();
-                        });
-                    }
-
-                    #[cfg(target_arch = "wasm32")]
-                    {
-                        let _url = self.url_input.clone();
-                        drop(_url);
-                        self.response = Some(HttpResponse {
-                            status: 0,
-                            headers: vec![],
-                            body: "WASM fetching not fully implemented. Use native build for full functionality.".to_string(),
-                        });
-                    }
+                    self.navigate(self.url_input.trim().to_owned());
                 }
             });
 
@@ -150,49 +965,111 @@ impl eframe::App for TemplateApp {
                 ui.spinner();
             }
 
-            if let Some(response) = &self.response {
-                ui.separator();
-
-                ui.label(format!("Status: {}", response.status));
-
+            #[cfg(not(target_arch = "wasm32"))]
+            if !self.show_raw_response {
                 ui.separator();
-
-                ui.label("Headers:");
-                for (name, value) in &response.headers {
-                    ui.label(format!("{name}: {value}"));
+                self.find_bar(ui);
+                if self.page_view(ui) {
+                    return;
                 }
+            }
 
-                ui.separator();
-
-                ui.label("Body:");
-                egui::ScrollArea::vertical()
-                    .auto_shrink([false; 2])
-                    .show(ui, |ui| {
-                        ui.label(&response.body);
-                    });
+            if let Some(response) = &self.response {
+                raw_response_view(ui, response, strings);
             }
 
             ui.separator();
 
             ui.add(egui::github_link_file!(
                 "https://github.com/emilk/eframe_template/blob/main/",
-                "Source code."
+                strings.source_code
             ));
 
             ui.with_layout(egui::Layout::bottom_up(egui::Align::LEFT), |ui| {
-                powered_by_egui_and_eframe(ui);
+                powered_by_egui_and_eframe(ui, strings);
                 egui::warn_if_debug_build(ui);
             });
         });
     }
 }
 
-fn powered_by_egui_and_eframe(ui: &mut egui::Ui) {
+/// Fetches `url` on the current thread, running extension hooks around the request.
+///
+/// `initiator` is the page that asked for `url`, or `None` for a navigation.
+#[cfg(not(target_arch = "wasm32"))]
+fn fetch_with_extensions(
+    extensions: &ExtensionRegistry,
+    strings: &Strings,
+    url: String,
+    initiator: Option<String>,
+) -> Result<HttpResponse, String> {
+    let request = prepare_request(extensions, strings, url, initiator)?;
+    let mut response =
+        fetch_url_with_headers(&request.url, &request.headers).map_err(|e| e.to_string())?;
+    extensions.run_response_hooks(&request.url, &mut response);
+    Ok(response)
+}
+
+/// Builds the request for `url` and runs the request hooks over it.
+#[cfg(not(target_arch = "wasm32"))]
+fn prepare_request(
+    extensions: &ExtensionRegistry,
+    strings: &Strings,
+    url: String,
+    initiator: Option<String>,
+) -> Result<OutgoingRequest, String> {
+    let mut request = OutgoingRequest::new(url);
+    request.initiator = initiator;
+    extensions
+        .run_request_hooks(&mut request)
+        .map_err(|name| format!("{} ({name})", strings.error_blocked_by_extension))?;
+    Ok(request)
+}
+
+/// The window as media queries see it.
+#[cfg(not(target_arch = "wasm32"))]
+fn device_of(ctx: &egui::Context) -> Device {
+    let rect = ctx.content_rect();
+    Device {
+        width: rect.width(),
+        height: rect.height(),
+        pixel_ratio: ctx.pixels_per_point(),
+        color_scheme: match ctx.theme() {
+            egui::Theme::Dark => ColorScheme::Dark,
+            egui::Theme::Light => ColorScheme::Light,
+        },
+    }
+}
+
+/// The response as it came: its status, its headers and its body.
+fn raw_response_view(ui: &mut egui::Ui, response: &HttpResponse, strings: &Strings) {
+    ui.separator();
+
+    ui.label(format!("{}: {}", strings.status, response.status));
+
+    ui.separator();
+
+    ui.label(strings.headers);
+    for (name, value) in &response.headers {
+        ui.label(format!("{name}: {value}"));
+    }
+
+    ui.separator();
+
+    ui.label(strings.body);
+    egui::ScrollArea::vertical()
+        .auto_shrink([false; 2])
+        .show(ui, |ui| {
+            ui.label(&response.body);
+        });
+}
+
+fn powered_by_egui_and_eframe(ui: &mut egui::Ui, strings: &Strings) {
     ui.horizontal(|ui| {
         ui.spacing_mut().item_spacing.x = 0.0;
-        ui.label("Powered by ");
+        ui.label(strings.powered_by);
         ui.hyperlink_to("egui", "https://github.com/emilk/egui");
-        ui.label(" and ");
+        ui.label(strings.and);
         ui.hyperlink_to(
             "eframe",
             "https://github.com/emilk/egui/tree/master/crates/eframe",
@@ -200,3 +1077,35 @@ fn powered_by_egui_and_eframe(ui: &mut egui::Ui) {
         ui.label(".");
     });
 }
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::content_blocker::FilterList;
+
+    #[test]
+    fn test_subresources_follow_the_pages_blocking_switch() {
+        let blocker = Arc::new(ContentBlocker::new(FilterList::parse("||ads.example.com^")));
+        blocker.set_enabled_for("news.example.org", false);
+        let mut extensions = ExtensionRegistry::new();
+        extensions.register(Box::new(Arc::clone(&blocker)));
+        let strings = Language::English.strings();
+        let sheet = "https://ads.example.com/site.css";
+
+        let request = prepare_request(
+            &extensions,
+            strings,
+            sheet.to_owned(),
+            Some("https://news.example.org/article".to_owned()),
+        );
+        assert_eq!(request.map(|request| request.url), Ok(sheet.to_owned()));
+
+        let request = prepare_request(
+            &extensions,
+            strings,
+            sheet.to_owned(),
+            Some("https://example.com/".to_owned()),
+        );
+        assert!(request.is_err());
+    }
+}
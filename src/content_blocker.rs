@@ -0,0 +1,384 @@
+use std::collections::BTreeSet;
+use std::io;
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError, RwLock};
+
+use crate::extension::{BrowserExtension, OutgoingRequest, RequestAction};
+
+/// The filter list compiled into the browser.
+pub const BUILTIN_FILTER_LIST: &str = include_str!("../assets/filter_list.txt");
+
+/// Domain rules loaded from hosts files or EasyList-style filter lists.
+///
+/// Only whole-domain rules are understood: `0.0.0.0 ads.example.com`,
+/// bare `ads.example.com` lines, `||ads.example.com^` and the matching
+/// `@@||...^` exceptions. Anything else is skipped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FilterList {
+    blocked: BTreeSet<String>,
+    allowed: BTreeSet<String>,
+}
+
+impl FilterList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn parse(source: &str) -> Self {
+        let mut list = Self::new();
+        list.add_rules(source);
+        list
+    }
+
+    /// Adds every supported rule in `source` to this list.
+    pub fn add_rules(&mut self, source: &str) {
+        for line in source.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(['!', '#', '[']) {
+                continue;
+            }
+
+            if let Some(rule) = line.strip_prefix("@@") {
+                if let Some(domain) = parse_domain_rule(rule) {
+                    self.allowed.insert(domain);
+                }
+            } else if line.starts_with("||") {
+                if let Some(domain) = parse_domain_rule(line) {
+                    self.blocked.insert(domain);
+                }
+            } else {
+                self.blocked.extend(parse_hosts_line(line));
+            }
+        }
+    }
+
+    /// Number of blocked domains.
+    pub fn len(&self) -> usize {
+        self.blocked.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocked.is_empty()
+    }
+
+    /// Whether `host` or any of its parent domains is blocked and not excepted.
+    pub fn blocks_host(&self, host: &str) -> bool {
+        let host = host.to_ascii_lowercase();
+        let mut blocked = false;
+        for domain in domain_suffixes(&host) {
+            if self.allowed.contains(domain) {
+                return false;
+            }
+            blocked |= self.blocked.contains(domain);
+        }
+        blocked
+    }
+
+    pub fn blocks_url(&self, url: &str) -> bool {
+        host_of(url).is_some_and(|host| self.blocks_host(&host))
+    }
+}
+
+/// Parses `||example.com^`, ignoring any `$options`.
+fn parse_domain_rule(rule: &str) -> Option<String> {
+    let rule = rule.strip_prefix("||")?;
+    let rule = rule.split_once('$').map_or(rule, |(pattern, _)| pattern);
+    let domain = rule.strip_suffix('^').unwrap_or(rule);
+    is_domain(domain).then(|| domain.to_ascii_lowercase())
+}
+
+/// Parses a hosts-file line (`0.0.0.0 a.com b.com`) or a bare domain line.
+fn parse_hosts_line(line: &str) -> Vec<String> {
+    let line = line.split_once('#').map_or(line, |(content, _)| content);
+    let fields: Vec<&str> = line.split_whitespace().collect();
+
+    let domains = match fields.as_slice() {
+        [domain] => std::slice::from_ref(domain),
+        [address, domains @ ..] if address.parse::<IpAddr>().is_ok() => domains,
+        _ => &[],
+    };
+
+    domains
+        .iter()
+        .filter(|domain| is_domain(domain) && **domain != "localhost.localdomain")
+        .map(|domain| domain.to_ascii_lowercase())
+        .collect()
+}
+
+fn is_domain(candidate: &str) -> bool {
+    candidate.contains('.')
+        && !candidate.starts_with('.')
+        && !candidate.ends_with('.')
+        && candidate.parse::<IpAddr>().is_err()
+        && candidate
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+}
+
+/// `a.b.c`, `b.c`, `c`.
+fn domain_suffixes(host: &str) -> impl Iterator<Item = &str> {
+    std::iter::successors(Some(host), |domain| {
+        domain.split_once('.').map(|(_, parent)| parent)
+    })
+}
+
+/// Extracts the lowercase host from an absolute URL.
+pub fn host_of(url: &str) -> Option<String> {
+    let (_, rest) = url.split_once("://")?;
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or(rest);
+    let host_and_port = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let host = if host_and_port.starts_with('[') {
+        host_and_port
+            .split_once(']')
+            .map_or(host_and_port, |(host, _)| host)
+            .trim_start_matches('[')
+    } else {
+        host_and_port
+            .split_once(':')
+            .map_or(host_and_port, |(host, _)| host)
+    };
+
+    (!host.is_empty()).then(|| host.to_ascii_lowercase())
+}
+
+/// Blocks subresource requests to domains on a [`FilterList`].
+///
+/// Keeps a running count of blocked requests for the toolbar badge, and
+/// can be switched off for individual sites. Top-level navigations are
+/// never blocked: typing an address is a request from the user.
+pub struct ContentBlocker {
+    filters: RwLock<FilterList>,
+    blocked_count: AtomicUsize,
+    disabled_sites: Mutex<BTreeSet<String>>,
+}
+
+impl ContentBlocker {
+    pub fn new(filters: FilterList) -> Self {
+        Self {
+            filters: RwLock::new(filters),
+            blocked_count: AtomicUsize::new(0),
+            disabled_sites: Mutex::new(BTreeSet::new()),
+        }
+    }
+
+    /// Adds the rules of a user hosts file or EasyList-style list on top of
+    /// the ones already loaded.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read as UTF-8 text.
+    pub fn add_list_file(&self, path: &Path) -> io::Result<()> {
+        let source = std::fs::read_to_string(path)?;
+        self.filters
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .add_rules(&source);
+        Ok(())
+    }
+
+    /// Number of requests blocked since the last [`Self::reset_blocked_count`].
+    pub fn blocked_count(&self) -> usize {
+        self.blocked_count.load(Ordering::Relaxed)
+    }
+
+    /// Called when a new page starts loading.
+    pub fn reset_blocked_count(&self) {
+        self.blocked_count.store(0, Ordering::Relaxed);
+    }
+
+    pub fn is_enabled_for(&self, site: &str) -> bool {
+        !self
+            .disabled_sites
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .contains(&site.to_ascii_lowercase())
+    }
+
+    pub fn set_enabled_for(&self, site: &str, enabled: bool) {
+        let mut disabled_sites = self
+            .disabled_sites
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let site = site.to_ascii_lowercase();
+        if enabled {
+            disabled_sites.remove(&site);
+        } else {
+            disabled_sites.insert(site);
+        }
+    }
+
+    /// Sites where blocking has been switched off, in sorted order.
+    pub fn disabled_sites(&self) -> Vec<String> {
+        self.disabled_sites
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .cloned()
+            .collect()
+    }
+}
+
+impl Default for ContentBlocker {
+    fn default() -> Self {
+        Self::new(FilterList::parse(BUILTIN_FILTER_LIST))
+    }
+}
+
+impl BrowserExtension for ContentBlocker {
+    fn name(&self) -> &'static str {
+        "content-blocker"
+    }
+
+    fn on_request(&self, request: &mut OutgoingRequest) -> RequestAction {
+        let Some(initiator) = &request.initiator else {
+            return RequestAction::Continue;
+        };
+        if let Some(site) = host_of(initiator)
+            && !self.is_enabled_for(&site)
+        {
+            return RequestAction::Continue;
+        }
+
+        let blocked = self
+            .filters
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .blocks_url(&request.url);
+        if blocked {
+            self.blocked_count.fetch_add(1, Ordering::Relaxed);
+            RequestAction::Block
+        } else {
+            RequestAction::Continue
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hosts_file() {
+        let list = FilterList::parse(
+            "# hosts\n127.0.0.1 localhost\n0.0.0.0 ads.example.com tracker.example.org # inline\n",
+        );
+
+        assert_eq!(list.len(), 2);
+        assert!(list.blocks_host("ads.example.com"));
+        assert!(list.blocks_host("tracker.example.org"));
+        assert!(!list.blocks_host("localhost"));
+    }
+
+    #[test]
+    fn test_parse_easylist_domain_rules() {
+        let list = FilterList::parse(
+            "[Adblock Plus 2.0]\n! comment\n||ads.example.com^\n||track.example.net^$third-party\n/banner/*.gif\n",
+        );
+
+        assert_eq!(list.len(), 2);
+        assert!(list.blocks_host("ads.example.com"));
+        assert!(list.blocks_host("track.example.net"));
+    }
+
+    #[test]
+    fn test_subdomains_are_blocked() {
+        let list = FilterList::parse("||example.com^");
+
+        assert!(list.blocks_host("example.com"));
+        assert!(list.blocks_host("cdn.ads.EXAMPLE.com"));
+        assert!(!list.blocks_host("notexample.com"));
+    }
+
+    #[test]
+    fn test_exception_rules_win() {
+        let list = FilterList::parse("||example.com^\n@@||good.example.com^");
+
+        assert!(list.blocks_host("bad.example.com"));
+        assert!(!list.blocks_host("good.example.com"));
+        assert!(!list.blocks_host("img.good.example.com"));
+    }
+
+    #[test]
+    fn test_host_of() {
+        assert_eq!(
+            host_of("https://Ads.Example.com/path?q=1"),
+            Some("ads.example.com".to_string())
+        );
+        assert_eq!(
+            host_of("http://user:pw@example.com:8080/"),
+            Some("example.com".to_string())
+        );
+        assert_eq!(host_of("http://[::1]:80/"), Some("::1".to_string()));
+        assert_eq!(host_of("not a url"), None);
+    }
+
+    #[test]
+    fn test_blocker_counts_blocked_requests() {
+        let blocker = ContentBlocker::new(FilterList::parse("||ads.example.com^"));
+
+        let mut ad = OutgoingRequest::new("https://ads.example.com/banner.js");
+        ad.initiator = Some("https://example.com/".to_string());
+        let mut page = OutgoingRequest::new("https://example.com/style.css");
+        page.initiator = Some("https://example.com/".to_string());
+
+        assert_eq!(blocker.on_request(&mut ad), RequestAction::Block);
+        assert_eq!(blocker.on_request(&mut page), RequestAction::Continue);
+        assert_eq!(blocker.blocked_count(), 1);
+
+        blocker.reset_blocked_count();
+        assert_eq!(blocker.blocked_count(), 0);
+    }
+
+    #[test]
+    fn test_navigations_are_not_blocked() {
+        let blocker = ContentBlocker::new(FilterList::parse("||ads.example.com^"));
+
+        let mut navigation = OutgoingRequest::new("https://ads.example.com/");
+        assert_eq!(blocker.on_request(&mut navigation), RequestAction::Continue);
+        assert_eq!(blocker.blocked_count(), 0);
+    }
+
+    #[test]
+    fn test_add_list_file() {
+        let path = std::env::temp_dir().join(format!("filter-list-{}.txt", std::process::id()));
+        std::fs::write(&path, "0.0.0.0 tracker.example.net\n").unwrap();
+
+        let blocker = ContentBlocker::new(FilterList::new());
+        blocker.add_list_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let mut request = OutgoingRequest::new("https://tracker.example.net/pixel.gif");
+        request.initiator = Some("https://example.com/".to_string());
+        assert_eq!(blocker.on_request(&mut request), RequestAction::Block);
+        assert!(blocker.add_list_file(&path).is_err());
+    }
+
+    #[test]
+    fn test_blocker_respects_per_site_switch() {
+        let blocker = ContentBlocker::new(FilterList::parse("||ads.example.com^"));
+        blocker.set_enabled_for("news.example.org", false);
+
+        let mut request = OutgoingRequest::new("https://ads.example.com/banner.js");
+        request.initiator = Some("https://news.example.org/article".to_string());
+        assert_eq!(blocker.on_request(&mut request), RequestAction::Continue);
+        assert_eq!(
+            blocker.disabled_sites(),
+            vec!["news.example.org".to_string()]
+        );
+
+        blocker.set_enabled_for("news.example.org", true);
+        assert_eq!(blocker.on_request(&mut request), RequestAction::Block);
+    }
+
+    #[test]
+    fn test_builtin_list_parses() {
+        let list = FilterList::parse(BUILTIN_FILTER_LIST);
+
+        assert!(!list.is_empty());
+        assert!(list.blocks_url("https://stats.g.doubleclick.net/collect"));
+    }
+}
@@ -0,0 +1,18 @@
+//! CSS style sheets, tokenized and parsed by the algorithms of CSS Syntax
+//! Level 3 (<https://www.w3.org/TR/css-syntax-3/>).
+//!
+//! Parsing stops at rules, declarations and component values; what a
+//! selector or a property value means is left to the code that uses them.
+//! As in a browser, nothing here fails: invalid rules and declarations are
+//! dropped and the rest of the sheet is kept.
+
+mod parser;
+mod serialize;
+mod tokenizer;
+
+pub use parser::{
+    AtRule, AtRuleBlock, BlockKind, ComponentValue, Declaration, Import, Rule, StyleRule,
+    Stylesheet, parse_component_values, parse_declarations, trim_whitespace,
+};
+pub use serialize::serialize;
+pub use tokenizer::{CssToken, CssTokenizer, Number};
@@ -0,0 +1,611 @@
+use super::tokenizer::{CssToken, CssTokenizer};
+
+/// At-rules whose block holds rules rather than declarations.
+const GROUPING_AT_RULES: [&str; 7] = [
+    "container",
+    "document",
+    "layer",
+    "media",
+    "scope",
+    "starting-style",
+    "supports",
+];
+
+/// Which brackets a [`ComponentValue::Block`] is in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockKind {
+    /// `{...}`
+    Curly,
+
+    /// `[...]`
+    Square,
+
+    /// `(...)`
+    Paren,
+}
+
+/// A token, or a function or block with the component values inside it
+/// (<https://www.w3.org/TR/css-syntax-3/#component-value>).
+#[derive(Debug, Clone, PartialEq)]
+pub enum ComponentValue {
+    Token(CssToken),
+    Function {
+        name: String,
+        arguments: Vec<Self>,
+    },
+    Block {
+        kind: BlockKind,
+        contents: Vec<Self>,
+    },
+}
+
+impl ComponentValue {
+    /// Whether the value is a whitespace token.
+    pub fn is_whitespace(&self) -> bool {
+        *self == Self::Token(CssToken::Whitespace)
+    }
+}
+
+/// A `name: value` pair, such as `color: red !important`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Declaration {
+    /// The property name, lowercased unless it is a custom property such
+    /// as `--main-color`.
+    pub name: String,
+
+    /// The value, without surrounding whitespace or `!important`.
+    pub value: Vec<ComponentValue>,
+    pub important: bool,
+}
+
+/// A selector and its declarations, such as `p.intro { margin: 0 }`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StyleRule {
+    /// Everything before the `{`, which is the selector list.
+    pub prelude: Vec<ComponentValue>,
+    pub declarations: Vec<Declaration>,
+
+    /// The line the rule starts on, counting from 1.
+    pub line: usize,
+}
+
+/// The block of an [`AtRule`], whose contents depend on the rule.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AtRuleBlock {
+    /// The rules inside a grouping rule such as `@media` or `@supports`.
+    Rules(Vec<Rule>),
+
+    /// The declarations of a rule such as `@font-face` or `@page`.
+    Declarations(Vec<Declaration>),
+}
+
+/// A rule starting with an at-keyword, such as `@media screen { ... }` or
+/// `@import url(a.css);`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtRule {
+    /// The lowercase name, without the `@`.
+    pub name: String,
+
+    /// Everything between the name and the block or `;`.
+    pub prelude: Vec<ComponentValue>,
+
+    /// `None` for a rule that ends with `;` instead of a block.
+    pub block: Option<AtRuleBlock>,
+
+    /// The line the rule starts on, counting from 1.
+    pub line: usize,
+}
+
+/// A rule at the top level of a style sheet or inside a grouping rule.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rule {
+    Style(StyleRule),
+    At(AtRule),
+}
+
+/// An `@import` rule of a style sheet; see [`Stylesheet::imports`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Import {
+    /// The URL of the imported sheet, as written.
+    pub url: String,
+
+    /// The media queries the imported sheet applies under, which are empty
+    /// if it always applies.
+    pub media: Vec<ComponentValue>,
+}
+
+/// A parsed style sheet.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stylesheet {
+    pub rules: Vec<Rule>,
+}
+
+impl Stylesheet {
+    /// Parses `css` as a style sheet. Like a browser, this never fails:
+    /// rules and declarations that cannot be parsed are skipped, and the
+    /// rest of the sheet is kept.
+    pub fn parse(css: &str) -> Self {
+        Self {
+            rules: Parser::new(css).consume_rules(true),
+        }
+    }
+
+    /// The style rules of the sheet, including those inside grouping rules
+    /// such as `@media`, in order.
+    pub fn style_rules(&self) -> impl Iterator<Item = &StyleRule> + '_ {
+        let mut stack: Vec<std::slice::Iter<'_, Rule>> = vec![self.rules.iter()];
+        std::iter::from_fn(move || {
+            loop {
+                let Some(rule) = stack.last_mut()?.next() else {
+                    stack.pop();
+                    continue;
+                };
+                match rule {
+                    Rule::Style(rule) => return Some(rule),
+                    Rule::At(AtRule {
+                        block: Some(AtRuleBlock::Rules(rules)),
+                        ..
+                    }) => stack.push(rules.iter()),
+                    Rule::At(_) => {}
+                }
+            }
+        })
+    }
+
+    /// The `@import` rules of the sheet. Only those at its start count,
+    /// before any rule other than `@charset` and `@layer` statements; those
+    /// with a `supports()` condition are left out.
+    pub fn imports(&self) -> Vec<Import> {
+        let mut imports = Vec::new();
+        for rule in &self.rules {
+            let Rule::At(rule) = rule else {
+                break;
+            };
+            match rule.name.as_str() {
+                "import" => imports.extend(import(&rule.prelude)),
+                "charset" => {}
+                "layer" if rule.block.is_none() => {}
+                _ => break,
+            }
+        }
+        imports
+    }
+}
+
+/// Parses the prelude of an `@import` rule: the URL, as a `url()` or a
+/// string, then an optional `layer`, then the media queries.
+fn import(prelude: &[ComponentValue]) -> Option<Import> {
+    let start = prelude.iter().position(|value| !value.is_whitespace())?;
+    let (url, rest) = prelude.get(start..)?.split_first()?;
+    let url = match url {
+        ComponentValue::Token(CssToken::Url(url) | CssToken::String(url)) => url.clone(),
+        ComponentValue::Function { name, arguments } if name.eq_ignore_ascii_case("url") => {
+            match trim_whitespace(arguments) {
+                [ComponentValue::Token(CssToken::String(url))] => url.clone(),
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+    let mut media = trim_whitespace(rest).to_vec();
+    let is_layer = match media.first() {
+        Some(
+            ComponentValue::Token(CssToken::Ident(name)) | ComponentValue::Function { name, .. },
+        ) => name.eq_ignore_ascii_case("layer"),
+        _ => false,
+    };
+    if is_layer {
+        media = trim_whitespace(media.get(1..).unwrap_or_default()).to_vec();
+    }
+    if matches!(
+        media.first(),
+        Some(ComponentValue::Function { name, .. }) if name.eq_ignore_ascii_case("supports")
+    ) {
+        return None;
+    }
+    Some(Import { url, media })
+}
+
+/// Parses a list of declarations, such as the value of a `style`
+/// attribute.
+pub fn parse_declarations(css: &str) -> Vec<Declaration> {
+    Parser::new(css).consume_declarations()
+}
+
+/// Parses `css` as a list of component values, such as a selector list
+/// on its own.
+pub fn parse_component_values(css: &str) -> Vec<ComponentValue> {
+    let mut parser = Parser::new(css);
+    std::iter::from_fn(|| parser.consume_component_value()).collect()
+}
+
+/// Turns tokens into rules by the parsing algorithms of CSS Syntax
+/// (<https://www.w3.org/TR/css-syntax-3/#parsing>).
+struct Parser {
+    tokenizer: CssTokenizer,
+    peeked: Option<CssToken>,
+}
+
+impl Parser {
+    fn new(css: &str) -> Self {
+        Self {
+            tokenizer: CssTokenizer::new(css),
+            peeked: None,
+        }
+    }
+
+    fn peek(&mut self) -> Option<&CssToken> {
+        if self.peeked.is_none() {
+            self.peeked = self.tokenizer.next();
+        }
+        self.peeked.as_ref()
+    }
+
+    fn next(&mut self) -> Option<CssToken> {
+        self.peeked.take().or_else(|| self.tokenizer.next())
+    }
+
+    /// Consumes rules up to the end of the input, or, inside a block, up to
+    /// and including the `}` that closes it.
+    fn consume_rules(&mut self, top_level: bool) -> Vec<Rule> {
+        let mut rules = Vec::new();
+        loop {
+            match self.peek() {
+                None => break,
+                Some(CssToken::Whitespace) => {
+                    self.next();
+                }
+                Some(CssToken::Cdo | CssToken::Cdc) if top_level => {
+                    self.next();
+                }
+                Some(CssToken::CloseCurly) if !top_level => {
+                    self.next();
+                    break;
+                }
+                Some(CssToken::AtKeyword(_)) => rules.push(Rule::At(self.consume_at_rule())),
+                Some(_) => {
+                    if let Some(rule) = self.consume_style_rule(top_level) {
+                        rules.push(Rule::Style(rule));
+                    }
+                }
+            }
+        }
+        rules
+    }
+
+    fn consume_at_rule(&mut self) -> AtRule {
+        let line = self.tokenizer.line();
+        let name = match self.next() {
+            Some(CssToken::AtKeyword(name)) => name.to_ascii_lowercase(),
+            _ => String::new(),
+        };
+        let mut prelude = Vec::new();
+        let block = loop {
+            match self.peek() {
+                None => break None,
+                Some(CssToken::Semicolon) => {
+                    self.next();
+                    break None;
+                }
+                Some(CssToken::OpenCurly) => {
+                    self.next();
+                    break Some(if GROUPING_AT_RULES.contains(&name.as_str()) {
+                        AtRuleBlock::Rules(self.consume_rules(false))
+                    } else {
+                        AtRuleBlock::Declarations(self.consume_declarations())
+                    });
+                }
+                Some(_) => prelude.extend(self.consume_component_value()),
+            }
+        };
+        AtRule {
+            name,
+            prelude: trim_whitespace(&prelude).to_vec(),
+            block,
+            line,
+        }
+    }
+
+    /// Consumes a style rule. Returns `None` if the input, or the block the
+    /// rule is in, ends before the rule's own block starts.
+    fn consume_style_rule(&mut self, top_level: bool) -> Option<StyleRule> {
+        let line = self.tokenizer.line();
+        let mut prelude = Vec::new();
+        loop {
+            match self.peek()? {
+                CssToken::OpenCurly => {
+                    self.next();
+                    return Some(StyleRule {
+                        prelude: trim_whitespace(&prelude).to_vec(),
+                        declarations: self.consume_declarations(),
+                        line,
+                    });
+                }
+                CssToken::CloseCurly if !top_level => return None,
+                _ => prelude.extend(self.consume_component_value()),
+            }
+        }
+    }
+
+    /// Consumes declarations up to the end of the input or a `}`, which is
+    /// consumed too. Anything that is not a valid declaration, such as a
+    /// nested rule, is skipped.
+    fn consume_declarations(&mut self) -> Vec<Declaration> {
+        let mut declarations = Vec::new();
+        loop {
+            match self.peek() {
+                None => break,
+                Some(CssToken::Whitespace | CssToken::Semicolon) => {
+                    self.next();
+                }
+                Some(CssToken::CloseCurly) => {
+                    self.next();
+                    break;
+                }
+                Some(CssToken::AtKeyword(_)) => {
+                    self.consume_at_rule();
+                }
+                Some(_) => {
+                    let values = self.consume_until_declaration_end();
+                    declarations.extend(declaration(values));
+                }
+            }
+        }
+        declarations
+    }
+
+    /// Consumes component values up to, but not including, a `;` or `}`.
+    /// Unless the values start with a name and a colon, a `{}` block ends
+    /// them too, as it belongs to a nested rule rather than a declaration.
+    fn consume_until_declaration_end(&mut self) -> Vec<ComponentValue> {
+        let mut values = Vec::new();
+        let mut seen_colon = false;
+        while let Some(token) = self.peek() {
+            if matches!(token, CssToken::Semicolon | CssToken::CloseCurly) {
+                break;
+            }
+            let after_name = matches!(
+                values.first(),
+                Some(ComponentValue::Token(CssToken::Ident(_)))
+            ) && values.iter().skip(1).all(ComponentValue::is_whitespace);
+            seen_colon |= after_name && *token == CssToken::Colon;
+            let Some(value) = self.consume_component_value() else {
+                break;
+            };
+            let ends_rule = !seen_colon
+                && matches!(
+                    value,
+                    ComponentValue::Block {
+                        kind: BlockKind::Curly,
+                        ..
+                    }
+                );
+            values.push(value);
+            if ends_rule {
+                break;
+            }
+        }
+        values
+    }
+
+    fn consume_component_value(&mut self) -> Option<ComponentValue> {
+        let value = match self.next()? {
+            CssToken::OpenCurly => ComponentValue::Block {
+                kind: BlockKind::Curly,
+                contents: self.consume_until(&CssToken::CloseCurly),
+            },
+            CssToken::OpenSquare => ComponentValue::Block {
+                kind: BlockKind::Square,
+                contents: self.consume_until(&CssToken::CloseSquare),
+            },
+            CssToken::OpenParen => ComponentValue::Block {
+                kind: BlockKind::Paren,
+                contents: self.consume_until(&CssToken::CloseParen),
+            },
+            CssToken::Function(name) => ComponentValue::Function {
+                name,
+                arguments: self.consume_until(&CssToken::CloseParen),
+            },
+            token => ComponentValue::Token(token),
+        };
+        Some(value)
+    }
+
+    /// Consumes component values up to and including `close`, or to the
+    /// end of the input.
+    fn consume_until(&mut self, close: &CssToken) -> Vec<ComponentValue> {
+        let mut values = Vec::new();
+        while let Some(token) = self.peek() {
+            if token == close {
+                self.next();
+                break;
+            }
+            values.extend(self.consume_component_value());
+        }
+        values
+    }
+}
+
+/// Parses the component values of one declaration. Returns `None` unless
+/// they start with a name and a colon.
+fn declaration(values: Vec<ComponentValue>) -> Option<Declaration> {
+    let mut values = values.into_iter();
+    let Some(ComponentValue::Token(CssToken::Ident(name))) = values.next() else {
+        return None;
+    };
+    let mut values = values.skip_while(ComponentValue::is_whitespace);
+    if values.next() != Some(ComponentValue::Token(CssToken::Colon)) {
+        return None;
+    }
+    let values: Vec<ComponentValue> = values.collect();
+    let mut value = trim_whitespace(&values).to_vec();
+
+    let important = match value.as_slice() {
+        [
+            ..,
+            ComponentValue::Token(CssToken::Delim('!')),
+            ComponentValue::Token(CssToken::Ident(keyword)),
+        ] if keyword.eq_ignore_ascii_case("important") => {
+            value.truncate(value.len() - 2);
+            true
+        }
+        [
+            ..,
+            ComponentValue::Token(CssToken::Delim('!')),
+            ComponentValue::Token(CssToken::Whitespace),
+            ComponentValue::Token(CssToken::Ident(keyword)),
+        ] if keyword.eq_ignore_ascii_case("important") => {
+            value.truncate(value.len() - 3);
+            true
+        }
+        _ => false,
+    };
+
+    Some(Declaration {
+        name: if name.starts_with("--") {
+            name
+        } else {
+            name.to_ascii_lowercase()
+        },
+        value: trim_whitespace(&value).to_vec(),
+        important,
+    })
+}
+
+/// `values` without leading and trailing whitespace.
+pub fn trim_whitespace(values: &[ComponentValue]) -> &[ComponentValue] {
+    let start = values
+        .iter()
+        .position(|value| !value.is_whitespace())
+        .unwrap_or(values.len());
+    let end = values
+        .iter()
+        .rposition(|value| !value.is_whitespace())
+        .map_or(start, |end| end + 1);
+    values.get(start..end).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::Number;
+
+    fn ident(name: &str) -> ComponentValue {
+        ComponentValue::Token(CssToken::Ident(name.to_owned()))
+    }
+
+    #[test]
+    fn test_style_rules_and_declarations() {
+        let sheet = Stylesheet::parse(
+            "p, .a { color: red; Margin : 0 !important }\n\
+             div{--Main-Color:blue}",
+        );
+        let rules: Vec<_> = sheet.style_rules().collect();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].line, 1);
+        assert_eq!(rules[1].line, 2);
+        assert_eq!(rules[0].prelude.len(), 5);
+        assert_eq!(
+            rules[0].declarations,
+            vec![
+                Declaration {
+                    name: "color".to_owned(),
+                    value: vec![ident("red")],
+                    important: false,
+                },
+                Declaration {
+                    name: "margin".to_owned(),
+                    value: vec![ComponentValue::Token(CssToken::Number(Number {
+                        value: 0.0,
+                        integer: true,
+                    }))],
+                    important: true,
+                },
+            ]
+        );
+        assert_eq!(rules[1].declarations[0].name, "--Main-Color");
+    }
+
+    #[test]
+    fn test_invalid_declarations_are_skipped() {
+        let sheet = Stylesheet::parse(
+            "a { color red; : x; width: calc(1px + (2px)); ; &:hover { color: blue } \
+             height: 1px; @apply --x; }",
+        );
+        let names: Vec<_> = sheet
+            .style_rules()
+            .flat_map(|rule| &rule.declarations)
+            .map(|declaration| declaration.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["width", "height"]);
+    }
+
+    #[test]
+    fn test_at_rules() {
+        let sheet = Stylesheet::parse(
+            "@import url(a.css) screen;\n\
+             @media (min-width: 600px) { p { margin: 0 } }\n\
+             @font-face { font-family: X; src: url(x.woff) }\n\
+             h1 { x: y }",
+        );
+        assert_eq!(sheet.rules.len(), 4);
+        let Rule::At(import) = &sheet.rules[0] else {
+            panic!("expected @import, got {:?}", sheet.rules[0]);
+        };
+        assert_eq!(import.name, "import");
+        assert_eq!(import.block, None);
+        assert_eq!(import.prelude.len(), 3);
+
+        let Rule::At(media) = &sheet.rules[1] else {
+            panic!("expected @media, got {:?}", sheet.rules[1]);
+        };
+        assert_eq!(media.line, 2);
+        assert!(matches!(&media.block, Some(AtRuleBlock::Rules(rules)) if rules.len() == 1));
+
+        let Rule::At(font_face) = &sheet.rules[2] else {
+            panic!("expected @font-face, got {:?}", sheet.rules[2]);
+        };
+        assert!(matches!(
+            &font_face.block,
+            Some(AtRuleBlock::Declarations(declarations)) if declarations.len() == 2
+        ));
+        assert_eq!(sheet.style_rules().count(), 2);
+    }
+
+    #[test]
+    fn test_imports() {
+        let sheet = Stylesheet::parse(
+            "@charset \"utf-8\"; @import url(a.css); @import 'b.css' screen and (color);\
+             @layer base; @import url('c.css') layer(base) print; \
+             @import url(d.css) supports(display: grid); @import;\
+             p {} @import url(late.css);",
+        );
+        let imports = sheet.imports();
+        let urls: Vec<&str> = imports.iter().map(|import| import.url.as_str()).collect();
+        assert_eq!(urls, ["a.css", "b.css", "c.css"]);
+        assert!(imports[0].media.is_empty());
+        assert_eq!(imports[1].media.len(), 5);
+        assert_eq!(imports[2].media, vec![ident("print")]);
+    }
+
+    #[test]
+    fn test_unclosed_and_stray_input() {
+        let sheet = Stylesheet::parse("} a { color: red; <!-- b { x: y");
+        let rules: Vec<_> = sheet.style_rules().collect();
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0].declarations.len(), 1);
+
+        assert_eq!(Stylesheet::parse("p { color: red").style_rules().count(), 1);
+        assert_eq!(Stylesheet::parse("p").rules, vec![]);
+    }
+
+    #[test]
+    fn test_parse_declarations() {
+        let declarations = parse_declarations("color: red; background: url( x.png ) ;bad");
+        assert_eq!(declarations.len(), 2);
+        assert_eq!(
+            declarations[1].value,
+            vec![ComponentValue::Token(CssToken::Url("x.png".to_owned()))]
+        );
+    }
+}
@@ -0,0 +1,161 @@
+use std::fmt;
+
+use super::parser::{BlockKind, ComponentValue, Declaration, StyleRule};
+use super::tokenizer::{CssToken, Number};
+
+/// Writes `values` back as CSS text, such as `1px solid var(--border)`.
+///
+/// The text parses to the same values, though it is not always the text
+/// they were parsed from: comments are gone, and numbers and strings are
+/// written in one way.
+pub fn serialize(values: &[ComponentValue]) -> String {
+    values.iter().map(ToString::to_string).collect()
+}
+
+impl fmt::Display for CssToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ident(name) => f.write_str(name),
+            Self::Function(name) => write!(f, "{name}("),
+            Self::AtKeyword(name) => write!(f, "@{name}"),
+            Self::Hash { value, .. } => write!(f, "#{value}"),
+            Self::String(string) => write_string(f, string),
+            Self::Url(url) => write!(f, "url({url})"),
+            Self::BadString | Self::BadUrl => Ok(()),
+            Self::Delim(delim) => write!(f, "{delim}"),
+            Self::Number(number) => write_number(f, *number),
+            Self::Percentage(number) => {
+                write_number(f, *number)?;
+                f.write_str("%")
+            }
+            Self::Dimension { value, unit } => {
+                write_number(f, *value)?;
+                f.write_str(unit)
+            }
+            Self::Whitespace => f.write_str(" "),
+            Self::Cdo => f.write_str("<!--"),
+            Self::Cdc => f.write_str("-->"),
+            Self::Colon => f.write_str(":"),
+            Self::Semicolon => f.write_str(";"),
+            Self::Comma => f.write_str(","),
+            Self::OpenSquare => f.write_str("["),
+            Self::CloseSquare => f.write_str("]"),
+            Self::OpenParen => f.write_str("("),
+            Self::CloseParen => f.write_str(")"),
+            Self::OpenCurly => f.write_str("{"),
+            Self::CloseCurly => f.write_str("}"),
+        }
+    }
+}
+
+/// Writes `number` so that it parses back the same, keeping a `.0` on a
+/// whole number that was not written as an integer, such as `1e3`.
+fn write_number(f: &mut fmt::Formatter<'_>, number: Number) -> fmt::Result {
+    if number.integer || number.value.fract() != 0.0 || !number.value.is_finite() {
+        write!(f, "{}", number.value)
+    } else {
+        write!(f, "{:.1}", number.value)
+    }
+}
+
+/// Writes `string` in double quotes, escaping what has to be.
+fn write_string(f: &mut fmt::Formatter<'_>, string: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in string.chars() {
+        match c {
+            '"' | '\\' => write!(f, "\\{c}")?,
+            '\n' => f.write_str("\\a ")?,
+            c => write!(f, "{c}")?,
+        }
+    }
+    f.write_str("\"")
+}
+
+impl fmt::Display for ComponentValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Token(token) => token.fmt(f),
+            Self::Function { name, arguments } => write!(f, "{name}({})", serialize(arguments)),
+            Self::Block { kind, contents } => {
+                let (open, close) = match kind {
+                    BlockKind::Curly => ("{", "}"),
+                    BlockKind::Square => ("[", "]"),
+                    BlockKind::Paren => ("(", ")"),
+                };
+                write!(f, "{open}{}{close}", serialize(contents))
+            }
+        }
+    }
+}
+
+impl Declaration {
+    /// The value as CSS text, without `!important`.
+    pub fn value_text(&self) -> String {
+        serialize(&self.value)
+    }
+}
+
+/// Writes the declaration as `name: value`, with `!important` if it is.
+impl fmt::Display for Declaration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.name, self.value_text())?;
+        if self.important {
+            f.write_str(" !important")?;
+        }
+        Ok(())
+    }
+}
+
+impl StyleRule {
+    /// The selector list as CSS text, such as `div > p, .note`.
+    pub fn selector_text(&self) -> String {
+        serialize(&self.prelude).trim().to_owned()
+    }
+}
+
+/// Writes the rule as `selectors { declarations }`, on one line.
+impl fmt::Display for StyleRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {{", self.selector_text())?;
+        for declaration in &self.declarations {
+            write!(f, " {declaration};")?;
+        }
+        f.write_str(" }")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::{Stylesheet, parse_component_values};
+
+    #[test]
+    fn test_serialize_round_trips() {
+        for css in [
+            "1px solid var(--border, #ccc)",
+            "url(a.png) no-repeat, linear-gradient(to right, red 10%, blue)",
+            "\"Times \\\"New\\\" Roman\", serif",
+            "calc(100% - 2.5em) [name] {x}",
+            "-0.5 +3 1e3",
+        ] {
+            let values = parse_component_values(css);
+            let text = serialize(&values);
+            assert_eq!(parse_component_values(&text), values, "{css} -> {text}");
+        }
+        assert_eq!(serialize(&parse_component_values("1.50em  +2")), "1.5em 2");
+    }
+
+    #[test]
+    fn test_rule_text() {
+        let sheet = Stylesheet::parse(
+            "div  >  p, .note /* c */ { color: RED; margin: 0 auto !important; --x:{a} }",
+        );
+        let rule = sheet.style_rules().next().unwrap();
+        assert_eq!(rule.selector_text(), "div > p, .note");
+        assert_eq!(rule.declarations[0].value_text(), "RED");
+        assert_eq!(
+            rule.to_string(),
+            "div > p, .note { color: RED; margin: 0 auto !important; --x: {a}; }"
+        );
+    }
+}
@@ -0,0 +1,576 @@
+/// A token of CSS, as produced by the tokenization stage of CSS Syntax
+/// (<https://www.w3.org/TR/css-syntax-3/#tokenization>). Comments are
+/// dropped.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CssToken {
+    Ident(String),
+
+    /// A name followed by `(`, as in `rgb(`. The name does not include the
+    /// parenthesis.
+    Function(String),
+
+    /// `@` and a name, as in `@media`. The name does not include the `@`.
+    AtKeyword(String),
+
+    /// `#` and a name. `id` is set when the name would be a valid
+    /// identifier, so that the hash can be an ID selector.
+    Hash {
+        value: String,
+        id: bool,
+    },
+    String(String),
+
+    /// A string with an unescaped newline in it.
+    BadString,
+
+    /// An unquoted `url(...)`.
+    Url(String),
+    BadUrl,
+    Delim(char),
+    Number(Number),
+    Percentage(Number),
+    Dimension {
+        value: Number,
+        unit: String,
+    },
+    Whitespace,
+
+    /// `<!--`, allowed in style sheets for compatibility.
+    Cdo,
+
+    /// `-->`.
+    Cdc,
+    Colon,
+    Semicolon,
+    Comma,
+    OpenSquare,
+    CloseSquare,
+    OpenParen,
+    CloseParen,
+    OpenCurly,
+    CloseCurly,
+}
+
+/// A numeric value, remembering whether it was written as an integer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Number {
+    pub value: f64,
+    pub integer: bool,
+}
+
+/// Splits CSS source into [`CssToken`]s.
+///
+/// Tokenization never fails: malformed input comes out as
+/// [`CssToken::BadString`], [`CssToken::BadUrl`] or [`CssToken::Delim`],
+/// which the parser then skips over.
+#[derive(Debug, Clone)]
+pub struct CssTokenizer {
+    /// The input, with newlines and NULs replaced as the spec preprocesses
+    /// them.
+    chars: Vec<char>,
+    position: usize,
+
+    /// The line `line_position` is on, counting from 1, kept to answer
+    /// [`CssTokenizer::line`] without rescanning from the start.
+    line: usize,
+    line_position: usize,
+}
+
+impl CssTokenizer {
+    /// A tokenizer over `css`, which is preprocessed up front.
+    pub fn new(css: &str) -> Self {
+        let mut chars = Vec::with_capacity(css.len());
+        let mut input = css.chars().peekable();
+        while let Some(c) = input.next() {
+            match c {
+                '\r' => {
+                    input.next_if_eq(&'\n');
+                    chars.push('\n');
+                }
+                '\u{c}' => chars.push('\n'),
+                '\0' => chars.push(char::REPLACEMENT_CHARACTER),
+                c => chars.push(c),
+            }
+        }
+        Self {
+            chars,
+            position: 0,
+            line: 1,
+            line_position: 0,
+        }
+    }
+
+    /// The line, counting from 1, that the tokenizer has got to.
+    pub fn line(&mut self) -> usize {
+        let newlines = self
+            .chars
+            .get(self.line_position..self.position)
+            .unwrap_or_default()
+            .iter()
+            .filter(|&&c| c == '\n')
+            .count();
+        self.line += newlines;
+        self.line_position = self.position;
+        self.line
+    }
+
+    fn peek(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.position + offset).copied()
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let c = self.peek(0)?;
+        self.position += 1;
+        Some(c)
+    }
+
+    fn consume_token(&mut self) -> Option<CssToken> {
+        self.consume_comments();
+        let c = self.advance()?;
+        let token = match c {
+            c if is_whitespace(c) => {
+                while self.peek(0).is_some_and(is_whitespace) {
+                    self.position += 1;
+                }
+                CssToken::Whitespace
+            }
+            '"' | '\'' => self.consume_string(c),
+            '#' => {
+                if self.peek(0).is_some_and(is_name_char) || self.starts_escape(0) {
+                    let id = self.starts_identifier(0);
+                    CssToken::Hash {
+                        value: self.consume_name(),
+                        id,
+                    }
+                } else {
+                    CssToken::Delim('#')
+                }
+            }
+            '(' => CssToken::OpenParen,
+            ')' => CssToken::CloseParen,
+            '[' => CssToken::OpenSquare,
+            ']' => CssToken::CloseSquare,
+            '{' => CssToken::OpenCurly,
+            '}' => CssToken::CloseCurly,
+            ',' => CssToken::Comma,
+            ':' => CssToken::Colon,
+            ';' => CssToken::Semicolon,
+            '+' | '-' | '.' if self.starts_number(-1) => {
+                self.position -= 1;
+                self.consume_numeric()
+            }
+            '-' if self.peek(0) == Some('-') && self.peek(1) == Some('>') => {
+                self.position += 2;
+                CssToken::Cdc
+            }
+            '-' if self.starts_identifier(-1) => {
+                self.position -= 1;
+                self.consume_ident_like()
+            }
+            '<' if self.peek(0) == Some('!')
+                && self.peek(1) == Some('-')
+                && self.peek(2) == Some('-') =>
+            {
+                self.position += 3;
+                CssToken::Cdo
+            }
+            '@' if self.starts_identifier(0) => CssToken::AtKeyword(self.consume_name()),
+            '\\' if self.starts_escape(-1) => {
+                self.position -= 1;
+                self.consume_ident_like()
+            }
+            c if c.is_ascii_digit() => {
+                self.position -= 1;
+                self.consume_numeric()
+            }
+            c if is_name_start_char(c) => {
+                self.position -= 1;
+                self.consume_ident_like()
+            }
+            c => CssToken::Delim(c),
+        };
+        Some(token)
+    }
+
+    fn consume_comments(&mut self) {
+        while self.peek(0) == Some('/') && self.peek(1) == Some('*') {
+            self.position += 2;
+            loop {
+                match self.advance() {
+                    Some('*') if self.peek(0) == Some('/') => {
+                        self.position += 1;
+                        break;
+                    }
+                    Some(_) => {}
+                    None => return,
+                }
+            }
+        }
+    }
+
+    /// The character `offset` places from the current one, which may be
+    /// before it.
+    fn at(&self, offset: isize) -> Option<char> {
+        let index = self.position.checked_add_signed(offset)?;
+        self.chars.get(index).copied()
+    }
+
+    /// Whether the characters from `offset` are a backslash that starts an
+    /// escape.
+    fn starts_escape(&self, offset: isize) -> bool {
+        self.at(offset) == Some('\\') && self.at(offset + 1).is_some_and(|c| c != '\n')
+    }
+
+    /// Whether the characters from `offset` start an identifier.
+    fn starts_identifier(&self, offset: isize) -> bool {
+        match self.at(offset) {
+            Some('-') => {
+                self.at(offset + 1)
+                    .is_some_and(|c| is_name_start_char(c) || c == '-')
+                    || self.starts_escape(offset + 1)
+            }
+            Some('\\') => self.starts_escape(offset),
+            Some(c) => is_name_start_char(c),
+            None => false,
+        }
+    }
+
+    /// Whether the characters from `offset` start a number.
+    fn starts_number(&self, offset: isize) -> bool {
+        let digit = |offset| self.at(offset).is_some_and(|c: char| c.is_ascii_digit());
+        match self.at(offset) {
+            Some('+' | '-') => {
+                digit(offset + 1) || (self.at(offset + 1) == Some('.') && digit(offset + 2))
+            }
+            Some('.') => digit(offset + 1),
+            Some(c) => c.is_ascii_digit(),
+            None => false,
+        }
+    }
+
+    fn consume_string(&mut self, quote: char) -> CssToken {
+        let mut value = String::new();
+        while let Some(c) = self.advance() {
+            match c {
+                c if c == quote => return CssToken::String(value),
+                '\n' => {
+                    self.position -= 1;
+                    return CssToken::BadString;
+                }
+                '\\' => match self.peek(0) {
+                    None => {}
+                    Some('\n') => self.position += 1,
+                    Some(_) => value.push(self.consume_escape()),
+                },
+                c => value.push(c),
+            }
+        }
+        CssToken::String(value)
+    }
+
+    /// Consumes the escape after a backslash.
+    fn consume_escape(&mut self) -> char {
+        let Some(c) = self.advance() else {
+            return char::REPLACEMENT_CHARACTER;
+        };
+        if !c.is_ascii_hexdigit() {
+            return c;
+        }
+        let mut hex = String::from(c);
+        while hex.len() < 6
+            && let Some(c) = self.peek(0).filter(char::is_ascii_hexdigit)
+        {
+            hex.push(c);
+            self.position += 1;
+        }
+        if self.peek(0).is_some_and(is_whitespace) {
+            self.position += 1;
+        }
+        u32::from_str_radix(&hex, 16)
+            .ok()
+            .filter(|&code| code != 0)
+            .and_then(char::from_u32)
+            .unwrap_or(char::REPLACEMENT_CHARACTER)
+    }
+
+    fn consume_name(&mut self) -> String {
+        let mut name = String::new();
+        loop {
+            match self.peek(0) {
+                Some(c) if is_name_char(c) => {
+                    name.push(c);
+                    self.position += 1;
+                }
+                Some('\\') if self.starts_escape(0) => {
+                    self.position += 1;
+                    name.push(self.consume_escape());
+                }
+                _ => return name,
+            }
+        }
+    }
+
+    fn consume_number(&mut self) -> Number {
+        let mut repr = String::new();
+        let mut integer = true;
+        if let Some(sign) = self.peek(0).filter(|&c| c == '+' || c == '-') {
+            repr.push(sign);
+            self.position += 1;
+        }
+        self.consume_digits(&mut repr);
+        if self.peek(0) == Some('.') && self.peek(1).is_some_and(|c| c.is_ascii_digit()) {
+            integer = false;
+            repr.push('.');
+            self.position += 1;
+            self.consume_digits(&mut repr);
+        }
+        if matches!(self.peek(0), Some('e' | 'E')) {
+            let exponent_digits = match self.peek(1) {
+                Some('+' | '-') => 2,
+                _ => 1,
+            };
+            if self
+                .peek(exponent_digits)
+                .is_some_and(|c| c.is_ascii_digit())
+            {
+                integer = false;
+                for _ in 0..exponent_digits {
+                    repr.extend(self.advance());
+                }
+                self.consume_digits(&mut repr);
+            }
+        }
+        Number {
+            value: repr.parse().unwrap_or_default(),
+            integer,
+        }
+    }
+
+    fn consume_digits(&mut self, repr: &mut String) {
+        while let Some(c) = self.peek(0).filter(char::is_ascii_digit) {
+            repr.push(c);
+            self.position += 1;
+        }
+    }
+
+    fn consume_numeric(&mut self) -> CssToken {
+        let value = self.consume_number();
+        if self.starts_identifier(0) {
+            CssToken::Dimension {
+                value,
+                unit: self.consume_name(),
+            }
+        } else if self.peek(0) == Some('%') {
+            self.position += 1;
+            CssToken::Percentage(value)
+        } else {
+            CssToken::Number(value)
+        }
+    }
+
+    fn consume_ident_like(&mut self) -> CssToken {
+        let name = self.consume_name();
+        if self.peek(0) != Some('(') {
+            return CssToken::Ident(name);
+        }
+        self.position += 1;
+        if !name.eq_ignore_ascii_case("url") {
+            return CssToken::Function(name);
+        }
+        // `url(` followed by a quote is a function taking a string.
+        let mut lookahead = 0;
+        while self.peek(lookahead).is_some_and(is_whitespace) {
+            lookahead += 1;
+        }
+        if matches!(self.peek(lookahead), Some('"' | '\'')) {
+            return CssToken::Function(name);
+        }
+        self.position += lookahead;
+        self.consume_url()
+    }
+
+    /// Consumes an unquoted `url(...)` after its `(` and any whitespace.
+    fn consume_url(&mut self) -> CssToken {
+        let mut url = String::new();
+        while let Some(c) = self.advance() {
+            match c {
+                ')' => return CssToken::Url(url),
+                c if is_whitespace(c) => {
+                    while self.peek(0).is_some_and(is_whitespace) {
+                        self.position += 1;
+                    }
+                    if matches!(self.peek(0), Some(')') | None) {
+                        self.position += usize::from(self.peek(0).is_some());
+                        return CssToken::Url(url);
+                    }
+                    self.consume_bad_url();
+                    return CssToken::BadUrl;
+                }
+                '"' | '\'' | '(' => {
+                    self.consume_bad_url();
+                    return CssToken::BadUrl;
+                }
+                c if is_non_printable(c) => {
+                    self.consume_bad_url();
+                    return CssToken::BadUrl;
+                }
+                '\\' if self.starts_escape(-1) => url.push(self.consume_escape()),
+                '\\' => {
+                    self.consume_bad_url();
+                    return CssToken::BadUrl;
+                }
+                c => url.push(c),
+            }
+        }
+        CssToken::Url(url)
+    }
+
+    /// Skips the rest of a malformed URL, up to and including its `)`.
+    fn consume_bad_url(&mut self) {
+        while let Some(c) = self.advance() {
+            match c {
+                ')' => return,
+                '\\' if self.starts_escape(-1) => {
+                    self.consume_escape();
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Iterator for CssTokenizer {
+    type Item = CssToken;
+
+    fn next(&mut self) -> Option<CssToken> {
+        self.consume_token()
+    }
+}
+
+fn is_whitespace(c: char) -> bool {
+    matches!(c, ' ' | '\t' | '\n')
+}
+
+fn is_name_start_char(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_' || !c.is_ascii()
+}
+
+fn is_name_char(c: char) -> bool {
+    is_name_start_char(c) || c.is_ascii_digit() || c == '-'
+}
+
+fn is_non_printable(c: char) -> bool {
+    matches!(c, '\0'..='\u{8}' | '\u{b}' | '\u{e}'..='\u{1f}' | '\u{7f}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(css: &str) -> Vec<CssToken> {
+        CssTokenizer::new(css).collect()
+    }
+
+    fn number(value: f64) -> Number {
+        Number {
+            value,
+            integer: value.fract() == 0.0,
+        }
+    }
+
+    #[test]
+    fn test_rule() {
+        assert_eq!(
+            tokens("p.a > #b{color:red}/* note */"),
+            vec![
+                CssToken::Ident("p".to_owned()),
+                CssToken::Delim('.'),
+                CssToken::Ident("a".to_owned()),
+                CssToken::Whitespace,
+                CssToken::Delim('>'),
+                CssToken::Whitespace,
+                CssToken::Hash {
+                    value: "b".to_owned(),
+                    id: true,
+                },
+                CssToken::OpenCurly,
+                CssToken::Ident("color".to_owned()),
+                CssToken::Colon,
+                CssToken::Ident("red".to_owned()),
+                CssToken::CloseCurly,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_numbers_and_dimensions() {
+        assert_eq!(
+            tokens("10px -.5em 50% +3 1e3 #123"),
+            vec![
+                CssToken::Dimension {
+                    value: number(10.0),
+                    unit: "px".to_owned(),
+                },
+                CssToken::Whitespace,
+                CssToken::Dimension {
+                    value: Number {
+                        value: -0.5,
+                        integer: false,
+                    },
+                    unit: "em".to_owned(),
+                },
+                CssToken::Whitespace,
+                CssToken::Percentage(number(50.0)),
+                CssToken::Whitespace,
+                CssToken::Number(number(3.0)),
+                CssToken::Whitespace,
+                CssToken::Number(Number {
+                    value: 1000.0,
+                    integer: false,
+                }),
+                CssToken::Whitespace,
+                CssToken::Hash {
+                    value: "123".to_owned(),
+                    id: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_strings_urls_and_escapes() {
+        assert_eq!(
+            tokens("url( a.png ) url(\"b.png\") 'it\\'s' \"bad\n\\31 x @media -->"),
+            vec![
+                CssToken::Url("a.png".to_owned()),
+                CssToken::Whitespace,
+                CssToken::Function("url".to_owned()),
+                CssToken::String("b.png".to_owned()),
+                CssToken::CloseParen,
+                CssToken::Whitespace,
+                CssToken::String("it's".to_owned()),
+                CssToken::Whitespace,
+                CssToken::BadString,
+                CssToken::Whitespace,
+                CssToken::Ident("1x".to_owned()),
+                CssToken::Whitespace,
+                CssToken::AtKeyword("media".to_owned()),
+                CssToken::Whitespace,
+                CssToken::Cdc,
+            ]
+        );
+        assert_eq!(tokens("url(a b)"), vec![CssToken::BadUrl]);
+    }
+
+    #[test]
+    fn test_line_numbers() {
+        let mut tokenizer = CssTokenizer::new("a\r\n/* x\n */ b");
+        assert_eq!(tokenizer.line(), 1);
+        tokenizer.next();
+        tokenizer.next();
+        assert_eq!(tokenizer.line(), 2);
+        assert_eq!(tokenizer.next(), Some(CssToken::Whitespace));
+        assert_eq!(tokenizer.next(), Some(CssToken::Ident("b".to_owned())));
+        assert_eq!(tokenizer.line(), 3);
+    }
+}
@@ -0,0 +1,459 @@
+//! The document tree built from [`HtmlTokenizer`](crate::html_tokenizer::HtmlTokenizer)
+//! output by a [`TreeBuilder`].
+//!
+//! Nodes live in an arena owned by their [`Document`] and are referred to by
+//! [`NodeId`], a plain index into it, rather than through reference-counted
+//! pointers. Handles are cheap to copy, traversal stays within one `Vec`, and
+//! each node links to its parent and siblings, so it can be detached in
+//! constant time. A `NodeId` is only meaningful for the document it came from.
+
+mod attributes;
+mod diff;
+mod find;
+mod forms;
+mod images;
+mod index;
+mod json;
+mod links;
+mod lists;
+mod metadata;
+mod order;
+mod query;
+mod quirks;
+mod serialize;
+mod state;
+mod stylesheets;
+mod tables;
+mod text;
+mod traversal;
+mod tree_builder;
+mod url;
+
+pub use attributes::{ClassList, ClassListMut, Dataset};
+pub use diff::{Change, diff};
+pub use find::TextMatch;
+pub use forms::{ControlKind, FormControl, FormMethod, FormModel, SelectOption};
+pub use json::JsonNode;
+pub use links::{Link, LinkKind};
+pub use metadata::{Icon, PageMetadata};
+pub use order::DocumentPosition;
+pub use quirks::CompatMode;
+pub use stylesheets::{DocumentStylesheet, ImportedStylesheet};
+pub use tables::{TableCell, TableModel};
+pub use traversal::{Children, Visit, Visitor};
+pub use tree_builder::TreeBuilder;
+pub use url::resolve;
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::html_tokenizer::{HtmlTokenizer, TokenizerOptions};
+use index::ElementIndex;
+use order::TreePosition;
+use state::ElementStates;
+
+/// A handle to a node of a [`Document`]. It stays valid for as long as the
+/// document does, even if the node is detached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(u32);
+
+impl NodeId {
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// The namespace of an element. Elements inside `<svg>` and `<math>` are
+/// foreign; everything else is HTML.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, Default, serde::Deserialize, serde::Serialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Namespace {
+    #[default]
+    Html,
+    Svg,
+    MathMl,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Element {
+    pub name: String,
+    pub namespace: Namespace,
+    pub attributes: Vec<(String, String)>,
+}
+
+impl Element {
+    pub fn new(name: &str, attributes: Vec<(String, String)>) -> Self {
+        Self {
+            name: name.to_owned(),
+            namespace: Namespace::Html,
+            attributes,
+        }
+    }
+
+    pub fn get_attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(attribute, _)| attribute.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Whether this is the HTML element `name`.
+    pub fn is(&self, name: &str) -> bool {
+        self.namespace == Namespace::Html && self.name == name
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node {
+    /// The root of a [`Document`].
+    Document,
+
+    /// The content of a `<template>`, which is kept out of the document
+    /// tree; see [`Document::template_content`].
+    DocumentFragment,
+    Doctype(String),
+    Element(Element),
+    Text(String),
+    Comment(String),
+}
+
+impl Node {
+    pub fn as_element(&self) -> Option<&Element> {
+        match self {
+            Self::Element(element) => Some(element),
+            _ => None,
+        }
+    }
+
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            Self::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+}
+
+/// A node with its links to the nodes around it.
+#[derive(Debug, Clone)]
+struct NodeData {
+    node: Node,
+    parent: Option<NodeId>,
+    first_child: Option<NodeId>,
+    last_child: Option<NodeId>,
+    previous_sibling: Option<NodeId>,
+    next_sibling: Option<NodeId>,
+}
+
+impl NodeData {
+    fn new(node: Node) -> Self {
+        Self {
+            node,
+            parent: None,
+            first_child: None,
+            last_child: None,
+            previous_sibling: None,
+            next_sibling: None,
+        }
+    }
+}
+
+/// A parsed HTML document.
+#[derive(Debug, Clone)]
+pub struct Document {
+    /// Every node ever created, indexed by [`NodeId`]. Nodes are never
+    /// removed, only detached.
+    nodes: Vec<NodeData>,
+    index: ElementIndex,
+
+    /// The content fragment of each `<template>` element.
+    template_contents: HashMap<NodeId, NodeId>,
+
+    /// Where the document was loaded from; see [`Document::url`].
+    url: Option<String>,
+
+    /// The `href` of the first `<base>` element that has one, recorded as
+    /// it is parsed.
+    base_href: Option<String>,
+    compat_mode: CompatMode,
+
+    /// The sheets of the `<style>` elements, added as each is parsed.
+    stylesheets: Vec<DocumentStylesheet>,
+
+    /// The size of each `<img>` element's image, once decoded.
+    natural_sizes: HashMap<NodeId, (u32, u32)>,
+
+    /// Which elements are hovered, focused and so on.
+    states: ElementStates,
+
+    /// Each node's place in a preorder numbering of its tree, computed on
+    /// first use and dropped whenever the tree changes.
+    tree_order: OnceLock<Vec<TreePosition>>,
+}
+
+impl Default for Document {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Document {
+    /// An empty document, holding only its root node.
+    pub fn new() -> Self {
+        Self {
+            nodes: vec![NodeData::new(Node::Document)],
+            index: ElementIndex::default(),
+            template_contents: HashMap::new(),
+            url: None,
+            base_href: None,
+            compat_mode: CompatMode::NoQuirks,
+            stylesheets: Vec::new(),
+            natural_sizes: HashMap::new(),
+            states: ElementStates::default(),
+            tree_order: OnceLock::new(),
+        }
+    }
+
+    /// Parses `html` with the spec-conformant tokenizer, which accepts any
+    /// input, so this never fails.
+    pub fn parse(html: &str) -> Self {
+        let tokenizer =
+            HtmlTokenizer::new(html).with_options(TokenizerOptions::new().spec_conformance(true));
+        let mut builder = TreeBuilder::new();
+        for token in tokenizer.iter().flatten() {
+            builder.process_token(token);
+        }
+        builder.finish()
+    }
+
+    /// The [`Node::Document`] node at the root of the tree.
+    pub fn root(&self) -> NodeId {
+        NodeId(0)
+    }
+
+    pub fn node(&self, id: NodeId) -> &Node {
+        &self.entry(id).node
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.entry(id).parent
+    }
+
+    pub fn element(&self, id: NodeId) -> Option<&Element> {
+        self.node(id).as_element()
+    }
+
+    /// The `<html>` element.
+    pub fn document_element(&self) -> Option<NodeId> {
+        self.children(self.root())
+            .find(|&child| self.element(child).is_some())
+    }
+
+    pub fn head(&self) -> Option<NodeId> {
+        self.child_element(self.document_element()?, "head")
+    }
+
+    pub fn body(&self) -> Option<NodeId> {
+        self.child_element(self.document_element()?, "body")
+    }
+
+    /// The first child of `parent` that is the HTML element `name`.
+    pub fn child_element(&self, parent: NodeId, name: &str) -> Option<NodeId> {
+        self.children(parent)
+            .find(|&child| self.element(child).is_some_and(|element| element.is(name)))
+    }
+
+    /// The [`Node::DocumentFragment`] holding the content of the
+    /// `<template>` element `id`. Its nodes are not part of the document
+    /// tree, so they are not found by queries or lookups by id.
+    pub fn template_content(&self, id: NodeId) -> Option<NodeId> {
+        self.template_contents.get(&id).copied()
+    }
+
+    /// Whether the document is in quirks mode, as its doctype decided.
+    /// Documents that were not parsed are in no-quirks mode.
+    pub fn compat_mode(&self) -> CompatMode {
+        self.compat_mode
+    }
+
+    /// Removes `id`, with its descendants, from its parent. The nodes stay
+    /// in the document, so their handles remain valid, but they are no
+    /// longer part of the tree. Does nothing for a node without a parent.
+    pub fn detach(&mut self, id: NodeId) {
+        let Some(parent) = self.parent(id) else {
+            return;
+        };
+        self.tree_order.take();
+        let data = self.entry_mut(id);
+        let previous = data.previous_sibling.take();
+        let next = data.next_sibling.take();
+        data.parent = None;
+
+        match previous {
+            Some(previous) => self.entry_mut(previous).next_sibling = next,
+            None => self.entry_mut(parent).first_child = next,
+        }
+        match next {
+            Some(next) => self.entry_mut(next).previous_sibling = previous,
+            None => self.entry_mut(parent).last_child = previous,
+        }
+    }
+
+    /// Whether `id` is in the document tree, rather than detached or in a
+    /// template's content.
+    pub fn is_connected(&self, id: NodeId) -> bool {
+        std::iter::once(id)
+            .chain(self.ancestors(id))
+            .last()
+            .is_some_and(|top| top == self.root())
+    }
+
+    /// Creates the content fragment of the `<template>` element `id`.
+    fn create_template_content(&mut self, id: NodeId) -> NodeId {
+        let content = self.create_node(Node::DocumentFragment);
+        self.template_contents.insert(id, content);
+        content
+    }
+
+    #[expect(
+        clippy::indexing_slicing,
+        reason = "a NodeId is only handed out by the document it indexes, which never removes entries"
+    )]
+    fn entry(&self, id: NodeId) -> &NodeData {
+        &self.nodes[id.index()]
+    }
+
+    #[expect(
+        clippy::indexing_slicing,
+        reason = "a NodeId is only handed out by the document it indexes, which never removes entries"
+    )]
+    fn entry_mut(&mut self, id: NodeId) -> &mut NodeData {
+        &mut self.nodes[id.index()]
+    }
+
+    fn node_mut(&mut self, id: NodeId) -> &mut Node {
+        &mut self.entry_mut(id).node
+    }
+
+    /// Adds `node` to the arena without a parent.
+    fn create_node(&mut self, node: Node) -> NodeId {
+        self.tree_order.take();
+        let id = u32::try_from(self.nodes.len()).expect("a document has fewer than 2^32 nodes");
+        self.nodes.push(NodeData::new(node));
+        NodeId(id)
+    }
+
+    /// Adds `node` as the last child of `parent`.
+    fn append(&mut self, parent: NodeId, node: Node) -> NodeId {
+        let connected = self.is_connected(parent);
+        let id = self.create_node(node);
+        if connected
+            && let Some(Node::Element(element)) = self.nodes.get(id.index()).map(|data| &data.node)
+        {
+            self.index.insert(id, element);
+        }
+
+        let previous = self.entry(parent).last_child;
+        let data = self.entry_mut(id);
+        data.parent = Some(parent);
+        data.previous_sibling = previous;
+        match previous {
+            Some(previous) => self.entry_mut(previous).next_sibling = Some(id),
+            None => self.entry_mut(parent).first_child = Some(id),
+        }
+        self.entry_mut(parent).last_child = Some(id);
+        id
+    }
+
+    /// Adds an attribute to the element `id` unless it already has one
+    /// named `name`.
+    fn add_attribute(&mut self, id: NodeId, name: String, value: String) {
+        if self
+            .element(id)
+            .is_none_or(|element| element.get_attribute(&name).is_some())
+        {
+            return;
+        }
+        self.index.insert_attribute(id, &name, &value);
+        if let Node::Element(element) = self.node_mut(id) {
+            element.attributes.push((name, value));
+        }
+    }
+
+    /// Appends `text` to `parent`, extending its last child if that is
+    /// already text, so that adjacent text is a single node.
+    fn append_text(&mut self, parent: NodeId, text: &str) {
+        if let Some(last) = self.entry(parent).last_child
+            && let Node::Text(existing) = self.node_mut(last)
+        {
+            existing.push_str(text);
+            return;
+        }
+        self.append(parent, Node::Text(text.to_owned()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_builds_html_head_and_body() {
+        let document = Document::parse("<!DOCTYPE html><title>T</title><p class=x>Hi</p>");
+
+        let root_children: Vec<_> = document.children(document.root()).collect();
+        assert_eq!(root_children.len(), 2);
+        assert_eq!(
+            document.node(root_children[0]),
+            &Node::Doctype("html".to_string())
+        );
+
+        let head = document.head().unwrap();
+        let title = document.child_element(head, "title").unwrap();
+        assert_eq!(
+            document
+                .node(document.children(title).next().unwrap())
+                .as_text(),
+            Some("T")
+        );
+
+        let body = document.body().unwrap();
+        let p = document.child_element(body, "p").unwrap();
+        assert_eq!(document.parent(p), Some(body));
+        assert_eq!(
+            document.element(p).unwrap().get_attribute("class"),
+            Some("x")
+        );
+    }
+
+    #[test]
+    fn test_detach_unlinks_a_node_from_its_siblings() {
+        let mut document = Document::parse("<p id=a>1</p><p id=b>2</p><p id=c>3</p>");
+        let body = document.body().unwrap();
+        let [a, b, c] = ["a", "b", "c"].map(|id| document.get_element_by_id(id).unwrap());
+
+        document.detach(b);
+        assert_eq!(document.children(body).collect::<Vec<_>>(), vec![a, c]);
+        assert_eq!(document.next_sibling(a), Some(c));
+        assert_eq!(document.previous_sibling(c), Some(a));
+        assert_eq!(document.parent(b), None);
+        assert!(!document.is_connected(b));
+        assert_eq!(document.get_element_by_id("b"), None);
+        assert_eq!(document.text_content(b), "2");
+
+        document.detach(a);
+        document.detach(c);
+        assert_eq!(document.children(body).next(), None);
+        document.detach(c);
+        document.detach(document.root());
+        assert!(document.is_connected(body));
+    }
+
+    #[test]
+    fn test_empty_document_still_has_html_head_and_body() {
+        let document = Document::parse("");
+        assert!(document.head().is_some());
+        assert!(document.body().is_some());
+    }
+}
@@ -0,0 +1,269 @@
+use super::{Document, Element, Node, NodeId};
+
+/// The classes in an element's `class` attribute, without duplicates; see
+/// [`Element::class_list`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassList<'e>(Vec<&'e str>);
+
+impl<'e> ClassList<'e> {
+    fn parse(classes: &'e str) -> Self {
+        let mut list: Vec<&str> = Vec::new();
+        for class in classes.split_ascii_whitespace() {
+            if !list.contains(&class) {
+                list.push(class);
+            }
+        }
+        Self(list)
+    }
+
+    pub fn contains(&self, class: &str) -> bool {
+        self.0.contains(&class)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &'e str> + '_ {
+        self.0.iter().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Changes the classes of an element; see [`Document::class_list_mut`].
+///
+/// Each change rewrites the `class` attribute with the classes separated
+/// by single spaces and duplicates removed, as the DOM's `classList` does.
+#[derive(Debug)]
+pub struct ClassListMut<'d> {
+    document: &'d mut Document,
+    element: NodeId,
+}
+
+impl ClassListMut<'_> {
+    pub fn contains(&self, class: &str) -> bool {
+        self.document
+            .element(self.element)
+            .is_some_and(|element| element.class_list().contains(class))
+    }
+
+    /// Adds `class` unless the element already has it.
+    pub fn add(&mut self, class: &str) {
+        let mut classes = self.classes();
+        if !classes.iter().any(|existing| existing == class) {
+            classes.push(class.to_owned());
+        }
+        self.set(&classes);
+    }
+
+    pub fn remove(&mut self, class: &str) {
+        let mut classes = self.classes();
+        classes.retain(|existing| existing != class);
+        self.set(&classes);
+    }
+
+    /// Removes `class` if the element has it and adds it if not. Returns
+    /// whether the element has it now.
+    pub fn toggle(&mut self, class: &str) -> bool {
+        let has_class = self.contains(class);
+        if has_class {
+            self.remove(class);
+        } else {
+            self.add(class);
+        }
+        !has_class
+    }
+
+    fn classes(&self) -> Vec<String> {
+        self.document
+            .element(self.element)
+            .map(|element| element.class_list().iter().map(str::to_owned).collect())
+            .unwrap_or_default()
+    }
+
+    fn set(&mut self, classes: &[String]) {
+        self.document
+            .set_attribute(self.element, "class", &classes.join(" "));
+    }
+}
+
+/// The `data-*` attributes of an element, by the camel-cased names the
+/// DOM's `dataset` gives them: `data-user-id` is `userId`. See
+/// [`Element::dataset`].
+#[derive(Debug, Clone, Copy)]
+pub struct Dataset<'e>(&'e Element);
+
+impl<'e> Dataset<'e> {
+    /// The value of the `data-*` attribute for the camel-cased `key`.
+    pub fn get(&self, key: &str) -> Option<&'e str> {
+        self.iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value)
+    }
+
+    /// Every `data-*` attribute as a key and value, in attribute order.
+    pub fn iter(&self) -> impl Iterator<Item = (String, &'e str)> + 'e {
+        self.0.attributes.iter().filter_map(|(name, value)| {
+            let name = name.strip_prefix("data-")?;
+            Some((dataset_key(name), value.as_str()))
+        })
+    }
+
+    /// The name of the attribute holding `key`, such as `data-user-id` for
+    /// `userId`, for use with [`Document::set_attribute`].
+    pub fn attribute_name(key: &str) -> String {
+        let mut name = String::from("data-");
+        for c in key.chars() {
+            if c.is_ascii_uppercase() {
+                name.push('-');
+                name.push(c.to_ascii_lowercase());
+            } else {
+                name.push(c);
+            }
+        }
+        name
+    }
+}
+
+/// Camel-cases the part of a `data-*` attribute name after `data-`: a dash
+/// followed by a lowercase letter becomes the uppercase letter.
+fn dataset_key(name: &str) -> String {
+    let mut key = String::with_capacity(name.len());
+    let mut chars = name.chars().peekable();
+    while let Some(c) = chars.next() {
+        match chars.peek() {
+            Some(next) if c == '-' && next.is_ascii_lowercase() => {
+                key.push(next.to_ascii_uppercase());
+                chars.next();
+            }
+            _ => key.push(c),
+        }
+    }
+    key
+}
+
+impl Element {
+    /// The classes in the `class` attribute, in order.
+    pub fn class_list(&self) -> ClassList<'_> {
+        ClassList::parse(self.get_attribute("class").unwrap_or_default())
+    }
+
+    /// The `data-*` attributes, keyed as the DOM's `dataset` keys them.
+    pub fn dataset(&self) -> Dataset<'_> {
+        Dataset(self)
+    }
+}
+
+impl Document {
+    /// A view of the classes of the element `id` that can change them, or
+    /// `None` if `id` is not an element.
+    pub fn class_list_mut(&mut self, id: NodeId) -> Option<ClassListMut<'_>> {
+        self.element(id)?;
+        Some(ClassListMut {
+            document: self,
+            element: id,
+        })
+    }
+
+    /// Sets the attribute `name` of the element `id` to `value`, adding it
+    /// if the element does not have it yet. Does nothing if `id` is not an
+    /// element.
+    pub fn set_attribute(&mut self, id: NodeId, name: &str, value: &str) {
+        let Some(element) = self.element(id) else {
+            return;
+        };
+        match element.get_attribute(name).map(str::to_owned) {
+            Some(old) => {
+                self.index.remove_attribute(id, name, &old);
+                self.index.insert_attribute(id, name, value);
+                if let Node::Element(element) = self.node_mut(id)
+                    && let Some((_, existing)) = element
+                        .attributes
+                        .iter_mut()
+                        .find(|(attribute, _)| attribute.eq_ignore_ascii_case(name))
+                {
+                    value.clone_into(existing);
+                }
+            }
+            None => self.add_attribute(id, name.to_ascii_lowercase(), value.to_owned()),
+        }
+    }
+
+    /// Removes the attribute `name` from the element `id`, if it has it.
+    pub fn remove_attribute(&mut self, id: NodeId, name: &str) {
+        let Some(old) = self
+            .element(id)
+            .and_then(|element| element.get_attribute(name))
+            .map(str::to_owned)
+        else {
+            return;
+        };
+        self.index.remove_attribute(id, name, &old);
+        if let Node::Element(element) = self.node_mut(id) {
+            element
+                .attributes
+                .retain(|(attribute, _)| !attribute.eq_ignore_ascii_case(name));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_class_list() {
+        let mut document = Document::parse("<p id=p class=' a b  a'>x</p>");
+        let p = document.get_element_by_id("p").unwrap();
+        let classes = document.element(p).unwrap().class_list();
+        assert_eq!(classes.iter().collect::<Vec<_>>(), vec!["a", "b"]);
+        assert!(classes.contains("b"));
+        assert!(!classes.contains("c"));
+
+        let mut classes = document.class_list_mut(p).unwrap();
+        classes.add("c");
+        classes.remove("a");
+        assert!(!classes.toggle("b"));
+        assert!(classes.toggle("d"));
+        assert_eq!(
+            document.element(p).unwrap().get_attribute("class"),
+            Some("c d")
+        );
+        assert_eq!(document.get_elements_by_class_name("d"), vec![p]);
+        assert!(document.get_elements_by_class_name("a").is_empty());
+    }
+
+    #[test]
+    fn test_dataset() {
+        let mut document = Document::parse("<p id=p data-user-id=7 data-x=y title=t>");
+        let p = document.get_element_by_id("p").unwrap();
+        let dataset = document.element(p).unwrap().dataset();
+        assert_eq!(dataset.get("userId"), Some("7"));
+        assert_eq!(dataset.get("title"), None);
+        assert_eq!(
+            dataset.iter().collect::<Vec<_>>(),
+            vec![("userId".to_owned(), "7"), ("x".to_owned(), "y")]
+        );
+
+        document.set_attribute(p, &Dataset::attribute_name("userName"), "mochi");
+        let element = document.element(p).unwrap();
+        assert_eq!(element.get_attribute("data-user-name"), Some("mochi"));
+        assert_eq!(element.dataset().get("userName"), Some("mochi"));
+    }
+
+    #[test]
+    fn test_set_and_remove_attribute_keep_lookups_current() {
+        let mut document = Document::parse("<p id=a>1</p><p>2</p>");
+        let a = document.get_element_by_id("a").unwrap();
+        document.set_attribute(a, "ID", "renamed");
+        assert_eq!(document.get_element_by_id("a"), None);
+        assert_eq!(document.get_element_by_id("renamed"), Some(a));
+
+        document.remove_attribute(a, "id");
+        assert_eq!(document.get_element_by_id("renamed"), None);
+        assert_eq!(document.element(a).unwrap().attributes, vec![]);
+    }
+}
@@ -0,0 +1,255 @@
+use super::{Document, Element, Node, NodeId};
+
+/// One difference between two documents; see [`diff`].
+///
+/// Nodes that are only in the old document are referred to by their id
+/// there, nodes only in the new one by their id there, and nodes in both
+/// by both.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// The new document's `node`, with its descendants, was added as the
+    /// `index`th child of `parent`.
+    Insert {
+        parent: NodeId,
+        index: usize,
+        node: NodeId,
+    },
+
+    /// The old document's `node` was removed, with its descendants.
+    Remove { node: NodeId },
+
+    /// The attribute `name` of an element was set to `value`, or removed if
+    /// that is `None`.
+    SetAttribute {
+        old: NodeId,
+        new: NodeId,
+        name: String,
+        value: Option<String>,
+    },
+
+    /// The text of a text or comment node changed.
+    SetText {
+        old: NodeId,
+        new: NodeId,
+        text: String,
+    },
+}
+
+/// The changes that turn `old` into `new`. The changes to a node's
+/// children come before those to their descendants.
+///
+/// Children are matched up by the longest common subsequence of their
+/// kinds: the same element name and `id`, or both text, or both comments.
+/// Matched nodes are compared in turn, so an edited paragraph shows up as a
+/// [`Change::SetText`] instead of a removal and an insertion. Unmatched
+/// nodes are removed or inserted with all their descendants.
+pub fn diff(old: &Document, new: &Document) -> Vec<Change> {
+    let mut changes = Vec::new();
+    // Without recursion, so that deeply nested markup cannot overflow the
+    // stack. Pairs are pushed in reverse to be compared in document order.
+    let mut pairs = vec![(old.root(), new.root())];
+    while let Some((old_id, new_id)) = pairs.pop() {
+        match (old.node(old_id), new.node(new_id)) {
+            (Node::Element(old_element), Node::Element(new_element)) => {
+                diff_attributes(old_id, old_element, new_id, new_element, &mut changes);
+            }
+            (Node::Text(old_text), Node::Text(new_text))
+            | (Node::Comment(old_text), Node::Comment(new_text))
+                if old_text != new_text =>
+            {
+                changes.push(Change::SetText {
+                    old: old_id,
+                    new: new_id,
+                    text: new_text.clone(),
+                });
+            }
+            _ => {}
+        }
+
+        let old_children: Vec<NodeId> = old.children(old_id).collect();
+        let new_children: Vec<NodeId> = new.children(new_id).collect();
+        let matched = match_children(old, &old_children, new, &new_children);
+
+        let mut matched_pairs = Vec::new();
+        let (mut old_index, mut new_index) = (0, 0);
+        // A final pair past the end of both lists flushes what is left.
+        let end = (old_children.len(), new_children.len());
+        for &(old_match, new_match) in matched.iter().chain([&end]) {
+            for &node in old_children.get(old_index..old_match).unwrap_or_default() {
+                changes.push(Change::Remove { node });
+            }
+            for (index, &node) in new_children
+                .iter()
+                .enumerate()
+                .take(new_match)
+                .skip(new_index)
+            {
+                changes.push(Change::Insert {
+                    parent: new_id,
+                    index,
+                    node,
+                });
+            }
+            if let (Some(&old_child), Some(&new_child)) =
+                (old_children.get(old_match), new_children.get(new_match))
+            {
+                matched_pairs.push((old_child, new_child));
+            }
+            (old_index, new_index) = (old_match + 1, new_match + 1);
+        }
+        pairs.extend(matched_pairs.into_iter().rev());
+    }
+    changes
+}
+
+fn diff_attributes(
+    old_id: NodeId,
+    old: &Element,
+    new_id: NodeId,
+    new: &Element,
+    changes: &mut Vec<Change>,
+) {
+    for (name, value) in &new.attributes {
+        if old.get_attribute(name) != Some(value.as_str()) {
+            changes.push(Change::SetAttribute {
+                old: old_id,
+                new: new_id,
+                name: name.clone(),
+                value: Some(value.clone()),
+            });
+        }
+    }
+    for (name, _) in &old.attributes {
+        if new.get_attribute(name).is_none() {
+            changes.push(Change::SetAttribute {
+                old: old_id,
+                new: new_id,
+                name: name.clone(),
+                value: None,
+            });
+        }
+    }
+}
+
+/// Whether `new` can be treated as an edited version of `old`.
+fn same_kind(old: &Node, new: &Node) -> bool {
+    match (old, new) {
+        (Node::Element(old), Node::Element(new)) => {
+            old.name == new.name
+                && old.namespace == new.namespace
+                && old.get_attribute("id") == new.get_attribute("id")
+        }
+        (Node::Doctype(old), Node::Doctype(new)) => old == new,
+        (Node::Document, Node::Document)
+        | (Node::DocumentFragment, Node::DocumentFragment)
+        | (Node::Text(_), Node::Text(_))
+        | (Node::Comment(_), Node::Comment(_)) => true,
+        _ => false,
+    }
+}
+
+/// The index pairs of the longest common subsequence of `old` and `new`
+/// under [`same_kind`], in order.
+fn match_children(
+    old: &Document,
+    old_children: &[NodeId],
+    new: &Document,
+    new_children: &[NodeId],
+) -> Vec<(usize, usize)> {
+    let same = |i: usize, j: usize| match (old_children.get(i), new_children.get(j)) {
+        (Some(&old_child), Some(&new_child)) => same_kind(old.node(old_child), new.node(new_child)),
+        _ => false,
+    };
+    let (rows, columns) = (old_children.len(), new_children.len());
+
+    // lengths[i][j] is the length of the longest common subsequence of
+    // old_children[i..] and new_children[j..].
+    let mut lengths = vec![vec![0_usize; columns + 1]; rows + 1];
+    let length = |lengths: &[Vec<usize>], i: usize, j: usize| {
+        lengths
+            .get(i)
+            .and_then(|row| row.get(j))
+            .copied()
+            .unwrap_or(0)
+    };
+    for i in (0..rows).rev() {
+        for j in (0..columns).rev() {
+            let value = if same(i, j) {
+                length(&lengths, i + 1, j + 1) + 1
+            } else {
+                length(&lengths, i + 1, j).max(length(&lengths, i, j + 1))
+            };
+            if let Some(slot) = lengths.get_mut(i).and_then(|row| row.get_mut(j)) {
+                *slot = value;
+            }
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < rows && j < columns {
+        if same(i, j) {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if length(&lengths, i + 1, j) >= length(&lengths, i, j + 1) {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identical_documents_have_no_changes() {
+        let html = "<!DOCTYPE html><title>T</title><p class=a>Hi<!--c--></p>";
+        assert_eq!(diff(&Document::parse(html), &Document::parse(html)), vec![]);
+    }
+
+    #[test]
+    fn test_diff_reports_edits_insertions_and_removals() {
+        let old = Document::parse("<p id=a class=x>one</p><p id=b>two</p><div>gone</div>");
+        let new = Document::parse("<p id=a title=t>uno</p><ul><li>new</ul><p id=b>two</p>");
+        let a = (
+            old.get_element_by_id("a").unwrap(),
+            new.get_element_by_id("a").unwrap(),
+        );
+        let body = new.body().unwrap();
+        let ul = new.child_element(body, "ul").unwrap();
+        let div = old.child_element(old.body().unwrap(), "div").unwrap();
+
+        assert_eq!(
+            diff(&old, &new),
+            vec![
+                Change::Insert {
+                    parent: body,
+                    index: 1,
+                    node: ul,
+                },
+                Change::Remove { node: div },
+                Change::SetAttribute {
+                    old: a.0,
+                    new: a.1,
+                    name: "title".to_owned(),
+                    value: Some("t".to_owned()),
+                },
+                Change::SetAttribute {
+                    old: a.0,
+                    new: a.1,
+                    name: "class".to_owned(),
+                    value: None,
+                },
+                Change::SetText {
+                    old: old.first_child(a.0).unwrap(),
+                    new: new.first_child(a.1).unwrap(),
+                    text: "uno".to_owned(),
+                },
+            ]
+        );
+    }
+}
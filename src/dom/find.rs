@@ -0,0 +1,111 @@
+use std::ops::Range;
+
+use super::text::HIDDEN_ELEMENTS;
+use super::{Document, Node, NodeId};
+
+/// A match of find in page: a range of the text of a text node, in bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextMatch {
+    pub node: NodeId,
+    pub range: Range<usize>,
+}
+
+impl Document {
+    /// The matches of `query` in the text of the document, in document
+    /// order, the way find in page looks for them: case is ignored, and
+    /// white space in `query` matches any run of white space. Hidden
+    /// content such as `<script>` and `<head>` is left out, and a match
+    /// does not reach from one text node into the next.
+    pub fn find_text(&self, query: &str) -> Vec<TextMatch> {
+        let query: Vec<char> = query
+            .split_ascii_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .chars()
+            .collect();
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let is_hidden = |id: NodeId| {
+            self.element(id)
+                .is_some_and(|element| HIDDEN_ELEMENTS.contains(&element.name.as_str()))
+        };
+        let mut matches = Vec::new();
+        for id in self.descendants(self.root()) {
+            let Node::Text(text) = self.node(id) else {
+                continue;
+            };
+            if self.ancestors(id).any(is_hidden) {
+                continue;
+            }
+            let mut end = 0;
+            for (start, c) in text.char_indices() {
+                if start < end || c.is_ascii_whitespace() {
+                    continue;
+                }
+                if let Some(match_end) = match_at(text, start, &query) {
+                    matches.push(TextMatch {
+                        node: id,
+                        range: start..match_end,
+                    });
+                    end = match_end;
+                }
+            }
+        }
+        matches
+    }
+}
+
+/// Where the match of `query` that starts at the byte `start` of `text`
+/// ends, if there is one there.
+fn match_at(text: &str, start: usize, query: &[char]) -> Option<usize> {
+    let mut chars = text
+        .get(start..)?
+        .char_indices()
+        .map(|(index, c)| (start + index, c))
+        .peekable();
+    for &wanted in query {
+        if wanted == ' ' {
+            chars.next_if(|&(_, c)| c.is_ascii_whitespace())?;
+            while chars.next_if(|&(_, c)| c.is_ascii_whitespace()).is_some() {}
+        } else {
+            let (_, c) = chars.next()?;
+            if c != wanted && !c.to_lowercase().eq(wanted.to_lowercase()) {
+                return None;
+            }
+        }
+    }
+    Some(chars.peek().map_or(text.len(), |&(index, _)| index))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_text() {
+        let document = Document::parse(
+            "<title>foo</title><p id=p>Foo\n  bar, <b>FOO</b> bar</p>\
+             <script>foo()</script><p>Ünïcode ÜNÏCODE</p>",
+        );
+        let p = document.get_element_by_id("p").unwrap();
+        let mut children = document.children(p);
+        let (first, bold) = (children.next().unwrap(), children.next().unwrap());
+        let bold = document.children(bold).next().unwrap();
+        assert_eq!(
+            document.find_text(" foo  BAR"),
+            [TextMatch {
+                node: first,
+                range: 0..9,
+            }]
+        );
+        let foo: Vec<(NodeId, Range<usize>)> = document
+            .find_text("foo")
+            .into_iter()
+            .map(|found| (found.node, found.range))
+            .collect();
+        assert_eq!(foo, [(first, 0..3), (bold, 0..3)]);
+        assert_eq!(document.find_text("ünïcode").len(), 2);
+        assert!(document.find_text("  ").is_empty());
+    }
+}
@@ -0,0 +1,306 @@
+use super::tree_builder::is_html_whitespace;
+use super::{Document, Element, NodeId};
+
+/// How a form is submitted, from its `method` attribute.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FormMethod {
+    #[default]
+    Get,
+    Post,
+
+    /// Closes the `<dialog>` the form is in instead of submitting it.
+    Dialog,
+}
+
+/// Which element a [`FormControl`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlKind {
+    Input,
+    Button,
+    Select,
+    TextArea,
+}
+
+/// An `<option>` of a `<select>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectOption {
+    /// The `value` attribute, or else the option's text.
+    pub value: String,
+
+    /// The option's text, with whitespace collapsed.
+    pub label: String,
+    pub selected: bool,
+    pub disabled: bool,
+}
+
+/// An `<input>`, `<button>`, `<select>` or `<textarea>` with the state it
+/// starts in, before the user changes anything.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormControl {
+    pub element: NodeId,
+    pub kind: ControlKind,
+
+    /// The lowercase `type` of an `<input>` or `<button>`, defaulting to
+    /// `text` and `submit`. Empty for the other kinds.
+    pub control_type: String,
+
+    /// The `name` the value is submitted under. Controls without one are
+    /// not submitted.
+    pub name: Option<String>,
+
+    /// The default value: the `value` attribute, the text of a
+    /// `<textarea>`, or the value of the selected option of a `<select>`.
+    pub value: String,
+
+    /// Whether a checkbox or radio button starts checked.
+    pub checked: bool,
+
+    /// Whether the control, or a `<fieldset>` it is in, is disabled.
+    pub disabled: bool,
+
+    /// The options of a `<select>`, in document order.
+    pub options: Vec<SelectOption>,
+}
+
+/// A `<form>` and the controls that belong to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormModel {
+    pub element: NodeId,
+
+    /// Where the form is submitted: its `action` resolved against the
+    /// document's base URL, which an empty or missing `action` stands for.
+    /// `None` if that cannot be resolved.
+    pub action: Option<String>,
+    pub method: FormMethod,
+
+    /// The controls inside the form, and those elsewhere whose `form`
+    /// attribute names it, in document order.
+    pub controls: Vec<FormControl>,
+}
+
+impl FormMethod {
+    fn parse(method: Option<&str>) -> Self {
+        match method.map(str::to_ascii_lowercase).as_deref() {
+            Some("post") => Self::Post,
+            Some("dialog") => Self::Dialog,
+            _ => Self::Get,
+        }
+    }
+}
+
+impl ControlKind {
+    fn of(element: &Element) -> Option<Self> {
+        if element.is("input") {
+            Some(Self::Input)
+        } else if element.is("button") {
+            Some(Self::Button)
+        } else if element.is("select") {
+            Some(Self::Select)
+        } else if element.is("textarea") {
+            Some(Self::TextArea)
+        } else {
+            None
+        }
+    }
+}
+
+impl FormControl {
+    fn new(document: &Document, id: NodeId, element: &Element, kind: ControlKind) -> Self {
+        let attribute = |name| element.get_attribute(name).map(str::to_owned);
+        let control_type = match kind {
+            ControlKind::Input => attribute("type").map_or_else(
+                || "text".to_owned(),
+                |control_type| control_type.to_ascii_lowercase(),
+            ),
+            ControlKind::Button => attribute("type").map_or_else(
+                || "submit".to_owned(),
+                |control_type| control_type.to_ascii_lowercase(),
+            ),
+            ControlKind::Select | ControlKind::TextArea => String::new(),
+        };
+        let options = if kind == ControlKind::Select {
+            select_options(document, id, element.get_attribute("multiple").is_some())
+        } else {
+            Vec::new()
+        };
+        let value = match kind {
+            ControlKind::Input if matches!(control_type.as_str(), "checkbox" | "radio") => {
+                attribute("value").unwrap_or_else(|| "on".to_owned())
+            }
+            ControlKind::Input | ControlKind::Button => attribute("value").unwrap_or_default(),
+            ControlKind::Select => options
+                .iter()
+                .find(|option| option.selected)
+                .map(|option| option.value.clone())
+                .unwrap_or_default(),
+            ControlKind::TextArea => {
+                let text = document.text_content(id);
+                // A newline right after the start tag is not part of the value.
+                text.strip_prefix('\n').map(str::to_owned).unwrap_or(text)
+            }
+        };
+        let disabled = element.get_attribute("disabled").is_some()
+            || document.ancestors(id).any(|ancestor| {
+                document.element(ancestor).is_some_and(|ancestor| {
+                    ancestor.is("fieldset") && ancestor.get_attribute("disabled").is_some()
+                })
+            });
+
+        Self {
+            element: id,
+            kind,
+            checked: kind == ControlKind::Input && element.get_attribute("checked").is_some(),
+            control_type,
+            name: attribute("name").filter(|name| !name.is_empty()),
+            value,
+            disabled,
+            options,
+        }
+    }
+}
+
+/// The options of the `<select>` element `id`. Unless it is `multiple`,
+/// only one can be selected: the last one marked `selected`, or else the
+/// first that is not disabled.
+fn select_options(document: &Document, id: NodeId, multiple: bool) -> Vec<SelectOption> {
+    let mut options: Vec<SelectOption> = document
+        .descendants(id)
+        .filter(|&option| document.element(option).is_some_and(|e| e.is("option")))
+        .filter_map(|option| {
+            let element = document.element(option)?;
+            let label = collapse_whitespace(&document.text_content(option));
+            Some(SelectOption {
+                value: element
+                    .get_attribute("value")
+                    .map_or_else(|| label.clone(), str::to_owned),
+                label,
+                selected: element.get_attribute("selected").is_some(),
+                disabled: element.get_attribute("disabled").is_some(),
+            })
+        })
+        .collect();
+    if !multiple {
+        let selected = options
+            .iter()
+            .rposition(|option| option.selected)
+            .or_else(|| options.iter().position(|option| !option.disabled));
+        for (index, option) in options.iter_mut().enumerate() {
+            option.selected = Some(index) == selected;
+        }
+    }
+    options
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split(is_html_whitespace)
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl Document {
+    /// Every `<form>` in the document with its controls, in document order.
+    pub fn forms(&self) -> Vec<FormModel> {
+        let mut forms: Vec<FormModel> = self
+            .get_elements_by_tag_name("form")
+            .into_iter()
+            .filter_map(|id| {
+                let element = self.element(id).filter(|element| element.is("form"))?;
+                Some(FormModel {
+                    element: id,
+                    action: self.resolve_url(element.get_attribute("action").unwrap_or_default()),
+                    method: FormMethod::parse(element.get_attribute("method")),
+                    controls: Vec::new(),
+                })
+            })
+            .collect();
+
+        for id in self.descendants(self.root()) {
+            let Some(element) = self.element(id) else {
+                continue;
+            };
+            let Some(kind) = ControlKind::of(element) else {
+                continue;
+            };
+            let owner = self.form_owner(id, element);
+            if let Some(form) = forms.iter_mut().find(|form| Some(form.element) == owner) {
+                form.controls
+                    .push(FormControl::new(self, id, element, kind));
+            }
+        }
+        forms
+    }
+
+    /// The form the control `id` belongs to: the one its `form` attribute
+    /// names, if it has one, or else the nearest `<form>` around it.
+    fn form_owner(&self, id: NodeId, element: &Element) -> Option<NodeId> {
+        let is_form = |form: &NodeId| self.element(*form).is_some_and(|e| e.is("form"));
+        match element.get_attribute("form") {
+            Some(form) => self.get_element_by_id(form).filter(is_form),
+            None => self.ancestors(id).find(is_form),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_forms_collect_their_controls() {
+        let mut document = Document::parse(
+            "<form id=search action=/search method=POST>\
+             <input name=q value=rust><input type=Checkbox name=safe checked>\
+             <fieldset disabled><input name=page></fieldset>\
+             <select name=lang><option>  Any  </option><option value=ja selected>日本語</select>\
+             <textarea name=note>\nline</textarea><button>Go</button>\
+             </form><input name=outside><input name=remote form=search>",
+        );
+        document.set_url("https://example.com/index.html".to_owned());
+        let forms = document.forms();
+        assert_eq!(forms.len(), 1);
+        let form = &forms[0];
+        assert_eq!(form.action.as_deref(), Some("https://example.com/search"));
+        assert_eq!(form.method, FormMethod::Post);
+
+        let controls: Vec<_> = form
+            .controls
+            .iter()
+            .map(|control| {
+                (
+                    control.name.as_deref().unwrap_or_default(),
+                    control.value.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            controls,
+            vec![
+                ("q", "rust"),
+                ("safe", "on"),
+                ("page", ""),
+                ("lang", "ja"),
+                ("note", "line"),
+                ("", ""),
+                ("remote", ""),
+            ]
+        );
+        assert_eq!(form.controls[1].control_type, "checkbox");
+        assert!(form.controls[1].checked);
+        assert!(form.controls[2].disabled);
+        assert_eq!(form.controls[3].options[0].value, "Any");
+        assert_eq!(form.controls[5].kind, ControlKind::Button);
+        assert_eq!(form.controls[5].control_type, "submit");
+    }
+
+    #[test]
+    fn test_select_defaults_to_its_first_enabled_option() {
+        let document = Document::parse(
+            "<form><select name=s><option disabled>a<option>b<option>c</select></form>",
+        );
+        let form = &document.forms()[0];
+        assert_eq!(form.action, None);
+        assert_eq!(form.method, FormMethod::Get);
+        assert_eq!(form.controls[0].value, "b");
+    }
+}
@@ -0,0 +1,67 @@
+use super::{Document, NodeId};
+
+impl Document {
+    /// The size of the image of the `<img>` element `id`, as `(width,
+    /// height)` in pixels, once it has been fetched and decoded.
+    pub fn natural_size(&self, id: NodeId) -> Option<(u32, u32)> {
+        self.natural_sizes.get(&id).copied()
+    }
+
+    /// Records the size of the image decoded for the `<img>` element `id`.
+    /// Returns whether it changed, in which case the page must be laid out
+    /// again: until then the element was sized by its attributes.
+    pub fn set_natural_size(&mut self, id: NodeId, size: (u32, u32)) -> bool {
+        self.natural_sizes.insert(id, size) != Some(size)
+    }
+
+    /// The `width` or `height` attribute of the element `id` in pixels,
+    /// after the rules for parsing dimension values
+    /// (<https://html.spec.whatwg.org/multipage/common-microsyntaxes.html#rules-for-parsing-dimension-values>).
+    /// Returns `None` if it is missing, not a number, or a percentage.
+    pub fn dimension_attribute(&self, id: NodeId, name: &str) -> Option<f32> {
+        let value = self.element(id)?.get_attribute(name)?;
+        let value = value.trim_start_matches(|c: char| c.is_ascii_whitespace());
+        let digits = value
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(value.len());
+        let (number, rest) = value.split_at(digits);
+        if rest.starts_with('%') {
+            return None;
+        }
+        let number = number.trim_end_matches('.');
+        // Only the first decimal point is part of the number.
+        let number = match number.match_indices('.').nth(1) {
+            Some((second, _)) => number.get(..second)?,
+            None => number,
+        };
+        number.parse().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dimension_attributes() {
+        let document = Document::parse(
+            "<img id=a width=' 120px' height=50.5><img id=b width=50% height=x><img id=c>",
+        );
+        let id = |name| document.get_element_by_id(name).unwrap();
+        assert_eq!(document.dimension_attribute(id("a"), "width"), Some(120.0));
+        assert_eq!(document.dimension_attribute(id("a"), "height"), Some(50.5));
+        assert_eq!(document.dimension_attribute(id("b"), "width"), None);
+        assert_eq!(document.dimension_attribute(id("b"), "height"), None);
+        assert_eq!(document.dimension_attribute(id("c"), "width"), None);
+    }
+
+    #[test]
+    fn test_natural_size() {
+        let mut document = Document::parse("<img id=a src=a.png>");
+        let a = document.get_element_by_id("a").unwrap();
+        assert_eq!(document.natural_size(a), None);
+        assert!(document.set_natural_size(a, (40, 30)));
+        assert!(!document.set_natural_size(a, (40, 30)));
+        assert_eq!(document.natural_size(a), Some((40, 30)));
+    }
+}
@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+
+use super::{Document, Element, Namespace, NodeId};
+
+/// Elements by id, tag name and class, kept up to date as elements are
+/// added so that lookups do not walk the tree. Each list is sorted by
+/// [`NodeId`], which is document order, since the tree builder only ever
+/// appends.
+///
+/// Detached elements are left in the lists, so that detaching stays cheap,
+/// and filtered out when looked up.
+#[derive(Debug, Clone, Default)]
+pub(super) struct ElementIndex {
+    by_id: HashMap<String, Vec<NodeId>>,
+    by_tag_name: HashMap<String, Vec<NodeId>>,
+    by_class: HashMap<String, Vec<NodeId>>,
+}
+
+impl ElementIndex {
+    pub(super) fn insert(&mut self, id: NodeId, element: &Element) {
+        let tag_name = match element.namespace {
+            Namespace::Html => element.name.to_ascii_lowercase(),
+            Namespace::Svg | Namespace::MathMl => element.name.clone(),
+        };
+        self.by_tag_name.entry(tag_name).or_default().push(id);
+        for (name, value) in &element.attributes {
+            self.insert_attribute(id, name, value);
+        }
+    }
+
+    /// Indexes an attribute added to the element `id`. Attributes can be
+    /// set on any element after parsing, so `id` is inserted where it
+    /// keeps its lists sorted rather than pushed.
+    pub(super) fn insert_attribute(&mut self, id: NodeId, name: &str, value: &str) {
+        if name.eq_ignore_ascii_case("id") && !value.is_empty() {
+            insert_sorted(self.by_id.entry(value.to_owned()).or_default(), id);
+        } else if name.eq_ignore_ascii_case("class") {
+            for class in value.split_ascii_whitespace() {
+                insert_sorted(self.by_class.entry(class.to_owned()).or_default(), id);
+            }
+        }
+    }
+
+    /// Drops the entries for an attribute the element `id` no longer has.
+    pub(super) fn remove_attribute(&mut self, id: NodeId, name: &str, value: &str) {
+        if name.eq_ignore_ascii_case("id") {
+            remove_sorted(&mut self.by_id, value, id);
+        } else if name.eq_ignore_ascii_case("class") {
+            for class in value.split_ascii_whitespace() {
+                remove_sorted(&mut self.by_class, class, id);
+            }
+        }
+    }
+}
+
+fn insert_sorted(elements: &mut Vec<NodeId>, id: NodeId) {
+    if let Err(index) = elements.binary_search(&id) {
+        elements.insert(index, id);
+    }
+}
+
+fn remove_sorted(lists: &mut HashMap<String, Vec<NodeId>>, key: &str, id: NodeId) {
+    let Some(elements) = lists.get_mut(key) else {
+        return;
+    };
+    if let Ok(index) = elements.binary_search(&id) {
+        elements.remove(index);
+    }
+    if elements.is_empty() {
+        lists.remove(key);
+    }
+}
+
+impl Document {
+    /// The first element whose `id` attribute is `id`.
+    pub fn get_element_by_id(&self, id: &str) -> Option<NodeId> {
+        self.index
+            .by_id
+            .get(id)?
+            .iter()
+            .copied()
+            .find(|&element| self.is_connected(element))
+    }
+
+    /// The elements named `name`, in document order. HTML elements match
+    /// regardless of case.
+    pub fn get_elements_by_tag_name(&self, name: &str) -> Vec<NodeId> {
+        self.index
+            .by_tag_name
+            .get(name)
+            .or_else(|| self.index.by_tag_name.get(&name.to_ascii_lowercase()))
+            .map_or(&[][..], Vec::as_slice)
+            .iter()
+            .copied()
+            .filter(|&element| self.is_connected(element))
+            .collect()
+    }
+
+    /// The elements that have every class in the whitespace-separated
+    /// `class_names`, in document order.
+    pub fn get_elements_by_class_name(&self, class_names: &str) -> Vec<NodeId> {
+        let mut classes = class_names.split_ascii_whitespace();
+        let Some(first) = classes.next() else {
+            return Vec::new();
+        };
+        let lists: Vec<&[NodeId]> = classes
+            .map(|class| {
+                self.index
+                    .by_class
+                    .get(class)
+                    .map_or(&[][..], Vec::as_slice)
+            })
+            .collect();
+        self.index
+            .by_class
+            .get(first)
+            .map_or(&[][..], Vec::as_slice)
+            .iter()
+            .copied()
+            // Lists are sorted, as ids are handed out in increasing order.
+            .filter(|id| lists.iter().all(|list| list.binary_search(id).is_ok()))
+            .filter(|&id| self.is_connected(id))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dom::Document;
+
+    #[test]
+    fn test_get_element_by_id() {
+        let document = Document::parse("<p id=a>1</p><div><p id=b>2</p><p id=b>3</p></div>");
+        let b = document.get_element_by_id("b").unwrap();
+        assert_eq!(document.text_content(b), "2");
+        assert!(document.get_element_by_id("a").is_some());
+        assert_eq!(document.get_element_by_id("c"), None);
+        assert_eq!(document.get_element_by_id(""), None);
+
+        let document = Document::parse("<p>1</p><body id=late>");
+        assert_eq!(document.get_element_by_id("late"), document.body());
+    }
+
+    #[test]
+    fn test_get_elements_by_tag_name() {
+        let document = Document::parse("<p>1<div><P>2</div><svg><foreignObject/></svg>");
+        assert_eq!(document.get_elements_by_tag_name("p").len(), 2);
+        assert_eq!(document.get_elements_by_tag_name("P").len(), 2);
+        assert_eq!(document.get_elements_by_tag_name("foreignObject").len(), 1);
+        assert_eq!(document.get_elements_by_tag_name("body").len(), 1);
+        assert!(document.get_elements_by_tag_name("table").is_empty());
+    }
+
+    #[test]
+    fn test_get_elements_by_class_name() {
+        let document = Document::parse(
+            "<body class=page><p class='a b'>1</p><p class=a>2</p><p class='b a a'>3",
+        );
+        assert_eq!(document.get_elements_by_class_name("a").len(), 3);
+        assert_eq!(document.get_elements_by_class_name(" b  a ").len(), 2);
+        assert_eq!(document.get_elements_by_class_name("a c").len(), 0);
+        assert_eq!(document.get_elements_by_class_name("").len(), 0);
+        assert_eq!(
+            document.get_elements_by_class_name("page"),
+            vec![document.body().unwrap()]
+        );
+    }
+}
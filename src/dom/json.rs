@@ -0,0 +1,150 @@
+use std::collections::BTreeMap;
+
+use super::{Document, Namespace, Node, NodeId, Visit, Visitor};
+
+/// A node and its descendants as a plain nested structure, which serde
+/// can write out as JSON for other tools or for snapshot tests:
+///
+/// ```text
+/// {"type":"element","tag":"p","namespace":"html","attrs":{"class":"x"},
+///  "children":[{"type":"text","text":"Hi"}]}
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum JsonNode {
+    Document {
+        children: Vec<Self>,
+    },
+    Doctype {
+        name: String,
+    },
+    Element {
+        tag: String,
+        namespace: Namespace,
+        attrs: BTreeMap<String, String>,
+        children: Vec<Self>,
+    },
+    Text {
+        text: String,
+    },
+    Comment {
+        text: String,
+    },
+}
+
+/// Builds [`JsonNode`]s bottom-up as a walk exits each node, keeping the
+/// children collected so far for every open node.
+#[derive(Debug)]
+struct JsonBuilder {
+    open: Vec<Vec<JsonNode>>,
+}
+
+impl Visitor for JsonBuilder {
+    fn enter(&mut self, _document: &Document, _id: NodeId) -> Visit {
+        self.open.push(Vec::new());
+        Visit::Continue
+    }
+
+    fn exit(&mut self, document: &Document, id: NodeId) {
+        let children = self.open.pop().unwrap_or_default();
+        let node = match document.node(id) {
+            Node::Document | Node::DocumentFragment => JsonNode::Document { children },
+            Node::Doctype(name) => JsonNode::Doctype { name: name.clone() },
+            Node::Element(element) => JsonNode::Element {
+                tag: element.name.clone(),
+                namespace: element.namespace,
+                attrs: element.attributes.iter().cloned().collect(),
+                children,
+            },
+            Node::Text(text) => JsonNode::Text { text: text.clone() },
+            Node::Comment(text) => JsonNode::Comment { text: text.clone() },
+        };
+        if let Some(siblings) = self.open.last_mut() {
+            siblings.push(node);
+        }
+    }
+}
+
+impl Document {
+    /// The whole document as a [`JsonNode`] tree.
+    pub fn to_json(&self) -> JsonNode {
+        let mut builder = JsonBuilder {
+            open: vec![Vec::new()],
+        };
+        self.walk(self.root(), &mut builder);
+        builder
+            .open
+            .pop()
+            .and_then(|mut nodes| nodes.pop())
+            .unwrap_or(JsonNode::Document {
+                children: Vec::new(),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn element(tag: &str, attrs: &[(&str, &str)], children: Vec<JsonNode>) -> JsonNode {
+        JsonNode::Element {
+            tag: tag.to_string(),
+            namespace: Namespace::Html,
+            attrs: attrs
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            children,
+        }
+    }
+
+    #[test]
+    fn test_to_json_nests_the_tree() {
+        let document = Document::parse("<!DOCTYPE html><p class=x id=y>Hi<!--c--></p>");
+        assert_eq!(
+            document.to_json(),
+            JsonNode::Document {
+                children: vec![
+                    JsonNode::Doctype {
+                        name: "html".to_string()
+                    },
+                    element(
+                        "html",
+                        &[],
+                        vec![
+                            element("head", &[], vec![]),
+                            element(
+                                "body",
+                                &[],
+                                vec![element(
+                                    "p",
+                                    &[("id", "y"), ("class", "x")],
+                                    vec![
+                                        JsonNode::Text {
+                                            text: "Hi".to_string()
+                                        },
+                                        JsonNode::Comment {
+                                            text: "c".to_string()
+                                        },
+                                    ]
+                                )]
+                            ),
+                        ]
+                    ),
+                ]
+            }
+        );
+    }
+
+    #[test]
+    fn test_to_json_serializes_with_serde() {
+        let document = Document::parse("<svg width=1></svg>");
+        let JsonNode::Document { children } = document.to_json() else {
+            panic!("the root is not a document");
+        };
+        assert_eq!(
+            serde_json::to_string(&children[0]).unwrap(),
+            r#"{"type":"element","tag":"html","namespace":"html","attrs":{},"children":[{"type":"element","tag":"head","namespace":"html","attrs":{},"children":[]},{"type":"element","tag":"body","namespace":"html","attrs":{},"children":[{"type":"element","tag":"svg","namespace":"svg","attrs":{"width":"1"},"children":[]}]}]}"#
+        );
+    }
+}
@@ -0,0 +1,175 @@
+use super::metadata::has_rel;
+use super::{Document, Element, NodeId};
+
+/// What a [`Link`] points at, which decides whether it is fetched along
+/// with the page or only followed when the user asks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkKind {
+    /// `<a href>` or `<area href>`, a page to navigate to.
+    Hyperlink,
+
+    /// `<link rel=stylesheet>`.
+    Stylesheet,
+
+    /// `<link rel=icon>` or `<link rel=apple-touch-icon>`.
+    Icon,
+
+    /// `<img src>`.
+    Image,
+
+    /// `<script src>`.
+    Script,
+
+    /// A `<link>` with any other relation, such as `canonical` or
+    /// `preload`.
+    Other,
+}
+
+/// A URL the document refers to; see [`Document::links`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    /// The element the URL is on.
+    pub element: NodeId,
+    pub kind: LinkKind,
+
+    /// The URL as written in the document.
+    pub href: String,
+
+    /// `href` resolved against the document's base URL, or `None` if it is
+    /// relative and the document has no URL.
+    pub url: Option<String>,
+}
+
+impl LinkKind {
+    /// The kind of link `element` is, and the attribute holding its URL.
+    fn of(element: &Element) -> Option<(Self, &'static str)> {
+        if element.is("a") || element.is("area") {
+            Some((Self::Hyperlink, "href"))
+        } else if element.is("img") {
+            Some((Self::Image, "src"))
+        } else if element.is("script") {
+            Some((Self::Script, "src"))
+        } else if element.is("link") {
+            let kind = if has_rel(element, "stylesheet") {
+                Self::Stylesheet
+            } else if has_rel(element, "icon") || has_rel(element, "apple-touch-icon") {
+                Self::Icon
+            } else {
+                Self::Other
+            };
+            Some((kind, "href"))
+        } else {
+            None
+        }
+    }
+}
+
+impl Document {
+    /// Every `<a href>`, `<area href>`, `<link href>`, `<img src>` and
+    /// `<script src>` in the document, in document order, with its URL
+    /// resolved with [`Document::resolve_url`].
+    pub fn links(&self) -> Vec<Link> {
+        self.descendants(self.root())
+            .filter_map(|id| {
+                let element = self.element(id)?;
+                let (kind, attribute) = LinkKind::of(element)?;
+                let href = element.get_attribute(attribute)?;
+                Some(Link {
+                    element: id,
+                    kind,
+                    href: href.to_owned(),
+                    url: self.resolve_url(href),
+                })
+            })
+            .collect()
+    }
+
+    /// The link `id` is in, which following it from `id` follows: `id`
+    /// itself or its nearest ancestor that is an `<a>` or `<area>` with an
+    /// `href`.
+    pub fn enclosing_link(&self, id: NodeId) -> Option<NodeId> {
+        std::iter::once(id)
+            .chain(self.ancestors(id))
+            .find(|&ancestor| self.is_link(ancestor))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_links_are_resolved_and_classified() {
+        let mut document = Document::parse(
+            "<link rel=stylesheet href=style.css><link rel='shortcut icon' href=/favicon.ico>\
+             <link rel=canonical href=https://example.org/>\
+             <script src=app.js></script><script>inline()</script>\
+             <p><a href=../up.html>up</a> <a>no href</a> <img src='img/a.png' alt=a>\
+             <svg><a href=svg-link /></svg>",
+        );
+        document.set_url("https://example.com/docs/page.html".to_owned());
+        let links: Vec<_> = document
+            .links()
+            .into_iter()
+            .map(|link| (link.kind, link.url.unwrap_or_default()))
+            .collect();
+        assert_eq!(
+            links,
+            vec![
+                (
+                    LinkKind::Stylesheet,
+                    "https://example.com/docs/style.css".to_owned()
+                ),
+                (LinkKind::Icon, "https://example.com/favicon.ico".to_owned()),
+                (LinkKind::Other, "https://example.org/".to_owned()),
+                (
+                    LinkKind::Script,
+                    "https://example.com/docs/app.js".to_owned()
+                ),
+                (
+                    LinkKind::Hyperlink,
+                    "https://example.com/up.html".to_owned()
+                ),
+                (
+                    LinkKind::Image,
+                    "https://example.com/docs/img/a.png".to_owned()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_links_use_the_base_element() {
+        let mut document =
+            Document::parse("<base href=/assets/><a href=next.html>next</a><a href=#top>top</a>");
+        let urls: Vec<_> = document.links().into_iter().map(|link| link.url).collect();
+        assert_eq!(urls, vec![None, None]);
+
+        document.set_url("https://example.com/index.html".to_owned());
+        let urls: Vec<_> = document.links().into_iter().map(|link| link.url).collect();
+        assert_eq!(
+            urls,
+            vec![
+                Some("https://example.com/assets/next.html".to_owned()),
+                Some("https://example.com/assets/#top".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_enclosing_link() {
+        let mut document = Document::parse(
+            "<p id=p><a id=a href=next.html><em id=em>next</em></a><a id=none>none</a>",
+        );
+        document.set_url("https://example.com/".to_owned());
+        let id = |name| document.get_element_by_id(name).unwrap();
+        let link = document.enclosing_link(id("em"));
+        assert_eq!(link, Some(id("a")));
+        assert_eq!(
+            link.and_then(|link| document.link_url(link)).as_deref(),
+            Some("https://example.com/next.html")
+        );
+        assert_eq!(document.enclosing_link(id("p")), None);
+        assert_eq!(document.enclosing_link(id("none")), None);
+    }
+}
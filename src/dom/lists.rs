@@ -0,0 +1,78 @@
+use super::{Document, NodeId};
+
+impl Document {
+    /// The `<ol>`, `<ul>` or `<menu>` element whose items include the
+    /// `<li>` element `id`: its nearest such ancestor, or its parent if it
+    /// has none
+    /// (<https://html.spec.whatwg.org/multipage/grouping-content.html#list-owner>).
+    pub fn list_owner(&self, id: NodeId) -> Option<NodeId> {
+        self.ancestors(id)
+            .find(|&ancestor| {
+                self.element(ancestor).is_some_and(|element| {
+                    ["ol", "ul", "menu"].iter().any(|&name| element.is(name))
+                })
+            })
+            .or_else(|| self.parent(id))
+    }
+
+    /// The number of the list item `id` in its list, as its marker shows
+    /// it
+    /// (<https://html.spec.whatwg.org/multipage/grouping-content.html#ordinal-value>).
+    /// Items count up from the `start` attribute of their `<ol>`, or down
+    /// if it is `reversed`, and an item's `value` attribute restarts the
+    /// count from it.
+    pub fn ordinal_value(&self, id: NodeId) -> i64 {
+        let Some(owner) = self.list_owner(id) else {
+            return 1;
+        };
+        let items: Vec<NodeId> = self
+            .descendants(owner)
+            .filter(|&item| {
+                self.element(item).is_some_and(|element| element.is("li"))
+                    && self.list_owner(item) == Some(owner)
+            })
+            .collect();
+        let list = self.element(owner).filter(|element| element.is("ol"));
+        let reversed = list.is_some_and(|list| list.get_attribute("reversed").is_some());
+        let start = list
+            .and_then(|list| list.get_attribute("start"))
+            .and_then(|start| start.trim().parse().ok());
+        let mut number = match start {
+            Some(start) => start,
+            None if reversed => items.len() as i64,
+            None => 1,
+        };
+        for item in items {
+            if let Some(value) = self
+                .element(item)
+                .and_then(|element| element.get_attribute("value"))
+                .and_then(|value| value.trim().parse().ok())
+            {
+                number = value;
+            }
+            if item == id {
+                break;
+            }
+            number += if reversed { -1 } else { 1 };
+        }
+        number
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ordinal_values() {
+        let document = Document::parse(
+            "<ol start=5><li id=a>a<li id=b>b<ul><li id=c>c</ul><li id=d value=10>d<li id=e>e</ol>\
+             <ol reversed><li id=f>f<li id=g>g<li id=h>h</ol>",
+        );
+        let ordinals: Vec<i64> = ["a", "b", "c", "d", "e", "f", "g", "h"]
+            .iter()
+            .map(|name| document.ordinal_value(document.get_element_by_id(name).unwrap()))
+            .collect();
+        assert_eq!(ordinals, [5, 6, 1, 10, 11, 3, 2, 1]);
+    }
+}
@@ -0,0 +1,218 @@
+use super::tree_builder::is_html_whitespace;
+use super::{Document, Element, NodeId};
+
+/// An icon the page declares with `<link rel=icon>` or
+/// `<link rel=apple-touch-icon>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Icon {
+    /// The URL as written in the document, not yet resolved.
+    pub href: String,
+
+    /// The `sizes` attribute, such as `32x32` or `any`.
+    pub sizes: Option<String>,
+
+    /// The `type` attribute, such as `image/png`.
+    pub mime_type: Option<String>,
+}
+
+/// What a page says about itself in its `<head>`: the title for the tab,
+/// and what a link preview needs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PageMetadata {
+    /// The text of the first `<title>`, with whitespace collapsed.
+    pub title: Option<String>,
+
+    /// `<meta name=description>`.
+    pub description: Option<String>,
+
+    /// `<meta charset>`, or the charset in `<meta http-equiv=content-type>`.
+    pub charset: Option<String>,
+
+    /// `<link rel=canonical>`, as written in the document.
+    pub canonical_url: Option<String>,
+
+    /// Open Graph properties such as `og:title` and `og:image`, in document
+    /// order. A property may appear more than once.
+    pub open_graph: Vec<(String, String)>,
+
+    pub icons: Vec<Icon>,
+}
+
+impl PageMetadata {
+    /// Collects the metadata of `document`. Where a page repeats a tag, the
+    /// first one wins, except for Open Graph properties and icons, which
+    /// are all kept.
+    pub fn from_document(document: &Document) -> Self {
+        let mut metadata = Self {
+            title: title(document),
+            ..Self::default()
+        };
+
+        for element in elements(document, "meta") {
+            let content = element.get_attribute("content");
+            if let Some(charset) = element.get_attribute("charset") {
+                metadata
+                    .charset
+                    .get_or_insert_with(|| charset.trim().to_owned());
+            } else if element
+                .get_attribute("http-equiv")
+                .is_some_and(|header| header.eq_ignore_ascii_case("content-type"))
+                && let Some(charset) = content.and_then(charset_from_content_type)
+            {
+                metadata.charset.get_or_insert(charset);
+            }
+
+            if let Some(content) = content {
+                if element
+                    .get_attribute("name")
+                    .is_some_and(|name| name.eq_ignore_ascii_case("description"))
+                {
+                    metadata
+                        .description
+                        .get_or_insert_with(|| content.to_owned());
+                }
+                if let Some(property) = element.get_attribute("property")
+                    && property.starts_with("og:")
+                {
+                    metadata
+                        .open_graph
+                        .push((property.to_owned(), content.to_owned()));
+                }
+            }
+        }
+
+        for element in elements(document, "link") {
+            let Some(href) = element.get_attribute("href") else {
+                continue;
+            };
+            if has_rel(element, "canonical") {
+                metadata
+                    .canonical_url
+                    .get_or_insert_with(|| href.to_owned());
+            }
+            if has_rel(element, "icon") || has_rel(element, "apple-touch-icon") {
+                metadata.icons.push(Icon {
+                    href: href.to_owned(),
+                    sizes: element.get_attribute("sizes").map(str::to_owned),
+                    mime_type: element.get_attribute("type").map(str::to_owned),
+                });
+            }
+        }
+
+        metadata
+    }
+
+    /// The first value of the Open Graph `property`, such as `og:image`.
+    pub fn open_graph(&self, property: &str) -> Option<&str> {
+        self.open_graph
+            .iter()
+            .find(|(name, _)| name == property)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// The HTML elements named `name` in the document, in document order.
+fn elements<'d>(document: &'d Document, name: &str) -> impl Iterator<Item = &'d Element> {
+    document
+        .get_elements_by_tag_name(name)
+        .into_iter()
+        .filter_map(|id| document.element(id))
+        .filter(move |element| element.is(name))
+}
+
+fn title(document: &Document) -> Option<String> {
+    let title: NodeId = document
+        .get_elements_by_tag_name("title")
+        .into_iter()
+        .find(|&id| {
+            document
+                .element(id)
+                .is_some_and(|element| element.is("title"))
+        })?;
+    let text = document.text_content(title);
+    let words: Vec<&str> = text
+        .split(is_html_whitespace)
+        .filter(|word| !word.is_empty())
+        .collect();
+    Some(words.join(" "))
+}
+
+/// Whether the element's `rel` attribute has the keyword `keyword`.
+pub(super) fn has_rel(element: &Element, keyword: &str) -> bool {
+    element.get_attribute("rel").is_some_and(|rel| {
+        rel.split_ascii_whitespace()
+            .any(|value| value.eq_ignore_ascii_case(keyword))
+    })
+}
+
+/// The charset in a `Content-Type` value such as
+/// `text/html; charset=utf-8`.
+fn charset_from_content_type(content_type: &str) -> Option<String> {
+    content_type.split(';').skip(1).find_map(|parameter| {
+        let (name, value) = parameter.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("charset")
+            .then(|| value.trim().trim_matches(['"', '\'']).to_owned())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_metadata() {
+        let document = Document::parse(
+            "<head><meta charset=utf-8><title>\n  My   page </title>\
+             <meta name=Description content='About it'>\
+             <meta property=og:title content='OG title'>\
+             <meta property=og:image content=/a.png><meta property=og:image content=/b.png>\
+             <link rel=canonical href=https://example.com/page>\
+             <link rel='shortcut icon' href=/favicon.ico>\
+             <link rel=icon href=/icon.png sizes=32x32 type=image/png>\
+             <link rel=stylesheet href=/style.css></head><title>Not this</title>",
+        );
+        let metadata = PageMetadata::from_document(&document);
+        assert_eq!(metadata.title.as_deref(), Some("My page"));
+        assert_eq!(metadata.description.as_deref(), Some("About it"));
+        assert_eq!(metadata.charset.as_deref(), Some("utf-8"));
+        assert_eq!(
+            metadata.canonical_url.as_deref(),
+            Some("https://example.com/page")
+        );
+        assert_eq!(metadata.open_graph("og:title"), Some("OG title"));
+        assert_eq!(metadata.open_graph("og:image"), Some("/a.png"));
+        assert_eq!(metadata.open_graph.len(), 3);
+        assert_eq!(
+            metadata.icons,
+            vec![
+                Icon {
+                    href: "/favicon.ico".to_string(),
+                    sizes: None,
+                    mime_type: None,
+                },
+                Icon {
+                    href: "/icon.png".to_string(),
+                    sizes: Some("32x32".to_string()),
+                    mime_type: Some("image/png".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_charset_from_http_equiv() {
+        let document = Document::parse(
+            "<meta http-equiv=Content-Type content='text/html; charset=\"Shift_JIS\"'>",
+        );
+        let metadata = PageMetadata::from_document(&document);
+        assert_eq!(metadata.charset.as_deref(), Some("Shift_JIS"));
+        assert_eq!(metadata.title, None);
+    }
+
+    #[test]
+    fn test_svg_title_is_not_the_page_title() {
+        let document = Document::parse("<svg><title>Icon</title></svg>");
+        assert_eq!(PageMetadata::from_document(&document).title, None);
+    }
+}
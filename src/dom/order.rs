@@ -0,0 +1,168 @@
+use super::{Document, NodeId};
+
+/// Where a node is relative to another; see
+/// [`Document::compare_document_position`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DocumentPosition {
+    /// The two are the same node.
+    Same,
+
+    /// The nodes are in different trees, such as a detached subtree or a
+    /// template's content, so neither comes first.
+    Disconnected,
+
+    /// The other node is an ancestor, and so comes first.
+    Contains,
+
+    /// The other node is a descendant, and so comes after.
+    ContainedBy,
+
+    /// The other node comes first, and is not an ancestor.
+    Preceding,
+
+    /// The other node comes after, and is not a descendant.
+    Following,
+}
+
+/// A node's place in a preorder numbering of the tree it is in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct TreePosition {
+    /// The node at the top of the tree.
+    root: NodeId,
+
+    /// The node's number.
+    start: u32,
+
+    /// The number of the node's last descendant, or its own if it has none.
+    /// The descendants of a node are the ones numbered from `start` to
+    /// `end`.
+    end: u32,
+}
+
+impl TreePosition {
+    /// A placeholder until every node is numbered.
+    const UNNUMBERED: Self = Self {
+        root: NodeId(0),
+        start: 0,
+        end: 0,
+    };
+}
+
+impl Document {
+    /// Whether `other` is `id` or one of its descendants, like the DOM's
+    /// `Node.contains`.
+    pub fn contains(&self, id: NodeId, other: NodeId) -> bool {
+        let (node, other) = (self.tree_position(id), self.tree_position(other));
+        node.root == other.root && node.start <= other.start && other.start <= node.end
+    }
+
+    /// Where `other` is relative to `id`, like the DOM's
+    /// `Node.compareDocumentPosition`.
+    pub fn compare_document_position(&self, id: NodeId, other: NodeId) -> DocumentPosition {
+        let (node, other) = (self.tree_position(id), self.tree_position(other));
+        if node.root != other.root {
+            DocumentPosition::Disconnected
+        } else if node.start == other.start {
+            DocumentPosition::Same
+        } else if other.start < node.start {
+            if node.end <= other.end {
+                DocumentPosition::Contains
+            } else {
+                DocumentPosition::Preceding
+            }
+        } else if other.start <= node.end {
+            DocumentPosition::ContainedBy
+        } else {
+            DocumentPosition::Following
+        }
+    }
+
+    /// The position of `id`, numbering every tree the first time it is
+    /// needed after a change.
+    fn tree_position(&self, id: NodeId) -> TreePosition {
+        self.tree_order
+            .get_or_init(|| self.number_trees())
+            .get(id.index())
+            .copied()
+            .unwrap_or(TreePosition::UNNUMBERED)
+    }
+
+    /// Numbers the nodes of each tree in document order: the document, the
+    /// content of each template, and each detached subtree.
+    fn number_trees(&self) -> Vec<TreePosition> {
+        let mut positions = vec![TreePosition::UNNUMBERED; self.nodes.len()];
+        let mut number = 0;
+        for root in (0..self.nodes.len()).filter_map(|index| {
+            let root = NodeId(u32::try_from(index).ok()?);
+            self.parent(root).is_none().then_some(root)
+        }) {
+            let nodes: Vec<NodeId> = std::iter::once(root)
+                .chain(self.descendants(root))
+                .collect();
+            for &node in &nodes {
+                if let Some(position) = positions.get_mut(node.index()) {
+                    *position = TreePosition {
+                        root,
+                        start: number,
+                        end: number,
+                    };
+                }
+                number += 1;
+            }
+            // Children come after their parent, so going backwards each
+            // node's last child already knows where its subtree ends.
+            for &node in nodes.iter().rev() {
+                let end = self
+                    .last_child(node)
+                    .and_then(|child| positions.get(child.index()))
+                    .map(|child| child.end);
+                if let (Some(end), Some(position)) = (end, positions.get_mut(node.index())) {
+                    position.end = end;
+                }
+            }
+        }
+        positions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contains() {
+        let mut document = Document::parse("<div id=a><p id=b>x</p></div><p id=c>");
+        let [a, b, c] = ["a", "b", "c"].map(|id| document.get_element_by_id(id).unwrap());
+        assert!(document.contains(a, b));
+        assert!(document.contains(a, a));
+        assert!(document.contains(document.root(), c));
+        assert!(!document.contains(b, a));
+        assert!(!document.contains(a, c));
+
+        document.detach(a);
+        assert!(!document.contains(document.root(), b));
+        assert!(document.contains(a, b));
+    }
+
+    #[test]
+    fn test_compare_document_position() {
+        let mut document =
+            Document::parse("<div id=a><p id=b>x</p></div><p id=c><template><i id=t></i>");
+        let [a, b, c] = ["a", "b", "c"].map(|id| document.get_element_by_id(id).unwrap());
+        let position =
+            |document: &Document, id, other| document.compare_document_position(id, other);
+        assert_eq!(position(&document, a, a), DocumentPosition::Same);
+        assert_eq!(position(&document, a, b), DocumentPosition::ContainedBy);
+        assert_eq!(position(&document, b, a), DocumentPosition::Contains);
+        assert_eq!(position(&document, b, c), DocumentPosition::Following);
+        assert_eq!(position(&document, c, b), DocumentPosition::Preceding);
+
+        let template = document.get_elements_by_tag_name("template")[0];
+        let content = document.template_content(template).unwrap();
+        let t = document.first_child(content).unwrap();
+        assert_eq!(position(&document, c, t), DocumentPosition::Disconnected);
+
+        document.detach(c);
+        assert_eq!(position(&document, a, c), DocumentPosition::Disconnected);
+    }
+}
@@ -0,0 +1,97 @@
+use super::{Document, NodeId};
+use crate::selectors::SelectorList;
+
+impl Document {
+    /// The first element, in document order, that matches the CSS
+    /// `selectors`, such as `div.article > p:first-child`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the problem if `selectors` cannot be
+    /// parsed or uses unsupported syntax.
+    pub fn query_selector(&self, selectors: &str) -> Result<Option<NodeId>, String> {
+        let list = SelectorList::parse(selectors)?;
+        Ok(self
+            .descendants(self.root())
+            .find(|&id| list.matches(self, id)))
+    }
+
+    /// Every element that matches the CSS `selectors`, in document order.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the problem if `selectors` cannot be
+    /// parsed or uses unsupported syntax.
+    pub fn query_selector_all(&self, selectors: &str) -> Result<Vec<NodeId>, String> {
+        let list = SelectorList::parse(selectors)?;
+        Ok(self
+            .descendants(self.root())
+            .filter(|&id| list.matches(self, id))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dom::Document;
+
+    fn names(document: &Document, selectors: &str) -> Vec<String> {
+        document
+            .query_selector_all(selectors)
+            .unwrap()
+            .into_iter()
+            .map(|id| {
+                let element = document.element(id).unwrap();
+                match element.get_attribute("id") {
+                    Some(id) => format!("{}#{id}", element.name),
+                    None => element.name.clone(),
+                }
+            })
+            .collect()
+    }
+
+    const PAGE: &str = "<div class='article main'><p id=a>1</p><p id=b class=x>2</p>\
+        <section><p id=c lang=en>3</p></section></div><p id=d>4</p>";
+
+    #[test]
+    fn test_query_selector_all_by_type_class_and_id() {
+        let document = Document::parse(PAGE);
+        assert_eq!(names(&document, "p"), vec!["p#a", "p#b", "p#c", "p#d"]);
+        assert_eq!(names(&document, ".x"), vec!["p#b"]);
+        assert_eq!(names(&document, "div.main.article"), vec!["div"]);
+        assert_eq!(names(&document, "#c"), vec!["p#c"]);
+        assert_eq!(names(&document, "p[lang=en], #a"), vec!["p#a", "p#c"]);
+        assert_eq!(names(&document, "[lang]"), vec!["p#c"]);
+        assert_eq!(names(&document, "*:root"), vec!["html"]);
+        assert!(names(&document, ".missing").is_empty());
+    }
+
+    #[test]
+    fn test_query_selector_combinators_and_pseudo_classes() {
+        let document = Document::parse(PAGE);
+        assert_eq!(names(&document, "div.article > p:first-child"), vec!["p#a"]);
+        assert_eq!(names(&document, "div p"), vec!["p#a", "p#b", "p#c"]);
+        assert_eq!(names(&document, "div > p"), vec!["p#a", "p#b"]);
+        assert_eq!(names(&document, "body > p:last-child"), vec!["p#d"]);
+        assert_eq!(names(&document, "section p:only-child"), vec!["p#c"]);
+        assert_eq!(names(&document, "html  div>section   p"), vec!["p#c"]);
+
+        let first = document.query_selector("div p").unwrap().unwrap();
+        assert_eq!(
+            document.element(first).unwrap().get_attribute("id"),
+            Some("a")
+        );
+        assert_eq!(document.query_selector("table").unwrap(), None);
+    }
+
+    #[test]
+    fn test_query_selector_rejects_bad_selectors() {
+        let document = Document::parse(PAGE);
+        assert!(document.query_selector("").is_err());
+        assert!(document.query_selector("p,").is_err());
+        assert!(document.query_selector("p:nth-child").is_err());
+        assert!(document.query_selector("p[lang=]").is_err());
+        assert!(document.query_selector("p ~").is_err());
+        assert!(document.query_selector("> p").is_err());
+    }
+}
@@ -0,0 +1,214 @@
+/// Whether a document is rendered in quirks mode, which emulates old
+/// browsers for pages written for them.
+///
+/// The doctype decides it
+/// (<https://html.spec.whatwg.org/multipage/parsing.html#the-initial-insertion-mode>).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum CompatMode {
+    /// Standards mode, for `<!DOCTYPE html>` and modern doctypes.
+    #[default]
+    NoQuirks,
+
+    /// Almost standards mode, with only the quirk that images in table
+    /// cells do not get space below the baseline.
+    LimitedQuirks,
+    Quirks,
+}
+
+/// Public identifiers that put a document in quirks mode when the one in
+/// its doctype starts with them, compared ignoring ASCII case.
+const QUIRKS_PUBLIC_ID_PREFIXES: [&str; 55] = [
+    "+//silmaril//dtd html pro v0r11 19970101//",
+    "-//as//dtd html 3.0 aswedit + extensions//",
+    "-//advasoft ltd//dtd html 3.0 aswedit + extensions//",
+    "-//ietf//dtd html 2.0 level 1//",
+    "-//ietf//dtd html 2.0 level 2//",
+    "-//ietf//dtd html 2.0 strict level 1//",
+    "-//ietf//dtd html 2.0 strict level 2//",
+    "-//ietf//dtd html 2.0 strict//",
+    "-//ietf//dtd html 2.0//",
+    "-//ietf//dtd html 2.1e//",
+    "-//ietf//dtd html 3.0//",
+    "-//ietf//dtd html 3.2 final//",
+    "-//ietf//dtd html 3.2//",
+    "-//ietf//dtd html 3//",
+    "-//ietf//dtd html level 0//",
+    "-//ietf//dtd html level 1//",
+    "-//ietf//dtd html level 2//",
+    "-//ietf//dtd html level 3//",
+    "-//ietf//dtd html strict level 0//",
+    "-//ietf//dtd html strict level 1//",
+    "-//ietf//dtd html strict level 2//",
+    "-//ietf//dtd html strict level 3//",
+    "-//ietf//dtd html strict//",
+    "-//ietf//dtd html//",
+    "-//metrius//dtd metrius presentational//",
+    "-//microsoft//dtd internet explorer 2.0 html strict//",
+    "-//microsoft//dtd internet explorer 2.0 html//",
+    "-//microsoft//dtd internet explorer 2.0 tables//",
+    "-//microsoft//dtd internet explorer 3.0 html strict//",
+    "-//microsoft//dtd internet explorer 3.0 html//",
+    "-//microsoft//dtd internet explorer 3.0 tables//",
+    "-//netscape comm. corp.//dtd html//",
+    "-//netscape comm. corp.//dtd strict html//",
+    "-//o'reilly and associates//dtd html 2.0//",
+    "-//o'reilly and associates//dtd html extended 1.0//",
+    "-//o'reilly and associates//dtd html extended relaxed 1.0//",
+    "-//sq//dtd html 2.0 hotmetal + extensions//",
+    "-//softquad software//dtd hotmetal pro 6.0::19990601::extensions to html 4.0//",
+    "-//softquad//dtd hotmetal pro 4.0::19971010::extensions to html 4.0//",
+    "-//spyglass//dtd html 2.0 extended//",
+    "-//sun microsystems corp.//dtd hotjava html//",
+    "-//sun microsystems corp.//dtd hotjava strict html//",
+    "-//w3c//dtd html 3 1995-03-24//",
+    "-//w3c//dtd html 3.2 draft//",
+    "-//w3c//dtd html 3.2 final//",
+    "-//w3c//dtd html 3.2//",
+    "-//w3c//dtd html 3.2s draft//",
+    "-//w3c//dtd html 4.0 frameset//",
+    "-//w3c//dtd html 4.0 transitional//",
+    "-//w3c//dtd html experimental 19960712//",
+    "-//w3c//dtd html experimental 970421//",
+    "-//w3c//dtd w3 html//",
+    "-//w3o//dtd w3 html 3.0//",
+    "-//webtechs//dtd mozilla html 2.0//",
+    "-//webtechs//dtd mozilla html//",
+];
+
+/// Public identifiers that put a document in quirks mode if its doctype
+/// has no system identifier, and in limited quirks mode if it has one.
+const FRAMESET_PUBLIC_ID_PREFIXES: [&str; 2] = [
+    "-//w3c//dtd html 4.01 frameset//",
+    "-//w3c//dtd html 4.01 transitional//",
+];
+
+/// Public identifiers that put a document in limited quirks mode.
+const LIMITED_QUIRKS_PUBLIC_ID_PREFIXES: [&str; 2] = [
+    "-//w3c//dtd xhtml 1.0 frameset//",
+    "-//w3c//dtd xhtml 1.0 transitional//",
+];
+
+/// The parts of a doctype that decide the compatibility mode.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Doctype<'d> {
+    name: &'d str,
+    public_id: Option<&'d str>,
+    system_id: Option<&'d str>,
+}
+
+impl<'d> Doctype<'d> {
+    /// Splits a doctype token, which holds the lowercase name followed by
+    /// any `PUBLIC` or `SYSTEM` keyword and quoted identifiers as written.
+    fn parse(doctype: &'d str) -> Self {
+        let (name, rest) = doctype.split_once(' ').unwrap_or((doctype, ""));
+        let rest = rest.trim_start();
+        let mut doctype = Self {
+            name,
+            ..Self::default()
+        };
+        let keyword_length = "public".len();
+        let Some((keyword, rest)) = rest
+            .is_char_boundary(keyword_length)
+            .then(|| rest.split_at(keyword_length))
+        else {
+            return doctype;
+        };
+        let mut identifiers = std::iter::from_fn({
+            let mut rest = rest;
+            move || {
+                rest = rest.trim_start();
+                let quote = rest.chars().next().filter(|c| matches!(c, '"' | '\''))?;
+                let (identifier, after) = rest
+                    .get(1..)?
+                    .split_once(quote)
+                    .unwrap_or((rest.get(1..)?, ""));
+                rest = after;
+                Some(identifier)
+            }
+        });
+        if keyword.eq_ignore_ascii_case("public") {
+            doctype.public_id = identifiers.next();
+            doctype.system_id = identifiers.next();
+        } else if keyword.eq_ignore_ascii_case("system") {
+            doctype.system_id = identifiers.next();
+        }
+        doctype
+    }
+}
+
+/// The compatibility mode a document with the doctype token `doctype`
+/// is in.
+pub(super) fn compat_mode_of(doctype: &str) -> CompatMode {
+    let doctype = Doctype::parse(doctype);
+    let public_id = doctype.public_id.map(str::to_ascii_lowercase);
+    let public_id = public_id.as_deref().unwrap_or_default();
+    let system_id = doctype.system_id.map(str::to_ascii_lowercase);
+    let starts_with_any =
+        |prefixes: &[&str]| prefixes.iter().any(|prefix| public_id.starts_with(prefix));
+
+    if doctype.name != "html"
+        || matches!(
+            public_id,
+            "-//w3o//dtd w3 html strict 3.0//en//" | "-/w3c/dtd html 4.0 transitional/en" | "html"
+        )
+        || system_id.as_deref()
+            == Some("http://www.ibm.com/data/dtd/v11/ibmxhtml1-transitional.dtd")
+        || starts_with_any(&QUIRKS_PUBLIC_ID_PREFIXES)
+        || (system_id.is_none() && starts_with_any(&FRAMESET_PUBLIC_ID_PREFIXES))
+    {
+        CompatMode::Quirks
+    } else if starts_with_any(&LIMITED_QUIRKS_PUBLIC_ID_PREFIXES)
+        || starts_with_any(&FRAMESET_PUBLIC_ID_PREFIXES)
+    {
+        CompatMode::LimitedQuirks
+    } else {
+        CompatMode::NoQuirks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dom::Document;
+
+    #[test]
+    fn test_compat_mode_from_doctype() {
+        for (html, expected) in [
+            ("<!DOCTYPE html>", CompatMode::NoQuirks),
+            (
+                "<!DOCTYPE html SYSTEM \"about:legacy-compat\">",
+                CompatMode::NoQuirks,
+            ),
+            (
+                "<!DOCTYPE HTML PUBLIC \"-//W3C//DTD HTML 4.01//EN\" \
+                 \"http://www.w3.org/TR/html4/strict.dtd\">",
+                CompatMode::NoQuirks,
+            ),
+            (
+                "<!DOCTYPE html PUBLIC \"-//W3C//DTD XHTML 1.0 Transitional//EN\" \
+                 \"http://www.w3.org/TR/xhtml1/DTD/xhtml1-transitional.dtd\">",
+                CompatMode::LimitedQuirks,
+            ),
+            (
+                "<!DOCTYPE HTML PUBLIC '-//W3C//DTD HTML 4.01 Transitional//EN' \
+                 'http://www.w3.org/TR/html4/loose.dtd'>",
+                CompatMode::LimitedQuirks,
+            ),
+            (
+                "<!DOCTYPE HTML PUBLIC \"-//W3C//DTD HTML 4.01 Transitional//EN\">",
+                CompatMode::Quirks,
+            ),
+            (
+                "<!DOCTYPE HTML PUBLIC \"-//W3C//DTD HTML 3.2 Final//EN\">",
+                CompatMode::Quirks,
+            ),
+            ("<!DOCTYPE svg>", CompatMode::Quirks),
+            ("<!DOCTYPE>", CompatMode::Quirks),
+            ("<p>No doctype", CompatMode::Quirks),
+            ("", CompatMode::Quirks),
+        ] {
+            assert_eq!(Document::parse(html).compat_mode(), expected, "{html}");
+        }
+        assert_eq!(Document::new().compat_mode(), CompatMode::NoQuirks);
+    }
+}
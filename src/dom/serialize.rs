@@ -0,0 +1,116 @@
+use super::{Document, Namespace, Node, NodeId, Visit, Visitor};
+use crate::html_tokenizer::{HtmlToken, is_void_element, serialize_tokens};
+
+/// Collects the tokens that reproduce a subtree, so that it is written out
+/// by the same escaping rules as a token stream.
+#[derive(Debug, Default)]
+struct TokenCollector(Vec<HtmlToken>);
+
+impl Visitor for TokenCollector {
+    fn enter(&mut self, document: &Document, id: NodeId) -> Visit {
+        let token = match document.node(id) {
+            Node::Document | Node::DocumentFragment => return Visit::Continue,
+            Node::Doctype(doctype) => HtmlToken::Doctype(doctype.clone()),
+            Node::Element(element) => HtmlToken::StartTag {
+                name: element.name.clone(),
+                attributes: element.attributes.clone(),
+                self_closing: false,
+            },
+            Node::Text(text) => HtmlToken::Text(text.clone()),
+            Node::Comment(comment) => HtmlToken::Comment(comment.clone()),
+        };
+        self.0.push(token);
+        if let Some(content) = document.template_content(id) {
+            for child in document.children(content) {
+                document.walk(child, self);
+            }
+        }
+        Visit::Continue
+    }
+
+    fn exit(&mut self, document: &Document, id: NodeId) {
+        if let Some(element) = document.element(id)
+            && !(element.namespace == Namespace::Html && is_void_element(&element.name))
+        {
+            self.0.push(HtmlToken::EndTag {
+                name: element.name.clone(),
+            });
+        }
+    }
+}
+
+impl Document {
+    /// Serializes the node `id` and its descendants as HTML, with text and
+    /// attribute values escaped and no end tags for void elements. For the
+    /// root, this is the whole document.
+    pub fn outer_html(&self, id: NodeId) -> String {
+        let mut collector = TokenCollector::default();
+        self.walk(id, &mut collector);
+        serialize_tokens(&collector.0)
+    }
+
+    /// Serializes the descendants of `id` as HTML; see
+    /// [`outer_html`](Self::outer_html).
+    pub fn inner_html(&self, id: NodeId) -> String {
+        let mut collector = TokenCollector::default();
+        for child in self.children(id) {
+            self.walk(child, &mut collector);
+        }
+        serialize_tokens(&collector.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dom::Document;
+
+    #[test]
+    fn test_outer_and_inner_html() {
+        let document = Document::parse("<div id=a class='x y'>a<br>b<img src=i.png alt></div>");
+        let div = document.get_element_by_id("a").unwrap();
+        assert_eq!(
+            document.outer_html(div),
+            "<div id=\"a\" class=\"x y\">a<br>b<img src=\"i.png\" alt></div>"
+        );
+        assert_eq!(document.inner_html(div), "a<br>b<img src=\"i.png\" alt>");
+    }
+
+    #[test]
+    fn test_template_content_is_serialized() {
+        let html = "<template><p>a</p></template>";
+        let document = Document::parse(html);
+        let head = document.head().unwrap();
+        assert_eq!(document.inner_html(head), html);
+    }
+
+    #[test]
+    fn test_serialization_escapes_text_and_attributes() {
+        let document = Document::parse(
+            "<p title='say \"hi\" &amp; <bye>'>1 &lt; 2 &amp;&nbsp;3</p>\
+             <script>if (a < b && c) {}</script>",
+        );
+        let body = document.body().unwrap();
+        assert_eq!(
+            document.inner_html(body),
+            "<p title=\"say &quot;hi&quot; &amp; <bye>\">1 &lt; 2 &amp;&nbsp;3</p>\
+             <script>if (a < b && c) {}</script>"
+        );
+    }
+
+    #[test]
+    fn test_serialized_document_parses_to_the_same_tree() {
+        let html = "<!DOCTYPE html><html lang=en><head><title>A &amp; B</title></head>\
+            <body><!-- c --><ul><li>1<li>2</ul><svg><circle r=1></circle></svg>\
+            <p>x<textarea>&lt;/textarea&gt;</textarea></body></html>";
+        let document = Document::parse(html);
+        let serialized = document.outer_html(document.root());
+        assert_eq!(
+            serialized,
+            "<!DOCTYPE html><html lang=\"en\"><head><title>A &amp; B</title></head>\
+             <body><!-- c --><ul><li>1</li><li>2</li></ul><svg><circle r=\"1\"></circle></svg>\
+             <p>x<textarea>&lt;/textarea&gt;</textarea></p></body></html>"
+        );
+        let reparsed = Document::parse(&serialized);
+        assert_eq!(reparsed.outer_html(reparsed.root()), serialized);
+    }
+}
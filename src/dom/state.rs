@@ -0,0 +1,195 @@
+use std::collections::HashSet;
+
+use super::{Document, NodeId};
+
+/// How the user is interacting with the elements of a document, which the
+/// dynamic pseudo-classes such as `:hover` and `:visited` match against.
+/// The page does not change it; the app does, as the pointer moves and as
+/// pages are visited.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(super) struct ElementStates {
+    hovered: Option<NodeId>,
+    active: Option<NodeId>,
+    focused: Option<NodeId>,
+
+    /// The absolute URLs of the pages the user has visited.
+    visited: HashSet<String>,
+}
+
+impl Document {
+    /// The element under the pointer, if any.
+    pub fn hovered(&self) -> Option<NodeId> {
+        self.states.hovered
+    }
+
+    /// Makes `id` the element under the pointer. Returns the elements
+    /// whose `:hover` state changed, which are those whose styles, and
+    /// those of the elements after them, must be computed again; see
+    /// [`Cascade::restyle`](crate::style::Cascade::restyle).
+    pub fn set_hovered(&mut self, id: Option<NodeId>) -> Vec<NodeId> {
+        let previous = std::mem::replace(&mut self.states.hovered, id);
+        self.chain_changes(previous, id)
+    }
+
+    /// The element being pressed, if any.
+    pub fn active(&self) -> Option<NodeId> {
+        self.states.active
+    }
+
+    /// Makes `id` the element being pressed. Returns the elements whose
+    /// `:active` state changed.
+    pub fn set_active(&mut self, id: Option<NodeId>) -> Vec<NodeId> {
+        let previous = std::mem::replace(&mut self.states.active, id);
+        self.chain_changes(previous, id)
+    }
+
+    /// The element that has the keyboard focus, if any.
+    pub fn focused(&self) -> Option<NodeId> {
+        self.states.focused
+    }
+
+    /// Gives `id` the keyboard focus. Returns the elements whose `:focus`
+    /// or `:focus-within` state changed.
+    pub fn set_focused(&mut self, id: Option<NodeId>) -> Vec<NodeId> {
+        let previous = std::mem::replace(&mut self.states.focused, id);
+        self.chain_changes(previous, id)
+    }
+
+    /// Records that the user has visited the absolute URLs `urls`.
+    /// Returns the links to those that were not known yet, whose
+    /// `:visited` state changed.
+    pub fn add_visited_urls(&mut self, urls: impl IntoIterator<Item = String>) -> Vec<NodeId> {
+        let added: HashSet<String> = urls
+            .into_iter()
+            .filter(|url| self.states.visited.insert(url.clone()))
+            .collect();
+        if added.is_empty() {
+            return Vec::new();
+        }
+        self.descendants(self.root())
+            .filter(|&id| self.link_url(id).is_some_and(|url| added.contains(&url)))
+            .collect()
+    }
+
+    /// Whether `id` matches `:hover`: it is the hovered element or one of
+    /// its ancestors.
+    pub fn is_hovered(&self, id: NodeId) -> bool {
+        self.is_in_chain(self.states.hovered, id)
+    }
+
+    /// Whether `id` matches `:active`: it is the element being pressed or
+    /// one of its ancestors.
+    pub fn is_active(&self, id: NodeId) -> bool {
+        self.is_in_chain(self.states.active, id)
+    }
+
+    /// Whether `id` matches `:focus`.
+    pub fn is_focused(&self, id: NodeId) -> bool {
+        self.states.focused == Some(id)
+    }
+
+    /// Whether `id` matches `:focus-within`: it or one of its descendants
+    /// has the focus.
+    pub fn has_focus_within(&self, id: NodeId) -> bool {
+        self.is_in_chain(self.states.focused, id)
+    }
+
+    /// Whether `id` is a link, an `<a>` or `<area>` with an `href`, to a
+    /// URL the user has visited. Links that are not to a visited URL match
+    /// `:link` instead.
+    pub fn is_visited_link(&self, id: NodeId) -> bool {
+        self.link_url(id)
+            .is_some_and(|url| self.states.visited.contains(&url))
+    }
+
+    /// Whether `id` is an `<a>` or `<area>` with an `href`, which is what
+    /// `:any-link` matches.
+    pub fn is_link(&self, id: NodeId) -> bool {
+        self.element(id).is_some_and(|element| {
+            (element.is("a") || element.is("area")) && element.get_attribute("href").is_some()
+        })
+    }
+
+    /// The resolved URL of the link `id`, or `None` if it is not a link or
+    /// its URL cannot be resolved.
+    pub fn link_url(&self, id: NodeId) -> Option<String> {
+        if !self.is_link(id) {
+            return None;
+        }
+        self.resolve_url(self.element(id)?.get_attribute("href")?)
+    }
+
+    /// Whether `id` is `target` or one of its ancestors.
+    fn is_in_chain(&self, target: Option<NodeId>, id: NodeId) -> bool {
+        target.is_some_and(|target| target == id || self.ancestors(target).any(|a| a == id))
+    }
+
+    /// The elements in the chain of one of `previous` and `current`, each
+    /// with its ancestors, but not in both.
+    fn chain_changes(&self, previous: Option<NodeId>, current: Option<NodeId>) -> Vec<NodeId> {
+        let chain = |target: Option<NodeId>| -> Vec<NodeId> {
+            target
+                .into_iter()
+                .flat_map(|target| std::iter::once(target).chain(self.ancestors(target)))
+                .filter(|&id| self.element(id).is_some())
+                .collect()
+        };
+        let (previous, current) = (chain(previous), chain(current));
+        previous
+            .iter()
+            .filter(|id| !current.contains(id))
+            .chain(current.iter().filter(|id| !previous.contains(id)))
+            .copied()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dom::Document;
+
+    #[test]
+    fn test_hover_chain_changes() {
+        let mut document =
+            Document::parse("<div id=a><p id=b><em id=c>c</em></p><p id=d>d</p></div>");
+        let id = |name: &str| document.get_element_by_id(name).unwrap();
+        let (a, b, c, d) = (id("a"), id("b"), id("c"), id("d"));
+        let body = document.body().unwrap();
+
+        let changed = document.set_hovered(Some(c));
+        assert!(changed.contains(&c) && changed.contains(&b) && changed.contains(&a));
+        assert!(changed.contains(&body));
+        assert!(document.is_hovered(a) && document.is_hovered(c));
+        assert!(!document.is_hovered(d));
+
+        let mut changed = document.set_hovered(Some(d));
+        changed.sort_unstable();
+        let mut expected = vec![b, c, d];
+        expected.sort_unstable();
+        assert_eq!(changed, expected);
+        assert_eq!(document.set_hovered(Some(d)), Vec::new());
+
+        document.set_focused(Some(c));
+        assert!(document.is_focused(c) && !document.is_focused(b));
+        assert!(document.has_focus_within(a));
+    }
+
+    #[test]
+    fn test_visited_links() {
+        let mut document =
+            Document::parse("<a id=a href=/x>x</a><a id=b href=y>y</a><a id=c>c</a>");
+        document.set_url("https://example.com/".to_owned());
+        let id = |name: &str| document.get_element_by_id(name).unwrap();
+        let (a, b, c) = (id("a"), id("b"), id("c"));
+        assert!(document.is_link(a) && !document.is_link(c));
+        assert!(!document.is_visited_link(a));
+
+        let changed = document.add_visited_urls(["https://example.com/x".to_owned()]);
+        assert_eq!(changed, vec![a]);
+        assert!(document.is_visited_link(a) && !document.is_visited_link(b));
+        assert_eq!(
+            document.add_visited_urls(["https://example.com/x".to_owned()]),
+            Vec::new()
+        );
+    }
+}
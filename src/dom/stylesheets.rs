@@ -0,0 +1,137 @@
+use super::{Document, DocumentPosition, NodeId};
+use crate::css::{ComponentValue, Stylesheet};
+
+/// A style sheet the document brings with it; see
+/// [`Document::stylesheets`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocumentStylesheet {
+    /// The `<style>` or `<link rel=stylesheet>` element the sheet comes
+    /// from.
+    pub owner: NodeId,
+
+    /// The `media` attribute of the owner, such as `print` or
+    /// `(max-width: 600px)`, which decides when the sheet applies.
+    pub media: Option<String>,
+    pub sheet: Stylesheet,
+
+    /// The sheets the `@import` rules of `sheet` brought in, once they
+    /// are fetched. Their rules come before those of `sheet`.
+    pub imports: Vec<ImportedStylesheet>,
+}
+
+/// A style sheet fetched for an `@import` rule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedStylesheet {
+    /// The absolute URL the sheet was fetched from.
+    pub url: String,
+
+    /// The media queries of the `@import` rule, empty if it has none.
+    pub media: Vec<ComponentValue>,
+    pub sheet: Stylesheet,
+
+    /// The sheets this one imports in turn.
+    pub imports: Vec<Self>,
+}
+
+impl Document {
+    /// The style sheets of the `<style>` elements in the document, and of
+    /// the `<link rel=stylesheet>` elements whose sheet has been fetched,
+    /// in document order, whatever their `media`.
+    pub fn stylesheets(&self) -> &[DocumentStylesheet] {
+        &self.stylesheets
+    }
+
+    /// Adds `sheet`, fetched for the `<link rel=stylesheet>` element
+    /// `owner`, among the style sheets of the document. Sheets arrive in
+    /// any order, but are kept in the order of their owners, which is the
+    /// order the cascade needs.
+    pub fn add_linked_stylesheet(&mut self, owner: NodeId, sheet: Stylesheet) {
+        self.insert_stylesheet(owner, sheet);
+    }
+
+    /// Sets the sheets the `@import` rules of the style sheet of `owner`
+    /// brought in. Does nothing if `owner` has no style sheet.
+    pub fn set_stylesheet_imports(&mut self, owner: NodeId, imports: Vec<ImportedStylesheet>) {
+        if let Some(stylesheet) = self
+            .stylesheets
+            .iter_mut()
+            .find(|stylesheet| stylesheet.owner == owner)
+        {
+            stylesheet.imports = imports;
+        }
+    }
+
+    /// Parses the text of the `<style>` element `id` into a style sheet of
+    /// the document, once the element is complete. Styles in a template, or
+    /// in foreign content such as `<svg>`, are left alone.
+    pub(super) fn add_style_element(&mut self, id: NodeId) {
+        if !self.element(id).is_some_and(|element| element.is("style")) || !self.is_connected(id) {
+            return;
+        }
+        let sheet = Stylesheet::parse(&self.text_content(id));
+        self.insert_stylesheet(id, sheet);
+    }
+
+    fn insert_stylesheet(&mut self, owner: NodeId, sheet: Stylesheet) {
+        let media = self
+            .element(owner)
+            .and_then(|element| element.get_attribute("media"))
+            .map(str::to_owned);
+        let index = self
+            .stylesheets
+            .iter()
+            .position(|stylesheet| {
+                self.compare_document_position(owner, stylesheet.owner)
+                    == DocumentPosition::Following
+            })
+            .unwrap_or(self.stylesheets.len());
+        self.stylesheets.insert(
+            index,
+            DocumentStylesheet {
+                owner,
+                media,
+                sheet,
+                imports: Vec::new(),
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::css::Stylesheet;
+    use crate::dom::{Document, NodeId};
+
+    #[test]
+    fn test_style_elements() {
+        let document = Document::parse(
+            "<style>p > a { color: red }</style><style media=print>p {}</style>\
+             <p><style media='only screen, print'>a {}</style>\
+             <template><style>b {}</style></template><svg><style>c {}</style></svg>",
+        );
+        let sheets = document.stylesheets();
+        assert_eq!(sheets.len(), 3);
+        assert_eq!(sheets[0].sheet.style_rules().count(), 1);
+        assert_eq!(sheets[0].media, None);
+        assert_eq!(sheets[1].media.as_deref(), Some("print"));
+        assert_eq!(
+            document.parent(sheets[2].owner),
+            document.get_elements_by_tag_name("p").first().copied()
+        );
+    }
+
+    #[test]
+    fn test_linked_stylesheets() {
+        let mut document = Document::parse(
+            "<link rel=stylesheet href=a.css media=print><style>p {}</style>\
+             <link rel=stylesheet href=b.css>",
+        );
+        let links = document.get_elements_by_tag_name("link");
+        document.add_linked_stylesheet(links[1], Stylesheet::parse("b {}"));
+        document.add_linked_stylesheet(links[0], Stylesheet::parse("a {}"));
+        let owners: Vec<NodeId> = document.stylesheets().iter().map(|s| s.owner).collect();
+        let style = document.get_elements_by_tag_name("style")[0];
+        assert_eq!(owners, vec![links[0], style, links[1]]);
+        assert_eq!(document.stylesheets()[0].media.as_deref(), Some("print"));
+    }
+}
@@ -0,0 +1,324 @@
+use super::{Document, NodeId};
+
+/// The most columns a cell can span, as in the spec.
+const MAX_COLUMN_SPAN: usize = 1000;
+
+/// The most rows a cell can span, as in the spec.
+const MAX_ROW_SPAN: usize = 65534;
+
+/// A `<td>` or `<th>` placed on the grid of a [`TableModel`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableCell {
+    pub element: NodeId,
+
+    /// Whether this is a `<th>`.
+    pub header: bool,
+
+    /// The row and column of the cell's top left slot.
+    pub row: usize,
+    pub column: usize,
+    pub row_span: usize,
+    pub column_span: usize,
+
+    /// The `<th>` elements that label this cell; see [`TableModel`].
+    pub headers: Vec<NodeId>,
+}
+
+/// The grid a `<table>` lays its cells out on, following the table
+/// processing model
+/// (<https://html.spec.whatwg.org/multipage/tables.html#forming-a-table>).
+///
+/// Rows come from `<tr>` elements directly in the table or in one of its
+/// `<thead>`, `<tbody>` and `<tfoot>` row groups, in document order. Cells
+/// that span several rows or columns cover every slot they reach, and a
+/// `rowspan` of 0, or one that runs past the end of the row group, ends
+/// with the row group.
+///
+/// A cell's headers are the ones its `headers` attribute names. Without
+/// one, they are the nearest run of `<th>` cells to its left and the
+/// nearest run above it, skipping those whose `scope` points the other
+/// way. This is a simplification of the spec's algorithm, which also
+/// handles row and column groups.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableModel {
+    pub element: NodeId,
+
+    /// The first `<caption>` of the table.
+    pub caption: Option<NodeId>,
+
+    /// Every cell, in the order its element appears in the document.
+    pub cells: Vec<TableCell>,
+
+    /// For each row, the index in `cells` of the cell covering each of its
+    /// slots, if any.
+    slots: Vec<Vec<Option<usize>>>,
+    column_count: usize,
+}
+
+impl TableModel {
+    /// The number of rows, including those only spanned into.
+    pub fn row_count(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// The number of columns, which is that of the widest row.
+    pub fn column_count(&self) -> usize {
+        self.column_count
+    }
+
+    /// The cell covering the slot at `row` and `column`, which may start in
+    /// an earlier row or column.
+    pub fn cell_at(&self, row: usize, column: usize) -> Option<&TableCell> {
+        let index = (*self.slots.get(row)?.get(column)?)?;
+        self.cells.get(index)
+    }
+
+    /// The cells that start in `row`, left to right.
+    pub fn row(&self, row: usize) -> impl Iterator<Item = &TableCell> + '_ {
+        self.cells.iter().filter(move |cell| cell.row == row)
+    }
+
+    fn place(&mut self, cell: TableCell) {
+        let index = self.cells.len();
+        let rows = cell.row..cell.row + cell.row_span;
+        let columns = cell.column..cell.column + cell.column_span;
+        self.column_count = self.column_count.max(columns.end);
+        if self.slots.len() < rows.end {
+            self.slots.resize_with(rows.end, Vec::new);
+        }
+        for row in self.slots.iter_mut().skip(rows.start).take(rows.len()) {
+            if row.len() < columns.end {
+                row.resize(columns.end, None);
+            }
+            for slot in row.iter_mut().skip(columns.start).take(columns.len()) {
+                // Where cells overlap, the slot keeps the first one.
+                slot.get_or_insert(index);
+            }
+        }
+        self.cells.push(cell);
+    }
+
+    fn is_covered(&self, row: usize, column: usize) -> bool {
+        self.slots
+            .get(row)
+            .and_then(|slots| slots.get(column))
+            .is_some_and(Option::is_some)
+    }
+
+    /// Fills in the `headers` of every cell once all are placed.
+    fn assign_headers(&mut self, document: &Document) {
+        let headers: Vec<Vec<NodeId>> = (0..self.cells.len())
+            .map(|index| self.headers_of(document, index))
+            .collect();
+        for (cell, headers) in self.cells.iter_mut().zip(headers) {
+            cell.headers = headers;
+        }
+    }
+
+    fn headers_of(&self, document: &Document, index: usize) -> Vec<NodeId> {
+        let Some(cell) = self.cells.get(index) else {
+            return Vec::new();
+        };
+        let scope = |cell: &TableCell| {
+            document
+                .element(cell.element)
+                .and_then(|element| element.get_attribute("scope"))
+                .map(str::to_ascii_lowercase)
+        };
+
+        if let Some(ids) = document
+            .element(cell.element)
+            .and_then(|element| element.get_attribute("headers"))
+        {
+            return ids
+                .split_ascii_whitespace()
+                .filter_map(|id| {
+                    self.cells
+                        .iter()
+                        .find(|other| {
+                            other.header
+                                && other.element != cell.element
+                                && document
+                                    .element(other.element)
+                                    .and_then(|element| element.get_attribute("id"))
+                                    == Some(id)
+                        })
+                        .map(|other| other.element)
+                })
+                .collect();
+        }
+
+        let left = (0..cell.column)
+            .rev()
+            .map(|column| self.cell_at(cell.row, column));
+        let above = (0..cell.row)
+            .rev()
+            .map(|row| self.cell_at(row, cell.column));
+        let mut headers = Vec::new();
+        for (slots, other_axis) in [(left.collect::<Vec<_>>(), "col"), (above.collect(), "row")] {
+            let mut seen_header = false;
+            for other in slots.into_iter().flatten() {
+                if other.element == cell.element {
+                    continue;
+                }
+                if !other.header {
+                    if seen_header {
+                        break;
+                    }
+                    continue;
+                }
+                seen_header = true;
+                let scope = scope(other);
+                if scope
+                    .as_deref()
+                    .is_some_and(|scope| scope.starts_with(other_axis))
+                {
+                    continue;
+                }
+                if !headers.contains(&other.element) {
+                    headers.push(other.element);
+                }
+            }
+        }
+        headers
+    }
+}
+
+impl Document {
+    /// The grid model of the `<table>` element `id`, or `None` if `id` is
+    /// not a table.
+    pub fn table(&self, id: NodeId) -> Option<TableModel> {
+        if !self.element(id)?.is("table") {
+            return None;
+        }
+        let mut table = TableModel {
+            element: id,
+            caption: self.child_element(id, "caption"),
+            cells: Vec::new(),
+            slots: Vec::new(),
+            column_count: 0,
+        };
+
+        // Consecutive `<tr>` children of the table form an implied row group.
+        let mut implied_group = Vec::new();
+        for child in self.children(id) {
+            let Some(element) = self.element(child) else {
+                continue;
+            };
+            if element.is("tr") {
+                implied_group.push(child);
+                continue;
+            }
+            self.add_row_group(&mut table, std::mem::take(&mut implied_group));
+            if element.is("thead") || element.is("tbody") || element.is("tfoot") {
+                let rows = self
+                    .children(child)
+                    .filter(|&row| self.element(row).is_some_and(|e| e.is("tr")))
+                    .collect();
+                self.add_row_group(&mut table, rows);
+            }
+        }
+        self.add_row_group(&mut table, implied_group);
+
+        table.assign_headers(self);
+        Some(table)
+    }
+
+    fn add_row_group(&self, table: &mut TableModel, rows: Vec<NodeId>) {
+        let start = table.row_count();
+        let end = start + rows.len();
+        for (y, row) in (start..).zip(rows) {
+            let mut x = 0;
+            for child in self.children(row) {
+                let Some(element) = self.element(child) else {
+                    continue;
+                };
+                let header = element.is("th");
+                if !header && !element.is("td") {
+                    continue;
+                }
+                while table.is_covered(y, x) {
+                    x += 1;
+                }
+                let span = |name, max| {
+                    element
+                        .get_attribute(name)
+                        .and_then(|span| span.trim().parse::<usize>().ok())
+                        .map(|span: usize| span.min(max))
+                };
+                let column_span = span("colspan", MAX_COLUMN_SPAN).unwrap_or(1).max(1);
+                let row_span = match span("rowspan", MAX_ROW_SPAN).unwrap_or(1) {
+                    0 => end - y,
+                    row_span => row_span.min(end - y),
+                };
+                table.place(TableCell {
+                    element: child,
+                    header,
+                    row: y,
+                    column: x,
+                    row_span,
+                    column_span,
+                    headers: Vec::new(),
+                });
+                x += column_span;
+            }
+            // A row without cells still takes up a row of the grid.
+            if table.slots.len() <= y {
+                table.slots.resize_with(y + 1, Vec::new);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spans_cover_their_slots() {
+        let document = Document::parse(
+            "<table id=t><caption>Spans</caption>\
+             <tr><td id=a rowspan=2>a<td id=b colspan=2>b\
+             <tr><td id=c>c<td id=d rowspan=0>d\
+             <tr><td id=e>e</table>",
+        );
+        let id = |name| document.get_element_by_id(name).unwrap();
+        let table = document.table(id("t")).unwrap();
+        assert!(table.caption.is_some());
+        assert_eq!((table.row_count(), table.column_count()), (3, 3));
+
+        let at = |row, column| table.cell_at(row, column).map(|cell| cell.element);
+        assert_eq!(at(0, 0), Some(id("a")));
+        assert_eq!(at(0, 2), Some(id("b")));
+        assert_eq!(at(1, 0), Some(id("a")));
+        assert_eq!(at(1, 1), Some(id("c")));
+        assert_eq!(at(2, 2), Some(id("d")));
+        assert_eq!(at(2, 0), Some(id("e")));
+        assert_eq!(at(2, 1), None);
+        assert_eq!(table.row(1).count(), 2);
+        assert_eq!(document.table(document.body().unwrap()), None);
+    }
+
+    #[test]
+    fn test_headers_are_associated_with_cells() {
+        let document = Document::parse(
+            "<table id=t>\
+             <thead><tr><td><th id=q1>Q1<th id=q2>Q2</thead>\
+             <tbody><tr><th id=apples>Apples<td id=x>1<td id=y headers=q1>2</tbody></table>",
+        );
+        let id = |name| document.get_element_by_id(name).unwrap();
+        let table = document.table(id("t")).unwrap();
+        let headers = |name| {
+            table
+                .cells
+                .iter()
+                .find(|cell| cell.element == id(name))
+                .unwrap()
+                .headers
+                .clone()
+        };
+        assert_eq!(headers("x"), vec![id("apples"), id("q1")]);
+        assert_eq!(headers("y"), vec![id("q1")]);
+        assert_eq!(headers("apples"), Vec::<NodeId>::new());
+    }
+}
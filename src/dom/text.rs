@@ -0,0 +1,212 @@
+use super::tree_builder::is_html_whitespace;
+use super::{Document, Namespace, Node, NodeId, Visit, Visitor};
+
+/// Elements whose content a browser does not render.
+pub(super) const HIDDEN_ELEMENTS: [&str; 8] = [
+    "head", "iframe", "noembed", "noframes", "script", "style", "template", "title",
+];
+
+/// Elements that start on a new line and end one, as the user-agent style
+/// sheet makes them `display: block` (or a list item or table row).
+const BLOCK_ELEMENTS: [&str; 37] = [
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "caption",
+    "dd",
+    "details",
+    "dialog",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hgroup",
+    "hr",
+    "legend",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "summary",
+    "table",
+    "tr",
+    "ul",
+];
+
+/// Collects rendered text while walking the tree, holding back whitespace
+/// and line breaks until the next word shows whether they are needed.
+#[derive(Debug, Default)]
+struct VisibleText {
+    text: String,
+    pending_space: bool,
+    pending_break: bool,
+
+    /// How many `<pre>` and `<textarea>` elements are open, inside which
+    /// whitespace is kept as it is.
+    preformatted: usize,
+}
+
+impl VisibleText {
+    fn push_word(&mut self, word: &str) {
+        if !self.text.is_empty() && !self.text.ends_with('\n') {
+            if self.pending_break {
+                self.text.push('\n');
+            } else if self.pending_space {
+                self.text.push(' ');
+            }
+        }
+        self.pending_space = false;
+        self.pending_break = false;
+        self.text.push_str(word);
+    }
+
+    fn push_text(&mut self, text: &str) {
+        if self.preformatted > 0 {
+            self.push_word(text);
+            return;
+        }
+        self.pending_space |= text.starts_with(is_html_whitespace);
+        let mut words = text
+            .split(is_html_whitespace)
+            .filter(|word| !word.is_empty());
+        if let Some(word) = words.next() {
+            self.push_word(word);
+            for word in words {
+                self.pending_space = true;
+                self.push_word(word);
+            }
+            self.pending_space = text.ends_with(is_html_whitespace);
+        }
+    }
+
+    fn push_line_break(&mut self) {
+        if self.pending_break && !self.text.is_empty() && !self.text.ends_with('\n') {
+            self.text.push('\n');
+        }
+        self.text.push('\n');
+        self.pending_space = false;
+        self.pending_break = false;
+    }
+}
+
+impl Visitor for VisibleText {
+    fn enter(&mut self, document: &Document, id: NodeId) -> Visit {
+        let element = match document.node(id) {
+            Node::Text(text) => {
+                self.push_text(text);
+                return Visit::Continue;
+            }
+            Node::Element(element) if element.namespace == Namespace::Html => element,
+            _ => return Visit::Continue,
+        };
+        let name = element.name.as_str();
+        if HIDDEN_ELEMENTS.contains(&name) || element.get_attribute("hidden").is_some() {
+            return Visit::SkipChildren;
+        }
+        match name {
+            "br" => self.push_line_break(),
+            "td" | "th" => self.pending_space = true,
+            "pre" | "textarea" => self.preformatted += 1,
+            _ => {}
+        }
+        if BLOCK_ELEMENTS.contains(&name) {
+            self.pending_break = true;
+        }
+        Visit::Continue
+    }
+
+    fn exit(&mut self, document: &Document, id: NodeId) {
+        let Some(element) = document.element(id) else {
+            return;
+        };
+        if element.namespace != Namespace::Html {
+            return;
+        }
+        if matches!(element.name.as_str(), "pre" | "textarea") {
+            self.preformatted = self.preformatted.saturating_sub(1);
+        }
+        if BLOCK_ELEMENTS.contains(&element.name.as_str()) {
+            self.pending_break = true;
+        }
+    }
+}
+
+impl Document {
+    /// The text of `id` and all its descendants, concatenated as it is in
+    /// the source, like the DOM's `textContent`.
+    pub fn text_content(&self, id: NodeId) -> String {
+        std::iter::once(id)
+            .chain(self.descendants(id))
+            .filter_map(|node| match self.node(node) {
+                Node::Text(text) => Some(text.as_str()),
+                Node::Comment(comment) if node == id => Some(comment.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The text of `id` roughly as a browser would render it, for reader
+    /// mode and text-only dumps.
+    ///
+    /// Hidden content such as `<script>`, `<style>` and `<head>` is left
+    /// out. Runs of whitespace collapse to one space, except inside `<pre>`,
+    /// and block elements such as `<p>` and `<div>` start a new line, as does
+    /// `<br>`.
+    pub fn visible_text(&self, id: NodeId) -> String {
+        let mut text = VisibleText::default();
+        self.walk(id, &mut text);
+        text.text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dom::Document;
+
+    #[test]
+    fn test_text_content_concatenates_descendant_text() {
+        let document = Document::parse("<p id=a>Hello, <b>big</b>\n  world<!--no--></p>");
+        let p = document.get_element_by_id("a").unwrap();
+        assert_eq!(document.text_content(p), "Hello, big\n  world");
+        let comment = document.last_child(p).unwrap();
+        assert_eq!(document.text_content(comment), "no");
+    }
+
+    #[test]
+    fn test_visible_text_collapses_whitespace_and_breaks_blocks() {
+        let document = Document::parse(
+            "<title>T</title><style>p {}</style>\n<h1>  Big\n title </h1>\
+             <p>One <b>two</b>\tthree<br>four</p><script>x()</script>\
+             <ul><li>a</li><li>b <span hidden>c</span></li></ul>\
+             <table><tr><td>1</td><td>2</td></tr></table>",
+        );
+        assert_eq!(
+            document.visible_text(document.root()),
+            "Big title\nOne two three\nfour\na\nb\n1 2"
+        );
+    }
+
+    #[test]
+    fn test_visible_text_keeps_preformatted_whitespace() {
+        let document = Document::parse("<p>a  b</p><pre>x  y\n  z</pre>c");
+        assert_eq!(
+            document.visible_text(document.body().unwrap()),
+            "a b\nx  y\n  z\nc"
+        );
+    }
+}
@@ -0,0 +1,264 @@
+use super::{Document, NodeId};
+
+/// What a [`Visitor`] wants to happen after entering a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Visit {
+    Continue,
+
+    /// Do not visit the node's descendants. The node is still exited.
+    SkipChildren,
+}
+
+/// The children of a node, first to last; see [`Document::children`].
+#[derive(Debug, Clone)]
+pub struct Children<'d> {
+    document: &'d Document,
+    front: Option<NodeId>,
+    back: Option<NodeId>,
+}
+
+impl Iterator for Children<'_> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let next = self.front?;
+        if self.front == self.back {
+            self.front = None;
+            self.back = None;
+        } else {
+            self.front = self.document.next_sibling(next);
+        }
+        Some(next)
+    }
+}
+
+impl DoubleEndedIterator for Children<'_> {
+    fn next_back(&mut self) -> Option<NodeId> {
+        let next = self.back?;
+        if self.front == self.back {
+            self.front = None;
+            self.back = None;
+        } else {
+            self.back = self.document.previous_sibling(next);
+        }
+        Some(next)
+    }
+}
+
+/// Callbacks for [`Document::walk`].
+///
+/// Both hooks have a no-op default, so visitors only implement the ones
+/// they need.
+pub trait Visitor {
+    /// Called for a node before its descendants.
+    fn enter(&mut self, _document: &Document, _id: NodeId) -> Visit {
+        Visit::Continue
+    }
+
+    /// Called for a node after its descendants.
+    fn exit(&mut self, _document: &Document, _id: NodeId) {}
+}
+
+impl Document {
+    /// Walks the subtree rooted at `id` in document order, calling
+    /// `visitor` on the way into and out of every node.
+    pub fn walk(&self, id: NodeId, visitor: &mut impl Visitor) {
+        // Without recursion, so that deeply nested markup cannot overflow
+        // the stack. `true` marks a node whose children have been visited.
+        let mut stack = vec![(id, false)];
+        while let Some((id, exiting)) = stack.pop() {
+            if exiting {
+                visitor.exit(self, id);
+                continue;
+            }
+            stack.push((id, true));
+            if visitor.enter(self, id) == Visit::Continue {
+                stack.extend(self.children(id).rev().map(|child| (child, false)));
+            }
+        }
+    }
+
+    pub fn children(&self, id: NodeId) -> Children<'_> {
+        let data = self.entry(id);
+        Children {
+            document: self,
+            front: data.first_child,
+            back: data.last_child,
+        }
+    }
+
+    pub fn first_child(&self, id: NodeId) -> Option<NodeId> {
+        self.entry(id).first_child
+    }
+
+    pub fn last_child(&self, id: NodeId) -> Option<NodeId> {
+        self.entry(id).last_child
+    }
+
+    pub fn next_sibling(&self, id: NodeId) -> Option<NodeId> {
+        self.entry(id).next_sibling
+    }
+
+    pub fn previous_sibling(&self, id: NodeId) -> Option<NodeId> {
+        self.entry(id).previous_sibling
+    }
+
+    /// The descendants of `id` in document order, not including `id`.
+    pub fn descendants(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        let mut next = self.first_child(id);
+        std::iter::from_fn(move || {
+            let current = next?;
+            next = self
+                .first_child(current)
+                .or_else(|| self.next_in_subtree(id, current));
+            Some(current)
+        })
+    }
+
+    /// The ancestors of `id`, nearest first, ending with the root.
+    pub fn ancestors(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        std::iter::successors(self.parent(id), |&ancestor| self.parent(ancestor))
+    }
+
+    /// The siblings after `id`, nearest first.
+    pub fn next_siblings(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        std::iter::successors(self.next_sibling(id), |&sibling| self.next_sibling(sibling))
+    }
+
+    /// The siblings before `id`, nearest first.
+    pub fn previous_siblings(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        std::iter::successors(self.previous_sibling(id), |&sibling| {
+            self.previous_sibling(sibling)
+        })
+    }
+
+    /// The nodes before `id` in document order that are not its ancestors,
+    /// nearest first.
+    pub fn preceding(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        let ancestors: Vec<NodeId> = self.ancestors(id).collect();
+        std::iter::successors(self.previous_in_document(id), |&node| {
+            self.previous_in_document(node)
+        })
+        .filter(move |node| !ancestors.contains(node))
+    }
+
+    /// The node after the descendants of `node` in document order, as long
+    /// as it is still inside `root`.
+    fn next_in_subtree(&self, root: NodeId, node: NodeId) -> Option<NodeId> {
+        let mut node = node;
+        loop {
+            if let Some(sibling) = self.next_sibling(node) {
+                return Some(sibling);
+            }
+            node = self.parent(node)?;
+            if node == root {
+                return None;
+            }
+        }
+    }
+
+    /// The node just before `id` in document order: the last descendant of
+    /// its previous sibling, the sibling itself, or else its parent.
+    fn previous_in_document(&self, id: NodeId) -> Option<NodeId> {
+        let Some(mut node) = self.previous_sibling(id) else {
+            return self.parent(id);
+        };
+        while let Some(last) = self.last_child(node) {
+            node = last;
+        }
+        Some(node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dom::Node;
+
+    /// Element names, and text as `"text"`, for a list of nodes.
+    fn names(document: &Document, ids: impl IntoIterator<Item = NodeId>) -> Vec<String> {
+        ids.into_iter()
+            .map(|id| match document.node(id) {
+                Node::Element(element) => element.name.clone(),
+                Node::Text(text) => format!("{text:?}"),
+                node => format!("{node:?}"),
+            })
+            .collect()
+    }
+
+    const PAGE: &str = "<div id=a><p id=b>x</p><ul id=c><li>1<li id=d>2</ul></div><hr id=e>";
+
+    #[test]
+    fn test_descendants_and_ancestors() {
+        let document = Document::parse(PAGE);
+        let a = document.get_element_by_id("a").unwrap();
+        assert_eq!(
+            names(&document, document.descendants(a)),
+            vec!["p", "\"x\"", "ul", "li", "\"1\"", "li", "\"2\""]
+        );
+        let d = document.get_element_by_id("d").unwrap();
+        assert_eq!(
+            names(&document, document.ancestors(d)),
+            vec!["ul", "div", "body", "html", "Document"]
+        );
+        assert_eq!(document.ancestors(document.root()).count(), 0);
+    }
+
+    #[test]
+    fn test_siblings() {
+        let document = Document::parse(PAGE);
+        let b = document.get_element_by_id("b").unwrap();
+        let c = document.get_element_by_id("c").unwrap();
+        assert_eq!(document.next_sibling(b), Some(c));
+        assert_eq!(document.previous_sibling(c), Some(b));
+        assert_eq!(document.previous_sibling(b), None);
+        assert_eq!(document.next_siblings(b).collect::<Vec<_>>(), vec![c]);
+        assert_eq!(document.previous_siblings(c).collect::<Vec<_>>(), vec![b]);
+        assert_eq!(document.next_sibling(document.root()), None);
+    }
+
+    #[test]
+    fn test_preceding_skips_ancestors() {
+        let document = Document::parse(PAGE);
+        let d = document.get_element_by_id("d").unwrap();
+        assert_eq!(
+            names(&document, document.preceding(d)),
+            vec!["\"1\"", "li", "\"x\"", "p", "head"]
+        );
+        let e = document.get_element_by_id("e").unwrap();
+        assert_eq!(document.preceding(e).count(), 9);
+    }
+
+    #[test]
+    fn test_walk_enters_and_exits_in_order() {
+        struct Outline(String);
+
+        impl Visitor for Outline {
+            fn enter(&mut self, document: &Document, id: NodeId) -> Visit {
+                let Some(element) = document.element(id) else {
+                    return Visit::Continue;
+                };
+                self.0.push_str(&format!("<{}>", element.name));
+                if element.is("ul") {
+                    Visit::SkipChildren
+                } else {
+                    Visit::Continue
+                }
+            }
+
+            fn exit(&mut self, document: &Document, id: NodeId) {
+                if let Some(element) = document.element(id) {
+                    self.0.push_str(&format!("</{}>", element.name));
+                }
+            }
+        }
+
+        let document = Document::parse(PAGE);
+        let mut outline = Outline(String::new());
+        document.walk(document.body().unwrap(), &mut outline);
+        assert_eq!(
+            outline.0,
+            "<body><div><p></p><ul></ul></div><hr></hr></body>"
+        );
+    }
+}
@@ -0,0 +1,1266 @@
+use super::quirks::compat_mode_of;
+use super::{CompatMode, Document, Element, Namespace, Node, NodeId};
+use crate::html_tokenizer::{HtmlToken, HtmlTokenizer, TokenizeError};
+
+/// Elements that stop the search for a matching open element when an end
+/// tag is seen in the body: `</b>` inside `<div>` does not close a `<b>`
+/// outside it. From the spec's "special" category.
+const SPECIAL_ELEMENTS: [&str; 81] = [
+    "address",
+    "applet",
+    "area",
+    "article",
+    "aside",
+    "base",
+    "basefont",
+    "bgsound",
+    "blockquote",
+    "body",
+    "br",
+    "button",
+    "caption",
+    "center",
+    "col",
+    "colgroup",
+    "dd",
+    "details",
+    "dir",
+    "div",
+    "dl",
+    "dt",
+    "embed",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "frame",
+    "frameset",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "head",
+    "header",
+    "hgroup",
+    "hr",
+    "html",
+    "iframe",
+    "img",
+    "input",
+    "keygen",
+    "li",
+    "link",
+    "listing",
+    "main",
+    "marquee",
+    "menu",
+    "meta",
+    "nav",
+    "noembed",
+    "noframes",
+    "noscript",
+    "object",
+    "ol",
+    "p",
+    "param",
+    "plaintext",
+    "pre",
+    "script",
+    "search",
+    "section",
+    "select",
+    "source",
+    "style",
+    "summary",
+    "table",
+    "tbody",
+    "td",
+    "template",
+    "textarea",
+    "tfoot",
+    "th",
+    "thead",
+    "title",
+    "tr",
+    "track",
+    "ul",
+];
+
+/// Elements whose end tag is implied when a sibling starts or their parent
+/// ends, from the spec's "generate implied end tags" steps.
+const IMPLIED_END_TAG_ELEMENTS: [&str; 10] = [
+    "dd", "dt", "li", "optgroup", "option", "p", "rb", "rp", "rt", "rtc",
+];
+
+/// Start tags that close an open `<p>`, which cannot contain them.
+const CLOSES_P_ELEMENTS: [&str; 38] = [
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "center",
+    "details",
+    "dialog",
+    "dir",
+    "div",
+    "dl",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hgroup",
+    "hr",
+    "listing",
+    "main",
+    "menu",
+    "nav",
+    "ol",
+    "p",
+    "plaintext",
+    "pre",
+    "search",
+    "section",
+    "summary",
+    "table",
+    "ul",
+    "xmp",
+];
+
+const HEADING_ELEMENTS: [&str; 6] = ["h1", "h2", "h3", "h4", "h5", "h6"];
+
+const TABLE_SECTION_ELEMENTS: [&str; 3] = ["tbody", "tfoot", "thead"];
+
+/// Elements that belong in `<head>` even when they appear later.
+const HEAD_ELEMENTS: [&str; 10] = [
+    "base", "basefont", "bgsound", "link", "meta", "noframes", "script", "style", "template",
+    "title",
+];
+
+/// Elements whose text is collected in the text insertion mode when they
+/// appear in `<head>`.
+const HEAD_TEXT_ELEMENTS: [&str; 5] = ["noframes", "noscript", "script", "style", "title"];
+
+/// The subset of the spec's insertion modes
+/// (<https://html.spec.whatwg.org/multipage/parsing.html#the-insertion-mode>)
+/// the builder implements.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InsertionMode {
+    Initial,
+    BeforeHtml,
+    BeforeHead,
+    InHead,
+    AfterHead,
+    InBody,
+    Text,
+    AfterBody,
+    AfterAfterBody,
+}
+
+/// The spec's kinds of element scope
+/// (<https://html.spec.whatwg.org/multipage/parsing.html#has-an-element-in-scope>):
+/// how far out the stack of open elements an element is looked for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Scope {
+    Default,
+    ListItem,
+    Button,
+    Table,
+}
+
+impl Scope {
+    /// Whether the search stops at `element`.
+    fn is_boundary(self, element: &Element) -> bool {
+        let name = element.name.as_str();
+        match element.namespace {
+            Namespace::Html => match self {
+                Self::Table => matches!(name, "html" | "table" | "template"),
+                _ => {
+                    matches!(
+                        name,
+                        "applet"
+                            | "caption"
+                            | "html"
+                            | "table"
+                            | "td"
+                            | "th"
+                            | "marquee"
+                            | "object"
+                            | "template"
+                    ) || (self == Self::ListItem && matches!(name, "ol" | "ul"))
+                        || (self == Self::Button && name == "button")
+                }
+            },
+            Namespace::MathMl => {
+                self != Self::Table
+                    && matches!(name, "mi" | "mo" | "mn" | "ms" | "mtext" | "annotation-xml")
+            }
+            Namespace::Svg => self != Self::Table && is_html_integration_point(element),
+        }
+    }
+}
+
+/// Builds a [`Document`] from tokens.
+///
+/// This follows the tree construction stage of the HTML parsing algorithm
+/// (<https://html.spec.whatwg.org/multipage/parsing.html#tree-construction>),
+/// with its insertion modes and stack of open elements.
+///
+/// End tags that authors usually leave out are implied as the spec does:
+/// a `<p>` is closed by a following `<div>`, an `<li>` by the next `<li>`,
+/// a `<td>` by the next cell, and so on.
+///
+/// The table insertion modes, forms and the adoption agency algorithm for
+/// misnested formatting elements are not handled; their elements are
+/// inserted like any other.
+#[derive(Debug)]
+pub struct TreeBuilder {
+    document: Document,
+    mode: InsertionMode,
+
+    /// The mode to return to when the element the text insertion mode was
+    /// entered for is closed.
+    original_mode: InsertionMode,
+
+    /// Open elements, outermost (`<html>`) first.
+    open_elements: Vec<NodeId>,
+    head: Option<NodeId>,
+
+    /// The mode to return to when each open `<template>` is closed,
+    /// innermost last. Template content is built in the body mode.
+    template_modes: Vec<InsertionMode>,
+}
+
+impl Default for TreeBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TreeBuilder {
+    pub fn new() -> Self {
+        Self {
+            document: Document::new(),
+            mode: InsertionMode::Initial,
+            original_mode: InsertionMode::Initial,
+            open_elements: Vec::new(),
+            head: None,
+            template_modes: Vec::new(),
+        }
+    }
+
+    /// Builds a document from everything `tokenizer` produces.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error the tokenizer runs into. A spec-conformant
+    /// tokenizer never fails; see [`Document::parse`].
+    pub fn build(tokenizer: &HtmlTokenizer<'_>) -> Result<Document, TokenizeError> {
+        let mut builder = Self::new();
+        for token in tokenizer {
+            builder.process_token(token?);
+        }
+        Ok(builder.finish())
+    }
+
+    pub fn process_token(&mut self, token: HtmlToken) {
+        let mut token = token;
+        while let Some(reprocess) = self.step(token) {
+            token = reprocess;
+        }
+    }
+
+    /// Ends the input, creating the `<html>`, `<head>` and `<body>` elements
+    /// if they were never opened.
+    pub fn finish(mut self) -> Document {
+        if let Some(&mode) = self.template_modes.first() {
+            // Close any templates left open.
+            if let Some(index) = self
+                .open_elements
+                .iter()
+                .position(|&id| self.is_template(id))
+            {
+                self.open_elements.truncate(index);
+            }
+            self.template_modes.clear();
+            self.mode = mode;
+        }
+        loop {
+            match self.mode {
+                InsertionMode::Initial => {
+                    self.document.compat_mode = CompatMode::Quirks;
+                    self.mode = InsertionMode::BeforeHtml;
+                }
+                InsertionMode::BeforeHtml => self.insert_html(Vec::new()),
+                InsertionMode::BeforeHead => self.insert_head(Vec::new()),
+                InsertionMode::InHead => self.leave_head(),
+                InsertionMode::AfterHead => self.insert_body(Vec::new()),
+                InsertionMode::Text => {
+                    self.close_text_element();
+                    self.mode = self.original_mode;
+                }
+                InsertionMode::InBody
+                | InsertionMode::AfterBody
+                | InsertionMode::AfterAfterBody => break,
+            }
+        }
+        self.document
+    }
+
+    /// Processes `token` in the current insertion mode. Returns it back if
+    /// it has to be processed again in the mode that was switched to.
+    fn step(&mut self, token: HtmlToken) -> Option<HtmlToken> {
+        if let HtmlToken::ParseError(_) = token {
+            return None;
+        }
+        match self.mode {
+            InsertionMode::Initial => self.initial(token),
+            InsertionMode::BeforeHtml => self.before_html(token),
+            InsertionMode::BeforeHead => self.before_head(token),
+            InsertionMode::InHead => self.in_head(token),
+            InsertionMode::AfterHead => self.after_head(token),
+            InsertionMode::InBody => self.in_body(token),
+            InsertionMode::Text => self.text(token),
+            InsertionMode::AfterBody => self.after_body(token),
+            InsertionMode::AfterAfterBody => self.after_after_body(token),
+        }
+    }
+
+    fn initial(&mut self, token: HtmlToken) -> Option<HtmlToken> {
+        match token {
+            HtmlToken::Text(text) if is_whitespace(&text) => None,
+            HtmlToken::Comment(comment) => {
+                let root = self.document.root();
+                self.document.append(root, Node::Comment(comment));
+                None
+            }
+            HtmlToken::Doctype(doctype) => {
+                self.document.compat_mode = compat_mode_of(&doctype);
+                let root = self.document.root();
+                self.document.append(root, Node::Doctype(doctype));
+                self.mode = InsertionMode::BeforeHtml;
+                None
+            }
+            token => {
+                self.document.compat_mode = CompatMode::Quirks;
+                self.mode = InsertionMode::BeforeHtml;
+                Some(strip_leading_whitespace(token))
+            }
+        }
+    }
+
+    fn before_html(&mut self, token: HtmlToken) -> Option<HtmlToken> {
+        match token {
+            HtmlToken::Doctype(_) => None,
+            HtmlToken::Comment(comment) => {
+                let root = self.document.root();
+                self.document.append(root, Node::Comment(comment));
+                None
+            }
+            HtmlToken::Text(text) if is_whitespace(&text) => None,
+            HtmlToken::StartTag {
+                name, attributes, ..
+            } if name == "html" => {
+                self.insert_html(attributes);
+                None
+            }
+            HtmlToken::EndTag { name }
+                if !matches!(name.as_str(), "head" | "body" | "html" | "br") =>
+            {
+                None
+            }
+            token => {
+                self.insert_html(Vec::new());
+                Some(strip_leading_whitespace(token))
+            }
+        }
+    }
+
+    fn before_head(&mut self, token: HtmlToken) -> Option<HtmlToken> {
+        match token {
+            HtmlToken::Doctype(_) => None,
+            HtmlToken::Comment(comment) => {
+                self.insert_node(Node::Comment(comment));
+                None
+            }
+            HtmlToken::Text(text) if is_whitespace(&text) => None,
+            HtmlToken::StartTag {
+                name, attributes, ..
+            } if name == "html" => {
+                self.merge_into_open_element(0, attributes);
+                None
+            }
+            HtmlToken::StartTag {
+                name, attributes, ..
+            } if name == "head" => {
+                self.insert_head(attributes);
+                None
+            }
+            HtmlToken::EndTag { name }
+                if !matches!(name.as_str(), "head" | "body" | "html" | "br") =>
+            {
+                None
+            }
+            token => {
+                self.insert_head(Vec::new());
+                Some(strip_leading_whitespace(token))
+            }
+        }
+    }
+
+    fn in_head(&mut self, token: HtmlToken) -> Option<HtmlToken> {
+        match token {
+            HtmlToken::Text(text) => {
+                let (whitespace, rest) = split_leading_whitespace(&text);
+                if !whitespace.is_empty() {
+                    self.insert_text(whitespace);
+                }
+                if rest.is_empty() {
+                    return None;
+                }
+                self.leave_head();
+                Some(HtmlToken::Text(rest.to_owned()))
+            }
+            HtmlToken::Comment(comment) => {
+                self.insert_node(Node::Comment(comment));
+                None
+            }
+            HtmlToken::Doctype(_) => None,
+            HtmlToken::StartTag {
+                name, attributes, ..
+            } if name == "html" => {
+                self.merge_into_open_element(0, attributes);
+                None
+            }
+            HtmlToken::StartTag {
+                name, attributes, ..
+            } if HEAD_TEXT_ELEMENTS.contains(&name.as_str()) => {
+                let element = self.insert_node(Node::Element(Element::new(&name, attributes)));
+                self.open_elements.push(element);
+                self.original_mode = self.mode;
+                self.mode = InsertionMode::Text;
+                None
+            }
+            HtmlToken::StartTag {
+                name, attributes, ..
+            } if HEAD_ELEMENTS.contains(&name.as_str()) => {
+                let element = self.insert_node(Node::Element(Element::new(&name, attributes)));
+                if name == "base" {
+                    self.record_base_href(element);
+                }
+                if name == "template" {
+                    self.document.create_template_content(element);
+                    self.open_elements.push(element);
+                    self.template_modes.push(self.mode);
+                    self.mode = InsertionMode::InBody;
+                }
+                None
+            }
+            HtmlToken::StartTag { name, .. } if name == "head" => None,
+            HtmlToken::EndTag { name } if name == "head" => {
+                self.leave_head();
+                None
+            }
+            HtmlToken::EndTag { name } if name == "template" => {
+                self.close_template();
+                None
+            }
+            HtmlToken::EndTag { name } if !matches!(name.as_str(), "body" | "html" | "br") => None,
+            token => {
+                self.leave_head();
+                Some(token)
+            }
+        }
+    }
+
+    fn after_head(&mut self, token: HtmlToken) -> Option<HtmlToken> {
+        match token {
+            HtmlToken::Text(text) => {
+                let (whitespace, rest) = split_leading_whitespace(&text);
+                if !whitespace.is_empty() {
+                    self.insert_text(whitespace);
+                }
+                if rest.is_empty() {
+                    return None;
+                }
+                self.insert_body(Vec::new());
+                Some(HtmlToken::Text(rest.to_owned()))
+            }
+            HtmlToken::Comment(comment) => {
+                self.insert_node(Node::Comment(comment));
+                None
+            }
+            HtmlToken::Doctype(_) => None,
+            HtmlToken::StartTag {
+                name, attributes, ..
+            } if name == "html" => {
+                self.merge_into_open_element(0, attributes);
+                None
+            }
+            HtmlToken::StartTag {
+                name, attributes, ..
+            } if name == "body" => {
+                self.insert_body(attributes);
+                None
+            }
+            token if start_tag_name(&token).is_some_and(|name| HEAD_ELEMENTS.contains(&name)) => {
+                // Late head content still goes into `<head>`.
+                let head = self.head?;
+                self.open_elements.push(head);
+                let reprocess = self.in_head(token);
+                self.open_elements.retain(|&element| element != head);
+                reprocess
+            }
+            HtmlToken::StartTag { name, .. } if name == "head" => None,
+            HtmlToken::EndTag { name } if !matches!(name.as_str(), "body" | "html" | "br") => None,
+            token => {
+                self.insert_body(Vec::new());
+                Some(token)
+            }
+        }
+    }
+
+    fn in_body(&mut self, token: HtmlToken) -> Option<HtmlToken> {
+        match token {
+            HtmlToken::Text(text) => {
+                self.insert_text(&text);
+                None
+            }
+            HtmlToken::Comment(comment) => {
+                self.insert_node(Node::Comment(comment));
+                None
+            }
+            HtmlToken::Doctype(_) | HtmlToken::ParseError(_) => None,
+            HtmlToken::StartTag { name, .. } | HtmlToken::EndTag { name }
+                if !self.template_modes.is_empty() && matches!(name.as_str(), "html" | "body") =>
+            {
+                None
+            }
+            HtmlToken::StartTag {
+                name, attributes, ..
+            } if name == "html" => {
+                self.merge_into_open_element(0, attributes);
+                None
+            }
+            HtmlToken::StartTag {
+                name, attributes, ..
+            } if name == "body" => {
+                self.merge_into_open_element(1, attributes);
+                None
+            }
+            HtmlToken::StartTag { name, .. } if name == "head" => None,
+            token
+                if start_tag_name(&token).is_some_and(|name| HEAD_ELEMENTS.contains(&name))
+                    && !self.in_foreign_content() =>
+            {
+                self.in_head(token)
+            }
+            HtmlToken::StartTag {
+                name,
+                attributes,
+                self_closing,
+            } => {
+                self.insert_start_tag(name, attributes, self_closing);
+                None
+            }
+            HtmlToken::EndTag { name } if name == "body" => {
+                if self.has_open_element("body") {
+                    self.mode = InsertionMode::AfterBody;
+                }
+                None
+            }
+            HtmlToken::EndTag { name } if name == "html" => {
+                if !self.has_open_element("body") {
+                    return None;
+                }
+                self.mode = InsertionMode::AfterBody;
+                Some(HtmlToken::EndTag { name })
+            }
+            HtmlToken::EndTag { name } if name == "template" => {
+                self.close_template();
+                None
+            }
+            HtmlToken::EndTag { name } if name == "br" => {
+                // `</br>` is treated as `<br>`.
+                self.insert_start_tag(name, Vec::new(), false);
+                None
+            }
+            HtmlToken::EndTag { name } => {
+                self.end_tag_in_body(&name);
+                None
+            }
+        }
+    }
+
+    /// The text insertion mode, for the content of `<title>`, `<script>`
+    /// and the like in `<head>`.
+    fn text(&mut self, token: HtmlToken) -> Option<HtmlToken> {
+        self.mode = self.original_mode;
+        match token {
+            HtmlToken::Text(text) => {
+                self.mode = InsertionMode::Text;
+                self.insert_text(&text);
+                None
+            }
+            HtmlToken::EndTag { .. } => {
+                self.close_text_element();
+                None
+            }
+            token => {
+                self.close_text_element();
+                Some(token)
+            }
+        }
+    }
+
+    /// Pops the element whose text the text insertion mode was reading. A
+    /// `<style>` is complete at this point, so its style sheet is added.
+    fn close_text_element(&mut self) {
+        if let Some(id) = self.open_elements.pop() {
+            self.document.add_style_element(id);
+        }
+    }
+
+    fn after_body(&mut self, token: HtmlToken) -> Option<HtmlToken> {
+        match token {
+            token if is_whitespace_text(&token) => self.in_body(token),
+            HtmlToken::Comment(comment) => {
+                if let Some(&html) = self.open_elements.first() {
+                    self.document.append(html, Node::Comment(comment));
+                }
+                None
+            }
+            HtmlToken::Doctype(_) => None,
+            token if start_tag_name(&token) == Some("html") => self.in_body(token),
+            HtmlToken::EndTag { name } if name == "html" => {
+                self.mode = InsertionMode::AfterAfterBody;
+                None
+            }
+            token => {
+                self.mode = InsertionMode::InBody;
+                Some(token)
+            }
+        }
+    }
+
+    fn after_after_body(&mut self, token: HtmlToken) -> Option<HtmlToken> {
+        match token {
+            HtmlToken::Comment(comment) => {
+                let root = self.document.root();
+                self.document.append(root, Node::Comment(comment));
+                None
+            }
+            HtmlToken::Doctype(_) => None,
+            token if is_whitespace_text(&token) => self.in_body(token),
+            token if start_tag_name(&token) == Some("html") => self.in_body(token),
+            token => {
+                self.mode = InsertionMode::InBody;
+                Some(token)
+            }
+        }
+    }
+
+    /// The node new content is appended to.
+    fn current_node(&self) -> NodeId {
+        self.open_elements
+            .last()
+            .copied()
+            .unwrap_or_else(|| self.document.root())
+    }
+
+    fn current_element(&self) -> Option<&Element> {
+        self.document.element(*self.open_elements.last()?)
+    }
+
+    fn in_foreign_content(&self) -> bool {
+        self.current_element()
+            .is_some_and(|element| element.namespace != Namespace::Html)
+    }
+
+    /// Where new content goes: the current node, or its content if it is
+    /// a `<template>`.
+    fn insertion_parent(&self) -> NodeId {
+        let current = self.current_node();
+        self.document.template_content(current).unwrap_or(current)
+    }
+
+    fn insert_node(&mut self, node: Node) -> NodeId {
+        let parent = self.insertion_parent();
+        self.document.append(parent, node)
+    }
+
+    fn insert_text(&mut self, text: &str) {
+        let parent = self.insertion_parent();
+        self.document.append_text(parent, text);
+    }
+
+    fn insert_html(&mut self, attributes: Vec<(String, String)>) {
+        let html = self.insert_node(Node::Element(Element::new("html", attributes)));
+        self.open_elements.push(html);
+        self.mode = InsertionMode::BeforeHead;
+    }
+
+    fn insert_head(&mut self, attributes: Vec<(String, String)>) {
+        let head = self.insert_node(Node::Element(Element::new("head", attributes)));
+        self.open_elements.push(head);
+        self.head = Some(head);
+        self.mode = InsertionMode::InHead;
+    }
+
+    /// Keeps the `href` of the `<base>` element `id` for resolving URLs,
+    /// unless an earlier one already set it. Only the document tree
+    /// counts, not the content of a template.
+    fn record_base_href(&mut self, id: NodeId) {
+        if self.document.base_href.is_some() || !self.document.is_connected(id) {
+            return;
+        }
+        self.document.base_href = self
+            .document
+            .element(id)
+            .and_then(|element| element.get_attribute("href"))
+            .map(str::to_owned);
+    }
+
+    fn leave_head(&mut self) {
+        self.open_elements.pop();
+        self.mode = InsertionMode::AfterHead;
+    }
+
+    fn insert_body(&mut self, attributes: Vec<(String, String)>) {
+        let body = self.insert_node(Node::Element(Element::new("body", attributes)));
+        self.open_elements.push(body);
+        self.mode = InsertionMode::InBody;
+    }
+
+    /// Inserts an element for a start tag in the body, in the namespace of
+    /// the current node unless it starts `<svg>` or `<math>`. An HTML
+    /// element first closes the elements it implicitly ends.
+    fn insert_start_tag(
+        &mut self,
+        name: String,
+        attributes: Vec<(String, String)>,
+        self_closing: bool,
+    ) {
+        let namespace = match name.as_str() {
+            "svg" => Namespace::Svg,
+            "math" => Namespace::MathMl,
+            _ => self
+                .current_element()
+                .filter(|element| !is_html_integration_point(element))
+                .map_or(Namespace::Html, |element| element.namespace),
+        };
+        if namespace == Namespace::Html {
+            self.close_implied_elements(&name);
+        }
+        let element = Element {
+            name,
+            namespace,
+            attributes,
+        };
+        let closes = if namespace == Namespace::Html {
+            crate::html_tokenizer::is_void_element(&element.name)
+        } else {
+            self_closing
+        };
+
+        let id = self.insert_node(Node::Element(element));
+        if !closes {
+            self.open_elements.push(id);
+        }
+    }
+
+    /// Adds the attributes the open element at `index` (`<html>` or
+    /// `<body>`) does not already have, as a repeated `<html>` or `<body>`
+    /// start tag does.
+    fn merge_into_open_element(&mut self, index: usize, attributes: Vec<(String, String)>) {
+        let Some(&id) = self.open_elements.get(index) else {
+            return;
+        };
+        for (name, value) in attributes {
+            self.document.add_attribute(id, name, value);
+        }
+    }
+
+    fn is_template(&self, id: NodeId) -> bool {
+        self.document
+            .element(id)
+            .is_some_and(|element| element.is("template"))
+    }
+
+    /// Closes the innermost open `<template>` and returns to the mode it
+    /// was opened in.
+    fn close_template(&mut self) {
+        let Some(index) = self
+            .open_elements
+            .iter()
+            .rposition(|&id| self.is_template(id))
+        else {
+            return;
+        };
+        self.open_elements.truncate(index);
+        self.mode = self.template_modes.pop().unwrap_or(InsertionMode::InBody);
+    }
+
+    fn has_open_element(&self, name: &str) -> bool {
+        self.open_elements.iter().any(|&id| {
+            self.document
+                .element(id)
+                .is_some_and(|element| element.is(name))
+        })
+    }
+
+    /// Closes the elements a start tag for the HTML element `name` ends
+    /// implicitly, such as an open `<p>` before a `<div>` or the previous
+    /// `<li>` before an `<li>`.
+    fn close_implied_elements(&mut self, name: &str) {
+        match name {
+            "li" => self.close_list_item(&["li"]),
+            "dd" | "dt" => self.close_list_item(&["dd", "dt"]),
+            "option" => self.pop_current_if(&["option"]),
+            "optgroup" => {
+                self.pop_current_if(&["option"]);
+                self.pop_current_if(&["optgroup"]);
+            }
+            "rb" | "rtc" if self.in_scope(&["ruby"], Scope::Default) => {
+                self.generate_implied_end_tags(None);
+            }
+            "rp" | "rt" if self.in_scope(&["ruby"], Scope::Default) => {
+                self.generate_implied_end_tags(Some("rtc"));
+            }
+            "button" => self.close_in_scope(&["button"], Scope::Default),
+            "td" | "th" => self.close_in_scope(&["td", "th"], Scope::Table),
+            "tr" => self.close_in_scope(&["tr"], Scope::Table),
+            _ if TABLE_SECTION_ELEMENTS.contains(&name) => {
+                self.close_in_scope(&TABLE_SECTION_ELEMENTS, Scope::Table);
+            }
+            _ if CLOSES_P_ELEMENTS.contains(&name) => {
+                self.close_p_element();
+                if HEADING_ELEMENTS.contains(&name) {
+                    // Headings do not nest.
+                    self.pop_current_if(&HEADING_ELEMENTS);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The start tag steps for `<li>`, `<dd>` and `<dt>`: closes the
+    /// nearest open item named one of `names`, unless a special element
+    /// other than `<address>`, `<div>` or `<p>` is nearer, and then any
+    /// open `<p>`.
+    fn close_list_item(&mut self, names: &[&str]) {
+        for index in (0..self.open_elements.len()).rev() {
+            let Some(element) = self
+                .open_elements
+                .get(index)
+                .and_then(|&id| self.document.element(id))
+            else {
+                continue;
+            };
+            if element.namespace == Namespace::Html && names.contains(&element.name.as_str()) {
+                self.open_elements.truncate(index);
+                break;
+            }
+            if is_special(element) && !matches!(element.name.as_str(), "address" | "div" | "p") {
+                break;
+            }
+        }
+        self.close_p_element();
+    }
+
+    fn end_tag_in_body(&mut self, name: &str) {
+        match name {
+            "p" => {
+                if !self.in_scope(&["p"], Scope::Button) {
+                    // A `</p>` without an open paragraph makes an empty one.
+                    self.insert_start_tag(name.to_owned(), Vec::new(), false);
+                }
+                self.close_p_element();
+            }
+            "li" => self.close_in_scope(&["li"], Scope::ListItem),
+            _ if HEADING_ELEMENTS.contains(&name) => {
+                self.close_in_scope(&HEADING_ELEMENTS, Scope::Default);
+            }
+            _ if SPECIAL_ELEMENTS.contains(&name) => self.close_in_scope(&[name], Scope::Default),
+            _ => self.close_element(name),
+        }
+    }
+
+    fn close_p_element(&mut self) {
+        self.close_in_scope(&["p"], Scope::Button);
+    }
+
+    /// Whether an HTML element named one of `names` is open within `scope`.
+    fn in_scope(&self, names: &[&str], scope: Scope) -> bool {
+        for &id in self.open_elements.iter().rev() {
+            let Some(element) = self.document.element(id) else {
+                continue;
+            };
+            if element.namespace == Namespace::Html && names.contains(&element.name.as_str()) {
+                return true;
+            }
+            if scope.is_boundary(element) {
+                return false;
+            }
+        }
+        false
+    }
+
+    /// Pops up to and including the innermost HTML element named one of
+    /// `names`, if one is open within `scope`. The elements above it have
+    /// their end tags implied.
+    fn close_in_scope(&mut self, names: &[&str], scope: Scope) {
+        if !self.in_scope(names, scope) {
+            return;
+        }
+        let index = self.open_elements.iter().rposition(|&id| {
+            self.document.element(id).is_some_and(|element| {
+                element.namespace == Namespace::Html && names.contains(&element.name.as_str())
+            })
+        });
+        if let Some(index) = index {
+            self.open_elements.truncate(index);
+        }
+    }
+
+    /// Pops the current node if it is an HTML element named one of `names`.
+    fn pop_current_if(&mut self, names: &[&str]) {
+        if self.current_element().is_some_and(|element| {
+            element.namespace == Namespace::Html && names.contains(&element.name.as_str())
+        }) {
+            self.open_elements.pop();
+        }
+    }
+
+    /// Pops elements whose end tag is implied, other than `except`.
+    fn generate_implied_end_tags(&mut self, except: Option<&str>) {
+        while self.current_element().is_some_and(|element| {
+            element.namespace == Namespace::Html
+                && IMPLIED_END_TAG_ELEMENTS.contains(&element.name.as_str())
+                && except != Some(element.name.as_str())
+        }) {
+            self.open_elements.pop();
+        }
+    }
+
+    /// The spec's "any other end tag" steps: pops up to and including the
+    /// innermost open element named `name`, unless a special element comes
+    /// first, in which case the end tag is ignored.
+    fn close_element(&mut self, name: &str) {
+        for index in (0..self.open_elements.len()).rev() {
+            let Some(element) = self
+                .open_elements
+                .get(index)
+                .and_then(|&id| self.document.element(id))
+            else {
+                continue;
+            };
+            if element.name.eq_ignore_ascii_case(name) {
+                self.open_elements.truncate(index);
+                return;
+            }
+            if is_special(element) {
+                return;
+            }
+        }
+    }
+}
+
+fn start_tag_name(token: &HtmlToken) -> Option<&str> {
+    match token {
+        HtmlToken::StartTag { name, .. } => Some(name),
+        _ => None,
+    }
+}
+
+fn is_whitespace_text(token: &HtmlToken) -> bool {
+    matches!(token, HtmlToken::Text(text) if is_whitespace(text))
+}
+
+fn is_special(element: &Element) -> bool {
+    element.namespace == Namespace::Html && SPECIAL_ELEMENTS.contains(&element.name.as_str())
+}
+
+/// Whether HTML content can appear inside `element`, although it is foreign.
+fn is_html_integration_point(element: &Element) -> bool {
+    element.namespace == Namespace::Svg
+        && matches!(element.name.as_str(), "foreignObject" | "desc" | "title")
+}
+
+fn is_whitespace(text: &str) -> bool {
+    text.chars().all(is_html_whitespace)
+}
+
+pub(super) fn is_html_whitespace(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\u{c}' | '\r' | ' ')
+}
+
+fn split_leading_whitespace(text: &str) -> (&str, &str) {
+    let rest = text.trim_start_matches(is_html_whitespace);
+    text.split_at(text.len() - rest.len())
+}
+
+/// Drops leading whitespace from a text token that is reprocessed in a mode
+/// that ignores it.
+fn strip_leading_whitespace(token: HtmlToken) -> HtmlToken {
+    match token {
+        HtmlToken::Text(text) => HtmlToken::Text(split_leading_whitespace(&text).1.to_owned()),
+        token => token,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Dumps the tree in the html5lib tree-construction test format.
+    fn dump(document: &Document) -> String {
+        fn visit(document: &Document, id: NodeId, depth: usize, out: &mut String) {
+            let indent = "  ".repeat(depth);
+            match document.node(id) {
+                Node::Document | Node::DocumentFragment => {}
+                Node::Doctype(doctype) => {
+                    out.push_str(&format!("| {indent}<!DOCTYPE {doctype}>\n"))
+                }
+                Node::Element(element) => {
+                    let prefix = match element.namespace {
+                        Namespace::Html => "",
+                        Namespace::Svg => "svg ",
+                        Namespace::MathMl => "math ",
+                    };
+                    out.push_str(&format!("| {indent}<{prefix}{}>\n", element.name));
+                    for (name, value) in &element.attributes {
+                        out.push_str(&format!("| {indent}  {name}=\"{value}\"\n"));
+                    }
+                }
+                Node::Text(text) => out.push_str(&format!("| {indent}\"{text}\"\n")),
+                Node::Comment(comment) => out.push_str(&format!("| {indent}<!-- {comment} -->\n")),
+            }
+            let depth = if id == document.root() { 0 } else { depth + 1 };
+            for child in document.children(id) {
+                visit(document, child, depth, out);
+            }
+            if let Some(content) = document.template_content(id) {
+                out.push_str(&format!("| {}content\n", "  ".repeat(depth)));
+                for child in document.children(content) {
+                    visit(document, child, depth + 1, out);
+                }
+            }
+        }
+
+        let mut out = String::new();
+        visit(document, document.root(), 0, &mut out);
+        out
+    }
+
+    #[test]
+    fn test_missing_html_head_and_body_are_created() {
+        assert_eq!(
+            dump(&Document::parse("Hello")),
+            "| <html>\n|   <head>\n|   <body>\n|     \"Hello\"\n"
+        );
+    }
+
+    #[test]
+    fn test_full_document() {
+        let html = "<!DOCTYPE html>\n<html lang=en>\n<head>\n<meta charset=utf-8>\n\
+            <title>A &amp; B</title>\n</head>\n<body>\n<div><p>x</p></div>\n</body>\n</html>\n";
+        assert_eq!(
+            dump(&Document::parse(html)),
+            "| <!DOCTYPE html>\n\
+             | <html>\n\
+             |   lang=\"en\"\n\
+             |   <head>\n\
+             |     \"\n\"\n\
+             |     <meta>\n\
+             |       charset=\"utf-8\"\n\
+             |     \"\n\"\n\
+             |     <title>\n\
+             |       \"A & B\"\n\
+             |     \"\n\"\n\
+             |   \"\n\"\n\
+             |   <body>\n\
+             |     \"\n\"\n\
+             |     <div>\n\
+             |       <p>\n\
+             |         \"x\"\n\
+             |     \"\n\n\n\"\n"
+        );
+    }
+
+    #[test]
+    fn test_late_head_elements_go_into_head() {
+        let document = Document::parse("<head></head><link rel=x><p>y");
+        let head = document.head().unwrap();
+        assert!(document.child_element(head, "link").is_some());
+        assert!(
+            document
+                .child_element(document.body().unwrap(), "p")
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn test_void_and_unmatched_end_tags() {
+        assert_eq!(
+            dump(&Document::parse("<div>a<br>b</span>c</div>d")),
+            "| <html>\n|   <head>\n|   <body>\n|     <div>\n|       \"a\"\n|       <br>\n\
+             |       \"bc\"\n|     \"d\"\n"
+        );
+    }
+
+    #[test]
+    fn test_end_tag_does_not_cross_special_elements() {
+        assert_eq!(
+            dump(&Document::parse("<b><div>x</b>y</div>")),
+            "| <html>\n|   <head>\n|   <body>\n|     <b>\n|       <div>\n|         \"xy\"\n"
+        );
+    }
+
+    #[test]
+    fn test_foreign_content() {
+        assert_eq!(
+            dump(&Document::parse(
+                "<svg><circle/><foreignObject><p>x</p></foreignObject></svg>"
+            )),
+            "| <html>\n|   <head>\n|   <body>\n|     <svg svg>\n|       <svg circle>\n\
+             |       <svg foreignObject>\n|         <p>\n|           \"x\"\n"
+        );
+    }
+
+    #[test]
+    fn test_comments_and_content_after_body() {
+        assert_eq!(
+            dump(&Document::parse(
+                "<!--a--><html><body></body><!--b--></html><!--c-->x"
+            )),
+            "| <!-- a -->\n| <html>\n|   <head>\n|   <body>\n|     \"x\"\n|   <!-- b -->\n\
+             | <!-- c -->\n"
+        );
+    }
+
+    #[test]
+    fn test_repeated_html_and_body_merge_attributes() {
+        let document = Document::parse("<body class=a><body class=b id=c><html lang=en>");
+        let body = document.element(document.body().unwrap()).unwrap();
+        assert_eq!(body.get_attribute("class"), Some("a"));
+        assert_eq!(body.get_attribute("id"), Some("c"));
+        let html = document.document_element().unwrap();
+        assert_eq!(
+            document.element(html).unwrap().get_attribute("lang"),
+            Some("en")
+        );
+    }
+
+    #[test]
+    fn test_block_start_tags_close_paragraphs() {
+        assert_eq!(
+            dump(&Document::parse("<p>a<div>b</div><p>c<p>d<h1>e<h2>f</p>")),
+            "| <html>\n|   <head>\n|   <body>\n|     <p>\n|       \"a\"\n|     <div>\n\
+             |       \"b\"\n|     <p>\n|       \"c\"\n|     <p>\n|       \"d\"\n\
+             |     <h1>\n|       \"e\"\n|     <h2>\n|       \"f\"\n|       <p>\n"
+        );
+    }
+
+    #[test]
+    fn test_end_tag_closes_elements_with_implied_end_tags() {
+        assert_eq!(
+            dump(&Document::parse("<div><p>a</div>b")),
+            "| <html>\n|   <head>\n|   <body>\n|     <div>\n|       <p>\n|         \"a\"\n\
+             |     \"b\"\n"
+        );
+    }
+
+    #[test]
+    fn test_list_items_close_previous_items() {
+        assert_eq!(
+            dump(&Document::parse(
+                "<ul><li>a<li>b<ul><li>c</ul><li>d</ul><dl><dt>e<dd>f<dt>g</dl>"
+            )),
+            "| <html>\n|   <head>\n|   <body>\n|     <ul>\n|       <li>\n|         \"a\"\n\
+             |       <li>\n|         \"b\"\n|         <ul>\n|           <li>\n\
+             |             \"c\"\n|       <li>\n|         \"d\"\n|     <dl>\n\
+             |       <dt>\n|         \"e\"\n|       <dd>\n|         \"f\"\n\
+             |       <dt>\n|         \"g\"\n"
+        );
+    }
+
+    #[test]
+    fn test_table_cells_and_rows_close_previous_ones() {
+        assert_eq!(
+            dump(&Document::parse(
+                "<table><tbody><tr><td>a<td>b<tr><th>c</table>"
+            )),
+            "| <html>\n|   <head>\n|   <body>\n|     <table>\n|       <tbody>\n\
+             |         <tr>\n|           <td>\n|             \"a\"\n|           <td>\n\
+             |             \"b\"\n|         <tr>\n|           <th>\n|             \"c\"\n"
+        );
+    }
+
+    #[test]
+    fn test_options_close_previous_options() {
+        assert_eq!(
+            dump(&Document::parse(
+                "<select><optgroup><option>a<option>b<optgroup><option>c</select>"
+            )),
+            "| <html>\n|   <head>\n|   <body>\n|     <select>\n|       <optgroup>\n\
+             |         <option>\n|           \"a\"\n|         <option>\n\
+             |           \"b\"\n|       <optgroup>\n|         <option>\n\
+             |           \"c\"\n"
+        );
+    }
+
+    #[test]
+    fn test_template_content_is_kept_out_of_the_tree() {
+        let document = Document::parse(
+            "<template id=t><p id=p>a</p><template><b>b</b></template></template>\
+             <div><template><li>c</template>d</div>",
+        );
+        assert_eq!(
+            dump(&document),
+            "| <html>\n|   <head>\n|     <template>\n|       id=\"t\"\n|       content\n\
+             |         <p>\n|           id=\"p\"\n|           \"a\"\n|         <template>\n\
+             |           content\n|             <b>\n|               \"b\"\n|   <body>\n\
+             |     <div>\n|       <template>\n|         content\n|           <li>\n\
+             |             \"c\"\n|       \"d\"\n"
+        );
+
+        let template = document.get_element_by_id("t").unwrap();
+        assert_eq!(document.first_child(template), None);
+        let content = document.template_content(template).unwrap();
+        assert_eq!(document.node(content), &Node::DocumentFragment);
+        assert_eq!(document.parent(content), None);
+        assert_eq!(document.get_element_by_id("p"), None);
+        assert_eq!(document.query_selector("li").unwrap(), None);
+    }
+
+    #[test]
+    fn test_unclosed_template_is_closed_at_end_of_input() {
+        assert_eq!(
+            dump(&Document::parse("<template><body>x</html>")),
+            "| <html>\n|   <head>\n|     <template>\n|       content\n|         \"x\"\n\
+             |   <body>\n"
+        );
+    }
+
+    #[test]
+    fn test_build_from_tokenizer() {
+        let document = TreeBuilder::build(&HtmlTokenizer::new("<p>a</p>")).unwrap();
+        assert!(
+            document
+                .child_element(document.body().unwrap(), "p")
+                .is_some()
+        );
+        assert!(TreeBuilder::build(&HtmlTokenizer::new("<a =x>")).is_err());
+    }
+}
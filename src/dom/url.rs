@@ -0,0 +1,248 @@
+use super::Document;
+use super::tree_builder::is_html_whitespace;
+
+/// An absolute URL split into the parts that reference resolution
+/// (<https://www.rfc-editor.org/rfc/rfc3986#section-5.2>) works with. Each
+/// part keeps its delimiter, so joining them gives back the URL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Parts<'u> {
+    /// Up to and including the `:`, as in `https:`.
+    scheme: &'u str,
+
+    /// Including the leading `//`, as in `//example.com:8080`.
+    authority: Option<&'u str>,
+    path: &'u str,
+
+    /// Including the leading `?`.
+    query: Option<&'u str>,
+}
+
+impl<'u> Parts<'u> {
+    /// Splits `url`, which must start with a scheme. The fragment is
+    /// dropped, as no resolved URL keeps the base's.
+    fn parse(url: &'u str) -> Option<Self> {
+        let url = url.split_once('#').map_or(url, |(url, _)| url);
+        let scheme_end = scheme_length(url)? + 1;
+        let (scheme, rest) = url.split_at(scheme_end);
+        let (authority, rest) = match rest.strip_prefix("//") {
+            Some(after) => {
+                let end = after.find(['/', '?']).map_or(rest.len(), |end| end + 2);
+                let (authority, rest) = rest.split_at(end);
+                (Some(authority), rest)
+            }
+            None => (None, rest),
+        };
+        let (path, query) = match rest.find('?') {
+            Some(start) => {
+                let (path, query) = rest.split_at(start);
+                (path, Some(query))
+            }
+            None => (rest, None),
+        };
+        Some(Self {
+            scheme,
+            authority,
+            path,
+            query,
+        })
+    }
+
+    /// The URL up to, but not including, its path.
+    fn origin_prefix(&self) -> String {
+        format!("{}{}", self.scheme, self.authority.unwrap_or_default())
+    }
+
+    fn without_fragment(&self) -> String {
+        format!(
+            "{}{}{}",
+            self.origin_prefix(),
+            self.path,
+            self.query.unwrap_or_default()
+        )
+    }
+}
+
+/// Resolves `url`, as written in an attribute or a style sheet, against
+/// the absolute URL `base`. Returns `None` if `base` is not absolute, unless `url` is.
+pub fn resolve(base: &str, url: &str) -> Option<String> {
+    let url = url.trim_matches(is_html_whitespace);
+    if scheme_length(url).is_some() {
+        return Some(url.to_owned());
+    }
+    let base = Parts::parse(base.trim_matches(is_html_whitespace))?;
+
+    if url.starts_with("//") {
+        return Some(format!("{}{url}", base.scheme));
+    }
+    if url.is_empty() {
+        return Some(base.without_fragment());
+    }
+    if url.starts_with('#') {
+        return Some(format!("{}{url}", base.without_fragment()));
+    }
+    if url.starts_with('?') {
+        return Some(format!("{}{}{url}", base.origin_prefix(), base.path));
+    }
+
+    let end = url.find(['?', '#']).unwrap_or(url.len());
+    let (path, rest) = url.split_at(end);
+    let merged = if path.starts_with('/') {
+        path.to_owned()
+    } else if base.authority.is_some() && base.path.is_empty() {
+        format!("/{path}")
+    } else {
+        let directory = base
+            .path
+            .rfind('/')
+            .map_or("", |end| base.path.split_at(end + 1).0);
+        format!("{directory}{path}")
+    };
+    Some(format!(
+        "{}{}{rest}",
+        base.origin_prefix(),
+        remove_dot_segments(&merged)
+    ))
+}
+
+/// The length of the scheme `url` starts with, such as `https` in
+/// `https://example.com`, not counting the `:`.
+fn scheme_length(url: &str) -> Option<usize> {
+    let end = url.find(':')?;
+    let (scheme, _) = url.split_at(end);
+    let mut chars = scheme.chars();
+    (chars.next()?.is_ascii_alphabetic()
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.')))
+    .then_some(end)
+}
+
+/// Removes `.` and `..` segments from `path`
+/// (<https://www.rfc-editor.org/rfc/rfc3986#section-5.2.4>).
+fn remove_dot_segments(path: &str) -> String {
+    let mut output: Vec<&str> = Vec::new();
+    let mut segments = path.split('/').peekable();
+    let absolute = path.starts_with('/');
+    if absolute {
+        segments.next();
+    }
+    while let Some(segment) = segments.next() {
+        let last = segments.peek().is_none();
+        match segment {
+            "." => {
+                if last {
+                    output.push("");
+                }
+            }
+            ".." => {
+                output.pop();
+                if last {
+                    output.push("");
+                }
+            }
+            segment => output.push(segment),
+        }
+    }
+    let path = output.join("/");
+    if absolute { format!("/{path}") } else { path }
+}
+
+impl Document {
+    /// The URL the document was loaded from, if it is known.
+    pub fn url(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+
+    /// Records where the document was loaded from, so that relative URLs
+    /// in it can be resolved.
+    pub fn set_url(&mut self, url: String) {
+        self.url = Some(url);
+    }
+
+    /// The URL that relative URLs in the document resolve against: the
+    /// `href` of the first `<base>` that has one, itself resolved against
+    /// the document's URL, or else the document's URL.
+    pub fn base_url(&self) -> Option<String> {
+        match &self.base_href {
+            Some(href) => resolve(self.url().unwrap_or_default(), href),
+            None => self.url.clone(),
+        }
+    }
+
+    /// Resolves `url`, as written in an attribute of the document, against
+    /// [`Document::base_url`]. Returns `None` if it is relative and there is
+    /// no base URL to resolve it against.
+    pub fn resolve_url(&self, url: &str) -> Option<String> {
+        resolve(self.base_url().as_deref().unwrap_or_default(), url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_reference_examples() {
+        // From RFC 3986, section 5.4.
+        let base = "http://a/b/c/d;p?q";
+        for (url, expected) in [
+            ("g:h", "g:h"),
+            ("g", "http://a/b/c/g"),
+            ("./g", "http://a/b/c/g"),
+            ("g/", "http://a/b/c/g/"),
+            ("/g", "http://a/g"),
+            ("//g", "http://g"),
+            ("?y", "http://a/b/c/d;p?y"),
+            ("g?y", "http://a/b/c/g?y"),
+            ("#s", "http://a/b/c/d;p?q#s"),
+            ("g#s", "http://a/b/c/g#s"),
+            ("", "http://a/b/c/d;p?q"),
+            (".", "http://a/b/c/"),
+            ("./", "http://a/b/c/"),
+            ("..", "http://a/b/"),
+            ("../g", "http://a/b/g"),
+            ("../..", "http://a/"),
+            ("../../g", "http://a/g"),
+            ("../../../g", "http://a/g"),
+            ("/./g", "http://a/g"),
+            ("g/../h", "http://a/b/c/h"),
+        ] {
+            assert_eq!(resolve(base, url).as_deref(), Some(expected), "{url}");
+        }
+    }
+
+    #[test]
+    fn test_resolve_url_uses_the_first_base_href() {
+        let mut document = Document::parse("<p>");
+        assert_eq!(document.resolve_url("x.png"), None);
+        document.set_url("https://example.com/dir/page".to_owned());
+        assert_eq!(
+            document.resolve_url("x.png").as_deref(),
+            Some("https://example.com/dir/x.png")
+        );
+
+        let document = Document::parse(
+            "<template><base href=/ignored/></template><base target=_top>\
+             <base href=https://cdn.example.com/a/><base href=/b/>",
+        );
+        assert_eq!(
+            document.resolve_url("x.png").as_deref(),
+            Some("https://cdn.example.com/a/x.png")
+        );
+    }
+
+    #[test]
+    fn test_resolve_against_a_bare_origin() {
+        assert_eq!(
+            resolve("https://example.com", " page.html ").as_deref(),
+            Some("https://example.com/page.html")
+        );
+        assert_eq!(
+            resolve("https://example.com?a#b", "#top").as_deref(),
+            Some("https://example.com?a#top")
+        );
+        assert_eq!(resolve("not a url", "page.html"), None);
+        assert_eq!(
+            resolve("not a url", "mailto:a@b.c").as_deref(),
+            Some("mailto:a@b.c")
+        );
+    }
+}
@@ -0,0 +1,282 @@
+use std::sync::Arc;
+
+use crate::dom::Document;
+use crate::http::HttpResponse;
+
+/// A request the browser is about to send, as seen by extensions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutgoingRequest {
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+
+    /// URL of the page that caused this request, or `None` for a top-level navigation.
+    pub initiator: Option<String>,
+}
+
+impl OutgoingRequest {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            headers: Vec::new(),
+            initiator: None,
+        }
+    }
+}
+
+/// What should happen to a request after an extension has inspected it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestAction {
+    Continue,
+    Block,
+}
+
+/// A compiled-in browser extension.
+///
+/// Every hook has a no-op default, so extensions only implement the points
+/// they care about.
+pub trait BrowserExtension: Send + Sync {
+    /// Short human-readable name, used in logs and error messages.
+    fn name(&self) -> &str;
+
+    /// Called before a request is sent. Extensions may rewrite the URL or
+    /// headers, or block the request entirely.
+    fn on_request(&self, _request: &mut OutgoingRequest) -> RequestAction {
+        RequestAction::Continue
+    }
+
+    /// Called after a response has been received, before it is shown.
+    fn on_response(&self, _url: &str, _response: &mut HttpResponse) {}
+
+    /// Called once the page has been parsed, before its subresources are
+    /// fetched. Extensions may change the document, such as to drop
+    /// elements before their images or style sheets are requested.
+    fn on_dom_ready(&self, _document: &mut Document) {}
+
+    /// Called each time the styles of the page are about to be computed
+    /// for it to be laid out and painted, as when its style sheets arrive
+    /// or the window changes.
+    fn before_render(&self, _document: &mut Document) {}
+}
+
+/// Lets the app keep a handle to an extension it has registered.
+impl<T: BrowserExtension + ?Sized> BrowserExtension for Arc<T> {
+    fn name(&self) -> &str {
+        (**self).name()
+    }
+
+    fn on_request(&self, request: &mut OutgoingRequest) -> RequestAction {
+        (**self).on_request(request)
+    }
+
+    fn on_response(&self, url: &str, response: &mut HttpResponse) {
+        (**self).on_response(url, response);
+    }
+
+    fn on_dom_ready(&self, document: &mut Document) {
+        (**self).on_dom_ready(document);
+    }
+
+    fn before_render(&self, document: &mut Document) {
+        (**self).before_render(document);
+    }
+}
+
+/// The set of extensions active in the browser, run in registration order.
+#[derive(Default)]
+pub struct ExtensionRegistry {
+    extensions: Vec<Box<dyn BrowserExtension>>,
+}
+
+impl ExtensionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, extension: Box<dyn BrowserExtension>) {
+        log::debug!("Registered extension {}", extension.name());
+        self.extensions.push(extension);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.extensions.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.extensions.len()
+    }
+
+    /// Runs every extension's request hook.
+    ///
+    /// # Errors
+    ///
+    /// Returns the name of the first extension that blocked the request.
+    pub fn run_request_hooks(&self, request: &mut OutgoingRequest) -> Result<(), String> {
+        for extension in &self.extensions {
+            if extension.on_request(request) == RequestAction::Block {
+                log::debug!("{} blocked {}", extension.name(), request.url);
+                return Err(extension.name().to_owned());
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs every extension's response hook.
+    pub fn run_response_hooks(&self, url: &str, response: &mut HttpResponse) {
+        for extension in &self.extensions {
+            extension.on_response(url, response);
+        }
+    }
+
+    /// Runs every extension's hook for a freshly parsed document.
+    pub fn run_dom_ready_hooks(&self, document: &mut Document) {
+        for extension in &self.extensions {
+            extension.on_dom_ready(document);
+        }
+    }
+
+    /// Runs every extension's hook for a document about to be styled and
+    /// laid out.
+    pub fn run_before_render_hooks(&self, document: &mut Document) {
+        for extension in &self.extensions {
+            extension.before_render(document);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct AddHeader;
+
+    impl BrowserExtension for AddHeader {
+        fn name(&self) -> &str {
+            "add-header"
+        }
+
+        fn on_request(&self, request: &mut OutgoingRequest) -> RequestAction {
+            request
+                .headers
+                .push(("X-Mochi".to_string(), "1".to_string()));
+            RequestAction::Continue
+        }
+    }
+
+    struct BlockEverything;
+
+    impl BrowserExtension for BlockEverything {
+        fn name(&self) -> &str {
+            "block-everything"
+        }
+
+        fn on_request(&self, _request: &mut OutgoingRequest) -> RequestAction {
+            RequestAction::Block
+        }
+    }
+
+    struct Shout;
+
+    impl BrowserExtension for Shout {
+        fn name(&self) -> &str {
+            "shout"
+        }
+
+        fn on_response(&self, _url: &str, response: &mut HttpResponse) {
+            response.body = response.body.to_uppercase();
+        }
+    }
+
+    #[test]
+    fn test_empty_registry_passes_requests_through() {
+        let registry = ExtensionRegistry::new();
+        let mut request = OutgoingRequest::new("https://example.com/");
+
+        assert!(registry.is_empty());
+        assert_eq!(registry.run_request_hooks(&mut request), Ok(()));
+        assert!(request.headers.is_empty());
+    }
+
+    #[test]
+    fn test_request_hook_can_add_headers() {
+        let mut registry = ExtensionRegistry::new();
+        registry.register(Box::new(AddHeader));
+        let mut request = OutgoingRequest::new("https://example.com/");
+
+        assert_eq!(registry.run_request_hooks(&mut request), Ok(()));
+        assert_eq!(
+            request.headers,
+            vec![("X-Mochi".to_string(), "1".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_block_stops_later_extensions() {
+        let mut registry = ExtensionRegistry::new();
+        registry.register(Box::new(BlockEverything));
+        registry.register(Box::new(AddHeader));
+        let mut request = OutgoingRequest::new("https://example.com/");
+
+        assert_eq!(
+            registry.run_request_hooks(&mut request),
+            Err("block-everything".to_string())
+        );
+        assert!(request.headers.is_empty());
+        assert_eq!(registry.len(), 2);
+    }
+
+    #[test]
+    fn test_response_hook_rewrites_body() {
+        let mut registry = ExtensionRegistry::new();
+        registry.register(Box::new(Shout));
+        let mut response = HttpResponse {
+            status: 200,
+            headers: vec![],
+            body: "hello".to_string(),
+        };
+
+        registry.run_response_hooks("https://example.com/", &mut response);
+
+        assert_eq!(response.body, "HELLO");
+    }
+
+    struct MarkStages;
+
+    impl BrowserExtension for MarkStages {
+        fn name(&self) -> &str {
+            "mark-stages"
+        }
+
+        fn on_dom_ready(&self, document: &mut Document) {
+            if let Some(id) = document.get_element_by_id("page") {
+                document.set_attribute(id, "data-ready", "");
+            }
+        }
+
+        fn before_render(&self, document: &mut Document) {
+            if let Some(id) = document.get_element_by_id("page") {
+                document.set_attribute(id, "data-rendered", "");
+            }
+        }
+    }
+
+    #[test]
+    fn test_document_hooks() {
+        let mut registry = ExtensionRegistry::new();
+        registry.register(Box::new(Shout));
+        registry.register(Box::new(Arc::new(MarkStages)));
+        let mut document = Document::parse("<p id=page>hello</p>");
+        let page = document.get_element_by_id("page").unwrap();
+        let has = |document: &Document, name| {
+            document
+                .element(page)
+                .is_some_and(|element| element.get_attribute(name).is_some())
+        };
+
+        registry.run_dom_ready_hooks(&mut document);
+        assert!(has(&document, "data-ready"));
+        assert!(!has(&document, "data-rendered"));
+
+        registry.run_before_render_hooks(&mut document);
+        assert!(has(&document, "data-rendered"));
+    }
+}
@@ -1,11 +1,40 @@
 use std::fmt;
 
+mod checkpoint;
+mod diagnostics;
+mod encoding;
+pub mod entities;
+mod foreign;
+mod incremental;
+mod options;
+#[cfg(test)]
+mod property_tests;
+mod rewriter;
+mod serialize;
+mod spec;
+mod streaming;
+
+pub use checkpoint::Checkpoint;
+pub use diagnostics::Location;
+pub use encoding::{decode_html, sniff_encoding};
+use entities::{decode_attribute_character_references, decode_character_references};
+use foreign::ForeignNamespace;
+pub use incremental::{IncrementalTokenizer, TokenId};
+pub use options::TokenizerOptions;
+pub use rewriter::{Element, Selector, TokenRewriter};
+pub use serialize::serialize_tokens;
+pub use streaming::StreamingTokenizer;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum HtmlToken {
     Doctype(String),
     StartTag {
         name: String,
         attributes: Vec<(String, String)>,
+
+        /// Whether the tag was written as `<name ... />`. This is syntax
+        /// only: HTML ignores it except on void and foreign elements, so
+        /// `<div/>` still opens a `div`. See [`HtmlToken::is_void`].
         self_closing: bool,
     },
     EndTag {
@@ -13,6 +42,86 @@ pub enum HtmlToken {
     },
     Text(String),
     Comment(String),
+
+    /// A recoverable error, reported in the token stream when
+    /// [`TokenizerOptions::emit_parse_errors`] is set. It comes just before
+    /// the token whose markup caused it.
+    ParseError(ParseError),
+}
+
+/// Elements that can never have children or an end tag, including the
+/// obsolete ones the tree builder still treats that way.
+pub const VOID_ELEMENTS: [&str; 18] = [
+    "area", "base", "basefont", "bgsound", "br", "col", "embed", "frame", "hr", "img", "input",
+    "keygen", "link", "meta", "param", "source", "track", "wbr",
+];
+
+/// Whether `name` is a void element such as `br` or `img`.
+pub fn is_void_element(name: &str) -> bool {
+    VOID_ELEMENTS
+        .iter()
+        .any(|element| name.eq_ignore_ascii_case(element))
+}
+
+impl HtmlToken {
+    /// Whether this is a start or end tag for a void element, which has no
+    /// content and is complete as soon as its start tag is seen, whether or
+    /// not it was written self-closing.
+    pub fn is_void(&self) -> bool {
+        match self {
+            Self::StartTag { name, .. } | Self::EndTag { name } => is_void_element(name),
+            _ => false,
+        }
+    }
+
+    /// Whether this start tag has no content to wait for: either the element
+    /// is void, or it used self-closing syntax inside foreign content where
+    /// that syntax is honoured. `in_foreign_content` says which applies.
+    pub fn closes_immediately(&self, in_foreign_content: bool) -> bool {
+        match self {
+            Self::StartTag { self_closing, .. } => {
+                self.is_void() || (in_foreign_content && *self_closing)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// A byte range `start..end` in the tokenizer input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    pub fn len(&self) -> usize {
+        self.end.saturating_sub(self.start)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The slice of `source` this span covers, or `""` if it is out of bounds.
+    pub fn source_text<'s>(&self, source: &'s str) -> &'s str {
+        source.get(self.start..self.end).unwrap_or_default()
+    }
+}
+
+/// A token together with where it came from in the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpannedToken {
+    pub token: HtmlToken,
+    pub span: Span,
+
+    /// One span per attribute of a `StartTag`, in the same order, covering
+    /// `name` or `name=value` including any quotes. Empty for other tokens.
+    pub attribute_spans: Vec<Span>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -21,6 +130,7 @@ pub enum TokenizeError {
     InvalidTag,
     InvalidAttribute,
     MalformedComment,
+    DuplicateAttribute,
 }
 
 impl fmt::Display for TokenizeError {
@@ -30,36 +140,232 @@ impl fmt::Display for TokenizeError {
             Self::InvalidTag => write!(f, "Invalid HTML tag"),
             Self::InvalidAttribute => write!(f, "Invalid HTML attribute"),
             Self::MalformedComment => write!(f, "Malformed HTML comment"),
+            Self::DuplicateAttribute => write!(f, "Duplicate HTML attribute"),
         }
     }
 }
 
 impl std::error::Error for TokenizeError {}
 
+/// A problem the tokenizer recovered from: malformed markup in
+/// error-recovery or spec-conformance mode, or a warning such as a duplicate
+/// attribute in any mode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub error: TokenizeError,
+
+    /// The markup the error is about.
+    pub span: Span,
+}
+
+/// Elements whose content is not tokenized as markup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RawTextElement {
+    name: String,
+
+    /// RCDATA elements (`title`, `textarea`) decode character references;
+    /// RAWTEXT elements (`script`, `style`, the legacy `xmp`, ...) keep
+    /// them verbatim.
+    decode_entities: bool,
+}
+
+impl RawTextElement {
+    fn for_start_tag(name: &str) -> Option<Self> {
+        const RAW_TEXT: [&str; 6] = ["script", "style", "iframe", "noembed", "noframes", "xmp"];
+        const RCDATA: [&str; 2] = ["title", "textarea"];
+
+        let decode_entities = if RAW_TEXT.iter().any(|e| name.eq_ignore_ascii_case(e)) {
+            false
+        } else if RCDATA.iter().any(|e| name.eq_ignore_ascii_case(e)) {
+            true
+        } else {
+            return None;
+        };
+
+        Some(Self {
+            name: name.to_ascii_lowercase(),
+            decode_entities,
+        })
+    }
+}
+
+/// The part of the tokenizer's state that carries over between tokens, so
+/// tokenization can resume on a different buffer.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct TokenizerState {
+    raw_text_element: Option<RawTextElement>,
+    foreign_elements: Vec<ForeignNamespace>,
+    plaintext: bool,
+}
+
 pub struct HtmlTokenizer<'a> {
     input: &'a str,
+
+    /// Byte offset of the next character in `input`.
     position: usize,
+
+    options: TokenizerOptions,
+
+    /// Errors recovered from so far.
+    parse_errors: Vec<ParseError>,
+
+    /// The last error returned instead of a token.
+    fatal_error: Option<ParseError>,
+
+    /// Set after the start tag of a raw text element until its end tag.
+    raw_text_element: Option<RawTextElement>,
+
+    /// Open `<svg>`/`<math>` elements, innermost last. CDATA sections are
+    /// only recognized inside such foreign content, and names there have
+    /// their case adjusted.
+    foreign_elements: Vec<ForeignNamespace>,
+
+    /// Set after a `<plaintext>` start tag; the rest of the input is text,
+    /// since nothing can end the element.
+    plaintext: bool,
+
+    /// Spans of the attributes parsed for the current token.
+    attribute_spans: Vec<Span>,
+
+    /// How many of `parse_errors` have been emitted as tokens.
+    emitted_errors: usize,
+
+    /// A token read ahead while its parse errors are emitted first.
+    held_token: Option<Result<SpannedToken, TokenizeError>>,
+
+    /// The furthest `position` has been. Error recovery moves back to
+    /// re-read markup as text, so this can be past `position`.
+    furthest_position: usize,
 }
 
 impl<'a> HtmlTokenizer<'a> {
     pub fn new(input: &'a str) -> Self {
-        Self { input, position: 0 }
+        Self {
+            input,
+            position: 0,
+            options: TokenizerOptions::default(),
+            parse_errors: Vec::new(),
+            fatal_error: None,
+            raw_text_element: None,
+            foreign_elements: Vec::new(),
+            plaintext: false,
+            attribute_spans: Vec::new(),
+            emitted_errors: 0,
+            held_token: None,
+            furthest_position: 0,
+        }
     }
 
-    fn peek(&self, offset: usize) -> Option<char> {
-        self.input.chars().nth(self.position + offset)
+    fn state(&self) -> TokenizerState {
+        TokenizerState {
+            raw_text_element: self.raw_text_element.clone(),
+            foreign_elements: self.foreign_elements.clone(),
+            plaintext: self.plaintext,
+        }
     }
 
-    fn advance(&mut self) -> Option<char> {
-        if self.position < self.input.len() {
-            let c = self.input.chars().nth(self.position);
-            self.position += 1;
-            c
+    fn with_state(mut self, state: TokenizerState) -> Self {
+        self.raw_text_element = state.raw_text_element;
+        self.foreign_elements = state.foreign_elements;
+        self.plaintext = state.plaintext;
+        self
+    }
+
+    /// A fresh tokenizer over the same input with the same settings.
+    fn restarted(&self) -> Self {
+        Self::new(self.input).with_options(self.options.clone())
+    }
+
+    #[must_use]
+    pub fn with_options(mut self, options: TokenizerOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn options(&self) -> &TokenizerOptions {
+        &self.options
+    }
+
+    /// See [`TokenizerOptions::decode_entities`].
+    #[must_use]
+    pub fn with_decode_entities(mut self, decode_entities: bool) -> Self {
+        self.options = self.options.decode_entities(decode_entities);
+        self
+    }
+
+    /// See [`TokenizerOptions::lowercase_names`].
+    #[must_use]
+    pub fn with_lowercase_names(mut self, lowercase_names: bool) -> Self {
+        self.options = self.options.lowercase_names(lowercase_names);
+        self
+    }
+
+    /// See [`TokenizerOptions::error_recovery`].
+    #[must_use]
+    pub fn with_error_recovery(mut self, recover_errors: bool) -> Self {
+        self.options = self.options.error_recovery(recover_errors);
+        self
+    }
+
+    /// See [`TokenizerOptions::spec_conformance`].
+    #[must_use]
+    pub fn with_spec_conformance(mut self, spec_conformance: bool) -> Self {
+        self.options = self.options.spec_conformance(spec_conformance);
+        self
+    }
+
+    /// The errors and warnings recorded so far. See [`ParseError`].
+    pub fn parse_errors(&self) -> &[ParseError] {
+        &self.parse_errors
+    }
+
+    /// The error last returned in place of a token, with the span of the
+    /// markup that caused it, for use with [`ParseError::render_snippet`].
+    pub fn fatal_error(&self) -> Option<&ParseError> {
+        self.fatal_error.as_ref()
+    }
+
+    fn normalize_name(&self, name: String) -> String {
+        if self.options.lowercase_names {
+            name.to_ascii_lowercase()
         } else {
-            None
+            name
+        }
+    }
+
+    fn decode(&self, raw: String) -> String {
+        // Most text has no references; skip the copy the decoder would make.
+        if self.options.decode_entities && memchr::memchr(b'&', raw.as_bytes()).is_some() {
+            decode_character_references(&raw)
+        } else {
+            raw
         }
     }
 
+    fn decode_attribute(&self, raw: String) -> String {
+        if self.options.decode_entities && memchr::memchr(b'&', raw.as_bytes()).is_some() {
+            decode_attribute_character_references(&raw)
+        } else {
+            raw
+        }
+    }
+
+    /// The input from the current position onwards.
+    fn remaining(&self) -> &'a str {
+        self.input.get(self.position..).unwrap_or_default()
+    }
+
+    /// The character `offset` characters ahead of the current position.
+    fn peek(&self, offset: usize) -> Option<char> {
+        self.remaining().chars().nth(offset)
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let c = self.remaining().chars().next()?;
+        self.position += c.len_utf8();
+        Some(c)
+    }
+
     fn advance_n(&mut self, n: usize) {
         for _ in 0..n {
             if self.advance().is_none() {
@@ -72,6 +378,18 @@ impl<'a> HtmlTokenizer<'a> {
         self.position >= self.input.len()
     }
 
+    /// Whether the input continues with exactly `expected`.
+    fn lookahead_is(&self, expected: &str) -> bool {
+        self.remaining().starts_with(expected)
+    }
+
+    /// Whether the input continues with `expected`, ignoring ASCII case.
+    fn lookahead_is_ignore_case(&self, expected: &str) -> bool {
+        self.remaining()
+            .get(..expected.len())
+            .is_some_and(|candidate| candidate.eq_ignore_ascii_case(expected))
+    }
+
     fn skip_whitespace(&mut self) {
         while let Some(c) = self.peek(0) {
             if c.is_whitespace() {
@@ -81,6 +399,25 @@ impl<'a> HtmlTokenizer<'a> {
             }
         }
     }
+
+    /// Consumes the longest run of input whose characters all satisfy
+    /// `predicate`.
+    fn consume_while(&mut self, predicate: impl Fn(char) -> bool) -> &'a str {
+        let rest = self.remaining();
+        let len = rest.find(|c| !predicate(c)).unwrap_or(rest.len());
+        self.position += len;
+        rest.get(..len).unwrap_or_default()
+    }
+
+    /// Consumes input up to (not including) the next `byte`, or to the end,
+    /// with a single `memchr` scan rather than char by char. `byte` must be
+    /// ASCII so the split falls on a char boundary.
+    fn consume_until(&mut self, byte: u8) -> &'a str {
+        let rest = self.remaining();
+        let len = memchr::memchr(byte, rest.as_bytes()).unwrap_or(rest.len());
+        self.position += len;
+        rest.get(..len).unwrap_or_default()
+    }
 }
 
 pub struct HtmlTokenizerIter<'a> {
@@ -93,6 +430,16 @@ impl<'a> HtmlTokenizerIter<'a> {
             tokenizer: HtmlTokenizer::new(input),
         }
     }
+
+    /// See [`HtmlTokenizer::parse_errors`].
+    pub fn parse_errors(&self) -> &[ParseError] {
+        self.tokenizer.parse_errors()
+    }
+
+    /// See [`HtmlTokenizer::fatal_error`].
+    pub fn fatal_error(&self) -> Option<&ParseError> {
+        self.tokenizer.fatal_error()
+    }
 }
 
 impl Iterator for HtmlTokenizerIter<'_> {
@@ -103,26 +450,187 @@ impl Iterator for HtmlTokenizerIter<'_> {
     }
 }
 
+/// Like [`HtmlTokenizerIter`], but yields tokens with their source spans.
+pub struct SpannedTokenIter<'a> {
+    tokenizer: HtmlTokenizer<'a>,
+}
+
+impl SpannedTokenIter<'_> {
+    /// See [`HtmlTokenizer::parse_errors`].
+    pub fn parse_errors(&self) -> &[ParseError] {
+        self.tokenizer.parse_errors()
+    }
+
+    /// See [`HtmlTokenizer::fatal_error`].
+    pub fn fatal_error(&self) -> Option<&ParseError> {
+        self.tokenizer.fatal_error()
+    }
+}
+
+impl Iterator for SpannedTokenIter<'_> {
+    type Item = Result<SpannedToken, TokenizeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.tokenizer.next_spanned_token()
+    }
+}
+
 impl<'a> HtmlTokenizer<'a> {
     pub fn iter(&self) -> HtmlTokenizerIter<'a> {
         HtmlTokenizerIter {
-            tokenizer: HtmlTokenizer::new(self.input),
+            tokenizer: self.restarted(),
+        }
+    }
+
+    pub fn spanned_iter(&self) -> SpannedTokenIter<'a> {
+        SpannedTokenIter {
+            tokenizer: self.restarted(),
         }
     }
 
     pub fn next_token(&mut self) -> Option<Result<HtmlToken, TokenizeError>> {
-        self.skip_whitespace();
+        self.next_spanned_token()
+            .map(|result| result.map(|spanned| spanned.token))
+    }
+
+    /// Returns the next token along with the byte range it was parsed from.
+    pub fn next_spanned_token(&mut self) -> Option<Result<SpannedToken, TokenizeError>> {
+        if let Some(error) = self.next_error_token() {
+            return Some(Ok(error));
+        }
+        if let Some(token) = self.held_token.take() {
+            return Some(token);
+        }
+
+        loop {
+            let result = if self.options.spec_conformance {
+                self.next_spec_token().map(Ok)
+            } else {
+                self.next_simple_token()
+            };
+
+            match &result {
+                Some(Ok(spanned)) if !self.wanted(&spanned.token) => {}
+                _ => {
+                    let Some(error) = self.next_error_token() else {
+                        return result;
+                    };
+                    self.held_token = result;
+                    return Some(Ok(error));
+                }
+            }
+        }
+    }
+
+    /// The next recorded parse error not yet emitted, if errors are emitted
+    /// as tokens.
+    fn next_error_token(&mut self) -> Option<SpannedToken> {
+        if !self.options.emit_parse_errors {
+            return None;
+        }
+        let error = self.parse_errors.get(self.emitted_errors)?.clone();
+        self.emitted_errors += 1;
+        Some(SpannedToken {
+            span: error.span,
+            token: HtmlToken::ParseError(error),
+            attribute_spans: Vec::new(),
+        })
+    }
+
+    /// Whether `token` passes the comment and whitespace filters.
+    fn wanted(&self, token: &HtmlToken) -> bool {
+        match token {
+            HtmlToken::Comment(_) => self.options.preserve_comments,
+            HtmlToken::Text(text) if text.chars().all(char::is_whitespace) => {
+                self.options.preserve_whitespace
+            }
+            _ => true,
+        }
+    }
+
+    /// The tokenizer's own simplified rules, used unless spec conformance is
+    /// requested.
+    fn next_simple_token(&mut self) -> Option<Result<SpannedToken, TokenizeError>> {
+        self.attribute_spans.clear();
+
+        if self.plaintext {
+            if self.is_eof() {
+                return None;
+            }
+            let start = self.position;
+            let text = self.remaining().to_owned();
+            self.position = self.input.len();
+            return Some(Ok(self.spanned(HtmlToken::Text(text), start)));
+        }
+
+        if let Some(element) = self.raw_text_element.take() {
+            if self.is_eof() {
+                // Keep the state so a streaming caller can resume inside the element.
+                self.raw_text_element = Some(element);
+                return None;
+            }
+            let start = self.position;
+            if let Some(text) = self.parse_raw_text(&element) {
+                return Some(Ok(self.spanned(text, start)));
+            }
+        }
+
+        if !self.options.preserve_whitespace {
+            self.skip_whitespace();
+        }
 
         if self.is_eof() {
             return None;
         }
 
-        if self.peek(0) == Some('<') {
+        let start = self.position;
+        let result = if self.peek(0) == Some('<') {
             self.advance();
-            return Some(self.parse_tag());
+            self.parse_tag()
+        } else {
+            Ok(self.parse_text())
+        };
+
+        match result {
+            Ok(token) => Some(Ok(self.spanned(token, start))),
+            Err(error) if self.options.recover_errors => Some(Ok(self.recover_from(error, start))),
+            Err(error) => {
+                self.fatal_error = Some(ParseError {
+                    error: error.clone(),
+                    span: Span::new(start, self.position),
+                });
+                Some(Err(error))
+            }
         }
+    }
+
+    /// Records `error` and re-reads the markup starting at `start` as text,
+    /// up to the next `<`.
+    fn recover_from(&mut self, error: TokenizeError, start: usize) -> SpannedToken {
+        self.parse_errors.push(ParseError {
+            error,
+            span: Span::new(start, self.position),
+        });
+        self.attribute_spans.clear();
+        self.furthest_position = self.furthest_position.max(self.position);
+        self.position = start;
 
-        self.parse_text()
+        let mut text = String::new();
+        if let Some(c) = self.advance() {
+            text.push(c);
+        }
+        if let HtmlToken::Text(rest) = self.parse_text() {
+            text.push_str(&rest);
+        }
+        self.spanned(HtmlToken::Text(text), start)
+    }
+
+    fn spanned(&mut self, token: HtmlToken, start: usize) -> SpannedToken {
+        SpannedToken {
+            token,
+            span: Span::new(start, self.position),
+            attribute_spans: std::mem::take(&mut self.attribute_spans),
+        }
     }
 
     fn parse_tag(&mut self) -> Result<HtmlToken, TokenizeError> {
@@ -136,6 +644,11 @@ impl<'a> HtmlTokenizer<'a> {
             return self.parse_end_tag();
         }
 
+        // `<?xml ...?>` and other processing instructions are not HTML.
+        if self.peek(0) == Some('?') {
+            return Ok(self.parse_bogus_comment());
+        }
+
         self.parse_start_tag()
     }
 
@@ -147,9 +660,59 @@ impl<'a> HtmlTokenizer<'a> {
             } else {
                 Err(TokenizeError::MalformedComment)
             }
-        } else {
+        } else if self.lookahead_is("[CDATA[") {
+            self.advance_n("[CDATA[".len());
+            Ok(self.parse_cdata())
+        } else if self.lookahead_is_ignore_case("doctype") {
             self.parse_doctype()
+        } else {
+            Ok(self.parse_bogus_comment())
+        }
+    }
+
+    /// Parses markup the spec treats as a bogus comment (`<?...>`, `<!foo>`):
+    /// everything up to the next `>` becomes the comment text.
+    fn parse_bogus_comment(&mut self) -> HtmlToken {
+        let mut comment = String::new();
+        while let Some(c) = self.advance() {
+            if c == '>' {
+                break;
+            }
+            comment.push(if c == '\0' {
+                char::REPLACEMENT_CHARACTER
+            } else {
+                c
+            });
         }
+        HtmlToken::Comment(comment)
+    }
+
+    /// Parses the rest of a `<![CDATA[` section.
+    ///
+    /// In foreign content (`<svg>`, `<math>`) its content is literal text ending at
+    /// `]]>`. In HTML content the spec treats it as a bogus comment that ends
+    /// at the first `>`.
+    fn parse_cdata(&mut self) -> HtmlToken {
+        let mut content = String::new();
+
+        if self.in_foreign_content() {
+            while !self.is_eof() && !self.lookahead_is("]]>") {
+                if let Some(c) = self.advance() {
+                    content.push(c);
+                }
+            }
+            self.advance_n("]]>".len());
+            return HtmlToken::Text(content);
+        }
+
+        content.push_str("[CDATA[");
+        while let Some(c) = self.advance() {
+            if c == '>' {
+                break;
+            }
+            content.push(c);
+        }
+        HtmlToken::Comment(content)
     }
 
     fn parse_doctype(&mut self) -> Result<HtmlToken, TokenizeError> {
@@ -171,38 +734,26 @@ impl<'a> HtmlTokenizer<'a> {
     }
 
     fn parse_comment(&mut self) -> Result<HtmlToken, TokenizeError> {
-        let mut comment = String::new();
-        let mut prev_dash_count = 0;
+        let rest = self.remaining();
+        let Some(end) = memchr::memmem::find(rest.as_bytes(), b"-->") else {
+            self.position = self.input.len();
+            return Err(TokenizeError::MalformedComment);
+        };
 
-        loop {
-            if let Some(c) = self.advance() {
-                if prev_dash_count >= 2 && c == '>' {
-                    if comment.ends_with("--") {
-                        let new_len = comment.len().saturating_sub(2);
-                        comment.truncate(new_len);
-                    }
-                    return Ok(HtmlToken::Comment(comment));
-                }
-                comment.push(c);
-                if c == '-' {
-                    prev_dash_count += 1;
-                } else {
-                    prev_dash_count = 0;
-                }
-            } else {
-                return Err(TokenizeError::MalformedComment);
-            }
-        }
+        self.position += end + "-->".len();
+        Ok(HtmlToken::Comment(
+            rest.get(..end).unwrap_or_default().to_owned(),
+        ))
     }
 
     fn parse_start_tag(&mut self) -> Result<HtmlToken, TokenizeError> {
-        let Some(name) = self.parse_tag_name() else {
+        let Some(mut name) = self.parse_tag_name() else {
             return Err(TokenizeError::InvalidTag);
         };
 
         self.skip_whitespace();
 
-        let attributes = self.parse_attributes()?;
+        let mut attributes = self.parse_attributes()?;
 
         self.skip_whitespace();
 
@@ -221,6 +772,15 @@ impl<'a> HtmlTokenizer<'a> {
             return Err(TokenizeError::InvalidTag);
         };
 
+        // As in browsers, the self-closing flag does not stop `<script/>` from
+        // swallowing the following content.
+        // Inside foreign content, `<style>` and friends are ordinary elements.
+        if !self.in_foreign_content() {
+            self.raw_text_element = RawTextElement::for_start_tag(&name);
+            self.plaintext = name.eq_ignore_ascii_case("plaintext");
+        }
+        self.enter_foreign_content(&mut name, &mut attributes, self_closing);
+
         Ok(HtmlToken::StartTag {
             name,
             attributes,
@@ -228,15 +788,69 @@ impl<'a> HtmlTokenizer<'a> {
         })
     }
 
+    /// Consumes the content of a raw text element up to (not including) its
+    /// end tag, returning it as a single text token.
+    fn parse_raw_text(&mut self, element: &RawTextElement) -> Option<HtmlToken> {
+        let mut text = String::new();
+
+        loop {
+            text.push_str(self.consume_until(b'<'));
+            if self.is_eof() || self.at_end_tag(&element.name) {
+                break;
+            }
+            self.advance();
+            text.push('<');
+        }
+
+        if text.is_empty() {
+            None
+        } else if element.decode_entities {
+            Some(HtmlToken::Text(self.decode(text)))
+        } else {
+            Some(HtmlToken::Text(text))
+        }
+    }
+
+    /// Whether the input continues with `</name` followed by a tag delimiter.
+    fn at_end_tag(&self, name: &str) -> bool {
+        let Some(rest) = self.remaining().strip_prefix("</") else {
+            return false;
+        };
+
+        rest.get(..name.len())
+            .is_some_and(|candidate| candidate.eq_ignore_ascii_case(name))
+            && rest
+                .get(name.len()..)
+                .and_then(|after| after.chars().next())
+                .is_some_and(|c| c.is_whitespace() || c == '/' || c == '>')
+    }
+
     fn parse_end_tag(&mut self) -> Result<HtmlToken, TokenizeError> {
-        let Some(name) = self.parse_tag_name() else {
+        let Some(mut name) = self.parse_tag_name() else {
             return Err(TokenizeError::InvalidTag);
         };
 
         self.skip_whitespace();
 
+        // As in the spec, attributes and a `/` on an end tag are errors but
+        // are otherwise ignored: `</div class="x">` still closes the `div`.
+        let start = self.position;
+        let attributes = self.parse_attributes()?;
+        let self_closing = self.peek(0) == Some('/');
+        if self_closing {
+            self.advance();
+        }
+        self.attribute_spans.clear();
+        if !attributes.is_empty() || self_closing {
+            self.parse_errors.push(ParseError {
+                error: TokenizeError::InvalidTag,
+                span: Span::new(start, self.position),
+            });
+        }
+
         if self.peek(0) == Some('>') {
             self.advance();
+            self.leave_foreign_content(&mut name);
             Ok(HtmlToken::EndTag { name })
         } else {
             Err(TokenizeError::InvalidTag)
@@ -244,45 +858,18 @@ impl<'a> HtmlTokenizer<'a> {
     }
 
     fn parse_tag_name(&mut self) -> Option<String> {
-        let mut name = String::new();
-
-        while let Some(c) = self.peek(0) {
-            if c.is_whitespace() || c == '>' || c == '/' {
-                break;
-            }
-            if let Some(ch) = self.advance() {
-                name.push(ch);
-            } else {
-                break;
-            }
-        }
+        let name = self.consume_while(|c| !(c.is_whitespace() || c == '>' || c == '/'));
 
         if name.is_empty() {
             None
         } else {
-            Some(name)
+            Some(self.normalize_name(name.to_owned()))
         }
     }
 
-    fn parse_text(&mut self) -> Option<Result<HtmlToken, TokenizeError>> {
-        let mut text = String::new();
-
-        while let Some(c) = self.peek(0) {
-            if c == '<' {
-                break;
-            }
-            if let Some(ch) = self.advance() {
-                text.push(ch);
-            } else {
-                break;
-            }
-        }
-
-        if text.is_empty() {
-            self.next_token()
-        } else {
-            Some(Ok(HtmlToken::Text(text)))
-        }
+    fn parse_text(&mut self) -> HtmlToken {
+        let text = self.consume_until(b'<').to_owned();
+        HtmlToken::Text(self.decode(text))
     }
 
     fn parse_attributes(&mut self) -> Result<Vec<(String, String)>, TokenizeError> {
@@ -295,11 +882,30 @@ impl<'a> HtmlTokenizer<'a> {
                 break;
             }
 
+            // A `/` that does not end the tag, as in `<br/ >`, is an error
+            // that is skipped over.
             if self.peek(0) == Some('/') {
-                break;
+                if matches!(self.peek(1), Some('>') | None) {
+                    break;
+                }
+                let start = self.position;
+                self.advance();
+                self.parse_errors.push(ParseError {
+                    error: TokenizeError::InvalidTag,
+                    span: Span::new(start, self.position),
+                });
+                continue;
             }
 
             match self.parse_attribute() {
+                // As in the spec, the first occurrence of an attribute wins.
+                Some(Ok((name, _))) if attributes.iter().any(|(n, _)| *n == name) => {
+                    let span = self.attribute_spans.pop().unwrap_or_default();
+                    self.parse_errors.push(ParseError {
+                        error: TokenizeError::DuplicateAttribute,
+                        span,
+                    });
+                }
                 Some(Ok(attr)) => attributes.push(attr),
                 Some(Err(e)) => return Err(e),
                 None => break,
@@ -310,79 +916,60 @@ impl<'a> HtmlTokenizer<'a> {
     }
 
     fn parse_attribute(&mut self) -> Option<Result<(String, String), TokenizeError>> {
+        let start = self.position;
         let name = self.parse_attribute_name()?;
+        let mut end = self.position;
 
         self.skip_whitespace();
 
-        if self.peek(0) == Some('=') {
+        let value = if self.peek(0) == Some('=') {
             self.advance();
             self.skip_whitespace();
 
             match self.parse_attribute_value() {
-                Ok(value) => Some(Ok((name, value))),
-                Err(e) => Some(Err(e)),
+                Ok(value) => {
+                    end = self.position;
+                    value
+                }
+                Err(e) => return Some(Err(e)),
             }
         } else {
-            Some(Ok((name, String::new())))
-        }
+            String::new()
+        };
+
+        self.attribute_spans.push(Span::new(start, end));
+        Some(Ok((name, value)))
     }
 
     fn parse_attribute_name(&mut self) -> Option<String> {
-        let mut name = String::new();
-
-        while let Some(c) = self.peek(0) {
-            if c.is_whitespace() || c == '=' || c == '>' || c == '/' {
-                break;
-            }
-            if let Some(ch) = self.advance() {
-                name.push(ch);
-            } else {
-                break;
-            }
-        }
+        let name = self.consume_while(|c| !(c.is_whitespace() || c == '=' || c == '>' || c == '/'));
 
         if name.is_empty() {
             None
         } else {
-            Some(name)
+            Some(self.normalize_name(name.to_owned()))
         }
     }
 
     fn parse_attribute_value(&mut self) -> Result<String, TokenizeError> {
         let quote = match self.peek(0) {
-            Some('"' | '\'') => match self.advance() {
-                Some(q) => Some(q),
-                None => return Err(TokenizeError::InvalidAttribute),
-            },
+            Some('"') => Some(b'"'),
+            Some('\'') => Some(b'\''),
             _ => None,
         };
 
-        let mut value = String::new();
-
         if let Some(quote) = quote {
-            loop {
-                match self.advance() {
-                    Some(c) => {
-                        if c == quote {
-                            return Ok(value);
-                        }
-                        value.push(c);
-                    }
-                    None => return Err(TokenizeError::InvalidAttribute),
-                }
-            }
-        } else {
-            while let Some(c) = self.peek(0) {
-                if c.is_whitespace() || c == '>' || c == '/' {
-                    break;
-                }
-                if let Some(ch) = self.advance() {
-                    value.push(ch);
-                } else {
-                    return Err(TokenizeError::InvalidAttribute);
-                }
+            self.advance();
+            let value = self.consume_until(quote).to_owned();
+            if self.advance().is_none() {
+                return Err(TokenizeError::InvalidAttribute);
             }
-            Ok(value)
+            Ok(self.decode_attribute(value))
+        } else {
+            let value = self
+                .consume_while(|c| !(c.is_whitespace() || c == '>' || c == '/'))
+                .to_owned();
+            Ok(self.decode_attribute(value))
         }
     }
 }
@@ -649,9 +1236,23 @@ mod tests {
 
     #[test]
     fn test_only_whitespace() {
-        let tokenizer = HtmlTokenizer::new("   \t\n   ");
+        let tokenizer = HtmlTokenizer::new("   \t\n   ")
+            .with_options(TokenizerOptions::new().preserve_whitespace(false));
         let tokens: Vec<_> = tokenizer.iter().collect();
         assert!(tokens.is_empty());
+
+        let tokenizer = HtmlTokenizer::new("   \t\n   ");
+        let tokens: Vec<_> = tokenizer.iter().map(|t| t.unwrap()).collect();
+        assert_eq!(tokens, vec![HtmlToken::Text("   \t\n   ".to_string())]);
+    }
+
+    #[test]
+    fn test_inter_element_whitespace_is_kept() {
+        let tokenizer = HtmlTokenizer::new("<b>a</b> <i>b</i>");
+        let tokens: Vec<_> = tokenizer.iter().map(|t| t.unwrap()).collect();
+
+        assert_eq!(tokens.len(), 7);
+        assert_eq!(tokens[3], HtmlToken::Text(" ".to_string()));
     }
 
     #[test]
@@ -672,6 +1273,258 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_text_character_references_are_decoded() {
+        let mut tokenizer = HtmlTokenizer::new("Fish &amp; Chips &#x2014; &lt;3");
+        let token = tokenizer.next_token().unwrap().unwrap();
+
+        assert_eq!(token, HtmlToken::Text("Fish & Chips — <3".to_string()));
+    }
+
+    #[test]
+    fn test_attribute_character_references_are_decoded() {
+        let mut tokenizer = HtmlTokenizer::new("<a title=\"&quot;hi&quot;\" alt=&#65;>");
+        let token = tokenizer.next_token().unwrap().unwrap();
+
+        match token {
+            HtmlToken::StartTag { attributes, .. } => {
+                assert_eq!(attributes[0], ("title".to_string(), "\"hi\"".to_string()));
+                assert_eq!(attributes[1], ("alt".to_string(), "A".to_string()));
+            }
+            _ => panic!("Expected StartTag"),
+        }
+    }
+
+    #[test]
+    fn test_attribute_references_use_attribute_rules() {
+        let input = "<a href=\"a&amp;b=1\" data-q=?x=1&copy=2&lang=en title=\"&copy 2024\">";
+        let mut tokenizer = HtmlTokenizer::new(input);
+        let token = tokenizer.next_token().unwrap().unwrap();
+
+        match token {
+            HtmlToken::StartTag { attributes, .. } => {
+                assert_eq!(attributes[0], ("href".to_string(), "a&b=1".to_string()));
+                assert_eq!(
+                    attributes[1],
+                    ("data-q".to_string(), "?x=1&copy=2&lang=en".to_string())
+                );
+                assert_eq!(attributes[2], ("title".to_string(), "© 2024".to_string()));
+            }
+            _ => panic!("Expected StartTag"),
+        }
+
+        // The same text outside an attribute is decoded.
+        let mut tokenizer = HtmlTokenizer::new("?x=1&copy=2");
+        assert_eq!(
+            tokenizer.next_token(),
+            Some(Ok(HtmlToken::Text("?x=1©=2".to_string())))
+        );
+    }
+
+    #[test]
+    fn test_keep_raw_character_references() {
+        let tokenizer =
+            HtmlTokenizer::new("<p title='&amp;'>&lt;raw&gt;</p>").with_decode_entities(false);
+        let tokens: Vec<_> = tokenizer.iter().map(|t| t.unwrap()).collect();
+
+        assert_eq!(
+            tokens[0],
+            HtmlToken::StartTag {
+                name: "p".to_string(),
+                attributes: vec![("title".to_string(), "&amp;".to_string())],
+                self_closing: false,
+            }
+        );
+        assert_eq!(tokens[1], HtmlToken::Text("&lt;raw&gt;".to_string()));
+    }
+
+    #[test]
+    fn test_script_content_is_raw_text() {
+        let input = "<script>if (a < b && c > d) { x = '</div>'; }</script><p>";
+        let tokenizer = HtmlTokenizer::new(input);
+        let tokens: Vec<_> = tokenizer.iter().map(|t| t.unwrap()).collect();
+
+        assert_eq!(tokens.len(), 4);
+        assert_eq!(
+            tokens[1],
+            HtmlToken::Text("if (a < b && c > d) { x = '</div>'; }".to_string())
+        );
+        assert_eq!(
+            tokens[2],
+            HtmlToken::EndTag {
+                name: "script".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_style_content_is_raw_text() {
+        let input = "<style>\n  a > b { content: \"&amp;\" }\n</STYLE >";
+        let tokenizer = HtmlTokenizer::new(input);
+        let tokens: Vec<_> = tokenizer.iter().map(|t| t.unwrap()).collect();
+
+        assert_eq!(tokens.len(), 3);
+        assert_eq!(
+            tokens[1],
+            HtmlToken::Text("\n  a > b { content: \"&amp;\" }\n".to_string())
+        );
+        assert_eq!(
+            tokens[2],
+            HtmlToken::EndTag {
+                name: "style".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_xmp_content_is_raw_text() {
+        let input = "<xmp><b>&amp; <></xmp><p>";
+        for spec_conformance in [false, true] {
+            let tokens: Vec<_> = HtmlTokenizer::new(input)
+                .with_spec_conformance(spec_conformance)
+                .iter()
+                .map(|t| t.unwrap())
+                .collect();
+
+            assert_eq!(tokens.len(), 4);
+            assert_eq!(tokens[1], HtmlToken::Text("<b>&amp; <>".to_string()));
+            assert_eq!(
+                tokens[2],
+                HtmlToken::EndTag {
+                    name: "xmp".to_string()
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn test_plaintext_consumes_rest_of_input() {
+        let mut iter = HtmlTokenizer::new("<p><PLAINTEXT>a <> &amp;</plaintext><!--").iter();
+        let tokens: Vec<_> = iter.by_ref().map(|t| t.unwrap()).collect();
+
+        assert_eq!(tokens.len(), 3);
+        assert_eq!(
+            tokens[2],
+            HtmlToken::Text("a <> &amp;</plaintext><!--".to_string())
+        );
+        assert!(iter.parse_errors().is_empty());
+    }
+
+    #[test]
+    fn test_raw_text_requires_matching_end_tag() {
+        let input = "<script>document.write('</scripts>')</script>";
+        let tokenizer = HtmlTokenizer::new(input);
+        let tokens: Vec<_> = tokenizer.iter().map(|t| t.unwrap()).collect();
+
+        assert_eq!(
+            tokens[1],
+            HtmlToken::Text("document.write('</scripts>')".to_string())
+        );
+    }
+
+    #[test]
+    fn test_empty_and_unterminated_raw_text() {
+        let tokenizer = HtmlTokenizer::new("<script></script><style>a { }");
+        let tokens: Vec<_> = tokenizer.iter().map(|t| t.unwrap()).collect();
+
+        assert_eq!(tokens.len(), 4);
+        assert_eq!(
+            tokens[1],
+            HtmlToken::EndTag {
+                name: "script".to_string()
+            }
+        );
+        assert_eq!(tokens[3], HtmlToken::Text("a { }".to_string()));
+    }
+
+    #[test]
+    fn test_title_is_rcdata() {
+        let tokenizer = HtmlTokenizer::new("<title>a <b> &amp; c</title>");
+        let tokens: Vec<_> = tokenizer.iter().map(|t| t.unwrap()).collect();
+
+        assert_eq!(tokens.len(), 3);
+        assert_eq!(tokens[1], HtmlToken::Text("a <b> & c".to_string()));
+    }
+
+    #[test]
+    fn test_cdata_in_html_content_is_bogus_comment() {
+        let tokenizer = HtmlTokenizer::new("<p><![CDATA[x<y]]></p>");
+        let tokens: Vec<_> = tokenizer.iter().map(|t| t.unwrap()).collect();
+
+        assert_eq!(tokens[1], HtmlToken::Comment("[CDATA[x<y]]".to_string()));
+        assert_eq!(
+            tokens[2],
+            HtmlToken::EndTag {
+                name: "p".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_cdata_in_html_content_ends_at_first_gt() {
+        let tokenizer = HtmlTokenizer::new("<![CDATA[a>b]]>");
+        let tokens: Vec<_> = tokenizer.iter().map(|t| t.unwrap()).collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                HtmlToken::Comment("[CDATA[a".to_string()),
+                HtmlToken::Text("b]]>".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cdata_in_foreign_content_is_text() {
+        let input = "<svg><style><![CDATA[ a > b { fill: red } ]]></style></svg><![CDATA[x]]>";
+        let tokenizer = HtmlTokenizer::new(input);
+        let tokens: Vec<_> = tokenizer.iter().map(|t| t.unwrap()).collect();
+
+        assert!(tokens.contains(&HtmlToken::Text(" a > b { fill: red } ".to_string())));
+        assert_eq!(
+            tokens.last(),
+            Some(&HtmlToken::Comment("[CDATA[x]]".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_token_spans() {
+        let input = "<p class=\"a\">héllo</p> <!-- c -->";
+        let tokenizer = HtmlTokenizer::new(input);
+        let spanned: Vec<_> = tokenizer.spanned_iter().map(|t| t.unwrap()).collect();
+
+        let sources: Vec<_> = spanned.iter().map(|t| t.span.source_text(input)).collect();
+        assert_eq!(
+            sources,
+            vec!["<p class=\"a\">", "héllo", "</p>", " ", "<!-- c -->"]
+        );
+        assert_eq!(spanned[1].span, Span::new(13, 19));
+    }
+
+    #[test]
+    fn test_attribute_spans() {
+        let input = "<input  disabled value = 'x'  id=a/>";
+        let mut tokenizer = HtmlTokenizer::new(input);
+        let spanned = tokenizer.next_spanned_token().unwrap().unwrap();
+
+        let sources: Vec<_> = spanned
+            .attribute_spans
+            .iter()
+            .map(|span| span.source_text(input))
+            .collect();
+        assert_eq!(sources, vec!["disabled", "value = 'x'", "id=a"]);
+    }
+
+    #[test]
+    fn test_raw_text_span() {
+        let input = "<script>a<b</script>";
+        let tokenizer = HtmlTokenizer::new(input);
+        let spanned: Vec<_> = tokenizer.spanned_iter().map(|t| t.unwrap()).collect();
+
+        assert_eq!(spanned[1].span.source_text(input), "a<b");
+        assert!(spanned[1].attribute_spans.is_empty());
+    }
+
     #[test]
     fn test_parse_example_domain_html() {
         let input = r#"<!doctype html><html lang="en"><head><title>Example Domain</title><meta name="viewport" content="width=device-width, initial-scale=1"><style>body{background:#eee;width:60vw;margin:15vh auto;font-family:system-ui,sans-serif}h1{font-size:1.5em}div{opacity:0.8}a:link,a:visited{color:#348}</style><body><div><h1>Example Domain</h1><p>This domain is for use in documentation examples without needing permission. Avoid use in operations.<p><a href="https://iana.org/domains/example">Learn more</a></div></body></html>"#;
@@ -698,13 +1551,313 @@ mod tests {
             None
         });
         assert!(html_attrs.is_some());
-        assert!(html_attrs
-            .unwrap()
-            .iter()
-            .any(|(name, value)| name == "lang" && value == "en"));
+        assert!(
+            html_attrs
+                .unwrap()
+                .iter()
+                .any(|(name, value)| name == "lang" && value == "en")
+        );
 
         assert!(tokens.iter().any(|token| {
             matches!(token, HtmlToken::Text(text) if text.contains("Example Domain"))
         }));
     }
+
+    #[test]
+    fn test_large_input_is_linear() {
+        // Quadratic cursor movement made a document this size take minutes.
+        let input = "<p class=\"x\">héllo</p>".repeat(50_000);
+        let count = HtmlTokenizer::new(&input).iter().count();
+        assert_eq!(count, 150_000);
+    }
+
+    #[test]
+    fn test_error_recovery_emits_text() {
+        let mut tokenizer = HtmlTokenizer::new("<p>a <> b</p>").with_error_recovery(true);
+        let mut tokens = Vec::new();
+        while let Some(token) = tokenizer.next_token() {
+            tokens.push(token.unwrap());
+        }
+
+        assert_eq!(
+            tokens,
+            vec![
+                HtmlToken::StartTag {
+                    name: "p".to_string(),
+                    attributes: vec![],
+                    self_closing: false,
+                },
+                HtmlToken::Text("a ".to_string()),
+                HtmlToken::Text("<> b".to_string()),
+                HtmlToken::EndTag {
+                    name: "p".to_string()
+                },
+            ]
+        );
+        assert_eq!(
+            tokenizer.parse_errors(),
+            &[ParseError {
+                error: TokenizeError::InvalidTag,
+                span: Span::new(5, 6),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_error_recovery_unterminated_tag() {
+        let tokenizer = HtmlTokenizer::new("text <div class=\"x").with_error_recovery(true);
+        let mut iter = tokenizer.iter();
+        let tokens: Vec<_> = iter.by_ref().map(|t| t.unwrap()).collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                HtmlToken::Text("text ".to_string()),
+                HtmlToken::Text("<div class=\"x".to_string()),
+            ]
+        );
+        assert_eq!(
+            iter.parse_errors().first().map(|e| &e.error),
+            Some(&TokenizeError::InvalidAttribute)
+        );
+    }
+
+    #[test]
+    fn test_errors_abort_without_recovery() {
+        let mut tokenizer = HtmlTokenizer::new("<>");
+        assert_eq!(tokenizer.next_token(), Some(Err(TokenizeError::InvalidTag)));
+        assert!(tokenizer.parse_errors().is_empty());
+    }
+
+    #[test]
+    fn test_parse_errors_as_tokens() {
+        let error = HtmlToken::ParseError(ParseError {
+            error: TokenizeError::InvalidTag,
+            span: Span::new(5, 6),
+        });
+        let tokens: Vec<_> = HtmlTokenizer::new("<p>a <> b</p>")
+            .with_options(
+                TokenizerOptions::new()
+                    .error_recovery(true)
+                    .emit_parse_errors(true),
+            )
+            .iter()
+            .map(|t| t.unwrap())
+            .collect();
+
+        assert_eq!(tokens.get(1), Some(&HtmlToken::Text("a ".to_string())));
+        assert_eq!(tokens.get(2), Some(&error));
+        assert_eq!(tokens.get(3), Some(&HtmlToken::Text("<> b".to_string())));
+        assert_eq!(tokens.len(), 5);
+
+        let spanned: Vec<_> = HtmlTokenizer::new("<a href=a HREF=b>x")
+            .with_options(TokenizerOptions::new().emit_parse_errors(true))
+            .spanned_iter()
+            .map(|t| t.unwrap())
+            .collect();
+        assert_eq!(spanned.len(), 3);
+        assert_eq!(
+            spanned.first().map(|t| (&t.token, t.span)),
+            Some((
+                &HtmlToken::ParseError(ParseError {
+                    error: TokenizeError::DuplicateAttribute,
+                    span: Span::new(10, 16),
+                }),
+                Span::new(10, 16)
+            ))
+        );
+    }
+
+    #[test]
+    fn test_spec_parse_errors_as_tokens() {
+        let input = "<p>&notanentity; <a b='c'd></p>";
+        let plain: Vec<_> = HtmlTokenizer::new(input)
+            .with_spec_conformance(true)
+            .iter()
+            .map(|t| t.unwrap())
+            .collect();
+        let mut iter = HtmlTokenizer::new(input)
+            .with_options(
+                TokenizerOptions::new()
+                    .spec_conformance(true)
+                    .emit_parse_errors(true),
+            )
+            .iter();
+        let tokens: Vec<_> = iter.by_ref().map(|t| t.unwrap()).collect();
+
+        let errors: Vec<_> = tokens
+            .iter()
+            .filter_map(|token| match token {
+                HtmlToken::ParseError(error) => Some(error),
+                _ => None,
+            })
+            .collect();
+        assert!(!errors.is_empty());
+        assert_eq!(errors, iter.parse_errors().iter().collect::<Vec<_>>());
+
+        // Without the errors, the stream is the same as when not emitting them.
+        let others: Vec<_> = tokens
+            .into_iter()
+            .filter(|token| !matches!(token, HtmlToken::ParseError(_)))
+            .collect();
+        assert_eq!(others, plain);
+    }
+
+    #[test]
+    fn test_names_are_lowercased() {
+        let tokens: Vec<_> = HtmlTokenizer::new("<DIV Class=X></Div>")
+            .iter()
+            .map(|t| t.unwrap())
+            .collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                HtmlToken::StartTag {
+                    name: "div".to_string(),
+                    attributes: vec![("class".to_string(), "X".to_string())],
+                    self_closing: false,
+                },
+                HtmlToken::EndTag {
+                    name: "div".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_names_keep_case_when_disabled() {
+        let mut tokenizer = HtmlTokenizer::new("<DIV Class=X>").with_lowercase_names(false);
+
+        assert_eq!(
+            tokenizer.next_token(),
+            Some(Ok(HtmlToken::StartTag {
+                name: "DIV".to_string(),
+                attributes: vec![("Class".to_string(), "X".to_string())],
+                self_closing: false,
+            }))
+        );
+    }
+
+    #[test]
+    fn test_duplicate_attributes_keep_first() {
+        let mut tokenizer = HtmlTokenizer::new("<a href=a HREF=b>");
+        let token = tokenizer.next_spanned_token().unwrap().unwrap();
+
+        assert_eq!(
+            token.token,
+            HtmlToken::StartTag {
+                name: "a".to_string(),
+                attributes: vec![("href".to_string(), "a".to_string())],
+                self_closing: false,
+            }
+        );
+        assert_eq!(token.attribute_spans, vec![Span::new(3, 9)]);
+        assert_eq!(
+            tokenizer.parse_errors(),
+            &[ParseError {
+                error: TokenizeError::DuplicateAttribute,
+                span: Span::new(10, 16),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_end_tag_attributes_are_ignored() {
+        let mut tokenizer = HtmlTokenizer::new("<div></div class=\"x\" id=y/>");
+        let tokens: Vec<_> = std::iter::from_fn(|| tokenizer.next_spanned_token())
+            .map(|t| t.unwrap())
+            .collect();
+
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(
+            tokens[1].token,
+            HtmlToken::EndTag {
+                name: "div".to_string()
+            }
+        );
+        assert!(tokens[1].attribute_spans.is_empty());
+        assert_eq!(
+            tokenizer.parse_errors(),
+            &[ParseError {
+                error: TokenizeError::InvalidTag,
+                span: Span::new(11, 26),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_stray_solidus_in_tag() {
+        let mut tokenizer = HtmlTokenizer::new("<br/ ><input / type=text/>");
+        let tokens: Vec<_> = std::iter::from_fn(|| tokenizer.next_token())
+            .map(|t| t.unwrap())
+            .collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                HtmlToken::StartTag {
+                    name: "br".to_string(),
+                    attributes: vec![],
+                    self_closing: false,
+                },
+                HtmlToken::StartTag {
+                    name: "input".to_string(),
+                    attributes: vec![("type".to_string(), "text".to_string())],
+                    self_closing: true,
+                },
+            ]
+        );
+        let errors: Vec<_> = tokenizer.parse_errors().iter().map(|e| e.span).collect();
+        assert_eq!(errors, vec![Span::new(3, 4), Span::new(13, 14)]);
+    }
+
+    #[test]
+    fn test_bogus_comments() {
+        let tokens: Vec<_> = HtmlTokenizer::new("<?xml version=\"1.0\"?><!foo bar><!DocType html>")
+            .iter()
+            .map(|t| t.unwrap())
+            .collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                HtmlToken::Comment("?xml version=\"1.0\"?".to_string()),
+                HtmlToken::Comment("foo bar".to_string()),
+                HtmlToken::Doctype("DocType html".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_options_filter_comments_and_keep_whitespace() {
+        let options = TokenizerOptions::new()
+            .preserve_comments(false)
+            .preserve_whitespace(true);
+        let tokens: Vec<_> = HtmlTokenizer::new("<b>a</b> <!-- c --><i>b</i>")
+            .with_options(options)
+            .iter()
+            .map(|t| t.unwrap())
+            .collect();
+
+        assert_eq!(tokens.len(), 7);
+        assert_eq!(tokens[3], HtmlToken::Text(" ".to_string()));
+        assert!(!tokens.iter().any(|t| matches!(t, HtmlToken::Comment(_))));
+    }
+
+    #[test]
+    fn test_void_elements() {
+        let tokens: Vec<_> = HtmlTokenizer::new("<IMG src=a><br/><div/><svg><path/></svg>")
+            .iter()
+            .map(|t| t.unwrap())
+            .collect();
+
+        assert!(tokens[0].is_void());
+        assert!(tokens[0].closes_immediately(false));
+        assert!(tokens[1].is_void());
+        assert!(!tokens[2].is_void());
+        assert!(!tokens[2].closes_immediately(false));
+        assert!(tokens[4].closes_immediately(true));
+        assert!(!HtmlToken::Text("br".to_string()).is_void());
+    }
 }
@@ -0,0 +1,121 @@
+use super::{HtmlTokenizer, ParseError, SpannedToken, TokenizeError, TokenizerState};
+
+/// A saved position of an [`HtmlTokenizer`], taken with
+/// [`checkpoint`](HtmlTokenizer::checkpoint) and returned to with
+/// [`restore`](HtmlTokenizer::restore).
+///
+/// This lets a preload scanner tokenize ahead of a blocked tree builder,
+/// looking for subresources to fetch, and then rewind so the tokens are
+/// produced again for the tree builder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checkpoint {
+    position: usize,
+    state: TokenizerState,
+    parse_errors: usize,
+    fatal_error: Option<ParseError>,
+    emitted_errors: usize,
+    held_token: Option<Result<SpannedToken, TokenizeError>>,
+    furthest_position: usize,
+}
+
+impl HtmlTokenizer<'_> {
+    /// Saves where the tokenizer is, so that it can be rewound to here.
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            position: self.position,
+            state: self.state(),
+            parse_errors: self.parse_errors.len(),
+            fatal_error: self.fatal_error.clone(),
+            emitted_errors: self.emitted_errors,
+            held_token: self.held_token.clone(),
+            furthest_position: self.furthest_position,
+        }
+    }
+
+    /// Rewinds to `checkpoint`, which must have been taken from this
+    /// tokenizer. The tokens after it will be returned again, and parse
+    /// errors recorded since are dropped, as they will be recorded again.
+    pub fn restore(&mut self, checkpoint: &Checkpoint) {
+        let checkpoint = checkpoint.clone();
+        self.position = checkpoint.position.min(self.input.len());
+        self.raw_text_element = checkpoint.state.raw_text_element;
+        self.foreign_elements = checkpoint.state.foreign_elements;
+        self.plaintext = checkpoint.state.plaintext;
+        self.parse_errors.truncate(checkpoint.parse_errors);
+        self.fatal_error = checkpoint.fatal_error;
+        self.emitted_errors = checkpoint.emitted_errors;
+        self.held_token = checkpoint.held_token;
+        self.furthest_position = checkpoint.furthest_position;
+        self.attribute_spans.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::html_tokenizer::{HtmlToken, HtmlTokenizer, TokenizerOptions};
+
+    #[test]
+    fn test_restore_replays_tokens() {
+        let input = "<p>a</p><script>x</script><img src=b.png><svg><clippath/></svg>";
+        let mut tokenizer = HtmlTokenizer::new(input);
+        tokenizer.next_token();
+
+        let checkpoint = tokenizer.checkpoint();
+        let ahead: Vec<_> = std::iter::from_fn(|| tokenizer.next_token()).collect();
+        assert!(tokenizer.next_token().is_none());
+
+        tokenizer.restore(&checkpoint);
+        let replayed: Vec<_> = std::iter::from_fn(|| tokenizer.next_token()).collect();
+        assert_eq!(replayed, ahead);
+        assert_eq!(replayed.len(), 9);
+    }
+
+    #[test]
+    fn test_preload_scan_from_inside_raw_text() {
+        let mut tokenizer = HtmlTokenizer::new("<script>a<b</script><img src=x.png><p>");
+        tokenizer.next_token();
+        let checkpoint = tokenizer.checkpoint();
+
+        // Scan ahead for subresources, then hand the rest to the parser.
+        let sources: Vec<String> = std::iter::from_fn(|| tokenizer.next_token())
+            .filter_map(|token| match token {
+                Ok(HtmlToken::StartTag {
+                    name, attributes, ..
+                }) if name == "img" => attributes
+                    .into_iter()
+                    .find(|(n, _)| n == "src")
+                    .map(|(_, v)| v),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(sources, vec!["x.png".to_string()]);
+
+        tokenizer.restore(&checkpoint);
+        assert_eq!(
+            tokenizer.next_token(),
+            Some(Ok(HtmlToken::Text("a<b".to_string())))
+        );
+    }
+
+    #[test]
+    fn test_restore_drops_later_parse_errors() {
+        let mut tokenizer = HtmlTokenizer::new("<a x=1 x=2>b <> c").with_options(
+            TokenizerOptions::new()
+                .error_recovery(true)
+                .emit_parse_errors(true),
+        );
+        tokenizer.next_token();
+        tokenizer.next_token();
+        assert_eq!(tokenizer.parse_errors().len(), 1);
+
+        let checkpoint = tokenizer.checkpoint();
+        let ahead: Vec<_> = std::iter::from_fn(|| tokenizer.next_token()).collect();
+        assert_eq!(tokenizer.parse_errors().len(), 2);
+
+        tokenizer.restore(&checkpoint);
+        assert_eq!(tokenizer.parse_errors().len(), 1);
+        let replayed: Vec<_> = std::iter::from_fn(|| tokenizer.next_token()).collect();
+        assert_eq!(replayed, ahead);
+        assert_eq!(tokenizer.parse_errors().len(), 2);
+    }
+}
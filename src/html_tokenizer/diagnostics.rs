@@ -0,0 +1,156 @@
+use std::fmt;
+
+use super::ParseError;
+
+/// A position in the source, both as a byte offset and as a 1-based line
+/// and column. Columns count characters, not bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Location {
+    /// The location of byte `offset` in `source`. An offset past the end is
+    /// treated as the end.
+    pub fn new(source: &str, offset: usize) -> Self {
+        let mut line = 1;
+        let mut column = 1;
+        for (index, c) in source.char_indices() {
+            if index >= offset {
+                break;
+            }
+            if c == '\n' {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+        }
+
+        Self {
+            offset: offset.min(source.len()),
+            line,
+            column,
+        }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+impl ParseError {
+    /// Where the markup the error is about starts in `source`, which must
+    /// be the input the error came from.
+    pub fn location(&self, source: &str) -> Location {
+        Location::new(source, self.span.start)
+    }
+
+    /// Renders the error with the source line it occurred on and a caret
+    /// under the offending markup:
+    ///
+    /// ```text
+    /// error: Invalid HTML tag
+    ///  --> 2:4
+    ///   |
+    /// 2 | <p><a =x>y</a></p>
+    ///   |    ^^^
+    /// ```
+    pub fn render_snippet(&self, source: &str) -> String {
+        let location = self.location(source);
+        let start = location.offset;
+
+        let line_start = source
+            .get(..start)
+            .and_then(|before| before.rfind('\n'))
+            .map_or(0, |newline| newline + 1);
+        let line_end = source
+            .get(start..)
+            .and_then(|after| after.find('\n'))
+            .map_or(source.len(), |newline| start + newline);
+        let line = source
+            .get(line_start..line_end)
+            .unwrap_or_default()
+            .trim_end_matches('\r');
+
+        // Keep tabs so the caret lines up with the source line.
+        let padding: String = source
+            .get(line_start..start)
+            .unwrap_or_default()
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let underlined = source
+            .get(start..self.span.end.min(line_end).max(start))
+            .map_or(0, |text| text.trim_end_matches('\r').chars().count());
+        let carets = "^".repeat(underlined.max(1));
+
+        let line_number = location.line.to_string();
+        let gutter = " ".repeat(line_number.len());
+        format!(
+            "error: {}\n{gutter}--> {location}\n{gutter} |\n{line_number} | {line}\n{gutter} | {padding}{carets}\n",
+            self.error
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html_tokenizer::{HtmlTokenizer, Span, TokenizeError};
+
+    #[test]
+    fn test_location() {
+        let source = "<p>\n  é<b>\n";
+        assert_eq!(
+            Location::new(source, 0),
+            Location {
+                offset: 0,
+                line: 1,
+                column: 1
+            }
+        );
+        let b = source.find("<b>").unwrap();
+        assert_eq!(Location::new(source, b).to_string(), "2:4");
+        assert_eq!(Location::new(source, 100).offset, source.len());
+    }
+
+    #[test]
+    fn test_fatal_error_snippet() {
+        let source = "<html>\n<p><a =x>y</a></p>\n</html>";
+        let mut tokenizer = HtmlTokenizer::new(source);
+        let error = loop {
+            match tokenizer.next_token() {
+                Some(Ok(_)) => {}
+                Some(Err(error)) => break error,
+                None => panic!("expected an error"),
+            }
+        };
+
+        let fatal = tokenizer.fatal_error().unwrap();
+        assert_eq!(fatal.error, error);
+        assert_eq!(fatal.location(source).to_string(), "2:4");
+        assert_eq!(
+            fatal.render_snippet(source),
+            "error: Invalid HTML tag\n --> 2:4\n  |\n2 | <p><a =x>y</a></p>\n  |    ^^^\n"
+        );
+    }
+
+    #[test]
+    fn test_snippet_of_error_at_end_of_input() {
+        let source = "<p>\n\t<!-- open";
+        let error = ParseError {
+            error: TokenizeError::MalformedComment,
+            span: Span::new(5, source.len()),
+        };
+
+        assert_eq!(
+            error.render_snippet(source),
+            "error: Malformed HTML comment\n --> 2:2\n  |\n2 | \t<!-- open\n  | \t^^^^^^^^^\n"
+        );
+    }
+}
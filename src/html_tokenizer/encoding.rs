@@ -0,0 +1,324 @@
+//! Character encoding detection for HTML bytes, following the encoding
+//! sniffing algorithm (<https://html.spec.whatwg.org/multipage/parsing.html#encoding-sniffing-algorithm>).
+
+use encoding_rs::{Encoding, UTF_8, UTF_16BE, UTF_16LE, WINDOWS_1252, X_USER_DEFINED};
+
+/// How many bytes the `<meta>` prescan looks at, as the spec suggests.
+const PRESCAN_LENGTH: usize = 1024;
+
+/// Decodes an HTML document so it can be passed to
+/// [`HtmlTokenizer`](super::HtmlTokenizer).
+///
+/// Returns the text and the encoding used. See [`sniff_encoding`] for how
+/// the encoding is chosen. Malformed sequences become U+FFFD.
+pub fn decode_html(bytes: &[u8], content_type: Option<&str>) -> (String, &'static Encoding) {
+    let encoding = sniff_encoding(bytes, content_type);
+    let (text, _) = encoding.decode_with_bom_removal(bytes);
+    (text.into_owned(), encoding)
+}
+
+/// Picks the encoding of an HTML document from, in order:
+///
+/// 1. a byte order mark;
+/// 2. the `charset` parameter of the `Content-Type` header, if given;
+/// 3. a `<meta charset>` or `<meta http-equiv="Content-Type">` in the first
+///    1024 bytes.
+///
+/// Failing those, the document is taken as UTF-8 if it is valid UTF-8 and
+/// as windows-1252 (the spec's fallback for most locales) otherwise.
+pub fn sniff_encoding(bytes: &[u8], content_type: Option<&str>) -> &'static Encoding {
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return encoding;
+    }
+
+    let from_header = content_type
+        .and_then(charset_from_content_type)
+        .and_then(|label| Encoding::for_label(label.as_bytes()));
+    if let Some(encoding) = from_header {
+        return encoding;
+    }
+
+    let prefix = bytes.get(..PRESCAN_LENGTH).unwrap_or(bytes);
+    if let Some(encoding) = prescan(prefix) {
+        return encoding;
+    }
+
+    if std::str::from_utf8(bytes).is_ok() {
+        UTF_8
+    } else {
+        WINDOWS_1252
+    }
+}
+
+/// The `charset` parameter of a `Content-Type` value such as
+/// `text/html; charset="Shift_JIS"`.
+fn charset_from_content_type(content_type: &str) -> Option<&str> {
+    content_type.split(';').skip(1).find_map(|parameter| {
+        let (name, value) = parameter.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("charset")
+            .then(|| value.trim().trim_matches(['"', '\'']))
+    })
+}
+
+/// The prescan for a `<meta>` that declares the encoding.
+fn prescan(bytes: &[u8]) -> Option<&'static Encoding> {
+    let mut scanner = Prescanner { bytes, position: 0 };
+
+    while scanner.position < bytes.len() {
+        let rest = scanner.rest();
+        if rest.starts_with(b"<!--") {
+            scanner.position +=
+                memchr::memmem::find(rest, b"-->").map_or(rest.len(), |end| end + 3);
+        } else if starts_with_ignore_case(rest, b"<meta")
+            && rest
+                .get(5)
+                .is_some_and(|&b| b.is_ascii_whitespace() || b == b'/')
+        {
+            scanner.position += 5;
+            if let Some(encoding) = scanner.meta_encoding() {
+                return Some(encoding);
+            }
+        } else if rest.starts_with(b"<")
+            && rest
+                .get(1..)
+                .map(|after| after.strip_prefix(b"/").unwrap_or(after))
+                .and_then(|name| name.first())
+                .is_some_and(u8::is_ascii_alphabetic)
+        {
+            // Skip the tag name, then its attributes, whose values may contain `>`.
+            scanner.position += rest
+                .iter()
+                .position(|&b| b.is_ascii_whitespace() || b == b'>')
+                .unwrap_or(rest.len());
+            while scanner.attribute().is_some() {}
+        } else if rest.starts_with(b"<!") || rest.starts_with(b"</") || rest.starts_with(b"<?") {
+            scanner.position += memchr::memchr(b'>', rest).map_or(rest.len(), |end| end + 1);
+        } else {
+            scanner.position += 1;
+        }
+    }
+
+    None
+}
+
+struct Prescanner<'b> {
+    bytes: &'b [u8],
+    position: usize,
+}
+
+impl Prescanner<'_> {
+    fn rest(&self) -> &[u8] {
+        self.bytes.get(self.position..).unwrap_or_default()
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.position).copied()
+    }
+
+    fn skip_while(&mut self, predicate: impl Fn(u8) -> bool) {
+        while self.peek().is_some_and(&predicate) {
+            self.position += 1;
+        }
+    }
+
+    /// Reads the attributes of a `<meta>` tag and returns the encoding it
+    /// declares, if any.
+    fn meta_encoding(&mut self) -> Option<&'static Encoding> {
+        let mut got_pragma = false;
+        let mut need_pragma = None;
+        let mut charset = None;
+
+        while let Some((name, value)) = self.attribute() {
+            match name.as_str() {
+                "http-equiv" => got_pragma |= value == "content-type",
+                "content" if charset.is_none() => {
+                    if let Some(label) = charset_from_meta_content(&value) {
+                        charset = Encoding::for_label(label.as_bytes());
+                        need_pragma = Some(true);
+                    }
+                }
+                "charset" if need_pragma != Some(false) => {
+                    charset = Encoding::for_label(value.as_bytes());
+                    need_pragma = Some(false);
+                }
+                _ => {}
+            }
+        }
+
+        let charset = charset?;
+        if need_pragma? && !got_pragma {
+            return None;
+        }
+        // A document that could be read this far as ASCII is not UTF-16.
+        Some(if charset == UTF_16BE || charset == UTF_16LE {
+            UTF_8
+        } else if charset == X_USER_DEFINED {
+            WINDOWS_1252
+        } else {
+            charset
+        })
+    }
+
+    /// Reads one attribute, returning its lowercased name and value, or
+    /// `None` at the end of the tag. The spec's "get an attribute".
+    fn attribute(&mut self) -> Option<(String, String)> {
+        self.skip_while(|b| b.is_ascii_whitespace() || b == b'/');
+        if matches!(self.peek(), None | Some(b'>')) {
+            return None;
+        }
+
+        let name_start = self.position;
+        self.position += 1;
+        self.skip_while(|b| !(b.is_ascii_whitespace() || matches!(b, b'/' | b'>' | b'=')));
+        let name = self.lowercase(name_start, self.position);
+
+        self.skip_while(|b| b.is_ascii_whitespace());
+        if self.peek() != Some(b'=') {
+            return Some((name, String::new()));
+        }
+        self.position += 1;
+        self.skip_while(|b| b.is_ascii_whitespace());
+
+        let value = match self.peek() {
+            Some(quote @ (b'"' | b'\'')) => {
+                self.position += 1;
+                let start = self.position;
+                self.skip_while(|b| b != quote);
+                let value = self.lowercase(start, self.position);
+                self.position += 1;
+                value
+            }
+            Some(b'>') | None => String::new(),
+            Some(_) => {
+                let start = self.position;
+                self.skip_while(|b| !(b.is_ascii_whitespace() || b == b'>'));
+                self.lowercase(start, self.position)
+            }
+        };
+        Some((name, value))
+    }
+
+    fn lowercase(&self, start: usize, end: usize) -> String {
+        String::from_utf8_lossy(self.bytes.get(start..end).unwrap_or_default()).to_ascii_lowercase()
+    }
+}
+
+/// The spec's "extracting a character encoding from a meta element", on a
+/// `content` attribute such as `text/html; charset=euc-jp`.
+fn charset_from_meta_content(content: &str) -> Option<&str> {
+    let mut rest = content;
+    loop {
+        let after = rest.get(rest.to_ascii_lowercase().find("charset")? + "charset".len()..)?;
+        let after = after.trim_start_matches(|c: char| c.is_ascii_whitespace());
+        let Some(value) = after.strip_prefix('=') else {
+            rest = after;
+            continue;
+        };
+        let value = value.trim_start_matches(|c: char| c.is_ascii_whitespace());
+
+        return match value.chars().next()? {
+            quote @ ('"' | '\'') => {
+                let value = value.get(1..)?;
+                let end = value.find(quote)?;
+                value.get(..end)
+            }
+            _ => value
+                .split(|c: char| c.is_ascii_whitespace() || c == ';')
+                .next(),
+        };
+    }
+}
+
+fn starts_with_ignore_case(bytes: &[u8], prefix: &[u8]) -> bool {
+    bytes
+        .get(..prefix.len())
+        .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::{EUC_JP, SHIFT_JIS};
+
+    /// "日本語" in Shift_JIS.
+    const NIHONGO_SHIFT_JIS: [u8; 6] = [0x93, 0xfa, 0x96, 0x7b, 0x8c, 0xea];
+
+    #[test]
+    fn test_byte_order_mark_wins() {
+        let bytes = b"\xef\xbb\xbf<meta charset=shift_jis>ok";
+        assert_eq!(
+            sniff_encoding(bytes, Some("text/html; charset=euc-jp")),
+            UTF_8
+        );
+        assert_eq!(
+            decode_html(bytes, None).0,
+            "<meta charset=shift_jis>ok".to_string()
+        );
+    }
+
+    #[test]
+    fn test_content_type_charset() {
+        let mut bytes = b"<p>".to_vec();
+        bytes.extend_from_slice(&NIHONGO_SHIFT_JIS);
+
+        let (text, encoding) = decode_html(&bytes, Some("text/html; charset=\"Shift_JIS\""));
+        assert_eq!(encoding, SHIFT_JIS);
+        assert_eq!(text, "<p>日本語".to_string());
+    }
+
+    #[test]
+    fn test_meta_charset() {
+        let mut bytes = b"<!DOCTYPE html><!-- <meta charset=utf-8> -->\
+            <html lang=\"ja\" data-x=\"a>b\"><head><meta charset=\"Shift_JIS\">"
+            .to_vec();
+        bytes.extend_from_slice(&NIHONGO_SHIFT_JIS);
+
+        let (text, encoding) = decode_html(&bytes, Some("text/html"));
+        assert_eq!(encoding, SHIFT_JIS);
+        assert!(text.ends_with("日本語"));
+    }
+
+    #[test]
+    fn test_meta_http_equiv() {
+        let bytes = b"<meta http-equiv=\"Content-Type\" content=\"text/html; charset=EUC-JP\">\
+            \xc6\xfc\xcb\xdc\xb8\xec";
+        assert_eq!(sniff_encoding(bytes, None), EUC_JP);
+        assert!(decode_html(bytes, None).0.ends_with("日本語"));
+
+        // Without the pragma, `content` alone does not count.
+        let bytes = b"<meta content=\"text/html; charset=EUC-JP\">";
+        assert_eq!(sniff_encoding(bytes, None), UTF_8);
+    }
+
+    #[test]
+    fn test_meta_utf16_means_utf8() {
+        assert_eq!(sniff_encoding(b"<meta charset=utf-16le>", None), UTF_8);
+    }
+
+    #[test]
+    fn test_fallback() {
+        assert_eq!(sniff_encoding("<p>café</p>".as_bytes(), None), UTF_8);
+
+        let (text, encoding) = decode_html(b"<p>\x93quoted\x94 caf\xe9</p>", None);
+        assert_eq!(encoding, WINDOWS_1252);
+        assert_eq!(text, "<p>\u{201c}quoted\u{201d} café</p>".to_string());
+    }
+
+    #[test]
+    fn test_charset_from_meta_content() {
+        assert_eq!(
+            charset_from_meta_content("text/html; charset=euc-jp"),
+            Some("euc-jp")
+        );
+        assert_eq!(
+            charset_from_meta_content("text/html;charset = 'shift_jis' "),
+            Some("shift_jis")
+        );
+        assert_eq!(
+            charset_from_meta_content("charset;charset=utf-8"),
+            Some("utf-8")
+        );
+        assert_eq!(charset_from_meta_content("text/html"), None);
+    }
+}
@@ -0,0 +1,213 @@
+mod named_references;
+
+use named_references::NAMED_REFERENCES;
+
+/// Replacements for numeric references to C1 control code points, which
+/// browsers interpret as Windows-1252 for compatibility.
+const C1_REPLACEMENTS: [char; 32] = [
+    '\u{20ac}', '\u{81}', '\u{201a}', '\u{192}', '\u{201e}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{2c6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8d}', '\u{17d}', '\u{8f}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201c}', '\u{201d}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{2dc}', '\u{2122}', '\u{161}', '\u{203a}', '\u{153}', '\u{9d}', '\u{17e}', '\u{178}',
+];
+
+/// Decodes named (`&amp;`) and numeric (`&#65;`, `&#x41;`) character references.
+///
+/// References that are not recognized are left in the output verbatim.
+pub fn decode_character_references(input: &str) -> String {
+    decode(input, false)
+}
+
+/// Like [`decode_character_references`], but for attribute values.
+///
+/// A legacy reference without its semicolon is left alone if a letter,
+/// digit, or `=` follows, so `?a=1&copy=2` keeps its query string.
+pub fn decode_attribute_character_references(input: &str) -> String {
+    decode(input, true)
+}
+
+fn decode(input: &str, in_attribute: bool) -> String {
+    if !input.contains('&') {
+        return input.to_owned();
+    }
+
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(amp) = rest.find('&') {
+        let (before, after_amp) = rest.split_at(amp);
+        output.push_str(before);
+        let reference = after_amp.get(1..).unwrap_or_default();
+
+        if let Some((decoded, consumed)) = decode_reference(reference, in_attribute) {
+            output.push_str(&decoded);
+            rest = reference.get(consumed..).unwrap_or_default();
+        } else {
+            output.push('&');
+            rest = reference;
+        }
+    }
+
+    output.push_str(rest);
+    output
+}
+
+/// Decodes the reference at the start of `reference` (just after the `&`),
+/// returning the replacement text and the number of bytes consumed.
+fn decode_reference(reference: &str, in_attribute: bool) -> Option<(String, usize)> {
+    if let Some(numeric) = reference.strip_prefix('#') {
+        let (c, consumed) = decode_numeric(numeric)?;
+        return Some((c.to_string(), consumed + 1));
+    }
+
+    let (value, consumed) = decode_named(reference)?;
+    let terminated = reference.get(..consumed)?.ends_with(';');
+    let next = reference.get(consumed..)?.chars().next();
+    if in_attribute && !terminated && next.is_some_and(|c| c == '=' || c.is_ascii_alphanumeric()) {
+        return None;
+    }
+    Some((value.to_owned(), consumed))
+}
+
+/// Finds the longest reference name that `reference` starts with, as the
+/// spec requires (`&notin;` is `∉`, but `&notit;` is `¬it;`).
+fn decode_named(reference: &str) -> Option<(&'static str, usize)> {
+    let name_len = reference
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(reference.len());
+    // Names are ASCII, so every length up to here is a char boundary.
+    let longest = if reference.get(name_len..)?.starts_with(';') {
+        name_len + 1
+    } else {
+        name_len
+    };
+
+    (1..=longest).rev().find_map(|len| {
+        let candidate = reference.get(..len)?;
+        let index = NAMED_REFERENCES
+            .binary_search_by(|(name, _)| (*name).cmp(candidate))
+            .ok()?;
+        NAMED_REFERENCES.get(index).map(|(_, value)| (*value, len))
+    })
+}
+
+/// Decodes the digits of a numeric reference (just after the `&#`).
+fn decode_numeric(numeric: &str) -> Option<(char, usize)> {
+    let (digits_start, radix) = match numeric.as_bytes().first() {
+        Some(b'x' | b'X') => (1, 16),
+        _ => (0, 10),
+    };
+    let digits = numeric.get(digits_start..)?;
+    let digits_len = digits
+        .find(|c: char| !c.is_digit(radix))
+        .unwrap_or(digits.len());
+    if digits_len == 0 {
+        return None;
+    }
+
+    // Saturate rather than overflow; anything this large is out of range anyway.
+    let value = digits
+        .get(..digits_len)?
+        .chars()
+        .filter_map(|c| c.to_digit(radix))
+        .fold(0_u32, |acc, digit| {
+            acc.saturating_mul(radix).saturating_add(digit)
+        });
+
+    let mut consumed = digits_start + digits_len;
+    if numeric.get(consumed..)?.starts_with(';') {
+        consumed += 1;
+    }
+
+    Some((numeric_reference_char(value), consumed))
+}
+
+/// Maps a numeric reference's code point to the character it stands for.
+fn numeric_reference_char(value: u32) -> char {
+    match value {
+        0 => char::REPLACEMENT_CHARACTER,
+        0x80..=0x9f => C1_REPLACEMENTS
+            .get((value - 0x80) as usize)
+            .copied()
+            .unwrap_or(char::REPLACEMENT_CHARACTER),
+        _ => char::from_u32(value).unwrap_or(char::REPLACEMENT_CHARACTER),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_named_references() {
+        assert_eq!(
+            decode_character_references("a &lt; b &amp;&amp; c &gt; d"),
+            "a < b && c > d"
+        );
+        assert_eq!(decode_character_references("&copy; 2024"), "© 2024");
+    }
+
+    #[test]
+    fn test_decode_numeric_references() {
+        assert_eq!(decode_character_references("&#65;&#x42;&#X43;"), "ABC");
+        assert_eq!(decode_character_references("&#12354;"), "あ");
+    }
+
+    #[test]
+    fn test_numeric_reference_without_semicolon() {
+        assert_eq!(decode_character_references("&#65 B"), "A B");
+    }
+
+    #[test]
+    fn test_invalid_numeric_references() {
+        assert_eq!(decode_character_references("&#0;"), "\u{fffd}");
+        assert_eq!(decode_character_references("&#xD800;"), "\u{fffd}");
+        assert_eq!(decode_character_references("&#x110000;"), "\u{fffd}");
+        assert_eq!(
+            decode_character_references("&#99999999999999999999;"),
+            "\u{fffd}"
+        );
+        assert_eq!(decode_character_references("&#x80;"), "€");
+    }
+
+    #[test]
+    fn test_unknown_references_are_kept() {
+        assert_eq!(
+            decode_character_references("&unknown; & &#; &#x;"),
+            "&unknown; & &#; &#x;"
+        );
+        assert_eq!(decode_character_references("AT&T"), "AT&T");
+    }
+
+    #[test]
+    fn test_longest_named_match() {
+        assert_eq!(decode_character_references("&notin;"), "∉");
+        assert_eq!(decode_character_references("&notit;"), "¬it;");
+        assert_eq!(
+            decode_character_references("&NotEqualTilde;"),
+            "\u{2242}\u{338}"
+        );
+        assert_eq!(
+            decode_character_references("&rarr;&CounterClockwiseContourIntegral;"),
+            "→∳"
+        );
+    }
+
+    #[test]
+    fn test_legacy_references_without_semicolon() {
+        assert_eq!(decode_character_references("&copy 2024 &amp"), "© 2024 &");
+        assert_eq!(decode_character_references("&hellip"), "&hellip");
+    }
+
+    #[test]
+    fn test_attribute_legacy_rule() {
+        assert_eq!(
+            decode_attribute_character_references("?a=1&copy=2&amp;b&lt3"),
+            "?a=1&copy=2&b&lt3"
+        );
+        assert_eq!(
+            decode_attribute_character_references("&copy 2024"),
+            "© 2024"
+        );
+    }
+}
@@ -0,0 +1,297 @@
+//! Foreign content (`<svg>` and `<math>`), where names are case-sensitive.
+//!
+//! The tokenizer lowercases names, so SVG names such as `viewBox` and
+//! `foreignObject` are restored from the spec's adjustment tables
+//! (<https://html.spec.whatwg.org/multipage/parsing.html#creating-and-inserting-nodes>).
+
+use super::HtmlTokenizer;
+
+/// The namespace of an open `<svg>` or `<math>` element.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum ForeignNamespace {
+    Svg,
+    MathMl,
+}
+
+impl ForeignNamespace {
+    /// The namespace a start tag named `name` opens, if it is a foreign root.
+    fn for_root(name: &str) -> Option<Self> {
+        if name.eq_ignore_ascii_case("svg") {
+            Some(Self::Svg)
+        } else if name.eq_ignore_ascii_case("math") {
+            Some(Self::MathMl)
+        } else {
+            None
+        }
+    }
+
+    fn adjust_tag_name(self, name: &mut String) {
+        if self == Self::Svg {
+            adjust_case(name, &SVG_TAG_NAMES);
+        }
+    }
+
+    fn adjust_attribute_name(self, name: &mut String) {
+        match self {
+            Self::Svg => adjust_case(name, &SVG_ATTRIBUTE_NAMES),
+            Self::MathMl => adjust_case(name, &["definitionURL"]),
+        }
+    }
+}
+
+/// Replaces `name` with the entry of `table` it matches case-insensitively.
+fn adjust_case(name: &mut String, table: &[&str]) {
+    if let Some(adjusted) = table.iter().find(|entry| entry.eq_ignore_ascii_case(name)) {
+        (*adjusted).clone_into(name);
+    }
+}
+
+/// SVG element names with uppercase letters.
+const SVG_TAG_NAMES: [&str; 37] = [
+    "altGlyph",
+    "altGlyphDef",
+    "altGlyphItem",
+    "animateColor",
+    "animateMotion",
+    "animateTransform",
+    "clipPath",
+    "feBlend",
+    "feColorMatrix",
+    "feComponentTransfer",
+    "feComposite",
+    "feConvolveMatrix",
+    "feDiffuseLighting",
+    "feDisplacementMap",
+    "feDistantLight",
+    "feDropShadow",
+    "feFlood",
+    "feFuncA",
+    "feFuncB",
+    "feFuncG",
+    "feFuncR",
+    "feGaussianBlur",
+    "feImage",
+    "feMerge",
+    "feMergeNode",
+    "feMorphology",
+    "feOffset",
+    "fePointLight",
+    "feSpecularLighting",
+    "feSpotLight",
+    "feTile",
+    "feTurbulence",
+    "foreignObject",
+    "glyphRef",
+    "linearGradient",
+    "radialGradient",
+    "textPath",
+];
+
+/// SVG attribute names with uppercase letters.
+const SVG_ATTRIBUTE_NAMES: [&str; 58] = [
+    "attributeName",
+    "attributeType",
+    "baseFrequency",
+    "baseProfile",
+    "calcMode",
+    "clipPathUnits",
+    "diffuseConstant",
+    "edgeMode",
+    "filterUnits",
+    "glyphRef",
+    "gradientTransform",
+    "gradientUnits",
+    "kernelMatrix",
+    "kernelUnitLength",
+    "keyPoints",
+    "keySplines",
+    "keyTimes",
+    "lengthAdjust",
+    "limitingConeAngle",
+    "markerHeight",
+    "markerUnits",
+    "markerWidth",
+    "maskContentUnits",
+    "maskUnits",
+    "numOctaves",
+    "pathLength",
+    "patternContentUnits",
+    "patternTransform",
+    "patternUnits",
+    "pointsAtX",
+    "pointsAtY",
+    "pointsAtZ",
+    "preserveAlpha",
+    "preserveAspectRatio",
+    "primitiveUnits",
+    "refX",
+    "refY",
+    "repeatCount",
+    "repeatDur",
+    "requiredExtensions",
+    "requiredFeatures",
+    "specularConstant",
+    "specularExponent",
+    "spreadMethod",
+    "startOffset",
+    "stdDeviation",
+    "stitchTiles",
+    "surfaceScale",
+    "systemLanguage",
+    "tableValues",
+    "targetX",
+    "targetY",
+    "textLength",
+    "viewBox",
+    "viewTarget",
+    "xChannelSelector",
+    "yChannelSelector",
+    "zoomAndPan",
+];
+
+impl HtmlTokenizer<'_> {
+    /// Whether the tokenizer is inside an `<svg>` or `<math>` element.
+    pub(super) fn in_foreign_content(&self) -> bool {
+        !self.foreign_elements.is_empty()
+    }
+
+    /// Tracks a start tag entering foreign content and restores the case of
+    /// its names. A self-closing `<svg/>` opens nothing.
+    pub(super) fn enter_foreign_content(
+        &mut self,
+        name: &mut String,
+        attributes: &mut [(String, String)],
+        self_closing: bool,
+    ) {
+        let root = ForeignNamespace::for_root(name);
+        let Some(namespace) = root.or_else(|| self.foreign_elements.last().copied()) else {
+            return;
+        };
+        if root.is_some() && !self_closing {
+            self.foreign_elements.push(namespace);
+        }
+        if !self.adjusts_names() {
+            return;
+        }
+        namespace.adjust_tag_name(name);
+        for (attribute, _) in attributes {
+            namespace.adjust_attribute_name(attribute);
+        }
+    }
+
+    /// Restores the case of an end tag's name and tracks it leaving
+    /// foreign content.
+    pub(super) fn leave_foreign_content(&mut self, name: &mut String) {
+        let Some(&namespace) = self.foreign_elements.last() else {
+            return;
+        };
+        if self.adjusts_names() {
+            namespace.adjust_tag_name(name);
+        }
+        if ForeignNamespace::for_root(name) == Some(namespace) {
+            self.foreign_elements.pop();
+        }
+    }
+
+    /// Names are only adjusted when they were lowercased; otherwise the
+    /// source casing is kept as written.
+    fn adjusts_names(&self) -> bool {
+        self.options.lowercase_names || self.options.spec_conformance
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::html_tokenizer::{HtmlToken, HtmlTokenizer, TokenizerOptions};
+
+    fn tokenize(input: &str, spec_conformance: bool) -> Vec<HtmlToken> {
+        HtmlTokenizer::new(input)
+            .with_options(TokenizerOptions::new().spec_conformance(spec_conformance))
+            .iter()
+            .map(|t| t.unwrap())
+            .collect()
+    }
+
+    fn start_tag(name: &str, attributes: &[(&str, &str)], self_closing: bool) -> HtmlToken {
+        HtmlToken::StartTag {
+            name: name.to_string(),
+            attributes: attributes
+                .iter()
+                .map(|(n, v)| (n.to_string(), v.to_string()))
+                .collect(),
+            self_closing,
+        }
+    }
+
+    #[test]
+    fn test_svg_names_are_case_adjusted() {
+        let input = "<svg VIEWBOX=\"0 0 10 10\"><clippath id=\"c\"/>\
+            <lineargradient gradientunits=userSpaceOnUse></lineargradient></svg>";
+
+        for spec_conformance in [false, true] {
+            assert_eq!(
+                tokenize(input, spec_conformance),
+                vec![
+                    start_tag("svg", &[("viewBox", "0 0 10 10")], false),
+                    start_tag("clipPath", &[("id", "c")], true),
+                    start_tag(
+                        "linearGradient",
+                        &[("gradientUnits", "userSpaceOnUse")],
+                        false
+                    ),
+                    HtmlToken::EndTag {
+                        name: "linearGradient".to_string()
+                    },
+                    HtmlToken::EndTag {
+                        name: "svg".to_string()
+                    },
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn test_names_outside_foreign_content_are_not_adjusted() {
+        assert_eq!(
+            tokenize("<svg/><div viewbox=1></div>", false),
+            vec![
+                start_tag("svg", &[], true),
+                start_tag("div", &[("viewbox", "1")], false),
+                HtmlToken::EndTag {
+                    name: "div".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mathml_attributes_are_case_adjusted() {
+        assert_eq!(
+            tokenize("<math definitionurl=x><mi viewbox=1/></math>", false),
+            vec![
+                start_tag("math", &[("definitionURL", "x")], false),
+                start_tag("mi", &[("viewbox", "1")], true),
+                HtmlToken::EndTag {
+                    name: "math".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_inline_svg_with_cdata_and_style() {
+        let input = "<svg><style>a{}</style><text><![CDATA[x < y]]></text></svg><p>&lt;</p>";
+
+        for spec_conformance in [false, true] {
+            let tokens = tokenize(input, spec_conformance);
+            assert!(tokens.contains(&HtmlToken::Text("x < y".to_string())));
+            assert_eq!(
+                tokens.last(),
+                Some(&HtmlToken::EndTag {
+                    name: "p".to_string()
+                })
+            );
+            assert!(tokens.contains(&HtmlToken::Text("<".to_string())));
+        }
+    }
+}
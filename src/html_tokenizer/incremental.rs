@@ -0,0 +1,324 @@
+use std::ops::Range;
+
+use super::{
+    HtmlToken, HtmlTokenizer, SpannedToken, TokenizeError, TokenizerOptions, TokenizerState,
+};
+
+/// Identifies a token of an [`IncrementalTokenizer`] across edits. Tokens
+/// that an edit did not touch keep their ID; re-tokenized ones get new IDs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TokenId(u64);
+
+#[derive(Debug, Clone)]
+struct Entry {
+    id: TokenId,
+    token: SpannedToken,
+
+    /// Where the tokenizer was, and its state, before it produced this
+    /// token. Re-tokenizing restarts from here.
+    resume_at: usize,
+    state: TokenizerState,
+
+    /// The furthest the tokenizer had read by the end of this token, not
+    /// counting [`LOOKAHEAD`]. Edits before this point can change the token.
+    read_until: usize,
+}
+
+/// The most input past its current position the tokenizer looks at before
+/// deciding how a token ends, such as `</noframes` and a delimiter at the
+/// end of raw text.
+const LOOKAHEAD: usize = 16;
+
+/// A tokenized document that can be edited in place, for editors that
+/// re-tokenize on every keystroke.
+///
+/// [`retokenize`](Self::retokenize) restarts the tokenizer just before the
+/// edit and stops as soon as it is back in step with the old tokens, so the
+/// cost depends on the size of the edit rather than of the document.
+/// Create one with [`HtmlTokenizer::incremental`].
+#[derive(Debug, Clone)]
+pub struct IncrementalTokenizer {
+    source: String,
+    options: TokenizerOptions,
+    entries: Vec<Entry>,
+    next_id: u64,
+}
+
+impl HtmlTokenizer<'_> {
+    /// Tokenizes the whole input, with this tokenizer's options, into a
+    /// document that can then be edited with
+    /// [`IncrementalTokenizer::retokenize`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error the tokenizer runs into.
+    pub fn incremental(&self) -> Result<IncrementalTokenizer, TokenizeError> {
+        let mut next_id = 0;
+        let (entries, _) = IncrementalTokenizer::tokenize(
+            self.input,
+            &self.options,
+            &mut next_id,
+            (0, TokenizerState::default()),
+            &[],
+        )?;
+
+        Ok(IncrementalTokenizer {
+            source: self.input.to_owned(),
+            options: self.options.clone(),
+            entries,
+            next_id,
+        })
+    }
+}
+
+impl IncrementalTokenizer {
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn options(&self) -> &TokenizerOptions {
+        &self.options
+    }
+
+    pub fn tokens(&self) -> impl Iterator<Item = (TokenId, &SpannedToken)> {
+        self.entries.iter().map(|entry| (entry.id, &entry.token))
+    }
+
+    pub fn get(&self, id: TokenId) -> Option<&SpannedToken> {
+        self.entries
+            .iter()
+            .find(|entry| entry.id == id)
+            .map(|entry| &entry.token)
+    }
+
+    /// Replaces the bytes of the source in `range` with `new_text` and
+    /// updates the tokens to match. Returns the indices, within
+    /// [`tokens`](Self::tokens), of the tokens that were re-tokenized.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `range` is out of bounds or splits a character,
+    /// or if the edited source fails to tokenize. The document is left
+    /// unchanged either way.
+    pub fn retokenize(
+        &mut self,
+        range: Range<usize>,
+        new_text: &str,
+    ) -> Result<Range<usize>, String> {
+        if range.start > range.end
+            || !self.source.is_char_boundary(range.start)
+            || !self.source.is_char_boundary(range.end)
+        {
+            return Err(format!("Invalid edit range: {range:?}"));
+        }
+
+        let mut source = self.source.clone();
+        source.replace_range(range.clone(), new_text);
+        let new_end = range.start + new_text.len();
+
+        // Restart at the first token that read input at or past the edit. That
+        // can be well before the edit: an unterminated comment recovered as
+        // text depends on there being no `-->` up to the end of the input.
+        let first = self
+            .entries
+            .iter()
+            .position(|entry| entry.read_until + LOOKAHEAD >= range.start)
+            .unwrap_or(self.entries.len().saturating_sub(1));
+        let restart = self
+            .entries
+            .get(first)
+            .map_or((0, TokenizerState::default()), |entry| {
+                (entry.resume_at, entry.state.clone())
+            });
+
+        // Tokens that start after the edit can be reused once the tokenizer
+        // reaches them in the same state.
+        let tail_start = self
+            .entries
+            .partition_point(|entry| entry.resume_at < range.end)
+            .max(first);
+        let shift = |position: usize| position.saturating_sub(range.end) + new_end;
+        let tail: Vec<Entry> = self
+            .entries
+            .get(tail_start..)
+            .unwrap_or_default()
+            .iter()
+            .map(|entry| {
+                let mut entry = entry.clone();
+                entry.resume_at = shift(entry.resume_at);
+                entry.read_until = shift(entry.read_until);
+                let error_span = match &mut entry.token.token {
+                    HtmlToken::ParseError(error) => Some(&mut error.span),
+                    _ => None,
+                };
+                for span in std::iter::once(&mut entry.token.span)
+                    .chain(&mut entry.token.attribute_spans)
+                    .chain(error_span)
+                {
+                    span.start = shift(span.start);
+                    span.end = shift(span.end);
+                }
+                entry
+            })
+            .collect();
+
+        let (entries, rejoin) =
+            Self::tokenize(&source, &self.options, &mut self.next_id, restart, &tail)
+                .map_err(|error| error.to_string())?;
+
+        let retokenized = first..first + entries.len();
+        self.entries.truncate(first);
+        self.entries.extend(entries);
+        self.entries.extend(tail.into_iter().skip(rejoin));
+        self.source = source;
+        Ok(retokenized)
+    }
+
+    /// Tokenizes `source` from `restart` until the tokenizer reaches one of
+    /// the `old` entries at the same position and in the same state.
+    /// Returns the new entries and the index in `old` where they rejoin it.
+    fn tokenize(
+        source: &str,
+        options: &TokenizerOptions,
+        next_id: &mut u64,
+        (resume_at, state): (usize, TokenizerState),
+        old: &[Entry],
+    ) -> Result<(Vec<Entry>, usize), TokenizeError> {
+        let mut tokenizer = HtmlTokenizer::new(source)
+            .with_options(options.clone())
+            .with_state(state);
+        tokenizer.position = resume_at;
+
+        let mut entries = Vec::new();
+        let mut next_old = 0;
+        // Parse errors are emitted ahead of their token, which the tokenizer
+        // has already read; they share the token's restart point.
+        let mut held: Option<(usize, TokenizerState)> = None;
+        loop {
+            if let Some((resume_at, state)) = held.take() {
+                let Some(token) = tokenizer.next_spanned_token() else {
+                    return Ok((entries, old.len()));
+                };
+                let token = token?;
+                if matches!(token.token, HtmlToken::ParseError(_)) {
+                    held = Some((resume_at, state.clone()));
+                }
+                entries.push(Entry {
+                    id: TokenId(*next_id),
+                    token,
+                    resume_at,
+                    state,
+                    read_until: tokenizer.position.max(tokenizer.furthest_position),
+                });
+                *next_id += 1;
+                continue;
+            }
+
+            let resume_at = tokenizer.position;
+            let state = tokenizer.state();
+
+            while old
+                .get(next_old)
+                .is_some_and(|entry| entry.resume_at < resume_at)
+            {
+                next_old += 1;
+            }
+            if old
+                .get(next_old)
+                .is_some_and(|entry| entry.resume_at == resume_at && entry.state == state)
+            {
+                return Ok((entries, next_old));
+            }
+
+            held = Some((resume_at, state));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_matches_full_tokenization(document: &IncrementalTokenizer) {
+        let expected: Vec<SpannedToken> = HtmlTokenizer::new(document.source())
+            .with_options(document.options.clone())
+            .spanned_iter()
+            .map(|t| t.unwrap())
+            .collect();
+        let actual: Vec<SpannedToken> = document.tokens().map(|(_, t)| t.clone()).collect();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_edit_keeps_ids_outside_edited_region() {
+        let source = "<ul><li>first item</li><li>two</li><li>third item</li></ul>";
+        let mut document = HtmlTokenizer::new(source).incremental().unwrap();
+        let ids_before: Vec<TokenId> = document.tokens().map(|(id, _)| id).collect();
+
+        let start = source.find("two").unwrap();
+        let retokenized = document.retokenize(start..start + 3, "2").unwrap();
+
+        assert_eq!(
+            document.source(),
+            "<ul><li>first item</li><li>2</li><li>third item</li></ul>"
+        );
+        assert_matches_full_tokenization(&document);
+
+        let ids_after: Vec<TokenId> = document.tokens().map(|(id, _)| id).collect();
+        assert_eq!(ids_after.len(), ids_before.len());
+        for (index, (before, after)) in ids_before.iter().zip(&ids_after).enumerate() {
+            assert_eq!(before == after, !retokenized.contains(&index));
+        }
+        assert!(retokenized.start > 0 && retokenized.end < ids_after.len() - 3);
+    }
+
+    #[test]
+    fn test_edit_that_changes_the_rest_of_the_document() {
+        let mut document = HtmlTokenizer::new("<p>a</p><script>x</script><p>b</p>")
+            .incremental()
+            .unwrap();
+
+        // Breaking the end tag turns the rest of the input into script text.
+        document.retokenize(22..23, "X").unwrap();
+        assert_matches_full_tokenization(&document);
+        assert_eq!(
+            document.tokens().last().map(|(_, t)| &t.token),
+            Some(&HtmlToken::Text("x</scrXpt><p>b</p>".to_string()))
+        );
+
+        document.retokenize(22..23, "i").unwrap();
+        assert_matches_full_tokenization(&document);
+    }
+
+    #[test]
+    fn test_sequence_of_edits_matches_full_tokenization() {
+        let mut document = HtmlTokenizer::new("<div class=a>x &amp; y</div>")
+            .with_spec_conformance(true)
+            .incremental()
+            .unwrap();
+
+        let edits = [
+            (0..0, "<!-- c -->"),
+            (4..4, " more"),
+            (26..26, "b"),
+            (0..15, ""),
+            (0..0, "<svg viewbox=1><![CDATA[<]]></svg>"),
+            (35..35, "<!--"),
+        ];
+        for (range, text) in edits {
+            document.retokenize(range, text).unwrap();
+            assert_matches_full_tokenization(&document);
+        }
+    }
+
+    #[test]
+    fn test_invalid_edit_leaves_document_unchanged() {
+        let mut document = HtmlTokenizer::new("<p>é</p>").incremental().unwrap();
+
+        assert!(document.retokenize(4..5, "e").is_err());
+        assert!(document.retokenize(3..100, "").is_err());
+        assert!(document.retokenize(3..3, "<a href='").is_err());
+        assert_eq!(document.source(), "<p>é</p>");
+        assert_matches_full_tokenization(&document);
+    }
+}
@@ -0,0 +1,98 @@
+/// Settings for [`HtmlTokenizer`](super::HtmlTokenizer), applied with
+/// [`HtmlTokenizer::with_options`](super::HtmlTokenizer::with_options).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenizerOptions {
+    pub(super) decode_entities: bool,
+    pub(super) lowercase_names: bool,
+    pub(super) preserve_comments: bool,
+    pub(super) preserve_whitespace: bool,
+    pub(super) recover_errors: bool,
+    pub(super) spec_conformance: bool,
+    pub(super) emit_parse_errors: bool,
+}
+
+impl Default for TokenizerOptions {
+    fn default() -> Self {
+        Self {
+            decode_entities: true,
+            lowercase_names: true,
+            preserve_comments: true,
+            preserve_whitespace: true,
+            recover_errors: false,
+            spec_conformance: false,
+            emit_parse_errors: false,
+        }
+    }
+}
+
+impl TokenizerOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether character references (`&amp;`, `&#65;`) in text and attribute
+    /// values are decoded. Defaults to `true`; turn it off to keep the raw
+    /// source text for round-tripping.
+    #[must_use]
+    pub fn decode_entities(mut self, decode_entities: bool) -> Self {
+        self.decode_entities = decode_entities;
+        self
+    }
+
+    /// Whether tag and attribute names are ASCII-lowercased, as the spec
+    /// requires. Defaults to `true`; turn it off to keep the source casing.
+    /// Spec-conformance mode always lowercases.
+    #[must_use]
+    pub fn lowercase_names(mut self, lowercase_names: bool) -> Self {
+        self.lowercase_names = lowercase_names;
+        self
+    }
+
+    /// Whether `Comment` tokens are emitted. Defaults to `true`.
+    #[must_use]
+    pub fn preserve_comments(mut self, preserve_comments: bool) -> Self {
+        self.preserve_comments = preserve_comments;
+        self
+    }
+
+    /// Whether whitespace between tags is emitted as text. Defaults to
+    /// `true`, since it separates inline content (`<b>a</b> <i>b</i>`);
+    /// turn it off to drop whitespace-only text.
+    #[must_use]
+    pub fn preserve_whitespace(mut self, preserve_whitespace: bool) -> Self {
+        self.preserve_whitespace = preserve_whitespace;
+        self
+    }
+
+    /// In error-recovery mode, markup the tokenizer cannot make sense of (a
+    /// stray `<>`, an unterminated tag) is emitted as text and recorded in
+    /// `parse_errors` instead of ending the token stream with an error.
+    /// Defaults to `false`.
+    #[must_use]
+    pub fn error_recovery(mut self, recover_errors: bool) -> Self {
+        self.recover_errors = recover_errors;
+        self
+    }
+
+    /// Tokenizes with the WHATWG HTML tokenization state machine instead of
+    /// the default simplified rules: tag and attribute names are lowercased,
+    /// script data escapes are honoured, and malformed markup is recovered
+    /// from the way browsers do. Parse errors are recorded in
+    /// `parse_errors`; no `Err` is ever returned. Defaults to `false`.
+    #[must_use]
+    pub fn spec_conformance(mut self, spec_conformance: bool) -> Self {
+        self.spec_conformance = spec_conformance;
+        self
+    }
+
+    /// Whether each parse error is also emitted in the token stream, as an
+    /// [`HtmlToken::ParseError`](super::HtmlToken::ParseError) just before
+    /// the token it is about. Useful with error recovery or spec
+    /// conformance, which otherwise only collect errors in `parse_errors`.
+    /// Defaults to `false`.
+    #[must_use]
+    pub fn emit_parse_errors(mut self, emit_parse_errors: bool) -> Self {
+        self.emit_parse_errors = emit_parse_errors;
+        self
+    }
+}
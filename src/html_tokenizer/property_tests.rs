@@ -0,0 +1,217 @@
+//! Property tests: on arbitrary input every tokenizer entry point must
+//! return without panicking, and must make progress on every token.
+//!
+//! Inputs are built from a seeded generator so failures reproduce; the
+//! fragments favour markup that exercises the tricky paths (unterminated
+//! comments, references, raw text end tags, multi-byte characters).
+
+use super::{
+    HtmlToken, HtmlTokenizer, IncrementalTokenizer, StreamingTokenizer, TokenRewriter,
+    TokenizerOptions, serialize_tokens,
+};
+
+const FRAGMENTS: [&str; 40] = [
+    "<",
+    ">",
+    "/",
+    "!",
+    "-",
+    "--",
+    "=",
+    "\"",
+    "'",
+    " ",
+    "\n",
+    "\0",
+    "a",
+    "?",
+    "&",
+    "#",
+    "x",
+    ";",
+    "é",
+    "😀",
+    "<!--",
+    "-->",
+    "<![CDATA[",
+    "]]>",
+    "<!DOCTYPE",
+    "&amp",
+    "&#x1F600;",
+    "&#99999999",
+    "&notin",
+    "<script>",
+    "</script>",
+    "<!--<script>",
+    "<style>",
+    "<title>",
+    "<plaintext>",
+    "<svg>",
+    "</svg>",
+    "<math>",
+    "<a href=",
+    "</",
+];
+
+/// A xorshift generator; good enough to spread inputs around.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        usize::try_from(self.next() % bound.max(1) as u64).unwrap_or_default()
+    }
+
+    fn input(&mut self) -> String {
+        let mut input = String::new();
+        for _ in 0..self.below(40) {
+            let fragment = FRAGMENTS.get(self.below(FRAGMENTS.len())).unwrap();
+            input.push_str(fragment);
+        }
+        input
+    }
+
+    /// A char boundary of `text`.
+    fn boundary(&mut self, text: &str) -> usize {
+        let boundaries: Vec<usize> = text
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain([text.len()])
+            .collect();
+        *boundaries.get(self.below(boundaries.len())).unwrap()
+    }
+}
+
+fn all_options() -> Vec<TokenizerOptions> {
+    let mut options = Vec::new();
+    for spec_conformance in [false, true] {
+        for recover_errors in [false, true] {
+            for decode_entities in [false, true] {
+                options.push(
+                    TokenizerOptions::new()
+                        .spec_conformance(spec_conformance)
+                        .error_recovery(recover_errors)
+                        .decode_entities(decode_entities)
+                        .emit_parse_errors(recover_errors && decode_entities),
+                );
+            }
+        }
+    }
+    options
+}
+
+/// Tokenizes `input` to the end, checking that every token consumes input.
+/// Parse errors are about the token that follows them, so they are skipped.
+fn tokenize_to_end(input: &str, options: &TokenizerOptions) {
+    let mut tokenizer = HtmlTokenizer::new(input).with_options(options.clone());
+    let mut last_end = 0;
+    while let Some(result) = tokenizer.next_spanned_token() {
+        let Ok(spanned) = result else {
+            return;
+        };
+        if matches!(spanned.token, HtmlToken::ParseError(_)) {
+            continue;
+        }
+        assert!(
+            spanned.span.end > last_end || spanned.span.end == input.len(),
+            "no progress at {} on {input:?}",
+            spanned.span.end
+        );
+        assert!(spanned.span.start <= spanned.span.end && spanned.span.end <= input.len());
+        last_end = spanned.span.end;
+        serialize_tokens([&spanned.token]);
+    }
+}
+
+#[test]
+fn test_tokenizer_never_panics() {
+    let mut rng = Rng(0x5eed);
+    for _ in 0..2000 {
+        let input = rng.input();
+        for options in all_options() {
+            tokenize_to_end(&input, &options);
+        }
+    }
+}
+
+#[test]
+fn test_tokenizer_never_panics_on_arbitrary_bytes() {
+    let mut rng = Rng(0xb17e5);
+    for _ in 0..2000 {
+        let bytes: Vec<u8> = (0..rng.below(64))
+            .map(|_| u8::try_from(rng.below(256)).unwrap())
+            .collect();
+        let input = String::from_utf8_lossy(&bytes);
+        for options in all_options() {
+            tokenize_to_end(&input, &options);
+        }
+    }
+}
+
+#[test]
+fn test_streaming_never_panics() {
+    let mut rng = Rng(0xc4a2);
+    for _ in 0..300 {
+        let input = rng.input();
+        let bytes = input.as_bytes();
+        for options in all_options() {
+            let mut streaming = StreamingTokenizer::new().with_options(options);
+            let mut rewriter = TokenRewriter::new();
+            rewriter.on_element("a[href]", |el| el.remove()).unwrap();
+
+            // Split anywhere, including inside multi-byte characters.
+            let mut rest = bytes;
+            while !rest.is_empty() {
+                let (chunk, after) = rest.split_at(rng.below(rest.len()) + 1);
+                streaming.feed(chunk);
+                rewriter.feed(chunk);
+                rest = after;
+            }
+            streaming.finish();
+            rewriter.finish();
+        }
+    }
+}
+
+#[test]
+fn test_incremental_edits_never_panic() {
+    let mut rng = Rng(0xed17);
+    for _ in 0..300 {
+        let options = TokenizerOptions::new()
+            .spec_conformance(rng.below(2) == 0)
+            .error_recovery(true)
+            .emit_parse_errors(rng.below(2) == 0);
+        let Ok(mut document) = HtmlTokenizer::new(&rng.input())
+            .with_options(options)
+            .incremental()
+        else {
+            continue;
+        };
+
+        for _ in 0..5 {
+            let start = rng.boundary(document.source());
+            let rest = document.source().get(start..).unwrap();
+            let end = start + rng.boundary(rest);
+            let text = rng.input();
+            if document.retokenize(start..end, &text).is_ok() {
+                assert_matches_full_tokenization(&document);
+            }
+        }
+    }
+}
+
+fn assert_matches_full_tokenization(document: &IncrementalTokenizer) {
+    let expected: Vec<_> = HtmlTokenizer::new(document.source())
+        .with_options(document.options().clone())
+        .spanned_iter()
+        .map(|t| t.unwrap())
+        .collect();
+    let actual: Vec<_> = document.tokens().map(|(_, t)| t.clone()).collect();
+    assert_eq!(actual, expected, "on {:?}", document.source());
+}
@@ -0,0 +1,383 @@
+use super::{
+    HtmlToken, StreamingTokenizer, TokenizerOptions, is_void_element, serialize::Serializer,
+};
+
+/// A simple selector: an optional tag name (or `*`) followed by any number
+/// of `[attr]` or `[attr=value]` conditions, e.g. `script[src]` or
+/// `a[target="_blank"]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selector {
+    tag_name: Option<String>,
+    attributes: Vec<(String, Option<String>)>,
+}
+
+impl Selector {
+    /// # Errors
+    ///
+    /// Returns a description of the problem if `selector` is empty or uses
+    /// syntax beyond tag names and attribute conditions.
+    pub fn parse(selector: &str) -> Result<Self, String> {
+        let selector = selector.trim();
+        let (tag_name, mut rest) = selector
+            .find('[')
+            .map_or((selector, ""), |bracket| selector.split_at(bracket));
+
+        if tag_name.is_empty() && rest.is_empty() {
+            return Err("Empty selector".to_owned());
+        }
+        if !tag_name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '*')
+        {
+            return Err(format!("Unsupported selector: {selector}"));
+        }
+
+        let mut attributes = Vec::new();
+        while let Some(condition) = rest.strip_prefix('[') {
+            let Some((condition, after)) = condition.split_once(']') else {
+                return Err(format!("Unclosed attribute selector: {selector}"));
+            };
+            let (name, value) = match condition.split_once('=') {
+                Some((name, value)) => (name, Some(value.trim().trim_matches(['"', '\'']))),
+                None => (condition, None),
+            };
+            let name = name.trim();
+            if name.is_empty() {
+                return Err(format!("Empty attribute name in selector: {selector}"));
+            }
+            attributes.push((name.to_ascii_lowercase(), value.map(str::to_owned)));
+            rest = after;
+        }
+        if !rest.is_empty() {
+            return Err(format!("Unsupported selector: {selector}"));
+        }
+
+        Ok(Self {
+            tag_name: (!tag_name.is_empty() && tag_name != "*")
+                .then(|| tag_name.to_ascii_lowercase()),
+            attributes,
+        })
+    }
+
+    pub fn matches(&self, name: &str, attributes: &[(String, String)]) -> bool {
+        self.tag_name
+            .as_deref()
+            .is_none_or(|tag_name| name.eq_ignore_ascii_case(tag_name))
+            && self.attributes.iter().all(|(wanted, wanted_value)| {
+                attributes.iter().any(|(attribute, value)| {
+                    attribute.eq_ignore_ascii_case(wanted)
+                        && wanted_value.as_ref().is_none_or(|wanted| wanted == value)
+                })
+            })
+    }
+}
+
+/// What happens to an element after its handlers have run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum Disposition {
+    #[default]
+    Keep,
+
+    /// Drop the element and everything inside it.
+    Remove,
+
+    /// Drop the start and end tags but keep the content.
+    Unwrap,
+}
+
+/// A start tag passed to an element handler, which can change its
+/// attributes or remove it.
+#[derive(Debug)]
+pub struct Element<'t> {
+    name: &'t str,
+    attributes: &'t mut Vec<(String, String)>,
+    disposition: Disposition,
+}
+
+impl Element<'_> {
+    pub fn tag_name(&self) -> &str {
+        self.name
+    }
+
+    pub fn attributes(&self) -> &[(String, String)] {
+        self.attributes
+    }
+
+    pub fn get_attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(attribute, _)| attribute.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Sets `name` to `value`, adding the attribute if it is missing.
+    pub fn set_attribute(&mut self, name: &str, value: &str) {
+        if let Some((_, existing)) = self
+            .attributes
+            .iter_mut()
+            .find(|(attribute, _)| attribute.eq_ignore_ascii_case(name))
+        {
+            value.clone_into(existing);
+        } else {
+            self.attributes.push((name.to_owned(), value.to_owned()));
+        }
+    }
+
+    pub fn remove_attribute(&mut self, name: &str) {
+        self.attributes
+            .retain(|(attribute, _)| !attribute.eq_ignore_ascii_case(name));
+    }
+
+    /// Removes the element along with its content.
+    pub fn remove(&mut self) {
+        self.disposition = Disposition::Remove;
+    }
+
+    /// Removes the element's tags but keeps its content in place.
+    pub fn remove_and_keep_content(&mut self) {
+        self.disposition = Disposition::Unwrap;
+    }
+}
+
+type ElementHandler = Box<dyn FnMut(&mut Element<'_>)>;
+
+/// Rewrites HTML as it streams through, without building a DOM.
+///
+/// Handlers registered with [`on_element`](Self::on_element) run on every
+/// start tag matching their selector and can edit its attributes or drop
+/// it. Feed input with [`feed`](Self::feed), which returns the rewritten
+/// HTML for the tokens completed so far.
+pub struct TokenRewriter {
+    tokenizer: StreamingTokenizer,
+    serializer: Serializer,
+    handlers: Vec<(Selector, ElementHandler)>,
+
+    /// Open elements and what to do with their end tags.
+    open_elements: Vec<(String, Disposition)>,
+}
+
+impl Default for TokenRewriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TokenRewriter {
+    pub fn new() -> Self {
+        Self {
+            tokenizer: StreamingTokenizer::new()
+                .with_options(TokenizerOptions::new().error_recovery(true)),
+            serializer: Serializer::default(),
+            handlers: Vec::new(),
+            open_elements: Vec::new(),
+        }
+    }
+
+    /// Registers `handler` for start tags matching `selector`. Handlers run
+    /// in registration order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `selector` cannot be parsed; see [`Selector::parse`].
+    pub fn on_element(
+        &mut self,
+        selector: &str,
+        handler: impl FnMut(&mut Element<'_>) + 'static,
+    ) -> Result<(), String> {
+        self.handlers
+            .push((Selector::parse(selector)?, Box::new(handler)));
+        Ok(())
+    }
+
+    /// Adds a chunk of input and returns the rewritten HTML it completed.
+    pub fn feed(&mut self, bytes: &[u8]) -> String {
+        let tokens = self.tokenizer.feed(bytes);
+        self.rewrite_tokens(tokens)
+    }
+
+    /// Signals the end of input and returns the rest of the rewritten HTML.
+    pub fn finish(mut self) -> String {
+        let tokenizer = std::mem::take(&mut self.tokenizer);
+        let tokens = tokenizer.finish();
+        self.rewrite_tokens(tokens)
+    }
+
+    /// Rewrites a complete document.
+    pub fn rewrite(mut self, html: &str) -> String {
+        let mut output = self.feed(html.as_bytes());
+        output.push_str(&self.finish());
+        output
+    }
+
+    fn rewrite_tokens<E>(&mut self, tokens: Vec<Result<HtmlToken, E>>) -> String {
+        let mut html = String::new();
+        // Error recovery is on, so errors only stand for markup already emitted as text.
+        for mut token in tokens.into_iter().filter_map(Result::ok) {
+            if self.rewrite_token(&mut token) {
+                self.serializer.write_token(&mut html, &token);
+            }
+        }
+        html
+    }
+
+    /// Runs handlers on `token` and returns whether it should be written out.
+    fn rewrite_token(&mut self, token: &mut HtmlToken) -> bool {
+        let inside_removed = self
+            .open_elements
+            .iter()
+            .any(|(_, disposition)| *disposition == Disposition::Remove);
+
+        match token {
+            HtmlToken::StartTag {
+                name,
+                attributes,
+                self_closing,
+            } => {
+                let disposition = if inside_removed {
+                    Disposition::Remove
+                } else {
+                    self.run_handlers(name, attributes)
+                };
+                if !is_void_element(name) && !*self_closing {
+                    self.open_elements.push((name.clone(), disposition));
+                }
+                disposition == Disposition::Keep
+            }
+            HtmlToken::EndTag { name } => {
+                let Some(index) = self
+                    .open_elements
+                    .iter()
+                    .rposition(|(open, _)| open.eq_ignore_ascii_case(name))
+                else {
+                    return !inside_removed;
+                };
+                let disposition = self
+                    .open_elements
+                    .get(index)
+                    .map_or(Disposition::Keep, |(_, disposition)| *disposition);
+                self.open_elements.truncate(index);
+                !inside_removed && disposition == Disposition::Keep
+            }
+            _ => !inside_removed,
+        }
+    }
+
+    fn run_handlers(&mut self, name: &str, attributes: &mut Vec<(String, String)>) -> Disposition {
+        let mut element = Element {
+            name,
+            attributes,
+            disposition: Disposition::Keep,
+        };
+        for (selector, handler) in &mut self.handlers {
+            if selector.matches(element.name, element.attributes) {
+                handler(&mut element);
+            }
+        }
+        element.disposition
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selector_parse() {
+        let selector = Selector::parse("script[src]").unwrap();
+        assert!(selector.matches("script", &[("src".to_string(), "x.js".to_string())]));
+        assert!(!selector.matches("script", &[]));
+
+        let selector = Selector::parse("[target='_blank']").unwrap();
+        assert!(selector.matches("a", &[("target".to_string(), "_blank".to_string())]));
+        assert!(!selector.matches("a", &[("target".to_string(), "_self".to_string())]));
+
+        assert!(Selector::parse("").is_err());
+        assert!(Selector::parse("div > p").is_err());
+        assert!(Selector::parse("a[href").is_err());
+    }
+
+    #[test]
+    fn test_remove_tracker_scripts() {
+        let mut rewriter = TokenRewriter::new();
+        rewriter
+            .on_element("script[src]", |el| {
+                if el
+                    .get_attribute("src")
+                    .is_some_and(|src| src.contains("tracker"))
+                {
+                    el.remove();
+                }
+            })
+            .unwrap();
+
+        let html = "<p>a</p><script src=\"https://tracker.example/t.js\">x()</script>\
+            <script src=\"app.js\"></script>";
+        assert_eq!(
+            rewriter.rewrite(html),
+            "<p>a</p><script src=\"app.js\"></script>"
+        );
+    }
+
+    #[test]
+    fn test_rewrite_links() {
+        let mut rewriter = TokenRewriter::new();
+        rewriter
+            .on_element("a[href]", |el| {
+                let href = el.get_attribute("href").unwrap_or_default().to_owned();
+                el.set_attribute("href", &href.replace("http:", "https:"));
+                el.remove_attribute("onclick");
+            })
+            .unwrap();
+
+        assert_eq!(
+            rewriter.rewrite("<a href=\"http://example.com\" onclick=\"t()\">x &amp; y</a>"),
+            "<a href=\"https://example.com\">x &amp; y</a>"
+        );
+    }
+
+    #[test]
+    fn test_remove_and_keep_content() {
+        let mut rewriter = TokenRewriter::new();
+        rewriter
+            .on_element("font", |el| el.remove_and_keep_content())
+            .unwrap();
+
+        assert_eq!(
+            rewriter.rewrite("<p><font color=red>hi <b>there</b></font></p>"),
+            "<p>hi <b>there</b></p>"
+        );
+    }
+
+    #[test]
+    fn test_script_split_across_chunks_is_not_escaped() {
+        let mut rewriter = TokenRewriter::new();
+        let mut output = rewriter.feed(b"<script>if (a <");
+        output.push_str(&rewriter.feed(b" b) {}</script>"));
+        output.push_str(&rewriter.finish());
+
+        assert_eq!(output, "<script>if (a < b) {}</script>");
+    }
+
+    #[test]
+    fn test_streaming_matches_whole_input() {
+        let html = "<div><img src=\"ad.png\"><div class=\"ad\"><p>buy</p></div>text</div>";
+        let build = || {
+            let mut rewriter = TokenRewriter::new();
+            rewriter
+                .on_element("div[class=ad]", |el| el.remove())
+                .unwrap();
+            rewriter.on_element("img", |el| el.remove()).unwrap();
+            rewriter
+        };
+
+        let mut streaming = build();
+        let mut output = String::new();
+        for chunk in html.as_bytes().chunks(5) {
+            output.push_str(&streaming.feed(chunk));
+        }
+        output.push_str(&streaming.finish());
+
+        assert_eq!(output, build().rewrite(html));
+        assert_eq!(output, "<div>text</div>");
+    }
+}
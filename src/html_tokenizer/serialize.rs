@@ -0,0 +1,169 @@
+use std::fmt;
+
+use super::HtmlToken;
+
+/// Elements whose text content is written out verbatim, since escaping it
+/// would change what the element contains.
+const RAW_TEXT_ELEMENTS: [&str; 7] = [
+    "script",
+    "style",
+    "iframe",
+    "noembed",
+    "noframes",
+    "xmp",
+    "plaintext",
+];
+
+impl HtmlToken {
+    /// Writes the token as HTML. Text is escaped; use [`serialize_tokens`]
+    /// to leave the contents of `<script>` and `<style>` untouched.
+    ///
+    /// # Errors
+    ///
+    /// Fails only if writing to `out` fails.
+    pub fn write_to(&self, out: &mut impl fmt::Write) -> fmt::Result {
+        self.write_with_context(out, false)
+    }
+
+    fn write_with_context(&self, out: &mut impl fmt::Write, raw_text: bool) -> fmt::Result {
+        match self {
+            Self::Doctype(doctype) => {
+                // The spec-conformance mode drops the keyword; the default mode keeps it.
+                let has_keyword = doctype
+                    .get(.."doctype".len())
+                    .is_some_and(|keyword| keyword.eq_ignore_ascii_case("doctype"));
+                if has_keyword {
+                    write!(out, "<!{doctype}>")
+                } else {
+                    write!(out, "<!DOCTYPE {doctype}>")
+                }
+            }
+            Self::StartTag {
+                name,
+                attributes,
+                self_closing,
+            } => {
+                write!(out, "<{name}")?;
+                for (attribute, value) in attributes {
+                    if value.is_empty() {
+                        write!(out, " {attribute}")?;
+                    } else {
+                        write!(out, " {attribute}=\"")?;
+                        write_escaped(out, value, true)?;
+                        out.write_char('"')?;
+                    }
+                }
+                if *self_closing {
+                    out.write_str(" /")?;
+                }
+                out.write_char('>')
+            }
+            Self::EndTag { name } => write!(out, "</{name}>"),
+            Self::Text(text) if raw_text => out.write_str(text),
+            Self::Text(text) => write_escaped(out, text, false),
+            Self::Comment(comment) => write!(out, "<!--{comment}-->"),
+            Self::ParseError(_) => Ok(()),
+        }
+    }
+}
+
+/// Serializes a token stream back to HTML, escaping text and attribute
+/// values except inside raw text elements such as `<script>`.
+pub fn serialize_tokens<'t>(tokens: impl IntoIterator<Item = &'t HtmlToken>) -> String {
+    let mut html = String::new();
+    let mut serializer = Serializer::default();
+    for token in tokens {
+        serializer.write_token(&mut html, token);
+    }
+    html
+}
+
+/// Serializes tokens one at a time, remembering whether the last start tag
+/// opened a raw text element so a stream can be written in pieces.
+#[derive(Debug, Default)]
+pub(super) struct Serializer {
+    raw_text: bool,
+}
+
+impl Serializer {
+    pub(super) fn write_token(&mut self, html: &mut String, token: &HtmlToken) {
+        // Writing to a `String` cannot fail.
+        token.write_with_context(html, self.raw_text).ok();
+
+        match token {
+            HtmlToken::StartTag { name, .. } => {
+                self.raw_text = RAW_TEXT_ELEMENTS
+                    .iter()
+                    .any(|element| name.eq_ignore_ascii_case(element));
+            }
+            HtmlToken::EndTag { .. } => self.raw_text = false,
+            _ => {}
+        }
+    }
+}
+
+/// Escapes `text` as the HTML serialization algorithm does: `&`, no-break
+/// space, and either `"` (in attribute values) or `<` and `>` (in text).
+fn write_escaped(out: &mut impl fmt::Write, text: &str, in_attribute: bool) -> fmt::Result {
+    for c in text.chars() {
+        match c {
+            '&' => out.write_str("&amp;")?,
+            '\u{a0}' => out.write_str("&nbsp;")?,
+            '"' if in_attribute => out.write_str("&quot;")?,
+            '<' if !in_attribute => out.write_str("&lt;")?,
+            '>' if !in_attribute => out.write_str("&gt;")?,
+            c => out.write_char(c)?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html_tokenizer::HtmlTokenizer;
+
+    fn round_trip(input: &str) -> String {
+        let tokens: Vec<_> = HtmlTokenizer::new(input)
+            .iter()
+            .map(|t| t.unwrap())
+            .collect();
+        serialize_tokens(&tokens)
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let input = "<!DOCTYPE html><p class=\"a\" hidden>Hi <b>there</b></p><!-- c --><br />";
+        assert_eq!(round_trip(input), input);
+    }
+
+    #[test]
+    fn test_text_and_attributes_are_escaped() {
+        assert_eq!(
+            round_trip("<a title='say \"hi\" &amp; <go>'>1 &lt; 2 &amp;&nbsp;3</a>"),
+            "<a title=\"say &quot;hi&quot; &amp; <go>\">1 &lt; 2 &amp;&nbsp;3</a>"
+        );
+    }
+
+    #[test]
+    fn test_script_content_is_not_escaped() {
+        let input = "<script>if (a < b && c) {}</script><p>a &lt; b</p>";
+        assert_eq!(
+            round_trip(input),
+            "<script>if (a < b && c) {}</script><p>a &lt; b</p>"
+        );
+    }
+
+    #[test]
+    fn test_write_to() {
+        let mut out = String::new();
+        HtmlToken::Doctype("html".to_string())
+            .write_to(&mut out)
+            .unwrap();
+        HtmlToken::Text("<x>".to_string())
+            .write_to(&mut out)
+            .unwrap();
+
+        assert_eq!(out, "<!DOCTYPE html>&lt;x&gt;");
+    }
+}
@@ -0,0 +1,815 @@
+//! The WHATWG HTML tokenization state machine
+//! (<https://html.spec.whatwg.org/multipage/parsing.html#tokenization>),
+//! used when [`TokenizerOptions::spec_conformance`](super::TokenizerOptions::spec_conformance)
+//! is set.
+//!
+//! Character tokens are coalesced into one [`HtmlToken::Text`] per run, and
+//! character references are decoded over the whole run afterwards. The
+//! content model switches (RCDATA, RAWTEXT, script data, PLAINTEXT) that the
+//! tree builder would normally make are made here after each start tag.
+
+use super::{
+    HtmlToken, HtmlTokenizer, ParseError, RawTextElement, Span, SpannedToken, TokenizeError,
+};
+
+/// States of the tag states (tag name through self-closing start tag).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TagState {
+    TagName,
+    BeforeAttributeName,
+    AttributeName,
+    AfterAttributeName,
+    BeforeAttributeValue,
+    AttributeValueQuoted(char),
+    AttributeValueUnquoted,
+    AfterAttributeValueQuoted,
+    SelfClosingStartTag,
+}
+
+/// States of the comment states (comment start through comment end bang).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CommentState {
+    CommentStart,
+    CommentStartDash,
+    Comment,
+    CommentEndDash,
+    CommentEnd,
+    CommentEndBang,
+}
+
+/// Where the script data states are, for finding the end of a `<script>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScriptState {
+    ScriptData,
+    Escaped,
+    DoubleEscaped,
+}
+
+#[derive(Debug, Default)]
+struct Attribute {
+    name: String,
+    value: String,
+    span: Span,
+}
+
+/// A start or end tag under construction.
+#[derive(Debug, Default)]
+struct TagBuilder {
+    name: String,
+    attributes: Vec<(String, String)>,
+    attribute_spans: Vec<Span>,
+    current: Option<Attribute>,
+    self_closing: bool,
+
+    /// Spans of attributes dropped for repeating an earlier name.
+    duplicate_spans: Vec<Span>,
+}
+
+impl TagBuilder {
+    fn start_attribute(&mut self, start: usize) {
+        self.finish_attribute();
+        self.current = Some(Attribute {
+            span: Span::new(start, start),
+            ..Attribute::default()
+        });
+    }
+
+    /// Adds the attribute being built to the tag, unless it duplicates an
+    /// earlier one.
+    fn finish_attribute(&mut self) {
+        let Some(attribute) = self.current.take() else {
+            return;
+        };
+        if self
+            .attributes
+            .iter()
+            .any(|(name, _)| *name == attribute.name)
+        {
+            self.duplicate_spans.push(attribute.span);
+            return;
+        }
+        self.attributes.push((attribute.name, attribute.value));
+        self.attribute_spans.push(attribute.span);
+    }
+
+    fn push_to_name(&mut self, c: char, end: usize) {
+        if let Some(attribute) = &mut self.current {
+            attribute.name.push(c);
+            attribute.span.end = end;
+        }
+    }
+
+    fn push_to_value(&mut self, c: char, end: usize) {
+        if let Some(attribute) = &mut self.current {
+            attribute.value.push(c);
+            attribute.span.end = end;
+        }
+    }
+
+    fn extend_value_span(&mut self, end: usize) {
+        if let Some(attribute) = &mut self.current {
+            attribute.span.end = end;
+        }
+    }
+}
+
+impl HtmlTokenizer<'_> {
+    /// Returns the next token according to the spec state machine.
+    pub(super) fn next_spec_token(&mut self) -> Option<SpannedToken> {
+        loop {
+            self.attribute_spans.clear();
+            if self.is_eof() {
+                return None;
+            }
+
+            let start = self.position;
+            let token = if self.plaintext {
+                let text = self.remaining().to_owned();
+                self.position = self.input.len();
+                Some(HtmlToken::Text(text))
+            } else if let Some(text) = self.spec_raw_text() {
+                Some(text)
+            } else if self.peek(0) == Some('<') && self.at_markup_start() {
+                self.advance();
+                self.spec_tag_open(start)
+            } else {
+                Some(self.spec_data_text())
+            };
+
+            // `None` means the markup produced no token (`</>`, or EOF in a tag).
+            if let Some(token) = token {
+                return Some(self.spanned(token, start));
+            }
+        }
+    }
+
+    fn spec_error(&mut self, error: TokenizeError, start: usize) {
+        self.parse_errors.push(ParseError {
+            error,
+            span: Span::new(start, self.position),
+        });
+    }
+
+    /// Steps back over `c`, so the next state sees it again.
+    fn reconsume(&mut self, c: char) {
+        self.position -= c.len_utf8();
+    }
+
+    /// Whether the `<` at the current position opens markup rather than
+    /// being a literal character.
+    fn at_markup_start(&self) -> bool {
+        match self.peek(1) {
+            Some(c) if c.is_ascii_alphabetic() => true,
+            Some('!' | '?') => true,
+            // `</` at EOF is emitted as text.
+            Some('/') => self.peek(2).is_some(),
+            _ => false,
+        }
+    }
+
+    /// Data state: characters up to the next markup.
+    fn spec_data_text(&mut self) -> HtmlToken {
+        let mut text = String::new();
+        loop {
+            text.push_str(self.consume_until(b'<'));
+            if self.is_eof() || self.at_markup_start() {
+                break;
+            }
+            self.advance();
+            text.push('<');
+        }
+        HtmlToken::Text(self.decode(text))
+    }
+
+    /// The content of an RCDATA, RAWTEXT, or script data element, if the
+    /// tokenizer is in one and it is not empty. NUL characters in it are
+    /// replaced, as they are in those states.
+    fn spec_raw_text(&mut self) -> Option<HtmlToken> {
+        let element = self.raw_text_element.take()?;
+        let text = if element.name == "script" {
+            let text = self.scan_script_data();
+            (!text.is_empty()).then_some(HtmlToken::Text(text))
+        } else {
+            self.parse_raw_text(&element)
+        };
+        text.map(|token| match token {
+            HtmlToken::Text(text) if text.contains('\0') => {
+                HtmlToken::Text(text.replace('\0', "\u{fffd}"))
+            }
+            token => token,
+        })
+    }
+
+    /// Script data states: consumes script content up to its end tag,
+    /// honouring `<!--` escapes, inside which a nested `<script>` hides the
+    /// next `</script>`.
+    fn scan_script_data(&mut self) -> String {
+        let mut text = String::new();
+        let mut state = ScriptState::ScriptData;
+        let mut dashes = 0;
+
+        while let Some(c) = self.peek(0) {
+            let rest = self.remaining();
+            match state {
+                ScriptState::ScriptData => {
+                    // Only a `<` can change state here, so skip ahead to it.
+                    if c != '<' {
+                        text.push_str(self.consume_until(b'<'));
+                        continue;
+                    }
+                    if rest.starts_with("<!--") {
+                        text.push_str("<!--");
+                        self.advance_n(4);
+                        state = ScriptState::Escaped;
+                        dashes = 2;
+                        continue;
+                    }
+                    if self.at_end_tag("script") {
+                        break;
+                    }
+                }
+                ScriptState::Escaped => {
+                    if self.at_end_tag("script") {
+                        break;
+                    }
+                    if starts_with_tag_name(rest, "<", "script") {
+                        text.push_str(rest.get(.."<script".len()).unwrap_or_default());
+                        self.advance_n("<script".len());
+                        state = ScriptState::DoubleEscaped;
+                        dashes = 0;
+                        continue;
+                    }
+                }
+                ScriptState::DoubleEscaped => {
+                    if starts_with_tag_name(rest, "</", "script") {
+                        text.push_str(rest.get(.."</script".len()).unwrap_or_default());
+                        self.advance_n("</script".len());
+                        state = ScriptState::Escaped;
+                        dashes = 0;
+                        continue;
+                    }
+                }
+            }
+
+            if state != ScriptState::ScriptData && c == '>' && dashes >= 2 {
+                state = ScriptState::ScriptData;
+            }
+            dashes = if c == '-' { dashes + 1 } else { 0 };
+            self.advance();
+            text.push(c);
+        }
+
+        text
+    }
+
+    /// Tag open state, just after the `<`.
+    fn spec_tag_open(&mut self, start: usize) -> Option<HtmlToken> {
+        match self.peek(0) {
+            Some('!') => {
+                self.advance();
+                Some(self.spec_markup_declaration(start))
+            }
+            Some('/') => {
+                self.advance();
+                self.spec_end_tag_open(start)
+            }
+            Some('?') => {
+                self.spec_error(TokenizeError::InvalidTag, start);
+                Some(self.parse_bogus_comment())
+            }
+            _ => self.spec_tag(start, false),
+        }
+    }
+
+    /// End tag open state, just after the `</`.
+    fn spec_end_tag_open(&mut self, start: usize) -> Option<HtmlToken> {
+        match self.peek(0) {
+            Some(c) if c.is_ascii_alphabetic() => self.spec_tag(start, true),
+            Some('>') => {
+                self.advance();
+                self.spec_error(TokenizeError::InvalidTag, start);
+                None
+            }
+            _ => {
+                self.spec_error(TokenizeError::InvalidTag, start);
+                Some(self.parse_bogus_comment())
+            }
+        }
+    }
+
+    /// Tag name state through self-closing start tag state. Returns `None`
+    /// if the input ends inside the tag, which drops it.
+    fn spec_tag(&mut self, start: usize, is_end: bool) -> Option<HtmlToken> {
+        let mut tag = TagBuilder::default();
+        let mut state = TagState::TagName;
+
+        loop {
+            let position = self.position;
+            let Some(c) = self.advance() else {
+                self.spec_error(TokenizeError::UnexpectedEOF, start);
+                return None;
+            };
+
+            match self.spec_tag_step(&mut tag, state, c, position) {
+                Some(next) => state = next,
+                None => break,
+            }
+        }
+
+        tag.finish_attribute();
+        for span in std::mem::take(&mut tag.duplicate_spans) {
+            self.parse_errors.push(ParseError {
+                error: TokenizeError::DuplicateAttribute,
+                span,
+            });
+        }
+        Some(self.emit_spec_tag(tag, start, is_end))
+    }
+
+    /// Consumes `c` (found at `position`) in `state`, returning the next
+    /// state, or `None` once the tag is complete.
+    fn spec_tag_step(
+        &mut self,
+        tag: &mut TagBuilder,
+        state: TagState,
+        c: char,
+        position: usize,
+    ) -> Option<TagState> {
+        match state {
+            TagState::TagName => match c {
+                c if is_spec_whitespace(c) => Some(TagState::BeforeAttributeName),
+                '/' => Some(TagState::SelfClosingStartTag),
+                '>' => None,
+                c => {
+                    tag.name.push(replace_null(c).to_ascii_lowercase());
+                    Some(TagState::TagName)
+                }
+            },
+            TagState::BeforeAttributeName => match c {
+                c if is_spec_whitespace(c) => Some(TagState::BeforeAttributeName),
+                '/' | '>' => {
+                    self.reconsume(c);
+                    Some(TagState::AfterAttributeName)
+                }
+                '=' => {
+                    self.spec_error(TokenizeError::InvalidAttribute, position);
+                    tag.start_attribute(position);
+                    tag.push_to_name(c, self.position);
+                    Some(TagState::AttributeName)
+                }
+                c => {
+                    tag.start_attribute(position);
+                    self.reconsume(c);
+                    Some(TagState::AttributeName)
+                }
+            },
+            TagState::AttributeName => match c {
+                c if is_spec_whitespace(c) || c == '/' || c == '>' => {
+                    self.reconsume(c);
+                    Some(TagState::AfterAttributeName)
+                }
+                '=' => Some(TagState::BeforeAttributeValue),
+                c => {
+                    if matches!(c, '"' | '\'' | '<') {
+                        self.spec_error(TokenizeError::InvalidAttribute, position);
+                    }
+                    tag.push_to_name(replace_null(c).to_ascii_lowercase(), self.position);
+                    Some(TagState::AttributeName)
+                }
+            },
+            TagState::AfterAttributeName => match c {
+                c if is_spec_whitespace(c) => Some(TagState::AfterAttributeName),
+                '/' => Some(TagState::SelfClosingStartTag),
+                '=' => Some(TagState::BeforeAttributeValue),
+                '>' => None,
+                c => {
+                    tag.start_attribute(position);
+                    self.reconsume(c);
+                    Some(TagState::AttributeName)
+                }
+            },
+            TagState::SelfClosingStartTag => {
+                if c == '>' {
+                    tag.self_closing = true;
+                    return None;
+                }
+                self.spec_error(TokenizeError::InvalidTag, position);
+                self.reconsume(c);
+                Some(TagState::BeforeAttributeName)
+            }
+            _ => self.spec_attribute_value_step(tag, state, c, position),
+        }
+    }
+
+    /// [`spec_tag_step`](Self::spec_tag_step) for the attribute value states.
+    fn spec_attribute_value_step(
+        &mut self,
+        tag: &mut TagBuilder,
+        state: TagState,
+        c: char,
+        position: usize,
+    ) -> Option<TagState> {
+        match state {
+            TagState::BeforeAttributeValue => match c {
+                c if is_spec_whitespace(c) => Some(TagState::BeforeAttributeValue),
+                '"' | '\'' => Some(TagState::AttributeValueQuoted(c)),
+                '>' => {
+                    self.spec_error(TokenizeError::InvalidAttribute, position);
+                    None
+                }
+                c => {
+                    self.reconsume(c);
+                    Some(TagState::AttributeValueUnquoted)
+                }
+            },
+            TagState::AttributeValueQuoted(quote) => {
+                if c == quote {
+                    tag.extend_value_span(self.position);
+                    Some(TagState::AfterAttributeValueQuoted)
+                } else {
+                    tag.push_to_value(replace_null(c), self.position);
+                    Some(state)
+                }
+            }
+            TagState::AttributeValueUnquoted => match c {
+                c if is_spec_whitespace(c) => Some(TagState::BeforeAttributeName),
+                '>' => None,
+                c => {
+                    if matches!(c, '"' | '\'' | '<' | '=' | '`') {
+                        self.spec_error(TokenizeError::InvalidAttribute, position);
+                    }
+                    tag.push_to_value(replace_null(c), self.position);
+                    Some(state)
+                }
+            },
+            TagState::AfterAttributeValueQuoted => match c {
+                c if is_spec_whitespace(c) => Some(TagState::BeforeAttributeName),
+                '/' => Some(TagState::SelfClosingStartTag),
+                '>' => None,
+                c => {
+                    self.spec_error(TokenizeError::InvalidAttribute, position);
+                    self.reconsume(c);
+                    Some(TagState::BeforeAttributeName)
+                }
+            },
+            _ => Some(state),
+        }
+    }
+
+    fn emit_spec_tag(&mut self, tag: TagBuilder, start: usize, is_end: bool) -> HtmlToken {
+        let mut name = tag.name;
+
+        if is_end {
+            if !tag.attributes.is_empty() || tag.self_closing {
+                self.spec_error(TokenizeError::InvalidTag, start);
+            }
+            self.leave_foreign_content(&mut name);
+            return HtmlToken::EndTag { name };
+        }
+
+        if !self.in_foreign_content() {
+            self.raw_text_element = RawTextElement::for_start_tag(&name);
+            self.plaintext = name == "plaintext";
+        }
+
+        let mut attributes: Vec<_> = tag
+            .attributes
+            .into_iter()
+            .map(|(name, value)| (name, self.decode_attribute(value)))
+            .collect();
+        self.enter_foreign_content(&mut name, &mut attributes, tag.self_closing);
+        self.attribute_spans = tag.attribute_spans;
+
+        HtmlToken::StartTag {
+            name,
+            attributes,
+            self_closing: tag.self_closing,
+        }
+    }
+
+    /// Markup declaration open state, just after the `<!`.
+    fn spec_markup_declaration(&mut self, start: usize) -> HtmlToken {
+        if self.lookahead_is("--") {
+            self.advance_n(2);
+            return self.spec_comment(start);
+        }
+
+        if self.lookahead_is_ignore_case("DOCTYPE") {
+            self.advance_n("DOCTYPE".len());
+            return self.spec_doctype(start);
+        }
+
+        if self.lookahead_is("[CDATA[") && self.in_foreign_content() {
+            self.advance_n("[CDATA[".len());
+            return self.spec_cdata_section(start);
+        }
+
+        self.spec_error(TokenizeError::MalformedComment, start);
+        self.parse_bogus_comment()
+    }
+
+    /// Comment start state through comment end bang state.
+    fn spec_comment(&mut self, start: usize) -> HtmlToken {
+        let mut comment = String::new();
+        let mut state = CommentState::CommentStart;
+
+        loop {
+            let Some(c) = self.advance() else {
+                self.spec_error(TokenizeError::MalformedComment, start);
+                break;
+            };
+
+            state = match state {
+                CommentState::CommentStart => match c {
+                    '-' => CommentState::CommentStartDash,
+                    '>' => {
+                        self.spec_error(TokenizeError::MalformedComment, start);
+                        break;
+                    }
+                    c => {
+                        self.reconsume(c);
+                        CommentState::Comment
+                    }
+                },
+                CommentState::CommentStartDash => match c {
+                    '-' => CommentState::CommentEnd,
+                    '>' => {
+                        self.spec_error(TokenizeError::MalformedComment, start);
+                        break;
+                    }
+                    c => {
+                        comment.push('-');
+                        self.reconsume(c);
+                        CommentState::Comment
+                    }
+                },
+                CommentState::Comment => match c {
+                    '-' => CommentState::CommentEndDash,
+                    c => {
+                        comment.push(replace_null(c));
+                        CommentState::Comment
+                    }
+                },
+                CommentState::CommentEndDash => match c {
+                    '-' => CommentState::CommentEnd,
+                    c => {
+                        comment.push('-');
+                        self.reconsume(c);
+                        CommentState::Comment
+                    }
+                },
+                CommentState::CommentEnd => match c {
+                    '>' => break,
+                    '!' => CommentState::CommentEndBang,
+                    '-' => {
+                        comment.push('-');
+                        CommentState::CommentEnd
+                    }
+                    c => {
+                        comment.push_str("--");
+                        self.reconsume(c);
+                        CommentState::Comment
+                    }
+                },
+                CommentState::CommentEndBang => match c {
+                    '-' => {
+                        comment.push_str("--!");
+                        CommentState::CommentEndDash
+                    }
+                    '>' => {
+                        self.spec_error(TokenizeError::MalformedComment, start);
+                        break;
+                    }
+                    c => {
+                        comment.push_str("--!");
+                        self.reconsume(c);
+                        CommentState::Comment
+                    }
+                },
+            };
+        }
+
+        HtmlToken::Comment(comment)
+    }
+
+    /// DOCTYPE states, simplified: the token holds the lowercased name
+    /// followed by any public or system identifiers verbatim.
+    fn spec_doctype(&mut self, start: usize) -> HtmlToken {
+        let mut doctype = String::new();
+        let mut terminated = false;
+        while let Some(c) = self.advance() {
+            if c == '>' {
+                terminated = true;
+                break;
+            }
+            doctype.push(replace_null(c));
+        }
+        if !terminated {
+            self.spec_error(TokenizeError::UnexpectedEOF, start);
+        }
+
+        let doctype = doctype.trim_matches(is_spec_whitespace);
+        let (name, rest) = doctype
+            .split_once(is_spec_whitespace)
+            .unwrap_or((doctype, ""));
+        let rest = rest.trim_start_matches(is_spec_whitespace);
+
+        let mut normalized = name.to_ascii_lowercase();
+        if !rest.is_empty() {
+            normalized.push(' ');
+            normalized.push_str(rest);
+        }
+        HtmlToken::Doctype(normalized)
+    }
+
+    /// CDATA section state, in foreign content only.
+    fn spec_cdata_section(&mut self, start: usize) -> HtmlToken {
+        let rest = self.remaining();
+        let (content, consumed) = if let Some(end) = rest.find("]]>") {
+            (rest.get(..end).unwrap_or_default(), end + "]]>".len())
+        } else {
+            self.spec_error(TokenizeError::UnexpectedEOF, start);
+            (rest, rest.len())
+        };
+        let content = content.to_owned();
+        self.position += consumed;
+        HtmlToken::Text(content)
+    }
+}
+
+/// ASCII whitespace as the tokenizer defines it: tab, LF, FF, space (and CR,
+/// which preprocessing would have normalized away).
+fn is_spec_whitespace(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\u{c}' | ' ' | '\r')
+}
+
+fn replace_null(c: char) -> char {
+    if c == '\0' {
+        char::REPLACEMENT_CHARACTER
+    } else {
+        c
+    }
+}
+
+/// Whether `rest` starts with `opener` and `name` (case-insensitively)
+/// followed by whitespace, `/`, or `>`.
+fn starts_with_tag_name(rest: &str, opener: &str, name: &str) -> bool {
+    let Some(rest) = rest.strip_prefix(opener) else {
+        return false;
+    };
+    rest.get(..name.len())
+        .is_some_and(|candidate| candidate.eq_ignore_ascii_case(name))
+        && rest
+            .get(name.len()..)
+            .and_then(|after| after.chars().next())
+            .is_some_and(|c| is_spec_whitespace(c) || c == '/' || c == '>')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec_tokens(input: &str) -> Vec<HtmlToken> {
+        HtmlTokenizer::new(input)
+            .with_spec_conformance(true)
+            .iter()
+            .map(|t| t.unwrap())
+            .collect()
+    }
+
+    fn start_tag(name: &str, attributes: &[(&str, &str)]) -> HtmlToken {
+        HtmlToken::StartTag {
+            name: name.to_string(),
+            attributes: attributes
+                .iter()
+                .map(|(n, v)| (n.to_string(), v.to_string()))
+                .collect(),
+            self_closing: false,
+        }
+    }
+
+    fn end_tag(name: &str) -> HtmlToken {
+        HtmlToken::EndTag {
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_names_are_lowercased_and_whitespace_kept() {
+        assert_eq!(
+            spec_tokens("<DIV Class=a class=b>\n x </DIV>"),
+            vec![
+                start_tag("div", &[("class", "a")]),
+                HtmlToken::Text("\n x ".to_string()),
+                end_tag("div"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_attribute_is_reported() {
+        let mut tokenizer = HtmlTokenizer::new("<a id=x id=y>").with_spec_conformance(true);
+        tokenizer.next_token();
+
+        assert_eq!(
+            tokenizer.parse_errors(),
+            &[ParseError {
+                error: TokenizeError::DuplicateAttribute,
+                span: Span::new(8, 12),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_stray_less_than_is_text() {
+        let mut tokenizer = HtmlTokenizer::new("a < b <> c</>").with_spec_conformance(true);
+        let mut tokens = Vec::new();
+        while let Some(token) = tokenizer.next_token() {
+            tokens.push(token.unwrap());
+        }
+
+        assert_eq!(tokens, vec![HtmlToken::Text("a < b <> c".to_string())]);
+        assert_eq!(tokenizer.parse_errors().len(), 1);
+    }
+
+    #[test]
+    fn test_comment_edge_cases() {
+        assert_eq!(
+            spec_tokens("<!--><!--a--!><!-- b -- c -->"),
+            vec![
+                HtmlToken::Comment(String::new()),
+                HtmlToken::Comment("a".to_string()),
+                HtmlToken::Comment(" b -- c ".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bogus_comments() {
+        assert_eq!(
+            spec_tokens("<?xml version=\"1.0\"?></1><!x>"),
+            vec![
+                HtmlToken::Comment("?xml version=\"1.0\"?".to_string()),
+                HtmlToken::Comment("1".to_string()),
+                HtmlToken::Comment("x".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_script_data_escapes() {
+        let tokens = spec_tokens("<script><!--<script></script>--></script>");
+
+        assert_eq!(
+            tokens,
+            vec![
+                start_tag("script", &[]),
+                HtmlToken::Text("<!--<script></script>-->".to_string()),
+                end_tag("script"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_eof_in_tag_drops_it() {
+        assert_eq!(
+            spec_tokens("text<div class="),
+            vec![HtmlToken::Text("text".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_doctype_name_is_lowercased() {
+        assert_eq!(
+            spec_tokens("<!doctype HTML PUBLIC \"-//W3C//DTD HTML 4.01//EN\">"),
+            vec![HtmlToken::Doctype(
+                "html PUBLIC \"-//W3C//DTD HTML 4.01//EN\"".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_null_in_raw_text_is_replaced() {
+        for name in ["title", "style", "script"] {
+            assert_eq!(
+                spec_tokens(&format!("<{name}>a\0b</{name}>")),
+                vec![
+                    start_tag(name, &[]),
+                    HtmlToken::Text("a\u{fffd}b".to_string()),
+                    end_tag(name),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn test_plaintext_consumes_rest() {
+        assert_eq!(
+            spec_tokens("<plaintext><b>&amp;</plaintext>"),
+            vec![
+                start_tag("plaintext", &[]),
+                HtmlToken::Text("<b>&amp;</plaintext>".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,254 @@
+use super::{HtmlToken, HtmlTokenizer, TokenizeError, TokenizerOptions, TokenizerState};
+
+/// A push-based tokenizer for input that arrives in chunks, such as a page
+/// body being read from the network.
+///
+/// Each [`feed`](Self::feed) returns the tokens that are known to be
+/// complete. A token that reaches the end of the buffered input (an
+/// unterminated tag, or text that may continue) is held back until more
+/// input arrives or [`finish`](Self::finish) is called.
+#[derive(Debug, Default)]
+pub struct StreamingTokenizer {
+    /// Decoded input that has not been tokenized yet.
+    buffer: String,
+
+    /// Trailing bytes of an incomplete UTF-8 sequence from the last chunk.
+    pending_bytes: Vec<u8>,
+
+    /// Tokenizer state at the start of `buffer`.
+    state: TokenizerState,
+
+    /// How much decoded input came before `buffer`, so that the spans of
+    /// emitted parse errors are relative to the whole input.
+    offset: usize,
+
+    options: TokenizerOptions,
+}
+
+impl StreamingTokenizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn with_options(mut self, options: TokenizerOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// See [`TokenizerOptions::decode_entities`].
+    #[must_use]
+    pub fn with_decode_entities(mut self, decode_entities: bool) -> Self {
+        self.options = self.options.decode_entities(decode_entities);
+        self
+    }
+
+    /// Adds a chunk of UTF-8 input and returns the tokens it completed.
+    ///
+    /// Invalid UTF-8 is replaced with U+FFFD; a multi-byte character split
+    /// across chunks is reassembled.
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<Result<HtmlToken, TokenizeError>> {
+        self.pending_bytes.extend_from_slice(bytes);
+        self.decode_pending_bytes();
+        self.tokenize_buffer(false)
+    }
+
+    /// Signals the end of input and returns all remaining tokens.
+    pub fn finish(mut self) -> Vec<Result<HtmlToken, TokenizeError>> {
+        if !self.pending_bytes.is_empty() {
+            self.buffer
+                .push_str(&String::from_utf8_lossy(&self.pending_bytes));
+            self.pending_bytes.clear();
+        }
+        self.tokenize_buffer(true)
+    }
+
+    /// Moves every complete UTF-8 sequence from `pending_bytes` into `buffer`.
+    fn decode_pending_bytes(&mut self) {
+        let mut rest = std::mem::take(&mut self.pending_bytes);
+
+        loop {
+            match std::str::from_utf8(&rest) {
+                Ok(valid) => {
+                    self.buffer.push_str(valid);
+                    return;
+                }
+                Err(error) => {
+                    let (valid, after_valid) = rest.split_at(error.valid_up_to());
+                    self.buffer
+                        .push_str(std::str::from_utf8(valid).unwrap_or_default());
+
+                    let Some(invalid_len) = error.error_len() else {
+                        // Incomplete sequence at the end; wait for more bytes.
+                        self.pending_bytes = after_valid.to_vec();
+                        return;
+                    };
+                    self.buffer.push(char::REPLACEMENT_CHARACTER);
+                    rest = after_valid.get(invalid_len..).unwrap_or_default().to_vec();
+                }
+            }
+        }
+    }
+
+    fn tokenize_buffer(&mut self, at_end: bool) -> Vec<Result<HtmlToken, TokenizeError>> {
+        let mut tokens = Vec::new();
+        let mut tokenizer = HtmlTokenizer::new(&self.buffer)
+            .with_options(self.options.clone())
+            .with_state(self.state.clone());
+
+        // Parse errors come just before their token and are held back with it.
+        let mut errors = Vec::new();
+        let (consumed, state) = loop {
+            let state_before = tokenizer.state();
+            let position_before = tokenizer.position;
+
+            let result = loop {
+                match tokenizer.next_token() {
+                    Some(Ok(HtmlToken::ParseError(mut error))) => {
+                        error.span.start += self.offset;
+                        error.span.end += self.offset;
+                        errors.push(Ok(HtmlToken::ParseError(error)));
+                    }
+                    result => break result,
+                }
+            };
+            let Some(result) = result else {
+                tokens.append(&mut errors);
+                break (tokenizer.position, tokenizer.state());
+            };
+
+            // A token that ends at (or one character of lookahead before) the end
+            // of the buffer may change once more input arrives, as may one that
+            // was recovered from markup running to the end, such as `<!-- d`.
+            if !at_end
+                && (tokenizer.peek(1).is_none()
+                    || tokenizer.furthest_position >= tokenizer.input.len())
+            {
+                break (position_before, state_before);
+            }
+            tokens.append(&mut errors);
+            tokens.push(result);
+        };
+
+        let consumed = consumed.min(self.buffer.len());
+        self.buffer.drain(..consumed);
+        self.offset += consumed;
+        self.state = state;
+        tokens
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens_of(results: Vec<Result<HtmlToken, TokenizeError>>) -> Vec<HtmlToken> {
+        results.into_iter().map(|t| t.unwrap()).collect()
+    }
+
+    fn tokenize_in_chunks(input: &str, chunk_size: usize) -> Vec<HtmlToken> {
+        let mut streaming = StreamingTokenizer::new();
+        let mut tokens = Vec::new();
+        for chunk in input.as_bytes().chunks(chunk_size) {
+            tokens.extend(tokens_of(streaming.feed(chunk)));
+        }
+        tokens.extend(tokens_of(streaming.finish()));
+        tokens
+    }
+
+    #[test]
+    fn test_matches_whole_input_tokenization() {
+        let input = "<!DOCTYPE html><html><head><title>T &amp; U</title>\
+            <script>if (a < b) { x(); }</script></head>\
+            <body class=\"main\"><p>héllo <b>wörld</b></p><!-- note --></body></html>";
+        let expected: Vec<_> = HtmlTokenizer::new(input)
+            .iter()
+            .map(|t| t.unwrap())
+            .collect();
+
+        for chunk_size in [1, 2, 3, 7, 64, input.len()] {
+            assert_eq!(
+                tokenize_in_chunks(input, chunk_size),
+                expected,
+                "chunk size {chunk_size}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_errors_match_whole_input_tokenization() {
+        let input = "<p>a <> b <a href=x HREF=y>c</a> <!-- d</p>";
+        let options = TokenizerOptions::new()
+            .error_recovery(true)
+            .emit_parse_errors(true);
+        let expected: Vec<_> = HtmlTokenizer::new(input)
+            .with_options(options.clone())
+            .iter()
+            .map(|t| t.unwrap())
+            .collect();
+        assert!(
+            expected
+                .iter()
+                .filter(|t| matches!(t, HtmlToken::ParseError(_)))
+                .count()
+                >= 3
+        );
+
+        for chunk_size in [1, 2, 5, input.len()] {
+            let mut streaming = StreamingTokenizer::new().with_options(options.clone());
+            let mut tokens = Vec::new();
+            for chunk in input.as_bytes().chunks(chunk_size) {
+                tokens.extend(tokens_of(streaming.feed(chunk)));
+            }
+            tokens.extend(tokens_of(streaming.finish()));
+            assert_eq!(tokens, expected, "chunk size {chunk_size}");
+        }
+    }
+
+    #[test]
+    fn test_incomplete_tag_is_held_back() {
+        let mut streaming = StreamingTokenizer::new();
+
+        assert!(streaming.feed(b"<div cla").is_empty());
+        assert_eq!(
+            tokens_of(streaming.feed(b"ss=\"x\">text")),
+            vec![HtmlToken::StartTag {
+                name: "div".to_string(),
+                attributes: vec![("class".to_string(), "x".to_string())],
+                self_closing: false,
+            }]
+        );
+        assert_eq!(
+            tokens_of(streaming.finish()),
+            vec![HtmlToken::Text("text".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_split_utf8_sequence() {
+        let bytes = "<p>あ</p>".as_bytes();
+        let mut streaming = StreamingTokenizer::new();
+        let mut tokens = tokens_of(streaming.feed(bytes.get(..4).unwrap()));
+        tokens.extend(tokens_of(streaming.feed(bytes.get(4..).unwrap())));
+        tokens.extend(tokens_of(streaming.finish()));
+
+        assert_eq!(tokens[1], HtmlToken::Text("あ".to_string()));
+    }
+
+    #[test]
+    fn test_invalid_utf8_is_replaced() {
+        let mut streaming = StreamingTokenizer::new();
+        let mut tokens = tokens_of(streaming.feed(b"a\xffb"));
+        tokens.extend(tokens_of(streaming.finish()));
+
+        assert_eq!(tokens, vec![HtmlToken::Text("a\u{fffd}b".to_string())]);
+    }
+
+    #[test]
+    fn test_raw_text_across_chunks() {
+        let tokens = tokenize_in_chunks("<script>a</b>c</script>", 9);
+
+        assert_eq!(tokens[1], HtmlToken::Text("a</b>c".to_string()));
+        assert_eq!(tokens.len(), 3);
+    }
+}
@@ -9,33 +9,84 @@ pub struct HttpResponse {
 
 #[cfg(not(target_arch = "wasm32"))]
 mod fetch {
+    use std::sync::LazyLock;
+
     use super::HttpResponse;
+    use crate::html_tokenizer::decode_html;
 
-    /// Fetches a URL and returns the HTTP response.
+    /// Fetches a URL and returns the HTTP response. The body is decoded from
+    /// the encoding the response declares, as for an HTML page.
     ///
     /// # Errors
     ///
     /// Returns an error if the request fails, the response body cannot be read,
     /// or header values are not valid UTF-8.
     pub fn fetch_url(url: &str) -> Result<HttpResponse, Box<dyn std::error::Error>> {
-        let response = reqwest::blocking::get(url)?;
+        fetch_url_with_headers(url, &[])
+    }
+
+    /// Fetches a URL, sending the given extra request headers.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails, the response body cannot be read,
+    /// or header values are not valid UTF-8.
+    pub fn fetch_url_with_headers(
+        url: &str,
+        headers: &[(String, String)],
+    ) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+        let response = send(url, headers)?;
         let status = response.status().as_u16();
-        let headers = response
+        let headers: Vec<(String, String)> = response
             .headers()
             .iter()
             .map(|(name, value)| (name.to_string(), value.to_str().unwrap_or("").to_owned()))
             .collect();
-        let body = response.text()?;
+        let content_type = headers
+            .iter()
+            .find(|(name, _)| name == "content-type")
+            .map(|(_, value)| value.as_str());
+        let (body, _) = decode_html(&response.bytes()?, content_type);
         Ok(HttpResponse {
             status,
             headers,
             body,
         })
     }
+
+    /// Fetches a URL, sending the given extra request headers, and returns
+    /// the body as it came, for what is not text, such as an image.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails, the status is not a success,
+    /// or the response body cannot be read.
+    pub fn fetch_bytes_with_headers(
+        url: &str,
+        headers: &[(String, String)],
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let response = send(url, headers)?.error_for_status()?;
+        Ok(response.bytes()?.to_vec())
+    }
+
+    /// One client for every request, so connections are pooled and reused.
+    static CLIENT: LazyLock<reqwest::blocking::Client> =
+        LazyLock::new(reqwest::blocking::Client::new);
+
+    fn send(
+        url: &str,
+        headers: &[(String, String)],
+    ) -> reqwest::Result<reqwest::blocking::Response> {
+        let mut request = CLIENT.get(url);
+        for (name, value) in headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+        request.send()
+    }
 }
 
 #[cfg(not(target_arch = "wasm32"))]
-pub use fetch::fetch_url;
+pub use fetch::{fetch_bytes_with_headers, fetch_url, fetch_url_with_headers};
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,227 @@
+/// A language the browser chrome is translated into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+pub enum Language {
+    #[default]
+    English,
+    Japanese,
+}
+
+impl Language {
+    pub const ALL: [Self; 2] = [Self::English, Self::Japanese];
+
+    /// Picks a language from a locale string such as `ja_JP.UTF-8` or `en-US`.
+    ///
+    /// Unknown locales fall back to English.
+    pub fn from_locale(locale: &str) -> Self {
+        let code = locale
+            .split(['_', '-', '.', '@'])
+            .next()
+            .unwrap_or_default();
+        if code.eq_ignore_ascii_case("ja") {
+            Self::Japanese
+        } else {
+            Self::English
+        }
+    }
+
+    /// The language of the system locale, or English if it cannot be determined.
+    pub fn system() -> Self {
+        system_locale().map_or_else(Self::default, |locale| Self::from_locale(&locale))
+    }
+
+    /// The language's name in that language, for the language picker.
+    pub fn native_name(self) -> &'static str {
+        match self {
+            Self::English => "English",
+            Self::Japanese => "日本語",
+        }
+    }
+
+    pub fn strings(self) -> &'static Strings {
+        match self {
+            Self::English => &ENGLISH,
+            Self::Japanese => &JAPANESE,
+        }
+    }
+}
+
+/// Reads the locale from the POSIX environment variables, in priority order.
+#[cfg(not(target_arch = "wasm32"))]
+fn system_locale() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .into_iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty() && value != "C" && value != "POSIX")
+}
+
+#[cfg(target_arch = "wasm32")]
+fn system_locale() -> Option<String> {
+    None
+}
+
+/// Every user-visible string in the browser chrome.
+///
+/// Each language provides a complete bundle, so a missing translation is a
+/// compile error rather than a blank label.
+#[derive(Debug)]
+pub struct Strings {
+    pub menu_file: &'static str,
+    pub menu_quit: &'static str,
+    pub menu_language: &'static str,
+    pub language_system: &'static str,
+    pub heading: &'static str,
+    pub back: &'static str,
+    pub forward: &'static str,
+    pub url_label: &'static str,
+    pub fetch: &'static str,
+    pub status: &'static str,
+    pub headers: &'static str,
+    pub body: &'static str,
+    pub error_prefix: &'static str,
+    pub error_empty_url: &'static str,
+    pub error_blocked_by_extension: &'static str,
+    pub error_wasm_fetch: &'static str,
+    pub blocked_requests_hover: &'static str,
+    pub block_on_this_site: &'static str,
+    pub menu_filter_lists: &'static str,
+    pub add_filter_list: &'static str,
+    pub layout_tree: &'static str,
+    pub raw_response: &'static str,
+    pub find: &'static str,
+    pub find_previous: &'static str,
+    pub find_next: &'static str,
+    pub find_no_matches: &'static str,
+    pub find_close: &'static str,
+    pub source_code: &'static str,
+    pub powered_by: &'static str,
+    pub and: &'static str,
+}
+
+static ENGLISH: Strings = Strings {
+    menu_file: "File",
+    menu_quit: "Quit",
+    menu_language: "Language",
+    language_system: "System default",
+    heading: "URL Fetcher",
+    back: "Back",
+    forward: "Forward",
+    url_label: "URL: ",
+    fetch: "Fetch",
+    status: "Status",
+    headers: "Headers:",
+    body: "Body:",
+    error_prefix: "Error",
+    error_empty_url: "URL cannot be empty",
+    error_blocked_by_extension: "Request blocked by extension",
+    error_wasm_fetch: "WASM fetching not fully implemented. Use native build for full functionality.",
+    blocked_requests_hover: "Requests blocked on this page",
+    block_on_this_site: "Block on this site",
+    menu_filter_lists: "Filter lists",
+    add_filter_list: "Add",
+    layout_tree: "Layout tree",
+    raw_response: "Raw response",
+    find: "Find:",
+    find_previous: "Previous match",
+    find_next: "Next match",
+    find_no_matches: "No matches",
+    find_close: "Close",
+    source_code: "Source code.",
+    powered_by: "Powered by ",
+    and: " and ",
+};
+
+static JAPANESE: Strings = Strings {
+    menu_file: "ファイル",
+    menu_quit: "終了",
+    menu_language: "言語",
+    language_system: "システムの既定",
+    heading: "URL フェッチャー",
+    back: "戻る",
+    forward: "進む",
+    url_label: "URL: ",
+    fetch: "取得",
+    status: "ステータス",
+    headers: "ヘッダー:",
+    body: "本文:",
+    error_prefix: "エラー",
+    error_empty_url: "URL を入力してください",
+    error_blocked_by_extension: "拡張機能によってリクエストがブロックされました",
+    error_wasm_fetch: "WASM 版の取得機能は未完成です。すべての機能を使うにはネイティブ版をご利用ください。",
+    blocked_requests_hover: "このページでブロックしたリクエスト数",
+    block_on_this_site: "このサイトでブロックする",
+    menu_filter_lists: "フィルターリスト",
+    add_filter_list: "追加",
+    layout_tree: "レイアウトツリー",
+    raw_response: "生のレスポンス",
+    find: "ページ内検索:",
+    find_previous: "前の一致",
+    find_next: "次の一致",
+    find_no_matches: "一致なし",
+    find_close: "閉じる",
+    source_code: "ソースコード",
+    powered_by: "Powered by ",
+    and: " と ",
+};
+
+/// Font files that cover Japanese, tried in order.
+#[cfg(not(target_arch = "wasm32"))]
+const CJK_FONT_CANDIDATES: &[&str] = &[
+    "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/google-noto-cjk/NotoSansCJK-Regular.ttc",
+    "/System/Library/Fonts/ヒラギノ角ゴシック W3.ttc",
+    "/System/Library/Fonts/Hiragino Sans GB.ttc",
+    "C:\\Windows\\Fonts\\YuGothR.ttc",
+    "C:\\Windows\\Fonts\\meiryo.ttc",
+    "C:\\Windows\\Fonts\\msgothic.ttc",
+];
+
+/// egui's bundled fonts have no Japanese glyphs, so add the first system
+/// CJK font we can find as a fallback for every font family.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn install_cjk_fallback_font(ctx: &egui::Context) {
+    let Some(bytes) = CJK_FONT_CANDIDATES
+        .iter()
+        .find_map(|path| std::fs::read(path).ok())
+    else {
+        log::warn!("No Japanese font found; Japanese text will not render");
+        return;
+    };
+
+    let mut fonts = egui::FontDefinitions::default();
+    fonts.font_data.insert(
+        "cjk-fallback".to_owned(),
+        std::sync::Arc::new(egui::FontData::from_owned(bytes)),
+    );
+    for family in fonts.families.values_mut() {
+        family.push("cjk-fallback".to_owned());
+    }
+    ctx.set_fonts(fonts);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_locale() {
+        assert_eq!(Language::from_locale("ja_JP.UTF-8"), Language::Japanese);
+        assert_eq!(Language::from_locale("ja-JP"), Language::Japanese);
+        assert_eq!(Language::from_locale("JA"), Language::Japanese);
+        assert_eq!(Language::from_locale("en_US.UTF-8"), Language::English);
+        assert_eq!(Language::from_locale("fr_FR"), Language::English);
+        assert_eq!(Language::from_locale(""), Language::English);
+    }
+
+    #[test]
+    fn test_bundles_are_distinct() {
+        assert_eq!(Language::English.strings().fetch, "Fetch");
+        assert_eq!(Language::Japanese.strings().fetch, "取得");
+    }
+
+    #[test]
+    fn test_native_names() {
+        let names: Vec<_> = Language::ALL.iter().map(|l| l.native_name()).collect();
+        assert_eq!(names, vec!["English", "日本語"]);
+    }
+}
@@ -0,0 +1,295 @@
+//! Images: fetching and decoding the images of pages in the background,
+//! and keeping them as egui textures.
+//!
+//! [`ImageCache::load`] hands an image to a small pool of worker threads,
+//! which fetch and decode it, so that all the UI thread has left to do is
+//! upload it once [`ImageCache::poll`] picks it up. Images are kept by URL,
+//! so an image several pages show is only fetched once, until the cache
+//! grows past its limit and drops the least recently used.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError, mpsc};
+
+use egui::{ColorImage, TextureHandle, TextureOptions};
+
+/// How images are fetched: their bytes, as they came. It is called on a
+/// worker thread for each image, so the app can run its extension hooks
+/// around the request.
+pub type FetchBytes = Arc<dyn Fn(&str) -> Result<Vec<u8>, String> + Send + Sync>;
+
+/// How many images are fetched and decoded at once.
+const WORKERS: usize = 4;
+
+/// How much texture memory the loaded images may take, in bytes, before
+/// the least recently used are dropped.
+pub const MAX_CACHE_BYTES: usize = 256 * 1024 * 1024;
+
+/// An image for the workers to fetch and decode.
+type Job = (String, FetchBytes);
+
+/// Where an image of an [`ImageCache`] is at.
+#[derive(Clone)]
+pub enum ImageState {
+    /// It is being fetched or decoded.
+    Loading,
+
+    /// It is decoded and uploaded.
+    Loaded(TextureHandle),
+
+    /// It could not be fetched, or it is not in a format that decodes.
+    /// The next [`ImageCache::load`] of it tries again.
+    Broken,
+}
+
+/// An image of an [`ImageCache`], with when it was last asked for.
+struct Entry {
+    state: ImageState,
+    last_used: u64,
+}
+
+/// The images of the session, by URL.
+pub struct ImageCache {
+    images: HashMap<String, Entry>,
+
+    /// Bumped on every [`ImageCache::load`], to order the images by use.
+    clock: u64,
+
+    /// The texture memory the loaded images take, in bytes.
+    bytes: usize,
+    max_bytes: usize,
+    jobs: mpsc::Sender<Job>,
+    receiver: mpsc::Receiver<(String, Option<ColorImage>)>,
+}
+
+impl Default for ImageCache {
+    fn default() -> Self {
+        Self::with_max_bytes(MAX_CACHE_BYTES)
+    }
+}
+
+impl ImageCache {
+    /// A cache whose loaded images may take up to `max_bytes` of texture
+    /// memory.
+    pub fn with_max_bytes(max_bytes: usize) -> Self {
+        let (jobs, queue) = mpsc::channel::<Job>();
+        let (sender, receiver) = mpsc::channel();
+        let queue = Arc::new(Mutex::new(queue));
+        for _ in 0..WORKERS {
+            let queue = Arc::clone(&queue);
+            let sender = sender.clone();
+            std::thread::spawn(move || {
+                loop {
+                    let job = queue.lock().unwrap_or_else(PoisonError::into_inner).recv();
+                    let Ok((url, fetch)) = job else {
+                        break;
+                    };
+                    let image = fetch(&url).ok().and_then(|bytes| decode(&bytes).ok());
+                    if sender.send((url, image)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        Self {
+            images: HashMap::new(),
+            clock: 0,
+            bytes: 0,
+            max_bytes,
+            jobs,
+            receiver,
+        }
+    }
+
+    /// Queues the image at `url` to be fetched and decoded with `fetch`,
+    /// unless it is already loaded or on its way.
+    pub fn load(&mut self, url: &str, fetch: &FetchBytes) {
+        self.clock += 1;
+        if let Some(entry) = self.images.get_mut(url)
+            && !matches!(entry.state, ImageState::Broken)
+        {
+            entry.last_used = self.clock;
+            return;
+        }
+        self.images.insert(
+            url.to_owned(),
+            Entry {
+                state: ImageState::Loading,
+                last_used: self.clock,
+            },
+        );
+        self.jobs.send((url.to_owned(), Arc::clone(fetch))).ok();
+    }
+
+    /// Uploads the images that were decoded since the last call to
+    /// textures of `ctx`, and marks those that failed as broken. Returns
+    /// the URLs of both.
+    pub fn poll(&mut self, ctx: &egui::Context) -> Vec<String> {
+        let mut arrived = Vec::new();
+        while let Ok((url, image)) = self.receiver.try_recv() {
+            let Some(entry) = self.images.get_mut(&url) else {
+                continue;
+            };
+            entry.state = match image {
+                Some(image) => {
+                    self.bytes += texture_bytes(image.size);
+                    ImageState::Loaded(ctx.load_texture(&url, image, TextureOptions::LINEAR))
+                }
+                None => ImageState::Broken,
+            };
+            arrived.push(url);
+        }
+        self.evict();
+        arrived
+    }
+
+    /// Drops the least recently used loaded images until the rest fit.
+    fn evict(&mut self) {
+        while self.bytes > self.max_bytes {
+            let oldest = self
+                .images
+                .iter()
+                .filter(|(_, entry)| matches!(entry.state, ImageState::Loaded(_)))
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(url, _)| url.clone());
+            let Some(Entry {
+                state: ImageState::Loaded(texture),
+                ..
+            }) = oldest.and_then(|url| self.images.remove(&url))
+            else {
+                break;
+            };
+            self.bytes -= texture_bytes(texture.size());
+        }
+    }
+
+    /// Where the image at `url` is at, or `None` if it was never loaded or
+    /// has been dropped since.
+    pub fn get(&self, url: &str) -> Option<&ImageState> {
+        self.images.get(url).map(|entry| &entry.state)
+    }
+
+    /// The size of the image at `url`, as `(width, height)` in pixels, once
+    /// it is loaded.
+    pub fn size(&self, url: &str) -> Option<(u32, u32)> {
+        match self.get(url)? {
+            ImageState::Loaded(texture) => {
+                let [width, height] = texture.size();
+                Some((width as u32, height as u32))
+            }
+            ImageState::Loading | ImageState::Broken => None,
+        }
+    }
+
+    /// Whether any image is still being fetched or decoded.
+    pub fn is_loading(&self) -> bool {
+        self.images
+            .values()
+            .any(|entry| matches!(entry.state, ImageState::Loading))
+    }
+}
+
+/// The memory an RGBA texture of `[width, height]` takes.
+fn texture_bytes([width, height]: [usize; 2]) -> usize {
+    width * height * 4
+}
+
+/// Decodes a PNG, JPEG, GIF or WebP image from `bytes`, whose format is
+/// told by its signature. Only the first frame of an animation is kept.
+///
+/// # Errors
+///
+/// Returns an error if the format is not one of those, or the image is
+/// corrupt.
+pub fn decode(bytes: &[u8]) -> Result<ColorImage, image::ImageError> {
+    let image = image::load_from_memory(bytes)?.to_rgba8();
+    let size = [image.width() as usize, image.height() as usize];
+    Ok(ColorImage::from_rgba_unmultiplied(size, image.as_raw()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    /// A 3 by 2 PNG image.
+    fn png() -> Vec<u8> {
+        let mut bytes = Vec::new();
+        image::RgbaImage::from_pixel(3, 2, image::Rgba([255, 0, 0, 255]))
+            .write_to(&mut Cursor::new(&mut bytes), image::ImageFormat::Png)
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_decode() {
+        let image = decode(&png()).unwrap();
+        assert_eq!(image.size, [3, 2]);
+        assert_eq!(image.pixels[0], egui::Color32::RED);
+        assert!(decode(b"not an image").is_err());
+    }
+
+    #[test]
+    fn test_image_cache() {
+        let ctx = egui::Context::default();
+        let fetch: FetchBytes = Arc::new(|url: &str| match url {
+            "https://example.com/a.png" => Ok(png()),
+            "https://example.com/text.html" => Ok(b"<!doctype html>".to_vec()),
+            _ => Err("not found".to_owned()),
+        });
+        let mut cache = ImageCache::default();
+        for url in ["a.png", "text.html", "missing.png", "a.png"] {
+            cache.load(&format!("https://example.com/{url}"), &fetch);
+        }
+        assert!(cache.is_loading());
+
+        let mut arrived = Vec::new();
+        let start = Instant::now();
+        while cache.is_loading() && start.elapsed() < Duration::from_secs(10) {
+            arrived.extend(cache.poll(&ctx));
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        arrived.sort();
+        assert_eq!(
+            arrived,
+            [
+                "https://example.com/a.png",
+                "https://example.com/missing.png",
+                "https://example.com/text.html",
+            ]
+        );
+        assert_eq!(cache.size("https://example.com/a.png"), Some((3, 2)));
+        assert!(matches!(
+            cache.get("https://example.com/text.html"),
+            Some(ImageState::Broken)
+        ));
+        assert!(cache.get("https://example.com/other.png").is_none());
+
+        cache.load("https://example.com/a.png", &fetch);
+        cache.load("https://example.com/missing.png", &fetch);
+        assert!(matches!(
+            cache.get("https://example.com/missing.png"),
+            Some(ImageState::Loading)
+        ));
+    }
+
+    #[test]
+    fn test_image_cache_drops_least_recently_used() {
+        let ctx = egui::Context::default();
+        let fetch: FetchBytes = Arc::new(|_: &str| Ok(png()));
+        let mut cache = ImageCache::with_max_bytes(2 * 3 * 2 * 4);
+        for url in ["a.png", "b.png", "c.png"] {
+            cache.load(&format!("https://example.com/{url}"), &fetch);
+        }
+
+        let start = Instant::now();
+        while cache.is_loading() && start.elapsed() < Duration::from_secs(10) {
+            cache.poll(&ctx);
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert!(cache.get("https://example.com/a.png").is_none());
+        assert_eq!(cache.size("https://example.com/b.png"), Some((3, 2)));
+        assert_eq!(cache.size("https://example.com/c.png"), Some((3, 2)));
+    }
+}
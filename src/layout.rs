@@ -0,0 +1,110 @@
+//! Layout: where each piece of a styled document goes on the page.
+//!
+//! The first step turns the document and its computed styles into a
+//! [`BoxTree`], after the visual formatting model of CSS 2.1
+//! (<https://www.w3.org/TR/CSS21/visuren.html>): each element generates
+//! boxes according to its `display`, and anonymous boxes are added where
+//! block-level and inline-level boxes are siblings.
+//!
+//! The second step, [`BoxTree::layout`], gives each box a position and a
+//! size for a viewport, as a [`LayoutTree`] of [`Fragment`]s, which is
+//! what painting works from. Blocks are stacked, and inline content is
+//! broken into lines, with text measured by egui's fonts so that it paints
+//! in the room it was given. Images are replaced elements, sized by their
+//! image once it is decoded and by their `width` and `height` attributes
+//! until then. Tables size their columns to fit the content of their
+//! cells, and list items get a marker. Floats go to one side of their
+//! container, and the lines beside them are shortened to flow around them.
+//! Sizes are kept between their `min-` and `max-` values, and a box whose
+//! `overflow` is not `visible` clips its content; see
+//! [`Fragment::clip_rect`]. Those with `auto` or `scroll` can scroll it
+//! too, with [`LayoutTree::scroll_by`].
+//!
+//! Positioned boxes are moved once the flow is laid out: relative ones by
+//! their offsets, and absolute and fixed ones, which take no room in the
+//! flow, into their containing block. [`LayoutTree::paint_order`] stacks
+//! them by `z-index`, with the fixed ones over the rest of the page, which
+//! scrolls under them. The page is laid out for the size of the viewport
+//! it is shown in; [`LayoutTree::content_size`] is how far it can be
+//! scrolled.
+//!
+//! Layout is incremental: boxes keep the fragment they were last laid out
+//! into, and [`BoxTree::update`] marks those whose style changed as dirty,
+//! so that a hover style or a resized window only lays out again the parts
+//! of the page they affect.
+
+mod block;
+mod box_tree;
+mod dump;
+mod float;
+mod fragment;
+mod incremental;
+mod inline;
+mod length;
+mod list;
+mod position;
+mod replaced;
+mod scroll;
+mod table;
+mod viewport;
+
+pub use box_tree::{BoxKind, BoxTree, LayoutBox, ReplacedContent, TableGrid};
+pub use fragment::{Edges, Fragment, LayoutTree};
+pub use inline::font_id;
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use egui::epaint::AlphaFromCoverage;
+    use egui::epaint::text::{FontDefinitions, Fonts};
+    use egui::{Color32, FontId, Rect, Vec2};
+
+    use super::{BoxTree, LayoutTree};
+    use crate::css::Stylesheet;
+    use crate::dom::Document;
+    use crate::style::{Cascade, Device, Origin};
+
+    pub(crate) fn fonts() -> Fonts {
+        Fonts::new(
+            2048,
+            AlphaFromCoverage::default(),
+            FontDefinitions::default(),
+        )
+    }
+
+    /// Lays out `html`, with the user agent style sheet and then `css`, for
+    /// an 800 by 600 viewport.
+    pub(crate) fn layout(html: &str, css: &str) -> (Document, LayoutTree) {
+        let document = Document::parse(html);
+        let mut cascade = Cascade::for_document(&document, Device::default());
+        cascade.add_stylesheet(Origin::Author, &Stylesheet::parse(css));
+        let styles = cascade.compute(&document);
+        let mut fonts = fonts();
+        let tree = BoxTree::build(&document, &styles).layout(
+            Vec2::new(800.0, 600.0),
+            &mut fonts.with_pixels_per_point(1.0),
+        );
+        (document, tree)
+    }
+
+    /// The border box of the element with the ID `id`, on its first line.
+    pub(super) fn rect(document: &Document, tree: &LayoutTree, id: &str) -> Rect {
+        let id = document.get_element_by_id(id).unwrap();
+        tree.fragments()
+            .find(|fragment| fragment.node == id)
+            .unwrap()
+            .rect
+    }
+
+    /// The width of `text` in the default font.
+    pub(super) fn text_width(text: &str) -> f32 {
+        fonts()
+            .with_pixels_per_point(1.0)
+            .layout_no_wrap(
+                text.to_owned(),
+                FontId::proportional(16.0),
+                Color32::PLACEHOLDER,
+            )
+            .size()
+            .x
+    }
+}
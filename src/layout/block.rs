@@ -0,0 +1,574 @@
+use std::collections::BTreeMap;
+
+use egui::epaint::text::FontsView;
+use egui::{Pos2, Rect, Vec2};
+
+use super::float::Float;
+use super::length::{border_width, is_auto, length};
+use super::position::{placeholder, positioning};
+use super::replaced::replaced_size;
+use super::{BoxKind, BoxTree, Edges, Fragment, LayoutBox, LayoutTree};
+
+/// The rectangle a box is laid out in: its left edge and width, and its
+/// height if that does not depend on the content.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) struct ContainingBlock {
+    pub(super) x: f32,
+    pub(super) width: f32,
+    pub(super) height: Option<f32>,
+}
+
+/// What laying out boxes needs besides the boxes: the viewport, which
+/// some lengths are relative to, and the fonts text is measured with.
+pub(super) struct LayoutContext<'f, 'v> {
+    pub(super) viewport: Vec2,
+    pub(super) fonts: &'f mut FontsView<'v>,
+
+    /// The floats of the block formatting context being laid out.
+    pub(super) floats: Vec<Float>,
+}
+
+impl BoxTree {
+    /// Lays out the boxes for a viewport of size `viewport`, which is the
+    /// initial containing block of the root element: blocks are as wide as
+    /// their container allows and are stacked from the top down, and
+    /// inline content is broken into lines, with text measured with
+    /// `fonts`. Only the boxes that are dirty, or get a different room than
+    /// last time, are laid out again; see [`BoxTree::update`].
+    pub fn layout(&self, viewport: Vec2, fonts: &mut FontsView<'_>) -> LayoutTree {
+        let mut context = LayoutContext {
+            viewport,
+            fonts,
+            floats: Vec::new(),
+        };
+        let root = self.root().map(|root| {
+            let containing = ContainingBlock {
+                x: 0.0,
+                width: viewport.x,
+                height: Some(viewport.y),
+            };
+            let margin_top = context.margin(root, "margin-top", containing.width);
+            let mut fragment = context.layout_block(root, containing, margin_top);
+            let initial = Rect::from_min_size(Pos2::ZERO, viewport);
+            context.layout_out_of_flow(root, &mut fragment, initial, true);
+            context.offset_relative(&mut fragment);
+            fragment
+        });
+        let mut tree = LayoutTree {
+            root,
+            content_size: viewport,
+            viewport,
+            scroll_offsets: BTreeMap::new(),
+        };
+        if let Some(root) = tree.root() {
+            tree.content_size = viewport.max(root.overflow_rect().max.to_vec2());
+        }
+        tree
+    }
+}
+
+impl LayoutContext<'_, '_> {
+    /// Lays out the block-level or atomic inline `layout_box` in
+    /// `containing`, with the top of its border box at `y`. A box that is
+    /// not dirty keeps the fragment it was last laid out into if it gets
+    /// the same room, unless floats are around, as its lines may have to
+    /// make room for them, or it has floats inside for the boxes after it.
+    pub(super) fn layout_block(
+        &mut self,
+        layout_box: &LayoutBox,
+        containing: ContainingBlock,
+        y: f32,
+    ) -> Fragment {
+        if !self.floats.is_empty() {
+            return self.layout_block_again(layout_box, containing, y);
+        }
+        if let Some(fragment) = layout_box.cached_fragment(containing, y, self.viewport) {
+            return fragment;
+        }
+        let fragment = self.layout_block_again(layout_box, containing, y);
+        if self.floats.is_empty() {
+            layout_box.cache_fragment(containing, y, self.viewport, &fragment);
+        }
+        fragment
+    }
+
+    fn layout_block_again(
+        &mut self,
+        layout_box: &LayoutBox,
+        containing: ContainingBlock,
+        y: f32,
+    ) -> Fragment {
+        let style = &layout_box.style;
+        let (border, padding) = self.border_and_padding(layout_box, containing.width);
+        let (horizontal_sizing, vertical_sizing) =
+            if style.keyword("box-sizing") == Some("border-box") {
+                (
+                    border.horizontal() + padding.horizontal(),
+                    border.vertical() + padding.vertical(),
+                )
+            } else {
+                (0.0, 0.0)
+            };
+        let width = self
+            .size(layout_box, "width", Some(containing.width))
+            .map(|width| (width - horizontal_sizing).max(0.0));
+        let height = self
+            .size(layout_box, "height", containing.height)
+            .map(|height| (height - vertical_sizing).max(0.0))
+            .map(|height| {
+                self.clamp_size(
+                    layout_box,
+                    "height",
+                    height,
+                    containing.height,
+                    vertical_sizing,
+                )
+            });
+        let (width, height) = match &layout_box.kind {
+            BoxKind::Replaced(content) => {
+                let size = replaced_size(content, width, height);
+                (Some(size.x), Some(size.y))
+            }
+            BoxKind::Table(grid) => {
+                let width = self.table_width(layout_box, grid, containing.width, width);
+                (Some(width), height)
+            }
+            // The table gives its cells their width.
+            BoxKind::TableCell => (None, height),
+            _ => (width, height),
+        };
+        let (mut width, mut margin_left, mut margin_right) =
+            self.horizontal(layout_box, containing.width, width, border, padding);
+        // Tables are as wide as their columns need, and cells as wide as
+        // their table makes them.
+        if !matches!(layout_box.kind, BoxKind::Table(_) | BoxKind::TableCell) {
+            let clamped = self.clamp_size(
+                layout_box,
+                "width",
+                width,
+                Some(containing.width),
+                horizontal_sizing,
+            );
+            if clamped != width {
+                (width, margin_left, margin_right) =
+                    self.horizontal(layout_box, containing.width, Some(clamped), border, padding);
+            }
+        }
+
+        let content = Pos2::new(
+            containing.x + margin_left + border.left + padding.left,
+            y + border.top + padding.top,
+        );
+        let inner = ContainingBlock {
+            x: content.x,
+            width,
+            height,
+        };
+
+        let (children, content_height) = self.layout_contents(layout_box, inner, content);
+        let height = height.unwrap_or_else(|| {
+            self.clamp_size(
+                layout_box,
+                "height",
+                content_height,
+                containing.height,
+                vertical_sizing,
+            )
+        });
+
+        let margin = Edges {
+            top: self.margin(layout_box, "margin-top", containing.width),
+            right: margin_right,
+            bottom: self.margin(layout_box, "margin-bottom", containing.width),
+            left: margin_left,
+        };
+        let content_rect = Rect::from_min_size(content, Vec2::new(width, height));
+        let mut fragment = Fragment {
+            kind: layout_box.kind.clone(),
+            node: layout_box.node,
+            style: layout_box.style.clone(),
+            rect: border.expand(padding.expand(content_rect)),
+            margin,
+            border,
+            padding,
+            children,
+        };
+        if positioning(&layout_box.kind, style).is_some() {
+            let padding_box = fragment.padding_rect();
+            self.layout_out_of_flow(layout_box, &mut fragment, padding_box, false);
+        }
+        fragment
+    }
+
+    /// Lays out the content of `layout_box` in `inner`, from `content`, the
+    /// top left corner of its content box. Returns the fragments of its
+    /// children and the height they take, which includes the floats inside
+    /// if it establishes a block formatting context.
+    fn layout_contents(
+        &mut self,
+        layout_box: &LayoutBox,
+        inner: ContainingBlock,
+        content: Pos2,
+    ) -> (Vec<Fragment>, f32) {
+        let layout = |context: &mut Self| {
+            if let BoxKind::Table(grid) = &layout_box.kind {
+                context.layout_table(layout_box, grid, inner, content.y)
+            } else if layout_box.children.iter().any(LayoutBox::is_block_level) {
+                context.layout_block_children(layout_box, inner, content.y)
+            } else {
+                context.layout_inline_content(layout_box, inner, content.y)
+            }
+        };
+        let (mut children, height) = if layout_box.establishes_formatting_context() {
+            let ((children, height), floats) = self.in_formatting_context(layout);
+            let floats = floats.map_or(0.0, |bottom| bottom - content.y);
+            (children, height.max(floats))
+        } else {
+            layout(self)
+        };
+        if let Some(marker) = layout_box.children.first()
+            && let BoxKind::Marker(text) = &marker.kind
+        {
+            let marker = self.layout_marker(text, marker.node, &marker.style, content, &children);
+            children.insert(0, marker);
+        }
+        (children, height)
+    }
+
+    /// Stacks the block-level children of `layout_box` from `top` down in
+    /// `containing`, with floats to their side, and below the floats they
+    /// clear. Returns their fragments and the height they take.
+    fn layout_block_children(
+        &mut self,
+        layout_box: &LayoutBox,
+        containing: ContainingBlock,
+        top: f32,
+    ) -> (Vec<Fragment>, f32) {
+        let mut children = Vec::new();
+        let mut bottom = top;
+        // The bottom margin of the previous child, which collapses with the
+        // top margin of the next one.
+        let mut previous_margin: Option<f32> = None;
+        for child in &layout_box.children {
+            if child.is_out_of_flow() {
+                children.push(placeholder(child, Pos2::new(containing.x, bottom)));
+                continue;
+            }
+            // The marker of a list item is laid out by the item.
+            if !child.is_block_level() {
+                continue;
+            }
+            if child.is_floating() {
+                let y = bottom + previous_margin.unwrap_or(0.0);
+                children.push(self.layout_float(child, containing, y));
+                continue;
+            }
+            let margin_top = self.margin(child, "margin-top", containing.width);
+            let gap = previous_margin.map_or(margin_top, |previous| collapse(previous, margin_top));
+            let mut y = bottom + gap;
+            if let Some(clearance) = self.clearance(&child.style) {
+                y = y.max(clearance);
+            }
+            let fragment = self.layout_block(child, containing, y);
+            bottom = fragment.rect.bottom();
+            previous_margin = Some(fragment.margin.bottom);
+            children.push(fragment);
+        }
+        (children, bottom + previous_margin.unwrap_or(0.0) - top)
+    }
+
+    /// The border and padding widths of `layout_box`, in a containing block
+    /// `containing_width` wide. Anonymous blocks have none.
+    pub(super) fn border_and_padding(
+        &self,
+        layout_box: &LayoutBox,
+        containing_width: f32,
+    ) -> (Edges, Edges) {
+        if layout_box.kind == BoxKind::AnonymousBlock {
+            return (Edges::default(), Edges::default());
+        }
+        let style = &layout_box.style;
+        let border = Edges {
+            top: border_width(style, "top", self.viewport),
+            right: border_width(style, "right", self.viewport),
+            bottom: border_width(style, "bottom", self.viewport),
+            left: border_width(style, "left", self.viewport),
+        };
+        let padding = |property| {
+            length(style, property, Some(containing_width), self.viewport)
+                .unwrap_or(0.0)
+                .max(0.0)
+        };
+        let padding = Edges {
+            top: padding("padding-top"),
+            right: padding("padding-right"),
+            bottom: padding("padding-bottom"),
+            left: padding("padding-left"),
+        };
+        (border, padding)
+    }
+
+    /// The declared `width` or `height` of `layout_box`, or `None` if it is
+    /// `auto`, or a percentage of an unknown `percent_base`.
+    pub(super) fn size(
+        &self,
+        layout_box: &LayoutBox,
+        property: &str,
+        percent_base: Option<f32>,
+    ) -> Option<f32> {
+        if layout_box.kind == BoxKind::AnonymousBlock {
+            return None;
+        }
+        length(&layout_box.style, property, percent_base, self.viewport)
+    }
+
+    /// `size`, the content width or height of `layout_box` for `property`,
+    /// kept between its `min-` and `max-` values for it, where percentages
+    /// are of `percent_base` and `sizing` is the border and padding they
+    /// include with `box-sizing: border-box`
+    /// (<https://www.w3.org/TR/CSS21/visudet.html#min-max-widths>). The
+    /// minimum wins over the maximum.
+    fn clamp_size(
+        &self,
+        layout_box: &LayoutBox,
+        property: &str,
+        size: f32,
+        percent_base: Option<f32>,
+        sizing: f32,
+    ) -> f32 {
+        let limit = |prefix| {
+            self.size(layout_box, &format!("{prefix}-{property}"), percent_base)
+                .map(|limit| (limit - sizing).max(0.0))
+        };
+        let max = limit("max").unwrap_or(f32::INFINITY);
+        size.min(max).max(limit("min").unwrap_or(0.0))
+    }
+
+    /// The content width and the left and right margins of a block-level
+    /// box in a containing block `containing_width` wide, given its
+    /// declared `width`, if not `auto`
+    /// (<https://www.w3.org/TR/CSS21/visudet.html#blockwidth>). An atomic
+    /// inline has no `auto` margins, and shrinks to fit its content if its
+    /// width is `auto`.
+    fn horizontal(
+        &mut self,
+        layout_box: &LayoutBox,
+        containing_width: f32,
+        width: Option<f32>,
+        border: Edges,
+        padding: Edges,
+    ) -> (f32, f32, f32) {
+        let style = &layout_box.style;
+        let has_margins = has_margins(layout_box);
+        let margin = |property| {
+            if !has_margins {
+                Some(0.0)
+            } else if is_auto(style, property) {
+                None
+            } else {
+                Some(length(style, property, Some(containing_width), self.viewport).unwrap_or(0.0))
+            }
+        };
+        let (left, right) = (margin("margin-left"), margin("margin-right"));
+        let fixed = border.horizontal() + padding.horizontal();
+        // An out-of-flow box shrinks to fit unless it is placed on both
+        // sides.
+        let shrink_to_fit = if layout_box.is_out_of_flow() {
+            is_auto(style, "left") || is_auto(style, "right")
+        } else {
+            layout_box.is_floating()
+                || !layout_box.is_block_level() && layout_box.kind != BoxKind::TableCell
+        };
+        if shrink_to_fit {
+            let (left, right) = (left.unwrap_or(0.0), right.unwrap_or(0.0));
+            let width = width.unwrap_or_else(|| {
+                let available = containing_width - fixed - left - right;
+                self.max_content_width(layout_box).min(available).max(0.0)
+            });
+            return (width, left, right);
+        }
+        let Some(width) = width else {
+            let (left, right) = (left.unwrap_or(0.0), right.unwrap_or(0.0));
+            return (
+                (containing_width - fixed - left - right).max(0.0),
+                left,
+                right,
+            );
+        };
+
+        let remaining = containing_width - fixed - width;
+        // Auto margins are zero when the box does not fit anyway.
+        let (left, right) = if remaining - left.unwrap_or(0.0) - right.unwrap_or(0.0) < 0.0 {
+            (left.or(Some(0.0)), right.or(Some(0.0)))
+        } else {
+            (left, right)
+        };
+        match (left, right) {
+            (None, None) => (width, remaining / 2.0, remaining / 2.0),
+            (None, Some(right)) => (width, remaining - right, right),
+            // With both margins set, the right one gives way, as in
+            // left-to-right text.
+            (Some(left), _) => (width, left, remaining - left),
+        }
+    }
+
+    /// The width of the content of `layout_box` if nothing wraps: the
+    /// widest of its block-level children with their margins, borders and
+    /// padding, or its longest line. For a replaced element, it is the
+    /// width it has of its own.
+    pub(super) fn max_content_width(&mut self, layout_box: &LayoutBox) -> f32 {
+        self.content_width(layout_box, false)
+    }
+
+    /// The narrowest the content of `layout_box` can be without
+    /// overflowing, where lines break wherever they can.
+    pub(super) fn min_content_width(&mut self, layout_box: &LayoutBox) -> f32 {
+        self.content_width(layout_box, true)
+    }
+
+    /// The min-content width of `layout_box` if `min`, and its max-content
+    /// width if not.
+    fn content_width(&mut self, layout_box: &LayoutBox, min: bool) -> f32 {
+        match &layout_box.kind {
+            BoxKind::Replaced(content) => {
+                let height = self.size(layout_box, "height", None);
+                return replaced_size(content, None, height).x;
+            }
+            BoxKind::Table(grid) => return self.table_content_width(layout_box, grid, min),
+            _ => {}
+        }
+        if !layout_box.children.iter().any(LayoutBox::is_block_level) {
+            return if min {
+                self.inline_min_content_width(layout_box)
+            } else {
+                self.inline_max_content_width(layout_box)
+            };
+        }
+        layout_box
+            .children
+            .iter()
+            .filter(|child| child.is_block_level())
+            .map(|child| {
+                let (border, padding) = self.border_and_padding(child, 0.0);
+                let margins = self.margin(child, "margin-left", 0.0)
+                    + self.margin(child, "margin-right", 0.0);
+                let content = match self.size(child, "width", None) {
+                    Some(width) => width,
+                    None => self.content_width(child, min),
+                };
+                let content = self.clamp_size(child, "width", content, None, 0.0);
+                content + border.horizontal() + padding.horizontal() + margins
+            })
+            .fold(0.0, f32::max)
+    }
+
+    /// A margin of `layout_box`, where `auto` is zero.
+    pub(super) fn margin(
+        &self,
+        layout_box: &LayoutBox,
+        property: &str,
+        containing_width: f32,
+    ) -> f32 {
+        if !has_margins(layout_box) {
+            return 0.0;
+        }
+        length(
+            &layout_box.style,
+            property,
+            Some(containing_width),
+            self.viewport,
+        )
+        .unwrap_or(0.0)
+    }
+}
+
+/// Whether margins apply to `layout_box`: they do not to anonymous blocks,
+/// nor to table cells, which are spaced by the `border-spacing` of their
+/// table instead.
+fn has_margins(layout_box: &LayoutBox) -> bool {
+    !matches!(
+        layout_box.kind,
+        BoxKind::AnonymousBlock | BoxKind::TableCell
+    )
+}
+
+/// The space between two adjoining margins, which collapse into one
+/// (<https://www.w3.org/TR/CSS21/box.html#collapsing-margins>).
+fn collapse(a: f32, b: f32) -> f32 {
+    a.max(b).max(0.0) + a.min(b).min(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::tests::{layout, rect};
+
+    #[test]
+    fn test_widths() {
+        let (document, tree) = layout(
+            "<div id=a><div id=b></div><div id=c></div><div id=d></div><div id=e></div></div>",
+            "body { margin: 0 } \
+             #a { margin: 0 10px; padding: 5px; border: 1px solid } \
+             #b { width: 50% } \
+             #c { width: 100px; margin: 0 auto } \
+             #d { width: 100px; margin-left: auto; padding: 0 10px; box-sizing: border-box } \
+             #e { margin-left: 20px; padding-right: 25%; border-right: thick solid }",
+        );
+        // 800 - 2 * 10 - 2 * 5 - 2 * 1 = 768 wide content.
+        assert_eq!(
+            rect(&document, &tree, "a"),
+            Rect::from_min_size(Pos2::new(10.0, 0.0), Vec2::new(780.0, 12.0))
+        );
+        assert_eq!(
+            rect(&document, &tree, "b"),
+            Rect::from_min_size(Pos2::new(16.0, 6.0), Vec2::new(384.0, 0.0))
+        );
+        assert_eq!(rect(&document, &tree, "c").min.x, 16.0 + 334.0);
+        let d = rect(&document, &tree, "d");
+        assert_eq!((d.min.x, d.width()), (16.0 + 668.0, 100.0));
+        let e = rect(&document, &tree, "e");
+        assert_eq!((e.min.x, e.width()), (36.0, 748.0));
+    }
+
+    #[test]
+    fn test_vertical_stacking() {
+        let (document, tree) = layout(
+            "<div id=a><p id=b></p><p id=c></p><p id=d></p></div><div id=e></div>",
+            "body { margin: 0 } \
+             #a { padding-top: 1px } \
+             p { height: 10px; margin: 20px 0 } \
+             #c { margin-top: 30px; margin-bottom: -5px } \
+             #d { height: 10px; padding: 2px } \
+             #e { height: 5em; margin-top: 8px }",
+        );
+        assert_eq!(rect(&document, &tree, "b").min.y, 21.0);
+        // The margins between #b and #c collapse to the larger one.
+        assert_eq!(rect(&document, &tree, "c").min.y, 61.0);
+        // 20px and -5px make 15px.
+        let d = rect(&document, &tree, "d");
+        assert_eq!((d.min.y, d.height()), (86.0, 14.0));
+        let a = rect(&document, &tree, "a");
+        assert_eq!(
+            a.height(),
+            1.0 + 20.0 + 10.0 + 30.0 + 10.0 + 15.0 + 14.0 + 20.0
+        );
+        let e = rect(&document, &tree, "e");
+        assert_eq!((e.min.y, e.height()), (a.max.y + 8.0, 80.0));
+    }
+
+    #[test]
+    fn test_min_max_sizes() {
+        let (document, tree) = layout(
+            "<div id=a></div><div id=b></div><div id=c><p id=d></p></div>",
+            "body { margin: 0 } p { margin: 0; height: 300px }              #a { max-width: 50%; min-height: 20px; margin: 0 auto }              #b { width: 100px; min-width: 200px; max-width: 150px; height: 10px; max-height: 5px }              #c { max-height: 100px; padding: 10px; box-sizing: border-box }",
+        );
+        let a = rect(&document, &tree, "a");
+        assert_eq!((a.min.x, a.width(), a.height()), (200.0, 400.0, 20.0));
+        // The minimum wins over the maximum.
+        let b = rect(&document, &tree, "b");
+        assert_eq!((b.width(), b.height()), (200.0, 5.0));
+        // The content of #c overflows it.
+        assert_eq!(rect(&document, &tree, "c").height(), 100.0);
+        assert_eq!(rect(&document, &tree, "d").height(), 300.0);
+    }
+}
@@ -0,0 +1,511 @@
+use std::cell::RefCell;
+use std::sync::Arc;
+
+use egui::Vec2;
+
+use super::float::float_side;
+use super::incremental::CachedLayout;
+use super::length::uses_viewport_units;
+use super::list::marker_box;
+use super::position::takes_out_of_flow;
+use crate::dom::{Document, Node, NodeId, TableCell, TableModel};
+use crate::style::{ComputedStyle, Display, Styles};
+
+/// The boxes the elements and text of a document generate, which layout
+/// gives a position and a size.
+#[derive(Debug, Clone, Default)]
+pub struct BoxTree {
+    pub(super) root: Option<LayoutBox>,
+}
+
+/// What a [`LayoutBox`] is.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BoxKind {
+    /// A block container of an element, such as a `<div>` or `<p>`.
+    Block,
+
+    /// The inline box of an element, such as an `<a>` or `<em>`, which
+    /// flows with the text around it.
+    Inline,
+
+    /// A block container that flows like an inline box, such as an
+    /// element with `display: inline-block`.
+    InlineBlock,
+
+    /// A block that wraps inline-level boxes which are siblings of
+    /// block-level ones, so that a block container holds either only
+    /// block-level boxes or only inline-level ones.
+    AnonymousBlock,
+
+    /// A run of text, as it is in the document: white space is collapsed
+    /// by inline layout.
+    Text(String),
+
+    /// A forced line break, from a `<br>`.
+    LineBreak,
+
+    /// A replaced element, such as an `<img>`, whose content is not laid
+    /// out but has a size of its own. It is block-level or inline-level
+    /// according to its `display`, and an inline one is atomic, like an
+    /// inline-block.
+    Replaced(ReplacedContent),
+
+    /// A `<table>`, whose children are its caption, if it has one, and
+    /// then its cells, which the table lays out on its grid. Like a
+    /// replaced element, it is block-level or inline-level according to
+    /// its `display`.
+    Table(TableGrid),
+
+    /// A cell of a table: a block container the table gives a width and a
+    /// height.
+    TableCell,
+
+    /// The marker of a list item, such as a bullet or its number, which
+    /// hangs outside of it, left of its first line. It is the first child
+    /// of the list item, and shares its style.
+    Marker(String),
+}
+
+/// Where the cells of a table go; see [`BoxKind::Table`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TableGrid {
+    pub rows: usize,
+    pub columns: usize,
+
+    /// Whether the first child of the table is its caption.
+    pub caption: bool,
+
+    /// The slots of the cells, in the order of their boxes.
+    pub cells: Vec<TableCell>,
+}
+
+/// What layout knows about the content of a replaced element.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ReplacedContent {
+    /// The size of the image, once it is decoded.
+    pub natural_size: Option<Vec2>,
+
+    /// The `width` and `height` attributes, which size the element before
+    /// its image arrives, like the CSS properties they lose to.
+    pub width: Option<f32>,
+    pub height: Option<f32>,
+}
+
+/// A box of the [`BoxTree`].
+#[derive(Debug, Clone)]
+pub struct LayoutBox {
+    pub kind: BoxKind,
+
+    /// The element or text node that generates the box. For an anonymous
+    /// block, it is the element of the block container it is in.
+    pub node: NodeId,
+
+    /// The style of the box. Text and anonymous blocks share the style of
+    /// their parent, of which they only use the inherited properties.
+    pub style: Arc<ComputedStyle>,
+    pub children: Vec<Self>,
+
+    /// Whether the layout of the box depends on the size of the viewport,
+    /// through a length such as `50vw` in its style or in that of a box
+    /// inside it.
+    pub(super) viewport_dependent: bool,
+
+    /// The fragment the box was last laid out into, which layout reuses
+    /// until the box is marked dirty; see [`BoxTree::update`].
+    pub(super) cache: RefCell<Option<CachedLayout>>,
+}
+
+impl LayoutBox {
+    pub(super) fn new(
+        kind: BoxKind,
+        node: NodeId,
+        style: Arc<ComputedStyle>,
+        children: Vec<Self>,
+    ) -> Self {
+        let viewport_dependent =
+            uses_viewport_units(&style) || children.iter().any(|child| child.viewport_dependent);
+        Self {
+            kind,
+            node,
+            style,
+            children,
+            viewport_dependent,
+            cache: RefCell::default(),
+        }
+    }
+
+    /// Whether the box is laid out in a block formatting context, among
+    /// the blocks of its container.
+    pub fn is_block_level(&self) -> bool {
+        if self.is_out_of_flow() {
+            return false;
+        }
+        if self.is_floating() {
+            return true;
+        }
+        match self.kind {
+            BoxKind::Block | BoxKind::AnonymousBlock => true,
+            BoxKind::Replaced(_) | BoxKind::Table(_) => !matches!(
+                self.style.display(),
+                Display::Inline | Display::InlineBlock | Display::InlineTable
+            ),
+            _ => false,
+        }
+    }
+}
+
+/// A box before it is known where it goes: block-level boxes are placed
+/// one after the other, and runs of inline-level boxes on lines.
+enum Level {
+    Block(LayoutBox),
+    Inline(LayoutBox),
+}
+
+impl BoxTree {
+    /// The box tree of `document`, whose elements have the computed styles
+    /// `styles`. Elements with `display: none`, and their descendants,
+    /// generate no boxes, and neither do comments and other nodes that are
+    /// not rendered. Images are sized by the natural sizes recorded on
+    /// `document`, so the tree must be built again when one arrives.
+    pub fn build(document: &Document, styles: &Styles) -> Self {
+        let root = document.document_element().and_then(|html| {
+            let style = Arc::new(styles.style_for(html)?.clone());
+            if style.display() == Display::None {
+                return None;
+            }
+            // The root element always generates a block.
+            let children = block_children(document, styles, html, &style);
+            Some(LayoutBox::new(BoxKind::Block, html, style, children))
+        });
+        Self { root }
+    }
+
+    /// The box of the root element, or `None` if it generates no boxes.
+    pub fn root(&self) -> Option<&LayoutBox> {
+        self.root.as_ref()
+    }
+}
+
+/// The boxes of the children of the block container `parent`, which has
+/// the style `style`: only block-level boxes, with runs of inline-level
+/// ones wrapped in anonymous blocks, if any child is block-level, and only
+/// inline-level boxes if not. The marker of a list item comes first.
+pub(super) fn block_children(
+    document: &Document,
+    styles: &Styles,
+    parent: NodeId,
+    style: &Arc<ComputedStyle>,
+) -> Vec<LayoutBox> {
+    let levels: Vec<Level> = document
+        .children(parent)
+        .flat_map(|child| boxes(document, styles, child, style))
+        .collect();
+    let mut children: Vec<LayoutBox> = marker_box(document, parent, style).into_iter().collect();
+    if levels.iter().all(|level| match level {
+        Level::Block(layout_box) => layout_box.is_out_of_flow(),
+        Level::Inline(_) => true,
+    }) {
+        children.extend(
+            levels
+                .into_iter()
+                .map(|(Level::Block(layout_box) | Level::Inline(layout_box))| layout_box),
+        );
+        return children;
+    }
+
+    let mut run = Vec::new();
+    for level in levels {
+        match level {
+            Level::Inline(layout_box) => run.push(layout_box),
+            Level::Block(layout_box) => {
+                wrap_run(&mut children, std::mem::take(&mut run), parent, style);
+                children.push(layout_box);
+            }
+        }
+    }
+    wrap_run(&mut children, run, parent, style);
+    children
+}
+
+/// Wraps the inline-level boxes `run` in an anonymous block, added to
+/// `children`, unless they are only white space that collapses away.
+fn wrap_run(
+    children: &mut Vec<LayoutBox>,
+    run: Vec<LayoutBox>,
+    parent: NodeId,
+    style: &Arc<ComputedStyle>,
+) {
+    if run.iter().all(is_collapsible_white_space) {
+        return;
+    }
+    children.push(LayoutBox::new(
+        BoxKind::AnonymousBlock,
+        parent,
+        Arc::clone(style),
+        run,
+    ));
+}
+
+fn is_collapsible_white_space(layout_box: &LayoutBox) -> bool {
+    let BoxKind::Text(text) = &layout_box.kind else {
+        return false;
+    };
+    let preserved = matches!(
+        layout_box.style.keyword("white-space"),
+        Some("pre" | "pre-wrap" | "break-spaces")
+    );
+    !preserved && text.chars().all(|c| c.is_ascii_whitespace())
+}
+
+/// The boxes the node `id` generates, whose parent element has the style
+/// `parent_style`.
+fn boxes(
+    document: &Document,
+    styles: &Styles,
+    id: NodeId,
+    parent_style: &Arc<ComputedStyle>,
+) -> Vec<Level> {
+    let element = match document.node(id) {
+        Node::Text(text) => {
+            return vec![Level::Inline(LayoutBox::new(
+                BoxKind::Text(text.clone()),
+                id,
+                Arc::clone(parent_style),
+                Vec::new(),
+            ))];
+        }
+        Node::Element(element) => element,
+        _ => return Vec::new(),
+    };
+    let Some(style) = styles.style_for(id) else {
+        return Vec::new();
+    };
+    let style = Arc::new(style.clone());
+    let container = |kind| {
+        let children = block_children(document, styles, id, &style);
+        LayoutBox::new(kind, id, Arc::clone(&style), children)
+    };
+
+    if element.is("img") && style.display() != Display::None {
+        let layout_box = LayoutBox::new(
+            BoxKind::Replaced(replaced_content(document, id)),
+            id,
+            Arc::clone(&style),
+            Vec::new(),
+        );
+        return vec![level(layout_box)];
+    }
+    if element.is("table")
+        && matches!(style.display(), Display::Table | Display::InlineTable)
+        && let Some(model) = document.table(id)
+    {
+        let layout_box = table_box(document, styles, &model, style);
+        return vec![level(layout_box)];
+    }
+    match style.display() {
+        Display::None | Display::TableColumn | Display::TableColumnGroup => Vec::new(),
+        // Out-of-flow and floating boxes are blockified.
+        _ if takes_out_of_flow(&style) || float_side(&style).is_some() => {
+            vec![Level::Block(container(BoxKind::Block))]
+        }
+        Display::Block
+        | Display::ListItem
+        | Display::Table
+        | Display::TableRowGroup
+        | Display::TableHeaderGroup
+        | Display::TableFooterGroup
+        | Display::TableRow
+        | Display::TableCell
+        | Display::TableCaption => vec![Level::Block(container(BoxKind::Block))],
+        Display::InlineBlock | Display::InlineTable => {
+            vec![Level::Inline(container(BoxKind::InlineBlock))]
+        }
+        Display::Inline if element.is("br") => vec![Level::Inline(LayoutBox::new(
+            BoxKind::LineBreak,
+            id,
+            style,
+            Vec::new(),
+        ))],
+        Display::Inline => inline_boxes(document, styles, id, &style),
+    }
+}
+
+/// Where `layout_box` goes among its siblings. Out-of-flow boxes are
+/// block-level ones that do not split inline content, as they are not in
+/// its flow.
+fn level(layout_box: LayoutBox) -> Level {
+    if layout_box.is_block_level() || layout_box.is_out_of_flow() {
+        Level::Block(layout_box)
+    } else {
+        Level::Inline(layout_box)
+    }
+}
+
+/// The box of the table `model`, with the style `style`. Its caption and
+/// cells are block containers, and the other parts of the table, such as
+/// rows, generate no boxes of their own.
+fn table_box(
+    document: &Document,
+    styles: &Styles,
+    model: &TableModel,
+    style: Arc<ComputedStyle>,
+) -> LayoutBox {
+    let mut grid = TableGrid {
+        rows: model.row_count(),
+        columns: model.column_count(),
+        ..TableGrid::default()
+    };
+    let mut children = Vec::new();
+    let mut child = |kind, id| {
+        let style = Arc::new(styles.style_for(id)?.clone());
+        if style.display() == Display::None {
+            return None;
+        }
+        let grandchildren = block_children(document, styles, id, &style);
+        children.push(LayoutBox::new(kind, id, style, grandchildren));
+        Some(())
+    };
+    if let Some(caption) = model.caption {
+        grid.caption = child(BoxKind::Block, caption).is_some();
+    }
+    for cell in &model.cells {
+        if child(BoxKind::TableCell, cell.element).is_some() {
+            grid.cells.push(cell.clone());
+        }
+    }
+    LayoutBox::new(BoxKind::Table(grid), model.element, style, children)
+}
+
+/// The content of the `<img>` element `id`.
+pub(super) fn replaced_content(document: &Document, id: NodeId) -> ReplacedContent {
+    ReplacedContent {
+        natural_size: document
+            .natural_size(id)
+            .map(|(width, height)| Vec2::new(width as f32, height as f32)),
+        width: document.dimension_attribute(id, "width"),
+        height: document.dimension_attribute(id, "height"),
+    }
+}
+
+/// The boxes of the inline element `id`, with the style `style`. An inline
+/// box that holds a block-level box is split around it, so the block ends
+/// up between two parts of the inline box
+/// (<https://www.w3.org/TR/CSS21/visuren.html#anonymous-block-level>).
+fn inline_boxes(
+    document: &Document,
+    styles: &Styles,
+    id: NodeId,
+    style: &Arc<ComputedStyle>,
+) -> Vec<Level> {
+    let part = |children| LayoutBox::new(BoxKind::Inline, id, Arc::clone(style), children);
+    let mut levels = Vec::new();
+    let mut children = Vec::new();
+    for level in document
+        .children(id)
+        .flat_map(|child| boxes(document, styles, child, style))
+    {
+        match level {
+            Level::Inline(layout_box) => children.push(layout_box),
+            Level::Block(layout_box) if layout_box.is_out_of_flow() => children.push(layout_box),
+            Level::Block(layout_box) => {
+                levels.push(Level::Inline(part(std::mem::take(&mut children))));
+                levels.push(Level::Block(layout_box));
+            }
+        }
+    }
+    levels.push(Level::Inline(part(children)));
+    levels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::style::{Cascade, Device};
+
+    /// The tree as `kind(children)` text, with the tag name for element
+    /// boxes, `~` before inline-level ones, `anon` for anonymous blocks and
+    /// quoted text.
+    fn outline(document: &Document, layout_box: &LayoutBox) -> String {
+        let name = match &layout_box.kind {
+            BoxKind::Text(text) => return format!("{text:?}"),
+            BoxKind::LineBreak => return "br".to_owned(),
+            BoxKind::Replaced(_) => return "img".to_owned(),
+            BoxKind::Marker(text) => return format!("marker{text:?}"),
+            BoxKind::AnonymousBlock => "anon".to_owned(),
+            BoxKind::Block
+            | BoxKind::Inline
+            | BoxKind::InlineBlock
+            | BoxKind::Table(_)
+            | BoxKind::TableCell => {
+                let element = document.element(layout_box.node).unwrap();
+                let inline = !layout_box.is_block_level() && layout_box.kind != BoxKind::TableCell;
+                let level = if inline { "~" } else { "" };
+                format!("{level}{}", element.name)
+            }
+        };
+        let children: Vec<String> = layout_box
+            .children
+            .iter()
+            .map(|child| outline(document, child))
+            .collect();
+        format!("{name}({})", children.join(" "))
+    }
+
+    fn build(html: &str) -> String {
+        let document = Document::parse(html);
+        let styles = Cascade::for_document(&document, Device::default()).compute(&document);
+        let tree = BoxTree::build(&document, &styles);
+        outline(&document, tree.root().unwrap())
+    }
+
+    #[test]
+    fn test_anonymous_blocks() {
+        assert_eq!(
+            build("<p>a <em>b</em></p>\n<div>c<p>d</p> </div><script>x</script>"),
+            "html(body(p(\"a \" ~em(\"b\")) div(anon(\"c\") p(\"d\"))))"
+        );
+        assert_eq!(
+            build(
+                "<span>a<br><b style=display:none>b</b><i style=display:inline-block>c</i></span>"
+            ),
+            "html(body(~span(\"a\" br ~i(\"c\"))))"
+        );
+        assert_eq!(
+            build("<p>a<img><img style=display:block></p>"),
+            "html(body(p(anon(\"a\" img) img)))"
+        );
+    }
+
+    #[test]
+    fn test_tables() {
+        assert_eq!(
+            build(
+                "<table><caption>c</caption><tr><td>a<td style=display:none>b\
+                 <tr><th>d</table>"
+            ),
+            "html(body(table(caption(\"c\") td(\"a\") th(\"d\"))))"
+        );
+        let document = Document::parse("<table><tr><td colspan=2>a<tr><td>b<td>c</table>");
+        let styles = Cascade::for_document(&document, Device::default()).compute(&document);
+        let tree = BoxTree::build(&document, &styles);
+        let table = &tree.root().unwrap().children[0].children[0];
+        let BoxKind::Table(grid) = &table.kind else {
+            panic!("not a table: {table:?}");
+        };
+        assert_eq!((grid.rows, grid.columns, grid.caption), (2, 2, false));
+        let slots: Vec<_> = grid
+            .cells
+            .iter()
+            .map(|cell| (cell.row, cell.column, cell.column_span))
+            .collect();
+        assert_eq!(slots, [(0, 0, 2), (1, 0, 1), (1, 1, 1)]);
+    }
+
+    #[test]
+    fn test_block_inside_inline() {
+        assert_eq!(
+            build("<a href=x>a<div>b</div>c</a>"),
+            "html(body(anon(~a(\"a\")) div(\"b\") anon(~a(\"c\"))))"
+        );
+    }
+}
@@ -0,0 +1,154 @@
+use std::fmt::Write as _;
+
+use super::{BoxKind, Edges, Fragment, LayoutTree};
+use crate::css::serialize;
+use crate::dom::{Document, Node};
+use crate::style::ComputedStyle;
+
+/// The properties the dump shows for element boxes, when they are not at
+/// their initial value: those that decide how a box is laid out.
+const DUMPED_PROPERTIES: [&str; 18] = [
+    "display",
+    "position",
+    "float",
+    "clear",
+    "width",
+    "height",
+    "min-width",
+    "max-width",
+    "min-height",
+    "max-height",
+    "box-sizing",
+    "font-size",
+    "line-height",
+    "white-space",
+    "text-align",
+    "vertical-align",
+    "border-spacing",
+    "overflow",
+];
+
+impl LayoutTree {
+    /// The fragments as indented text, one per line with its children
+    /// under it: what kind of box it is, the node of `document` it is for,
+    /// its border box, the margin, border and padding it has, and the
+    /// layout properties of its style that are not at their initial value,
+    /// such as
+    ///
+    /// ```text
+    /// Block <p> (8, 8) 784x18 margin=(16, 0, 16, 0) [display: block; font-size: 16.0px]
+    ///   Text "Hello" (8, 8) 36x18
+    /// ```
+    pub fn dump(&self, document: &Document) -> String {
+        let mut out = String::new();
+        if let Some(root) = self.root() {
+            dump_fragment(document, root, 0, &mut out);
+        }
+        out
+    }
+}
+
+fn dump_fragment(document: &Document, fragment: &Fragment, depth: usize, out: &mut String) {
+    let name = match document.node(fragment.node) {
+        Node::Element(element) => format!("<{}>", element.name),
+        _ => String::new(),
+    };
+    let (kind, name) = match &fragment.kind {
+        BoxKind::Block => ("Block", name),
+        BoxKind::Inline => ("Inline", name),
+        BoxKind::InlineBlock => ("InlineBlock", name),
+        BoxKind::AnonymousBlock => ("AnonymousBlock", format!("in {name}")),
+        BoxKind::Text(text) => ("Text", format!("{text:?}")),
+        BoxKind::LineBreak => ("LineBreak", name),
+        BoxKind::Replaced(_) => ("Replaced", name),
+        BoxKind::Table(_) => ("Table", name),
+        BoxKind::TableCell => ("TableCell", name),
+        BoxKind::Marker(text) => ("Marker", format!("{text:?}")),
+    };
+    let rect = fragment.rect;
+    write!(
+        out,
+        "{}{kind} {name} ({}, {}) {}x{}",
+        "  ".repeat(depth),
+        rect.min.x,
+        rect.min.y,
+        rect.width(),
+        rect.height()
+    )
+    .ok();
+    for (label, edges) in [
+        ("margin", fragment.margin),
+        ("border", fragment.border),
+        ("padding", fragment.padding),
+    ] {
+        if edges != Edges::default() {
+            let Edges {
+                top,
+                right,
+                bottom,
+                left,
+            } = edges;
+            write!(out, " {label}=({top}, {right}, {bottom}, {left})").ok();
+        }
+    }
+    // Text, anonymous blocks and markers only borrow the style of their parent.
+    if matches!(
+        fragment.kind,
+        BoxKind::Block
+            | BoxKind::Inline
+            | BoxKind::InlineBlock
+            | BoxKind::Replaced(_)
+            | BoxKind::Table(_)
+            | BoxKind::TableCell
+    ) {
+        let styles = applied_styles(&fragment.style);
+        if !styles.is_empty() {
+            write!(out, " [{}]", styles.join("; ")).ok();
+        }
+    }
+    out.push('\n');
+    for child in &fragment.children {
+        dump_fragment(document, child, depth + 1, out);
+    }
+}
+
+/// The [`DUMPED_PROPERTIES`] of `style` that are not at their initial
+/// value, as `property: value`.
+fn applied_styles(style: &ComputedStyle) -> Vec<String> {
+    let initial = ComputedStyle::default();
+    DUMPED_PROPERTIES
+        .iter()
+        .filter_map(|&property| {
+            let value = style.value(property)?;
+            (initial.value(property) != Some(value))
+                .then(|| format!("{property}: {}", serialize(value)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::layout::tests::layout;
+
+    #[test]
+    fn test_dump() {
+        let (document, tree) = layout(
+            "<p>a<br><img width=10 height=5></p>",
+            "html { line-height: 20px } body { margin: 0 } \
+             p { margin: 0 0 4px; padding: 1px; width: 100px }",
+        );
+        let dump = tree.dump(&document);
+        let lines: Vec<&str> = dump.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(
+            lines.get(2).copied(),
+            Some(
+                "    Block <p> (0, 0) 102x42 margin=(0, 698, 4, 0) padding=(1, 1, 1, 1) \
+                 [display: block; width: 100px; font-size: 16.0px; line-height: 20.0px]"
+            )
+        );
+        assert!(lines.get(3).unwrap().starts_with("      Text \"a\" (1, "));
+        let image = lines.get(4).unwrap();
+        assert!(image.starts_with("      Replaced <img> (1, ") && image.contains(") 10x5 ["));
+    }
+}
@@ -0,0 +1,207 @@
+use egui::{Pos2, Rect};
+
+use super::block::{ContainingBlock, LayoutContext};
+use super::{BoxKind, Fragment, LayoutBox};
+use crate::style::ComputedStyle;
+
+/// The side a box floats to, or a `clear` keeps clear of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Side {
+    Left,
+    Right,
+}
+
+/// A box floated to one side of the block formatting context it is in,
+/// with its margin box: the lines next to it are shortened to make room
+/// for it.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Float {
+    side: Side,
+    rect: Rect,
+}
+
+/// The side `style` floats a box to, if its `float` is not `none`.
+pub(super) fn float_side(style: &ComputedStyle) -> Option<Side> {
+    match style.keyword("float")? {
+        "left" => Some(Side::Left),
+        "right" => Some(Side::Right),
+        _ => None,
+    }
+}
+
+impl LayoutBox {
+    /// Whether the box floats: it is taken out of the flow to one side of
+    /// its container, and the content after it flows around it. Out-of-flow
+    /// boxes do not float.
+    pub fn is_floating(&self) -> bool {
+        matches!(
+            self.kind,
+            BoxKind::Block | BoxKind::InlineBlock | BoxKind::Replaced(_) | BoxKind::Table(_)
+        ) && float_side(&self.style).is_some()
+            && !self.is_out_of_flow()
+    }
+
+    /// Whether the box lays out its content in a block formatting context
+    /// of its own, so that floats inside it stay in it and it grows to hold
+    /// them (<https://www.w3.org/TR/CSS21/visuren.html#block-formatting>).
+    pub(super) fn establishes_formatting_context(&self) -> bool {
+        matches!(
+            self.kind,
+            BoxKind::InlineBlock | BoxKind::Table(_) | BoxKind::TableCell
+        ) || self.is_floating()
+            || self.is_out_of_flow()
+            || self
+                .style
+                .keyword("overflow")
+                .is_some_and(|overflow| overflow != "visible")
+    }
+}
+
+impl LayoutContext<'_, '_> {
+    /// Lays out the floating box `layout_box` in `containing`, as high as
+    /// it can go from `y` on and as far to its side as the floats already
+    /// there let it (<https://www.w3.org/TR/CSS21/visuren.html#float-position>):
+    /// it goes no higher than the floats before it, and below those it is
+    /// cleared of or that leave no room for it. An `auto` width shrinks to
+    /// fit its content.
+    pub(super) fn layout_float(
+        &mut self,
+        layout_box: &LayoutBox,
+        containing: ContainingBlock,
+        y: f32,
+    ) -> Fragment {
+        let side = float_side(&layout_box.style).unwrap_or(Side::Left);
+        let margin_top = self.margin(layout_box, "margin-top", containing.width);
+        let mut fragment = self.layout_block(layout_box, containing, margin_top);
+        let size = fragment.margin_rect().size();
+
+        let mut top = self
+            .floats
+            .iter()
+            .map(|float| float.rect.top())
+            .fold(y, f32::max);
+        if let Some(clearance) = self.clearance(&layout_box.style) {
+            top = top.max(clearance);
+        }
+        let (x, width) = loop {
+            let (x, width) = self.line_space(containing, top, size.y);
+            match self.next_float_bottom(top, size.y) {
+                Some(bottom) if width < size.x => top = bottom,
+                _ => break (x, width),
+            }
+        };
+        let left = match side {
+            Side::Left => x,
+            Side::Right => x + width - size.x,
+        };
+        fragment.translate(Pos2::new(left, top) - fragment.margin_rect().min);
+        self.floats.push(Float {
+            side,
+            rect: fragment.margin_rect(),
+        });
+        fragment
+    }
+
+    /// Where the `clear` of `style` puts the top border edge of a box: below
+    /// the floats on the sides it clears, if there are any.
+    pub(super) fn clearance(&self, style: &ComputedStyle) -> Option<f32> {
+        let sides: &[Side] = match style.keyword("clear")? {
+            "left" => &[Side::Left],
+            "right" => &[Side::Right],
+            "both" => &[Side::Left, Side::Right],
+            _ => return None,
+        };
+        self.floats
+            .iter()
+            .filter(|float| sides.contains(&float.side))
+            .map(|float| float.rect.bottom())
+            .reduce(f32::max)
+    }
+
+    /// The left end and the width of the room the floats leave in
+    /// `containing` for a line, or a float, `height` tall at `top`.
+    pub(super) fn line_space(
+        &self,
+        containing: ContainingBlock,
+        top: f32,
+        height: f32,
+    ) -> (f32, f32) {
+        let mut left = containing.x;
+        let mut right = containing.x + containing.width;
+        for float in self.beside(top, height) {
+            match float.side {
+                Side::Left => left = left.max(float.rect.right()),
+                Side::Right => right = right.min(float.rect.left()),
+            }
+        }
+        (left, (right - left).max(0.0))
+    }
+
+    /// The nearest bottom of the floats beside something `height` tall at
+    /// `top`: where it can go down to for more room.
+    pub(super) fn next_float_bottom(&self, top: f32, height: f32) -> Option<f32> {
+        self.beside(top, height)
+            .map(|float| float.rect.bottom())
+            .reduce(f32::min)
+    }
+
+    /// Lays out the content of a box that establishes a block formatting
+    /// context with `layout`, in which the floats outside do not count.
+    /// Returns what `layout` does, and the bottom of the floats inside.
+    pub(super) fn in_formatting_context<T>(
+        &mut self,
+        layout: impl FnOnce(&mut Self) -> T,
+    ) -> (T, Option<f32>) {
+        let outside = std::mem::take(&mut self.floats);
+        let laid_out = layout(self);
+        let inside = std::mem::replace(&mut self.floats, outside);
+        let bottom = inside
+            .iter()
+            .map(|float| float.rect.bottom())
+            .reduce(f32::max);
+        (laid_out, bottom)
+    }
+
+    fn beside(&self, top: f32, height: f32) -> impl Iterator<Item = &Float> + '_ {
+        // Even something with no height is beside the floats at its top.
+        let bottom = top + height.max(1.0);
+        self.floats
+            .iter()
+            .filter(move |float| float.rect.top() < bottom && float.rect.bottom() > top)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::layout::BoxKind;
+    use crate::layout::tests::{layout, rect, text_width};
+
+    #[test]
+    fn test_floats() {
+        let (document, tree) = layout(
+            "<div id=a>one two three four</div><p id=p>five six seven eight</p>\
+             <div id=b>right</div><div id=c>cleared</div>",
+            "body { margin: 0; width: 200px } p { margin: 0 } \
+             #a { float: left; width: 60px; height: 50px } \
+             #b { float: right; margin-left: 5px } \
+             #c { clear: both }",
+        );
+        let [a, p, b, c] = ["a", "p", "b", "c"].map(|id| rect(&document, &tree, id));
+        assert_eq!((a.min.x, a.min.y, a.width()), (0.0, 0.0, 60.0));
+        // The paragraph is as wide as the body, but its lines start after
+        // the float.
+        assert_eq!((p.min.x, p.width()), (0.0, 200.0));
+        let first = tree
+            .fragments()
+            .find(|fragment| matches!(&fragment.kind, BoxKind::Text(text) if text.starts_with("five")))
+            .unwrap()
+            .rect;
+        assert_eq!(first.min.x, 60.0);
+        // The right float shrinks to fit and sits below the paragraph, and
+        // `clear` puts the last div below both floats.
+        assert_eq!(b.max.x, 200.0);
+        assert_eq!(b.width(), text_width("right"));
+        assert_eq!(b.min.y, p.max.y);
+        assert_eq!(c.min.y, a.max.y.max(b.max.y));
+    }
+}
@@ -0,0 +1,229 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use egui::{Rect, Vec2};
+
+use super::BoxKind;
+use super::length::length;
+use super::position::positioning;
+use crate::dom::NodeId;
+use crate::style::ComputedStyle;
+
+/// The widths of the four sides of a margin, border or padding, in
+/// pixels.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Edges {
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub left: f32,
+}
+
+impl Edges {
+    /// The left and right widths together.
+    pub fn horizontal(&self) -> f32 {
+        self.left + self.right
+    }
+
+    /// The top and bottom widths together.
+    pub fn vertical(&self) -> f32 {
+        self.top + self.bottom
+    }
+
+    /// `rect` grown by the edges.
+    pub fn expand(&self, rect: Rect) -> Rect {
+        Rect::from_min_max(
+            rect.min - Vec2::new(self.left, self.top),
+            rect.max + Vec2::new(self.right, self.bottom),
+        )
+    }
+
+    /// `rect` shrunk by the edges.
+    pub fn shrink(&self, rect: Rect) -> Rect {
+        Rect::from_min_max(
+            rect.min + Vec2::new(self.left, self.top),
+            rect.max - Vec2::new(self.right, self.bottom),
+        )
+    }
+}
+
+/// A laid out box: where a [`LayoutBox`](super::LayoutBox) ended up, and
+/// how big it is.
+#[derive(Debug, Clone)]
+pub struct Fragment {
+    pub kind: BoxKind,
+    pub node: NodeId,
+    pub style: Arc<ComputedStyle>,
+
+    /// The border box, in pixels from the top left corner of the page.
+    pub rect: Rect,
+    pub margin: Edges,
+    pub border: Edges,
+    pub padding: Edges,
+    pub children: Vec<Self>,
+}
+
+impl Fragment {
+    /// The box inside the padding, where the children go.
+    pub fn content_rect(&self) -> Rect {
+        self.padding.shrink(self.padding_rect())
+    }
+
+    /// The box inside the border.
+    pub fn padding_rect(&self) -> Rect {
+        self.border.shrink(self.rect)
+    }
+
+    /// The box with the margin around it.
+    pub fn margin_rect(&self) -> Rect {
+        self.margin.expand(self.rect)
+    }
+
+    /// Whether the `visibility` of the fragment lets it be seen, and hit.
+    pub fn is_visible(&self) -> bool {
+        !matches!(
+            self.style.keyword("visibility"),
+            Some("hidden" | "collapse")
+        )
+    }
+
+    /// The box the fragment clips its content to, its padding box, if its
+    /// `overflow` is not `visible`
+    /// (<https://www.w3.org/TR/CSS21/visufx.html#overflow>).
+    pub fn clip_rect(&self) -> Option<Rect> {
+        (self.overflow() != "visible").then(|| self.padding_rect())
+    }
+
+    /// Whether the fragment is a scroll container, one with `overflow:
+    /// auto` or `scroll`, whose content can be scrolled within its padding
+    /// box.
+    pub fn is_scroll_container(&self) -> bool {
+        matches!(self.overflow(), "auto" | "scroll")
+    }
+
+    /// The area the fragment and what overflows from it cover: its margin
+    /// box, its border box where a negative margin leaves it out, and its
+    /// content that is not clipped. Fixed layers are in the viewport, not
+    /// on the page, so they are left out.
+    pub fn overflow_rect(&self) -> Rect {
+        let area = self.margin_rect().union(self.rect);
+        if self.clip_rect().is_some() {
+            area
+        } else {
+            self.children
+                .iter()
+                .filter(|child| positioning(&child.kind, &child.style) != Some("fixed"))
+                .fold(area, |area, child| area.union(child.overflow_rect()))
+        }
+    }
+
+    /// The radii of the corners of the border box, from the
+    /// `border-*-radius` properties, in pixels: top left, top right, bottom
+    /// right and bottom left. Corners are round rather than elliptical, so
+    /// percentages are of the shorter side of the box. Radii that would
+    /// overlap along a side are all scaled down together
+    /// (<https://www.w3.org/TR/css-backgrounds-3/#corner-overlap>).
+    pub fn border_radii(&self, viewport: Vec2) -> [f32; 4] {
+        let size = self.rect.size();
+        let radii = [
+            "border-top-left-radius",
+            "border-top-right-radius",
+            "border-bottom-right-radius",
+            "border-bottom-left-radius",
+        ]
+        .map(|property| {
+            length(&self.style, property, Some(size.min_elem()), viewport)
+                .unwrap_or(0.0)
+                .max(0.0)
+        });
+        let [top_left, top_right, bottom_right, bottom_left] = radii;
+        let scale = [
+            (size.x, top_left + top_right),
+            (size.y, top_right + bottom_right),
+            (size.x, bottom_right + bottom_left),
+            (size.y, bottom_left + top_left),
+        ]
+        .into_iter()
+        .filter(|&(_, sum)| sum > 0.0)
+        .fold(1.0_f32, |scale, (side, sum)| scale.min(side / sum));
+        radii.map(|radius| radius * scale)
+    }
+
+    /// The `overflow` of the fragment. It only applies to block
+    /// containers, and boxes that share the style of theirs have none.
+    fn overflow(&self) -> &str {
+        match self.kind {
+            BoxKind::Block | BoxKind::InlineBlock | BoxKind::Table(_) | BoxKind::TableCell => {
+                self.style.keyword("overflow").unwrap_or("visible")
+            }
+            _ => "visible",
+        }
+    }
+
+    /// Moves the fragment and its children by `delta`.
+    pub(super) fn translate(&mut self, delta: Vec2) {
+        self.rect = self.rect.translate(delta);
+        for child in &mut self.children {
+            child.translate(delta);
+        }
+    }
+}
+
+/// The fragments of a laid out [`BoxTree`](super::BoxTree).
+#[derive(Debug, Clone, Default)]
+pub struct LayoutTree {
+    pub(super) root: Option<Fragment>,
+    pub(super) content_size: Vec2,
+    pub(super) viewport: Vec2,
+    pub(super) scroll_offsets: BTreeMap<NodeId, Vec2>,
+}
+
+impl LayoutTree {
+    /// The fragment of the root element, or `None` if it generates no
+    /// boxes.
+    pub fn root(&self) -> Option<&Fragment> {
+        self.root.as_ref()
+    }
+
+    /// Every fragment, each before its children, which is the order they
+    /// are painted in.
+    pub fn fragments(&self) -> impl Iterator<Item = &Fragment> + '_ {
+        let mut stack: Vec<&Fragment> = self.root.iter().collect();
+        std::iter::from_fn(move || {
+            let fragment = stack.pop()?;
+            stack.extend(fragment.children.iter().rev());
+            Some(fragment)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::layout::tests::{layout, rect};
+
+    #[test]
+    fn test_overflow() {
+        let (document, tree) = layout(
+            "<div id=a><div id=b><p id=c></p></div></div><div id=d><p></p></div>",
+            "body { margin: 0 } p { margin: 0; width: 900px; height: 500px }              #a { height: 100px; overflow: auto; border: 2px solid }              #b { height: 50px; overflow: hidden }              #d { height: 10px }",
+        );
+        let fragment = |id| {
+            let node = document.get_element_by_id(id).unwrap();
+            tree.fragments()
+                .find(|fragment| fragment.node == node)
+                .unwrap()
+        };
+        let (a, b) = (fragment("a"), fragment("b"));
+        // #a scrolls what overflows it within its padding box.
+        assert!(a.is_scroll_container());
+        assert_eq!(a.clip_rect(), Some(a.padding_rect()));
+        assert_eq!(a.overflow_rect(), a.rect);
+        // #b clips its paragraph without scrolling it.
+        assert!(!b.is_scroll_container());
+        assert_eq!(b.clip_rect(), Some(rect(&document, &tree, "b")));
+        assert_eq!(fragment("c").clip_rect(), None);
+        // The paragraph overflowing #d makes the page wider and taller.
+        let p = fragment("d").children[0].rect;
+        assert_eq!(tree.content_size(), p.max.to_vec2());
+    }
+}
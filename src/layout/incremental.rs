@@ -0,0 +1,330 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use egui::Vec2;
+
+use super::block::ContainingBlock;
+use super::box_tree::{block_children, replaced_content};
+use super::float::float_side;
+use super::list::{has_marker, marker_box};
+use super::position::takes_out_of_flow;
+use super::{BoxKind, BoxTree, Fragment, LayoutBox};
+use crate::dom::{Document, NodeId};
+use crate::style::{ComputedStyle, Display, Styles};
+
+/// The fragment a box was laid out into, and what it was laid out for.
+#[derive(Debug, Clone)]
+pub(super) struct CachedLayout {
+    containing: ContainingBlock,
+    y: f32,
+    viewport: Vec2,
+    fragment: Fragment,
+}
+
+/// What [`BoxTree::update`] found out about a box.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Change {
+    Unchanged,
+
+    /// The box, or one inside it, must be laid out again.
+    Dirty,
+
+    /// The box must be built again, with the other children of its block
+    /// container, as its `display` changed, or it went in or out of the
+    /// flow or started or stopped floating.
+    Rebuild,
+}
+
+impl LayoutBox {
+    /// Whether the box has to be laid out again: it changed, or a box
+    /// inside it did, since it was last laid out.
+    pub fn is_dirty(&self) -> bool {
+        self.cache.borrow().is_none()
+    }
+
+    /// The fragment the box was last laid out into, moved to the top `y`
+    /// in `containing`, if it is not dirty and it was laid out for a
+    /// containing block of the same size. The size of the viewport must be
+    /// the same too if the box depends on it.
+    pub(super) fn cached_fragment(
+        &self,
+        containing: ContainingBlock,
+        y: f32,
+        viewport: Vec2,
+    ) -> Option<Fragment> {
+        let cache = self.cache.borrow();
+        let cached = cache.as_ref()?;
+        let fits = cached.containing.width == containing.width
+            && cached.containing.height == containing.height
+            && (!self.viewport_dependent || cached.viewport == viewport);
+        if !fits {
+            return None;
+        }
+        let mut fragment = cached.fragment.clone();
+        fragment.translate(Vec2::new(containing.x - cached.containing.x, y - cached.y));
+        Some(fragment)
+    }
+
+    /// Keeps `fragment`, which the box was just laid out into at the top
+    /// `y` in `containing`, for the next layout.
+    pub(super) fn cache_fragment(
+        &self,
+        containing: ContainingBlock,
+        y: f32,
+        viewport: Vec2,
+        fragment: &Fragment,
+    ) {
+        *self.cache.borrow_mut() = Some(CachedLayout {
+            containing,
+            y,
+            viewport,
+            fragment: fragment.clone(),
+        });
+    }
+}
+
+impl BoxTree {
+    /// Brings the boxes up to date with `styles`, computed again for
+    /// `document`, such as after a change of the element under the pointer
+    /// or of the window size, and with the images that arrived since.
+    ///
+    /// The boxes whose style or image changed, and the boxes they are in,
+    /// are marked dirty, and the next [`BoxTree::layout`] only lays those
+    /// out again: the others keep their fragments unless the room they are
+    /// given changes. Where an element starts or stops generating boxes,
+    /// as when a menu is shown on hover, the children of its block
+    /// container are built again. A change to the document tree itself
+    /// needs a new tree from [`BoxTree::build`].
+    ///
+    /// Returns whether any box is dirty.
+    pub fn update(&mut self, document: &Document, styles: &Styles) -> bool {
+        let change = match &mut self.root {
+            Some(root) => update_box(document, styles, root, None),
+            None => Change::Rebuild,
+        };
+        match change {
+            Change::Unchanged => false,
+            Change::Dirty => true,
+            Change::Rebuild => {
+                *self = Self::build(document, styles);
+                true
+            }
+        }
+    }
+}
+
+/// Updates `layout_box`, which is in a box with the style `parent_style`,
+/// and the boxes inside it.
+fn update_box(
+    document: &Document,
+    styles: &Styles,
+    layout_box: &mut LayoutBox,
+    parent_style: Option<&Arc<ComputedStyle>>,
+) -> Change {
+    let mut change = Change::Unchanged;
+    match &layout_box.kind {
+        // These share the style of the box they are in.
+        BoxKind::Text(_) | BoxKind::AnonymousBlock | BoxKind::Marker(_) => {
+            if let Some(parent_style) = parent_style
+                && !Arc::ptr_eq(parent_style, &layout_box.style)
+            {
+                layout_box.style = Arc::clone(parent_style);
+                change = Change::Dirty;
+            }
+        }
+        _ => {
+            let Some(style) = styles.style_for(layout_box.node) else {
+                return Change::Rebuild;
+            };
+            if *style != *layout_box.style {
+                if style.display() != layout_box.style.display()
+                    || takes_out_of_flow(style) != takes_out_of_flow(&layout_box.style)
+                    || float_side(style).is_some() != float_side(&layout_box.style).is_some()
+                {
+                    return Change::Rebuild;
+                }
+                layout_box.style = Arc::new(style.clone());
+                change = Change::Dirty;
+            }
+        }
+    }
+    match &mut layout_box.kind {
+        BoxKind::Replaced(content) => {
+            let updated = replaced_content(document, layout_box.node);
+            if *content != updated {
+                *content = updated;
+                change = Change::Dirty;
+            }
+        }
+        BoxKind::Marker(text) => {
+            // The `list-style-type` of the list item may have changed.
+            let Some(updated) = marker_box(document, layout_box.node, &layout_box.style) else {
+                return Change::Rebuild;
+            };
+            if updated.kind != BoxKind::Marker(text.clone()) {
+                layout_box.kind = updated.kind;
+                change = Change::Dirty;
+            }
+        }
+        _ => {}
+    }
+
+    let mut rebuild = false;
+    for child in &mut layout_box.children {
+        match update_box(document, styles, child, Some(&layout_box.style)) {
+            Change::Unchanged => {}
+            Change::Dirty => change = Change::Dirty,
+            Change::Rebuild => rebuild = true,
+        }
+    }
+    let container = matches!(
+        layout_box.kind,
+        BoxKind::Block | BoxKind::InlineBlock | BoxKind::TableCell
+    );
+    if container && !rebuild {
+        let marker = layout_box
+            .children
+            .first()
+            .is_some_and(|child| matches!(child.kind, BoxKind::Marker(_)));
+        rebuild =
+            has_new_child(document, styles, layout_box) || marker != has_marker(&layout_box.style);
+    }
+    if rebuild {
+        if !container {
+            return Change::Rebuild;
+        }
+        layout_box.children = block_children(document, styles, layout_box.node, &layout_box.style);
+        change = Change::Dirty;
+    }
+
+    if change == Change::Dirty {
+        *layout_box.cache.get_mut() = None;
+    }
+    change
+}
+
+/// Whether an element in the block container `container` generates boxes
+/// that are not there: one that generated none when the boxes were built,
+/// such as a menu that was `display: none`. The elements looked at are
+/// the children of the container, and those of the inline elements in it.
+fn has_new_child(document: &Document, styles: &Styles, container: &LayoutBox) -> bool {
+    fn collect(layout_box: &LayoutBox, nodes: &mut HashSet<NodeId>) {
+        for child in &layout_box.children {
+            nodes.insert(child.node);
+            if matches!(child.kind, BoxKind::Inline | BoxKind::AnonymousBlock) {
+                collect(child, nodes);
+            }
+        }
+    }
+    let mut nodes = HashSet::new();
+    collect(container, &mut nodes);
+
+    let mut parents = vec![container.node];
+    while let Some(parent) = parents.pop() {
+        for child in document.children(parent) {
+            let Some(style) = styles.style_for(child) else {
+                continue;
+            };
+            let display = style.display();
+            if matches!(
+                display,
+                Display::None | Display::TableColumn | Display::TableColumnGroup
+            ) {
+                continue;
+            }
+            if !nodes.contains(&child) {
+                return true;
+            }
+            if display == Display::Inline {
+                parents.push(child);
+            }
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use egui::Rect;
+
+    use super::*;
+    use crate::css::Stylesheet;
+    use crate::layout::tests::fonts;
+    use crate::style::{Cascade, Device, Origin};
+
+    fn find(layout_box: &LayoutBox, id: NodeId) -> Option<&LayoutBox> {
+        if layout_box.node == id && layout_box.kind != BoxKind::AnonymousBlock {
+            return Some(layout_box);
+        }
+        layout_box.children.iter().find_map(|child| find(child, id))
+    }
+
+    #[test]
+    fn test_hover_marks_only_its_ancestors_dirty() {
+        let mut document = Document::parse(
+            "<div id=a><p id=b>one</p></div><div id=c><p id=d>two</p>\
+             <ul id=menu><li>three</li></ul></div>",
+        );
+        let mut cascade = Cascade::for_document(&document, Device::default());
+        cascade.add_stylesheet(
+            Origin::Author,
+            &Stylesheet::parse(
+                "p:hover { padding: 10px } #menu { display: none } \
+                 #c:hover #menu { display: block }",
+            ),
+        );
+        let mut styles = cascade.compute(&document);
+        let mut boxes = BoxTree::build(&document, &styles);
+        let mut fonts = fonts();
+        let viewport = Vec2::new(800.0, 600.0);
+        let layout = boxes.layout(viewport, &mut fonts.with_pixels_per_point(1.0));
+        let [a, b, c, d, menu] =
+            ["a", "b", "c", "d", "menu"].map(|name| document.get_element_by_id(name).unwrap());
+        let rect = |tree: &crate::layout::LayoutTree, id| -> Rect {
+            tree.fragments()
+                .find(|fragment| fragment.node == id)
+                .unwrap()
+                .rect
+        };
+        let first = rect(&layout, d);
+        assert!(!boxes.update(&document, &styles));
+
+        let changed = document.set_hovered(Some(b));
+        cascade.restyle(&document, &mut styles, &changed);
+        assert!(boxes.update(&document, &styles));
+        let root = boxes.root().unwrap();
+        assert!(find(root, b).unwrap().is_dirty());
+        assert!(find(root, a).unwrap().is_dirty());
+        assert!(root.is_dirty());
+        assert!(!find(root, c).unwrap().is_dirty());
+
+        let layout = boxes.layout(viewport, &mut fonts.with_pixels_per_point(1.0));
+        assert_eq!(rect(&layout, b).height(), first.height() + 20.0);
+        assert_eq!(rect(&layout, d).min.y, first.min.y + 20.0);
+        assert!(!boxes.root().unwrap().is_dirty());
+
+        // The menu had no boxes, and now has some.
+        let changed = document.set_hovered(Some(c));
+        cascade.restyle(&document, &mut styles, &changed);
+        assert!(boxes.update(&document, &styles));
+        let layout = boxes.layout(viewport, &mut fonts.with_pixels_per_point(1.0));
+        assert!(rect(&layout, menu).min.y > rect(&layout, d).max.y);
+        assert_eq!(
+            layout.dump(&document),
+            BoxTree::build(&document, &styles)
+                .layout(viewport, &mut fonts.with_pixels_per_point(1.0))
+                .dump(&document)
+        );
+
+        // A narrower window lays out again what it makes narrower.
+        let narrow = Vec2::new(500.0, 600.0);
+        assert_eq!(
+            boxes
+                .layout(narrow, &mut fonts.with_pixels_per_point(1.0))
+                .dump(&document),
+            BoxTree::build(&document, &styles)
+                .layout(narrow, &mut fonts.with_pixels_per_point(1.0))
+                .dump(&document)
+        );
+    }
+}
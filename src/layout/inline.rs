@@ -0,0 +1,766 @@
+use egui::{Color32, FontFamily, FontId, Pos2, Rect, Vec2};
+
+use super::block::{ContainingBlock, LayoutContext};
+use super::position::placeholder;
+use super::{BoxKind, Edges, Fragment, LayoutBox};
+use crate::css::{ComponentValue, CssToken};
+use crate::style::ComputedStyle;
+
+/// The egui font the text of `style` is measured and drawn with: its
+/// `font-size`, in a monospace font if `font-family` asks for one.
+pub fn font_id(style: &ComputedStyle) -> FontId {
+    let monospace = style.value("font-family").is_some_and(|families| {
+        families.iter().any(|family| match family {
+            ComponentValue::Token(CssToken::Ident(name) | CssToken::String(name)) => {
+                let name = name.to_ascii_lowercase();
+                name.contains("mono") || name.contains("courier")
+            }
+            _ => false,
+        })
+    });
+    let family = if monospace {
+        FontFamily::Monospace
+    } else {
+        FontFamily::Proportional
+    };
+    FontId::new(style.font_size() as f32, family)
+}
+
+/// The margin, border and padding of an inline box.
+#[derive(Debug, Clone, Copy)]
+struct InlineEdges {
+    margin: Edges,
+    border: Edges,
+    padding: Edges,
+}
+
+/// A piece of inline content, in the order it flows in.
+enum Token<'b> {
+    /// A word, in the style of the text box it comes from.
+    Word {
+        layout_box: &'b LayoutBox,
+        text: String,
+        width: f32,
+    },
+
+    /// White space between words: one space where white space collapses,
+    /// and the spaces as they are where it is preserved. Lines can break
+    /// at it if it `wraps`.
+    Space {
+        layout_box: &'b LayoutBox,
+        text: String,
+        width: f32,
+        wraps: bool,
+    },
+
+    /// The start of an inline box.
+    Open {
+        layout_box: &'b LayoutBox,
+        edges: InlineEdges,
+    },
+
+    /// The end of an inline box.
+    Close {
+        edges: InlineEdges,
+    },
+
+    /// An inline-block, inline table or inline replaced element, already
+    /// laid out with its top left margin corner at the origin.
+    Atomic(Fragment),
+
+    /// An out-of-flow box, whose placeholder keeps its place on the line
+    /// but takes no room.
+    OutOfFlow(&'b LayoutBox),
+    Break,
+}
+
+impl Token<'_> {
+    fn width(&self) -> f32 {
+        match self {
+            Self::Word { width, .. } | Self::Space { width, .. } => *width,
+            Self::Open { edges, .. } => edges.margin.left + edges.border.left + edges.padding.left,
+            Self::Close { edges, .. } => {
+                edges.margin.right + edges.border.right + edges.padding.right
+            }
+            Self::Atomic(fragment) => fragment.margin_rect().width(),
+            Self::OutOfFlow(_) | Self::Break => 0.0,
+        }
+    }
+
+    fn is_content(&self) -> bool {
+        matches!(self, Self::Word { .. } | Self::Atomic(_))
+    }
+}
+
+/// How far a piece of a line reaches above and below the baseline.
+#[derive(Debug, Clone, Copy, Default)]
+struct Extent {
+    above: f32,
+    below: f32,
+}
+
+/// An inline box being built on a line.
+struct OpenBox<'b> {
+    layout_box: &'b LayoutBox,
+    edges: InlineEdges,
+    start: f32,
+
+    /// Whether the box starts on this line, rather than going on from the
+    /// line before.
+    starts_here: bool,
+    children: Vec<Fragment>,
+}
+
+impl LayoutContext<'_, '_> {
+    /// Lays out the inline-level children of `layout_box` on lines from
+    /// `top` down, breaking lines where the width of `containing` is used
+    /// up (<https://www.w3.org/TR/CSS21/visuren.html#inline-formatting>),
+    /// or the room the floats beside a line leave. White space is
+    /// collapsed, and lines are aligned by `text-align`.
+    /// Returns the fragments of the text, inline boxes and inline-blocks,
+    /// and the height of the lines.
+    pub(super) fn layout_inline_content(
+        &mut self,
+        layout_box: &LayoutBox,
+        containing: ContainingBlock,
+        top: f32,
+    ) -> (Vec<Fragment>, f32) {
+        let mut tokens = Vec::new();
+        self.tokenize(layout_box, containing.width, &mut tokens);
+        if !tokens
+            .iter()
+            .any(|token| token.is_content() || matches!(token, Token::Break))
+        {
+            return (Vec::new(), 0.0);
+        }
+
+        let strut = self.extent(&layout_box.style);
+        let mut fragments = Vec::new();
+        let mut y = top;
+        let mut open: Vec<(&LayoutBox, InlineEdges)> = Vec::new();
+        let mut rest = tokens.as_slice();
+        loop {
+            // Lines are shortened by the floats beside them, and go down
+            // past them when what starts the line does not fit.
+            let (start, available) = self.line_space(containing, y, strut.above + strut.below);
+            let before = rest;
+            let Some(line) = break_line(&mut rest, available) else {
+                break;
+            };
+            let width: f32 = line.iter().map(|token| token.width()).sum();
+            if width > available
+                && available < containing.width
+                && let Some(bottom) = self.next_float_bottom(y, strut.above + strut.below)
+            {
+                rest = before;
+                y = bottom;
+                continue;
+            }
+            let offset = match layout_box.style.keyword("text-align") {
+                Some("center") => (available - width) / 2.0,
+                Some("right" | "end") => available - width,
+                _ => 0.0,
+            };
+            let x = start + offset.max(0.0);
+
+            let mut extent = strut;
+            let mut include = |other: Extent| {
+                extent.above = extent.above.max(other.above);
+                extent.below = extent.below.max(other.below);
+            };
+            for &(open_box, _) in &open {
+                include(self.extent(&open_box.style));
+            }
+            for token in &line {
+                match token {
+                    Token::Word { layout_box, .. }
+                    | Token::Space { layout_box, .. }
+                    | Token::Open { layout_box, .. } => include(self.extent(&layout_box.style)),
+                    Token::Atomic(fragment) => include(Extent {
+                        above: fragment.margin_rect().height(),
+                        below: 0.0,
+                    }),
+                    Token::Close { .. } | Token::OutOfFlow(_) | Token::Break => {}
+                }
+            }
+
+            let baseline = y + extent.above;
+            fragments.extend(self.line_fragments(&line, &mut open, Pos2::new(x, y), baseline));
+            y = baseline + extent.below;
+        }
+        (fragments, y - top)
+    }
+
+    /// The fragments of the tokens `line`, placed from `start` on, the left
+    /// end of the top of the line, on the baseline `baseline`. `open` holds the inline boxes that go on from
+    /// the line before, and is left with those that go on to the next.
+    fn line_fragments<'b>(
+        &mut self,
+        line: &[&Token<'b>],
+        open: &mut Vec<(&'b LayoutBox, InlineEdges)>,
+        start: Pos2,
+        baseline: f32,
+    ) -> Vec<Fragment> {
+        let mut x = start.x;
+        let mut stack: Vec<OpenBox<'_>> = open
+            .drain(..)
+            .map(|(layout_box, edges)| OpenBox {
+                layout_box,
+                edges,
+                start: x,
+                starts_here: false,
+                children: Vec::new(),
+            })
+            .collect();
+        let mut line_children = Vec::new();
+        for token in line {
+            let width = token.width();
+            match token {
+                Token::Open { layout_box, edges } => stack.push(OpenBox {
+                    layout_box,
+                    edges: *edges,
+                    start: x,
+                    starts_here: true,
+                    children: Vec::new(),
+                }),
+                Token::Close { .. } => {
+                    if let Some(open_box) = stack.pop() {
+                        let fragment = self.inline_fragment(open_box, x + width, baseline, true);
+                        children_of(&mut stack, &mut line_children).push(fragment);
+                    }
+                }
+                Token::Word {
+                    layout_box, text, ..
+                }
+                | Token::Space {
+                    layout_box, text, ..
+                } => {
+                    let fragment = self.text_fragment(layout_box, text, x, width, baseline);
+                    push_text(children_of(&mut stack, &mut line_children), fragment);
+                }
+                Token::Atomic(fragment) => {
+                    let mut fragment = fragment.clone();
+                    let margin_box = fragment.margin_rect();
+                    let position = Pos2::new(x, baseline - margin_box.height());
+                    fragment.translate(position - margin_box.min);
+                    children_of(&mut stack, &mut line_children).push(fragment);
+                }
+                Token::OutOfFlow(layout_box) => {
+                    let fragment = placeholder(layout_box, Pos2::new(x, start.y));
+                    children_of(&mut stack, &mut line_children).push(fragment);
+                }
+                Token::Break => {}
+            }
+            x += width;
+        }
+
+        *open = stack
+            .iter()
+            .map(|open_box| (open_box.layout_box, open_box.edges))
+            .collect();
+        while let Some(open_box) = stack.pop() {
+            let fragment = self.inline_fragment(open_box, x, baseline, false);
+            children_of(&mut stack, &mut line_children).push(fragment);
+        }
+        line_children
+    }
+
+    /// The fragment of the part of an inline box on one line, which ends at
+    /// `end`. Its left edges are only there on the line it starts on, and
+    /// its right ones on the line it `ends_here`.
+    fn inline_fragment(
+        &mut self,
+        open_box: OpenBox<'_>,
+        end: f32,
+        baseline: f32,
+        ends_here: bool,
+    ) -> Fragment {
+        let InlineEdges {
+            mut margin,
+            mut border,
+            mut padding,
+        } = open_box.edges;
+        if !open_box.starts_here {
+            (margin.left, border.left, padding.left) = (0.0, 0.0, 0.0);
+        }
+        if !ends_here {
+            (margin.right, border.right, padding.right) = (0.0, 0.0, 0.0);
+        }
+        // Vertical margins do not apply to inline boxes.
+        (margin.top, margin.bottom) = (0.0, 0.0);
+
+        let (ascent, row_height) = self.font_metrics(&open_box.layout_box.style);
+        let content = Rect::from_min_max(
+            Pos2::new(
+                open_box.start + margin.left + border.left + padding.left,
+                baseline - ascent,
+            ),
+            Pos2::new(
+                end - margin.right - border.right - padding.right,
+                baseline - ascent + row_height,
+            ),
+        );
+        Fragment {
+            kind: BoxKind::Inline,
+            node: open_box.layout_box.node,
+            style: open_box.layout_box.style.clone(),
+            rect: border.expand(padding.expand(content)),
+            margin,
+            border,
+            padding,
+            children: open_box.children,
+        }
+    }
+
+    fn text_fragment(
+        &mut self,
+        layout_box: &LayoutBox,
+        text: &str,
+        x: f32,
+        width: f32,
+        baseline: f32,
+    ) -> Fragment {
+        let (ascent, row_height) = self.font_metrics(&layout_box.style);
+        Fragment {
+            kind: BoxKind::Text(text.to_owned()),
+            node: layout_box.node,
+            style: layout_box.style.clone(),
+            rect: Rect::from_min_size(
+                Pos2::new(x, baseline - ascent),
+                Vec2::new(width, row_height),
+            ),
+            margin: Edges::default(),
+            border: Edges::default(),
+            padding: Edges::default(),
+            children: Vec::new(),
+        }
+    }
+
+    /// The width of the longest line of the inline content of
+    /// `layout_box` if lines are only broken where a `<br>` forces them.
+    pub(super) fn inline_max_content_width(&mut self, layout_box: &LayoutBox) -> f32 {
+        let mut tokens = Vec::new();
+        self.tokenize(layout_box, self.viewport.x, &mut tokens);
+        tokens
+            .split(|token| matches!(token, Token::Break))
+            .map(|line| {
+                let start = line
+                    .iter()
+                    .position(|token| !matches!(token, Token::Space { .. }))
+                    .unwrap_or(line.len());
+                line.iter().skip(start).map(Token::width).sum::<f32>()
+            })
+            .fold(0.0, f32::max)
+    }
+
+    /// The width of the widest piece of the inline content of `layout_box`
+    /// that lines cannot be broken in, such as its longest word.
+    pub(super) fn inline_min_content_width(&mut self, layout_box: &LayoutBox) -> f32 {
+        let mut tokens = Vec::new();
+        self.tokenize(layout_box, self.viewport.x, &mut tokens);
+        break_lines(&tokens, 0.0)
+            .iter()
+            .map(|line| line.iter().map(|token| token.width()).sum::<f32>())
+            .fold(0.0, f32::max)
+    }
+
+    /// Turns the inline-level children of `layout_box` into `tokens`, with
+    /// white space collapsed.
+    fn tokenize<'b>(
+        &mut self,
+        layout_box: &'b LayoutBox,
+        containing_width: f32,
+        tokens: &mut Vec<Token<'b>>,
+    ) {
+        for child in &layout_box.children {
+            if child.is_out_of_flow() {
+                tokens.push(Token::OutOfFlow(child));
+                continue;
+            }
+            match &child.kind {
+                BoxKind::Text(text) => self.tokenize_text(child, text, tokens),
+                BoxKind::LineBreak => tokens.push(Token::Break),
+                BoxKind::Inline => {
+                    let (border, padding) = self.border_and_padding(child, containing_width);
+                    let margin = Edges {
+                        left: self.margin(child, "margin-left", containing_width),
+                        right: self.margin(child, "margin-right", containing_width),
+                        ..Edges::default()
+                    };
+                    let edges = InlineEdges {
+                        margin,
+                        border,
+                        padding,
+                    };
+                    tokens.push(Token::Open {
+                        layout_box: child,
+                        edges,
+                    });
+                    self.tokenize(child, containing_width, tokens);
+                    tokens.push(Token::Close { edges });
+                }
+                BoxKind::InlineBlock | BoxKind::Replaced(_) | BoxKind::Table(_) => {
+                    let containing = ContainingBlock {
+                        x: 0.0,
+                        width: containing_width,
+                        height: None,
+                    };
+                    let mut fragment = self.layout_block(child, containing, 0.0);
+                    let origin = fragment.margin_rect().min;
+                    fragment.translate(Pos2::ZERO - origin);
+                    tokens.push(Token::Atomic(fragment));
+                }
+                BoxKind::Block
+                | BoxKind::AnonymousBlock
+                | BoxKind::TableCell
+                | BoxKind::Marker(_) => {}
+            }
+        }
+    }
+
+    /// Turns the text `text` of the text box `layout_box` into `tokens`,
+    /// after its `white-space`
+    /// (<https://www.w3.org/TR/css-text-3/#white-space-property>): `pre`
+    /// and `pre-wrap` keep spaces and newlines, `pre-line` only newlines,
+    /// and lines only wrap at spaces with `normal`, `pre-wrap` and
+    /// `pre-line`. Preserved tabs are as wide as eight spaces.
+    fn tokenize_text<'b>(
+        &mut self,
+        layout_box: &'b LayoutBox,
+        text: &str,
+        tokens: &mut Vec<Token<'b>>,
+    ) {
+        let (collapses, wraps, newlines) = match layout_box.style.keyword("white-space") {
+            Some("pre") => (false, false, true),
+            Some("nowrap") => (true, false, false),
+            Some("pre-wrap" | "break-spaces") => (false, true, true),
+            Some("pre-line") => (true, true, true),
+            _ => (true, true, false),
+        };
+        let text = if collapses {
+            text.to_owned()
+        } else {
+            text.replace('\t', "        ")
+        };
+        let lines: Vec<&str> = if newlines {
+            text.split('\n').collect()
+        } else {
+            vec![&text]
+        };
+        for (index, line) in lines.into_iter().enumerate() {
+            if index > 0 {
+                tokens.push(Token::Break);
+            }
+            if collapses {
+                self.tokenize_collapsed(layout_box, line, wraps, tokens);
+            } else {
+                self.tokenize_preserved(layout_box, line, wraps, tokens);
+            }
+        }
+    }
+
+    /// Turns `text`, where white space collapses, into words with a space
+    /// between them.
+    fn tokenize_collapsed<'b>(
+        &mut self,
+        layout_box: &'b LayoutBox,
+        text: &str,
+        wraps: bool,
+        tokens: &mut Vec<Token<'b>>,
+    ) {
+        let font = font_id(&layout_box.style);
+        let space = |tokens: &mut Vec<Token<'b>>, context: &mut Self| {
+            // A space collapses with the one before it, and there is none at
+            // the start of a line.
+            let previous = tokens
+                .iter()
+                .rev()
+                .find(|token| !matches!(token, Token::Open { .. } | Token::Close { .. }));
+            if matches!(previous, Some(Token::Word { .. } | Token::Atomic(_))) {
+                let width = context.text_width(" ", &font);
+                tokens.push(Token::Space {
+                    layout_box,
+                    text: " ".to_owned(),
+                    width,
+                    wraps,
+                });
+            }
+        };
+        if text.starts_with(|c: char| c.is_ascii_whitespace()) {
+            space(tokens, self);
+        }
+        for (index, word) in text.split_ascii_whitespace().enumerate() {
+            if index > 0 {
+                space(tokens, self);
+            }
+            tokens.push(Token::Word {
+                layout_box,
+                text: word.to_owned(),
+                width: self.text_width(word, &font),
+            });
+        }
+        if text.ends_with(|c: char| c.is_ascii_whitespace()) {
+            space(tokens, self);
+        }
+    }
+
+    /// Turns the line `text`, where white space is preserved, into tokens:
+    /// one word if lines do not wrap, and if they do, words with the runs
+    /// of spaces between them, which lines can break at. Spaces at the
+    /// start of the line stay with the first word, as they indent it.
+    fn tokenize_preserved<'b>(
+        &mut self,
+        layout_box: &'b LayoutBox,
+        text: &str,
+        wraps: bool,
+        tokens: &mut Vec<Token<'b>>,
+    ) {
+        let font = font_id(&layout_box.style);
+        let word = |tokens: &mut Vec<Token<'b>>, context: &mut Self, text: &str| {
+            if !text.is_empty() {
+                tokens.push(Token::Word {
+                    layout_box,
+                    text: text.to_owned(),
+                    width: context.text_width(text, &font),
+                });
+            }
+        };
+        if !wraps {
+            word(tokens, self, text);
+            return;
+        }
+        let indent = text.len() - text.trim_start_matches(' ').len();
+        let mut rest = text;
+        let mut first = true;
+        while !rest.is_empty() {
+            let skip = if first { indent } else { 0 };
+            first = false;
+            let end = rest
+                .get(skip..)
+                .and_then(|after| after.find(' '))
+                .map_or(rest.len(), |end| end + skip);
+            let (piece, after) = rest.split_at(end);
+            word(tokens, self, piece);
+            let spaces = after.len() - after.trim_start_matches(' ').len();
+            let (run, after) = after.split_at(spaces);
+            if !run.is_empty() {
+                tokens.push(Token::Space {
+                    layout_box,
+                    text: run.to_owned(),
+                    width: self.text_width(run, &font),
+                    wraps,
+                });
+            }
+            rest = after;
+        }
+    }
+
+    pub(super) fn text_width(&mut self, text: &str, font: &FontId) -> f32 {
+        self.fonts
+            .layout_no_wrap(text.to_owned(), font.clone(), Color32::PLACEHOLDER)
+            .size()
+            .x
+    }
+
+    /// The ascent and the row height of the font of `style`.
+    pub(super) fn font_metrics(&mut self, style: &ComputedStyle) -> (f32, f32) {
+        let font = font_id(style);
+        let metrics = self
+            .fonts
+            .fonts
+            .font(&font.family)
+            .scaled_metrics(1.0, font.size);
+        (metrics.ascent, self.fonts.row_height(&font))
+    }
+
+    /// How far text in `style` reaches above and below the baseline, with
+    /// the leading its `line-height` adds shared between both sides.
+    fn extent(&mut self, style: &ComputedStyle) -> Extent {
+        let (ascent, row_height) = self.font_metrics(style);
+        let line_height = match style.value("line-height") {
+            Some([ComponentValue::Token(CssToken::Dimension { value, unit })]) if unit == "px" => {
+                value.value as f32
+            }
+            Some([ComponentValue::Token(CssToken::Number(number))]) => {
+                number.value as f32 * style.font_size() as f32
+            }
+            _ => row_height,
+        };
+        let half_leading = (line_height - row_height) / 2.0;
+        Extent {
+            above: ascent + half_leading,
+            below: row_height - ascent + half_leading,
+        }
+    }
+}
+
+/// Breaks `tokens` into lines at most `width` wide; see [`break_line`].
+fn break_lines<'t, 'b>(tokens: &'t [Token<'b>], width: f32) -> Vec<Vec<&'t Token<'b>>> {
+    let mut rest = tokens;
+    std::iter::from_fn(|| break_line(&mut rest, width)).collect()
+}
+
+/// Takes the first line of `rest` at most `width` wide, breaking it where
+/// it can be: at spaces, which are dropped at the end of a line, and at
+/// forced breaks. A word too wide for a line of its own overflows it.
+/// Returns `None` once there is nothing left to put on a line.
+fn break_line<'t, 'b>(rest: &mut &'t [Token<'b>], width: f32) -> Option<Vec<&'t Token<'b>>> {
+    if !rest
+        .iter()
+        .any(|token| token.is_content() || matches!(token, Token::Break))
+    {
+        return None;
+    }
+    let mut line: Vec<&Token<'b>> = Vec::new();
+    while let Some((first, after)) = rest.split_first() {
+        if matches!(first, Token::Break) {
+            *rest = after;
+            return Some(line);
+        }
+        // The space before the segment, if any, and the segment up to the
+        // next place the line can break.
+        let (space, segment_start) = match first {
+            Token::Space { wraps: true, .. } => (Some(first), after),
+            _ => (None, *rest),
+        };
+        let end = segment_start
+            .iter()
+            .position(|token| matches!(token, Token::Space { wraps: true, .. } | Token::Break))
+            .unwrap_or(segment_start.len());
+        let (segment, after) = segment_start.split_at(end);
+
+        let has_content = line.iter().any(|token| token.is_content());
+        let used: f32 = line.iter().map(|token| token.width()).sum();
+        let space_width = space.map_or(0.0, Token::width);
+        let segment_width: f32 = segment.iter().map(Token::width).sum();
+        if has_content && used + space_width + segment_width > width {
+            *rest = segment_start;
+            return Some(line);
+        } else if has_content && let Some(space) = space {
+            line.push(space);
+        }
+        line.extend(segment);
+        *rest = after;
+    }
+    Some(line)
+}
+
+/// Where a fragment on a line goes: into the innermost open inline box, or
+/// onto the line itself.
+fn children_of<'a>(
+    stack: &'a mut [OpenBox<'_>],
+    line_children: &'a mut Vec<Fragment>,
+) -> &'a mut Vec<Fragment> {
+    match stack.last_mut() {
+        Some(open_box) => &mut open_box.children,
+        None => line_children,
+    }
+}
+
+/// Adds the text fragment `fragment` to `children`, as part of the one
+/// before it if that is the text of the same node just before it.
+fn push_text(children: &mut Vec<Fragment>, fragment: Fragment) {
+    if let Some(last) = children.last_mut()
+        && last.node == fragment.node
+        && let (BoxKind::Text(text), BoxKind::Text(more)) = (&mut last.kind, &fragment.kind)
+    {
+        text.push_str(more);
+        last.rect.max.x = fragment.rect.max.x;
+        return;
+    }
+    children.push(fragment);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::tests::{layout, rect, text_width};
+
+    fn texts(tree: &crate::layout::LayoutTree) -> Vec<(String, Rect)> {
+        tree.fragments()
+            .filter_map(|fragment| match &fragment.kind {
+                BoxKind::Text(text) => Some((text.clone(), fragment.rect)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_line_breaking() {
+        let (_, tree) = layout(
+            "<p>  one   two\n three <b>four</b>five<br>six</p>",
+            "body { margin: 0 } p { margin: 0; line-height: 20px }",
+        );
+        let texts = texts(&tree);
+        let words: Vec<&str> = texts.iter().map(|(text, _)| text.as_str()).collect();
+        assert_eq!(words, ["one two three ", "four", "five", "six"]);
+        let (_, first) = texts[0];
+        assert_eq!(first.min.x, 0.0);
+        assert_eq!(texts[1].1.min.x, first.max.x);
+        assert_eq!(texts[3].1.min.x, 0.0);
+        assert_eq!(texts[3].1.min.y - first.min.y, 20.0);
+
+        // A narrow block breaks the line at spaces.
+        let width = text_width("one two") + 10.0;
+        let (document, tree) = layout(
+            "<p id=p style='text-align: center'>one two one two</p>",
+            &format!("body {{ margin: 0 }} p {{ width: {width}px; line-height: 10px }}"),
+        );
+        let texts: Vec<(String, Rect)> = self::texts(&tree);
+        assert_eq!(texts.len(), 2);
+        assert_eq!(texts[0].0, "one two");
+        assert_eq!(texts[1].1.min.y - texts[0].1.min.y, 10.0);
+        assert_eq!(texts[0].1.min.x, (width - texts[0].1.width()) / 2.0);
+        assert_eq!(rect(&document, &tree, "p").height(), 20.0);
+    }
+
+    #[test]
+    fn test_inline_boxes() {
+        let (document, tree) = layout(
+            "<p id=p>a <span id=s style='padding: 0 5px; border: 2px solid'>b</span> \
+             <span id=i style='display: inline-block; width: 30px; height: 40px'></span></p>",
+            "body { margin: 0 } p { margin: 0; line-height: 20px }",
+        );
+        let s = rect(&document, &tree, "s");
+        let i = rect(&document, &tree, "i");
+        let b = tree
+            .fragments()
+            .find(|fragment| fragment.kind == BoxKind::Text("b".to_owned()))
+            .unwrap()
+            .rect;
+        assert_eq!(b.min.x, s.min.x + 7.0);
+        assert_eq!(i.size(), Vec2::new(30.0, 40.0));
+        assert!(i.min.x > s.max.x);
+        // The inline-block sits on the baseline, which moves down for it.
+        let p = rect(&document, &tree, "p");
+        assert_eq!(i.min.y, p.min.y);
+        assert!(p.height() > 40.0);
+        assert!(s.min.y > p.min.y);
+    }
+
+    #[test]
+    fn test_white_space() {
+        let css = "body { margin: 0 } pre { margin: 0; line-height: 20px; width: 60px } \
+                   .wrap { white-space: pre-wrap } .nowrap { white-space: nowrap }";
+        let (_, tree) = layout("<pre>a  b\n\n    c d\te</pre>", css);
+        let pre = texts(&tree);
+        let top = pre[0].1.min.y;
+        let lines: Vec<(&str, f32)> = pre
+            .iter()
+            .map(|(text, rect)| (text.as_str(), rect.min.y - top))
+            .collect();
+        // The empty line keeps its height.
+        assert_eq!(lines, [("a  b", 0.0), ("    c d        e", 40.0)]);
+
+        // Preserved spaces still wrap with pre-wrap, and lines do not at
+        // all with nowrap.
+        let (_, tree) = layout(
+            "<pre class=wrap style='width: 100px'>  one two three</pre>",
+            css,
+        );
+        let words: Vec<String> = texts(&tree).into_iter().map(|(text, _)| text).collect();
+        assert_eq!(words, ["  one two", "three"]);
+        let (_, tree) = layout("<p class=nowrap style='width: 10px'>one two three</p>", css);
+        assert_eq!(texts(&tree).len(), 1);
+    }
+}
@@ -0,0 +1,176 @@
+use egui::Vec2;
+
+use crate::css::{ComponentValue, CssToken};
+use crate::style::ComputedStyle;
+
+/// The font size of the root element `rem` is taken to stand for, in
+/// pixels.
+const ROOT_FONT_SIZE: f32 = 16.0;
+
+/// The length the `property` of `style` sets, in pixels, with percentages
+/// of `percent_base`. Returns `None` for `auto`, and for what cannot be
+/// resolved: a percentage when `percent_base` is `None`, or a value that
+/// is not a length.
+pub(super) fn length(
+    style: &ComputedStyle,
+    property: &str,
+    percent_base: Option<f32>,
+    viewport: Vec2,
+) -> Option<f32> {
+    match style.value(property)? {
+        [component] => resolve(component, style, percent_base, viewport),
+        _ => None,
+    }
+}
+
+/// The length `component`, part of a value of `style`, in pixels; see
+/// [`length`].
+pub(super) fn resolve(
+    component: &ComponentValue,
+    style: &ComputedStyle,
+    percent_base: Option<f32>,
+    viewport: Vec2,
+) -> Option<f32> {
+    match component {
+        ComponentValue::Token(CssToken::Dimension { value, unit }) => {
+            let font_size = style.font_size() as f32;
+            let pixels = match unit.to_ascii_lowercase().as_str() {
+                "px" => 1.0,
+                "em" => font_size,
+                "ex" | "ch" => font_size / 2.0,
+                "rem" => ROOT_FONT_SIZE,
+                "vw" => viewport.x / 100.0,
+                "vh" => viewport.y / 100.0,
+                "vmin" => viewport.min_elem() / 100.0,
+                "vmax" => viewport.max_elem() / 100.0,
+                "pt" => 4.0 / 3.0,
+                "pc" => 16.0,
+                "in" => 96.0,
+                "cm" => 96.0 / 2.54,
+                "mm" => 96.0 / 25.4,
+                "q" => 96.0 / 101.6,
+                _ => return None,
+            };
+            Some(value.value as f32 * pixels)
+        }
+        ComponentValue::Token(CssToken::Percentage(percentage)) => {
+            Some(percentage.value as f32 / 100.0 * percent_base?)
+        }
+        ComponentValue::Token(CssToken::Number(number)) if number.value == 0.0 => Some(0.0),
+        _ => None,
+    }
+}
+
+/// The properties layout reads lengths from.
+const LENGTH_PROPERTIES: [&str; 26] = [
+    "width",
+    "height",
+    "min-width",
+    "max-width",
+    "min-height",
+    "max-height",
+    "margin-top",
+    "margin-right",
+    "margin-bottom",
+    "margin-left",
+    "padding-top",
+    "padding-right",
+    "padding-bottom",
+    "padding-left",
+    "border-top-width",
+    "border-right-width",
+    "border-bottom-width",
+    "border-left-width",
+    "top",
+    "right",
+    "bottom",
+    "left",
+    "line-height",
+    "text-indent",
+    "border-spacing",
+    "vertical-align",
+];
+
+/// Whether any length layout reads from `style` is relative to the size
+/// of the viewport, such as `50vw`, so that its layout changes when the
+/// window is resized.
+pub(super) fn uses_viewport_units(style: &ComputedStyle) -> bool {
+    LENGTH_PROPERTIES.iter().any(|property| {
+        style.value(property).is_some_and(|value| {
+            value.iter().any(|component| {
+                matches!(
+                    component,
+                    ComponentValue::Token(CssToken::Dimension { unit, .. })
+                        if matches!(
+                            unit.to_ascii_lowercase().as_str(),
+                            "vw" | "vh" | "vmin" | "vmax"
+                        )
+                )
+            })
+        })
+    })
+}
+
+/// Whether the `property` of `style` is `auto`.
+pub(super) fn is_auto(style: &ComputedStyle, property: &str) -> bool {
+    style
+        .keyword(property)
+        .is_some_and(|keyword| keyword.eq_ignore_ascii_case("auto"))
+}
+
+/// The width of the border on `side`, such as `top`, in pixels: none
+/// unless the border has a style.
+pub(super) fn border_width(style: &ComputedStyle, side: &str, viewport: Vec2) -> f32 {
+    let border_style = style.keyword(&format!("border-{side}-style"));
+    if border_style.is_none_or(|keyword| matches!(keyword, "none" | "hidden")) {
+        return 0.0;
+    }
+    let property = format!("border-{side}-width");
+    match style.keyword(&property) {
+        Some("thin") => 1.0,
+        Some("medium") => 3.0,
+        Some("thick") => 5.0,
+        _ => length(style, &property, None, viewport).unwrap_or(3.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::Stylesheet;
+    use crate::dom::Document;
+    use crate::style::{Cascade, Origin};
+
+    #[test]
+    fn test_lengths() {
+        let document = Document::parse("<p id=a>a</p>");
+        let mut cascade = Cascade::new();
+        cascade.add_stylesheet(
+            Origin::Author,
+            &Stylesheet::parse(
+                "p { font-size: 20px; width: 50%; height: 2em; margin-left: 10vw; \
+                 margin-right: auto; padding-top: 0; padding-left: 1in; \
+                 border-top: thin solid; border-left: 2px; border-bottom: 4px dotted }",
+            ),
+        );
+        let styles = cascade.compute(&document);
+        let style = styles
+            .style_for(document.get_element_by_id("a").unwrap())
+            .unwrap();
+        let viewport = Vec2::new(800.0, 600.0);
+        let length = |property| length(style, property, Some(300.0), viewport);
+        assert_eq!(length("width"), Some(150.0));
+        assert_eq!(length("height"), Some(40.0));
+        assert_eq!(length("margin-left"), Some(80.0));
+        assert_eq!(length("margin-right"), None);
+        assert!(is_auto(style, "margin-right"));
+        assert_eq!(length("padding-top"), Some(0.0));
+        assert_eq!(length("padding-left"), Some(96.0));
+        assert_eq!(super::length(style, "width", None, viewport), None);
+
+        assert_eq!(border_width(style, "top", viewport), 1.0);
+        assert_eq!(border_width(style, "left", viewport), 0.0);
+        assert_eq!(border_width(style, "bottom", viewport), 4.0);
+        assert!(uses_viewport_units(style));
+    }
+}
@@ -0,0 +1,220 @@
+use std::sync::Arc;
+
+use egui::{Pos2, Rect, Vec2};
+
+use super::block::LayoutContext;
+use super::inline::font_id;
+use super::{BoxKind, Edges, Fragment, LayoutBox};
+use crate::dom::{Document, NodeId};
+use crate::style::{ComputedStyle, Display};
+
+/// The Roman numerals, with the subtractive pairs, from the largest.
+const ROMAN_NUMERALS: [(i64, &str); 13] = [
+    (1000, "m"),
+    (900, "cm"),
+    (500, "d"),
+    (400, "cd"),
+    (100, "c"),
+    (90, "xc"),
+    (50, "l"),
+    (40, "xl"),
+    (10, "x"),
+    (9, "ix"),
+    (5, "v"),
+    (4, "iv"),
+    (1, "i"),
+];
+
+/// Whether an element with the style `style` has a marker: it is a list
+/// item whose `list-style-type` is not `none`.
+pub(super) fn has_marker(style: &ComputedStyle) -> bool {
+    style.display() == Display::ListItem && style.keyword("list-style-type") != Some("none")
+}
+
+/// The marker box of the list item `id`, with the style `style`, if it has
+/// one. It goes first among the children of the list item.
+pub(super) fn marker_box(
+    document: &Document,
+    id: NodeId,
+    style: &Arc<ComputedStyle>,
+) -> Option<LayoutBox> {
+    if !has_marker(style) {
+        return None;
+    }
+    let text = marker_text(
+        style.keyword("list-style-type")?,
+        document.ordinal_value(id),
+    )?;
+    Some(LayoutBox::new(
+        BoxKind::Marker(text),
+        id,
+        Arc::clone(style),
+        Vec::new(),
+    ))
+}
+
+/// The text of the marker of a list item with the `list-style-type`
+/// `list_style_type` and the number `ordinal`: a bullet, or the number
+/// followed by a period, with a space after either. Counter styles that
+/// cannot show `ordinal`, and those that are not supported, fall back to
+/// `decimal` (<https://www.w3.org/TR/css-counter-styles-3/#simple-numeric>).
+pub(super) fn marker_text(list_style_type: &str, ordinal: i64) -> Option<String> {
+    let number = match list_style_type {
+        "none" => return None,
+        "disc" => return Some("• ".to_owned()),
+        "circle" => return Some("◦ ".to_owned()),
+        "square" => return Some("▪ ".to_owned()),
+        "decimal-leading-zero" if (0..10).contains(&ordinal) => format!("0{ordinal}"),
+        "lower-alpha" | "lower-latin" => alphabetic(ordinal).unwrap_or_else(|| ordinal.to_string()),
+        "upper-alpha" | "upper-latin" => alphabetic(ordinal)
+            .map_or_else(|| ordinal.to_string(), |number| number.to_ascii_uppercase()),
+        "lower-roman" => roman(ordinal).unwrap_or_else(|| ordinal.to_string()),
+        "upper-roman" => {
+            roman(ordinal).map_or_else(|| ordinal.to_string(), |number| number.to_ascii_uppercase())
+        }
+        _ => ordinal.to_string(),
+    };
+    Some(format!("{number}. "))
+}
+
+/// `ordinal` in the `lower-alpha` counter style: a to z, then aa to zz and
+/// so on. Only positive numbers have one.
+fn alphabetic(ordinal: i64) -> Option<String> {
+    if ordinal < 1 {
+        return None;
+    }
+    let mut letters = Vec::new();
+    let mut rest = ordinal;
+    while rest > 0 {
+        rest -= 1;
+        letters.push(char::from(b'a' + (rest % 26) as u8));
+        rest /= 26;
+    }
+    Some(letters.iter().rev().collect())
+}
+
+/// `ordinal` in the `lower-roman` counter style, which goes from 1 to 3999.
+fn roman(ordinal: i64) -> Option<String> {
+    if !(1..4000).contains(&ordinal) {
+        return None;
+    }
+    let mut numeral = String::new();
+    let mut rest = ordinal;
+    for (value, digits) in ROMAN_NUMERALS {
+        while rest >= value {
+            numeral.push_str(digits);
+            rest -= value;
+        }
+    }
+    Some(numeral)
+}
+
+impl LayoutContext<'_, '_> {
+    /// The fragment of the marker of a list item, `text` in `style` for
+    /// `node`, outside its content box, which starts at `content`: it ends
+    /// where the content starts, on the baseline of the first line of text
+    /// in `children`, the fragments of the content, or of a line of its own
+    /// if there is none.
+    pub(super) fn layout_marker(
+        &mut self,
+        text: &str,
+        node: NodeId,
+        style: &Arc<ComputedStyle>,
+        content: Pos2,
+        children: &[Fragment],
+    ) -> Fragment {
+        let width = self.text_width(text, &font_id(style));
+        let (ascent, row_height) = self.font_metrics(style);
+        let baseline = match first_text(children) {
+            Some(line) => line.rect.min.y + self.font_metrics(&line.style).0,
+            None => content.y + ascent,
+        };
+        Fragment {
+            kind: BoxKind::Marker(text.to_owned()),
+            node,
+            style: Arc::clone(style),
+            rect: Rect::from_min_size(
+                Pos2::new(content.x - width, baseline - ascent),
+                Vec2::new(width, row_height),
+            ),
+            margin: Edges::default(),
+            border: Edges::default(),
+            padding: Edges::default(),
+            children: Vec::new(),
+        }
+    }
+}
+
+/// The first text fragment in `fragments` and their descendants.
+fn first_text(fragments: &[Fragment]) -> Option<&Fragment> {
+    fragments.iter().find_map(|fragment| match fragment.kind {
+        BoxKind::Text(_) => Some(fragment),
+        BoxKind::Marker(_) => None,
+        _ => first_text(&fragment.children),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::tests::{layout, rect, text_width};
+
+    #[test]
+    fn test_marker_text() {
+        let texts: Vec<Option<String>> = [
+            ("disc", 1),
+            ("none", 1),
+            ("decimal", 12),
+            ("decimal-leading-zero", 7),
+            ("lower-alpha", 28),
+            ("upper-latin", 3),
+            ("lower-alpha", 0),
+            ("upper-roman", 1994),
+            ("lower-roman", -2),
+        ]
+        .into_iter()
+        .map(|(list_style_type, ordinal)| marker_text(list_style_type, ordinal))
+        .collect();
+        let expected = [
+            "• ",
+            "",
+            "12. ",
+            "07. ",
+            "ab. ",
+            "C. ",
+            "0. ",
+            "MCMXCIV. ",
+            "-2. ",
+        ];
+        assert_eq!(
+            texts,
+            expected.map(|text| (!text.is_empty()).then(|| text.to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_markers_in_layout() {
+        let (document, tree) = layout(
+            "<ol start=3><li id=a>a<li id=b><p id=p>b</p><ul><li id=c>c</ul></ol>\
+             <ul style='list-style-type: none'><li id=d>d</ul>",
+            "body { margin: 0 } p { margin: 10px 0 }",
+        );
+        let markers: Vec<(&str, Rect)> = tree
+            .fragments()
+            .filter_map(|fragment| match &fragment.kind {
+                BoxKind::Marker(text) => Some((text.as_str(), fragment.rect)),
+                _ => None,
+            })
+            .collect();
+        let texts: Vec<&str> = markers.iter().map(|(text, _)| *text).collect();
+        assert_eq!(texts, ["3. ", "4. ", "◦ "]);
+        let [a, p, c] = ["a", "p", "c"].map(|id| rect(&document, &tree, id));
+        // Markers hang outside the content, on the first line of text.
+        assert_eq!(a.min.x, 40.0);
+        assert_eq!(markers[0].1.max.x, a.min.x);
+        assert_eq!(markers[0].1.width(), text_width("3. "));
+        assert_eq!(markers[1].1.min.y, p.min.y);
+        assert_eq!(c.min.x, 80.0);
+        assert_eq!(markers[2].1.max.x, c.min.x);
+    }
+}
@@ -0,0 +1,445 @@
+use egui::{Pos2, Rect, Vec2};
+
+use super::block::{ContainingBlock, LayoutContext};
+use super::length::{is_auto, length};
+use super::{BoxKind, Edges, Fragment, LayoutBox, LayoutTree};
+use crate::css::{ComponentValue, CssToken};
+use crate::dom::NodeId;
+use crate::style::ComputedStyle;
+
+/// The `position` of a box of the kind `kind` with the style `style`, if
+/// it is positioned: `relative`, `absolute`, `fixed` or `sticky`. Text,
+/// anonymous blocks and markers share the style of their parent, so they
+/// are never positioned themselves.
+pub(super) fn positioning<'s>(kind: &BoxKind, style: &'s ComputedStyle) -> Option<&'s str> {
+    if matches!(
+        kind,
+        BoxKind::Text(_) | BoxKind::AnonymousBlock | BoxKind::Marker(_)
+    ) {
+        return None;
+    }
+    style
+        .keyword("position")
+        .filter(|position| matches!(*position, "relative" | "absolute" | "fixed" | "sticky"))
+}
+
+/// Whether `style` takes the box of an element out of the flow: it is
+/// `position: absolute` or `fixed`, so it takes no room among its
+/// siblings and is placed against its containing block instead.
+pub(super) fn takes_out_of_flow(style: &ComputedStyle) -> bool {
+    matches!(style.keyword("position"), Some("absolute" | "fixed"))
+}
+
+impl LayoutBox {
+    /// Whether the box is taken out of the flow by its `position`: it takes
+    /// no room among its siblings, where a placeholder keeps its static
+    /// position, and its containing block lays it out once its own size is
+    /// known.
+    pub fn is_out_of_flow(&self) -> bool {
+        matches!(
+            self.kind,
+            BoxKind::Block | BoxKind::InlineBlock | BoxKind::Replaced(_) | BoxKind::Table(_)
+        ) && takes_out_of_flow(&self.style)
+    }
+}
+
+/// A fragment that holds the place of the out-of-flow box `layout_box` at
+/// `position`, its static position: where it would be if it were in the
+/// flow. Its containing block lays the box out in its place.
+pub(super) fn placeholder(layout_box: &LayoutBox, position: Pos2) -> Fragment {
+    Fragment {
+        kind: layout_box.kind.clone(),
+        node: layout_box.node,
+        style: layout_box.style.clone(),
+        rect: Rect::from_min_size(position, Vec2::ZERO),
+        margin: Edges::default(),
+        border: Edges::default(),
+        padding: Edges::default(),
+        children: Vec::new(),
+    }
+}
+
+impl LayoutContext<'_, '_> {
+    /// Lays out the out-of-flow boxes inside `layout_box`, which was laid
+    /// out into `fragment`, in their containing block `containing`: the
+    /// padding box of `layout_box` for the `position: absolute` boxes
+    /// whose nearest positioned ancestor it is. If `initial`, `layout_box`
+    /// is the root and `containing` the viewport, which is the containing
+    /// block of every `position: fixed` box and of the absolute ones with
+    /// no positioned ancestor.
+    pub(super) fn layout_out_of_flow(
+        &mut self,
+        layout_box: &LayoutBox,
+        fragment: &mut Fragment,
+        containing: Rect,
+        initial: bool,
+    ) {
+        let mut boxes = Vec::new();
+        let positioned = positioning(&layout_box.kind, &layout_box.style).is_some();
+        out_of_flow_boxes(layout_box, initial, initial && positioned, &mut boxes);
+        for out_of_flow in boxes {
+            let laid_out = |context: &mut Self, position| {
+                context.layout_positioned(out_of_flow, containing, position)
+            };
+            if let Some(placeholder) = find_placeholder(fragment, out_of_flow.node) {
+                *placeholder = laid_out(self, placeholder.rect.min);
+            } else {
+                // Its place was not kept, as happens at the end of a line.
+                let position = fragment.content_rect().min;
+                fragment.children.push(laid_out(self, position));
+            }
+        }
+    }
+
+    /// Lays out the out-of-flow box `layout_box` in its containing block
+    /// `containing`, after its `top`, `right`, `bottom` and `left`
+    /// (<https://www.w3.org/TR/CSS21/visudet.html#abs-non-replaced-width>).
+    /// Where both sides of an axis are `auto`, the box stays at its static
+    /// position `position`. With both `left` and `right` set, an `auto`
+    /// width fills the room between them, and it shrinks to fit its content
+    /// if not; likewise, an `auto` height stretches between `top` and
+    /// `bottom`.
+    fn layout_positioned(
+        &mut self,
+        layout_box: &LayoutBox,
+        containing: Rect,
+        position: Pos2,
+    ) -> Fragment {
+        let style = &layout_box.style;
+        let offset = |property, base| {
+            (!is_auto(style, property))
+                .then(|| length(style, property, Some(base), self.viewport).unwrap_or(0.0))
+        };
+        let left = offset("left", containing.width());
+        let right = offset("right", containing.width());
+        let top = offset("top", containing.height());
+        let bottom = offset("bottom", containing.height());
+
+        let x = match (left, right) {
+            (Some(left), _) => containing.left() + left,
+            (None, Some(_)) => containing.left(),
+            (None, None) => position.x,
+        };
+        let block = ContainingBlock {
+            x,
+            width: containing.right() - right.unwrap_or(0.0) - x,
+            height: Some(containing.height()),
+        };
+        let y = top.map_or(position.y, |top| containing.top() + top);
+        let margin_top = self.margin(layout_box, "margin-top", containing.width());
+        let mut fragment = self.layout_block(layout_box, block, y + margin_top);
+
+        if let (None, Some(right)) = (left, right) {
+            let end = containing.right() - right;
+            fragment.translate(Vec2::new(end - fragment.margin_rect().right(), 0.0));
+        }
+        match (top, bottom) {
+            (None, Some(bottom)) => {
+                let end = containing.bottom() - bottom;
+                fragment.translate(Vec2::new(0.0, end - fragment.margin_rect().bottom()));
+            }
+            (Some(_), Some(bottom)) if is_auto(style, "height") => {
+                let end = containing.bottom() - bottom - fragment.margin.bottom;
+                fragment.rect.max.y = fragment.rect.max.y.max(end);
+            }
+            _ => {}
+        }
+        fragment
+    }
+
+    /// Moves the fragments of `position: relative` boxes inside
+    /// `fragment` by their `top`, `right`, `bottom` and `left`, after the
+    /// flow is laid out, so that their siblings stay where they are
+    /// (<https://www.w3.org/TR/CSS21/visuren.html#relative-positioning>).
+    /// `left` wins over `right` and `top` over `bottom`, and percentages
+    /// are of the width of the box they are in, or zero for vertical ones.
+    pub(super) fn offset_relative(&self, fragment: &mut Fragment) {
+        let width = fragment.content_rect().width();
+        for child in &mut fragment.children {
+            if positioning(&child.kind, &child.style) == Some("relative") {
+                let style = &child.style;
+                let offset = |property, base| {
+                    (!is_auto(style, property))
+                        .then(|| length(style, property, base, self.viewport).unwrap_or(0.0))
+                };
+                let x = offset("left", Some(width))
+                    .or_else(|| offset("right", Some(width)).map(|right| -right))
+                    .unwrap_or(0.0);
+                let y = offset("top", None)
+                    .or_else(|| offset("bottom", None).map(|bottom| -bottom))
+                    .unwrap_or(0.0);
+                child.translate(Vec2::new(x, y));
+            }
+            self.offset_relative(child);
+        }
+    }
+}
+
+/// Adds to `boxes` the out-of-flow boxes inside `layout_box` that a pass
+/// of [`LayoutContext::layout_out_of_flow`] lays out: the absolute ones
+/// with no positioned box between them and `layout_box`, unless
+/// `under_positioned`, and if `initial`, all the fixed ones.
+fn out_of_flow_boxes<'b>(
+    layout_box: &'b LayoutBox,
+    initial: bool,
+    under_positioned: bool,
+    boxes: &mut Vec<&'b LayoutBox>,
+) {
+    for child in &layout_box.children {
+        if child.is_out_of_flow() {
+            let fixed = child.style.keyword("position") == Some("fixed");
+            if if fixed { initial } else { !under_positioned } {
+                boxes.push(child);
+            }
+        }
+        let under_positioned = under_positioned || positioning(&child.kind, &child.style).is_some();
+        // Past a positioned box, only fixed boxes are left for the root.
+        if initial || !under_positioned {
+            out_of_flow_boxes(child, initial, under_positioned, boxes);
+        }
+    }
+}
+
+/// The placeholder of the out-of-flow box of `node` in `fragment` or the
+/// fragments inside it.
+fn find_placeholder(fragment: &mut Fragment, node: NodeId) -> Option<&mut Fragment> {
+    fragment.children.iter_mut().find_map(|child| {
+        if child.node == node && child.children.is_empty() && child.rect.size() == Vec2::ZERO {
+            Some(child)
+        } else {
+            find_placeholder(child, node)
+        }
+    })
+}
+
+/// Fragments in paint order, each with the rectangle it is clipped to.
+type PaintOrder<'f> = Vec<(&'f Fragment, Rect)>;
+
+/// The `z-index` of `style`, where `auto` is 0.
+fn z_index(style: &ComputedStyle) -> i32 {
+    match style.value("z-index") {
+        Some([ComponentValue::Token(CssToken::Number(number))]) if number.integer => {
+            number.value as i32
+        }
+        _ => 0,
+    }
+}
+
+impl LayoutTree {
+    /// The fragments in the order they are painted in, back to front
+    /// (<https://www.w3.org/TR/CSS21/zindex.html>): each positioned
+    /// fragment is a layer, painted with the fragments inside it, over or
+    /// under the fragments in the flow of the layer it is in after its
+    /// `z-index`. Layers with a negative `z-index` go under the flow, but
+    /// over the background of the layer they are in, and layers with the
+    /// same `z-index` go in the order of the document. A positioned
+    /// fragment with `z-index: auto` is a layer of its own too, which
+    /// simplifies the spec, where only the ones with an integer are.
+    ///
+    /// `position: fixed` layers stay where they are in the viewport while
+    /// the page scrolls under them, so they are painted last, over the rest
+    /// of the page, in the order of their `z-index`.
+    pub fn paint_order(&self) -> Vec<&Fragment> {
+        let (scrolled, fixed) = self.paint_orders();
+        scrolled
+            .into_iter()
+            .chain(fixed)
+            .map(|(fragment, _)| fragment)
+            .collect()
+    }
+
+    /// The fragments that scroll with the page in paint order, as
+    /// [`Self::paint_order`] has them, each with the rectangle it is
+    /// clipped to: where the clip rects of the fragments around it meet,
+    /// or [`Rect::EVERYTHING`] if none of them clips.
+    pub fn clipped_paint_order(&self) -> Vec<(&Fragment, Rect)> {
+        self.paint_orders().0
+    }
+
+    /// The fragments of the `position: fixed` layers, and those inside
+    /// them, in paint order with their clip rects, as
+    /// [`Self::clipped_paint_order`] has the others. They are where they
+    /// are in the viewport rather than on the page.
+    pub fn fixed_paint_order(&self) -> Vec<(&Fragment, Rect)> {
+        self.paint_orders().1
+    }
+
+    /// The node of the topmost fragment at `point` of the viewport, with
+    /// the page scrolled by `scroll`, which is what the pointer is over
+    /// there: an element, or a text node for text. The fixed layers are
+    /// over the page, so they are tried first. Fragments that are hidden,
+    /// or clipped out at `point`, are not hit. This is where links are
+    /// followed from, and what `:hover` matches.
+    pub fn hit_test(&self, point: Pos2, scroll: Vec2) -> Option<NodeId> {
+        let (scrolled, fixed) = self.paint_orders();
+        let hit = |order: PaintOrder<'_>, point: Pos2| {
+            order
+                .into_iter()
+                .rev()
+                .find(|(fragment, clip)| {
+                    clip.contains(point) && fragment.rect.contains(point) && fragment.is_visible()
+                })
+                .map(|(fragment, _)| fragment.node)
+        };
+        hit(fixed, point).or_else(|| hit(scrolled, point + scroll))
+    }
+
+    /// The fragments that scroll with the page, and those of the fixed
+    /// layers, each in paint order with their clip rects.
+    fn paint_orders(&self) -> (PaintOrder<'_>, PaintOrder<'_>) {
+        let mut order = Vec::new();
+        let mut fixed_layers = Vec::new();
+        if let Some(root) = self.root() {
+            stack(root, Rect::EVERYTHING, &mut order, Some(&mut fixed_layers));
+        }
+        fixed_layers.sort_by_key(|layer| z_index(&layer.style));
+        let mut fixed = Vec::new();
+        for layer in fixed_layers {
+            stack(layer, Rect::EVERYTHING, &mut fixed, None);
+        }
+        (order, fixed)
+    }
+}
+
+/// Adds the layer `root`, clipped to `clip`, and the fragments inside it,
+/// to `order`. The fixed layers inside it go to `fixed`, without the clip
+/// of the boxes around them, unless there is no `fixed` as they are
+/// already in a fixed layer.
+fn stack<'f>(
+    root: &'f Fragment,
+    clip: Rect,
+    order: &mut Vec<(&'f Fragment, Rect)>,
+    mut fixed: Option<&mut Vec<&'f Fragment>>,
+) {
+    fn collect<'f>(
+        fragment: &'f Fragment,
+        clip: Rect,
+        flow: &mut Vec<(&'f Fragment, Rect)>,
+        layers: &mut Vec<(&'f Fragment, Rect)>,
+        fixed: &mut Option<&mut Vec<&'f Fragment>>,
+    ) {
+        let clip = fragment
+            .clip_rect()
+            .map_or(clip, |rect| rect.intersect(clip));
+        for child in &fragment.children {
+            match (positioning(&child.kind, &child.style), fixed.as_deref_mut()) {
+                (Some("fixed"), Some(fixed)) => fixed.push(child),
+                (Some(_), _) => layers.push((child, clip)),
+                (None, _) => {
+                    flow.push((child, clip));
+                    collect(child, clip, flow, layers, fixed);
+                }
+            }
+        }
+    }
+    let mut flow = Vec::new();
+    let mut layers = Vec::new();
+    collect(root, clip, &mut flow, &mut layers, &mut fixed);
+    layers.sort_by_key(|(layer, _)| z_index(&layer.style));
+    let below = layers.partition_point(|(layer, _)| z_index(&layer.style) < 0);
+
+    order.push((root, clip));
+    for &(layer, clip) in layers.get(..below).unwrap_or_default() {
+        stack(layer, clip, order, fixed.as_deref_mut());
+    }
+    order.extend(flow);
+    for &(layer, clip) in layers.get(below..).unwrap_or_default() {
+        stack(layer, clip, order, fixed.as_deref_mut());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::tests::{layout, rect};
+
+    #[test]
+    fn test_positioning() {
+        let (document, tree) = layout(
+            "<div id=a>a</div><div id=r>r<span id=abs>abs</span>\
+             <div id=corner>c</div></div><div id=b>b</div><div id=fixed>f</div>",
+            "body { margin: 0 } div { height: 20px } \
+             #r { position: relative; top: 5px; left: 10px; margin-left: 100px; height: 100px } \
+             #abs { position: absolute; left: 0; top: 0; width: 30px } \
+             #corner { position: absolute; right: 10%; bottom: 0; width: 40px; height: 10px } \
+             #fixed { position: fixed; left: 0; right: 0; bottom: 0 }",
+        );
+        let [a, r, abs, corner, b, fixed] =
+            ["a", "r", "abs", "corner", "b", "fixed"].map(|id| rect(&document, &tree, id));
+        // The relative box moves, but its siblings stay where it was.
+        assert_eq!(r.min, Pos2::new(110.0, a.max.y + 5.0));
+        assert_eq!(b.min.y, a.max.y + 100.0);
+        // The absolute ones are placed in its padding box, and the fixed
+        // one in the viewport.
+        assert_eq!(abs.min, r.min);
+        assert_eq!(abs.width(), 30.0);
+        assert_eq!(corner.max, Pos2::new(r.max.x - 0.1 * r.width(), r.max.y));
+        assert_eq!(
+            fixed,
+            Rect::from_min_max(Pos2::new(0.0, 580.0), Pos2::new(800.0, 600.0))
+        );
+    }
+
+    #[test]
+    fn test_paint_order() {
+        let (document, tree) = layout(
+            "<div id=a><p id=top>top</p><p id=under>under</p></div><p id=flow>flow</p>",
+            "#top { position: relative; z-index: 2 } \
+             #under { position: absolute; z-index: -1 }",
+        );
+        let ids: Vec<&str> = tree
+            .paint_order()
+            .iter()
+            .filter_map(|fragment| {
+                document
+                    .element(fragment.node)
+                    .and_then(|element| element.get_attribute("id"))
+            })
+            .collect();
+        assert_eq!(ids, ["under", "a", "flow", "top"]);
+    }
+
+    #[test]
+    fn test_hit_test() {
+        let (document, tree) = layout(
+            "<div id=a>text</div><div id=over></div>\
+             <div id=clip><p id=inner></p></div><p id=hidden></p>",
+            "body { margin: 0 } p { margin: 0 } #a { height: 100px } \
+             #over { position: absolute; top: 50px; width: 100px; height: 100px } \
+             #clip { height: 10px; overflow: hidden } #inner { height: 100px } \
+             #hidden { height: 50px; visibility: hidden }",
+        );
+        let id = |name| document.get_element_by_id(name).unwrap();
+        let hit = |x, y| tree.hit_test(Pos2::new(x, y), Vec2::ZERO);
+        assert_eq!(hit(5.0, 5.0), document.children(id("a")).next());
+        assert_eq!(hit(500.0, 60.0), Some(id("a")));
+        assert_eq!(hit(50.0, 60.0), Some(id("over")));
+        assert_eq!(hit(500.0, 105.0), Some(id("inner")));
+        // Neither the clipped paragraph nor the hidden one is hit.
+        let body = document.get_elements_by_tag_name("body")[0];
+        assert_eq!(hit(500.0, 115.0), Some(body));
+        assert_eq!(hit(500.0, 1000.0), None);
+    }
+
+    #[test]
+    fn test_fixed_layers_stay_in_the_viewport() {
+        let (document, tree) = layout(
+            "<div id=bar></div><div id=page></div>",
+            "body { margin: 0 } #page { height: 2000px }              #bar { position: fixed; top: 0; width: 100%; height: 40px; z-index: -1 }",
+        );
+        let id = |name| document.get_element_by_id(name).unwrap();
+        let last = |order: Vec<(&Fragment, Rect)>| order.last().map(|(fragment, _)| fragment.node);
+        // The bar is painted over the page, whatever its z-index.
+        assert_eq!(last(tree.fixed_paint_order()), Some(id("bar")));
+        assert_eq!(tree.paint_order().last().map(|f| f.node), Some(id("bar")));
+        assert_eq!(last(tree.clipped_paint_order()), Some(id("page")));
+        // It is hit at the top of the viewport however far the page is
+        // scrolled, and the page under it is hit below it.
+        let scroll = Vec2::new(0.0, 1000.0);
+        assert_eq!(tree.hit_test(Pos2::new(5.0, 5.0), scroll), Some(id("bar")));
+        assert_eq!(
+            tree.hit_test(Pos2::new(5.0, 50.0), scroll),
+            Some(id("page"))
+        );
+    }
+}
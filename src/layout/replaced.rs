@@ -0,0 +1,115 @@
+use egui::Vec2;
+
+use super::ReplacedContent;
+
+/// The content size of a replaced element with the declared `width` and
+/// `height`, `None` where they are `auto`
+/// (<https://www.w3.org/TR/CSS21/visudet.html#inline-replaced-width>). Its
+/// `width` and `height` attributes stand in for `auto` ones, and what is
+/// still unknown comes from the natural size of its image, keeping its
+/// aspect ratio. An image that has not arrived, with no attributes, takes
+/// no room.
+pub(super) fn replaced_size(
+    content: &ReplacedContent,
+    width: Option<f32>,
+    height: Option<f32>,
+) -> Vec2 {
+    let width = width.or(content.width);
+    let height = height.or(content.height);
+    let natural = content.natural_size;
+    // The ratio of the image, or until it arrives, that of the attributes.
+    let ratio = natural
+        .map(|size| (size.x, size.y))
+        .or(content.width.zip(content.height))
+        .filter(|&(width, height)| width > 0.0 && height > 0.0)
+        .map(|(width, height)| width / height);
+
+    let width = width
+        .or_else(|| match height {
+            Some(height) => ratio
+                .map(|ratio| height * ratio)
+                .or(natural.map(|size| size.x)),
+            None => natural.map(|size| size.x),
+        })
+        .unwrap_or(0.0);
+    let height = height
+        .or_else(|| {
+            ratio
+                .map(|ratio| width / ratio)
+                .or(natural.map(|size| size.y))
+        })
+        .unwrap_or(0.0);
+    Vec2::new(width, height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::Stylesheet;
+    use crate::dom::Document;
+    use crate::layout::BoxTree;
+    use crate::layout::tests::{fonts, layout, rect};
+    use crate::style::{Cascade, Device, Origin};
+
+    #[test]
+    fn test_replaced_size() {
+        let content = ReplacedContent {
+            natural_size: Some(Vec2::new(200.0, 100.0)),
+            ..ReplacedContent::default()
+        };
+        assert_eq!(replaced_size(&content, None, None), Vec2::new(200.0, 100.0));
+        assert_eq!(
+            replaced_size(&content, Some(50.0), None),
+            Vec2::new(50.0, 25.0)
+        );
+        assert_eq!(
+            replaced_size(&content, None, Some(50.0)),
+            Vec2::new(100.0, 50.0)
+        );
+        let attributes = ReplacedContent {
+            width: Some(40.0),
+            height: Some(30.0),
+            ..content
+        };
+        assert_eq!(
+            replaced_size(&attributes, None, None),
+            Vec2::new(40.0, 30.0)
+        );
+        assert_eq!(
+            replaced_size(&attributes, Some(80.0), None),
+            Vec2::new(80.0, 30.0)
+        );
+        assert_eq!(
+            replaced_size(&ReplacedContent::default(), None, None),
+            Vec2::ZERO
+        );
+    }
+
+    #[test]
+    fn test_images_in_layout() {
+        let css = "body { margin: 0 } p { margin: 0 } \
+                   #b { display: block; margin: 0 auto; width: 100px }";
+        let html = "<p id=p>a <img id=a width=30 height=40> <img id=b src=b.png></p>";
+        let (document, tree) = layout(html, css);
+        let a = rect(&document, &tree, "a");
+        assert_eq!(a.size(), Vec2::new(30.0, 40.0));
+        assert!(a.min.x > 0.0);
+        assert_eq!(rect(&document, &tree, "b").size(), Vec2::new(100.0, 0.0));
+
+        // Once its image arrives, the block image takes its height from it.
+        let mut document = Document::parse(html);
+        let b = document.get_element_by_id("b").unwrap();
+        assert!(document.set_natural_size(b, (50, 20)));
+        let mut cascade = Cascade::for_document(&document, Device::default());
+        cascade.add_stylesheet(Origin::Author, &Stylesheet::parse(css));
+        let styles = cascade.compute(&document);
+        let tree = BoxTree::build(&document, &styles).layout(
+            Vec2::new(800.0, 600.0),
+            &mut fonts().with_pixels_per_point(1.0),
+        );
+        let b = rect(&document, &tree, "b");
+        assert_eq!(b.size(), Vec2::new(100.0, 40.0));
+        assert_eq!(b.min.x, 350.0);
+        assert!(b.min.y >= rect(&document, &tree, "a").max.y);
+    }
+}
@@ -0,0 +1,168 @@
+use std::collections::BTreeMap;
+
+use egui::{Pos2, Rect, Vec2};
+
+use super::position::positioning;
+use super::{Fragment, LayoutTree};
+use crate::dom::NodeId;
+
+impl LayoutTree {
+    /// How far the content of the scroll container of `node` is scrolled,
+    /// or zero if it is not scrolled or not a scroll container.
+    pub fn scroll_offset(&self, node: NodeId) -> Vec2 {
+        self.scroll_offsets
+            .get(&node)
+            .copied()
+            .unwrap_or(Vec2::ZERO)
+    }
+
+    /// Every scroll container that is scrolled, and how far.
+    pub fn scroll_offsets(&self) -> &BTreeMap<NodeId, Vec2> {
+        &self.scroll_offsets
+    }
+
+    /// Scrolls the content of the scroll container of `node` to `offset`,
+    /// as far as it overflows the container, and returns the offset it
+    /// ended up at. The content moves with it, so hit testing and painting
+    /// see it where it is shown. Fixed layers inside stay in the viewport.
+    pub fn scroll_to(&mut self, node: NodeId, offset: Vec2) -> Vec2 {
+        let current = self.scroll_offset(node);
+        let Some(container) = self
+            .root
+            .as_mut()
+            .and_then(|root| find_container(root, node))
+        else {
+            return Vec2::ZERO;
+        };
+        let offset = offset.clamp(Vec2::ZERO, max_offset(container, current));
+        for child in in_flow_children(&mut container.children) {
+            scroll_content(child, current - offset);
+        }
+        if offset == Vec2::ZERO {
+            self.scroll_offsets.remove(&node);
+        } else {
+            self.scroll_offsets.insert(node, offset);
+        }
+        offset
+    }
+
+    /// Scrolls the scroll containers at `point` of the viewport, with the
+    /// page scrolled by `scroll`, by `delta`, the innermost first, each as
+    /// far as it goes. Returns the part of `delta` none of them took, for
+    /// the page to scroll by, as nested egui `ScrollArea`s pass it on.
+    pub fn scroll_by(&mut self, point: Pos2, scroll: Vec2, mut delta: Vec2) -> Vec2 {
+        for node in self.scroll_containers_at(point, scroll) {
+            if delta == Vec2::ZERO {
+                break;
+            }
+            let current = self.scroll_offset(node);
+            delta -= self.scroll_to(node, current + delta) - current;
+        }
+        delta
+    }
+
+    /// The nodes of the scroll containers at `point` of the viewport, with
+    /// the page scrolled by `scroll`, topmost first, as
+    /// [`Self::hit_test`] finds them.
+    fn scroll_containers_at(&self, point: Pos2, scroll: Vec2) -> Vec<NodeId> {
+        let at = |order: Vec<(&Fragment, Rect)>, point: Pos2| {
+            order
+                .into_iter()
+                .rev()
+                .filter(|(fragment, clip)| {
+                    fragment.is_scroll_container()
+                        && clip.contains(point)
+                        && fragment.padding_rect().contains(point)
+                })
+                .map(|(fragment, _)| fragment.node)
+                .collect::<Vec<_>>()
+        };
+        let mut containers = at(self.fixed_paint_order(), point);
+        containers.extend(at(self.clipped_paint_order(), point + scroll));
+        containers
+    }
+}
+
+/// The scroll container fragment of `node` in `fragment`.
+fn find_container(fragment: &mut Fragment, node: NodeId) -> Option<&mut Fragment> {
+    if fragment.node == node && fragment.is_scroll_container() {
+        return Some(fragment);
+    }
+    fragment
+        .children
+        .iter_mut()
+        .find_map(|child| find_container(child, node))
+}
+
+/// How far `container`, scrolled by `current`, can be scrolled: as far as
+/// its content reaches past the end of its padding box.
+fn max_offset(container: &Fragment, current: Vec2) -> Vec2 {
+    let padding = container.padding_rect();
+    let content = container
+        .children
+        .iter()
+        .filter(|child| positioning(&child.kind, &child.style) != Some("fixed"))
+        .fold(padding, |area, child| {
+            area.union(child.overflow_rect().translate(current))
+        });
+    (content.max - padding.max).max(Vec2::ZERO)
+}
+
+/// The fragments of `children` that scroll with the box they are in:
+/// all but the fixed layers.
+fn in_flow_children(children: &mut [Fragment]) -> impl Iterator<Item = &mut Fragment> {
+    children
+        .iter_mut()
+        .filter(|child| positioning(&child.kind, &child.style) != Some("fixed"))
+}
+
+/// Moves `fragment` and what is inside it, but for fixed layers, by
+/// `delta`.
+fn scroll_content(fragment: &mut Fragment, delta: Vec2) {
+    fragment.rect = fragment.rect.translate(delta);
+    for child in in_flow_children(&mut fragment.children) {
+        scroll_content(child, delta);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::tests::{layout, rect};
+
+    #[test]
+    fn test_scroll_container() {
+        let (document, mut tree) = layout(
+            "<div id=a><p id=b></p></div><p id=c></p>",
+            "body { margin: 0 } p { margin: 0; height: 300px } \
+             #a { height: 100px; overflow: auto; border: 10px solid }",
+        );
+        let (a, b) = (
+            document.get_element_by_id("a").unwrap(),
+            document.get_element_by_id("b").unwrap(),
+        );
+        let top = rect(&document, &tree, "b").min.y;
+
+        // Scrolling stops at the end of the paragraph.
+        assert_eq!(
+            tree.scroll_to(a, Vec2::new(0.0, 500.0)),
+            Vec2::new(0.0, 200.0)
+        );
+        assert_eq!(rect(&document, &tree, "b").min.y, top - 200.0);
+        assert_eq!(tree.scroll_offset(a), Vec2::new(0.0, 200.0));
+        // The page below does not move.
+        assert_eq!(rect(&document, &tree, "c").min.y, 120.0);
+        assert_eq!(tree.hit_test(Pos2::new(50.0, 50.0), Vec2::ZERO), Some(b));
+
+        // What #a cannot take is left for the page.
+        let left = tree.scroll_by(Pos2::new(50.0, 50.0), Vec2::ZERO, Vec2::new(0.0, -250.0));
+        assert_eq!(left, Vec2::new(0.0, -50.0));
+        assert_eq!(rect(&document, &tree, "b").min.y, top);
+        assert!(tree.scroll_offsets().is_empty());
+
+        // With the page scrolled past #a, the point is over #c, which does not
+        // scroll.
+        let left = tree.scroll_by(Pos2::new(50.0, 50.0), Vec2::new(0.0, 200.0), Vec2::Y);
+        assert_eq!(left, Vec2::Y);
+    }
+}
@@ -0,0 +1,353 @@
+use std::ops::Range;
+
+use egui::Vec2;
+
+use super::block::{ContainingBlock, LayoutContext};
+use super::length::resolve;
+use super::{Fragment, LayoutBox, TableGrid};
+use crate::css::{ComponentValue, CssToken};
+use crate::dom::TableCell;
+use crate::style::ComputedStyle;
+
+/// The widths the columns of a table need: at least `min` each, for the
+/// content of their cells not to overflow, and `max` each for no line in
+/// them to break. Both include the borders and padding of the cells.
+#[derive(Debug, Clone, Default)]
+struct Columns {
+    min: Vec<f32>,
+    max: Vec<f32>,
+}
+
+impl Columns {
+    /// The widths of columns that fill `available`, after the automatic
+    /// table layout of CSS 2.1
+    /// (<https://www.w3.org/TR/CSS21/tables.html#auto-table-layout>): each
+    /// column gets its min-content width and shares the rest in proportion
+    /// to how much more it would take, up to its max-content width, and in
+    /// proportion to that width beyond it.
+    fn distribute(&self, available: f32) -> Vec<f32> {
+        let min_total: f32 = self.min.iter().sum();
+        let max_total: f32 = self.max.iter().sum();
+        if available >= max_total {
+            let extra = available - max_total;
+            let count = self.max.len() as f32;
+            self.max
+                .iter()
+                .map(|max| {
+                    let share = if max_total > 0.0 {
+                        max / max_total
+                    } else {
+                        1.0 / count
+                    };
+                    max + extra * share
+                })
+                .collect()
+        } else if available > min_total {
+            let ratio = (available - min_total) / (max_total - min_total);
+            self.min
+                .iter()
+                .zip(&self.max)
+                .map(|(min, max)| min + (max - min) * ratio)
+                .collect()
+        } else {
+            self.min.clone()
+        }
+    }
+}
+
+impl LayoutContext<'_, '_> {
+    /// The width of the grid of the table `layout_box`, given its declared
+    /// `width` if not `auto`, in a containing block `containing_width`
+    /// wide: never less than its columns need, and as wide as they would
+    /// be without breaking lines if there is room, when it is `auto`.
+    pub(super) fn table_width(
+        &mut self,
+        layout_box: &LayoutBox,
+        grid: &TableGrid,
+        containing_width: f32,
+        width: Option<f32>,
+    ) -> f32 {
+        let min = self.table_content_width(layout_box, grid, true);
+        if let Some(width) = width {
+            return width.max(min);
+        }
+        let max = self.table_content_width(layout_box, grid, false);
+        let (border, padding) = self.border_and_padding(layout_box, containing_width);
+        let margins = self.margin(layout_box, "margin-left", containing_width)
+            + self.margin(layout_box, "margin-right", containing_width);
+        let available = containing_width - border.horizontal() - padding.horizontal() - margins;
+        max.min(available).max(min)
+    }
+
+    /// The min-content width of the grid of the table `layout_box` if
+    /// `min`, and its max-content width if not, with the spacing between
+    /// and around its columns.
+    pub(super) fn table_content_width(
+        &mut self,
+        layout_box: &LayoutBox,
+        grid: &TableGrid,
+        min: bool,
+    ) -> f32 {
+        if grid.columns == 0 {
+            return 0.0;
+        }
+        let columns = self.columns(layout_box, grid);
+        let widths = if min { columns.min } else { columns.max };
+        let spacing = self.border_spacing(&layout_box.style).x;
+        widths.iter().sum::<f32>() + spacing * (grid.columns as f32 + 1.0)
+    }
+
+    /// Lays out the caption and the cells of the table `layout_box` from
+    /// `top` down in `containing`, which is as wide as its grid. The
+    /// caption goes above the grid, and each cell is as wide as the
+    /// columns it spans and as tall as its rows, which are as tall as
+    /// their tallest cell. Returns the fragments of the caption and cells,
+    /// and the height they take.
+    pub(super) fn layout_table(
+        &mut self,
+        layout_box: &LayoutBox,
+        grid: &TableGrid,
+        containing: ContainingBlock,
+        top: f32,
+    ) -> (Vec<Fragment>, f32) {
+        let spacing = self.border_spacing(&layout_box.style);
+        let mut fragments = Vec::new();
+        let mut y = top;
+        let mut children = layout_box.children.iter();
+        if grid.caption
+            && let Some(caption) = children.next()
+        {
+            let margin_top = self.margin(caption, "margin-top", containing.width);
+            let containing = ContainingBlock {
+                height: None,
+                ..containing
+            };
+            let fragment = self.layout_block(caption, containing, y + margin_top);
+            y = fragment.margin_rect().bottom();
+            fragments.push(fragment);
+        }
+        if grid.cells.is_empty() {
+            return (fragments, y - top);
+        }
+
+        let columns = self.columns(layout_box, grid);
+        let widths = columns.distribute(containing.width - spacing.x * (grid.columns as f32 + 1.0));
+        let mut left = containing.x + spacing.x;
+        let lefts: Vec<f32> = widths
+            .iter()
+            .map(|width| {
+                let column_left = left;
+                left += width + spacing.x;
+                column_left
+            })
+            .collect();
+
+        // Each cell is laid out at the top first, to find out how tall the
+        // rows must be.
+        let mut cells: Vec<(&TableCell, Fragment)> = Vec::new();
+        for (cell_box, cell) in children.zip(&grid.cells) {
+            let span = span(cell.column, cell.column_span, grid.columns);
+            let cell_containing = ContainingBlock {
+                x: lefts.get(cell.column).copied().unwrap_or(left),
+                width: spanned(&widths, span, spacing.x),
+                height: None,
+            };
+            cells.push((cell, self.layout_block(cell_box, cell_containing, 0.0)));
+        }
+        let mut heights = vec![0.0; grid.rows];
+        let mut by_span: Vec<&(&TableCell, Fragment)> = cells.iter().collect();
+        by_span.sort_by_key(|(cell, _)| cell.row_span);
+        for (cell, fragment) in by_span {
+            let span = span(cell.row, cell.row_span, grid.rows);
+            let gaps = spacing.y * (span.len().saturating_sub(1)) as f32;
+            if let Some(rows) = heights.get_mut(span) {
+                widen(rows, fragment.rect.height() - gaps);
+            }
+        }
+
+        let mut row_top = y + spacing.y;
+        let tops: Vec<f32> = heights
+            .iter()
+            .map(|height| {
+                let top = row_top;
+                row_top += height + spacing.y;
+                top
+            })
+            .collect();
+        for (cell, mut fragment) in cells {
+            let span = span(cell.row, cell.row_span, grid.rows);
+            fragment.translate(Vec2::new(
+                0.0,
+                tops.get(cell.row).copied().unwrap_or(row_top),
+            ));
+            stretch_cell(&mut fragment, spanned(&heights, span, spacing.y));
+            fragments.push(fragment);
+        }
+        (fragments, row_top - top)
+    }
+
+    /// The widths the columns of the table `layout_box` need. A cell that
+    /// spans several columns widens them evenly if they are not wide
+    /// enough for it together, once the cells that span fewer columns are
+    /// in.
+    fn columns(&mut self, layout_box: &LayoutBox, grid: &TableGrid) -> Columns {
+        let spacing = self.border_spacing(&layout_box.style).x;
+        let mut cells: Vec<(&TableCell, f32, f32)> = layout_box
+            .children
+            .iter()
+            .skip(usize::from(grid.caption))
+            .zip(&grid.cells)
+            .map(|(cell_box, cell)| {
+                let (min, max) = self.cell_widths(cell_box);
+                (cell, min, max)
+            })
+            .collect();
+        cells.sort_by_key(|(cell, ..)| cell.column_span);
+
+        let mut columns = Columns {
+            min: vec![0.0; grid.columns],
+            max: vec![0.0; grid.columns],
+        };
+        for (cell, min, max) in cells {
+            let span = span(cell.column, cell.column_span, grid.columns);
+            let gaps = spacing * (span.len().saturating_sub(1)) as f32;
+            if let Some(columns) = columns.min.get_mut(span.clone()) {
+                widen(columns, min - gaps);
+            }
+            if let Some(columns) = columns.max.get_mut(span) {
+                widen(columns, max - gaps);
+            }
+        }
+        for (min, max) in columns.min.iter().zip(&mut columns.max) {
+            *max = max.max(*min);
+        }
+        columns
+    }
+
+    /// The min-content and max-content widths of the cell `cell_box`, with
+    /// its borders and padding. A declared width is the least it takes.
+    fn cell_widths(&mut self, cell_box: &LayoutBox) -> (f32, f32) {
+        let (border, padding) = self.border_and_padding(cell_box, 0.0);
+        let edges = border.horizontal() + padding.horizontal();
+        let min = self.min_content_width(cell_box) + edges;
+        let max = self.max_content_width(cell_box) + edges;
+        match self.size(cell_box, "width", None) {
+            Some(width) => {
+                let width = if cell_box.style.keyword("box-sizing") == Some("border-box") {
+                    width
+                } else {
+                    width + edges
+                };
+                (min.max(width), min.max(width))
+            }
+            None => (min, max),
+        }
+    }
+
+    /// The horizontal and vertical `border-spacing` of `style`.
+    fn border_spacing(&self, style: &ComputedStyle) -> Vec2 {
+        let lengths: Vec<f32> = style
+            .value("border-spacing")
+            .unwrap_or_default()
+            .iter()
+            .filter(|component| !matches!(component, ComponentValue::Token(CssToken::Whitespace)))
+            .filter_map(|component| resolve(component, style, None, self.viewport))
+            .collect();
+        match lengths.as_slice() {
+            [both] => Vec2::splat(*both),
+            [horizontal, vertical] => Vec2::new(*horizontal, *vertical),
+            _ => Vec2::ZERO,
+        }
+    }
+}
+
+/// The rows or columns a cell that starts at `start` and spans `count` of
+/// them covers, in a grid of `total`.
+fn span(start: usize, count: usize, total: usize) -> Range<usize> {
+    start..(start + count).min(total)
+}
+
+/// The width of the columns, or height of the rows, `span` of `sizes`,
+/// with the spacing between them.
+fn spanned(sizes: &[f32], span: Range<usize>, spacing: f32) -> f32 {
+    let gaps = spacing * (span.len().saturating_sub(1)) as f32;
+    sizes.get(span).unwrap_or_default().iter().sum::<f32>() + gaps
+}
+
+/// Widens `sizes` evenly so that together they are at least `total`.
+fn widen(sizes: &mut [f32], total: f32) {
+    let missing = total - sizes.iter().sum::<f32>();
+    if missing > 0.0 && !sizes.is_empty() {
+        let share = missing / sizes.len() as f32;
+        for size in sizes {
+            *size += share;
+        }
+    }
+}
+
+/// Makes the cell `fragment` `height` tall, with its content where its
+/// `vertical-align` puts it: at the top, in the middle or at the bottom.
+fn stretch_cell(fragment: &mut Fragment, height: f32) {
+    let extra = height - fragment.rect.height();
+    if extra <= 0.0 {
+        return;
+    }
+    let offset = match fragment.style.keyword("vertical-align") {
+        Some("middle") => extra / 2.0,
+        Some("bottom") => extra,
+        _ => 0.0,
+    };
+    for child in &mut fragment.children {
+        child.translate(Vec2::new(0.0, offset));
+    }
+    fragment.rect.max.y += extra;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::tests::{layout, rect, text_width};
+
+    #[test]
+    fn test_column_widths() {
+        let columns = Columns {
+            min: vec![10.0, 20.0],
+            max: vec![30.0, 100.0],
+        };
+        assert_eq!(columns.distribute(20.0), [10.0, 20.0]);
+        assert_eq!(columns.distribute(80.0), [20.0, 60.0]);
+        assert_eq!(columns.distribute(260.0), [60.0, 200.0]);
+    }
+
+    #[test]
+    fn test_table_layout() {
+        let (document, tree) = layout(
+            "<table id=t><caption id=cap>caption</caption>\
+             <tr><td id=a>a</td><td id=b rowspan=2>b</td></tr>\
+             <tr><td id=c style='height: 50px'>c</td></tr>\
+             <tr><td id=d colspan=2>a longer cell</td></tr></table>",
+            "body { margin: 0 } table { border-spacing: 4px 2px } \
+             td { padding: 0; vertical-align: top } #b { vertical-align: middle }",
+        );
+        let [t, cap, a, b, c, d] =
+            ["t", "cap", "a", "b", "c", "d"].map(|id| rect(&document, &tree, id));
+        // The table shrinks to fit its widest row.
+        assert_eq!(t.width(), d.width() + 2.0 * 4.0);
+        assert!((d.width() - text_width("a longer cell")).abs() < 1.0);
+        assert_eq!(cap.width(), t.width());
+        assert_eq!(a.min, egui::Pos2::new(4.0, cap.max.y + 2.0));
+        assert_eq!(b.min.x, a.max.x + 4.0);
+        assert_eq!(d.width(), b.max.x - a.min.x);
+        assert_eq!(c.min.y, a.max.y + 2.0);
+        assert_eq!(c.height(), 50.0);
+        // The cell spanning both rows is as tall as they are.
+        assert_eq!((b.min.y, b.max.y), (a.min.y, c.max.y));
+        let text = tree
+            .fragments()
+            .find(|fragment| fragment.kind == crate::layout::BoxKind::Text("b".to_owned()))
+            .unwrap()
+            .rect;
+        assert_eq!(text.center().y, b.center().y);
+        assert_eq!(t.max.y, d.max.y + 2.0);
+    }
+}
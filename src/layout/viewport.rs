@@ -0,0 +1,33 @@
+use egui::Vec2;
+
+use super::LayoutTree;
+
+impl LayoutTree {
+    /// The size of the page: the viewport it was laid out for, or more if
+    /// the content reaches further down or right.
+    pub fn content_size(&self) -> Vec2 {
+        self.content_size
+    }
+
+    /// The size of the viewport the tree was laid out for, which lengths
+    /// such as `50vw` are relative to.
+    pub fn viewport(&self) -> Vec2 {
+        self.viewport
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::tests::layout;
+
+    #[test]
+    fn test_content_size() {
+        let (_, tree) = layout(
+            "<div></div><div></div><div></div>",
+            "body { margin: 0 } div { height: 500px }",
+        );
+        assert_eq!(tree.viewport(), Vec2::new(800.0, 600.0));
+        assert_eq!(tree.content_size(), Vec2::new(800.0, 1500.0));
+    }
+}
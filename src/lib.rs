@@ -1,7 +1,18 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
 mod app;
+pub mod content_blocker;
+pub mod css;
+pub mod dom;
+pub mod extension;
 pub mod html_tokenizer;
 pub mod http;
+pub mod i18n;
+pub mod images;
+pub mod layout;
+pub mod paint;
+pub mod resources;
+pub mod selectors;
+pub mod style;
 
 pub use app::TemplateApp;
@@ -0,0 +1,1043 @@
+//! Painting: what a laid out page looks like, drawn with egui.
+//!
+//! [`DisplayList::build`] goes through the fragments of a [`LayoutTree`]
+//! in paint order and records what to draw for each of them, clipped to
+//! the boxes around it whose `overflow` is not `visible`. The list is
+//! built once per layout, or scroll of a box, and [`DisplayList::paint`] draws it with an egui
+//! [`Painter`] every frame, with the page wherever it is on the screen.
+//! Only the items on screen are drawn: the list keeps them by band of the
+//! page, so a frame of a very long page costs about as much as one of a
+//! short page. The items of `position: fixed` layers are drawn apart, by
+//! [`DisplayList::paint_fixed`], where they are in the viewport.
+//! Boxes paint their background and border behind their content, and the
+//! background of the root element, or of the `<body>`, covers the whole
+//! canvas. Images are drawn from an [`ImageCache`], with a placeholder
+//! until they arrive and a frame around their `alt` text if they cannot be
+//! shown. The matches of find in page are [`Highlight`]s over the text runs
+//! they are in, drawn over the page.
+
+use std::collections::HashMap;
+
+use egui::epaint::text::FontsView;
+use egui::text::{LayoutJob, TextFormat};
+use egui::{Color32, CornerRadius, FontId, Painter, Pos2, Rect, Shape, Stroke, StrokeKind, Vec2};
+
+use crate::css::{ComponentValue, CssToken};
+use crate::dom::{Document, Node, NodeId, TextMatch};
+use crate::images::{ImageCache, ImageState};
+use crate::layout::{BoxKind, Fragment, LayoutTree, font_id};
+use crate::style::ComputedStyle;
+
+/// The color of the canvas the page is painted on, under the background
+/// of the page.
+pub const CANVAS: Color32 = Color32::WHITE;
+
+/// The height of the bands of the page a [`DisplayList`] keeps its items
+/// by, in pixels.
+const BAND_HEIGHT: f32 = 512.0;
+
+/// The most bands a [`DisplayList`] has. What is further down the page, as
+/// is the rest of a box with a huge `height`, is in the last one.
+const MAX_BANDS: usize = 1 << 14;
+
+/// The color of the matches of find in page, and of the current one.
+const HIGHLIGHT: Color32 = Color32::from_rgba_unmultiplied_const(255, 235, 0, 110);
+const CURRENT_HIGHLIGHT: Color32 = Color32::from_rgba_unmultiplied_const(255, 140, 0, 150);
+
+/// The color of an image that is still loading.
+const IMAGE_PLACEHOLDER: Color32 = Color32::from_gray(0xee);
+
+/// The color of the frame of an image that cannot be shown.
+const BROKEN_IMAGE_FRAME: Color32 = Color32::from_gray(0xaa);
+
+/// Something to draw for the fragment of a node, in page coordinates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisplayItem {
+    pub node: NodeId,
+
+    /// The border box of the fragment, which the item draws within.
+    pub rect: Rect,
+
+    /// What the item is clipped to, or [`Rect::EVERYTHING`] if nothing
+    /// clips it.
+    pub clip: Rect,
+    pub kind: DisplayKind,
+
+    /// Whether the item is in a `position: fixed` layer, so that its
+    /// coordinates are in the viewport rather than on the page.
+    pub fixed: bool,
+}
+
+/// What a [`DisplayItem`] draws.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DisplayKind {
+    Box(BoxDecoration),
+    Text(TextRun),
+    Image(ImageItem),
+}
+
+/// The background and border of a box whose border box is `rect`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoxDecoration {
+    pub rect: Rect,
+
+    /// The `background-color`, which may be transparent.
+    pub background: Color32,
+
+    /// The top, right, bottom and left sides of the border.
+    pub border: [BorderSide; 4],
+
+    /// The radii of the corners, clockwise from the top left one.
+    pub radii: [f32; 4],
+}
+
+/// A side of a border, as wide as the border of the fragment.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BorderSide {
+    pub width: f32,
+    pub color: Color32,
+    pub style: BorderStyle,
+}
+
+/// How a side of a border is drawn, from its `border-*-style`. `groove`,
+/// `ridge`, `inset` and `outset` are drawn solid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BorderStyle {
+    Solid,
+    Dashed,
+    Dotted,
+    Double,
+}
+
+impl BorderStyle {
+    fn parse(keyword: &str) -> Self {
+        match keyword.to_ascii_lowercase().as_str() {
+            "dashed" => Self::Dashed,
+            "dotted" => Self::Dotted,
+            "double" => Self::Double,
+            _ => Self::Solid,
+        }
+    }
+}
+
+impl BoxDecoration {
+    /// The decoration of `fragment`, laid out for `viewport`, or `None` if
+    /// it has neither a background nor a border to draw. Text, and boxes
+    /// that share the style of their parent, have none, and the box whose
+    /// background went to the canvas, which `background` is false for,
+    /// only draws its border.
+    fn new(fragment: &Fragment, viewport: Vec2, background: bool) -> Option<Self> {
+        if !matches!(
+            fragment.kind,
+            BoxKind::Block
+                | BoxKind::Inline
+                | BoxKind::InlineBlock
+                | BoxKind::Replaced(_)
+                | BoxKind::Table(_)
+                | BoxKind::TableCell
+        ) {
+            return None;
+        }
+        let style = &fragment.style;
+        let side = |name: &str, width| BorderSide {
+            width,
+            color: style
+                .color(&format!("border-{name}-color"))
+                .map_or(Color32::BLACK, Color32::from),
+            style: style
+                .keyword(&format!("border-{name}-style"))
+                .map_or(BorderStyle::Solid, BorderStyle::parse),
+        };
+        let edges = fragment.border;
+        let decoration = Self {
+            rect: fragment.rect,
+            background: background
+                .then(|| style.color("background-color"))
+                .flatten()
+                .map_or(Color32::TRANSPARENT, Color32::from),
+            border: [
+                side("top", edges.top),
+                side("right", edges.right),
+                side("bottom", edges.bottom),
+                side("left", edges.left),
+            ],
+            radii: fragment.border_radii(viewport),
+        };
+        let visible =
+            decoration.background.a() > 0 || decoration.border.iter().any(|side| side.width > 0.0);
+        visible.then_some(decoration)
+    }
+
+    /// Draws the background and then the border with `painter`, moved by
+    /// `origin`. A border that is solid and the same all around follows the
+    /// rounded corners; other borders are drawn side by side, with square
+    /// corners.
+    fn paint(&self, painter: &Painter, origin: Vec2) {
+        let outer = self.rect.translate(origin);
+        let [top_left, top_right, bottom_right, bottom_left] = self
+            .radii
+            .map(|radius| radius.round().clamp(0.0, 255.0) as u8);
+        let radius = CornerRadius {
+            nw: top_left,
+            ne: top_right,
+            se: bottom_right,
+            sw: bottom_left,
+        };
+        if self.background.a() > 0 {
+            painter.rect_filled(outer, radius, self.background);
+        }
+        let [top, right, bottom, left] = self.border;
+        if top.width > 0.0
+            && top.style == BorderStyle::Solid
+            && self.border.iter().all(|side| *side == top)
+        {
+            painter.rect_stroke(
+                outer,
+                radius,
+                Stroke::new(top.width, top.color),
+                StrokeKind::Inside,
+            );
+            return;
+        }
+        let inner = Rect::from_min_max(
+            outer.min + Vec2::new(left.width, top.width),
+            outer.max - Vec2::new(right.width, bottom.width),
+        );
+        let sides = [
+            (
+                top,
+                [outer.left_top(), outer.right_top()],
+                [inner.right_top(), inner.left_top()],
+            ),
+            (
+                right,
+                [outer.right_top(), outer.right_bottom()],
+                [inner.right_bottom(), inner.right_top()],
+            ),
+            (
+                bottom,
+                [outer.right_bottom(), outer.left_bottom()],
+                [inner.left_bottom(), inner.right_bottom()],
+            ),
+            (
+                left,
+                [outer.left_bottom(), outer.left_top()],
+                [inner.left_top(), inner.left_bottom()],
+            ),
+        ];
+        for (side, [outer_start, outer_end], [inner_end, inner_start]) in sides {
+            side.paint(painter, [outer_start, outer_end, inner_end, inner_start]);
+        }
+    }
+}
+
+impl BorderSide {
+    /// Draws the side with `painter` over the quadrilateral `corners`: its
+    /// two outer corners, and then its two inner ones, the other way round.
+    fn paint(&self, painter: &Painter, corners: [Pos2; 4]) {
+        if self.width <= 0.0 || self.color.a() == 0 {
+            return;
+        }
+        let [outer_start, outer_end, inner_end, inner_start] = corners;
+        let polygon = |points: Vec<Pos2>| Shape::convex_polygon(points, self.color, Stroke::NONE);
+        match self.style {
+            BorderStyle::Solid => {
+                painter.add(polygon(corners.to_vec()));
+            }
+            BorderStyle::Double => {
+                // Two lines, each a third of the width, with a gap between.
+                let third = |from: Pos2, to: Pos2| from.lerp(to, 1.0 / 3.0);
+                painter.add(polygon(vec![
+                    outer_start,
+                    outer_end,
+                    third(outer_end, inner_end),
+                    third(outer_start, inner_start),
+                ]));
+                painter.add(polygon(vec![
+                    third(inner_start, outer_start),
+                    third(inner_end, outer_end),
+                    inner_end,
+                    inner_start,
+                ]));
+            }
+            BorderStyle::Dashed | BorderStyle::Dotted => {
+                let line = [
+                    outer_start.lerp(inner_start, 0.5),
+                    outer_end.lerp(inner_end, 0.5),
+                ];
+                let shapes = if self.style == BorderStyle::Dashed {
+                    Shape::dashed_line(
+                        &line,
+                        Stroke::new(self.width, self.color),
+                        3.0 * self.width,
+                        3.0 * self.width,
+                    )
+                } else {
+                    Shape::dotted_line(&line, self.color, 2.0 * self.width, self.width / 2.0)
+                };
+                painter.extend(shapes);
+            }
+        }
+    }
+}
+
+/// A run of text in one style, on a line whose top left corner is at
+/// `pos`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextRun {
+    pub pos: Pos2,
+    pub text: String,
+
+    /// The font the text was measured with in layout, which has its
+    /// `font-size` and `font-family`.
+    pub font: FontId,
+    pub color: Color32,
+
+    /// Whether the `font-weight` is 600 or more.
+    pub bold: bool,
+
+    /// Whether the `font-style` is `italic` or `oblique`.
+    pub italic: bool,
+
+    /// Whether `text-decoration-line` draws a line under the text.
+    pub underline: bool,
+
+    /// Whether `text-decoration-line` draws a line through the text.
+    pub line_through: bool,
+}
+
+/// The image of an `<img>`, in its content box `rect`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageItem {
+    pub rect: Rect,
+
+    /// The URL of the image, or `None` if it has none that resolves.
+    pub url: Option<String>,
+
+    /// Its `alt` text, shown in its place if it cannot be shown.
+    pub alt: Option<TextRun>,
+}
+
+impl ImageItem {
+    /// Draws the image from `images` with `painter`, moved by `origin`: a
+    /// placeholder while it loads, and a frame around its `alt` text if it
+    /// is broken, or was never loaded.
+    fn paint(&self, painter: &Painter, origin: Vec2, images: &ImageCache) {
+        let rect = self.rect.translate(origin);
+        match self.url.as_deref().and_then(|url| images.get(url)) {
+            Some(ImageState::Loaded(texture)) => {
+                let uv = Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0));
+                painter.image(texture.id(), rect, uv, Color32::WHITE);
+            }
+            Some(ImageState::Loading) => {
+                painter.rect_filled(rect, 0.0, IMAGE_PLACEHOLDER);
+            }
+            Some(ImageState::Broken) | None => {
+                painter.rect_stroke(
+                    rect,
+                    0.0,
+                    Stroke::new(1.0, BROKEN_IMAGE_FRAME),
+                    StrokeKind::Inside,
+                );
+                if let Some(alt) = &self.alt {
+                    let clip = rect.intersect(painter.clip_rect());
+                    alt.paint(&painter.with_clip_rect(clip), origin);
+                }
+            }
+        }
+    }
+}
+
+impl TextRun {
+    /// The run of the text `text` in the style `style`, at `pos`.
+    fn new(pos: Pos2, text: &str, style: &ComputedStyle) -> Self {
+        let has_keyword = |property, keywords: &[&str]| {
+            style.value(property).is_some_and(|value| {
+                value.iter().any(|component| {
+                    matches!(
+                        component,
+                        ComponentValue::Token(CssToken::Ident(keyword))
+                            if keywords.iter().any(|wanted| keyword.eq_ignore_ascii_case(wanted))
+                    )
+                })
+            })
+        };
+        Self {
+            pos,
+            text: text.to_owned(),
+            font: font_id(style),
+            color: style.color("color").map_or(Color32::BLACK, Color32::from),
+            bold: style.font_weight() >= 600.0,
+            italic: has_keyword("font-style", &["italic", "oblique"]),
+            underline: has_keyword("text-decoration-line", &["underline"]),
+            line_through: has_keyword("text-decoration-line", &["line-through"]),
+        }
+    }
+
+    /// Draws the run with `painter`, moved by `origin`. egui's fonts come
+    /// in one weight, so bold text is drawn twice, a little apart, and
+    /// italic text is slanted. Decoration lines are as thick as a
+    /// tenth of the font size, and at least a pixel.
+    fn paint(&self, painter: &Painter, origin: Vec2) {
+        let line = |drawn| {
+            if drawn {
+                Stroke::new((self.font.size / 10.0).max(1.0), self.color)
+            } else {
+                Stroke::NONE
+            }
+        };
+        let job = LayoutJob::single_section(
+            self.text.clone(),
+            TextFormat {
+                font_id: self.font.clone(),
+                color: self.color,
+                italics: self.italic,
+                underline: line(self.underline),
+                strikethrough: line(self.line_through),
+                ..TextFormat::default()
+            },
+        );
+        let galley = painter.layout_job(job);
+        let pos = self.pos + origin;
+        if self.bold {
+            let offset = (self.font.size / 20.0).max(0.5);
+            painter.galley(pos + Vec2::new(offset, 0.0), galley.clone(), self.color);
+        }
+        painter.galley(pos, galley, self.color);
+    }
+}
+
+/// The items a page is painted with, back to front.
+#[derive(Debug, Clone)]
+pub struct DisplayList {
+    canvas: Color32,
+    items: Vec<DisplayItem>,
+
+    /// For each band of the page, from the top, the indices of the items
+    /// that reach into it, in order. Fixed items are in none.
+    bands: Vec<Vec<usize>>,
+}
+
+impl Default for DisplayList {
+    fn default() -> Self {
+        Self {
+            canvas: CANVAS,
+            items: Vec::new(),
+            bands: Vec::new(),
+        }
+    }
+}
+
+impl DisplayList {
+    /// The items that paint `tree`, in its paint order. Fragments that are
+    /// clipped out altogether, or whose `visibility` hides them, paint
+    /// nothing. Image URLs are resolved against the URL of `document`.
+    pub fn build(tree: &LayoutTree, document: &Document) -> Self {
+        let (canvas, canvas_node) = canvas(tree, document);
+        let scrolled = tree
+            .clipped_paint_order()
+            .into_iter()
+            .map(|(f, c)| (f, c, false));
+        let fixed = tree
+            .fixed_paint_order()
+            .into_iter()
+            .map(|(f, c)| (f, c, true));
+        let items = scrolled
+            .chain(fixed)
+            .filter(|(fragment, clip, _)| clip.is_positive() && fragment.is_visible())
+            .flat_map(|(fragment, clip, fixed)| {
+                let background = Some(fragment.node) != canvas_node;
+                BoxDecoration::new(fragment, tree.viewport(), background)
+                    .map(DisplayKind::Box)
+                    .into_iter()
+                    .chain(display_kind(fragment, document))
+                    .map(move |kind| DisplayItem {
+                        node: fragment.node,
+                        rect: fragment.rect,
+                        clip,
+                        kind,
+                        fixed,
+                    })
+            })
+            .collect();
+        let mut list = Self {
+            canvas,
+            items,
+            bands: Vec::new(),
+        };
+        let last = band(tree.content_size().y, MAX_BANDS - 1);
+        list.bands.resize_with(last + 1, Vec::new);
+        for (index, item) in list.items.iter().enumerate() {
+            let Some(bands) = bands(item.visible_rect(), last).filter(|_| !item.fixed) else {
+                continue;
+            };
+            for band in list.bands.get_mut(bands).into_iter().flatten() {
+                band.push(index);
+            }
+        }
+        list
+    }
+
+    /// The color of the canvas, under the items.
+    pub fn canvas(&self) -> Color32 {
+        self.canvas
+    }
+
+    /// The items, back to front.
+    pub fn items(&self) -> &[DisplayItem] {
+        &self.items
+    }
+
+    /// The items that draw something in `rect` of the page, back to
+    /// front, leaving out the fixed ones.
+    pub fn visible_items(&self, rect: Rect) -> impl Iterator<Item = &DisplayItem> + '_ {
+        let mut indices: Vec<usize> = self
+            .bands
+            .len()
+            .checked_sub(1)
+            .and_then(|last| bands(rect, last))
+            .and_then(|bands| self.bands.get(bands))
+            .into_iter()
+            .flatten()
+            .flatten()
+            .copied()
+            .collect();
+        indices.sort_unstable();
+        indices.dedup();
+        indices
+            .into_iter()
+            .filter_map(|index| self.items.get(index))
+            .filter(move |item| item.visible_rect().intersects(rect))
+    }
+
+    /// The highlights of `matches`, ranges of the text of `document`, over
+    /// the text runs that draw them, measured with `fonts`. Matches in text
+    /// that is not painted, such as that of an element with `display:
+    /// none`, or that is clipped out, are left out.
+    pub fn highlights(
+        &self,
+        document: &Document,
+        matches: &[TextMatch],
+        fonts: &mut FontsView<'_>,
+    ) -> Vec<Highlight> {
+        let mut by_node: HashMap<NodeId, Vec<usize>> = HashMap::new();
+        for (index, found) in matches.iter().enumerate() {
+            by_node.entry(found.node).or_default().push(index);
+        }
+        let empty = Highlight {
+            rects: Vec::new(),
+            fixed: false,
+        };
+        let mut highlights = vec![empty; matches.len()];
+        // Where the runs of each text node seen so far end in its text.
+        let mut cursors: HashMap<NodeId, usize> = HashMap::new();
+        for item in &self.items {
+            let (DisplayKind::Text(run), Some(indices), Node::Text(text)) = (
+                &item.kind,
+                by_node.get(&item.node),
+                document.node(item.node),
+            ) else {
+                continue;
+            };
+            let cursor = cursors.entry(item.node).or_default();
+            let Some(offsets) = source_offsets(&run.text, text, cursor) else {
+                continue;
+            };
+            let mut width = |end: usize| {
+                let text = run.text.get(..end).unwrap_or_default().to_owned();
+                fonts
+                    .layout_no_wrap(text, run.font.clone(), Color32::PLACEHOLDER)
+                    .size()
+                    .x
+            };
+            for &index in indices {
+                let Some(found) = matches.get(index) else {
+                    continue;
+                };
+                let mut inside = offsets
+                    .iter()
+                    .filter(|(_, source)| found.range.contains(source))
+                    .map(|&(index, _)| index);
+                let Some(start) = inside.next() else {
+                    continue;
+                };
+                let last = inside.next_back().unwrap_or(start);
+                let end = last
+                    + run
+                        .text
+                        .get(last..)
+                        .and_then(|rest| rest.chars().next())
+                        .map_or(0, char::len_utf8);
+                let rect = Rect::from_x_y_ranges(
+                    item.rect.min.x + width(start)..=item.rect.min.x + width(end),
+                    item.rect.y_range(),
+                )
+                .intersect(item.clip);
+                if let (true, Some(highlight)) = (rect.is_positive(), highlights.get_mut(index)) {
+                    highlight.rects.push(rect);
+                    highlight.fixed |= item.fixed;
+                }
+            }
+        }
+        highlights.retain(|highlight| !highlight.rects.is_empty());
+        highlights
+    }
+
+    /// Draws the items that scroll with the page with `painter`, with the
+    /// top left corner of the page at `origin` on the screen, and the
+    /// images from `images`. Only the items within the clip rectangle of
+    /// `painter` are drawn.
+    pub fn paint(&self, painter: &Painter, origin: Vec2, images: &ImageCache) {
+        let visible = painter.clip_rect().translate(-origin);
+        for item in self.visible_items(visible) {
+            item.paint(painter, origin, images);
+        }
+    }
+
+    /// Draws the items of the fixed layers with `painter`, with the top
+    /// left corner of the viewport at `origin` on the screen, over what
+    /// [`Self::paint`] drew.
+    pub fn paint_fixed(&self, painter: &Painter, origin: Vec2, images: &ImageCache) {
+        for item in self.items.iter().filter(|item| item.fixed) {
+            item.paint(painter, origin, images);
+        }
+    }
+}
+
+/// A match of find in page, over the text it covers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Highlight {
+    /// The part of each text run the match is in, in page coordinates,
+    /// clipped as the run is.
+    pub rects: Vec<Rect>,
+
+    /// Whether the match is in a fixed layer, so that its rects are in
+    /// viewport coordinates.
+    pub fixed: bool,
+}
+
+impl Highlight {
+    /// The rectangle around the whole match, to scroll it into view.
+    pub fn bounds(&self) -> Rect {
+        self.rects
+            .iter()
+            .fold(Rect::NOTHING, |bounds, rect| bounds.union(*rect))
+    }
+
+    /// Draws the highlight with `painter`, moved by `origin`, in a stronger
+    /// color if it is the `current` match. Only the rects within the clip
+    /// rectangle of `painter` are drawn.
+    pub fn paint(&self, painter: &Painter, origin: Vec2, current: bool) {
+        let color = if current {
+            CURRENT_HIGHLIGHT
+        } else {
+            HIGHLIGHT
+        };
+        for rect in &self.rects {
+            let rect = rect.translate(origin);
+            if painter.clip_rect().intersects(rect) {
+                painter.rect_filled(rect, 0.0, color);
+            }
+        }
+    }
+}
+
+impl DisplayItem {
+    /// The part of the page the item can draw on: its rectangle, within
+    /// what clips it.
+    fn visible_rect(&self) -> Rect {
+        self.rect.intersect(self.clip)
+    }
+
+    /// Draws the item with `painter`, moved by `origin`, within the clip
+    /// rectangle of `painter`.
+    fn paint(&self, painter: &Painter, origin: Vec2, images: &ImageCache) {
+        let clip = self.clip.translate(origin).intersect(painter.clip_rect());
+        if !clip.is_positive() {
+            return;
+        }
+        let painter = painter.with_clip_rect(clip);
+        match &self.kind {
+            DisplayKind::Box(decoration) => decoration.paint(&painter, origin),
+            DisplayKind::Text(run) => run.paint(&painter, origin),
+            DisplayKind::Image(image) => image.paint(&painter, origin, images),
+        }
+    }
+}
+
+/// Where each character of `run` comes from in `text`, the text node it is
+/// a run of, as the byte offsets of the character in `run` and in `text`.
+/// Layout collapses white space in runs, or expands tabs, so a space of
+/// `run` stands for one white space character of `text`, or none, and
+/// white space of `text` can be left out between runs. The search starts
+/// at `cursor`, which is moved past the run; `None` if the run is not
+/// there.
+fn source_offsets(run: &str, text: &str, cursor: &mut usize) -> Option<Vec<(usize, usize)>> {
+    let mut source = text
+        .get(*cursor..)?
+        .char_indices()
+        .map(|(index, c)| (*cursor + index, c))
+        .peekable();
+    let mut offsets = Vec::with_capacity(run.len());
+    for (index, c) in run.char_indices() {
+        if c == ' ' {
+            let next = source.peek().map_or(text.len(), |&(at, _)| at);
+            source.next_if(|&(_, space)| space.is_ascii_whitespace());
+            offsets.push((index, next));
+            continue;
+        }
+        while source
+            .next_if(|&(_, space)| space.is_ascii_whitespace())
+            .is_some()
+        {}
+        let (at, source_char) = source.next()?;
+        if source_char != c {
+            return None;
+        }
+        offsets.push((index, at));
+    }
+    *cursor = source.peek().map_or(text.len(), |&(at, _)| at);
+    Some(offsets)
+}
+
+/// The bands of the page `rect` reaches into, up to the band `last`, or
+/// `None` if it is empty or above the page, where it cannot be scrolled
+/// to.
+fn bands(rect: Rect, last: usize) -> Option<std::ops::RangeInclusive<usize>> {
+    if rect.is_negative() || rect.max.y < 0.0 {
+        return None;
+    }
+    Some(band(rect.min.y, last)..=band(rect.max.y, last))
+}
+
+/// The band `y` is in, or `last` if it is further down.
+fn band(y: f32, last: usize) -> usize {
+    ((y.max(0.0) / BAND_HEIGHT) as usize).min(last)
+}
+
+/// The color of the canvas: the background of the root element, or, if
+/// it has none and it is an `<html>`, that of its `<body>`, over
+/// [`CANVAS`] (<https://www.w3.org/TR/css-backgrounds-3/#special-backgrounds>).
+/// The node the background comes from is returned with it, as the box of
+/// that element paints no background of its own.
+fn canvas(tree: &LayoutTree, document: &Document) -> (Color32, Option<NodeId>) {
+    let background = |fragment: &Fragment| {
+        let color = Color32::from(fragment.style.color("background-color")?);
+        (color.a() > 0).then_some((CANVAS.blend(color), Some(fragment.node)))
+    };
+    let is_element = |fragment: &Fragment, name: &str| {
+        document
+            .element(fragment.node)
+            .is_some_and(|element| element.name == name)
+    };
+    let Some(root) = tree.root() else {
+        return (CANVAS, None);
+    };
+    background(root)
+        .or_else(|| {
+            if !is_element(root, "html") {
+                return None;
+            }
+            let body = root
+                .children
+                .iter()
+                .find(|child| child.kind == BoxKind::Block && is_element(child, "body"))?;
+            background(body)
+        })
+        .unwrap_or((CANVAS, None))
+}
+
+/// What `fragment`, of a node of `document`, draws, if anything.
+fn display_kind(fragment: &Fragment, document: &Document) -> Option<DisplayKind> {
+    match &fragment.kind {
+        BoxKind::Text(text) | BoxKind::Marker(text) => Some(DisplayKind::Text(TextRun::new(
+            fragment.rect.min,
+            text,
+            &fragment.style,
+        ))),
+        BoxKind::Replaced(_) => {
+            let element = document.element(fragment.node)?;
+            let rect = fragment.content_rect();
+            Some(DisplayKind::Image(ImageItem {
+                rect,
+                url: element
+                    .get_attribute("src")
+                    .and_then(|src| document.resolve_url(src)),
+                alt: element
+                    .get_attribute("alt")
+                    .filter(|alt| !alt.is_empty())
+                    .map(|alt| TextRun::new(rect.min, alt, &fragment.style)),
+            }))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use egui::FontFamily;
+
+    use super::*;
+    use crate::layout::tests::layout;
+
+    #[test]
+    fn test_display_list() {
+        let (document, tree) = layout(
+            "<ul><li>item</li></ul><p id=p>red <span>hidden</span></p>\
+             <div id=clip><p>clipped</p></div><div id=scroll><p>scrolled</p></div>",
+            "#p { color: red } span { visibility: hidden } \
+             #clip { height: 0; overflow: hidden } \
+             #scroll { height: 5px; overflow: auto }",
+        );
+        let list = DisplayList::build(&tree, &document);
+        let texts: Vec<(&str, Color32, Rect)> = list
+            .items()
+            .iter()
+            .filter_map(|item| match &item.kind {
+                DisplayKind::Text(run) => Some((run.text.as_str(), run.color, item.clip)),
+                DisplayKind::Box(_) | DisplayKind::Image(_) => None,
+            })
+            .collect();
+        let scroll = document.get_element_by_id("scroll").unwrap();
+        let scroll = tree
+            .fragments()
+            .find(|fragment| fragment.node == scroll)
+            .unwrap()
+            .padding_rect();
+        assert_eq!(
+            texts,
+            [
+                ("• ", Color32::BLACK, Rect::EVERYTHING),
+                ("item", Color32::BLACK, Rect::EVERYTHING),
+                ("red ", Color32::RED, Rect::EVERYTHING),
+                ("scrolled", Color32::BLACK, scroll),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_text_styles() {
+        let (document, tree) = layout(
+            "<h1>title</h1><p>plain <strong>strong</strong> <em>em</em> <code>code</code></p>",
+            "",
+        );
+        let list = DisplayList::build(&tree, &document);
+        let runs: Vec<(&str, f32, FontFamily, bool, bool)> = list
+            .items()
+            .iter()
+            .filter_map(|item| match &item.kind {
+                DisplayKind::Text(run) if run.text.trim().is_empty() => None,
+                DisplayKind::Box(_) | DisplayKind::Image(_) => None,
+                DisplayKind::Text(run) => Some((
+                    run.text.trim(),
+                    run.font.size,
+                    run.font.family.clone(),
+                    run.bold,
+                    run.italic,
+                )),
+            })
+            .collect();
+        assert_eq!(
+            runs,
+            [
+                ("title", 32.0, FontFamily::Proportional, true, false),
+                ("plain", 16.0, FontFamily::Proportional, false, false),
+                ("strong", 16.0, FontFamily::Proportional, true, false),
+                ("em", 16.0, FontFamily::Proportional, false, true),
+                ("code", 16.0, FontFamily::Monospace, false, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_text_decorations() {
+        let (document, tree) = layout("<a href=next.html>link</a> <del>deleted</del>", "");
+        let list = DisplayList::build(&tree, &document);
+        let runs: Vec<(&str, bool, bool, Color32)> = list
+            .items()
+            .iter()
+            .filter_map(|item| match &item.kind {
+                DisplayKind::Text(run) if run.text.trim().is_empty() => None,
+                DisplayKind::Box(_) | DisplayKind::Image(_) => None,
+                DisplayKind::Text(run) => Some((
+                    run.text.as_str(),
+                    run.underline,
+                    run.line_through,
+                    run.color,
+                )),
+            })
+            .collect();
+        assert_eq!(
+            runs,
+            [
+                ("link", true, false, Color32::from_rgb(0, 0, 0xee)),
+                ("deleted", false, true, Color32::BLACK),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_images() {
+        let (document, tree) = layout(
+            "<img src=https://example.com/a.png alt='A cat' width=30 height=20><img alt=''>",
+            "img { border: 2px solid }",
+        );
+        let list = DisplayList::build(&tree, &document);
+        let images: Vec<&ImageItem> = list
+            .items()
+            .iter()
+            .filter_map(|item| match &item.kind {
+                DisplayKind::Image(image) => Some(image),
+                DisplayKind::Box(_) | DisplayKind::Text(_) => None,
+            })
+            .collect();
+        let [cat, empty] = images.as_slice() else {
+            panic!("expected two images: {images:?}");
+        };
+        assert_eq!(cat.url.as_deref(), Some("https://example.com/a.png"));
+        assert_eq!(cat.rect.size(), Vec2::new(30.0, 20.0));
+        let alt = cat.alt.as_ref().unwrap();
+        assert_eq!((alt.text.as_str(), alt.pos), ("A cat", cat.rect.min));
+        assert_eq!((&empty.url, &empty.alt), (&None, &None));
+    }
+
+    #[test]
+    fn test_backgrounds_and_borders() {
+        let (document, tree) = layout(
+            "<div id=a></div><p id=b>b</p><span id=c>c</span><div id=d></div>",
+            "body { background: #000080 } \
+             #a { width: 100px; height: 40px; background-color: red; \
+                  border: 2px dashed blue; border-radius: 10px 50% } \
+             #b { border-top: 1px solid } #c { background: lime } \
+             #d { width: 20px; height: 20px; background: black; border-radius: 15px }",
+        );
+        let list = DisplayList::build(&tree, &document);
+        assert_eq!(list.canvas(), Color32::from_rgb(0, 0, 0x80));
+        let decoration = |id| {
+            let node = document.get_element_by_id(id).unwrap();
+            list.items().iter().find_map(|item| match &item.kind {
+                DisplayKind::Box(decoration) if item.node == node => Some(decoration),
+                _ => None,
+            })
+        };
+        // The body's background went to the canvas.
+        assert_eq!(decoration("a").map(|a| a.rect.min.y), Some(8.0));
+        assert!(list.items().iter().all(|item| {
+            !matches!(&item.kind, DisplayKind::Box(decoration)
+                if decoration.background == Color32::from_rgb(0, 0, 0x80))
+        }));
+
+        let a = decoration("a").unwrap();
+        let dashed = BorderSide {
+            width: 2.0,
+            color: Color32::BLUE,
+            style: BorderStyle::Dashed,
+        };
+        // 50% is of the shorter side of the border box, 44px high.
+        assert_eq!(
+            (a.background, a.border, a.radii),
+            (Color32::RED, [dashed; 4], [10.0, 22.0, 10.0, 22.0])
+        );
+        let b = decoration("b").unwrap();
+        assert_eq!(b.background, Color32::TRANSPARENT);
+        assert_eq!(b.border.map(|side| side.width), [1.0, 0.0, 0.0, 0.0]);
+        assert_eq!(
+            decoration("c").unwrap().background,
+            Color32::from_rgb(0, 255, 0)
+        );
+        // Corners that would overlap are scaled down together.
+        assert_eq!(decoration("d").unwrap().radii, [10.0; 4]);
+    }
+
+    #[test]
+    fn test_visible_items() {
+        let (document, tree) = layout(
+            "<p id=a>a</p><p id=b>b</p><div id=c><p id=d>d</p></div>",
+            "body { margin: 0 } p { margin: 0; height: 1000px } \
+             #c { height: 10px; overflow: hidden }",
+        );
+        let list = DisplayList::build(&tree, &document);
+        let nodes = |top: f32, bottom: f32| -> Vec<NodeId> {
+            let rect = Rect::from_x_y_ranges(0.0..=800.0, top..=bottom);
+            let mut nodes: Vec<NodeId> = list.visible_items(rect).map(|item| item.node).collect();
+            nodes.dedup();
+            nodes
+        };
+        let id = |name| document.get_element_by_id(name).unwrap();
+        let text = |name| document.children(id(name)).next().unwrap();
+        assert_eq!(nodes(0.0, 10.0), [text("a")]);
+        assert!(nodes(100.0, 900.0).is_empty());
+        assert_eq!(nodes(990.0, 1010.0), [text("b")]);
+        // The text of #d is clipped to the first 10 pixels of #c.
+        assert_eq!(nodes(2005.0, 2006.0), [text("d")]);
+        assert!(nodes(2012.0, 2015.0).is_empty());
+        assert!(nodes(-100.0, -10.0).is_empty());
+    }
+
+    #[test]
+    fn test_huge_boxes() {
+        for height in ["1e12px", "1e25px"] {
+            let (document, tree) = layout(
+                "<div id=tall></div>",
+                &format!("body {{ margin: 0 }} #tall {{ height: {height}; background: red }}"),
+            );
+            let list = DisplayList::build(&tree, &document);
+            assert!(list.bands.len() <= MAX_BANDS);
+            // Far down the page, the box is found in the last band.
+            let tall = document.get_element_by_id("tall");
+            let far = Rect::from_x_y_ranges(0.0..=800.0, 1e10..=1e10 + 600.0);
+            assert!(list.visible_items(far).any(|item| Some(item.node) == tall));
+        }
+    }
+
+    #[test]
+    fn test_fixed_items() {
+        let (document, tree) = layout(
+            "<p id=bar>bar</p><p>page</p>",
+            "body { margin: 0 } p { margin: 0; height: 1000px } \
+             #bar { position: fixed; top: 0; height: 20px }",
+        );
+        let list = DisplayList::build(&tree, &document);
+        let bar = document
+            .children(document.get_element_by_id("bar").unwrap())
+            .next();
+        let fixed: Vec<NodeId> = list
+            .items()
+            .iter()
+            .filter(|item| item.fixed)
+            .map(|item| item.node)
+            .collect();
+        assert_eq!(fixed.last().copied(), bar);
+        // Fixed items are not on the page, however it is scrolled.
+        let page = Rect::from_x_y_ranges(0.0..=800.0, 0.0..=5000.0);
+        assert!(list.visible_items(page).all(|item| !item.fixed));
+    }
+
+    #[test]
+    fn test_highlights() {
+        let (document, tree) = layout(
+            "<p id=p>one  two\n one</p><div id=none>one</div><p>tw<b>o</b></p>",
+            "body { margin: 0 } #none { display: none }",
+        );
+        let list = DisplayList::build(&tree, &document);
+        let mut fonts = crate::layout::tests::fonts();
+        let mut fonts = fonts.with_pixels_per_point(1.0);
+        let matches = document.find_text("ONE");
+        assert_eq!(matches.len(), 3);
+        let highlights = list.highlights(&document, &matches, &mut fonts);
+        // The match in the element that is not displayed is left out.
+        let [first, second] = highlights.as_slice() else {
+            panic!("expected two highlights: {highlights:?}");
+        };
+        let (first, second) = (first.bounds(), second.bounds());
+        assert_eq!(first.min.x, 0.0);
+        assert!(second.min.x > first.max.x + 20.0);
+        assert!((first.width() - second.width()).abs() < 1.0);
+        assert_eq!(first.y_range(), second.y_range());
+
+        let two = document.find_text("two");
+        let highlights = list.highlights(&document, &two, &mut fonts);
+        assert_eq!(highlights.len(), 1);
+        assert!(highlights[0].bounds().min.x > first.max.x);
+    }
+}
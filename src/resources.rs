@@ -0,0 +1,356 @@
+//! Loading the subresources of a page in the background once it is
+//! parsed, so that the page can be shown while they arrive.
+//!
+//! These are the style sheets of `<link rel=stylesheet>` elements, and
+//! those that style sheets bring in with `@import`. Images load through
+//! the [`ImageCache`](crate::images::ImageCache) instead, which keeps them
+//! across pages.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock, PoisonError, mpsc};
+
+use crate::css::Stylesheet;
+use crate::dom::{self, Document, ImportedStylesheet, LinkKind, NodeId};
+use crate::http::HttpResponse;
+
+/// How subresources are fetched. It is called on a background thread for
+/// each one, so the app can run its extension hooks around the request.
+pub type Fetch = Arc<dyn Fn(&str) -> Result<HttpResponse, String> + Send + Sync>;
+
+/// Style sheets that finished loading for the element `owner`.
+enum Loaded {
+    /// The sheet of a `<link rel=stylesheet>` with its imports, or `None`
+    /// if it failed.
+    Link {
+        owner: NodeId,
+        sheet: Option<(Stylesheet, Vec<ImportedStylesheet>)>,
+    },
+
+    /// The imports of the sheet of a `<style>` element.
+    Imports {
+        owner: NodeId,
+        imports: Vec<ImportedStylesheet>,
+    },
+}
+
+/// A style sheet of [`FetchedSheets`]: set once its fetch is done, to
+/// `None` if it failed.
+type Slot = Arc<OnceLock<Option<Stylesheet>>>;
+
+/// The style sheets fetched for one document, by URL, shared by its fetch
+/// threads, so that a sheet that several links or imports bring in, such
+/// as both sides of a diamond of imports, is only fetched once.
+#[derive(Clone, Default)]
+struct FetchedSheets(Arc<Mutex<HashMap<String, Slot>>>);
+
+impl FetchedSheets {
+    /// The style sheet at `url`, fetched with `fetch` unless it already
+    /// was, or is being fetched by another thread, which this one waits
+    /// for.
+    fn get(&self, fetch: &Fetch, url: &str) -> Option<Stylesheet> {
+        let slot = Arc::clone(
+            self.0
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .entry(url.to_owned())
+                .or_default(),
+        );
+        slot.get_or_init(|| fetch_stylesheet(fetch, url)).clone()
+    }
+}
+
+/// The subresources of one document that are still loading.
+///
+/// [`ResourceTracker::start`] fetches each of them on its own thread, and
+/// [`ResourceTracker::poll`] adds those that have arrived to the document.
+/// Styling is complete once nothing is pending: a page shown before then
+/// may still change its look.
+#[derive(Debug)]
+pub struct ResourceTracker {
+    receiver: mpsc::Receiver<Loaded>,
+    pending: usize,
+}
+
+impl ResourceTracker {
+    /// Starts fetching the style sheets `document` links to, and those
+    /// its `<style>` elements import. Each thread also fetches the imports
+    /// of the sheets it gets. A sheet is fetched once however many times
+    /// it is linked or imported. URLs that cannot be resolved, such as
+    /// relative ones in a document without a URL, are skipped.
+    pub fn start(document: &Document, fetch: &Fetch) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let sheets = FetchedSheets::default();
+        let mut pending = 0;
+        for link in document.links() {
+            let (LinkKind::Stylesheet, Some(url)) = (link.kind, link.url) else {
+                continue;
+            };
+            pending += 1;
+            let sender = sender.clone();
+            let fetch = Arc::clone(fetch);
+            let sheets = sheets.clone();
+            std::thread::spawn(move || {
+                let sheet = sheets.get(&fetch, &url).map(|sheet| {
+                    let imports =
+                        fetch_imports(&sheet, &url, &fetch, &sheets, &mut vec![url.clone()]);
+                    (sheet, imports)
+                });
+                sender
+                    .send(Loaded::Link {
+                        owner: link.element,
+                        sheet,
+                    })
+                    .ok();
+            });
+        }
+
+        let base = document.base_url().unwrap_or_default();
+        for stylesheet in document.stylesheets() {
+            if stylesheet.sheet.imports().is_empty() {
+                continue;
+            }
+            pending += 1;
+            let sender = sender.clone();
+            let fetch = Arc::clone(fetch);
+            let sheets = sheets.clone();
+            let (owner, sheet, base) = (stylesheet.owner, stylesheet.sheet.clone(), base.clone());
+            std::thread::spawn(move || {
+                let imports = fetch_imports(&sheet, &base, &fetch, &sheets, &mut Vec::new());
+                sender.send(Loaded::Imports { owner, imports }).ok();
+            });
+        }
+        Self { receiver, pending }
+    }
+
+    /// Adds the style sheets that have arrived since the last call to
+    /// `document`, which must be the one the tracker was started for.
+    /// Returns whether any was added, in which case styles must be
+    /// computed again. A sheet that failed to load is dropped, as if the
+    /// link or `@import` rule were not there.
+    pub fn poll(&mut self, document: &mut Document) -> bool {
+        let mut added = false;
+        loop {
+            match self.receiver.try_recv() {
+                Ok(loaded) => {
+                    self.pending = self.pending.saturating_sub(1);
+                    match loaded {
+                        Loaded::Link {
+                            owner,
+                            sheet: Some((sheet, imports)),
+                        } => {
+                            document.add_linked_stylesheet(owner, sheet);
+                            document.set_stylesheet_imports(owner, imports);
+                            added = true;
+                        }
+                        Loaded::Link { sheet: None, .. } => {}
+                        Loaded::Imports { owner, imports } => {
+                            added |= !imports.is_empty();
+                            document.set_stylesheet_imports(owner, imports);
+                        }
+                    }
+                }
+                Err(mpsc::TryRecvError::Empty) => break,
+                // Every fetch thread is gone, even one that panicked.
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    self.pending = 0;
+                    break;
+                }
+            }
+        }
+        added
+    }
+
+    /// The number of subresources still loading.
+    pub fn pending(&self) -> usize {
+        self.pending
+    }
+
+    /// Whether every subresource has loaded or failed, so the page has its
+    /// final styles.
+    pub fn is_complete(&self) -> bool {
+        self.pending == 0
+    }
+}
+
+/// Fetches the style sheet at `url`, or `None` if that fails.
+fn fetch_stylesheet(fetch: &Fetch, url: &str) -> Option<Stylesheet> {
+    fetch(url)
+        .ok()
+        .filter(|response| (200..300).contains(&response.status))
+        .map(|response| Stylesheet::parse(&response.body))
+}
+
+/// Fetches the sheets `sheet` imports, and theirs in turn, one after the
+/// other, unless they are in `sheets` already. Their URLs are resolved
+/// against `base`, the URL of `sheet`. `importers` are the URLs of the
+/// sheets that import `sheet`, directly or not: importing one of them
+/// again would be a cycle, so such an import is skipped.
+fn fetch_imports(
+    sheet: &Stylesheet,
+    base: &str,
+    fetch: &Fetch,
+    sheets: &FetchedSheets,
+    importers: &mut Vec<String>,
+) -> Vec<ImportedStylesheet> {
+    let mut imported = Vec::new();
+    for import in sheet.imports() {
+        let Some(url) = dom::resolve(base, &import.url) else {
+            continue;
+        };
+        if importers.contains(&url) {
+            continue;
+        }
+        let Some(sheet) = sheets.get(fetch, &url) else {
+            continue;
+        };
+        importers.push(url.clone());
+        let imports = fetch_imports(&sheet, &url, fetch, sheets, importers);
+        importers.pop();
+        imported.push(ImportedStylesheet {
+            url,
+            media: import.media,
+            sheet,
+            imports,
+        });
+    }
+    imported
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_linked_stylesheets_load() {
+        let mut document = Document::parse(
+            "<link rel=stylesheet href=a.css><link rel=stylesheet href=missing.css>\
+             <link rel=icon href=a.png><link rel=stylesheet href=b.css><p>",
+        );
+        document.set_url("https://example.com/page.html".to_owned());
+        let fetch: Fetch = Arc::new(|url: &str| match url {
+            "https://example.com/a.css" => Ok(HttpResponse {
+                status: 200,
+                headers: Vec::new(),
+                body: "p { color: red }".to_owned(),
+            }),
+            "https://example.com/b.css" => Err("connection refused".to_owned()),
+            _ => Ok(HttpResponse {
+                status: 404,
+                headers: Vec::new(),
+                body: "p { color: blue }".to_owned(),
+            }),
+        });
+        let mut tracker = ResourceTracker::start(&document, &fetch);
+        assert_eq!(tracker.pending(), 3);
+
+        let mut added = false;
+        while !tracker.is_complete() {
+            added |= tracker.poll(&mut document);
+            std::thread::yield_now();
+        }
+        assert!(added);
+        let sheets = document.stylesheets();
+        assert_eq!(sheets.len(), 1);
+        assert_eq!(
+            sheets[0].owner,
+            document.get_elements_by_tag_name("link")[0]
+        );
+    }
+
+    #[test]
+    fn test_imports_load() {
+        let mut document = Document::parse(
+            "<style>@import 'a.css' print; p {}</style><link rel=stylesheet href=css/b.css>",
+        );
+        document.set_url("https://example.com/page.html".to_owned());
+        let fetch: Fetch = Arc::new(|url: &str| {
+            let body = match url {
+                "https://example.com/a.css" => "@import url(c.css); a {}",
+                "https://example.com/c.css" => "@import 'a.css'; c {}",
+                "https://example.com/css/b.css" => "@import 'd.css'; @import url(/c.css); b {}",
+                "https://example.com/css/d.css" => "@import 'b.css'; d {}",
+                _ => return Err("not found".to_owned()),
+            };
+            Ok(HttpResponse {
+                status: 200,
+                headers: Vec::new(),
+                body: body.to_owned(),
+            })
+        });
+        let mut tracker = ResourceTracker::start(&document, &fetch);
+        assert_eq!(tracker.pending(), 2);
+        while !tracker.is_complete() {
+            tracker.poll(&mut document);
+            std::thread::yield_now();
+        }
+
+        let sheets = document.stylesheets();
+        assert_eq!(sheets.len(), 2);
+        // a.css imports c.css, whose import of a.css again is skipped.
+        let a = &sheets[0].imports[0];
+        assert_eq!(a.url, "https://example.com/a.css");
+        assert_eq!(a.media.len(), 1);
+        assert_eq!(a.imports[0].url, "https://example.com/c.css");
+        assert!(a.imports[0].imports.is_empty());
+
+        let urls: Vec<&str> = sheets[1]
+            .imports
+            .iter()
+            .map(|import| import.url.as_str())
+            .collect();
+        assert_eq!(
+            urls,
+            ["https://example.com/css/d.css", "https://example.com/c.css"]
+        );
+        assert!(sheets[1].imports[0].imports.is_empty());
+        assert_eq!(
+            sheets[1].imports[1].imports[0].url,
+            "https://example.com/a.css"
+        );
+    }
+
+    #[test]
+    fn test_shared_imports_fetch_once() {
+        let mut document = Document::parse(
+            "<link rel=stylesheet href=a.css><link rel=stylesheet href=d.css>\
+             <style>@import 'b.css';</style>",
+        );
+        document.set_url("https://example.com/page.html".to_owned());
+        let fetched = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::clone(&fetched);
+        let fetch: Fetch = Arc::new(move |url: &str| {
+            log.lock().unwrap().push(url.to_owned());
+            let body = match url {
+                "https://example.com/a.css" => "@import 'b.css'; @import 'c.css';",
+                "https://example.com/b.css" | "https://example.com/c.css" => "@import 'd.css';",
+                _ => "p {}",
+            };
+            Ok(HttpResponse {
+                status: 200,
+                headers: Vec::new(),
+                body: body.to_owned(),
+            })
+        });
+        let mut tracker = ResourceTracker::start(&document, &fetch);
+        while !tracker.is_complete() {
+            tracker.poll(&mut document);
+            std::thread::yield_now();
+        }
+
+        let mut fetched = fetched.lock().unwrap().clone();
+        fetched.sort();
+        assert_eq!(
+            fetched,
+            [
+                "https://example.com/a.css",
+                "https://example.com/b.css",
+                "https://example.com/c.css",
+                "https://example.com/d.css",
+            ]
+        );
+        // Both sides of the diamond still get d.css.
+        let a = &document.stylesheets()[0];
+        assert_eq!(a.imports[0].imports[0].url, "https://example.com/d.css");
+        assert_eq!(a.imports[1].imports[0].url, "https://example.com/d.css");
+    }
+}
@@ -0,0 +1,670 @@
+//! CSS selectors (<https://www.w3.org/TR/selectors-4/>), parsed from the
+//! prelude of a style rule or from a string, and matched against the
+//! elements of a [`Document`].
+//!
+//! The cascade and [`Document::query_selector`] share this engine, so a
+//! selector picks out the same elements in a style sheet as in a query.
+
+use crate::css::{BlockKind, ComponentValue, CssToken, parse_component_values};
+use crate::dom::{Document, Namespace, NodeId};
+
+/// How specific a selector is. Of two declarations from the same origin,
+/// the one whose selector is more specific wins.
+///
+/// The derived order compares `ids` first, then `classes`, then `types`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Specificity {
+    /// The number of ID selectors.
+    pub ids: u32,
+
+    /// The number of class selectors, attribute selectors and
+    /// pseudo-classes.
+    pub classes: u32,
+
+    /// The number of type selectors.
+    pub types: u32,
+}
+
+/// How two compound selectors of a complex selector relate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Combinator {
+    /// Whitespace, as in `div p`.
+    Descendant,
+
+    /// `>`, as in `div > p`.
+    Child,
+
+    /// `+`, as in `h1 + p`: the element right before.
+    NextSibling,
+
+    /// `~`, as in `h1 ~ p`: any element before, with the same parent.
+    SubsequentSibling,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PseudoClass {
+    Root,
+    FirstChild,
+    LastChild,
+    OnlyChild,
+
+    // The dynamic pseudo-classes, which depend on what the user does
+    // rather than on the document.
+    Hover,
+    Active,
+    Focus,
+    FocusWithin,
+    Link,
+    Visited,
+    AnyLink,
+}
+
+/// How an attribute selector compares the attribute's value with its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AttributeOperator {
+    /// `=`: the values are equal.
+    Equals,
+
+    /// `~=`: the value is one of the attribute's whitespace-separated words.
+    Includes,
+
+    /// `|=`: the attribute's value is the value, or starts with it and a
+    /// `-`, as language codes such as `en-US` do.
+    DashMatch,
+
+    /// `^=`: the attribute's value starts with the value.
+    Prefix,
+
+    /// `$=`: the attribute's value ends with the value.
+    Suffix,
+
+    /// `*=`: the attribute's value contains the value.
+    Substring,
+}
+
+/// `[name]`, or `[name=value]` and the like if `value` is set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct AttributeSelector {
+    name: String,
+    value: Option<(AttributeOperator, String)>,
+
+    /// Whether the values are compared ignoring ASCII case, as with
+    /// `[type=text i]`.
+    case_insensitive: bool,
+}
+
+/// A sequence of simple selectors that all apply to one element, such as
+/// `p.intro[lang]:first-child`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Compound {
+    /// `None` for `*`, or when there is no type selector.
+    tag_name: Option<String>,
+    ids: Vec<String>,
+    classes: Vec<String>,
+    attributes: Vec<AttributeSelector>,
+    pseudo_classes: Vec<PseudoClass>,
+}
+
+/// Compound selectors joined by combinators, such as `div.article > p`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selector {
+    /// The compound the matched element itself has to match.
+    subject: Compound,
+
+    /// The compounds to its left, nearest first, each with the combinator
+    /// that joins it to the compound before it in this list.
+    preceding: Vec<(Combinator, Compound)>,
+}
+
+/// A comma-separated list of selectors, matching an element that any of
+/// them matches.
+///
+/// Supports type, universal, `#id`, `.class` and attribute selectors, with
+/// all of the `=`, `~=`, `|=`, `^=`, `$=` and `*=` operators, the `:root`,
+/// `:first-child`, `:last-child` and `:only-child` pseudo-classes, the
+/// dynamic `:hover`, `:active`, `:focus`, `:focus-within`, `:link`,
+/// `:visited` and `:any-link` pseudo-classes, and the descendant, child,
+/// next-sibling and subsequent-sibling combinators.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectorList(Vec<Selector>);
+
+impl SelectorList {
+    /// Parses `selectors`, such as `div.article > p:first-child, #main`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the problem if `selectors` cannot be
+    /// parsed or uses unsupported syntax.
+    pub fn parse(selectors: &str) -> Result<Self, String> {
+        if selectors.trim().is_empty() {
+            return Err("Empty selector".to_owned());
+        }
+        Self::from_component_values(&parse_component_values(selectors))
+            .ok_or_else(|| format!("Unsupported selector: {selectors}"))
+    }
+
+    /// Parses the prelude of a style rule. Returns `None` if it is not a
+    /// selector list this engine supports, in which case a browser drops
+    /// the whole rule.
+    pub fn from_component_values(values: &[ComponentValue]) -> Option<Self> {
+        let mut parser = Parser {
+            values,
+            position: 0,
+        };
+        let mut list = vec![parser.complex()?];
+        while parser.eat(&CssToken::Comma) {
+            list.push(parser.complex()?);
+        }
+        parser.skip_whitespace();
+        (parser.position == values.len()).then_some(Self(list))
+    }
+
+    /// The selectors of the list, in the order they were written.
+    pub fn selectors(&self) -> &[Selector] {
+        &self.0
+    }
+
+    /// Whether any of the selectors has `:hover` in it, so that what the
+    /// list matches can change as the pointer moves.
+    pub fn uses_hover(&self) -> bool {
+        self.0.iter().any(|selector| {
+            std::iter::once(&selector.subject)
+                .chain(selector.preceding.iter().map(|(_, compound)| compound))
+                .any(|compound| compound.pseudo_classes.contains(&PseudoClass::Hover))
+        })
+    }
+
+    /// Whether the element `id` matches any selector in the list.
+    pub fn matches(&self, document: &Document, id: NodeId) -> bool {
+        self.0.iter().any(|selector| selector.matches(document, id))
+    }
+
+    /// The specificity of the most specific selector in the list that
+    /// matches `id`, which is the one a style rule applies with, or `None`
+    /// if none does.
+    pub fn matching_specificity(&self, document: &Document, id: NodeId) -> Option<Specificity> {
+        self.0
+            .iter()
+            .filter(|selector| selector.matches(document, id))
+            .map(Selector::specificity)
+            .max()
+    }
+}
+
+impl Selector {
+    /// The sum of the specificities of the selector's compounds.
+    pub fn specificity(&self) -> Specificity {
+        std::iter::once(&self.subject)
+            .chain(self.preceding.iter().map(|(_, compound)| compound))
+            .map(Compound::specificity)
+            .fold(Specificity::default(), |total, specificity| Specificity {
+                ids: total.ids.saturating_add(specificity.ids),
+                classes: total.classes.saturating_add(specificity.classes),
+                types: total.types.saturating_add(specificity.types),
+            })
+    }
+
+    /// Whether the element `id` matches the selector.
+    ///
+    /// Matching goes right to left: the element itself is checked first,
+    /// which rules out most elements for most selectors at once, and only
+    /// then are its ancestors and siblings walked.
+    pub fn matches(&self, document: &Document, id: NodeId) -> bool {
+        self.subject.matches(document, id) && preceding_match(document, id, &self.preceding)
+    }
+}
+
+/// Whether the elements around `id` match `preceding`, right to left,
+/// trying each candidate in turn for the descendant and
+/// subsequent-sibling combinators.
+fn preceding_match(document: &Document, id: NodeId, preceding: &[(Combinator, Compound)]) -> bool {
+    let Some(((combinator, compound), rest)) = preceding.split_first() else {
+        return true;
+    };
+    let matches = |candidate: NodeId| {
+        compound.matches(document, candidate) && preceding_match(document, candidate, rest)
+    };
+    let mut previous_elements = document
+        .previous_siblings(id)
+        .filter(|&sibling| document.element(sibling).is_some());
+    match combinator {
+        Combinator::Child => document.parent(id).is_some_and(matches),
+        Combinator::Descendant => document.ancestors(id).any(matches),
+        Combinator::NextSibling => previous_elements.next().is_some_and(matches),
+        Combinator::SubsequentSibling => previous_elements.any(matches),
+    }
+}
+
+impl Compound {
+    fn specificity(&self) -> Specificity {
+        let count = |n: usize| u32::try_from(n).unwrap_or(u32::MAX);
+        Specificity {
+            ids: count(self.ids.len()),
+            classes: count(self.classes.len() + self.attributes.len() + self.pseudo_classes.len()),
+            types: count(usize::from(self.tag_name.is_some())),
+        }
+    }
+
+    fn matches(&self, document: &Document, id: NodeId) -> bool {
+        let Some(element) = document.element(id) else {
+            return false;
+        };
+        self.tag_name
+            .as_deref()
+            .is_none_or(|tag_name| match element.namespace {
+                Namespace::Html => element.name.eq_ignore_ascii_case(tag_name),
+                Namespace::Svg | Namespace::MathMl => element.name == tag_name,
+            })
+            && self
+                .ids
+                .iter()
+                .all(|id| element.get_attribute("id") == Some(id.as_str()))
+            && self
+                .classes
+                .iter()
+                .all(|class| element.class_list().contains(class))
+            && self.attributes.iter().all(|attribute| {
+                element
+                    .get_attribute(&attribute.name)
+                    .is_some_and(|value| attribute.matches(value))
+            })
+            && self
+                .pseudo_classes
+                .iter()
+                .all(|pseudo_class| pseudo_class.matches(document, id))
+    }
+}
+
+impl AttributeSelector {
+    /// Whether an attribute whose value is `actual` matches.
+    fn matches(&self, actual: &str) -> bool {
+        let Some((operator, wanted)) = &self.value else {
+            return true;
+        };
+        let (actual, wanted) = if self.case_insensitive {
+            (actual.to_ascii_lowercase(), wanted.to_ascii_lowercase())
+        } else {
+            (actual.to_owned(), wanted.clone())
+        };
+        match operator {
+            AttributeOperator::Equals => actual == wanted,
+            AttributeOperator::Includes => {
+                !wanted.is_empty()
+                    && !wanted.contains(is_whitespace)
+                    && actual.split(is_whitespace).any(|word| word == wanted)
+            }
+            AttributeOperator::DashMatch => {
+                actual == wanted
+                    || actual
+                        .strip_prefix(&wanted)
+                        .is_some_and(|rest| rest.starts_with('-'))
+            }
+            AttributeOperator::Prefix => !wanted.is_empty() && actual.starts_with(&wanted),
+            AttributeOperator::Suffix => !wanted.is_empty() && actual.ends_with(&wanted),
+            AttributeOperator::Substring => !wanted.is_empty() && actual.contains(&wanted),
+        }
+    }
+}
+
+/// Whitespace as selectors see it in attribute values.
+fn is_whitespace(c: char) -> bool {
+    matches!(c, ' ' | '\t' | '\n' | '\r' | '\x0C')
+}
+
+impl PseudoClass {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "root" => Some(Self::Root),
+            "first-child" => Some(Self::FirstChild),
+            "last-child" => Some(Self::LastChild),
+            "only-child" => Some(Self::OnlyChild),
+            "hover" => Some(Self::Hover),
+            "active" => Some(Self::Active),
+            "focus" => Some(Self::Focus),
+            "focus-within" => Some(Self::FocusWithin),
+            "link" => Some(Self::Link),
+            "visited" => Some(Self::Visited),
+            "any-link" => Some(Self::AnyLink),
+            _ => None,
+        }
+    }
+
+    fn matches(self, document: &Document, id: NodeId) -> bool {
+        match self {
+            Self::Root | Self::FirstChild | Self::LastChild | Self::OnlyChild => {
+                self.matches_position(document, id)
+            }
+            Self::Hover => document.is_hovered(id),
+            Self::Active => document.is_active(id),
+            Self::Focus => document.is_focused(id),
+            Self::FocusWithin => document.has_focus_within(id),
+            Self::Link => document.is_link(id) && !document.is_visited_link(id),
+            Self::Visited => document.is_visited_link(id),
+            Self::AnyLink => document.is_link(id),
+        }
+    }
+
+    /// Matches the pseudo-classes about the element's place among its
+    /// siblings.
+    fn matches_position(self, document: &Document, id: NodeId) -> bool {
+        let Some(parent) = document.parent(id) else {
+            return false;
+        };
+        let mut siblings = document
+            .children(parent)
+            .filter(|&sibling| document.element(sibling).is_some());
+        match self {
+            Self::Root => parent == document.root(),
+            Self::FirstChild => siblings.next() == Some(id),
+            Self::LastChild => siblings.next_back() == Some(id),
+            Self::OnlyChild => siblings.next() == Some(id) && siblings.next().is_none(),
+            _ => false,
+        }
+    }
+}
+
+/// Reads selectors from component values. Each method returns `None` for
+/// syntax it does not support.
+struct Parser<'v> {
+    values: &'v [ComponentValue],
+    position: usize,
+}
+
+impl<'v> Parser<'v> {
+    fn peek(&self) -> Option<&'v ComponentValue> {
+        self.values.get(self.position)
+    }
+
+    fn peek_token(&self) -> Option<&'v CssToken> {
+        match self.peek()? {
+            ComponentValue::Token(token) => Some(token),
+            ComponentValue::Function { .. } | ComponentValue::Block { .. } => None,
+        }
+    }
+
+    fn skip_whitespace(&mut self) -> bool {
+        let start = self.position;
+        while self.peek_token() == Some(&CssToken::Whitespace) {
+            self.position += 1;
+        }
+        self.position > start
+    }
+
+    /// Consumes `token`, with any whitespace around it.
+    fn eat(&mut self, token: &CssToken) -> bool {
+        let start = self.position;
+        self.skip_whitespace();
+        if self.peek_token() == Some(token) {
+            self.position += 1;
+            self.skip_whitespace();
+            true
+        } else {
+            self.position = start;
+            false
+        }
+    }
+
+    fn identifier(&mut self) -> Option<String> {
+        let Some(CssToken::Ident(name)) = self.peek_token() else {
+            return None;
+        };
+        self.position += 1;
+        Some(name.clone())
+    }
+
+    fn complex(&mut self) -> Option<Selector> {
+        self.skip_whitespace();
+        let mut compounds = vec![self.compound()?];
+        let mut combinators = Vec::new();
+        loop {
+            let whitespace = self.skip_whitespace();
+            let combinator = match self.peek_token() {
+                Some(CssToken::Comma) => break,
+                _ if self.peek().is_none() => break,
+                Some(CssToken::Delim(delim @ ('>' | '+' | '~'))) => {
+                    self.position += 1;
+                    self.skip_whitespace();
+                    match delim {
+                        '>' => Combinator::Child,
+                        '+' => Combinator::NextSibling,
+                        _ => Combinator::SubsequentSibling,
+                    }
+                }
+                _ if whitespace => Combinator::Descendant,
+                _ => return None,
+            };
+            combinators.push(combinator);
+            compounds.push(self.compound()?);
+        }
+
+        let subject = compounds.pop()?;
+        let preceding = combinators
+            .into_iter()
+            .rev()
+            .zip(compounds.into_iter().rev())
+            .collect();
+        Some(Selector { subject, preceding })
+    }
+
+    fn compound(&mut self) -> Option<Compound> {
+        let start = self.position;
+        let mut compound = Compound::default();
+        match self.peek_token() {
+            Some(CssToken::Delim('*')) => self.position += 1,
+            Some(CssToken::Ident(name)) => {
+                compound.tag_name = Some(name.clone());
+                self.position += 1;
+            }
+            _ => {}
+        }
+
+        loop {
+            match self.peek() {
+                Some(ComponentValue::Token(CssToken::Hash { value, id: true })) => {
+                    self.position += 1;
+                    compound.ids.push(value.clone());
+                }
+                Some(ComponentValue::Token(CssToken::Delim('.'))) => {
+                    self.position += 1;
+                    compound.classes.push(self.identifier()?);
+                }
+                Some(ComponentValue::Token(CssToken::Colon)) => {
+                    self.position += 1;
+                    compound
+                        .pseudo_classes
+                        .push(PseudoClass::parse(&self.identifier()?)?);
+                }
+                Some(ComponentValue::Block {
+                    kind: BlockKind::Square,
+                    contents,
+                }) => {
+                    self.position += 1;
+                    compound.attributes.push(attribute(contents)?);
+                }
+                _ => break,
+            }
+        }
+
+        (self.position > start).then_some(compound)
+    }
+}
+
+/// Parses the contents of an attribute selector's `[...]`.
+fn attribute(contents: &[ComponentValue]) -> Option<AttributeSelector> {
+    let mut parser = Parser {
+        values: contents,
+        position: 0,
+    };
+    parser.skip_whitespace();
+    let name = parser.identifier()?.to_ascii_lowercase();
+    parser.skip_whitespace();
+    let operator = match parser.peek_token() {
+        None => {
+            return Some(AttributeSelector {
+                name,
+                value: None,
+                case_insensitive: false,
+            });
+        }
+        Some(CssToken::Delim('=')) => AttributeOperator::Equals,
+        Some(CssToken::Delim(delim)) => {
+            let operator = match delim {
+                '~' => AttributeOperator::Includes,
+                '|' => AttributeOperator::DashMatch,
+                '^' => AttributeOperator::Prefix,
+                '$' => AttributeOperator::Suffix,
+                '*' => AttributeOperator::Substring,
+                _ => return None,
+            };
+            // The `=` has to follow right away, as in `~=`.
+            parser.position += 1;
+            if parser.peek_token() != Some(&CssToken::Delim('=')) {
+                return None;
+            }
+            operator
+        }
+        Some(_) => return None,
+    };
+    parser.position += 1;
+    parser.skip_whitespace();
+    let value = match parser.peek_token()? {
+        CssToken::Ident(value) | CssToken::String(value) => value.clone(),
+        _ => return None,
+    };
+    parser.position += 1;
+    parser.skip_whitespace();
+    let case_insensitive = match parser.identifier() {
+        Some(flag) if flag.eq_ignore_ascii_case("i") => true,
+        Some(flag) if flag.eq_ignore_ascii_case("s") => false,
+        Some(_) => return None,
+        None => false,
+    };
+    parser.skip_whitespace();
+    (parser.position == contents.len()).then_some(AttributeSelector {
+        name,
+        value: Some((operator, value)),
+        case_insensitive,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::Stylesheet;
+
+    fn specificity(selector: &str) -> (u32, u32, u32) {
+        let list = SelectorList::parse(selector).unwrap();
+        let specificity = list.selectors()[0].specificity();
+        (specificity.ids, specificity.classes, specificity.types)
+    }
+
+    #[test]
+    fn test_specificity() {
+        assert_eq!(specificity("*"), (0, 0, 0));
+        assert_eq!(specificity("li"), (0, 0, 1));
+        assert_eq!(specificity("ul li"), (0, 0, 2));
+        assert_eq!(specificity("ul > li.red"), (0, 1, 2));
+        assert_eq!(specificity("#x[lang=en]:first-child"), (1, 2, 0));
+        assert_eq!(specificity("#a #b .c"), (2, 1, 0));
+        assert!(
+            SelectorList::parse("#a").unwrap().selectors()[0].specificity()
+                > SelectorList::parse(".a.b.c.d").unwrap().selectors()[0].specificity()
+        );
+    }
+
+    #[test]
+    fn test_uses_hover() {
+        let uses_hover = |selectors| SelectorList::parse(selectors).unwrap().uses_hover();
+        assert!(uses_hover("a:hover"));
+        assert!(uses_hover("p, li:hover > span"));
+        assert!(!uses_hover("a:active, a:link"));
+    }
+
+    #[test]
+    fn test_style_rule_selectors_match_elements() {
+        let sheet = Stylesheet::parse("div > p.note, #main em { color: red } p::before {}");
+        let document =
+            Document::parse("<div id=main><p class=note>a <em>b</em></p></div><p class=note>");
+        let mut rules = sheet.style_rules();
+        let list = SelectorList::from_component_values(&rules.next().unwrap().prelude).unwrap();
+        assert_eq!(
+            SelectorList::from_component_values(&rules.next().unwrap().prelude),
+            None
+        );
+
+        let matched = document.query_selector_all("*").unwrap();
+        let matched: Vec<_> = matched
+            .into_iter()
+            .filter_map(|id| Some((id, list.matching_specificity(&document, id)?)))
+            .map(|(id, specificity)| {
+                (
+                    document.element(id).unwrap().name.clone(),
+                    (specificity.ids, specificity.classes, specificity.types),
+                )
+            })
+            .collect();
+        assert_eq!(
+            matched,
+            vec![("p".to_owned(), (0, 1, 2)), ("em".to_owned(), (1, 0, 1))]
+        );
+    }
+
+    #[test]
+    fn test_attribute_operators_and_sibling_combinators() {
+        let document = Document::parse(
+            "<h1 id=h>h</h1><p id=a lang=en-US class='x  y' title=Hello>a</p>\
+             <!-- c --><p id=b lang=en data-x=abc>b</p>text<div id=c><p id=d>d</p></div><p id=e>e</p>",
+        );
+        let ids = |selector: &str| -> Vec<String> {
+            document
+                .query_selector_all(selector)
+                .unwrap()
+                .into_iter()
+                .map(|id| {
+                    document
+                        .element(id)
+                        .unwrap()
+                        .get_attribute("id")
+                        .unwrap()
+                        .to_owned()
+                })
+                .collect()
+        };
+        assert_eq!(ids("[lang|=en]"), ["a", "b"]);
+        assert_eq!(ids("[lang=EN i]"), ["b"]);
+        assert_eq!(ids("[lang='EN' s]"), Vec::<String>::new());
+        assert_eq!(ids("[class~=y]"), ["a"]);
+        assert_eq!(ids("[class~='x y']"), Vec::<String>::new());
+        assert_eq!(ids("[title^=He][title$=lo][title*=ell]"), ["a"]);
+        assert_eq!(ids("[data-x^='']"), Vec::<String>::new());
+        // Comments and text between elements do not count.
+        assert_eq!(ids("h1 + p"), ["a"]);
+        assert_eq!(ids("p + p"), ["b"]);
+        assert_eq!(ids("h1 ~ p"), ["a", "b", "e"]);
+        assert_eq!(ids("h1 ~ div > p"), ["d"]);
+        assert_eq!(ids("#a ~ p + div p"), ["d"]);
+        assert_eq!(specificity("h1 + p[lang|=en]"), (0, 1, 2));
+    }
+
+    #[test]
+    fn test_unsupported_selectors_are_rejected() {
+        for selector in [
+            "",
+            " ",
+            "p,",
+            "> p",
+            "p ~",
+            "p + > p",
+            "p:hover()",
+            "p:nth-child",
+            "p[lang=]",
+            "p[lang^ =en]",
+            "p[lang%=en]",
+            "p[lang=en x]",
+            "#1",
+        ] {
+            assert!(SelectorList::parse(selector).is_err(), "{selector}");
+        }
+        assert!(SelectorList::parse(" p , [ lang = 'en' ] ").is_ok());
+    }
+}
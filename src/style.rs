@@ -0,0 +1,655 @@
+//! Computed styles: which value each property of each element ends up
+//! with, given the document and its style sheets.
+//!
+//! The [`Cascade`] holds the style rules of every sheet. For each element it
+//! sorts the declarations that apply by origin and importance, then by
+//! whether they come from the `style` attribute, then by the specificity of
+//! the selector, then by order, and the last declaration for a property
+//! wins (<https://www.w3.org/TR/css-cascade-4/#cascade-sort>). Of the
+//! properties no declaration sets, inherited ones such as `color` take the
+//! parent element's value, and the others get their initial value.
+//!
+//! Which rules apply also depends on the [`Device`] the page is shown on,
+//! through `@media` rules and `media` attributes, so styles must be
+//! computed again when the window is resized.
+
+mod color;
+mod computed;
+mod explain;
+mod inspect;
+mod media;
+mod shorthands;
+mod variables;
+
+pub use color::Color;
+pub use computed::{ComputedStyle, Display};
+pub use explain::{ConsideredDeclaration, explain};
+pub use inspect::{InspectedStylesheet, MatchedRule, StylesheetSource, stylesheets};
+pub use media::{ColorScheme, Device, MediaQueryList};
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use shorthands::Longhand;
+
+use crate::css::{AtRule, AtRuleBlock, Rule, StyleRule, Stylesheet, parse_declarations};
+use crate::dom::{Document, ImportedStylesheet, NodeId};
+use crate::selectors::{SelectorList, Specificity};
+
+static USER_AGENT_STYLESHEET: LazyLock<Stylesheet> =
+    LazyLock::new(|| Stylesheet::parse(include_str!("style/ua.css")));
+
+/// The browser's default styles, which give elements their usual look on a
+/// page with no styles of its own: blocks and list items, heading sizes,
+/// underlined links and so on.
+pub fn user_agent_stylesheet() -> &'static Stylesheet {
+    &USER_AGENT_STYLESHEET
+}
+
+/// Where a style sheet comes from. Later origins win over earlier ones for
+/// normal declarations, and lose to them for `!important` ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Origin {
+    /// The browser's default styles.
+    UserAgent,
+
+    /// Styles the user set up.
+    User,
+
+    /// The page's own styles.
+    Author,
+}
+
+/// A style rule with its selectors parsed, and its declarations of
+/// shorthands such as `margin` expanded into longhands such as
+/// `margin-top`.
+#[derive(Debug, Clone)]
+struct CascadeRule {
+    origin: Origin,
+    selectors: SelectorList,
+    declarations: Vec<Longhand>,
+
+    /// The sheet the rule comes from, as an index into
+    /// [`Cascade::sources`].
+    source: usize,
+
+    /// The rule as it was parsed, for inspecting.
+    rule: StyleRule,
+}
+
+/// How a declaration ranks in the cascade. The derived order compares the
+/// fields in order, and the greatest wins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Precedence {
+    /// The origin and importance: normal user agent, user and author
+    /// declarations, then important author, user and user agent ones.
+    layer: u8,
+
+    /// Whether the declaration is in the element's `style` attribute.
+    inline: bool,
+    specificity: Specificity,
+
+    /// The position of the rule among all rules.
+    order: usize,
+}
+
+impl Precedence {
+    fn layer(origin: Origin, important: bool) -> u8 {
+        let rank = match origin {
+            Origin::UserAgent => 0,
+            Origin::User => 1,
+            Origin::Author => 2,
+        };
+        if important { 5 - rank } else { rank }
+    }
+}
+
+/// The style rules of a set of style sheets that apply on a device, ready
+/// to be matched against elements.
+#[derive(Debug, Clone, Default)]
+pub struct Cascade {
+    device: Device,
+    rules: Vec<CascadeRule>,
+
+    /// Where each style sheet added comes from, in the order they were
+    /// added.
+    sources: Vec<StylesheetSource>,
+}
+
+impl Cascade {
+    /// An empty cascade for the default [`Device`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// An empty cascade whose media queries are evaluated against
+    /// `device`.
+    pub fn for_device(device: Device) -> Self {
+        Self {
+            device,
+            rules: Vec::new(),
+            sources: Vec::new(),
+        }
+    }
+
+    /// The cascade for showing `document` on `device`: the user agent
+    /// style sheet, then the style sheets of the document.
+    pub fn for_document(document: &Document, device: Device) -> Self {
+        let mut cascade = Self::for_device(device);
+        cascade.add_sheet(
+            Origin::UserAgent,
+            user_agent_stylesheet(),
+            StylesheetSource::UserAgent,
+        );
+        cascade.add_document_stylesheets(document);
+        cascade
+    }
+
+    pub fn device(&self) -> &Device {
+        &self.device
+    }
+
+    /// Adds the style rules of `sheet`, including those inside grouping
+    /// rules such as `@supports`, and inside `@media` rules that match the
+    /// device. Of two rules that are otherwise equal, the one added later
+    /// wins. Rules whose selectors the engine does not support are dropped,
+    /// as a browser drops invalid ones.
+    pub fn add_stylesheet(&mut self, origin: Origin, sheet: &Stylesheet) {
+        self.add_sheet(origin, sheet, StylesheetSource::Other);
+    }
+
+    fn add_sheet(&mut self, origin: Origin, sheet: &Stylesheet, source: StylesheetSource) {
+        self.sources.push(source);
+        self.add_rules(origin, &sheet.rules, self.sources.len() - 1);
+    }
+
+    fn add_rules(&mut self, origin: Origin, rules: &[Rule], source: usize) {
+        for rule in rules {
+            match rule {
+                Rule::Style(rule) => {
+                    if let Some(selectors) = SelectorList::from_component_values(&rule.prelude) {
+                        self.rules.push(CascadeRule {
+                            origin,
+                            selectors,
+                            declarations: rule
+                                .declarations
+                                .iter()
+                                .flat_map(shorthands::expand)
+                                .collect(),
+                            source,
+                            rule: rule.clone(),
+                        });
+                    }
+                }
+                Rule::At(AtRule {
+                    name,
+                    prelude,
+                    block: Some(AtRuleBlock::Rules(rules)),
+                    ..
+                }) => {
+                    if name != "media"
+                        || MediaQueryList::from_component_values(prelude).matches(&self.device)
+                    {
+                        self.add_rules(origin, rules, source);
+                    }
+                }
+                Rule::At(_) => {}
+            }
+        }
+    }
+
+    /// Adds the style sheets `document` brings with it, as author styles,
+    /// each after the sheets it imports, leaving out those whose `media`
+    /// attribute or `@import` media queries do not match the device.
+    pub fn add_document_stylesheets(&mut self, document: &Document) {
+        for stylesheet in document.stylesheets() {
+            let media = stylesheet.media.as_deref().map(MediaQueryList::parse);
+            if media.is_none_or(|media| media.matches(&self.device)) {
+                self.add_imports(&stylesheet.imports);
+                let source = StylesheetSource::Element(stylesheet.owner);
+                self.add_sheet(Origin::Author, &stylesheet.sheet, source);
+            }
+        }
+    }
+
+    fn add_imports(&mut self, imports: &[ImportedStylesheet]) {
+        for import in imports {
+            if MediaQueryList::from_component_values(&import.media).matches(&self.device) {
+                self.add_imports(&import.imports);
+                let source = StylesheetSource::Import(import.url.clone());
+                self.add_sheet(Origin::Author, &import.sheet, source);
+            }
+        }
+    }
+
+    /// The computed style of every element of `document`.
+    pub fn compute(&self, document: &Document) -> Styles {
+        let mut styles = HashMap::new();
+        // Parents come before their children, so their style is ready.
+        for id in document.descendants(document.root()) {
+            if document.element(id).is_none() {
+                continue;
+            }
+            let parent = document.parent(id).and_then(|parent| styles.get(&parent));
+            let style = self.compute_element(document, id, parent);
+            styles.insert(id, style);
+        }
+        Styles(styles)
+    }
+
+    /// Whether any rule of the cascade has `:hover` in its selectors, so
+    /// that the styles it computes can change as the pointer moves.
+    pub fn uses_hover(&self) -> bool {
+        self.rules.iter().any(|rule| rule.selectors.uses_hover())
+    }
+
+    /// Computes `styles` again where a change in the state of the elements
+    /// `changed` can affect them, such as a change of the element under
+    /// the pointer, as reported by [`Document::set_hovered`]. A rule like
+    /// `:hover > p` or `:hover + p` can match the descendants of a changed
+    /// element, or the elements after it and their descendants, so those
+    /// subtrees are restyled, but nothing else is.
+    pub fn restyle(&self, document: &Document, styles: &mut Styles, changed: &[NodeId]) {
+        let mut roots: Vec<NodeId> = changed
+            .iter()
+            .flat_map(|&id| std::iter::once(id).chain(document.next_siblings(id)))
+            .filter(|&id| document.element(id).is_some())
+            .collect();
+        roots.sort_unstable();
+        roots.dedup();
+        let inside_other_root = |id: NodeId| {
+            document
+                .ancestors(id)
+                .any(|ancestor| roots.binary_search(&ancestor).is_ok())
+        };
+        let roots: Vec<NodeId> = roots
+            .iter()
+            .copied()
+            .filter(|&id| !inside_other_root(id))
+            .collect();
+        for root in roots {
+            // Parents come before their children, so their style is ready.
+            for id in std::iter::once(root).chain(document.descendants(root)) {
+                if document.element(id).is_none() {
+                    continue;
+                }
+                let parent = document.parent(id).and_then(|parent| styles.0.get(&parent));
+                let style = self.compute_element(document, id, parent);
+                styles.0.insert(id, style);
+            }
+        }
+    }
+
+    /// The computed style of the element `id`, whose parent element has the
+    /// style `parent`, if it has a parent element.
+    pub fn compute_element(
+        &self,
+        document: &Document,
+        id: NodeId,
+        parent: Option<&ComputedStyle>,
+    ) -> ComputedStyle {
+        let inline = inline_declarations(document, id);
+        let candidates = self.candidates(document, id, &inline);
+        let mut style = ComputedStyle::default();
+        if let Some(parent) = parent {
+            style.inherit_custom_properties(parent);
+        }
+        for Candidate { declaration, .. } in candidates {
+            let (name, value) = (declaration.name.clone(), declaration.value.clone());
+            match declaration.pending {
+                Some(shorthand) => style.set_pending(name, value, shorthand),
+                None => style.set(name, value),
+            }
+        }
+        style.resolve_variables();
+        style.apply_defaulting(parent);
+        style
+    }
+
+    /// The declarations that apply to the element `id`, from its matching
+    /// rules and from `inline`, the declarations of its `style` attribute,
+    /// sorted so that the last declaration for a property wins.
+    fn candidates<'c>(
+        &'c self,
+        document: &Document,
+        id: NodeId,
+        inline: &'c [Longhand],
+    ) -> Vec<Candidate<'c>> {
+        let mut candidates = Vec::new();
+        for (order, rule) in self.rules.iter().enumerate() {
+            let Some(specificity) = rule.selectors.matching_specificity(document, id) else {
+                continue;
+            };
+            candidates.extend(rule.declarations.iter().map(|declaration| Candidate {
+                precedence: Precedence {
+                    layer: Precedence::layer(rule.origin, declaration.important),
+                    inline: false,
+                    specificity,
+                    order,
+                },
+                declaration,
+                rule: Some(rule),
+            }));
+        }
+        candidates.extend(inline.iter().map(|declaration| Candidate {
+            precedence: Precedence {
+                layer: Precedence::layer(Origin::Author, declaration.important),
+                inline: true,
+                specificity: Specificity::default(),
+                order: self.rules.len(),
+            },
+            declaration,
+            rule: None,
+        }));
+
+        // A stable sort keeps declarations of one rule in source order, so
+        // the last of them wins.
+        candidates.sort_by_key(|candidate| candidate.precedence);
+        candidates
+    }
+}
+
+/// A declaration that applies to an element; see [`Cascade::candidates`].
+#[derive(Debug, Clone, Copy)]
+struct Candidate<'c> {
+    precedence: Precedence,
+    declaration: &'c Longhand,
+
+    /// The rule the declaration is in, or `None` if it is in the `style`
+    /// attribute.
+    rule: Option<&'c CascadeRule>,
+}
+
+/// The declarations of the `style` attribute of the element `id`, with
+/// shorthands expanded.
+fn inline_declarations(document: &Document, id: NodeId) -> Vec<Longhand> {
+    document
+        .element(id)
+        .and_then(|element| element.get_attribute("style"))
+        .map(parse_declarations)
+        .unwrap_or_default()
+        .iter()
+        .flat_map(shorthands::expand)
+        .collect()
+}
+
+/// The computed styles of the elements of a document; see
+/// [`Cascade::compute`].
+#[derive(Debug, Clone, Default)]
+pub struct Styles(HashMap<NodeId, ComputedStyle>);
+
+impl Styles {
+    /// The style of `node`, or `None` if it is not an element of the
+    /// document the styles were computed for.
+    pub fn style_for(&self, node: NodeId) -> Option<&ComputedStyle> {
+        self.0.get(&node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::{ComponentValue, CssToken, parse_component_values};
+
+    fn keyword<'s>(styles: &'s Styles, document: &Document, id: &str, property: &str) -> &'s str {
+        let id = document.get_element_by_id(id).unwrap();
+        styles.style_for(id).unwrap().keyword(property).unwrap()
+    }
+
+    #[test]
+    fn test_cascade_order() {
+        let document = Document::parse(
+            "<p id=a class=note>a</p><p id=b class=note style='color: teal'>b</p>\
+             <p id=c style='color: teal'>c</p><p id=d>d</p>",
+        );
+        let mut cascade = Cascade::new();
+        cascade.add_stylesheet(
+            Origin::UserAgent,
+            &Stylesheet::parse("p { display: block; color: black } #d { color: gray !important }"),
+        );
+        cascade.add_stylesheet(
+            Origin::Author,
+            &Stylesheet::parse(
+                "#a { color: red } p.note { color: green; color: blue } \
+                 p { color: olive !important } #c { color: navy !important } \
+                 #d { color: purple !important } p:hover { color: pink }",
+            ),
+        );
+        let styles = cascade.compute(&document);
+
+        // An ID beats a class, whatever the order.
+        assert_eq!(keyword(&styles, &document, "a", "color"), "olive");
+        assert_eq!(keyword(&styles, &document, "b", "color"), "olive");
+        assert_eq!(keyword(&styles, &document, "c", "color"), "navy");
+        // Important user agent declarations beat important author ones.
+        assert_eq!(keyword(&styles, &document, "d", "color"), "gray");
+        assert_eq!(keyword(&styles, &document, "a", "display"), "block");
+    }
+
+    #[test]
+    fn test_style_attribute_and_defaulting() {
+        let document = Document::parse("<p id=a class=x style='margin-top: 2px'>a</p>");
+        let mut cascade = Cascade::new();
+        cascade.add_stylesheet(
+            Origin::Author,
+            &Stylesheet::parse(".x { margin-top: 1px; color: red } #a.x { color: blue }"),
+        );
+        let styles = cascade.compute(&document);
+        let a = document.get_element_by_id("a").unwrap();
+        let style = styles.style_for(a).unwrap();
+        assert!(matches!(
+            style.value("margin-top"),
+            Some([ComponentValue::Token(CssToken::Dimension { unit, .. })]) if unit == "px"
+        ));
+        assert_eq!(
+            cascade
+                .compute_element(&document, a, None)
+                .value("margin-top"),
+            style.value("margin-top")
+        );
+        assert_eq!(style.keyword("color"), Some("blue"));
+        assert_eq!(style.display(), Display::Inline);
+        assert_eq!(style.keyword("visibility"), Some("visible"));
+        assert_eq!(styles.style_for(document.root()), None);
+    }
+
+    #[test]
+    fn test_media_rules() {
+        let document = Document::parse(
+            "<style>@media (max-width: 600px) { span { color: red } }\
+             @supports (display: grid) { @media print { span { color: blue } } }</style>\
+             <style media='(min-width: 601px)'>span { display: block }</style><span id=a></span>",
+        );
+        let narrow = Device {
+            width: 400.0,
+            ..Device::default()
+        };
+        let styles = Cascade::for_document(&document, narrow).compute(&document);
+        assert_eq!(keyword(&styles, &document, "a", "color"), "red");
+        assert_eq!(keyword(&styles, &document, "a", "display"), "inline");
+
+        let styles = Cascade::for_document(&document, Device::default()).compute(&document);
+        assert_eq!(keyword(&styles, &document, "a", "color"), "canvastext");
+        assert_eq!(keyword(&styles, &document, "a", "display"), "block");
+    }
+
+    #[test]
+    fn test_imported_stylesheets() {
+        let mut document = Document::parse(
+            "<style>@import 'a.css'; @import 'b.css' (max-width: 600px); \
+             span { display: block; color: red }</style><span id=a></span>",
+        );
+        let import = |url: &str, media: &str, css: &str| ImportedStylesheet {
+            url: url.to_owned(),
+            media: parse_component_values(media),
+            sheet: Stylesheet::parse(css),
+            imports: Vec::new(),
+        };
+        let mut a = import("a.css", "", "span { color: blue; visibility: hidden }");
+        a.imports
+            .push(import("c.css", "", "span { visibility: collapse }"));
+        let b = import(
+            "b.css",
+            "(max-width: 600px)",
+            "span { display: inline-block }",
+        );
+        let style = document.get_elements_by_tag_name("style")[0];
+        document.set_stylesheet_imports(style, vec![a, b]);
+
+        // Imported rules come first, so the importing sheet wins.
+        let styles = Cascade::for_document(&document, Device::default()).compute(&document);
+        assert_eq!(keyword(&styles, &document, "a", "color"), "red");
+        assert_eq!(keyword(&styles, &document, "a", "visibility"), "hidden");
+        assert_eq!(keyword(&styles, &document, "a", "display"), "block");
+    }
+
+    #[test]
+    fn test_custom_properties() {
+        let document =
+            Document::parse("<div id=a style='--accent: navy'><p id=b>b</p></div><p id=c>c</p>");
+        let mut cascade = Cascade::new();
+        cascade.add_stylesheet(
+            Origin::Author,
+            &Stylesheet::parse(
+                ":root { --accent: red; --Case: block } p { color: var(--accent) } \
+                 #c { display: var(--case, inline-block) } #b { display: var(--Case) }",
+            ),
+        );
+        let styles = cascade.compute(&document);
+        assert_eq!(keyword(&styles, &document, "b", "color"), "navy");
+        assert_eq!(keyword(&styles, &document, "c", "color"), "red");
+        assert_eq!(keyword(&styles, &document, "b", "--accent"), "navy");
+        // Custom property names are case-sensitive.
+        assert_eq!(keyword(&styles, &document, "b", "display"), "block");
+        assert_eq!(keyword(&styles, &document, "c", "display"), "inline-block");
+    }
+
+    #[test]
+    fn test_inheritance() {
+        let document = Document::parse(
+            "<div id=a style='color: green; font: italic 20px serif; border-top-style: solid'>\
+             <p id=b style='font-size: 2em; color: var(--missing)'><span id=c>c</span></p>\
+             <p id=d style='border-top-style: inherit; font-style: initial'>d</p></div>",
+        );
+        let styles = Cascade::new().compute(&document);
+        assert_eq!(keyword(&styles, &document, "c", "color"), "green");
+        assert_eq!(keyword(&styles, &document, "c", "font-style"), "italic");
+        assert_eq!(keyword(&styles, &document, "c", "font-family"), "serif");
+        let c = document.get_element_by_id("c").unwrap();
+        assert_eq!(styles.style_for(c).unwrap().font_size(), 40.0);
+        assert_eq!(keyword(&styles, &document, "c", "border-top-style"), "none");
+        assert_eq!(
+            keyword(&styles, &document, "d", "border-top-style"),
+            "solid"
+        );
+        assert_eq!(keyword(&styles, &document, "d", "font-style"), "normal");
+    }
+
+    #[test]
+    fn test_dynamic_pseudo_classes() {
+        let mut document = Document::parse(
+            "<div id=a><p id=b><em id=c>c</em></p><p id=d>d</p></div><p id=e>e</p>\
+             <a id=link href=/seen>seen</a><a id=other href=/new>new</a>",
+        );
+        document.set_url("https://example.com/".to_owned());
+        document.add_visited_urls(["https://example.com/seen".to_owned()]);
+        let mut cascade = Cascade::new();
+        cascade.add_stylesheet(
+            Origin::Author,
+            &Stylesheet::parse(
+                "p:hover { color: red } :hover > em { display: block } \
+                 p:hover + p { color: blue } :visited { color: purple } :link { color: teal }",
+            ),
+        );
+        let mut styles = cascade.compute(&document);
+        assert_eq!(keyword(&styles, &document, "link", "color"), "purple");
+        assert_eq!(keyword(&styles, &document, "other", "color"), "teal");
+        assert_eq!(keyword(&styles, &document, "b", "color"), "canvastext");
+
+        let c = document.get_element_by_id("c").unwrap();
+        let changed = document.set_hovered(Some(c));
+        cascade.restyle(&document, &mut styles, &changed);
+        assert_eq!(keyword(&styles, &document, "b", "color"), "red");
+        assert_eq!(keyword(&styles, &document, "c", "color"), "red");
+        assert_eq!(keyword(&styles, &document, "c", "display"), "block");
+        assert_eq!(keyword(&styles, &document, "d", "color"), "blue");
+        assert_eq!(keyword(&styles, &document, "e", "color"), "canvastext");
+        assert_eq!(styles.0, cascade.compute(&document).0);
+
+        let changed = document.set_hovered(None);
+        cascade.restyle(&document, &mut styles, &changed);
+        assert_eq!(keyword(&styles, &document, "d", "color"), "canvastext");
+        assert_eq!(styles.0, cascade.compute(&document).0);
+    }
+
+    #[test]
+    fn test_shorthands() {
+        let document = Document::parse("<p id=a>a</p><p id=b style='--w: 3px'>b</p>");
+        let mut cascade = Cascade::new();
+        cascade.add_stylesheet(
+            Origin::Author,
+            &Stylesheet::parse(
+                "p { border: solid var(--w, 1px) red; margin-left: 5px; margin: 0 auto } \
+                 #b { border-top-style: dashed; margin: var(--w) var(--w) var(--w) var(--w) var(--w) }",
+            ),
+        );
+        let styles = cascade.compute(&document);
+        let width = |id: &str, property: &str| {
+            let id = document.get_element_by_id(id).unwrap();
+            match styles.style_for(id).unwrap().value(property) {
+                Some([ComponentValue::Token(CssToken::Dimension { value, .. })]) => value.value,
+                _ => 0.0,
+            }
+        };
+        assert_eq!(width("a", "border-left-width"), 1.0);
+        assert_eq!(width("b", "border-left-width"), 3.0);
+        assert_eq!(
+            keyword(&styles, &document, "a", "border-top-style"),
+            "solid"
+        );
+        assert_eq!(
+            keyword(&styles, &document, "b", "border-top-style"),
+            "dashed"
+        );
+        assert_eq!(keyword(&styles, &document, "a", "margin-right"), "auto");
+        // A shorthand whose value is invalid once references are replaced
+        // leaves its longhands with their initial value.
+        let b = document.get_element_by_id("b").unwrap();
+        assert_eq!(
+            styles.style_for(b).unwrap().value("margin-left"),
+            Some(parse_component_values("0").as_slice())
+        );
+    }
+
+    #[test]
+    fn test_document_stylesheets() {
+        let document = Document::parse(
+            "<style>p { color: red }</style><style media=print>p { color: black }</style>\
+             <p id=a>a</p><style>#a { display: inline }</style>\
+             <h1 id=h>h</h1><ul><li id=li><a id=link href=/>a</a><a id=anchor>b</a></ul>\
+             <div id=hidden hidden></div><input id=hidden-input type=hidden>",
+        );
+        let styles = Cascade::for_document(&document, Device::default()).compute(&document);
+        assert_eq!(keyword(&styles, &document, "a", "color"), "red");
+        assert_eq!(keyword(&styles, &document, "a", "display"), "inline");
+
+        assert_eq!(keyword(&styles, &document, "h", "display"), "block");
+        assert_eq!(keyword(&styles, &document, "h", "font-weight"), "bold");
+        assert_eq!(keyword(&styles, &document, "li", "display"), "list-item");
+        assert_eq!(
+            keyword(&styles, &document, "link", "text-decoration-line"),
+            "underline"
+        );
+        assert_eq!(
+            keyword(&styles, &document, "anchor", "text-decoration-line"),
+            "none"
+        );
+        assert_eq!(keyword(&styles, &document, "hidden", "display"), "none");
+        assert_eq!(
+            keyword(&styles, &document, "hidden-input", "display"),
+            "none"
+        );
+        let head = document.head().unwrap();
+        assert_eq!(styles.style_for(head).unwrap().display(), Display::None);
+    }
+}
@@ -0,0 +1,443 @@
+use crate::css::{ComponentValue, CssToken};
+
+/// A color in sRGB with straight, not premultiplied, alpha; what every
+/// color value computes to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+
+    /// 0 is fully transparent and 255 fully opaque.
+    pub a: u8,
+}
+
+/// The named colors of CSS Color 4, sorted by name so they can be
+/// binary searched.
+const NAMED_COLORS: [(&str, u32); 148] = [
+    ("aliceblue", 0xf0f8ff),
+    ("antiquewhite", 0xfaebd7),
+    ("aqua", 0x00ffff),
+    ("aquamarine", 0x7fffd4),
+    ("azure", 0xf0ffff),
+    ("beige", 0xf5f5dc),
+    ("bisque", 0xffe4c4),
+    ("black", 0x000000),
+    ("blanchedalmond", 0xffebcd),
+    ("blue", 0x0000ff),
+    ("blueviolet", 0x8a2be2),
+    ("brown", 0xa52a2a),
+    ("burlywood", 0xdeb887),
+    ("cadetblue", 0x5f9ea0),
+    ("chartreuse", 0x7fff00),
+    ("chocolate", 0xd2691e),
+    ("coral", 0xff7f50),
+    ("cornflowerblue", 0x6495ed),
+    ("cornsilk", 0xfff8dc),
+    ("crimson", 0xdc143c),
+    ("cyan", 0x00ffff),
+    ("darkblue", 0x00008b),
+    ("darkcyan", 0x008b8b),
+    ("darkgoldenrod", 0xb8860b),
+    ("darkgray", 0xa9a9a9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xa9a9a9),
+    ("darkkhaki", 0xbdb76b),
+    ("darkmagenta", 0x8b008b),
+    ("darkolivegreen", 0x556b2f),
+    ("darkorange", 0xff8c00),
+    ("darkorchid", 0x9932cc),
+    ("darkred", 0x8b0000),
+    ("darksalmon", 0xe9967a),
+    ("darkseagreen", 0x8fbc8f),
+    ("darkslateblue", 0x483d8b),
+    ("darkslategray", 0x2f4f4f),
+    ("darkslategrey", 0x2f4f4f),
+    ("darkturquoise", 0x00ced1),
+    ("darkviolet", 0x9400d3),
+    ("deeppink", 0xff1493),
+    ("deepskyblue", 0x00bfff),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1e90ff),
+    ("firebrick", 0xb22222),
+    ("floralwhite", 0xfffaf0),
+    ("forestgreen", 0x228b22),
+    ("fuchsia", 0xff00ff),
+    ("gainsboro", 0xdcdcdc),
+    ("ghostwhite", 0xf8f8ff),
+    ("gold", 0xffd700),
+    ("goldenrod", 0xdaa520),
+    ("gray", 0x808080),
+    ("green", 0x008000),
+    ("greenyellow", 0xadff2f),
+    ("grey", 0x808080),
+    ("honeydew", 0xf0fff0),
+    ("hotpink", 0xff69b4),
+    ("indianred", 0xcd5c5c),
+    ("indigo", 0x4b0082),
+    ("ivory", 0xfffff0),
+    ("khaki", 0xf0e68c),
+    ("lavender", 0xe6e6fa),
+    ("lavenderblush", 0xfff0f5),
+    ("lawngreen", 0x7cfc00),
+    ("lemonchiffon", 0xfffacd),
+    ("lightblue", 0xadd8e6),
+    ("lightcoral", 0xf08080),
+    ("lightcyan", 0xe0ffff),
+    ("lightgoldenrodyellow", 0xfafad2),
+    ("lightgray", 0xd3d3d3),
+    ("lightgreen", 0x90ee90),
+    ("lightgrey", 0xd3d3d3),
+    ("lightpink", 0xffb6c1),
+    ("lightsalmon", 0xffa07a),
+    ("lightseagreen", 0x20b2aa),
+    ("lightskyblue", 0x87cefa),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xb0c4de),
+    ("lightyellow", 0xffffe0),
+    ("lime", 0x00ff00),
+    ("limegreen", 0x32cd32),
+    ("linen", 0xfaf0e6),
+    ("magenta", 0xff00ff),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66cdaa),
+    ("mediumblue", 0x0000cd),
+    ("mediumorchid", 0xba55d3),
+    ("mediumpurple", 0x9370db),
+    ("mediumseagreen", 0x3cb371),
+    ("mediumslateblue", 0x7b68ee),
+    ("mediumspringgreen", 0x00fa9a),
+    ("mediumturquoise", 0x48d1cc),
+    ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xf5fffa),
+    ("mistyrose", 0xffe4e1),
+    ("moccasin", 0xffe4b5),
+    ("navajowhite", 0xffdead),
+    ("navy", 0x000080),
+    ("oldlace", 0xfdf5e6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6b8e23),
+    ("orange", 0xffa500),
+    ("orangered", 0xff4500),
+    ("orchid", 0xda70d6),
+    ("palegoldenrod", 0xeee8aa),
+    ("palegreen", 0x98fb98),
+    ("paleturquoise", 0xafeeee),
+    ("palevioletred", 0xdb7093),
+    ("papayawhip", 0xffefd5),
+    ("peachpuff", 0xffdab9),
+    ("peru", 0xcd853f),
+    ("pink", 0xffc0cb),
+    ("plum", 0xdda0dd),
+    ("powderblue", 0xb0e0e6),
+    ("purple", 0x800080),
+    ("rebeccapurple", 0x663399),
+    ("red", 0xff0000),
+    ("rosybrown", 0xbc8f8f),
+    ("royalblue", 0x4169e1),
+    ("saddlebrown", 0x8b4513),
+    ("salmon", 0xfa8072),
+    ("sandybrown", 0xf4a460),
+    ("seagreen", 0x2e8b57),
+    ("seashell", 0xfff5ee),
+    ("sienna", 0xa0522d),
+    ("silver", 0xc0c0c0),
+    ("skyblue", 0x87ceeb),
+    ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xfffafa),
+    ("springgreen", 0x00ff7f),
+    ("steelblue", 0x4682b4),
+    ("tan", 0xd2b48c),
+    ("teal", 0x008080),
+    ("thistle", 0xd8bfd8),
+    ("tomato", 0xff6347),
+    ("turquoise", 0x40e0d0),
+    ("violet", 0xee82ee),
+    ("wheat", 0xf5deb3),
+    ("white", 0xffffff),
+    ("whitesmoke", 0xf5f5f5),
+    ("yellow", 0xffff00),
+    ("yellowgreen", 0x9acd32),
+];
+
+/// The system colors, which stand for the colors of the platform's own
+/// controls. These are the light scheme colors browsers commonly use.
+const SYSTEM_COLORS: [(&str, u32); 15] = [
+    ("activetext", 0xff0000),
+    ("buttonborder", 0x767676),
+    ("buttonface", 0xefefef),
+    ("buttontext", 0x000000),
+    ("canvas", 0xffffff),
+    ("canvastext", 0x000000),
+    ("field", 0xffffff),
+    ("fieldtext", 0x000000),
+    ("graytext", 0x808080),
+    ("highlight", 0xb5d5ff),
+    ("highlighttext", 0x000000),
+    ("linktext", 0x0000ee),
+    ("mark", 0xffff00),
+    ("marktext", 0x000000),
+    ("visitedtext", 0x551a8b),
+];
+
+impl Color {
+    pub const TRANSPARENT: Self = Self::rgba(0, 0, 0, 0);
+    pub const BLACK: Self = Self::rgb(0, 0, 0);
+    pub const WHITE: Self = Self::rgb(255, 255, 255);
+
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self::rgba(r, g, b, 255)
+    }
+
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// Parses a color value: a named or system color, `transparent`,
+    /// `#rgb`, `#rgba`, `#rrggbb`, `#rrggbbaa`, `rgb()`, `rgba()`,
+    /// `hsl()`, `hsla()`, or `currentcolor`, which is `current`.
+    pub fn parse(value: &[ComponentValue], current: Self) -> Option<Self> {
+        let mut values = value
+            .iter()
+            .filter(|value| !matches!(value, ComponentValue::Token(CssToken::Whitespace)));
+        let (Some(value), None) = (values.next(), values.next()) else {
+            return None;
+        };
+        match value {
+            ComponentValue::Token(CssToken::Ident(name)) => {
+                let name = name.to_ascii_lowercase();
+                match name.as_str() {
+                    "transparent" => Some(Self::TRANSPARENT),
+                    "currentcolor" => Some(current),
+                    name => Self::named(name),
+                }
+            }
+            ComponentValue::Token(CssToken::Hash { value, .. }) => Self::hex(value),
+            ComponentValue::Function { name, arguments } => {
+                match name.to_ascii_lowercase().as_str() {
+                    "rgb" | "rgba" => Self::rgb_function(arguments),
+                    "hsl" | "hsla" => Self::hsl_function(arguments),
+                    _ => None,
+                }
+            }
+            ComponentValue::Token(_) | ComponentValue::Block { .. } => None,
+        }
+    }
+
+    /// A named or system color, by its lowercase name.
+    fn named(name: &str) -> Option<Self> {
+        [&NAMED_COLORS[..], &SYSTEM_COLORS[..]]
+            .into_iter()
+            .find_map(|colors| {
+                let index = colors
+                    .binary_search_by(|&(candidate, _)| candidate.cmp(name))
+                    .ok()?;
+                colors.get(index)
+            })
+            .map(|&(_, rgb)| {
+                let [_, r, g, b] = rgb.to_be_bytes();
+                Self::rgb(r, g, b)
+            })
+    }
+
+    /// The color of a hex color such as `#f80` or `#ff880080`, without
+    /// the `#`.
+    fn hex(digits: &str) -> Option<Self> {
+        let digits: Vec<u8> = digits
+            .chars()
+            .map(|digit| u8::try_from(digit.to_digit(16)?).ok())
+            .collect::<Option<_>>()?;
+        match *digits.as_slice() {
+            [r, g, b] => Some(Self::rgb(r * 17, g * 17, b * 17)),
+            [r, g, b, a] => Some(Self::rgba(r * 17, g * 17, b * 17, a * 17)),
+            [r1, r2, g1, g2, b1, b2] => Some(Self::rgb(
+                hex_byte(r1, r2),
+                hex_byte(g1, g2),
+                hex_byte(b1, b2),
+            )),
+            [r1, r2, g1, g2, b1, b2, a1, a2] => Some(Self::rgba(
+                hex_byte(r1, r2),
+                hex_byte(g1, g2),
+                hex_byte(b1, b2),
+                hex_byte(a1, a2),
+            )),
+            _ => None,
+        }
+    }
+
+    /// `rgb(r, g, b)`, `rgb(r g b / a)` and the like. Each channel is a
+    /// number from 0 to 255 or a percentage.
+    fn rgb_function(arguments: &[ComponentValue]) -> Option<Self> {
+        let ([r, g, b], alpha) = channels(arguments)?;
+        let channel = |token: &CssToken| match token {
+            CssToken::Number(number) => Some(number.value),
+            CssToken::Percentage(number) => Some(number.value / 100.0 * 255.0),
+            CssToken::Ident(name) if name.eq_ignore_ascii_case("none") => Some(0.0),
+            _ => None,
+        };
+        Some(Self::rgba(
+            to_byte(channel(r)?),
+            to_byte(channel(g)?),
+            to_byte(channel(b)?),
+            alpha.map_or(Some(255), alpha_byte)?,
+        ))
+    }
+
+    /// `hsl(h, s%, l%)`, `hsl(h s l / a)` and the like, with the hue in
+    /// degrees or another angle unit.
+    fn hsl_function(arguments: &[ComponentValue]) -> Option<Self> {
+        let ([hue, saturation, lightness], alpha) = channels(arguments)?;
+        let hue = match hue {
+            CssToken::Number(number) => number.value,
+            CssToken::Dimension { value, unit } => match unit.to_ascii_lowercase().as_str() {
+                "deg" => value.value,
+                "grad" => value.value * 0.9,
+                "rad" => value.value.to_degrees(),
+                "turn" => value.value * 360.0,
+                _ => return None,
+            },
+            CssToken::Ident(name) if name.eq_ignore_ascii_case("none") => 0.0,
+            _ => return None,
+        };
+        let percentage = |token: &CssToken| match token {
+            CssToken::Number(number) | CssToken::Percentage(number) => {
+                Some((number.value / 100.0).clamp(0.0, 1.0))
+            }
+            CssToken::Ident(name) if name.eq_ignore_ascii_case("none") => Some(0.0),
+            _ => None,
+        };
+        let (saturation, lightness) = (percentage(saturation)?, percentage(lightness)?);
+
+        // https://www.w3.org/TR/css-color-4/#hsl-to-rgb
+        let channel = |n: f64| {
+            let k = (n + hue / 30.0).rem_euclid(12.0);
+            let a = saturation * lightness.min(1.0 - lightness);
+            to_byte((lightness - a * (k - 3.0).min(9.0 - k).clamp(-1.0, 1.0)) * 255.0)
+        };
+        Some(Self::rgba(
+            channel(0.0),
+            channel(8.0),
+            channel(4.0),
+            alpha.map_or(Some(255), alpha_byte)?,
+        ))
+    }
+}
+
+impl From<Color> for egui::Color32 {
+    fn from(color: Color) -> Self {
+        Self::from_rgba_unmultiplied(color.r, color.g, color.b, color.a)
+    }
+}
+
+/// Splits the arguments of a color function into three channels and an
+/// optional alpha. Both the legacy syntax, `rgb(1, 2, 3, 0.5)`, and the
+/// modern one, `rgb(1 2 3 / 0.5)`, are accepted.
+fn channels(arguments: &[ComponentValue]) -> Option<([&CssToken; 3], Option<&CssToken>)> {
+    let mut tokens = Vec::new();
+    for argument in arguments {
+        match argument {
+            ComponentValue::Token(CssToken::Whitespace) => {}
+            ComponentValue::Token(token) => tokens.push(token),
+            ComponentValue::Function { .. } | ComponentValue::Block { .. } => return None,
+        }
+    }
+    if tokens.contains(&&CssToken::Comma) {
+        let values: Vec<&CssToken> = tokens
+            .split(|token| **token == CssToken::Comma)
+            .map(|part| match *part {
+                [token] => Some(token),
+                _ => None,
+            })
+            .collect::<Option<_>>()?;
+        match *values.as_slice() {
+            [r, g, b] => Some(([r, g, b], None)),
+            [r, g, b, a] => Some(([r, g, b], Some(a))),
+            _ => None,
+        }
+    } else {
+        match *tokens.as_slice() {
+            [r, g, b] => Some(([r, g, b], None)),
+            [r, g, b, &CssToken::Delim('/'), a] => Some(([r, g, b], Some(a))),
+            _ => None,
+        }
+    }
+}
+
+/// An alpha value, a number from 0 to 1 or a percentage, as a byte.
+fn alpha_byte(token: &CssToken) -> Option<u8> {
+    match token {
+        CssToken::Number(number) => Some(to_byte(number.value * 255.0)),
+        CssToken::Percentage(number) => Some(to_byte(number.value / 100.0 * 255.0)),
+        CssToken::Ident(name) if name.eq_ignore_ascii_case("none") => Some(0),
+        _ => None,
+    }
+}
+
+/// Rounds `value` to the nearest byte, clamping it to 0 to 255.
+fn to_byte(value: f64) -> u8 {
+    value.round().clamp(0.0, 255.0) as u8
+}
+
+/// The byte whose hex digits are `high` and `low`.
+fn hex_byte(high: u8, low: u8) -> u8 {
+    high * 16 + low
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::parse_component_values;
+
+    fn parse(css: &str) -> Option<Color> {
+        Color::parse(&parse_component_values(css), Color::rgb(1, 2, 3))
+    }
+
+    #[test]
+    fn test_keywords_and_hex() {
+        assert_eq!(parse("red"), Some(Color::rgb(255, 0, 0)));
+        assert_eq!(parse(" RebeccaPurple "), Some(Color::rgb(0x66, 0x33, 0x99)));
+        assert_eq!(parse("CanvasText"), Some(Color::BLACK));
+        assert_eq!(parse("transparent"), Some(Color::TRANSPARENT));
+        assert_eq!(parse("currentColor"), Some(Color::rgb(1, 2, 3)));
+        assert_eq!(parse("#f80"), Some(Color::rgb(0xff, 0x88, 0x00)));
+        assert_eq!(parse("#f808"), Some(Color::rgba(0xff, 0x88, 0x00, 0x88)));
+        assert_eq!(parse("#0a0B0c"), Some(Color::rgb(10, 11, 12)));
+        assert_eq!(parse("#0a0b0c80"), Some(Color::rgba(10, 11, 12, 128)));
+        assert_eq!(parse("#12345"), None);
+        assert_eq!(parse("#ggg"), None);
+        assert_eq!(parse("reddish"), None);
+        assert_eq!(parse("red blue"), None);
+    }
+
+    #[test]
+    fn test_functions() {
+        assert_eq!(parse("rgb(255, 128, 0)"), Some(Color::rgb(255, 128, 0)));
+        assert_eq!(
+            parse("rgba(255, 128, 0, 0.5)"),
+            Some(Color::rgba(255, 128, 0, 128))
+        );
+        assert_eq!(
+            parse("rgb(100% 50% 0% / 25%)"),
+            Some(Color::rgba(255, 128, 0, 64))
+        );
+        assert_eq!(parse("rgb(300 -5 0)"), Some(Color::rgb(255, 0, 0)));
+        assert_eq!(parse("hsl(120, 100%, 50%)"), Some(Color::rgb(0, 255, 0)));
+        assert_eq!(
+            parse("hsl(0.5turn 100% 25% / 1)"),
+            Some(Color::rgb(0, 128, 128))
+        );
+        assert_eq!(
+            parse("hsla(240deg, 100%, 50%, 0)"),
+            Some(Color::rgba(0, 0, 255, 0))
+        );
+        assert_eq!(parse("rgb(1, 2)"), None);
+        assert_eq!(parse("rgb(1 2 3 4)"), None);
+        assert_eq!(parse("rgb(1, 2, 3 / 4)"), None);
+        assert_eq!(parse("lab(50% 0 0)"), None);
+    }
+}
@@ -0,0 +1,532 @@
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use super::Color;
+use super::shorthands;
+use super::variables::{self, is_custom};
+use crate::css::{ComponentValue, CssToken, Number, parse_component_values};
+
+/// The initial value of each property the engine knows about, which an
+/// element gets for a property no declaration sets.
+const INITIAL_VALUES: [(&str, &str); 63] = [
+    ("background-attachment", "scroll"),
+    ("background-clip", "border-box"),
+    ("background-color", "transparent"),
+    ("background-image", "none"),
+    ("background-origin", "padding-box"),
+    ("background-position", "0% 0%"),
+    ("background-repeat", "repeat"),
+    ("background-size", "auto"),
+    ("border-bottom-color", "currentcolor"),
+    ("border-bottom-left-radius", "0"),
+    ("border-bottom-right-radius", "0"),
+    ("border-bottom-style", "none"),
+    ("border-bottom-width", "medium"),
+    ("border-left-color", "currentcolor"),
+    ("border-left-style", "none"),
+    ("border-left-width", "medium"),
+    ("border-right-color", "currentcolor"),
+    ("border-right-style", "none"),
+    ("border-right-width", "medium"),
+    ("border-spacing", "0"),
+    ("border-top-color", "currentcolor"),
+    ("border-top-left-radius", "0"),
+    ("border-top-right-radius", "0"),
+    ("border-top-style", "none"),
+    ("border-top-width", "medium"),
+    ("bottom", "auto"),
+    ("clear", "none"),
+    ("color", "canvastext"),
+    ("display", "inline"),
+    ("float", "none"),
+    ("font-family", "sans-serif"),
+    ("font-size", "medium"),
+    ("font-stretch", "normal"),
+    ("font-style", "normal"),
+    ("font-variant", "normal"),
+    ("font-weight", "normal"),
+    ("height", "auto"),
+    ("left", "auto"),
+    ("line-height", "normal"),
+    ("list-style-type", "disc"),
+    ("margin-bottom", "0"),
+    ("margin-left", "0"),
+    ("margin-right", "0"),
+    ("margin-top", "0"),
+    ("max-height", "none"),
+    ("max-width", "none"),
+    ("min-height", "auto"),
+    ("min-width", "auto"),
+    ("overflow", "visible"),
+    ("padding-bottom", "0"),
+    ("padding-left", "0"),
+    ("padding-right", "0"),
+    ("padding-top", "0"),
+    ("position", "static"),
+    ("right", "auto"),
+    ("text-align", "start"),
+    ("text-decoration-line", "none"),
+    ("top", "auto"),
+    ("vertical-align", "baseline"),
+    ("visibility", "visible"),
+    ("white-space", "normal"),
+    ("width", "auto"),
+    ("z-index", "auto"),
+];
+
+/// The properties that inherit: an element gets its parent's value for
+/// those no declaration sets.
+const INHERITED: [&str; 13] = [
+    "border-spacing",
+    "color",
+    "font-family",
+    "font-size",
+    "font-stretch",
+    "font-style",
+    "font-variant",
+    "font-weight",
+    "line-height",
+    "list-style-type",
+    "text-align",
+    "visibility",
+    "white-space",
+];
+
+/// The font size of `medium`, and of the root element by default, in
+/// pixels.
+const MEDIUM_FONT_SIZE: f64 = 16.0;
+
+/// The weight of `font-weight: normal`; `bold` is 700.
+const NORMAL_FONT_WEIGHT: f64 = 400.0;
+
+static INITIAL: LazyLock<HashMap<&'static str, Vec<ComponentValue>>> = LazyLock::new(|| {
+    INITIAL_VALUES
+        .iter()
+        .map(|&(property, value)| (property, parse_component_values(value)))
+        .collect()
+});
+
+/// How an element generates boxes, from its `display` property.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Display {
+    /// No boxes at all, for the element or its descendants.
+    None,
+    #[default]
+    Inline,
+    Block,
+    InlineBlock,
+    ListItem,
+    Table,
+    InlineTable,
+    TableRowGroup,
+    TableHeaderGroup,
+    TableFooterGroup,
+    TableRow,
+    TableColumnGroup,
+    TableColumn,
+    TableCell,
+    TableCaption,
+}
+
+impl Display {
+    /// Parses a `display` keyword. Flex and grid layout are not
+    /// supported, so their containers are laid out as blocks.
+    pub fn parse(keyword: &str) -> Option<Self> {
+        let display = match keyword.to_ascii_lowercase().as_str() {
+            "none" => Self::None,
+            "inline" => Self::Inline,
+            "block" | "flow-root" | "flex" | "grid" => Self::Block,
+            "inline-block" | "inline-flex" | "inline-grid" => Self::InlineBlock,
+            "list-item" => Self::ListItem,
+            "table" => Self::Table,
+            "inline-table" => Self::InlineTable,
+            "table-row-group" => Self::TableRowGroup,
+            "table-header-group" => Self::TableHeaderGroup,
+            "table-footer-group" => Self::TableFooterGroup,
+            "table-row" => Self::TableRow,
+            "table-column-group" => Self::TableColumnGroup,
+            "table-column" => Self::TableColumn,
+            "table-cell" => Self::TableCell,
+            "table-caption" => Self::TableCaption,
+            _ => return None,
+        };
+        Some(display)
+    }
+}
+
+/// The style of one element: for each property, the value of the
+/// declaration that won the cascade, or else the property's initial value.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ComputedStyle {
+    cascaded: HashMap<String, Vec<ComponentValue>>,
+
+    /// The longhands whose value is that of a shorthand with `var()`
+    /// references, to split once they are replaced.
+    pending: HashMap<String, &'static str>,
+}
+
+impl ComputedStyle {
+    pub(super) fn set(&mut self, property: String, value: Vec<ComponentValue>) {
+        self.pending.remove(&property);
+        self.cascaded.insert(property, value);
+    }
+
+    /// Sets the longhand `property` to the whole `value` of `shorthand`.
+    pub(super) fn set_pending(
+        &mut self,
+        property: String,
+        value: Vec<ComponentValue>,
+        shorthand: &'static str,
+    ) {
+        self.pending.insert(property.clone(), shorthand);
+        self.cascaded.insert(property, value);
+    }
+
+    /// Takes the custom properties of `parent`, as they all inherit.
+    pub(super) fn inherit_custom_properties(&mut self, parent: &Self) {
+        let custom = parent
+            .cascaded
+            .iter()
+            .filter(|(property, _)| is_custom(property));
+        self.cascaded
+            .extend(custom.map(|(property, value)| (property.clone(), value.clone())));
+    }
+
+    /// Replaces the `var()` references in the values, once the cascade is
+    /// done, and splits the shorthands that had them.
+    pub(super) fn resolve_variables(&mut self) {
+        variables::resolve(&mut self.cascaded);
+        let pending: Vec<(String, &str)> = self.pending.drain().collect();
+        for (property, shorthand) in pending {
+            let value = self
+                .cascaded
+                .get(&property)
+                .and_then(|value| shorthands::split(shorthand, value))
+                .and_then(|values| values.into_iter().find(|(name, _)| *name == property));
+            match value {
+                Some((_, value)) => self.cascaded.insert(property, value),
+                None => self.cascaded.remove(&property),
+            };
+        }
+    }
+
+    /// Gives each property its value from the parent element's style
+    /// `parent`, or its initial value, where the cascade left that to
+    /// defaulting (<https://www.w3.org/TR/css-cascade-4/#defaulting>):
+    /// inherited properties no declaration set, and those set to `inherit`,
+    /// `initial` or `unset`. A property that `var()` made invalid counts as
+    /// `unset`, as it has been removed by then. Then turns the font size
+    /// into pixels, so that children inherit a size rather than a factor
+    /// such as `2em`, and `bolder` and `lighter` into weights.
+    pub(super) fn apply_defaulting(&mut self, parent: Option<&Self>) {
+        let explicit: Vec<(String, &str)> = self
+            .cascaded
+            .iter()
+            .filter_map(|(property, value)| {
+                let [ComponentValue::Token(CssToken::Ident(keyword))] = value.as_slice() else {
+                    return None;
+                };
+                let keyword = match keyword.to_ascii_lowercase().as_str() {
+                    "inherit" => "inherit",
+                    "initial" => "initial",
+                    "unset" | "revert" | "revert-layer" => "unset",
+                    // In `color`, `currentcolor` is the parent's color.
+                    "currentcolor" if property == "color" => "inherit",
+                    _ => return None,
+                };
+                Some((property.clone(), keyword))
+            })
+            .collect();
+        for (property, keyword) in explicit {
+            let inherit = keyword == "inherit" || keyword == "unset" && is_inherited(&property);
+            let value = parent
+                .filter(|_| inherit)
+                .and_then(|parent| parent.value(&property))
+                .or_else(|| INITIAL.get(property.as_str()).map(Vec::as_slice))
+                .map(<[ComponentValue]>::to_vec);
+            match value {
+                Some(value) => self.cascaded.insert(property, value),
+                None => self.cascaded.remove(&property),
+            };
+        }
+
+        if let Some(parent) = parent {
+            let inherited: Vec<(&String, &Vec<ComponentValue>)> = parent
+                .cascaded
+                .iter()
+                .filter(|(property, _)| {
+                    INHERITED.contains(&property.as_str()) && !self.cascaded.contains_key(*property)
+                })
+                .collect();
+            let inherited: Vec<(String, Vec<ComponentValue>)> = inherited
+                .into_iter()
+                .map(|(property, value)| (property.clone(), value.clone()))
+                .collect();
+            self.cascaded.extend(inherited);
+        }
+
+        let parent_font_size = parent.map_or(MEDIUM_FONT_SIZE, Self::font_size);
+        if let Some(size) = self
+            .value("font-size")
+            .and_then(|value| font_size(value, parent_font_size))
+        {
+            self.cascaded.insert("font-size".to_owned(), pixels(size));
+        }
+        let parent_weight = parent.map_or(NORMAL_FONT_WEIGHT, Self::font_weight);
+        if let Some(weight) = self
+            .value("font-weight")
+            .and_then(|value| relative_font_weight(value, parent_weight))
+        {
+            self.cascaded
+                .insert("font-weight".to_owned(), number(weight));
+        }
+        let font_size = self.font_size();
+        if let Some(height) = self.value("line-height").and_then(|value| match value {
+            [ComponentValue::Token(CssToken::Dimension { value, unit })] => {
+                relative_length(value.value, unit, font_size)
+            }
+            [ComponentValue::Token(CssToken::Percentage(percentage))] => {
+                Some(percentage.value / 100.0 * font_size)
+            }
+            _ => None,
+        }) {
+            self.cascaded
+                .insert("line-height".to_owned(), pixels(height));
+        }
+    }
+
+    /// The value of `property`, or `None` if no declaration set it and the
+    /// engine does not know its initial value. Custom properties, such as
+    /// `--main-color`, have no initial value.
+    pub fn value(&self, property: &str) -> Option<&[ComponentValue]> {
+        self.cascaded
+            .get(property)
+            .or_else(|| INITIAL.get(property))
+            .map(Vec::as_slice)
+    }
+
+    /// The value of `property` if it is a single keyword, such as `block`.
+    pub fn keyword(&self, property: &str) -> Option<&str> {
+        match self.value(property)? {
+            [ComponentValue::Token(CssToken::Ident(keyword))] => Some(keyword),
+            _ => None,
+        }
+    }
+
+    /// The color `property`, such as `color` or `background-color`,
+    /// computes to. `currentcolor` is the element's `color`, and a value
+    /// that is not a color falls back to the property's initial value.
+    pub fn color(&self, property: &str) -> Option<Color> {
+        let current = match property {
+            // Defaulting turns `currentcolor` in `color` itself into the
+            // parent's color; before that, it stands for the initial color.
+            "color" => Color::BLACK,
+            _ => self.color("color").unwrap_or(Color::BLACK),
+        };
+        self.value(property)
+            .and_then(|value| Color::parse(value, current))
+            .or_else(|| Color::parse(INITIAL.get(property)?, current))
+    }
+
+    /// The font size of the element in pixels.
+    pub fn font_size(&self) -> f64 {
+        match self.value("font-size") {
+            Some([ComponentValue::Token(CssToken::Dimension { value, unit })]) if unit == "px" => {
+                value.value
+            }
+            _ => MEDIUM_FONT_SIZE,
+        }
+    }
+
+    /// The `font-weight` of the element, from 1 to 1000, where `normal` is
+    /// 400 and `bold` 700.
+    pub fn font_weight(&self) -> f64 {
+        match self.value("font-weight") {
+            Some([ComponentValue::Token(CssToken::Number(number))]) => {
+                number.value.clamp(1.0, 1000.0)
+            }
+            Some([ComponentValue::Token(CssToken::Ident(keyword))])
+                if keyword.eq_ignore_ascii_case("bold") =>
+            {
+                700.0
+            }
+            _ => NORMAL_FONT_WEIGHT,
+        }
+    }
+
+    /// The `display` of the element, which is `inline` unless a valid
+    /// keyword was declared.
+    pub fn display(&self) -> Display {
+        self.keyword("display")
+            .and_then(Display::parse)
+            .unwrap_or_default()
+    }
+}
+
+fn is_inherited(property: &str) -> bool {
+    is_custom(property) || INHERITED.contains(&property)
+}
+
+/// A length of `pixels`, as a value.
+fn pixels(pixels: f64) -> Vec<ComponentValue> {
+    vec![ComponentValue::Token(CssToken::Dimension {
+        value: Number {
+            value: pixels,
+            integer: false,
+        },
+        unit: "px".to_owned(),
+    })]
+}
+
+/// A number, as a value.
+fn number(value: f64) -> Vec<ComponentValue> {
+    vec![ComponentValue::Token(CssToken::Number(Number {
+        value,
+        integer: value.fract() == 0.0,
+    }))]
+}
+
+/// The weight `bolder` or `lighter`, the `font-weight` of `value`, stands
+/// for, where the parent's weight is `parent`
+/// (<https://www.w3.org/TR/css-fonts-4/#relative-weights>).
+fn relative_font_weight(value: &[ComponentValue], parent: f64) -> Option<f64> {
+    let [ComponentValue::Token(CssToken::Ident(keyword))] = value else {
+        return None;
+    };
+    let weight = match keyword.to_ascii_lowercase().as_str() {
+        "bolder" if parent < 350.0 => 400.0,
+        "bolder" if parent < 550.0 => 700.0,
+        "bolder" => parent.max(900.0),
+        "lighter" if parent < 100.0 => parent,
+        "lighter" if parent < 550.0 => 100.0,
+        "lighter" if parent < 750.0 => 400.0,
+        "lighter" => 700.0,
+        _ => return None,
+    };
+    Some(weight)
+}
+
+/// The size in pixels a `font-size` of `value` gives, where the parent's
+/// font size is `parent`.
+fn font_size(value: &[ComponentValue], parent: f64) -> Option<f64> {
+    let size = match value {
+        [ComponentValue::Token(CssToken::Ident(keyword))] => {
+            match keyword.to_ascii_lowercase().as_str() {
+                "xx-small" => MEDIUM_FONT_SIZE * 3.0 / 5.0,
+                "x-small" => MEDIUM_FONT_SIZE * 3.0 / 4.0,
+                "small" => MEDIUM_FONT_SIZE * 8.0 / 9.0,
+                "medium" => MEDIUM_FONT_SIZE,
+                "large" => MEDIUM_FONT_SIZE * 6.0 / 5.0,
+                "x-large" => MEDIUM_FONT_SIZE * 3.0 / 2.0,
+                "xx-large" => MEDIUM_FONT_SIZE * 2.0,
+                "xxx-large" => MEDIUM_FONT_SIZE * 3.0,
+                "larger" => parent * 1.2,
+                "smaller" => parent / 1.2,
+                _ => return None,
+            }
+        }
+        [ComponentValue::Token(CssToken::Dimension { value, unit })] => {
+            relative_length(value.value, unit, parent)?
+        }
+        [ComponentValue::Token(CssToken::Percentage(percentage))] => {
+            percentage.value / 100.0 * parent
+        }
+        _ => return None,
+    };
+    (size >= 0.0).then_some(size)
+}
+
+/// A length of `value` in `unit`, in pixels, where `em` is `font_size`.
+/// `rem` is taken to be the medium font size. Returns `None` for units
+/// that depend on the viewport, or are not lengths.
+fn relative_length(value: f64, unit: &str, font_size: f64) -> Option<f64> {
+    let pixels = match unit.to_ascii_lowercase().as_str() {
+        "px" => 1.0,
+        "em" => font_size,
+        "ex" | "ch" => font_size / 2.0,
+        "rem" => MEDIUM_FONT_SIZE,
+        "pt" => 4.0 / 3.0,
+        "pc" => 16.0,
+        "in" => 96.0,
+        "cm" => 96.0 / 2.54,
+        "mm" => 96.0 / 25.4,
+        "q" => 96.0 / 101.6,
+        _ => return None,
+    };
+    Some(value * pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_initial_values() {
+        let mut style = ComputedStyle::default();
+        assert_eq!(style.display(), Display::Inline);
+        assert_eq!(style.keyword("position"), Some("static"));
+        assert_eq!(style.value("no-such-property"), None);
+
+        style.set("display".to_owned(), parse_component_values("Table-Cell"));
+        assert_eq!(style.display(), Display::TableCell);
+        style.set("display".to_owned(), parse_component_values("flex"));
+        assert_eq!(style.display(), Display::Block);
+        style.set("display".to_owned(), parse_component_values("inline math"));
+        assert_eq!(style.display(), Display::Inline);
+    }
+
+    #[test]
+    fn test_colors() {
+        let mut style = ComputedStyle::default();
+        assert_eq!(style.color("color"), Some(Color::BLACK));
+        assert_eq!(style.color("background-color"), Some(Color::TRANSPARENT));
+        assert_eq!(style.color("display"), None);
+
+        style.set("color".to_owned(), parse_component_values("#00f"));
+        style.set(
+            "border-top-color".to_owned(),
+            parse_component_values("not-a-color"),
+        );
+        assert_eq!(style.color("border-top-color"), Some(Color::rgb(0, 0, 255)));
+        style.set("color".to_owned(), parse_component_values("currentcolor"));
+        assert_eq!(style.color("color"), Some(Color::BLACK));
+    }
+
+    #[test]
+    fn test_defaulting() {
+        let mut parent = ComputedStyle::default();
+        parent.set("color".to_owned(), parse_component_values("red"));
+        parent.set("font-size".to_owned(), parse_component_values("2em"));
+        parent.set("margin-top".to_owned(), parse_component_values("3px"));
+        parent.set("line-height".to_owned(), parse_component_values("150%"));
+        parent.set("font-weight".to_owned(), parse_component_values("bold"));
+        parent.apply_defaulting(None);
+        assert_eq!(parent.font_weight(), 700.0);
+        assert_eq!(parent.font_size(), 32.0);
+        assert_eq!(parent.value("line-height"), Some(pixels(48.0).as_slice()));
+
+        let mut child = ComputedStyle::default();
+        child.set("font-size".to_owned(), parse_component_values("smaller"));
+        child.set("margin-top".to_owned(), parse_component_values("inherit"));
+        child.set("visibility".to_owned(), parse_component_values("Unset"));
+        child.set("white-space".to_owned(), parse_component_values("initial"));
+        child.set("font-weight".to_owned(), parse_component_values("bolder"));
+        child.apply_defaulting(Some(&parent));
+        assert_eq!(child.font_weight(), 900.0);
+        assert_eq!(child.keyword("color"), Some("red"));
+        assert!((child.font_size() - 32.0 / 1.2).abs() < 1e-9);
+        assert_eq!(child.value("line-height"), Some(pixels(48.0).as_slice()));
+        assert_eq!(child.value("margin-top"), parent.value("margin-top"));
+        assert_eq!(child.keyword("visibility"), Some("visible"));
+        assert_eq!(child.keyword("white-space"), Some("normal"));
+
+        let mut grandchild = ComputedStyle::default();
+        grandchild.set("color".to_owned(), parse_component_values("currentcolor"));
+        grandchild.set("font-size".to_owned(), parse_component_values("50%"));
+        grandchild.set("font-weight".to_owned(), parse_component_values("lighter"));
+        grandchild.apply_defaulting(Some(&child));
+        assert_eq!(grandchild.font_weight(), 700.0);
+        assert_eq!(grandchild.color("color"), Some(Color::rgb(255, 0, 0)));
+        assert!((grandchild.font_size() - 32.0 / 2.4).abs() < 1e-9);
+    }
+}
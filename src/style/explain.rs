@@ -0,0 +1,140 @@
+use super::inspect::style_attribute_rule;
+use super::{Cascade, Origin, StylesheetSource, inline_declarations};
+use crate::css::{StyleRule, serialize};
+use crate::dom::{Document, NodeId};
+use crate::selectors::Specificity;
+
+/// A declaration the cascade considered for a property of an element; see
+/// [`explain`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConsideredDeclaration {
+    pub source: StylesheetSource,
+    pub origin: Origin,
+
+    /// The rule the declaration is in, with its line in its sheet. For the
+    /// `style` attribute, it has no selector and is on line 1.
+    pub rule: StyleRule,
+
+    /// The specificity of the most specific selector of the rule that
+    /// matches the element.
+    pub specificity: Specificity,
+    pub important: bool,
+
+    /// The value as CSS text. For a longhand of a shorthand such as
+    /// `margin`, it is the longhand's part of the shorthand's value, or the
+    /// whole value if it has `var()` references.
+    pub value: String,
+
+    /// Whether the declaration won the cascade, and set the property.
+    pub won: bool,
+}
+
+/// Why the element `node` has the value it has for `property`: every
+/// declaration for it that applies to the element, the one that won first,
+/// then the ones it won over, each after the ones it loses to.
+///
+/// An empty list means that no declaration sets the property, so the
+/// element inherits it from its parent if it is inherited, and has its
+/// initial value if not. Declarations of shorthands are listed under the
+/// longhands they set, so `property` should be a longhand.
+pub fn explain(
+    cascade: &Cascade,
+    document: &Document,
+    node: NodeId,
+    property: &str,
+) -> Vec<ConsideredDeclaration> {
+    let inline = inline_declarations(document, node);
+    let style_attribute = style_attribute_rule(document, node);
+    let mut considered: Vec<ConsideredDeclaration> = cascade
+        .candidates(document, node, &inline)
+        .into_iter()
+        .rev()
+        .filter(|candidate| candidate.declaration.name == property)
+        .filter_map(|candidate| {
+            let (source, origin, rule) = match candidate.rule {
+                Some(rule) => (
+                    cascade
+                        .sources
+                        .get(rule.source)
+                        .cloned()
+                        .unwrap_or(StylesheetSource::Other),
+                    rule.origin,
+                    rule.rule.clone(),
+                ),
+                None => (
+                    StylesheetSource::StyleAttribute(node),
+                    Origin::Author,
+                    style_attribute.clone()?,
+                ),
+            };
+            Some(ConsideredDeclaration {
+                source,
+                origin,
+                rule,
+                specificity: candidate.precedence.specificity,
+                important: candidate.declaration.important,
+                value: serialize(&candidate.declaration.value),
+                won: false,
+            })
+        })
+        .collect();
+    if let Some(winner) = considered.first_mut() {
+        winner.won = true;
+    }
+    considered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::Stylesheet;
+    use crate::style::Device;
+
+    #[test]
+    fn test_explain() {
+        let document = Document::parse(
+            "<style>\n#a { color: blue !important }\np { color: red; margin: 1px 2px }\n\
+             .x { color: green }\n</style><p id=a class=x style='color: black'>a</p>",
+        );
+        let cascade = Cascade::for_document(&document, Device::default());
+        let a = document.get_element_by_id("a").unwrap();
+
+        let color = explain(&cascade, &document, a, "color");
+        let summary: Vec<(&str, bool)> = color
+            .iter()
+            .map(|declaration| (declaration.value.as_str(), declaration.won))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("blue", true),
+                ("black", false),
+                ("green", false),
+                ("red", false)
+            ]
+        );
+        assert!(color[0].important);
+        assert_eq!(color[0].rule.line, 2);
+        assert_eq!(color[0].specificity.ids, 1);
+        assert_eq!(color[1].source, StylesheetSource::StyleAttribute(a));
+
+        let margin = explain(&cascade, &document, a, "margin-top");
+        assert_eq!(margin.len(), 2);
+        assert_eq!(margin[0].value, "1px");
+        assert_eq!(margin[0].rule.selector_text(), "p");
+        assert_eq!(margin[1].origin, Origin::UserAgent);
+        assert_eq!(margin[1].value, "1em");
+        assert!(!margin[1].won);
+        assert_eq!(
+            explain(&cascade, &document, a, "margin-left")[0].value,
+            "2px"
+        );
+        assert!(explain(&cascade, &document, a, "float").is_empty());
+
+        let mut cascade = Cascade::new();
+        cascade.add_stylesheet(Origin::Author, &Stylesheet::parse("p { color: red }"));
+        let color = explain(&cascade, &document, a, "color");
+        assert_eq!(color[1].source, StylesheetSource::Other);
+        assert_eq!(color[1].rule.to_string(), "p { color: red; }");
+    }
+}
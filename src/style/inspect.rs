@@ -0,0 +1,262 @@
+use super::{Cascade, Origin, Precedence, user_agent_stylesheet};
+use crate::css::{StyleRule, Stylesheet, parse_declarations};
+use crate::dom::{Document, ImportedStylesheet, NodeId};
+use crate::selectors::Specificity;
+
+/// Where a style sheet, or a style rule, comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StylesheetSource {
+    /// The browser's default styles; see [`user_agent_stylesheet`].
+    UserAgent,
+
+    /// The `<style>` or `<link rel=stylesheet>` element of the document.
+    Element(NodeId),
+
+    /// An `@import` rule, which fetched the sheet from the URL.
+    Import(String),
+
+    /// The `style` attribute of the element.
+    StyleAttribute(NodeId),
+
+    /// A sheet added with [`Cascade::add_stylesheet`].
+    Other,
+}
+
+impl StylesheetSource {
+    /// A short description of the source for a devtools panel, such as
+    /// the URL of a linked sheet.
+    pub fn label(&self, document: &Document) -> String {
+        match self {
+            Self::UserAgent => "user agent stylesheet".to_owned(),
+            Self::Element(owner) => document
+                .element(*owner)
+                .filter(|element| element.is("link"))
+                .and_then(|element| element.get_attribute("href"))
+                .map_or_else(
+                    || "<style>".to_owned(),
+                    |href| {
+                        document
+                            .resolve_url(href)
+                            .unwrap_or_else(|| href.to_owned())
+                    },
+                ),
+            Self::Import(url) => url.clone(),
+            Self::StyleAttribute(_) => "style attribute".to_owned(),
+            Self::Other => "stylesheet".to_owned(),
+        }
+    }
+}
+
+/// A style sheet a document is styled with; see [`stylesheets`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct InspectedStylesheet<'d> {
+    pub source: StylesheetSource,
+    pub origin: Origin,
+
+    /// The `media` attribute of the owner element, or the media queries of
+    /// the `@import` rule, if any.
+    pub media: Option<String>,
+
+    /// The parsed sheet, whose rules give their selectors and declarations
+    /// as text with [`StyleRule::selector_text`] and
+    /// [`Declaration::value_text`](crate::css::Declaration::value_text).
+    pub sheet: &'d Stylesheet,
+}
+
+/// Every style sheet `document` is styled with, whether or not its media
+/// queries match.
+///
+/// The user agent style sheet comes first, then the sheets of the
+/// document, each after the sheets it imports. This is the order of the
+/// cascade.
+pub fn stylesheets(document: &Document) -> Vec<InspectedStylesheet<'_>> {
+    let mut sheets = vec![InspectedStylesheet {
+        source: StylesheetSource::UserAgent,
+        origin: Origin::UserAgent,
+        media: None,
+        sheet: user_agent_stylesheet(),
+    }];
+    for stylesheet in document.stylesheets() {
+        add_imports(&mut sheets, &stylesheet.imports);
+        sheets.push(InspectedStylesheet {
+            source: StylesheetSource::Element(stylesheet.owner),
+            origin: Origin::Author,
+            media: stylesheet.media.clone(),
+            sheet: &stylesheet.sheet,
+        });
+    }
+    sheets
+}
+
+fn add_imports<'d>(sheets: &mut Vec<InspectedStylesheet<'d>>, imports: &'d [ImportedStylesheet]) {
+    for import in imports {
+        add_imports(sheets, &import.imports);
+        sheets.push(InspectedStylesheet {
+            source: StylesheetSource::Import(import.url.clone()),
+            origin: Origin::Author,
+            media: (!import.media.is_empty()).then(|| crate::css::serialize(&import.media)),
+            sheet: &import.sheet,
+        });
+    }
+}
+
+/// A style rule that applies to an element; see [`Cascade::matched_rules`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchedRule {
+    pub source: StylesheetSource,
+    pub origin: Origin,
+
+    /// The rule, with its line in its sheet. For the `style` attribute, it
+    /// has no selector and is on line 1.
+    pub rule: StyleRule,
+
+    /// The specificity of the most specific selector of the rule that
+    /// matches the element.
+    pub specificity: Specificity,
+}
+
+impl Cascade {
+    /// The style rules that apply to the element `id`, with the `style`
+    /// attribute as a rule of its own, in the order a Styles panel lists
+    /// them: the rule that wins over the others first. `!important`
+    /// declarations are not taken into account in that order.
+    pub fn matched_rules(&self, document: &Document, id: NodeId) -> Vec<MatchedRule> {
+        let mut matched: Vec<(Precedence, MatchedRule)> = self
+            .rules
+            .iter()
+            .enumerate()
+            .filter_map(|(order, rule)| {
+                let specificity = rule.selectors.matching_specificity(document, id)?;
+                let precedence = Precedence {
+                    layer: Precedence::layer(rule.origin, false),
+                    inline: false,
+                    specificity,
+                    order,
+                };
+                let matched = MatchedRule {
+                    source: self
+                        .sources
+                        .get(rule.source)
+                        .cloned()
+                        .unwrap_or(StylesheetSource::Other),
+                    origin: rule.origin,
+                    rule: rule.rule.clone(),
+                    specificity,
+                };
+                Some((precedence, matched))
+            })
+            .collect();
+
+        if let Some(rule) = style_attribute_rule(document, id) {
+            let precedence = Precedence {
+                layer: Precedence::layer(Origin::Author, false),
+                inline: true,
+                specificity: Specificity::default(),
+                order: self.rules.len(),
+            };
+            let rule = MatchedRule {
+                source: StylesheetSource::StyleAttribute(id),
+                origin: Origin::Author,
+                rule,
+                specificity: Specificity::default(),
+            };
+            matched.push((precedence, rule));
+        }
+
+        matched.sort_by(|(a, _), (b, _)| b.cmp(a));
+        matched.into_iter().map(|(_, rule)| rule).collect()
+    }
+}
+
+/// The `style` attribute of the element `id` as a rule with no selector,
+/// on line 1, if it has one.
+pub(super) fn style_attribute_rule(document: &Document, id: NodeId) -> Option<StyleRule> {
+    let style = document.element(id)?.get_attribute("style")?;
+    Some(StyleRule {
+        prelude: Vec::new(),
+        declarations: parse_declarations(style),
+        line: 1,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::Rule;
+    use crate::style::Device;
+
+    #[test]
+    fn test_matched_rules() {
+        let mut document = Document::parse(
+            "<link rel=stylesheet href=site.css>\
+             <style>\np { color: red }\n\n#a.x { color: blue; margin: 0 auto }\n</style>\
+             <p id=a class=x style='color: green !important'>a</p>",
+        );
+        document.set_url("https://example.com/".to_owned());
+        let link = document.get_elements_by_tag_name("link")[0];
+        document.add_linked_stylesheet(link, Stylesheet::parse("@media screen {\n  .x { }\n}"));
+
+        let cascade = Cascade::for_document(&document, Device::default());
+        let a = document.get_element_by_id("a").unwrap();
+        let matched = cascade.matched_rules(&document, a);
+        let summary: Vec<(String, String, usize)> = matched
+            .iter()
+            .map(|matched| {
+                (
+                    matched.source.label(&document),
+                    matched.rule.selector_text(),
+                    matched.rule.line,
+                )
+            })
+            .collect();
+        let expected = [
+            ("style attribute", "", 1),
+            ("<style>", "#a.x", 4),
+            ("https://example.com/site.css", ".x", 2),
+            ("<style>", "p", 2),
+        ]
+        .map(|(label, selector, line)| (label.to_owned(), selector.to_owned(), line));
+        assert_eq!(summary.get(..4), Some(expected.as_slice()));
+        // The user agent rules for `<p>` come last.
+        assert!(matched.len() > 4);
+        assert!(
+            matched[4..]
+                .iter()
+                .all(|rule| rule.source == StylesheetSource::UserAgent)
+        );
+        assert_eq!(
+            matched[0].rule.declarations[0].to_string(),
+            "color: green !important"
+        );
+        assert_eq!(matched[1].specificity.ids, 1);
+        assert_eq!(matched[1].rule.declarations[1].value_text(), "0 auto");
+    }
+
+    #[test]
+    fn test_stylesheets() {
+        let mut document =
+            Document::parse("<style media=print>@import 'a.css' screen; p {}</style>");
+        let style = document.get_elements_by_tag_name("style")[0];
+        document.set_stylesheet_imports(
+            style,
+            vec![ImportedStylesheet {
+                url: "https://example.com/a.css".to_owned(),
+                media: crate::css::parse_component_values("screen"),
+                sheet: Stylesheet::parse("a {}"),
+                imports: Vec::new(),
+            }],
+        );
+        let sheets = stylesheets(&document);
+        assert_eq!(sheets.len(), 3);
+        assert_eq!(sheets[0].origin, Origin::UserAgent);
+        assert_eq!(
+            sheets[1].source,
+            StylesheetSource::Import("https://example.com/a.css".to_owned())
+        );
+        assert_eq!(sheets[1].media.as_deref(), Some("screen"));
+        assert_eq!(sheets[2].media.as_deref(), Some("print"));
+        assert!(
+            matches!(&sheets[2].sheet.rules[1], Rule::Style(rule) if rule.selector_text() == "p")
+        );
+    }
+}
@@ -0,0 +1,547 @@
+use crate::css::{BlockKind, ComponentValue, CssToken, parse_component_values};
+
+/// Whether the user prefers light or dark pages, from the app's theme.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ColorScheme {
+    #[default]
+    Light,
+    Dark,
+}
+
+/// What media queries are evaluated against: the window the page is shown
+/// in and the user's preferences.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Device {
+    /// The width of the viewport, in CSS pixels.
+    pub width: f32,
+
+    /// The height of the viewport, in CSS pixels.
+    pub height: f32,
+
+    /// Device pixels per CSS pixel.
+    pub pixel_ratio: f32,
+    pub color_scheme: ColorScheme,
+}
+
+impl Default for Device {
+    fn default() -> Self {
+        Self {
+            width: 1024.0,
+            height: 768.0,
+            pixel_ratio: 1.0,
+            color_scheme: ColorScheme::Light,
+        }
+    }
+}
+
+/// A comparison in a media feature, read as `feature <op> value`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Equal,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl Comparison {
+    /// The comparison that holds with the operands swapped, for
+    /// `value <op> feature`.
+    fn flipped(self) -> Self {
+        match self {
+            Self::Equal => Self::Equal,
+            Self::Less => Self::Greater,
+            Self::LessOrEqual => Self::GreaterOrEqual,
+            Self::Greater => Self::Less,
+            Self::GreaterOrEqual => Self::LessOrEqual,
+        }
+    }
+
+    /// Whether both comparisons go the same way, as in `a < b <= c`.
+    fn same_direction(self, other: Self) -> bool {
+        let less = |comparison| matches!(comparison, Self::Less | Self::LessOrEqual);
+        let greater = |comparison| matches!(comparison, Self::Greater | Self::GreaterOrEqual);
+        (less(self) && less(other)) || (greater(self) && greater(other))
+    }
+
+    fn holds(self, left: f64, right: f64) -> bool {
+        match self {
+            Self::Equal => (left - right).abs() < 1e-6,
+            Self::Less => left < right,
+            Self::LessOrEqual => left <= right,
+            Self::Greater => left > right,
+            Self::GreaterOrEqual => left >= right,
+        }
+    }
+}
+
+/// The value of a media feature. Lengths are in CSS pixels, resolutions in
+/// device pixels per CSS pixel, and ratios are divided out.
+#[derive(Debug, Clone, PartialEq)]
+enum FeatureValue {
+    Number(f64),
+    Keyword(String),
+}
+
+/// A test of one media feature, such as `(min-width: 600px)` or
+/// `(prefers-color-scheme: dark)`.
+#[derive(Debug, Clone, PartialEq)]
+struct Feature {
+    name: String,
+
+    /// The comparisons the feature must pass. With none, as in `(hover)`,
+    /// the feature must merely not be zero or `none`.
+    comparisons: Vec<(Comparison, FeatureValue)>,
+}
+
+/// A media condition (<https://www.w3.org/TR/mediaqueries-4/#media-conditions>).
+#[derive(Debug, Clone, PartialEq)]
+enum Condition {
+    Feature(Feature),
+    Not(Box<Self>),
+    And(Vec<Self>),
+    Or(Vec<Self>),
+
+    /// Something that is not a valid condition. It is neither true nor
+    /// false, even negated, so a query that depends on it never matches.
+    Unknown,
+}
+
+/// One query of a list, such as `only screen and (max-width: 600px)`.
+#[derive(Debug, Clone, PartialEq)]
+struct MediaQuery {
+    negated: bool,
+
+    /// The lowercase media type, or `None` for `all`.
+    media_type: Option<String>,
+    condition: Option<Condition>,
+}
+
+/// A comma-separated list of media queries, from the prelude of an
+/// `@media` rule or a `media` attribute. It matches if any of its queries
+/// does, or if it is empty.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MediaQueryList {
+    /// `None` for a query that is not valid, which never matches.
+    queries: Vec<Option<MediaQuery>>,
+}
+
+impl MediaQueryList {
+    pub fn parse(media: &str) -> Self {
+        Self::from_component_values(&parse_component_values(media))
+    }
+
+    pub fn from_component_values(values: &[ComponentValue]) -> Self {
+        let values: Vec<&ComponentValue> = values
+            .iter()
+            .filter(|value| !value.is_whitespace())
+            .collect();
+        if values.is_empty() {
+            return Self::default();
+        }
+        Self {
+            queries: values
+                .split(|value| matches!(value, ComponentValue::Token(CssToken::Comma)))
+                .map(MediaQuery::parse)
+                .collect(),
+        }
+    }
+
+    pub fn matches(&self, device: &Device) -> bool {
+        self.queries.is_empty()
+            || self
+                .queries
+                .iter()
+                .flatten()
+                .any(|query| query.matches(device))
+    }
+}
+
+impl MediaQuery {
+    fn parse(values: &[&ComponentValue]) -> Option<Self> {
+        let (modifier, rest) = match values {
+            [first, rest @ ..]
+                if matches!(ident(first).as_deref(), Some("not" | "only"))
+                    && rest.first().and_then(|value| ident(value)).is_some() =>
+            {
+                (ident(first), rest)
+            }
+            _ => (None, values),
+        };
+        let Some(media_type) = rest
+            .first()
+            .and_then(|value| ident(value))
+            .filter(|name| name != "not")
+        else {
+            return Some(Self {
+                negated: false,
+                media_type: None,
+                condition: Some(condition(rest)?),
+            });
+        };
+        let media_type = match media_type.as_str() {
+            "all" => None,
+            "and" | "or" | "only" | "layer" => return None,
+            _ => Some(media_type),
+        };
+        let condition = match rest.get(1..).unwrap_or_default() {
+            [] => None,
+            [and, rest @ ..] if ident(and).as_deref() == Some("and") => {
+                let condition = condition(rest)?;
+                if matches!(condition, Condition::Or(_)) {
+                    return None;
+                }
+                Some(condition)
+            }
+            _ => return None,
+        };
+        Some(Self {
+            negated: modifier.as_deref() == Some("not"),
+            media_type,
+            condition,
+        })
+    }
+
+    fn matches(&self, device: &Device) -> bool {
+        let type_matches = self
+            .media_type
+            .as_deref()
+            .is_none_or(|media_type| media_type == "screen");
+        let matches = if type_matches {
+            self.condition
+                .as_ref()
+                .map_or(Some(true), |condition| condition.matches(device))
+        } else {
+            Some(false)
+        };
+        matches.is_some_and(|matches| matches != self.negated)
+    }
+}
+
+impl Condition {
+    /// Whether the condition holds, or `None` if that is unknown because
+    /// of a feature or syntax this engine does not understand.
+    fn matches(&self, device: &Device) -> Option<bool> {
+        match self {
+            Self::Feature(feature) => feature.matches(device),
+            Self::Not(condition) => condition.matches(device).map(|matches| !matches),
+            Self::And(conditions) | Self::Or(conditions) => {
+                // One false makes `and` false and one true makes `or` true,
+                // whatever the others are.
+                let decisive = matches!(self, Self::Or(_));
+                let results: Vec<Option<bool>> = conditions
+                    .iter()
+                    .map(|condition| condition.matches(device))
+                    .collect();
+                if results.contains(&Some(decisive)) {
+                    Some(decisive)
+                } else if results.contains(&None) {
+                    None
+                } else {
+                    Some(!decisive)
+                }
+            }
+            Self::Unknown => None,
+        }
+    }
+}
+
+impl Feature {
+    fn matches(&self, device: &Device) -> Option<bool> {
+        let actual = device_value(&self.name, device)?;
+        if self.comparisons.is_empty() {
+            return Some(match actual {
+                FeatureValue::Number(number) => number != 0.0,
+                FeatureValue::Keyword(keyword) => keyword != "none" && keyword != "no-preference",
+            });
+        }
+        Some(
+            self.comparisons
+                .iter()
+                .all(|(comparison, expected)| match (&actual, expected) {
+                    (FeatureValue::Number(actual), FeatureValue::Number(expected)) => {
+                        comparison.holds(*actual, *expected)
+                    }
+                    (FeatureValue::Keyword(actual), FeatureValue::Keyword(expected)) => {
+                        *comparison == Comparison::Equal && actual == expected
+                    }
+                    _ => false,
+                }),
+        )
+    }
+}
+
+/// The value of the media feature `name` on `device`, or `None` for a
+/// feature this engine does not know.
+fn device_value(name: &str, device: &Device) -> Option<FeatureValue> {
+    let (width, height) = (f64::from(device.width), f64::from(device.height));
+    let keyword = |keyword: &str| Some(FeatureValue::Keyword(keyword.to_owned()));
+    match name {
+        "width" | "device-width" => Some(FeatureValue::Number(width)),
+        "height" | "device-height" => Some(FeatureValue::Number(height)),
+        "aspect-ratio" | "device-aspect-ratio" => Some(FeatureValue::Number(width / height)),
+        "resolution" | "device-pixel-ratio" => {
+            Some(FeatureValue::Number(f64::from(device.pixel_ratio)))
+        }
+        "orientation" if height >= width => keyword("portrait"),
+        "orientation" => keyword("landscape"),
+        "prefers-color-scheme" => match device.color_scheme {
+            ColorScheme::Light => keyword("light"),
+            ColorScheme::Dark => keyword("dark"),
+        },
+        "hover" | "any-hover" => keyword("hover"),
+        "pointer" | "any-pointer" => keyword("fine"),
+        "color" => Some(FeatureValue::Number(8.0)),
+        "color-index" | "monochrome" | "grid" => Some(FeatureValue::Number(0.0)),
+        "scripting" | "forced-colors" | "inverted-colors" => keyword("none"),
+        "update" => keyword("fast"),
+        "display-mode" => keyword("browser"),
+        "dynamic-range" | "video-dynamic-range" => keyword("standard"),
+        "prefers-contrast"
+        | "prefers-reduced-data"
+        | "prefers-reduced-motion"
+        | "prefers-reduced-transparency" => keyword("no-preference"),
+        _ => None,
+    }
+}
+
+/// Parses a media condition: `not (...)`, or parenthesized conditions
+/// joined by all `and` or all `or`.
+fn condition(values: &[&ComponentValue]) -> Option<Condition> {
+    if let [not, value] = values
+        && ident(not).as_deref() == Some("not")
+    {
+        return Some(Condition::Not(Box::new(in_parens(value)?)));
+    }
+    let (first, rest) = values.split_first()?;
+    let mut conditions = vec![in_parens(first)?];
+    let mut joiner = None;
+    for pair in rest.chunks(2) {
+        let [word, value] = pair else {
+            return None;
+        };
+        let word = ident(word)?;
+        if !matches!(word.as_str(), "and" | "or") || joiner.as_ref().is_some_and(|j| *j != word) {
+            return None;
+        }
+        joiner = Some(word);
+        conditions.push(in_parens(value)?);
+    }
+    Some(match joiner.as_deref() {
+        None => conditions.pop()?,
+        Some("and") => Condition::And(conditions),
+        Some(_) => Condition::Or(conditions),
+    })
+}
+
+/// Parses a parenthesized condition or media feature. Anything else in
+/// parentheses is an unknown condition, which is allowed but never
+/// matches, so that newer syntax does not invalidate the whole query.
+fn in_parens(value: &ComponentValue) -> Option<Condition> {
+    let ComponentValue::Block {
+        kind: BlockKind::Paren,
+        contents,
+    } = value
+    else {
+        return None;
+    };
+    let contents: Vec<&ComponentValue> = contents
+        .iter()
+        .filter(|value| !value.is_whitespace())
+        .collect();
+    Some(
+        condition(&contents)
+            .or_else(|| feature(&contents).map(Condition::Feature))
+            .unwrap_or(Condition::Unknown),
+    )
+}
+
+/// Parses the inside of a media feature: `name`, `name: value`, or the
+/// range syntax of Media Queries 4.
+fn feature(values: &[&ComponentValue]) -> Option<Feature> {
+    let name = |value| {
+        let name = ident(value)?;
+        Some(name.strip_prefix("-webkit-").unwrap_or(&name).to_owned())
+    };
+    match values {
+        [value] => Some(Feature {
+            name: name(value)?,
+            comparisons: Vec::new(),
+        }),
+        [feature, ComponentValue::Token(CssToken::Colon), value @ ..] => {
+            let feature = name(feature)?;
+            let value = feature_value(value)?;
+            let (comparison, feature) = if let Some(feature) = feature.strip_prefix("min-") {
+                (Comparison::GreaterOrEqual, feature)
+            } else if let Some(feature) = feature.strip_prefix("max-") {
+                (Comparison::LessOrEqual, feature)
+            } else {
+                (Comparison::Equal, feature.as_str())
+            };
+            Some(Feature {
+                name: feature.to_owned(),
+                comparisons: vec![(comparison, value)],
+            })
+        }
+        _ => range(values),
+    }
+}
+
+/// Parses `name <op> value`, `value <op> name` or
+/// `value <op> name <op> value`, as in `(400px <= width < 700px)`.
+fn range(values: &[&ComponentValue]) -> Option<Feature> {
+    let mut segments: Vec<Vec<&ComponentValue>> = vec![Vec::new()];
+    let mut comparisons = Vec::new();
+    let mut rest = values;
+    while let Some((&value, after)) = rest.split_first() {
+        if let Some((comparison, length)) = comparison(rest) {
+            comparisons.push(comparison);
+            segments.push(Vec::new());
+            rest = rest.get(length..).unwrap_or_default();
+        } else {
+            segments.last_mut()?.push(value);
+            rest = after;
+        }
+    }
+    let name = |segment: &[&ComponentValue]| match segment {
+        [value] => ident(value),
+        _ => None,
+    };
+    let comparisons = match (segments.as_slice(), comparisons.as_slice()) {
+        ([feature, value], &[comparison]) if name(feature).is_some() => {
+            (name(feature)?, vec![(comparison, feature_value(value)?)])
+        }
+        ([value, feature], &[comparison]) => (
+            name(feature)?,
+            vec![(comparison.flipped(), feature_value(value)?)],
+        ),
+        ([low, feature, high], &[first, second]) if first.same_direction(second) => (
+            name(feature)?,
+            vec![
+                (first.flipped(), feature_value(low)?),
+                (second, feature_value(high)?),
+            ],
+        ),
+        _ => return None,
+    };
+    Some(Feature {
+        name: comparisons.0,
+        comparisons: comparisons.1,
+    })
+}
+
+/// The comparison operator at the start of `values`, and how many tokens
+/// it takes.
+fn comparison(values: &[&ComponentValue]) -> Option<(Comparison, usize)> {
+    let delim = |index: usize| match values.get(index) {
+        Some(ComponentValue::Token(CssToken::Delim(delim))) => Some(*delim),
+        _ => None,
+    };
+    let or_equal = delim(1) == Some('=');
+    match delim(0)? {
+        '=' => Some((Comparison::Equal, 1)),
+        '<' if or_equal => Some((Comparison::LessOrEqual, 2)),
+        '<' => Some((Comparison::Less, 1)),
+        '>' if or_equal => Some((Comparison::GreaterOrEqual, 2)),
+        '>' => Some((Comparison::Greater, 1)),
+        _ => None,
+    }
+}
+
+/// Parses a feature value: a number, a length, a resolution, a ratio
+/// such as `16/9`, or a keyword.
+fn feature_value(values: &[&ComponentValue]) -> Option<FeatureValue> {
+    match values {
+        [ComponentValue::Token(CssToken::Number(number))] => {
+            Some(FeatureValue::Number(number.value))
+        }
+        [ComponentValue::Token(CssToken::Dimension { value, unit })] => {
+            let scale = match unit.to_ascii_lowercase().as_str() {
+                "px" | "dppx" | "x" => 1.0,
+                "em" | "rem" | "pc" => 16.0,
+                "in" => 96.0,
+                "cm" => 96.0 / 2.54,
+                "mm" => 96.0 / 25.4,
+                "q" => 96.0 / 101.6,
+                "pt" => 96.0 / 72.0,
+                "dpi" => 1.0 / 96.0,
+                "dpcm" => 2.54 / 96.0,
+                _ => return None,
+            };
+            Some(FeatureValue::Number(value.value * scale))
+        }
+        [
+            ComponentValue::Token(CssToken::Number(numerator)),
+            ComponentValue::Token(CssToken::Delim('/')),
+            ComponentValue::Token(CssToken::Number(denominator)),
+        ] if denominator.value != 0.0 => {
+            Some(FeatureValue::Number(numerator.value / denominator.value))
+        }
+        [value] => ident(value).map(FeatureValue::Keyword),
+        _ => None,
+    }
+}
+
+/// The lowercase name of an identifier.
+fn ident(value: &ComponentValue) -> Option<String> {
+    match value {
+        ComponentValue::Token(CssToken::Ident(name)) => Some(name.to_ascii_lowercase()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(media: &str, device: &Device) -> bool {
+        MediaQueryList::parse(media).matches(device)
+    }
+
+    #[test]
+    fn test_media_types() {
+        let device = Device::default();
+        assert!(matches("", &device));
+        assert!(matches("ALL", &device));
+        assert!(matches("print, screen", &device));
+        assert!(matches("not print", &device));
+        assert!(matches("only screen", &device));
+        assert!(!matches("not screen", &device));
+        assert!(!matches("print", &device));
+        assert!(!matches("only", &device));
+        assert!(!matches("screen and", &device));
+        assert!(!matches("and, print", &device));
+        assert!(matches("screen (color), all", &device));
+    }
+
+    #[test]
+    fn test_media_features() {
+        let device = Device {
+            width: 800.0,
+            height: 600.0,
+            pixel_ratio: 2.0,
+            color_scheme: ColorScheme::Dark,
+        };
+        assert!(matches("screen and (min-width: 600px)", &device));
+        assert!(matches("(max-width: 50em)", &device));
+        assert!(!matches("(max-width: 799px)", &device));
+        assert!(matches("(width >= 800px) and (height < 601px)", &device));
+        assert!(matches("(400px < width <= 800px)", &device));
+        assert!(!matches("(800px < width)", &device));
+        assert!(!matches("(400px < width > 300px)", &device));
+        assert!(matches("(prefers-color-scheme: dark)", &device));
+        assert!(matches(
+            "(orientation: landscape) and (aspect-ratio: 4/3)",
+            &device
+        ));
+        assert!(matches("(-webkit-min-device-pixel-ratio: 2)", &device));
+        assert!(matches("(min-resolution: 192dpi)", &device));
+        assert!(matches("(hover) and (not (monochrome))", &device));
+        assert!(matches("not all and (max-width: 600px)", &device));
+        assert!(matches("(max-width: 600px) or (color)", &device));
+        assert!(!matches("(prefers-reduced-motion)", &device));
+        assert!(!matches("(unknown-feature: 1)", &device));
+        assert!(!matches("not (unknown-feature: 1)", &device));
+        assert!(!matches("screen and (color) or (hover)", &device));
+    }
+}
@@ -0,0 +1,667 @@
+use super::Color;
+use super::variables::contains_var;
+use crate::css::{ComponentValue, CssToken, Declaration, parse_component_values, trim_whitespace};
+
+/// The shorthand properties the engine expands, with their longhands in
+/// the order [`split`] gives their values.
+const SHORTHANDS: [(&str, &[&str]); 13] = [
+    (
+        "background",
+        &[
+            "background-color",
+            "background-image",
+            "background-repeat",
+            "background-attachment",
+            "background-position",
+            "background-size",
+            "background-origin",
+            "background-clip",
+        ],
+    ),
+    (
+        "border",
+        &[
+            "border-top-width",
+            "border-top-style",
+            "border-top-color",
+            "border-right-width",
+            "border-right-style",
+            "border-right-color",
+            "border-bottom-width",
+            "border-bottom-style",
+            "border-bottom-color",
+            "border-left-width",
+            "border-left-style",
+            "border-left-color",
+        ],
+    ),
+    (
+        "border-bottom",
+        &[
+            "border-bottom-width",
+            "border-bottom-style",
+            "border-bottom-color",
+        ],
+    ),
+    (
+        "border-color",
+        &[
+            "border-top-color",
+            "border-right-color",
+            "border-bottom-color",
+            "border-left-color",
+        ],
+    ),
+    (
+        "border-left",
+        &[
+            "border-left-width",
+            "border-left-style",
+            "border-left-color",
+        ],
+    ),
+    (
+        "border-radius",
+        &[
+            "border-top-left-radius",
+            "border-top-right-radius",
+            "border-bottom-right-radius",
+            "border-bottom-left-radius",
+        ],
+    ),
+    (
+        "border-right",
+        &[
+            "border-right-width",
+            "border-right-style",
+            "border-right-color",
+        ],
+    ),
+    (
+        "border-style",
+        &[
+            "border-top-style",
+            "border-right-style",
+            "border-bottom-style",
+            "border-left-style",
+        ],
+    ),
+    (
+        "border-top",
+        &["border-top-width", "border-top-style", "border-top-color"],
+    ),
+    (
+        "border-width",
+        &[
+            "border-top-width",
+            "border-right-width",
+            "border-bottom-width",
+            "border-left-width",
+        ],
+    ),
+    (
+        "font",
+        &[
+            "font-style",
+            "font-variant",
+            "font-weight",
+            "font-stretch",
+            "font-size",
+            "line-height",
+            "font-family",
+        ],
+    ),
+    (
+        "margin",
+        &["margin-top", "margin-right", "margin-bottom", "margin-left"],
+    ),
+    (
+        "padding",
+        &[
+            "padding-top",
+            "padding-right",
+            "padding-bottom",
+            "padding-left",
+        ],
+    ),
+];
+
+/// A declaration of a longhand property, which is what the cascade sorts.
+#[derive(Debug, Clone)]
+pub(super) struct Longhand {
+    pub(super) name: String,
+    pub(super) value: Vec<ComponentValue>,
+    pub(super) important: bool,
+
+    /// The shorthand the declaration comes from, if the shorthand's value
+    /// has `var()` references. The value is then the whole value of the
+    /// shorthand, which can only be split once they are replaced.
+    pub(super) pending: Option<&'static str>,
+}
+
+/// The longhand declarations `declaration` stands for: itself if it sets a
+/// longhand, and nothing if it sets a shorthand to a value that is not
+/// valid.
+pub(super) fn expand(declaration: &Declaration) -> Vec<Longhand> {
+    let longhand = |name: &str, value: Vec<ComponentValue>, pending| Longhand {
+        name: name.to_owned(),
+        value,
+        important: declaration.important,
+        pending,
+    };
+    let Some(&(shorthand, longhands)) = SHORTHANDS
+        .iter()
+        .find(|(shorthand, _)| *shorthand == declaration.name)
+    else {
+        return vec![longhand(&declaration.name, declaration.value.clone(), None)];
+    };
+    if contains_var(&declaration.value) {
+        return longhands
+            .iter()
+            .map(|name| longhand(name, declaration.value.clone(), Some(shorthand)))
+            .collect();
+    }
+    split(shorthand, &declaration.value)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(name, value)| longhand(name, value, None))
+        .collect()
+}
+
+/// The value `value` of `shorthand` gives each of its longhands, with
+/// those it leaves out reset to their initial value, or `None` if it is not
+/// a valid value for the shorthand. A CSS-wide keyword such as `inherit`
+/// goes to every longhand.
+pub(super) fn split(
+    shorthand: &str,
+    value: &[ComponentValue],
+) -> Option<Vec<(&'static str, Vec<ComponentValue>)>> {
+    let &(_, longhands) = SHORTHANDS.iter().find(|(name, _)| *name == shorthand)?;
+    let components: Vec<&ComponentValue> = value
+        .iter()
+        .filter(|value| !value.is_whitespace())
+        .collect();
+    if let [component] = components.as_slice()
+        && keyword(component).is_some_and(|keyword| {
+            matches!(keyword.as_str(), "inherit" | "initial" | "unset" | "revert")
+        })
+    {
+        let value = vec![(*component).clone()];
+        return Some(
+            longhands
+                .iter()
+                .map(|&name| (name, value.clone()))
+                .collect(),
+        );
+    }
+    let values = match shorthand {
+        "background" => background(value)?,
+        "border" => {
+            let side = border_side(&components)?;
+            (0..4).flat_map(|_| side.clone()).collect()
+        }
+        "border-top" | "border-right" | "border-bottom" | "border-left" => {
+            border_side(&components)?
+        }
+        "border-color" => four_sides(&components, is_color)?,
+        "border-style" => four_sides(&components, is_border_style)?,
+        "border-width" => four_sides(&components, is_border_width)?,
+        "font" => font(value)?,
+        "margin" => four_sides(&components, |value| {
+            keyword(value).as_deref() == Some("auto") || is_length_or_percentage(value)
+        })?,
+        // Corners go clockwise from the top left one, as sides do from the
+        // top. Elliptical corners, after a `/`, are not supported.
+        "border-radius" | "padding" => four_sides(&components, is_length_or_percentage)?,
+        _ => return None,
+    };
+    Some(longhands.iter().copied().zip(values).collect())
+}
+
+/// Splits one to four values into the top, right, bottom and left ones, as
+/// `margin` does.
+fn four_sides(
+    components: &[&ComponentValue],
+    valid: fn(&ComponentValue) -> bool,
+) -> Option<Vec<Vec<ComponentValue>>> {
+    if !components.iter().all(|component| valid(component)) {
+        return None;
+    }
+    let sides = match *components {
+        [all] => [all; 4],
+        [vertical, horizontal] => [vertical, horizontal, vertical, horizontal],
+        [top, horizontal, bottom] => [top, horizontal, bottom, horizontal],
+        [top, right, bottom, left] => [top, right, bottom, left],
+        _ => return None,
+    };
+    Some(sides.iter().map(|&side| vec![side.clone()]).collect())
+}
+
+/// Splits a value of `border-top` or the like into the width, style and
+/// color, which can come in any order.
+fn border_side(components: &[&ComponentValue]) -> Option<Vec<Vec<ComponentValue>>> {
+    if components.is_empty() {
+        return None;
+    }
+    let (mut width, mut style, mut color) = (None, None, None);
+    for &component in components {
+        let slot = if is_border_style(component) {
+            &mut style
+        } else if is_border_width(component) {
+            &mut width
+        } else if is_color(component) {
+            &mut color
+        } else {
+            return None;
+        };
+        if slot.replace(component).is_some() {
+            return None;
+        }
+    }
+    Some(vec![
+        or_initial(width, "medium"),
+        or_initial(style, "none"),
+        or_initial(color, "currentcolor"),
+    ])
+}
+
+/// Splits a value of `font`: optional style, variant, weight and stretch in
+/// any order, then the size with an optional `/` and line height, then the
+/// family list.
+fn font(value: &[ComponentValue]) -> Option<Vec<Vec<ComponentValue>>> {
+    let (mut style, mut variant, mut weight, mut stretch) = (None, None, None, None);
+    let mut rest = value;
+    let size = loop {
+        let (component, after) = next(rest)?;
+        rest = after;
+        match keyword(component).as_deref() {
+            // `normal` is allowed for any of them, and changes nothing.
+            Some("normal") => {}
+            Some("italic" | "oblique") if style.is_none() => style = Some(component),
+            Some("small-caps") if variant.is_none() => variant = Some(component),
+            Some("bold" | "bolder" | "lighter") if weight.is_none() => weight = Some(component),
+            Some(
+                "ultra-condensed" | "extra-condensed" | "condensed" | "semi-condensed"
+                | "semi-expanded" | "expanded" | "extra-expanded" | "ultra-expanded",
+            ) if stretch.is_none() => stretch = Some(component),
+            _ if weight.is_none() && is_font_weight_number(component) => {
+                weight = Some(component);
+            }
+            _ => break component,
+        }
+    };
+    let is_size_keyword = keyword(size).is_some_and(|keyword| {
+        matches!(
+            keyword.as_str(),
+            "xx-small"
+                | "x-small"
+                | "small"
+                | "medium"
+                | "large"
+                | "x-large"
+                | "xx-large"
+                | "xxx-large"
+                | "larger"
+                | "smaller"
+        )
+    });
+    if !is_size_keyword && !is_length_or_percentage(size) {
+        return None;
+    }
+    let mut line_height = None;
+    if let Some((ComponentValue::Token(CssToken::Delim('/')), after)) = next(rest) {
+        let (component, after) = next(after)?;
+        line_height = Some(component);
+        rest = after;
+    }
+    let family = trim_whitespace(rest);
+    if family.is_empty() {
+        return None;
+    }
+    Some(vec![
+        or_initial(style, "normal"),
+        or_initial(variant, "normal"),
+        or_initial(weight, "normal"),
+        or_initial(stretch, "normal"),
+        vec![size.clone()],
+        or_initial(line_height, "normal"),
+        family.to_vec(),
+    ])
+}
+
+/// One comma-separated layer of a `background` value.
+#[derive(Default)]
+struct BackgroundLayer<'v> {
+    color: Vec<&'v ComponentValue>,
+    image: Vec<&'v ComponentValue>,
+    repeat: Vec<&'v ComponentValue>,
+    attachment: Vec<&'v ComponentValue>,
+    position: Vec<&'v ComponentValue>,
+    size: Vec<&'v ComponentValue>,
+    boxes: Vec<&'v ComponentValue>,
+}
+
+/// Splits a value of `background`, giving each longhand but
+/// `background-color` a comma-separated list with a value for each layer.
+/// Only the last layer can have a color.
+fn background(value: &[ComponentValue]) -> Option<Vec<Vec<ComponentValue>>> {
+    let layers: Vec<&[ComponentValue]> = value
+        .split(|value| *value == ComponentValue::Token(CssToken::Comma))
+        .collect();
+    let layers = layers
+        .iter()
+        .enumerate()
+        .map(|(index, layer)| background_layer(layer, index + 1 == layers.len()))
+        .collect::<Option<Vec<_>>>()?;
+
+    let list = |values: &dyn Fn(&BackgroundLayer<'_>) -> Vec<ComponentValue>| {
+        let mut list = Vec::new();
+        for (index, layer) in layers.iter().enumerate() {
+            if index > 0 {
+                list.push(ComponentValue::Token(CssToken::Comma));
+                list.push(ComponentValue::Token(CssToken::Whitespace));
+            }
+            list.extend(values(layer));
+        }
+        list
+    };
+    let or_initial = |values: &[&ComponentValue], initial: &str| {
+        if values.is_empty() {
+            parse_component_values(initial)
+        } else {
+            join(values)
+        }
+    };
+    let color = layers.last().and_then(|layer| layer.color.first().copied());
+    Some(vec![
+        or_initial(color.as_slice(), "transparent"),
+        list(&|layer| or_initial(&layer.image, "none")),
+        list(&|layer| or_initial(&layer.repeat, "repeat")),
+        list(&|layer| or_initial(&layer.attachment, "scroll")),
+        list(&|layer| or_initial(&layer.position, "0% 0%")),
+        list(&|layer| or_initial(&layer.size, "auto")),
+        list(&|layer| or_initial(layer.boxes.get(..1).unwrap_or_default(), "padding-box")),
+        list(&|layer| {
+            let clip = layer.boxes.get(1..).filter(|clip| !clip.is_empty());
+            or_initial(clip.unwrap_or(layer.boxes.as_slice()), "border-box")
+        }),
+    ])
+}
+
+fn background_layer(layer: &[ComponentValue], last: bool) -> Option<BackgroundLayer<'_>> {
+    let mut parsed = BackgroundLayer::default();
+    // Whether the components are the size, after a `/` that follows the
+    // position.
+    let mut in_size = false;
+    let mut has_size = false;
+    for component in layer.iter().filter(|value| !value.is_whitespace()) {
+        let keyword = keyword(component);
+        let keyword = keyword.as_deref();
+        if in_size {
+            if matches!(keyword, Some("auto" | "cover" | "contain"))
+                || is_length_or_percentage(component)
+            {
+                parsed.size.push(component);
+                continue;
+            }
+            in_size = false;
+        }
+        match keyword {
+            Some("repeat" | "repeat-x" | "repeat-y" | "no-repeat" | "space" | "round") => {
+                parsed.repeat.push(component);
+            }
+            Some("scroll" | "fixed" | "local") => parsed.attachment.push(component),
+            Some("border-box" | "padding-box" | "content-box") => parsed.boxes.push(component),
+            Some("left" | "right" | "top" | "bottom" | "center") => {
+                parsed.position.push(component);
+            }
+            _ if is_length_or_percentage(component) => parsed.position.push(component),
+            _ if is_image(component) => parsed.image.push(component),
+            _ if last && is_color(component) => parsed.color.push(component),
+            _ if *component == ComponentValue::Token(CssToken::Delim('/'))
+                && !parsed.position.is_empty()
+                && !has_size =>
+            {
+                in_size = true;
+                has_size = true;
+            }
+            _ => return None,
+        }
+    }
+    let valid = parsed.color.len() <= 1
+        && parsed.image.len() <= 1
+        && parsed.repeat.len() <= 2
+        && parsed.attachment.len() <= 1
+        && parsed.position.len() <= 4
+        && parsed.size.len() <= 2
+        && parsed.boxes.len() <= 2
+        && has_size != parsed.size.is_empty();
+    valid.then_some(parsed)
+}
+
+/// `component` on its own, or else `initial` parsed.
+fn or_initial(component: Option<&ComponentValue>, initial: &str) -> Vec<ComponentValue> {
+    component.map_or_else(
+        || parse_component_values(initial),
+        |component| vec![component.clone()],
+    )
+}
+
+/// `components` with whitespace between them.
+fn join(components: &[&ComponentValue]) -> Vec<ComponentValue> {
+    let mut joined = Vec::with_capacity(components.len() * 2);
+    for (index, &component) in components.iter().enumerate() {
+        if index > 0 {
+            joined.push(ComponentValue::Token(CssToken::Whitespace));
+        }
+        joined.push(component.clone());
+    }
+    joined
+}
+
+/// The first component of `values` that is not whitespace, and the values
+/// after it.
+fn next(values: &[ComponentValue]) -> Option<(&ComponentValue, &[ComponentValue])> {
+    let start = values.iter().position(|value| !value.is_whitespace())?;
+    let (first, rest) = values.get(start..)?.split_first()?;
+    Some((first, rest))
+}
+
+/// The keyword `value` is, in lowercase, if it is one.
+fn keyword(value: &ComponentValue) -> Option<String> {
+    match value {
+        ComponentValue::Token(CssToken::Ident(keyword)) => Some(keyword.to_ascii_lowercase()),
+        _ => None,
+    }
+}
+
+fn is_length_or_percentage(value: &ComponentValue) -> bool {
+    match value {
+        ComponentValue::Token(CssToken::Dimension { .. } | CssToken::Percentage(_)) => true,
+        ComponentValue::Token(CssToken::Number(number)) => number.value == 0.0,
+        ComponentValue::Function { name, .. } => {
+            matches!(
+                name.to_ascii_lowercase().as_str(),
+                "calc" | "min" | "max" | "clamp"
+            )
+        }
+        _ => false,
+    }
+}
+
+fn is_border_width(value: &ComponentValue) -> bool {
+    matches!(keyword(value).as_deref(), Some("thin" | "medium" | "thick"))
+        || is_length_or_percentage(value)
+            && !matches!(value, ComponentValue::Token(CssToken::Percentage(_)))
+}
+
+fn is_border_style(value: &ComponentValue) -> bool {
+    matches!(
+        keyword(value).as_deref(),
+        Some(
+            "none"
+                | "hidden"
+                | "dotted"
+                | "dashed"
+                | "solid"
+                | "double"
+                | "groove"
+                | "ridge"
+                | "inset"
+                | "outset"
+        )
+    )
+}
+
+fn is_color(value: &ComponentValue) -> bool {
+    Color::parse(std::slice::from_ref(value), Color::BLACK).is_some()
+}
+
+fn is_font_weight_number(value: &ComponentValue) -> bool {
+    matches!(
+        value,
+        ComponentValue::Token(CssToken::Number(number)) if (1.0..=1000.0).contains(&number.value)
+    )
+}
+
+fn is_image(value: &ComponentValue) -> bool {
+    match value {
+        ComponentValue::Token(CssToken::Url(_)) => true,
+        ComponentValue::Token(CssToken::Ident(keyword)) => keyword.eq_ignore_ascii_case("none"),
+        ComponentValue::Function { name, .. } => {
+            let name = name.to_ascii_lowercase();
+            name == "url" || name == "image-set" || name.ends_with("-gradient")
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::{parse_declarations, serialize};
+
+    /// The longhands the declarations in `css` expand to, with their values
+    /// written back as text.
+    fn expanded(css: &str) -> Vec<(String, String)> {
+        parse_declarations(css)
+            .iter()
+            .flat_map(expand)
+            .map(|longhand| (longhand.name, serialize(&longhand.value)))
+            .collect()
+    }
+
+    fn pairs(expected: &[(&str, &str)]) -> Vec<(String, String)> {
+        expected
+            .iter()
+            .map(|&(name, value)| (name.to_owned(), value.to_owned()))
+            .collect()
+    }
+
+    #[test]
+    fn test_box_and_border() {
+        assert_eq!(
+            expanded("margin: 1px auto; padding: 1px 2px 3px !important"),
+            pairs(&[
+                ("margin-top", "1px"),
+                ("margin-right", "auto"),
+                ("margin-bottom", "1px"),
+                ("margin-left", "auto"),
+                ("padding-top", "1px"),
+                ("padding-right", "2px"),
+                ("padding-bottom", "3px"),
+                ("padding-left", "2px"),
+            ])
+        );
+        assert_eq!(
+            expanded("border-top: red 2px; border-style: solid dashed none dotted"),
+            pairs(&[
+                ("border-top-width", "2px"),
+                ("border-top-style", "none"),
+                ("border-top-color", "red"),
+                ("border-top-style", "solid"),
+                ("border-right-style", "dashed"),
+                ("border-bottom-style", "none"),
+                ("border-left-style", "dotted"),
+            ])
+        );
+        assert_eq!(
+            expanded("border-radius: 4px 50%"),
+            pairs(&[
+                ("border-top-left-radius", "4px"),
+                ("border-top-right-radius", "50%"),
+                ("border-bottom-right-radius", "4px"),
+                ("border-bottom-left-radius", "50%"),
+            ])
+        );
+        let border = expanded("border: thin solid");
+        assert_eq!(border.len(), 12);
+        assert_eq!(border[9], pairs(&[("border-left-width", "thin")])[0]);
+        assert_eq!(
+            border[11],
+            pairs(&[("border-left-color", "currentcolor")])[0]
+        );
+
+        // Invalid values are dropped, and CSS-wide keywords apply to all.
+        assert!(expanded("margin: 1px 2px 3px 4px 5px; padding: auto; border: 1px 2px").is_empty());
+        assert_eq!(
+            expanded("padding: inherit")[3],
+            pairs(&[("padding-left", "inherit")])[0]
+        );
+        assert!(
+            parse_declarations("margin: 0 !important")
+                .iter()
+                .flat_map(expand)
+                .all(|longhand| longhand.important && longhand.pending.is_none())
+        );
+    }
+
+    #[test]
+    fn test_font_and_background() {
+        assert_eq!(
+            expanded("font: italic bold 12px/1.5 \"Times New Roman\", serif"),
+            pairs(&[
+                ("font-style", "italic"),
+                ("font-variant", "normal"),
+                ("font-weight", "bold"),
+                ("font-stretch", "normal"),
+                ("font-size", "12px"),
+                ("line-height", "1.5"),
+                ("font-family", "\"Times New Roman\", serif"),
+            ])
+        );
+        assert_eq!(expanded("font: 700 large monospace")[2].1, "700");
+        assert!(expanded("font: bold; font: 12px").is_empty());
+
+        assert_eq!(
+            expanded("background: url(a.png) no-repeat center / cover, #fff"),
+            pairs(&[
+                ("background-color", "#fff"),
+                ("background-image", "url(a.png), none"),
+                ("background-repeat", "no-repeat, repeat"),
+                ("background-attachment", "scroll, scroll"),
+                ("background-position", "center, 0% 0%"),
+                ("background-size", "cover, auto"),
+                ("background-origin", "padding-box, padding-box"),
+                ("background-clip", "border-box, border-box"),
+            ])
+        );
+        assert!(expanded("background: red, blue").is_empty());
+    }
+
+    #[test]
+    fn test_variables() {
+        let longhands: Vec<Longhand> = parse_declarations("margin: var(--m) 2px; color: var(--c)")
+            .iter()
+            .flat_map(expand)
+            .collect();
+        assert_eq!(longhands.len(), 5);
+        assert_eq!(longhands[0].pending, Some("margin"));
+        assert_eq!(serialize(&longhands[3].value), "var(--m) 2px");
+        assert_eq!(longhands[4].pending, None);
+
+        let margin = split("margin", &parse_component_values("1px 2px")).unwrap();
+        assert_eq!(margin[3].0, "margin-left");
+        assert_eq!(serialize(&margin[3].1), "2px");
+    }
+}
@@ -0,0 +1,218 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::css::{ComponentValue, CssToken, trim_whitespace};
+
+/// Replaces the `var()` references in the values of `properties`
+/// (<https://www.w3.org/TR/css-variables-1/#substitute-a-var>).
+///
+/// Custom properties are resolved first, as their values can refer to each
+/// other. A property that refers to a custom property that is not set, or
+/// that is part of a cycle, and has no fallback is invalid at computed-value
+/// time, and is removed.
+pub(super) fn resolve(properties: &mut HashMap<String, Vec<ComponentValue>>) {
+    let custom: Vec<&String> = properties
+        .iter()
+        .filter(|(name, value)| is_custom(name) && contains_var(value))
+        .map(|(name, _)| name)
+        .collect();
+    let mut resolver = CustomResolver {
+        properties,
+        resolved: HashMap::new(),
+        stack: Vec::new(),
+        cyclic: HashSet::new(),
+    };
+    for name in custom {
+        resolver.resolve(name);
+    }
+    let resolved: Vec<(String, Option<Vec<ComponentValue>>)> =
+        resolver.resolved.into_iter().collect();
+    for (name, value) in resolved {
+        match value {
+            Some(value) => properties.insert(name, value),
+            None => properties.remove(&name),
+        };
+    }
+
+    let standard: Vec<String> = properties
+        .iter()
+        .filter(|(name, value)| !is_custom(name) && contains_var(value))
+        .map(|(name, _)| name.clone())
+        .collect();
+    for name in standard {
+        let value = properties
+            .get(&name)
+            .and_then(|value| substitute(value, &mut |custom| properties.get(custom).cloned()));
+        match value {
+            Some(value) => properties.insert(name, value),
+            None => properties.remove(&name),
+        };
+    }
+}
+
+/// Whether `name` is a custom property, such as `--main-color`.
+pub(super) fn is_custom(name: &str) -> bool {
+    name.starts_with("--")
+}
+
+/// Resolves custom properties that refer to each other, depth first.
+struct CustomResolver<'p> {
+    properties: &'p HashMap<String, Vec<ComponentValue>>,
+
+    /// The properties resolved so far, with `None` for invalid ones.
+    resolved: HashMap<String, Option<Vec<ComponentValue>>>,
+
+    /// The properties being resolved, each referring to the next.
+    stack: Vec<String>,
+
+    /// The properties found to be part of a cycle, which are invalid even
+    /// if their references have fallbacks.
+    cyclic: HashSet<String>,
+}
+
+impl CustomResolver<'_> {
+    /// The value of the custom property `name` with its references
+    /// replaced, or `None` if it is not set or is invalid.
+    fn resolve(&mut self, name: &str) -> Option<Vec<ComponentValue>> {
+        if let Some(value) = self.resolved.get(name) {
+            return value.clone();
+        }
+        let properties = self.properties;
+        let value = properties.get(name)?;
+        if !contains_var(value) {
+            return Some(value.clone());
+        }
+        if let Some(start) = self.stack.iter().position(|resolving| resolving == name) {
+            self.cyclic
+                .extend(self.stack.get(start..).unwrap_or_default().iter().cloned());
+            return None;
+        }
+        self.stack.push(name.to_owned());
+        let value = substitute(value, &mut |custom| self.resolve(custom));
+        self.stack.pop();
+        let value = value.filter(|_| !self.cyclic.contains(name));
+        self.resolved.insert(name.to_owned(), value.clone());
+        value
+    }
+}
+
+/// Whether `value` has a `var()` reference, at any depth.
+pub(super) fn contains_var(value: &[ComponentValue]) -> bool {
+    value.iter().any(|value| match value {
+        ComponentValue::Function { name, arguments } => {
+            name.eq_ignore_ascii_case("var") || contains_var(arguments)
+        }
+        ComponentValue::Block { contents, .. } => contains_var(contents),
+        ComponentValue::Token(_) => false,
+    })
+}
+
+/// `value` with each `var(--name, fallback)` replaced by what `lookup`
+/// gives for `--name`, or else by the fallback. Returns `None` if a
+/// reference has neither, or is not valid.
+fn substitute(
+    value: &[ComponentValue],
+    lookup: &mut dyn FnMut(&str) -> Option<Vec<ComponentValue>>,
+) -> Option<Vec<ComponentValue>> {
+    let mut substituted = Vec::with_capacity(value.len());
+    for value in value {
+        match value {
+            ComponentValue::Function { name, arguments } if name.eq_ignore_ascii_case("var") => {
+                let (custom, fallback) = var_arguments(arguments)?;
+                match (lookup(custom), fallback) {
+                    (Some(value), _) => substituted.extend(value),
+                    (None, Some(fallback)) => substituted.extend(substitute(fallback, lookup)?),
+                    (None, None) => return None,
+                }
+            }
+            ComponentValue::Function { name, arguments } => {
+                substituted.push(ComponentValue::Function {
+                    name: name.clone(),
+                    arguments: substitute(arguments, lookup)?,
+                });
+            }
+            ComponentValue::Block { kind, contents } => {
+                substituted.push(ComponentValue::Block {
+                    kind: *kind,
+                    contents: substitute(contents, lookup)?,
+                });
+            }
+            ComponentValue::Token(_) => substituted.push(value.clone()),
+        }
+    }
+    Some(substituted)
+}
+
+/// Splits the arguments of `var()` into the custom property name and the
+/// fallback after the first comma, if there is one.
+fn var_arguments(arguments: &[ComponentValue]) -> Option<(&str, Option<&[ComponentValue]>)> {
+    let [ComponentValue::Token(CssToken::Ident(name)), rest @ ..] = trim_whitespace(arguments)
+    else {
+        return None;
+    };
+    if !is_custom(name) {
+        return None;
+    }
+    match trim_whitespace(rest) {
+        [] => Some((name, None)),
+        [ComponentValue::Token(CssToken::Comma), fallback @ ..] => {
+            Some((name, Some(trim_whitespace(fallback))))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::{parse_component_values, serialize};
+
+    fn resolved(declarations: &[(&str, &str)]) -> HashMap<String, String> {
+        let mut properties: HashMap<String, Vec<ComponentValue>> = declarations
+            .iter()
+            .map(|&(name, value)| (name.to_owned(), parse_component_values(value)))
+            .collect();
+        resolve(&mut properties);
+        properties
+            .into_iter()
+            .map(|(name, value)| (name, serialize(&value)))
+            .collect()
+    }
+
+    #[test]
+    fn test_substitution() {
+        let properties = resolved(&[
+            ("--color", "red"),
+            ("--border", "1px solid var(--color)"),
+            ("border", "var(--border)"),
+            ("color", "var( --missing , var(--color) )"),
+            ("font-family", "var(--missing, Arial, sans-serif)"),
+            ("margin", "calc(var(--size, 2px) + 1px)"),
+        ]);
+        assert_eq!(properties["--border"], "1px solid red");
+        assert_eq!(properties["border"], "1px solid red");
+        assert_eq!(properties["color"], "red");
+        assert_eq!(properties["font-family"], "Arial, sans-serif");
+        assert_eq!(properties["margin"], "calc(2px + 1px)");
+    }
+
+    #[test]
+    fn test_invalid_references() {
+        let properties = resolved(&[
+            ("--a", "var(--b)"),
+            ("--b", "var(--a)"),
+            ("--c", "var(--c, blue)"),
+            ("--d", "green"),
+            ("color", "var(--a)"),
+            ("background-color", "var(--unset)"),
+            ("border-top-color", "var(d)"),
+            ("outline-color", "var(--d)"),
+        ]);
+        assert!(!properties.contains_key("--a"));
+        assert!(!properties.contains_key("--b"));
+        assert!(!properties.contains_key("color"));
+        assert!(!properties.contains_key("background-color"));
+        assert!(!properties.contains_key("border-top-color"));
+        assert_eq!(properties["outline-color"], "green");
+        assert_eq!(properties.get("--c").map(String::as_str), None);
+    }
+}
@@ -0,0 +1,34 @@
+#!/usr/bin/env bash
+# Copies the upstream html5lib-tests tokenizer files at a commit of
+# html5lib-tests into this directory, as they are, and records the commit in
+# REVISION. Set a BASELINE entry in tests/html5lib_tokenizer.rs for each file
+# from the pass counts of `cargo test --test html5lib_tokenizer -- --nocapture`.
+set -eu
+
+if [ $# -ne 1 ]; then
+    echo "usage: $0 <html5lib-tests commit>" >&2
+    exit 2
+fi
+revision="$1"
+base="https://raw.githubusercontent.com/html5lib/html5lib-tests/${revision}/tokenizer"
+cd "$(dirname "$0")"
+
+for file in \
+    contentModelFlags.test \
+    domjs.test \
+    entities.test \
+    escapeFlag.test \
+    namedEntities.test \
+    numericEntities.test \
+    pendingSpecChanges.test \
+    test1.test \
+    test2.test \
+    test3.test \
+    test4.test \
+    unicodeChars.test \
+    unicodeCharsProblematic.test \
+    xmlViolation.test
+do
+    curl --fail --silent --show-error --location "${base}/${file}" --output "${file}"
+done
+echo "${revision}" > REVISION
@@ -0,0 +1,253 @@
+//! Runs tokenizer tests in the html5lib-tests format
+//! (<https://github.com/html5lib/html5lib-tests/tree/master/tokenizer>) from
+//! `tests/fixtures/html5lib/tokenizer` against `HtmlTokenizer` in spec
+//! conformance mode, and prints a pass/fail matrix:
+//!
+//! ```text
+//! cargo test --test html5lib_tokenizer -- --nocapture
+//! ```
+//!
+//! Only the tokens are compared, not the parse errors. Not every test passes
+//! yet, so each file has a minimum number of passes in [`BASELINE`]; raise it
+//! when the tokenizer improves, so that regressions fail.
+
+#![expect(
+    clippy::print_stdout,
+    reason = "the pass/fail matrix is the point of this test"
+)]
+
+use std::path::Path;
+
+use mochi_browser::html_tokenizer::{HtmlToken, HtmlTokenizer, TokenizerOptions};
+use serde_json::Value;
+
+/// The fewest tests each fixture file must pass, counting each initial
+/// state of a test separately.
+const BASELINE: [(&str, usize); 4] = [
+    ("basic.test", 23),
+    ("comments.test", 12),
+    ("references.test", 24),
+    ("states.test", 16),
+];
+
+#[derive(Debug, Default)]
+struct Tally {
+    passed: usize,
+    failed: Vec<String>,
+    skipped: usize,
+}
+
+#[test]
+fn test_html5lib_tokenizer_conformance() {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/html5lib/tokenizer");
+    let mut files: Vec<_> = std::fs::read_dir(&directory)
+        .expect("fixture directory is readable")
+        .map(|entry| entry.expect("fixture directory is readable").path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "test"))
+        .collect();
+    files.sort();
+    assert!(!files.is_empty(), "no fixtures in {}", directory.display());
+
+    println!(
+        "{:<24} {:>6} {:>6} {:>7}",
+        "file", "passed", "failed", "skipped"
+    );
+    let mut regressions = Vec::new();
+    for path in files {
+        let name = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+        let text = std::fs::read_to_string(&path).expect("fixture is readable");
+        let fixture: Value = serde_json::from_str(&text).expect("fixture is valid JSON");
+
+        // Upstream `xmlViolation.test` only has `xmlViolationTests`, for a
+        // mode the tokenizer does not have, so it runs no tests.
+        let mut tally = Tally::default();
+        let tests = fixture.get("tests").and_then(Value::as_array);
+        for test in tests.into_iter().flatten() {
+            run_test(test, &mut tally);
+        }
+
+        println!(
+            "{name:<24} {:>6} {:>6} {:>7}",
+            tally.passed,
+            tally.failed.len(),
+            tally.skipped
+        );
+        for description in &tally.failed {
+            println!("    failed: {description}");
+        }
+
+        let minimum = BASELINE
+            .iter()
+            .find(|(file, _)| *file == name)
+            .map_or(0, |(_, minimum)| *minimum);
+        if tally.passed < minimum {
+            regressions.push(format!("{name}: {} < {minimum}", tally.passed));
+        }
+    }
+
+    assert!(
+        regressions.is_empty(),
+        "fewer html5lib tests pass than before: {regressions:?}"
+    );
+}
+
+/// Runs `test` once per initial state it lists.
+fn run_test(test: &Value, tally: &mut Tally) {
+    let description = test
+        .get("description")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let double_escaped = test
+        .get("doubleEscaped")
+        .and_then(Value::as_bool)
+        .unwrap_or(false);
+    let unescape = |text: &str| {
+        if double_escaped {
+            unescape_double_escaped(text)
+        } else {
+            Some(text.to_owned())
+        }
+    };
+
+    let input = test.get("input").and_then(Value::as_str).and_then(unescape);
+    let expected = test
+        .get("output")
+        .and_then(Value::as_array)
+        .and_then(|output| {
+            output
+                .iter()
+                .map(|token| expected_token(token, &unescape))
+                .collect::<Option<Vec<_>>>()
+        });
+    let states: Vec<&str> = test
+        .get("initialStates")
+        .and_then(Value::as_array)
+        .map_or_else(
+            || vec!["Data state"],
+            |states| states.iter().filter_map(Value::as_str).collect(),
+        );
+    let last_start_tag = test.get("lastStartTag").and_then(Value::as_str);
+
+    for state in states {
+        let (Some(input), Some(expected)) = (&input, &expected) else {
+            tally.skipped += 1;
+            continue;
+        };
+        let Some(prefix) = state_prefix(state, last_start_tag) else {
+            tally.skipped += 1;
+            continue;
+        };
+
+        let source = format!("{prefix}{input}");
+        let tokens: Result<Vec<_>, _> = HtmlTokenizer::new(&source)
+            .with_options(TokenizerOptions::new().spec_conformance(true))
+            .iter()
+            .collect();
+        let actual = tokens.ok().map(|tokens| {
+            let skip = usize::from(!prefix.is_empty());
+            normalize(tokens.into_iter().skip(skip).collect())
+        });
+
+        if actual.as_ref() == Some(&normalize(expected.clone())) {
+            tally.passed += 1;
+        } else {
+            tally.failed.push(format!("{description} ({state})"));
+        }
+    }
+}
+
+/// The start tag that puts the tokenizer in `state`, which the public API
+/// cannot set directly. Without a `lastStartTag`, a typical element for the
+/// state is used. States no element leads to are skipped.
+fn state_prefix(state: &str, last_start_tag: Option<&str>) -> Option<String> {
+    let (elements, default): (&[&str], &str) = match state {
+        "Data state" => return Some(String::new()),
+        "RCDATA state" => (&["title", "textarea"], "title"),
+        "RAWTEXT state" => (&["style", "xmp", "iframe", "noembed", "noframes"], "style"),
+        "Script data state" => (&["script"], "script"),
+        "PLAINTEXT state" => (&["plaintext"], "plaintext"),
+        _ => return None,
+    };
+    let element = last_start_tag.unwrap_or(default);
+    elements.contains(&element).then(|| format!("<{element}>"))
+}
+
+/// Converts an html5lib output token, or returns `None` for one that has no
+/// equivalent.
+fn expected_token(token: &Value, unescape: &impl Fn(&str) -> Option<String>) -> Option<HtmlToken> {
+    let token = token.as_array()?;
+    let string = |index: usize| token.get(index).and_then(Value::as_str).and_then(unescape);
+
+    match token.first()?.as_str()? {
+        "Character" => Some(HtmlToken::Text(string(1)?)),
+        "Comment" => Some(HtmlToken::Comment(string(1)?)),
+        "EndTag" => Some(HtmlToken::EndTag { name: string(1)? }),
+        "StartTag" => {
+            let attributes = token
+                .get(2)?
+                .as_object()?
+                .iter()
+                .map(|(name, value)| Some((unescape(name)?, unescape(value.as_str()?)?)))
+                .collect::<Option<Vec<_>>>()?;
+            Some(HtmlToken::StartTag {
+                name: string(1)?,
+                attributes,
+                self_closing: token.get(3).and_then(Value::as_bool).unwrap_or(false),
+            })
+        }
+        "DOCTYPE" => {
+            // `HtmlToken::Doctype` keeps the identifiers as written, so this
+            // only matches the usual quoting.
+            let mut doctype = string(1).unwrap_or_default();
+            let public = string(2);
+            let system = string(3);
+            if let Some(public) = &public {
+                doctype.push_str(&format!(" PUBLIC \"{public}\""));
+            }
+            if let Some(system) = system {
+                let keyword = if public.is_some() { "" } else { " SYSTEM" };
+                doctype.push_str(&format!("{keyword} \"{system}\""));
+            }
+            Some(HtmlToken::Doctype(doctype))
+        }
+        _ => None,
+    }
+}
+
+/// Merges adjacent text, as html5lib does, and sorts attributes, since
+/// fixtures give them as an unordered object.
+fn normalize(tokens: Vec<HtmlToken>) -> Vec<HtmlToken> {
+    let mut normalized: Vec<HtmlToken> = Vec::new();
+    for mut token in tokens {
+        if let (Some(HtmlToken::Text(previous)), HtmlToken::Text(text)) =
+            (normalized.last_mut(), &token)
+        {
+            previous.push_str(text);
+            continue;
+        }
+        if let HtmlToken::StartTag { attributes, .. } = &mut token {
+            attributes.sort();
+        }
+        normalized.push(token);
+    }
+    normalized
+}
+
+/// Decodes the `\uXXXX` escapes of a `doubleEscaped` test. Returns `None`
+/// for a lone surrogate, which a Rust string cannot hold.
+fn unescape_double_escaped(text: &str) -> Option<String> {
+    let mut unescaped = String::new();
+    let mut rest = text;
+    while let Some(index) = rest.find("\\u") {
+        unescaped.push_str(rest.get(..index)?);
+        let code = u32::from_str_radix(rest.get(index + 2..index + 6)?, 16).ok()?;
+        unescaped.push(char::from_u32(code)?);
+        rest = rest.get(index + 6..)?;
+    }
+    unescaped.push_str(rest);
+    Some(unescaped)
+}